@@ -0,0 +1,169 @@
+use random;
+use random::Source;
+use std::env;
+use std::error::Error;
+use std::process::exit;
+use std::result::Result;
+use std::thread;
+use std::time::Duration;
+use tensorflow::ops;
+use tensorflow::train::GradientDescentOptimizer;
+use tensorflow::train::MinimizeOptions;
+use tensorflow::train::Optimizer;
+use tensorflow::train::ReplicaDeviceSetter;
+use tensorflow::ClusterSpec;
+use tensorflow::DataType;
+use tensorflow::Scope;
+use tensorflow::Server;
+use tensorflow::ServerDef;
+use tensorflow::Session;
+use tensorflow::SessionOptions;
+use tensorflow::SessionRunArgs;
+use tensorflow::Shape;
+use tensorflow::Tensor;
+use tensorflow::Variable;
+
+#[cfg_attr(feature = "examples_system_alloc", global_allocator)]
+#[cfg(feature = "examples_system_alloc")]
+static ALLOCATOR: std::alloc::System = std::alloc::System;
+
+// Fits y = w * x + b with one parameter server and two workers which train
+// asynchronously.
+//
+// Each task normally runs in its own process:
+//
+//     cargo run --example parameter_server --features experimental_training -- ps 0
+//     cargo run --example parameter_server --features experimental_training -- worker 0
+//     cargo run --example parameter_server --features experimental_training -- worker 1
+//
+// Without arguments, all tasks run in this process.
+fn main() {
+    exit(match run() {
+        Ok(_) => 0,
+        Err(e) => {
+            println!("{}", e);
+            1
+        }
+    })
+}
+
+fn cluster() -> ClusterSpec {
+    ClusterSpec::new()
+        .job("ps", &["localhost:2222"])
+        .job("worker", &["localhost:2223", "localhost:2224"])
+}
+
+fn run() -> Result<(), Box<dyn Error>> {
+    let args: Vec<String> = env::args().collect();
+    if args.len() == 3 {
+        let task: i32 = args[2].parse()?;
+        let server = Server::from_server_def(&ServerDef::new(cluster(), &args[1], task))?;
+        server.start()?;
+        match args[1].as_str() {
+            "ps" => server.join()?,
+            "worker" => worker(&server, task)?,
+            job => return Err(format!("Unknown job {}", job).into()),
+        }
+        return Ok(());
+    }
+
+    let mut servers = Vec::new();
+    for (job, task) in &[("ps", 0), ("worker", 0), ("worker", 1)] {
+        let server = Server::from_server_def(&ServerDef::new(cluster(), job, *task))?;
+        server.start()?;
+        servers.push(server);
+    }
+    let target_0 = servers[1].target()?;
+    let target_1 = servers[2].target()?;
+    let other = thread::spawn(move || worker_on(&target_1, 1).map_err(|e| e.to_string()));
+    worker_on(&target_0, 0)?;
+    other.join().unwrap()?;
+    Ok(())
+}
+
+fn worker(server: &Server, task: i32) -> Result<(), Box<dyn Error>> {
+    worker_on(&server.target()?, task)
+}
+
+fn worker_on(target: &str, task: i32) -> Result<(), Box<dyn Error>> {
+    let w = 0.1;
+    let b = 0.3;
+    let num_points = 100;
+    let steps = 200;
+
+    // Every worker builds the same graph.  The device setter places the
+    // variables on the parameter server and everything else on this worker.
+    let root = Scope::new_root_scope();
+    let mut setter = ReplicaDeviceSetter::new(&cluster(), task)?;
+    let w_var = setter.variable(&root, "w", Variable::builder().const_initial_value(0.0f32))?;
+    let b_var = setter.variable(&root, "b", Variable::builder().const_initial_value(0.0f32))?;
+    let mut scope = setter.worker_scope(&root);
+    let x = ops::Placeholder::new()
+        .data_type(DataType::Float)
+        .shape(Shape::from(Some(vec![Some(num_points as i64)])))
+        .build(&mut scope.with_op_name("x"))?;
+    let y = ops::Placeholder::new()
+        .data_type(DataType::Float)
+        .shape(Shape::from(Some(vec![Some(num_points as i64)])))
+        .build(&mut scope.with_op_name("y"))?;
+    let w_x = ops::multiply(&mut scope, w_var.output().clone(), x.clone())?;
+    let y_hat = ops::add(&mut scope, w_x, b_var.output().clone())?;
+    let error = ops::subtract(&mut scope, y_hat, y.clone())?;
+    let loss = ops::multiply(&mut scope, error.clone(), error)?;
+    let optimizer = GradientDescentOptimizer::new(ops::constant(&mut scope, 0.002f32)?.into());
+    let (_, train) = optimizer.minimize(
+        &mut scope,
+        loss.into(),
+        MinimizeOptions::default().with_variables(&[w_var.clone(), b_var.clone()]),
+    )?;
+
+    let mut options = SessionOptions::new();
+    options.set_target(target)?;
+    options.set_cluster_spec(&cluster())?;
+    let session = Session::new(&options, &scope.graph())?;
+
+    // The chief initializes the shared variables; the other workers wait.
+    if task == 0 {
+        let mut init_step = SessionRunArgs::new();
+        init_step.add_target(w_var.initializer());
+        init_step.add_target(b_var.initializer());
+        session.run(&mut init_step)?;
+    } else {
+        loop {
+            let mut check_step = SessionRunArgs::new();
+            check_step.request_fetch(&w_var.output().operation, 0);
+            if session.run(&mut check_step).is_ok() {
+                break;
+            }
+            thread::sleep(Duration::from_millis(100));
+        }
+    }
+
+    // Generate some training data and train asynchronously.
+    let mut rand = random::default().seed([task as u64, 1]);
+    let mut x_data = Tensor::new(&[num_points as u64]);
+    let mut y_data = Tensor::new(&[num_points as u64]);
+    for i in 0..num_points {
+        x_data[i] = (2.0 * rand.read::<f64>() - 1.0) as f32;
+        y_data[i] = w * x_data[i] + b;
+    }
+    let mut train_step = SessionRunArgs::new();
+    train_step.add_feed(&x, 0, &x_data);
+    train_step.add_feed(&y, 0, &y_data);
+    train_step.add_target(&train);
+    for _ in 0..steps {
+        session.run(&mut train_step)?;
+    }
+
+    let mut output_step = SessionRunArgs::new();
+    let w_ix = output_step.request_fetch(&w_var.output().operation, 0);
+    let b_ix = output_step.request_fetch(&b_var.output().operation, 0);
+    session.run(&mut output_step)?;
+    let w_hat: f32 = output_step.fetch(w_ix)?[0];
+    let b_hat: f32 = output_step.fetch(b_ix)?[0];
+    println!(
+        "Worker {}: expected w = {}, b = {}, got w = {}, b = {}",
+        task, w, b, w_hat, b_hat
+    );
+    Ok(())
+}
@@ -0,0 +1,311 @@
+use crate::Code;
+use crate::Output;
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Status;
+use crate::Tensor;
+use crate::TensorType;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::sync::mpsc;
+use std::sync::mpsc::Receiver;
+use std::sync::mpsc::RecvTimeoutError;
+use std::sync::mpsc::Sender;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread;
+use std::thread::JoinHandle;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Options controlling how `BatchedRunner` groups requests into batches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchingOptions {
+    max_batch_size: usize,
+    batch_timeout: Duration,
+}
+
+impl Default for BatchingOptions {
+    fn default() -> Self {
+        Self {
+            max_batch_size: 32,
+            batch_timeout: Duration::from_millis(1),
+        }
+    }
+}
+
+impl BatchingOptions {
+    /// Creates the default options: batches of up to 32 examples, waiting at
+    /// most 1 ms for a batch to fill.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the maximum number of examples run together.
+    pub fn max_batch_size(self, max_batch_size: usize) -> Self {
+        Self {
+            max_batch_size,
+            ..self
+        }
+    }
+
+    /// Sets how long to wait for more examples after the first example of a
+    /// batch arrives.  Longer timeouts give larger batches at the cost of
+    /// latency when the load is light.
+    pub fn batch_timeout(self, batch_timeout: Duration) -> Self {
+        Self {
+            batch_timeout,
+            ..self
+        }
+    }
+}
+
+struct Request<T, U> {
+    example: Vec<T>,
+    // The output values and dims for the example.
+    reply: Sender<Result<(Vec<U>, Vec<u64>)>>,
+}
+
+/// Runs single examples through a model in dynamically formed batches.
+///
+/// The model takes a batch of examples as a single input whose first
+/// dimension is the batch size, and produces a single output whose first
+/// dimension matches.  Requests from any number of threads are queued, and a
+/// background thread runs them together in batches of up to
+/// `max_batch_size` examples, waiting at most `batch_timeout` for a batch to
+/// fill.  Each caller receives its own slice of the output.
+///
+/// Input tensors are allocated once per batch size and reused for later
+/// batches of the same size.
+#[derive(Debug)]
+pub struct BatchedRunner<T: TensorType, U: TensorType> {
+    sender: Option<Mutex<Sender<Request<T, U>>>>,
+    worker: Option<JoinHandle<()>>,
+    example_dims: Vec<u64>,
+    example_len: usize,
+    phantom: PhantomData<U>,
+}
+
+impl<T: TensorType + Send, U: TensorType + Send> BatchedRunner<T, U> {
+    /// Creates a runner which feeds batches to `input` and fetches `output`
+    /// using `session`.  `example_dims` is the shape of a single example,
+    /// i.e. the shape of `input` without the batch dimension.
+    pub fn new(
+        session: Arc<Session>,
+        input: Output,
+        output: Output,
+        example_dims: &[u64],
+        options: BatchingOptions,
+    ) -> Result<Self> {
+        if options.max_batch_size == 0 {
+            return Err(invalid_arg!("max_batch_size must be positive"));
+        }
+        let example_len = example_dims.iter().product::<u64>() as usize;
+        let (sender, receiver) = mpsc::channel();
+        let dims = example_dims.to_vec();
+        let worker = thread::Builder::new()
+            .name("batched_runner".to_string())
+            .spawn(move || {
+                let mut batcher = Batcher {
+                    session,
+                    input,
+                    output,
+                    example_dims: dims,
+                    options,
+                    input_tensors: HashMap::new(),
+                };
+                batcher.run(receiver)
+            })
+            .map_err(|e| invalid_arg!("Unable to start batching thread: {}", e))?;
+        Ok(Self {
+            sender: Some(Mutex::new(sender)),
+            worker: Some(worker),
+            example_dims: example_dims.to_vec(),
+            example_len,
+            phantom: PhantomData,
+        })
+    }
+
+    /// Runs a single example, whose shape is the `example_dims` given when
+    /// the runner was created, and returns the model's output for it.  Blocks
+    /// until the batch containing the example has run.
+    pub fn run(&self, example: &[T]) -> Result<Tensor<U>> {
+        if example.len() != self.example_len {
+            return Err(invalid_arg!(
+                "Expected an example with {} values (shape {:?}) but got {}",
+                self.example_len,
+                self.example_dims,
+                example.len()
+            ));
+        }
+        let (reply, response) = mpsc::channel();
+        let request = Request {
+            example: example.to_vec(),
+            reply,
+        };
+        if let Some(sender) = &self.sender {
+            // If the batching thread has stopped, the request and its reply
+            // channel are dropped, so the receive below fails.
+            let _ = sender.lock().unwrap().send(request);
+        }
+        let (values, dims) = response.recv().map_err(|_| {
+            Status::new_set(Code::Unavailable, "The batching thread has stopped").unwrap()
+        })??;
+        Tensor::new(&dims).with_values(&values)
+    }
+}
+
+impl<T: TensorType, U: TensorType> Drop for BatchedRunner<T, U> {
+    fn drop(&mut self) {
+        // Closing the channel stops the batching thread once the queued
+        // requests have run.
+        self.sender = None;
+        if let Some(worker) = self.worker.take() {
+            let _ = worker.join();
+        }
+    }
+}
+
+/// The state owned by the batching thread.
+struct Batcher<T: TensorType> {
+    session: Arc<Session>,
+    input: Output,
+    output: Output,
+    example_dims: Vec<u64>,
+    options: BatchingOptions,
+    input_tensors: HashMap<usize, Tensor<T>>,
+}
+
+impl<T: TensorType> Batcher<T> {
+    fn run<U: TensorType>(&mut self, receiver: Receiver<Request<T, U>>) {
+        while let Ok(first) = receiver.recv() {
+            let deadline = Instant::now() + self.options.batch_timeout;
+            let mut batch = vec![first];
+            while batch.len() < self.options.max_batch_size {
+                let now = Instant::now();
+                if now >= deadline {
+                    break;
+                }
+                match receiver.recv_timeout(deadline - now) {
+                    Ok(request) => batch.push(request),
+                    Err(RecvTimeoutError::Timeout) | Err(RecvTimeoutError::Disconnected) => break,
+                }
+            }
+            match self.run_batch(&batch) {
+                Ok((values, dims)) => {
+                    let len = values.len() / batch.len();
+                    for (i, request) in batch.iter().enumerate() {
+                        let chunk = values[i * len..(i + 1) * len].to_vec();
+                        let _ = request.reply.send(Ok((chunk, dims.clone())));
+                    }
+                }
+                Err(e) => {
+                    for request in &batch {
+                        // Messages come from C strings, so they can't contain NULs.
+                        let error = Status::new_set(e.code(), e.message()).unwrap();
+                        let _ = request.reply.send(Err(error));
+                    }
+                }
+            }
+        }
+    }
+
+    /// Runs a batch, returning the output values and the dims of the output
+    /// for a single example.
+    fn run_batch<U: TensorType>(&mut self, batch: &[Request<T, U>]) -> Result<(Vec<U>, Vec<u64>)> {
+        let example_dims = &self.example_dims;
+        let input = self.input_tensors.entry(batch.len()).or_insert_with(|| {
+            let mut dims = vec![batch.len() as u64];
+            dims.extend_from_slice(example_dims);
+            Tensor::new(&dims)
+        });
+        let len = input.len() / batch.len();
+        for (i, request) in batch.iter().enumerate() {
+            input[i * len..(i + 1) * len].clone_from_slice(&request.example);
+        }
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&self.input.operation, self.input.index, input);
+        let token = args.request_fetch(&self.output.operation, self.output.index);
+        self.session.run(&mut args)?;
+        let output: Tensor<U> = args.fetch(token)?;
+        match output.dims().first() {
+            Some(&n) if n == batch.len() as u64 => {}
+            _ => {
+                return Err(invalid_arg!(
+                    "Expected an output with batch size {} but got shape {:?}",
+                    batch.len(),
+                    output.dims()
+                ))
+            }
+        }
+        Ok((output.to_vec(), output.dims()[1..].to_vec()))
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::Graph;
+    use crate::SessionOptions;
+    use crate::Shape;
+
+    fn create_runner(options: BatchingOptions) -> BatchedRunner<f32, f32> {
+        let mut g = Graph::new();
+        let two = {
+            let mut nd = g.new_operation("Const", "two").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::new(&[]).with_values(&[2.0f32]).unwrap())
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![None, Some(2)])))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = g.new_operation("Mul", "y").unwrap();
+            nd.add_input(two);
+            nd.add_input(x.clone());
+            nd.finish().unwrap()
+        };
+        let session = Arc::new(Session::new(&SessionOptions::new(), &g).unwrap());
+        BatchedRunner::new(session, x.into(), y.into(), &[2], options).unwrap()
+    }
+
+    #[test]
+    fn single() {
+        let runner = create_runner(BatchingOptions::new());
+        let result = runner.run(&[1.0, 2.0]).unwrap();
+        assert_eq!(result.dims(), &[2]);
+        assert_eq!(&result[..], &[2.0, 4.0]);
+        assert!(runner.run(&[1.0]).is_err());
+    }
+
+    #[test]
+    fn concurrent() {
+        let runner = Arc::new(create_runner(
+            BatchingOptions::new()
+                .max_batch_size(4)
+                .batch_timeout(Duration::from_millis(10)),
+        ));
+        let threads: Vec<_> = (0..10)
+            .map(|i| {
+                let runner = runner.clone();
+                thread::spawn(move || {
+                    let result = runner.run(&[i as f32, -(i as f32)]).unwrap();
+                    assert_eq!(&result[..], &[2.0 * i as f32, -2.0 * i as f32]);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+    }
+}
@@ -0,0 +1,301 @@
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+use std::fmt;
+use std::fmt::Debug;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::time::Duration;
+use std::time::Instant;
+
+/// Measures the latency and throughput of running a step repeatedly.
+///
+/// The same `SessionRunArgs` (and therefore the same feeds and fetches) are
+/// run for each iteration, so running the benchmark with sessions created
+/// from different `SessionOptions` compares those options directly.
+///
+/// ```
+/// # use tensorflow::Benchmark;
+/// # use tensorflow::Graph;
+/// # use tensorflow::Session;
+/// # use tensorflow::SessionOptions;
+/// # use tensorflow::SessionRunArgs;
+/// # let graph = Graph::new();
+/// # let session = Session::new(&SessionOptions::new(), &graph)?;
+/// let mut args = SessionRunArgs::new();
+/// // Add feeds, fetches and targets here.
+/// let stats = Benchmark::new().warmup_runs(5).runs(100).run(&session, &mut args)?;
+/// println!("{}", stats);
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+pub struct Benchmark<'a> {
+    warmup_runs: usize,
+    runs: usize,
+    trace_every: usize,
+    on_trace: Option<TraceHook<'a>>,
+}
+
+/// Receives the index and serialized `RunMetadata` of a traced run.
+type TraceHook<'a> = Box<dyn FnMut(usize, &[u8]) -> Result<()> + 'a>;
+
+impl<'a> Debug for Benchmark<'a> {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Benchmark")
+            .field("warmup_runs", &self.warmup_runs)
+            .field("runs", &self.runs)
+            .field("trace_every", &self.trace_every)
+            .finish()
+    }
+}
+
+impl<'a> Default for Benchmark<'a> {
+    fn default() -> Self {
+        Self {
+            warmup_runs: 10,
+            runs: 100,
+            trace_every: 0,
+            on_trace: None,
+        }
+    }
+}
+
+impl<'a> Benchmark<'a> {
+    /// Creates a benchmark with 10 warmup runs and 100 timed runs.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of untimed runs before measurement starts, which lets
+    /// TensorFlow finish lazy initialization such as memory allocation and
+    /// compilation.
+    pub fn warmup_runs(self, warmup_runs: usize) -> Self {
+        Self {
+            warmup_runs,
+            ..self
+        }
+    }
+
+    /// Sets the number of timed runs.
+    pub fn runs(self, runs: usize) -> Self {
+        Self { runs, ..self }
+    }
+
+    /// Traces every `every`th run (starting with the first timed run) and
+    /// passes its index and serialized `RunMetadata` to `on_trace`, e.g. to
+    /// save a timeline or inspect memory usage with
+    /// `DeviceMemoryStats::from_run_metadata`.
+    ///
+    /// Tracing slows down a run, so traced runs are not included in the
+    /// statistics.
+    pub fn trace<F: FnMut(usize, &[u8]) -> Result<()> + 'a>(
+        self,
+        every: usize,
+        on_trace: F,
+    ) -> Self {
+        Self {
+            trace_every: every,
+            on_trace: Some(Box::new(on_trace)),
+            ..self
+        }
+    }
+
+    /// Runs `args` in `session` and returns the latency statistics.
+    pub fn run(
+        &mut self,
+        session: &Session,
+        args: &mut SessionRunArgs<'_>,
+    ) -> Result<BenchmarkStats> {
+        for _ in 0..self.warmup_runs {
+            session.run(args)?;
+        }
+        let mut latencies = Vec::with_capacity(self.runs);
+        for i in 0..self.runs {
+            if self.trace_every > 0 && i % self.trace_every == 0 {
+                if let Some(on_trace) = self.on_trace.as_mut() {
+                    traced_run(session, args, |metadata| on_trace(i, metadata))?;
+                    continue;
+                }
+            }
+            let start = Instant::now();
+            session.run(args)?;
+            latencies.push(start.elapsed());
+        }
+        Ok(BenchmarkStats::from_latencies(latencies))
+    }
+}
+
+/// Runs `args` once with full tracing, restoring its run options afterwards.
+fn traced_run<F: FnOnce(&[u8]) -> Result<()>>(
+    session: &Session,
+    args: &mut SessionRunArgs<'_>,
+    on_trace: F,
+) -> Result<()> {
+    let run_options = args.get_run_options().map(<[u8]>::to_vec);
+    let request_metadata = args.is_request_metadata();
+    let mut traced_options = run_options.clone().unwrap_or_default();
+    // RunOptions.trace_level = FULL_TRACE.  The last occurrence of a field
+    // wins, so this overrides any level already set.
+    traced_options.extend_from_slice(&[0x08, 0x03]);
+    args.set_run_options(&traced_options);
+    args.set_request_metadata(true);
+    let result = session.run(args);
+    let metadata = args.get_metadata().map(<[u8]>::to_vec);
+    match &run_options {
+        Some(run_options) => args.set_run_options(run_options),
+        None => args.clear_run_options(),
+    }
+    args.set_request_metadata(request_metadata);
+    result?;
+    on_trace(metadata.as_deref().unwrap_or(&[]))
+}
+
+/// Latency statistics from a `Benchmark`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BenchmarkStats {
+    // Sorted in increasing order.
+    latencies: Vec<Duration>,
+}
+
+impl BenchmarkStats {
+    pub(crate) fn from_latencies(mut latencies: Vec<Duration>) -> Self {
+        latencies.sort();
+        Self { latencies }
+    }
+
+    /// Returns the number of timed runs.
+    pub fn runs(&self) -> usize {
+        self.latencies.len()
+    }
+
+    /// Returns the latencies of the timed runs, from fastest to slowest.
+    pub fn latencies(&self) -> &[Duration] {
+        &self.latencies
+    }
+
+    /// Returns the fastest latency, or zero if there were no timed runs.
+    pub fn min(&self) -> Duration {
+        self.latencies.first().cloned().unwrap_or_default()
+    }
+
+    /// Returns the slowest latency, or zero if there were no timed runs.
+    pub fn max(&self) -> Duration {
+        self.latencies.last().cloned().unwrap_or_default()
+    }
+
+    /// Returns the mean latency, or zero if there were no timed runs.
+    pub fn mean(&self) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::default();
+        }
+        self.total() / self.latencies.len() as u32
+    }
+
+    /// Returns the latency at percentile `p` (between 0 and 100) using the
+    /// nearest-rank method, e.g. `percentile(99.0)` is the latency which 99%
+    /// of runs were at least as fast as.  Returns zero if there were no timed
+    /// runs.
+    pub fn percentile(&self, p: f64) -> Duration {
+        if self.latencies.is_empty() {
+            return Duration::default();
+        }
+        let n = self.latencies.len();
+        let rank = (p.clamp(0.0, 100.0) / 100.0 * n as f64).ceil() as usize;
+        self.latencies[rank.max(1).min(n) - 1]
+    }
+
+    /// Returns the number of runs per second, assuming runs happen one after
+    /// another.
+    pub fn throughput(&self) -> f64 {
+        let total = self.total().as_secs_f64();
+        if total == 0.0 {
+            return 0.0;
+        }
+        self.latencies.len() as f64 / total
+    }
+
+    fn total(&self) -> Duration {
+        self.latencies.iter().sum()
+    }
+}
+
+impl Display for BenchmarkStats {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{} runs: mean {:?}, min {:?}, p50 {:?}, p90 {:?}, p99 {:?}, max {:?}, {:.1} runs/s",
+            self.runs(),
+            self.mean(),
+            self.min(),
+            self.percentile(50.0),
+            self.percentile(90.0),
+            self.percentile(99.0),
+            self.max(),
+            self.throughput()
+        )
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::Graph;
+    use crate::SessionOptions;
+    use crate::Shape;
+    use crate::Tensor;
+
+    #[test]
+    fn stats() {
+        let stats =
+            BenchmarkStats::from_latencies((1..=100).rev().map(Duration::from_millis).collect());
+        assert_eq!(stats.runs(), 100);
+        assert_eq!(stats.min(), Duration::from_millis(1));
+        assert_eq!(stats.max(), Duration::from_millis(100));
+        assert_eq!(stats.percentile(50.0), Duration::from_millis(50));
+        assert_eq!(stats.percentile(99.0), Duration::from_millis(99));
+        assert_eq!(stats.percentile(0.0), Duration::from_millis(1));
+        assert_eq!(stats.mean(), Duration::from_micros(50500));
+        assert!((stats.throughput() - 100.0 / 5.05).abs() < 1e-9);
+
+        let empty = BenchmarkStats::from_latencies(vec![]);
+        assert_eq!(empty.percentile(50.0), Duration::default());
+        assert_eq!(empty.throughput(), 0.0);
+    }
+
+    #[test]
+    fn run() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![]))).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = g.new_operation("Square", "y").unwrap();
+            nd.add_input(x.clone());
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+        let value = Tensor::from(3.0f32);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &value);
+        args.request_fetch(&y, 0);
+        let mut traced = vec![];
+        let stats = Benchmark::new()
+            .warmup_runs(2)
+            .runs(10)
+            .trace(5, |i, metadata| {
+                traced.push((i, metadata.is_empty()));
+                Ok(())
+            })
+            .run(&session, &mut args)
+            .unwrap();
+        assert_eq!(stats.runs(), 8);
+        assert_eq!(traced, vec![(0, false), (5, false)]);
+        assert!(!args.is_request_metadata());
+        assert!(args.get_run_options().is_none());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::DataType;
+use crate::Graph;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Shape;
+use crate::Tensor;
+use crate::TensorType;
+
+/// Fetches a large tensor a block of rows at a time, so that it never has to
+/// be held in memory in full outside the TensorFlow runtime, e.g. to export
+/// the embeddings of millions of items.
+///
+/// ```no_run
+/// # use tensorflow::ChunkedFetch;
+/// # use tensorflow::Graph;
+/// # use tensorflow::Operation;
+/// # use tensorflow::Session;
+/// # fn f(graph: &mut Graph, session: &Session, embeddings: Operation) -> tensorflow::Result<()> {
+/// let fetch = ChunkedFetch::new(graph, embeddings.into(), 10000)?;
+/// fetch.for_each_row::<f32, _>(session, |item, embedding| {
+///     println!("{}: {:?}", item, embedding);
+///     Ok(())
+/// })?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Each chunk is fetched with a separate run of a `Slice` op added to the
+/// graph, so the tensor is computed once per chunk.  This suits tensors
+/// which are cheap to compute or already held by the runtime, such as
+/// variables; the tensor must not depend on fed placeholders.
+#[derive(Debug, Clone)]
+pub struct ChunkedFetch {
+    shape: Operation,
+    begin: Operation,
+    size: Operation,
+    slice: Operation,
+    chunk_rows: u64,
+}
+
+impl ChunkedFetch {
+    /// Adds ops to `graph` for fetching `output` in chunks of up to
+    /// `chunk_rows` rows, i.e. slices along its first dimension.
+    pub fn new(graph: &mut Graph, output: Output, chunk_rows: u64) -> Result<Self> {
+        if chunk_rows == 0 {
+            return Err(invalid_arg!("chunk_rows must be positive"));
+        }
+        // The shape op is created first, so its name tells whether the prefix
+        // is taken.
+        let id = graph.generate_operation_name("ChunkedFetch_{}/shape")?;
+        let prefix = format!("ChunkedFetch_{}", id);
+        let shape = {
+            let mut nd = graph.new_operation("Shape", &format!("{}/shape", prefix))?;
+            nd.add_input(output.clone());
+            nd.set_attr_type("out_type", DataType::Int64)?;
+            nd.finish()?
+        };
+        let mut placeholder = |name: &str| -> Result<Operation> {
+            let mut nd = graph.new_operation("Placeholder", &format!("{}/{}", prefix, name))?;
+            nd.set_attr_type("dtype", DataType::Int64)?;
+            nd.set_attr_shape("shape", &Shape(Some(vec![None])))?;
+            nd.finish()
+        };
+        let begin = placeholder("begin")?;
+        let size = placeholder("size")?;
+        let slice = {
+            let mut nd = graph.new_operation("Slice", &format!("{}/slice", prefix))?;
+            nd.add_input(output);
+            nd.add_input(begin.clone());
+            nd.add_input(size.clone());
+            nd.finish()?
+        };
+        Ok(Self {
+            shape,
+            begin,
+            size,
+            slice,
+            chunk_rows,
+        })
+    }
+
+    /// Returns the shape of the tensor.
+    pub fn dims(&self, session: &Session) -> Result<Vec<u64>> {
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&self.shape, 0);
+        session.run(&mut args)?;
+        let shape = args.fetch::<i64>(token)?;
+        Ok(shape.iter().map(|&d| d as u64).collect())
+    }
+
+    /// Calls `f` for each chunk of rows in order, with the index of the
+    /// chunk's first row.  Each chunk has the shape of the tensor, except
+    /// that its first dimension is at most `chunk_rows`.  An error returned
+    /// by `f` stops the iteration.
+    pub fn for_each_chunk<T, F>(&self, session: &Session, mut f: F) -> Result<()>
+    where
+        T: TensorType,
+        F: FnMut(u64, Tensor<T>) -> Result<()>,
+    {
+        let dims = self.dims(session)?;
+        if dims.is_empty() {
+            return Err(invalid_arg!("A scalar can't be fetched in chunks"));
+        }
+        let rank = dims.len() as u64;
+        let mut begin = Tensor::<i64>::new(&[rank]);
+        // -1 means all remaining elements of the dimension.
+        let mut size = Tensor::<i64>::new(&[rank]).with_values(&vec![-1; dims.len()])?;
+        let mut start = 0;
+        while start < dims[0] {
+            let rows = self.chunk_rows.min(dims[0] - start);
+            begin[0] = start as i64;
+            size[0] = rows as i64;
+            let mut args = SessionRunArgs::new();
+            args.add_feed(&self.begin, 0, &begin);
+            args.add_feed(&self.size, 0, &size);
+            let token = args.request_fetch(&self.slice, 0);
+            session.run(&mut args)?;
+            f(start, args.fetch(token)?)?;
+            start += rows;
+        }
+        Ok(())
+    }
+
+    /// Calls `f` for each row in order, with the row's index and its
+    /// elements in row-major order.  Rows are fetched in chunks of
+    /// `chunk_rows`.  An error returned by `f` stops the iteration.
+    pub fn for_each_row<T, F>(&self, session: &Session, mut f: F) -> Result<()>
+    where
+        T: TensorType,
+        F: FnMut(u64, &[T]) -> Result<()>,
+    {
+        self.for_each_chunk(session, |start, chunk: Tensor<T>| {
+            let row_len = chunk.dims()[1..].iter().product::<u64>() as usize;
+            for i in 0..chunk.dims()[0] as usize {
+                f(start + i as u64, &chunk[i * row_len..(i + 1) * row_len])?;
+            }
+            Ok(())
+        })
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SessionOptions;
+
+    #[test]
+    fn fetch_in_chunks() {
+        let mut graph = Graph::new();
+        let values = Tensor::new(&[5, 2])
+            .with_values(&[0i32, 1, 2, 3, 4, 5, 6, 7, 8, 9])
+            .unwrap();
+        let table = {
+            let mut nd = graph.new_operation("Const", "table").unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.set_attr_tensor("value", values).unwrap();
+            nd.finish().unwrap()
+        };
+        let scalar = {
+            let mut nd = graph.new_operation("Const", "scalar").unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.set_attr_tensor("value", Tensor::from(1i32)).unwrap();
+            nd.finish().unwrap()
+        };
+        let fetch = ChunkedFetch::new(&mut graph, table.clone().into(), 2).unwrap();
+        let scalar_fetch = ChunkedFetch::new(&mut graph, scalar.into(), 2).unwrap();
+        assert!(ChunkedFetch::new(&mut graph, fetch.slice.clone().into(), 0).is_err());
+        let session = Session::new(&SessionOptions::new(), &graph).unwrap();
+        assert_eq!(fetch.dims(&session).unwrap(), vec![5, 2]);
+
+        let mut chunks = vec![];
+        fetch
+            .for_each_chunk(&session, |start, chunk: Tensor<i32>| {
+                chunks.push((start, chunk.dims().to_vec(), chunk[0]));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(
+            chunks,
+            vec![(0, vec![2, 2], 0), (2, vec![2, 2], 4), (4, vec![1, 2], 8)]
+        );
+
+        let mut rows = vec![];
+        fetch
+            .for_each_row(&session, |i, row: &[i32]| {
+                rows.push((i, row.to_vec()));
+                Ok(())
+            })
+            .unwrap();
+        assert_eq!(rows.len(), 5);
+        assert_eq!(rows[3], (3, vec![6, 7]));
+
+        // Errors from the callback stop the iteration.
+        let mut calls = 0;
+        assert!(fetch
+            .for_each_row(&session, |_, _: &[i32]| {
+                calls += 1;
+                Err(invalid_arg!("stop"))
+            })
+            .is_err());
+        assert_eq!(calls, 1);
+        assert!(scalar_fetch
+            .for_each_chunk(&session, |_, _: Tensor<i32>| Ok(()))
+            .is_err());
+
+        // Ops added after the session was created can be run too.
+        let late_fetch = ChunkedFetch::new(&mut graph, table.into(), 3).unwrap();
+        assert_eq!(late_fetch.slice.name().unwrap(), "ChunkedFetch_2/slice");
+        assert_eq!(late_fetch.dims(&session).unwrap(), vec![5, 2]);
+    }
+}
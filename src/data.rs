@@ -0,0 +1,921 @@
+//! This module builds `tf.data` input pipelines in the graph.
+//!
+//! This module currently requires the `experimental_training` feature.
+//!
+//! A `Dataset` is a tensor describing a sequence of elements, each of which
+//! is a list of tensors.  A pipeline starts from tensors already in the
+//! graph, is transformed with methods such as `batch` and `shuffle`, and is
+//! read through a `DatasetIterator`, whose `get_next` outputs yield a new
+//! element every time they're run, so a training loop needs no feeds:
+//!
+//! ```ignore
+//! let dataset = Dataset::from_tensor_slices(&mut scope, &[features, labels])?
+//!     .shuffle(&mut scope, 1000, Some(42))?
+//!     .batch(&mut scope, 32, false)?
+//!     .repeat(&mut scope, Some(10))?
+//!     .prefetch(&mut scope, 1)?;
+//! let iterator = dataset.make_iterator(&mut scope)?;
+//! let next = iterator.get_next(&mut scope)?;
+//! // ... build the model on next[0] and next[1] ...
+//! let mut args = SessionRunArgs::new();
+//! args.add_target(iterator.initializer());
+//! session.run(&mut args)?;
+//! loop {
+//!     let mut args = SessionRunArgs::new();
+//!     args.add_target(&train_op);
+//!     match session.run(&mut args) {
+//!         Err(ref e) if is_end_of_sequence(e) => break,
+//!         result => result?,
+//!     }
+//! }
+//! ```
+
+use crate::ops;
+use crate::ops::trace;
+use crate::protos::ProtoReader;
+use crate::Code;
+use crate::DataType;
+use crate::Function;
+use crate::Operation;
+use crate::OperationDescription;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Shape;
+use crate::Status;
+
+/// The field numbers of the messages inspected here.
+const FUNCTION_DEF_SIGNATURE: u32 = 1;
+const OP_DEF_OUTPUT_ARG: u32 = 3;
+const ARG_DEF_TYPE: u32 = 3;
+
+/// A sequence of elements, each of which is a list of tensors with the
+/// dataset's output types and shapes.
+#[derive(Debug, Clone)]
+pub struct Dataset {
+    handle: Output,
+    output_types: Vec<DataType>,
+    output_shapes: Vec<Shape>,
+}
+
+impl Dataset {
+    /// Creates a dataset with a single element, `components`.
+    pub fn from_tensors(scope: &mut Scope, components: &[Output]) -> Result<Self> {
+        let (output_types, output_shapes) = signature(scope, components)?;
+        Self::source(
+            scope,
+            "TensorDataset",
+            components,
+            output_types,
+            output_shapes,
+        )
+    }
+
+    /// Creates a dataset whose elements are the slices of `components` along
+    /// their first dimension, which must be the same for all of them.
+    pub fn from_tensor_slices(scope: &mut Scope, components: &[Output]) -> Result<Self> {
+        let (output_types, shapes) = signature(scope, components)?;
+        let mut output_shapes = Vec::with_capacity(shapes.len());
+        for (component, shape) in components.iter().zip(shapes) {
+            let dims: Option<Vec<Option<i64>>> = shape.into();
+            output_shapes.push(match dims {
+                Some(ref dims) if dims.is_empty() => {
+                    return Err(invalid_arg!(
+                        "Cannot slice {:?}, which is a scalar",
+                        component
+                    ))
+                }
+                Some(dims) => Shape::from(Some(dims[1..].to_vec())),
+                None => Shape::from(None),
+            });
+        }
+        Self::source(
+            scope,
+            "TensorSliceDataset",
+            components,
+            output_types,
+            output_shapes,
+        )
+    }
+
+    fn source(
+        scope: &mut Scope,
+        op_type: &str,
+        components: &[Output],
+        output_types: Vec<DataType>,
+        output_shapes: Vec<Shape>,
+    ) -> Result<Self> {
+        if components.is_empty() {
+            return Err(invalid_arg!("A dataset needs at least one component"));
+        }
+        let op = scope.new_operation(op_type, |nd| {
+            nd.add_input_list(components);
+            nd.set_attr_type_list("Toutput_types", &output_types)?;
+            nd.set_attr_shape_list("output_shapes", &output_shapes)?;
+            Ok(())
+        })?;
+        Ok(Self {
+            handle: op.into(),
+            output_types,
+            output_shapes,
+        })
+    }
+
+    /// Returns the variant tensor representing the dataset.
+    pub fn handle(&self) -> &Output {
+        &self.handle
+    }
+
+    /// Returns the types of the components of each element.
+    pub fn output_types(&self) -> &[DataType] {
+        &self.output_types
+    }
+
+    /// Returns the shapes of the components of each element, as far as
+    /// they're known.
+    pub fn output_shapes(&self) -> &[Shape] {
+        &self.output_shapes
+    }
+
+    /// Adds a dataset op taking this dataset and the inputs added by `f`,
+    /// whose elements have the given shapes and this dataset's types.
+    fn transform<F>(
+        &self,
+        scope: &mut Scope,
+        op_type: &str,
+        output_shapes: Vec<Shape>,
+        f: F,
+    ) -> Result<Self>
+    where
+        F: FnOnce(&mut OperationDescription<'_>) -> Result<()>,
+    {
+        let output_types = self.output_types.clone();
+        self.transform_with_types(scope, op_type, output_types, output_shapes, f)
+    }
+
+    fn transform_with_types<F>(
+        &self,
+        scope: &mut Scope,
+        op_type: &str,
+        output_types: Vec<DataType>,
+        output_shapes: Vec<Shape>,
+        f: F,
+    ) -> Result<Self>
+    where
+        F: FnOnce(&mut OperationDescription<'_>) -> Result<()>,
+    {
+        let op = scope.new_operation(op_type, |nd| {
+            nd.add_input(self.handle.clone());
+            f(nd)?;
+            nd.set_attr_type_list("output_types", &output_types)?;
+            nd.set_attr_shape_list("output_shapes", &output_shapes)?;
+            Ok(())
+        })?;
+        Ok(Self {
+            handle: op.into(),
+            output_types,
+            output_shapes,
+        })
+    }
+
+    /// Combines consecutive elements into batches of `batch_size`, stacking
+    /// each component along a new first dimension.  The last batch is
+    /// smaller if the number of elements isn't a multiple of `batch_size`,
+    /// unless `drop_remainder` is true, in which case it's dropped.
+    pub fn batch(&self, scope: &mut Scope, batch_size: i64, drop_remainder: bool) -> Result<Self> {
+        if batch_size <= 0 {
+            return Err(invalid_arg!(
+                "Batch size must be positive, but was {}",
+                batch_size
+            ));
+        }
+        let batch_dim = if drop_remainder {
+            Some(batch_size)
+        } else {
+            None
+        };
+        let batch_size = ops::constant(scope, batch_size)?;
+        let drop_remainder_input = ops::constant(scope, drop_remainder)?;
+        let output_shapes = self
+            .output_shapes
+            .iter()
+            .map(|shape| {
+                let dims: Option<Vec<Option<i64>>> = shape.clone().into();
+                Shape::from(dims.map(|dims| {
+                    let mut batched = vec![batch_dim];
+                    batched.extend(dims);
+                    batched
+                }))
+            })
+            .collect();
+        self.transform(scope, "BatchDatasetV2", output_shapes, |nd| {
+            nd.add_input(batch_size);
+            nd.add_input(drop_remainder_input);
+            Ok(())
+        })
+    }
+
+    /// Combines consecutive elements into batches of `batch_size` like
+    /// `batch`, but first pads each component to a common shape, so that
+    /// elements of different shapes, such as variable-length sequences, can
+    /// be batched.
+    ///
+    /// `padded_shapes` gives the shape each component is padded to, in which
+    /// unknown dimensions are padded to the largest size in the batch, and
+    /// `padding_values` gives the scalar each component is padded with,
+    /// which must be of the component's type.  Both have one entry per
+    /// component.
+    pub fn padded_batch(
+        &self,
+        scope: &mut Scope,
+        batch_size: i64,
+        padded_shapes: &[Shape],
+        padding_values: &[Output],
+        drop_remainder: bool,
+    ) -> Result<Self> {
+        if batch_size <= 0 {
+            return Err(invalid_arg!(
+                "Batch size must be positive, but was {}",
+                batch_size
+            ));
+        }
+        let components = self.output_types.len();
+        if padded_shapes.len() != components || padding_values.len() != components {
+            return Err(invalid_arg!(
+                "Expected a padded shape and a padding value for each of {} components, but got \
+                 {} and {}",
+                components,
+                padded_shapes.len(),
+                padding_values.len()
+            ));
+        }
+        let batch_dim = if drop_remainder {
+            Some(batch_size)
+        } else {
+            None
+        };
+        let mut shape_inputs = Vec::with_capacity(components);
+        let mut output_shapes = Vec::with_capacity(components);
+        for (i, padded_shape) in padded_shapes.iter().enumerate() {
+            let dims: Vec<Option<i64>> = match padded_shape.clone().into() {
+                Some(dims) => dims,
+                None => {
+                    return Err(invalid_arg!(
+                        "The padded shape of component {} must have a known rank",
+                        i
+                    ))
+                }
+            };
+            if let Some(rank) = self.output_shapes[i].dims() {
+                if rank != dims.len() {
+                    return Err(invalid_arg!(
+                        "Component {} has rank {}, but its padded shape is {:?}",
+                        i,
+                        rank,
+                        padded_shape
+                    ));
+                }
+            }
+            let padding_type = padding_values[i]
+                .operation
+                .output_type(padding_values[i].index as usize);
+            if padding_type != self.output_types[i] {
+                return Err(invalid_arg!(
+                    "Component {} is {}, but its padding value is {}",
+                    i,
+                    self.output_types[i],
+                    padding_type
+                ));
+            }
+            let sizes: Vec<i64> = dims.iter().map(|dim| dim.unwrap_or(-1)).collect();
+            shape_inputs.push(ops::constant(scope, &sizes[..])?.into());
+            let mut batched = vec![batch_dim];
+            batched.extend(dims);
+            output_shapes.push(Shape::from(Some(batched)));
+        }
+        let batch_size = ops::constant(scope, batch_size)?;
+        let drop_remainder = ops::constant(scope, drop_remainder)?;
+        let output_types = &self.output_types;
+        let op = scope.new_operation("PaddedBatchDatasetV2", |nd| {
+            nd.add_input(self.handle.clone());
+            nd.add_input(batch_size);
+            nd.add_input_list(&shape_inputs);
+            nd.add_input_list(padding_values);
+            nd.add_input(drop_remainder);
+            nd.set_attr_type_list("Toutput_types", output_types)?;
+            nd.set_attr_shape_list("output_shapes", &output_shapes)?;
+            Ok(())
+        })?;
+        Ok(Self {
+            handle: op.into(),
+            output_types: self.output_types.clone(),
+            output_shapes,
+        })
+    }
+
+    /// Groups elements of similar length into padded batches, which reduces
+    /// the padding needed for variable-length sequences.  This is the
+    /// in-graph counterpart of `train::bucket_by_sequence_length`.
+    ///
+    /// `element_length` returns the length of an element as an integer
+    /// scalar, and is traced once, on a fresh scope with placeholders
+    /// standing for the components of an element.  An element of length `l`
+    /// goes into bucket `b` if `boundaries[b - 1] <= l < boundaries[b]`, so
+    /// there is one more bucket than boundaries.  A bucket becomes a batch,
+    /// padded as by `padded_batch`, whenever it holds the number of elements
+    /// given by `batch_sizes` for that bucket.  Partial batches are produced
+    /// at the end.
+    pub fn bucket_by_sequence_length<F>(
+        &self,
+        scope: &mut Scope,
+        element_length: F,
+        boundaries: &[i64],
+        batch_sizes: &[i64],
+        padded_shapes: &[Shape],
+        padding_values: &[Output],
+    ) -> Result<Self>
+    where
+        F: FnOnce(&mut Scope, &[Output]) -> Result<Output>,
+    {
+        if batch_sizes.len() != boundaries.len() + 1 {
+            return Err(invalid_arg!(
+                "There must be one more batch size than bucket boundaries, but got {} and {}",
+                batch_sizes.len(),
+                boundaries.len()
+            ));
+        }
+        if boundaries.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(invalid_arg!(
+                "Bucket boundaries must be increasing, but are {:?}",
+                boundaries
+            ));
+        }
+        if batch_sizes.iter().any(|&size| size <= 0) {
+            return Err(invalid_arg!("Batch sizes must be positive"));
+        }
+        let max_batch_size = batch_sizes.iter().cloned().max().unwrap();
+        let scalar = || Shape::from(Some(vec![]));
+        let inputs: Vec<_> = self
+            .output_types
+            .iter()
+            .cloned()
+            .zip(self.output_shapes.iter().cloned())
+            .collect();
+        // The bucket is the number of boundaries at most the length.
+        let key_func = trace("bucket_key", &inputs, |scope, element| {
+            let length = element_length(scope, element)?;
+            let length = ops::Cast::new()
+                .dst_type(DataType::Int64)
+                .build(scope, length)?;
+            let boundaries = ops::constant(scope, boundaries)?;
+            let reached = ops::greater_equal(scope, length, boundaries)?;
+            let reached = ops::Cast::new()
+                .dst_type(DataType::Int64)
+                .build(scope, reached)?;
+            let axis = ops::constant(scope, 0)?;
+            Ok(vec![ops::sum(scope, reached, axis)?.into()])
+        })?;
+        let window_size_func = trace(
+            "bucket_batch_size",
+            &[(DataType::Int64, scalar())],
+            |scope, key| {
+                let batch_sizes = ops::constant(scope, batch_sizes)?;
+                let axis = ops::constant(scope, 0)?;
+                Ok(vec![
+                    ops::gather(scope, batch_sizes, key[0].clone(), axis)?.into()
+                ])
+            },
+        )?;
+        // Called with the bucket, the bucket's elements as a dataset, and the
+        // padding values, which are passed in from the outer graph.
+        let mut reduce_inputs = vec![(DataType::Int64, scalar()), (DataType::Variant, scalar())];
+        reduce_inputs.extend(self.output_types.iter().map(|&t| (t, scalar())));
+        let mut batched = None;
+        let reduce_func = trace("bucket_batch", &reduce_inputs, |scope, args| {
+            let window = Dataset {
+                handle: args[1].clone(),
+                output_types: self.output_types.clone(),
+                output_shapes: self.output_shapes.clone(),
+            };
+            // Each window holds at most its bucket's batch size, so this
+            // makes it a single batch.
+            let padded =
+                window.padded_batch(scope, max_batch_size, padded_shapes, &args[2..], false)?;
+            let handle = padded.handle.clone();
+            batched = Some(padded);
+            Ok(vec![handle])
+        })?;
+        let batched = batched.expect("reduce_func was traced");
+        for traced in &[&key_func, &window_size_func, &reduce_func] {
+            scope.graph_mut().copy_function(&traced.function, None)?;
+        }
+        let padding_types = self.output_types.clone();
+        self.transform_with_types(
+            scope,
+            "GroupByWindowDataset",
+            batched.output_types,
+            batched.output_shapes,
+            |nd| {
+                nd.add_input_list(&[]);
+                nd.add_input_list(padding_values);
+                nd.add_input_list(&[]);
+                nd.set_attr_func_name("key_func", &key_func.name)?;
+                nd.set_attr_func_name("reduce_func", &reduce_func.name)?;
+                nd.set_attr_func_name("window_size_func", &window_size_func.name)?;
+                nd.set_attr_type_list("Tkey_func_other_arguments", &[])?;
+                nd.set_attr_type_list("Treduce_func_other_arguments", &padding_types)?;
+                nd.set_attr_type_list("Twindow_size_func_other_arguments", &[])?;
+                Ok(())
+            },
+        )
+    }
+
+    /// Shuffles the elements with a buffer of `buffer_size` elements, from
+    /// which each element is drawn at random.  A buffer at least as large as
+    /// the dataset gives a uniform shuffle.  With a `seed`, the order is the
+    /// same in every run of the program; without one, it differs.  The order
+    /// is reshuffled every time the dataset is iterated over.
+    pub fn shuffle(&self, scope: &mut Scope, buffer_size: i64, seed: Option<i64>) -> Result<Self> {
+        if buffer_size <= 0 {
+            return Err(invalid_arg!(
+                "Shuffle buffer size must be positive, but was {}",
+                buffer_size
+            ));
+        }
+        // TensorFlow treats a pair of zero seeds as unseeded.
+        let (seed, seed2) = match seed {
+            Some(seed) => (seed, 1),
+            None => (0, 0),
+        };
+        let buffer_size = ops::constant(scope, buffer_size)?;
+        let seed = ops::constant(scope, seed)?;
+        let seed2 = ops::constant(scope, seed2)?;
+        let output_shapes = self.output_shapes.clone();
+        self.transform(scope, "ShuffleDataset", output_shapes, |nd| {
+            nd.add_input(buffer_size);
+            nd.add_input(seed);
+            nd.add_input(seed2);
+            nd.set_attr_bool("reshuffle_each_iteration", true)?;
+            Ok(())
+        })
+    }
+
+    /// Repeats the elements `count` times, or forever if `count` is None.
+    pub fn repeat(&self, scope: &mut Scope, count: Option<i64>) -> Result<Self> {
+        let count = ops::constant(scope, count.unwrap_or(-1))?;
+        let output_shapes = self.output_shapes.clone();
+        self.transform(scope, "RepeatDataset", output_shapes, |nd| {
+            nd.add_input(count);
+            Ok(())
+        })
+    }
+
+    /// Prepares up to `buffer_size` elements in the background while the
+    /// current one is consumed, which overlaps preprocessing with training.
+    pub fn prefetch(&self, scope: &mut Scope, buffer_size: i64) -> Result<Self> {
+        let buffer_size = ops::constant(scope, buffer_size)?;
+        let output_shapes = self.output_shapes.clone();
+        self.transform(scope, "PrefetchDataset", output_shapes, |nd| {
+            nd.add_input(buffer_size);
+            Ok(())
+        })
+    }
+
+    /// Replaces each element with the outputs of `f`, which is traced once,
+    /// on a fresh scope with placeholders standing for the components of an
+    /// element.  Tensors from the outer graph can't be used in `f`; use
+    /// `map_function` to pass them in.
+    pub fn map<F>(&self, scope: &mut Scope, f: F) -> Result<Self>
+    where
+        F: FnOnce(&mut Scope, &[Output]) -> Result<Vec<Output>>,
+    {
+        let inputs: Vec<_> = self
+            .output_types
+            .iter()
+            .cloned()
+            .zip(self.output_shapes.iter().cloned())
+            .collect();
+        let traced = trace("dataset_map", &inputs, f)?;
+        scope.graph_mut().copy_function(&traced.function, None)?;
+        self.map_op(
+            scope,
+            &traced.name,
+            &[],
+            traced.output_types,
+            traced.output_shapes,
+        )
+    }
+
+    /// Replaces each element with the outputs of `function`, called with the
+    /// components of the element followed by `captured`, which lets the
+    /// function use tensors from the outer graph.  `function` is added to
+    /// the graph if it isn't already, and must have outputs of fixed types.
+    pub fn map_function(
+        &self,
+        scope: &mut Scope,
+        function: &Function,
+        captured: &[Output],
+    ) -> Result<Self> {
+        let output_types = function_output_types(function)?;
+        let output_shapes = vec![Shape::from(None); output_types.len()];
+        scope.graph_mut().copy_function(function, None)?;
+        self.map_op(
+            scope,
+            &function.get_name()?,
+            captured,
+            output_types,
+            output_shapes,
+        )
+    }
+
+    fn map_op(
+        &self,
+        scope: &mut Scope,
+        function_name: &str,
+        captured: &[Output],
+        output_types: Vec<DataType>,
+        output_shapes: Vec<Shape>,
+    ) -> Result<Self> {
+        let captured_types: Vec<DataType> = captured
+            .iter()
+            .map(|c| c.operation.output_type(c.index as usize))
+            .collect();
+        self.transform_with_types(scope, "MapDataset", output_types, output_shapes, |nd| {
+            nd.add_input_list(captured);
+            nd.set_attr_func_name("f", function_name)?;
+            nd.set_attr_type_list("Targuments", &captured_types)?;
+            Ok(())
+        })
+    }
+
+    /// Creates an iterator over the dataset.  Its initializer must be run
+    /// before its elements are read.
+    pub fn make_iterator(&self, scope: &mut Scope) -> Result<DatasetIterator> {
+        let (output_types, output_shapes) = (&self.output_types, &self.output_shapes);
+        let resource = scope.new_operation("IteratorV2", |nd| {
+            nd.set_attr_string("shared_name", "")?;
+            nd.set_attr_string("container", "")?;
+            nd.set_attr_type_list("output_types", output_types)?;
+            nd.set_attr_shape_list("output_shapes", output_shapes)?;
+            Ok(())
+        })?;
+        let initializer = scope.new_operation("MakeIterator", |nd| {
+            nd.add_input(self.handle.clone());
+            nd.add_input(resource.clone());
+            Ok(())
+        })?;
+        Ok(DatasetIterator {
+            resource: resource.into(),
+            initializer,
+            output_types: self.output_types.clone(),
+            output_shapes: self.output_shapes.clone(),
+        })
+    }
+}
+
+/// An iterator over the elements of a `Dataset`, created by
+/// `Dataset::make_iterator`.
+#[derive(Debug, Clone)]
+pub struct DatasetIterator {
+    resource: Output,
+    initializer: Operation,
+    output_types: Vec<DataType>,
+    output_shapes: Vec<Shape>,
+}
+
+impl DatasetIterator {
+    /// Returns the op which starts (or restarts) iterating over the
+    /// dataset.
+    pub fn initializer(&self) -> &Operation {
+        &self.initializer
+    }
+
+    /// Returns the resource tensor representing the iterator.
+    pub fn resource(&self) -> &Output {
+        &self.resource
+    }
+
+    /// Returns outputs holding the components of the next element.  Every
+    /// run which evaluates them advances the iterator, and once there are no
+    /// more elements, runs fail with an error for which
+    /// `is_end_of_sequence` is true.
+    pub fn get_next(&self, scope: &mut Scope) -> Result<Vec<Output>> {
+        let op = scope.new_operation("IteratorGetNext", |nd| {
+            nd.add_input(self.resource.clone());
+            nd.set_attr_type_list("output_types", &self.output_types)?;
+            nd.set_attr_shape_list("output_shapes", &self.output_shapes)?;
+            Ok(())
+        })?;
+        Ok((0..self.output_types.len())
+            .map(|index| Output {
+                operation: op.clone(),
+                index: index as i32,
+            })
+            .collect())
+    }
+}
+
+/// Returns true if `status` is the error a `DatasetIterator` reports when it
+/// runs out of elements.
+pub fn is_end_of_sequence(status: &Status) -> bool {
+    status.code() == Code::OutOfRange
+}
+
+/// Returns the types and shapes of `components`.
+fn signature(scope: &Scope, components: &[Output]) -> Result<(Vec<DataType>, Vec<Shape>)> {
+    let types = components
+        .iter()
+        .map(|c| c.operation.output_type(c.index as usize))
+        .collect();
+    let shapes = components
+        .iter()
+        .map(|c| Ok(Shape::from(ops::known_dims(scope, c)?)))
+        .collect::<Result<_>>()?;
+    Ok((types, shapes))
+}
+
+/// Reads the output types from the signature of `function`.
+fn function_output_types(function: &Function) -> Result<Vec<DataType>> {
+    let mut types = Vec::new();
+    for field in ProtoReader::new(&function.to_function_def()?) {
+        if let (FUNCTION_DEF_SIGNATURE, signature) = field? {
+            for field in ProtoReader::new(signature.as_bytes()?) {
+                if let (OP_DEF_OUTPUT_ARG, arg) = field? {
+                    let mut data_type = None;
+                    for field in ProtoReader::new(arg.as_bytes()?) {
+                        if let (ARG_DEF_TYPE, value) = field? {
+                            data_type = Some(DataType::from_int(value.as_i64()? as _));
+                        }
+                    }
+                    types.push(data_type.ok_or_else(|| {
+                        invalid_arg!("Functions mapped over datasets need fixed output types")
+                    })?);
+                }
+            }
+        }
+    }
+    Ok(types)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::FunctionOptions;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    fn read_all(scope: &mut Scope, dataset: &Dataset) -> Vec<Vec<f32>> {
+        let iterator = dataset.make_iterator(scope).unwrap();
+        let next = iterator.get_next(scope).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(iterator.initializer());
+        session.run(&mut args).unwrap();
+        let mut elements = Vec::new();
+        loop {
+            let mut args = SessionRunArgs::new();
+            let token = args.request_fetch(&next[0].operation, next[0].index);
+            match session.run(&mut args) {
+                Err(ref e) if is_end_of_sequence(e) => break,
+                result => result.unwrap(),
+            }
+            elements.push(args.fetch::<f32>(token).unwrap().to_vec());
+        }
+        elements
+    }
+
+    fn slices(scope: &mut Scope) -> Dataset {
+        let values = ops::constant(
+            scope,
+            Tensor::new(&[4, 2])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0])
+                .unwrap(),
+        )
+        .unwrap();
+        Dataset::from_tensor_slices(scope, &[values.into()]).unwrap()
+    }
+
+    #[test]
+    fn batch_and_repeat() {
+        let mut scope = Scope::new_root_scope();
+        let dataset = slices(&mut scope);
+        assert_eq!(dataset.output_types(), &[DataType::Float]);
+        assert_eq!(dataset.output_shapes(), &[Shape::from(Some(vec![Some(2)]))]);
+        let dataset = dataset
+            .batch(&mut scope, 3, false)
+            .unwrap()
+            .repeat(&mut scope, Some(2))
+            .unwrap()
+            .prefetch(&mut scope, 1)
+            .unwrap();
+        assert_eq!(
+            dataset.output_shapes(),
+            &[Shape::from(Some(vec![None, Some(2)]))]
+        );
+        let elements = read_all(&mut scope, &dataset);
+        assert_eq!(
+            elements,
+            vec![
+                vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+                vec![7.0, 8.0],
+                vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0],
+                vec![7.0, 8.0],
+            ]
+        );
+    }
+
+    #[test]
+    fn shuffle() {
+        let mut scope = Scope::new_root_scope();
+        let dataset = slices(&mut scope).shuffle(&mut scope, 10, Some(7)).unwrap();
+        let mut elements = read_all(&mut scope, &dataset);
+        elements.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert_eq!(elements[0], vec![1.0, 2.0]);
+        assert_eq!(elements.len(), 4);
+    }
+
+    #[test]
+    fn map() {
+        let mut scope = Scope::new_root_scope();
+        let dataset = slices(&mut scope)
+            .map(&mut scope, |scope, element| {
+                Ok(vec![ops::add(
+                    scope,
+                    element[0].clone(),
+                    element[0].clone(),
+                )?
+                .into()])
+            })
+            .unwrap();
+        assert_eq!(dataset.output_shapes(), &[Shape::from(Some(vec![Some(2)]))]);
+        let elements = read_all(&mut scope, &dataset);
+        assert_eq!(elements[3], vec![14.0, 16.0]);
+    }
+
+    /// Sequences of lengths 1, 3, 2 and 4.
+    fn sequences(scope: &mut Scope) -> Dataset {
+        let tokens = ops::constant(
+            scope,
+            Tensor::new(&[4, 4])
+                .with_values(&[
+                    1.0f32, 0.0, 0.0, 0.0, 2.0, 3.0, 4.0, 0.0, 5.0, 6.0, 0.0, 0.0, 7.0, 8.0, 9.0,
+                    10.0,
+                ])
+                .unwrap(),
+        )
+        .unwrap();
+        let lengths = ops::constant(scope, &[1, 3, 2, 4][..]).unwrap();
+        Dataset::from_tensor_slices(scope, &[tokens.into(), lengths.into()])
+            .unwrap()
+            .map(scope, |scope, element| {
+                let begin = ops::constant(scope, &[0][..])?;
+                let shape = ops::constant(scope, &[1][..])?;
+                let size = ops::reshape(scope, element[1].clone(), shape)?;
+                Ok(vec![
+                    ops::slice(scope, element[0].clone(), begin, size)?.into()
+                ])
+            })
+            .unwrap()
+    }
+
+    #[test]
+    fn padded_batch() {
+        let mut scope = Scope::new_root_scope();
+        let dataset = sequences(&mut scope);
+        let padding = ops::constant(&mut scope, -1.0f32).unwrap();
+        let unknown = [Shape::from(Some(vec![None]))];
+        let batched = dataset
+            .padded_batch(&mut scope, 2, &unknown, &[padding.clone().into()], false)
+            .unwrap();
+        assert_eq!(
+            batched.output_shapes(),
+            &[Shape::from(Some(vec![None, None]))]
+        );
+        assert_eq!(
+            read_all(&mut scope, &batched),
+            vec![
+                vec![1.0, -1.0, -1.0, 2.0, 3.0, 4.0],
+                vec![5.0, 6.0, -1.0, -1.0, 7.0, 8.0, 9.0, 10.0],
+            ]
+        );
+        let fixed = [Shape::from(Some(vec![Some(5)]))];
+        let batched = dataset
+            .padded_batch(&mut scope, 2, &fixed, &[padding.clone().into()], true)
+            .unwrap();
+        assert_eq!(
+            batched.output_shapes(),
+            &[Shape::from(Some(vec![Some(2), Some(5)]))]
+        );
+        assert_eq!(read_all(&mut scope, &batched)[0].len(), 10);
+
+        let int_padding = ops::constant(&mut scope, 0).unwrap();
+        assert!(dataset
+            .padded_batch(&mut scope, 2, &unknown, &[int_padding.into()], false)
+            .is_err());
+        assert!(dataset
+            .padded_batch(&mut scope, 2, &[], &[], false)
+            .is_err());
+        let matrix = [Shape::from(Some(vec![None, None]))];
+        assert!(dataset
+            .padded_batch(&mut scope, 2, &matrix, &[padding.into()], false)
+            .is_err());
+    }
+
+    #[test]
+    fn bucket_by_sequence_length() {
+        let mut scope = Scope::new_root_scope();
+        let dataset = sequences(&mut scope);
+        let padding = ops::constant(&mut scope, -1.0f32).unwrap();
+        let unknown = [Shape::from(Some(vec![None]))];
+        let length = |scope: &mut Scope, element: &[Output]| -> Result<Output> {
+            let axis = ops::constant(scope, 0)?;
+            let shape = ops::shape(scope, element[0].clone())?;
+            Ok(ops::gather(scope, shape, axis.clone(), axis)?.into())
+        };
+        // Sequences shorter than 3 are batched in pairs, and the others
+        // alone.
+        let bucketed = dataset
+            .bucket_by_sequence_length(
+                &mut scope,
+                length,
+                &[3],
+                &[2, 1],
+                &unknown,
+                &[padding.clone().into()],
+            )
+            .unwrap();
+        assert_eq!(
+            bucketed.output_shapes(),
+            &[Shape::from(Some(vec![None, None]))]
+        );
+        assert_eq!(
+            read_all(&mut scope, &bucketed),
+            vec![
+                vec![2.0, 3.0, 4.0],
+                vec![1.0, -1.0, 5.0, 6.0],
+                vec![7.0, 8.0, 9.0, 10.0],
+            ]
+        );
+        assert!(dataset
+            .bucket_by_sequence_length(
+                &mut scope,
+                length,
+                &[3],
+                &[2],
+                &unknown,
+                &[padding.clone().into()],
+            )
+            .is_err());
+        assert!(dataset
+            .bucket_by_sequence_length(
+                &mut scope,
+                length,
+                &[3, 2],
+                &[2, 1, 1],
+                &unknown,
+                &[padding.into()],
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn map_function() {
+        // A function adding its second argument to its first.
+        let mut function_scope = Scope::new_root_scope();
+        let x = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .build(&mut function_scope)
+            .unwrap();
+        let offset = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .build(&mut function_scope)
+            .unwrap();
+        let sum = ops::add(&mut function_scope, x.clone(), offset.clone()).unwrap();
+        let function = function_scope
+            .graph()
+            .to_function(
+                "add_offset",
+                false,
+                None,
+                &[x.into(), offset.into()],
+                &[sum.into()],
+                None::<&[&str]>,
+                &FunctionOptions::new(),
+                None,
+            )
+            .unwrap();
+
+        let mut scope = Scope::new_root_scope();
+        let offset = ops::constant(&mut scope, 10.0f32).unwrap();
+        let dataset = slices(&mut scope)
+            .map_function(&mut scope, &function, &[offset.into()])
+            .unwrap();
+        assert_eq!(dataset.output_types(), &[DataType::Float]);
+        let elements = read_all(&mut scope, &dataset);
+        assert_eq!(elements[0], vec![11.0, 12.0]);
+    }
+}
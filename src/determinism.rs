@@ -0,0 +1,130 @@
+use crate::fnv::Fnv1a;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::AtomicI64;
+use std::sync::atomic::Ordering;
+
+static DETERMINISTIC: AtomicBool = AtomicBool::new(false);
+
+/// The graph-level seed used by TensorFlow's Python API when none is set.
+const DEFAULT_SEED: i64 = 87_654_321;
+
+static SEED: AtomicI64 = AtomicI64::new(DEFAULT_SEED);
+
+/// The environment variables TensorFlow checks to select deterministic
+/// kernels.
+const DETERMINISM_VARIABLES: &[&str] = &["TF_DETERMINISTIC_OPS", "TF_CUDNN_DETERMINISTIC"];
+
+/// The stateful random ops, which draw different numbers on every run unless
+/// they are seeded.
+#[cfg_attr(not(feature = "experimental_training"), allow(dead_code))]
+const RANDOM_OPS: &[&str] = &[
+    "Multinomial",
+    "ParameterizedTruncatedNormal",
+    "RandomCrop",
+    "RandomGamma",
+    "RandomPoisson",
+    "RandomPoissonV2",
+    "RandomShuffle",
+    "RandomStandardNormal",
+    "RandomUniform",
+    "RandomUniformInt",
+    "SampleDistortedBoundingBox",
+    "SampleDistortedBoundingBoxV2",
+    "TruncatedNormal",
+];
+
+/// Enables or disables deterministic mode for the whole process, for
+/// bitwise-reproducible training.
+///
+/// While enabled:
+///
+/// * TensorFlow selects deterministic kernels (through the
+///   `TF_DETERMINISTIC_OPS` and `TF_CUDNN_DETERMINISTIC` environment
+///   variables), and fails ops which have no deterministic implementation
+///   rather than running them nondeterministically.
+/// * `SessionOptions::new` runs independent ops one at a time, so updates to
+///   shared state such as variables happen in the same order on every run.
+/// * Stateful random ops created through a `Scope` without an explicit seed
+///   are seeded from the seed set with `set_random_seed` and the op's name,
+///   so they produce the same numbers in every process which builds the same
+///   graph.
+///
+/// Deterministic kernels can be considerably slower.
+///
+/// # Safety
+///
+/// TensorFlow 1.13 can only be told to select deterministic kernels through
+/// the environment, so this sets environment variables, which is undefined
+/// behavior if another thread reads or writes the environment at the same
+/// time.  TensorFlow's own threads read it, so this may only be called at
+/// startup, while the program has a single thread and before any other
+/// function of this crate is called.
+pub unsafe fn set_deterministic(enabled: bool) {
+    DETERMINISTIC.store(enabled, Ordering::SeqCst);
+    for variable in DETERMINISM_VARIABLES {
+        if enabled {
+            std::env::set_var(variable, "1");
+        } else {
+            std::env::remove_var(variable);
+        }
+    }
+}
+
+/// Returns whether deterministic mode is enabled.  See `set_deterministic`.
+pub fn is_deterministic() -> bool {
+    DETERMINISTIC.load(Ordering::SeqCst)
+}
+
+/// Sets the seed from which random ops without an explicit seed are seeded
+/// in deterministic mode.  Changing the seed changes the numbers drawn by
+/// graphs built afterwards.
+pub fn set_random_seed(seed: i64) {
+    SEED.store(seed, Ordering::SeqCst);
+}
+
+/// Returns the `seed` and `seed2` attributes for a new op of type `op_type`
+/// named `op_name`, if it is a stateful random op and deterministic mode is
+/// enabled.
+#[cfg_attr(not(feature = "experimental_training"), allow(dead_code))]
+pub(crate) fn default_op_seeds(op_type: &str, op_name: &str) -> Option<(i64, i64)> {
+    if !is_deterministic() {
+        return None;
+    }
+    op_seeds(SEED.load(Ordering::SeqCst), op_type, op_name)
+}
+
+/// Returns the `seed` and `seed2` attributes derived from the graph-level
+/// `seed` for a new op of type `op_type` named `op_name`, if it is a stateful
+/// random op.
+#[cfg_attr(not(feature = "experimental_training"), allow(dead_code))]
+fn op_seeds(seed: i64, op_type: &str, op_name: &str) -> Option<(i64, i64)> {
+    if !RANDOM_OPS.contains(&op_type) {
+        return None;
+    }
+    let hash = Fnv1a::hash(op_name.as_bytes());
+    // TensorFlow treats a pair of zero seeds as unseeded.
+    let seed2 = (hash >> 1).max(1) as i64;
+    Some((seed, seed2))
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Deterministic mode and the seed are process-wide, and changing them
+    // would affect the other tests running concurrently, so this tests the
+    // seeds without them.  tests/deterministic_mode.rs tests the mode in a
+    // separate process.
+    #[test]
+    fn op_seeds_are_stable() {
+        let seeds = op_seeds(DEFAULT_SEED, "RandomUniform", "x").unwrap();
+        assert_eq!(seeds.0, DEFAULT_SEED);
+        assert_ne!(seeds.1, 0);
+        assert_eq!(op_seeds(DEFAULT_SEED, "RandomUniform", "x"), Some(seeds));
+        assert_ne!(op_seeds(DEFAULT_SEED, "RandomUniform", "y"), Some(seeds));
+        assert_eq!(op_seeds(7, "RandomUniform", "x"), Some((7, seeds.1)));
+        assert_eq!(op_seeds(DEFAULT_SEED, "Add", "x"), None);
+    }
+}
@@ -0,0 +1,204 @@
+use crate::feed::run_by_name;
+use crate::FeedValue;
+use crate::Graph;
+use crate::ImportGraphDefOptions;
+use crate::Result;
+use crate::Session;
+use crate::SessionOptions;
+use crate::Tensor;
+use crate::TensorType;
+
+/// A copy of the graph pinned to one device, and the session running it.
+#[derive(Debug)]
+struct Route {
+    device: String,
+    min_batch_size: u64,
+    graph: Graph,
+    session: Session,
+}
+
+/// Runs one graph on a device chosen for each run, e.g. to serve small
+/// requests on the CPU and large batches on a GPU.
+///
+/// ```no_run
+/// # use tensorflow::DeviceRouter;
+/// # use tensorflow::Graph;
+/// # use tensorflow::SessionOptions;
+/// # use tensorflow::Tensor;
+/// # let graph = Graph::new();
+/// let mut options = SessionOptions::new();
+/// options.set_allow_soft_placement(true)?;
+/// let router = DeviceRouter::new(
+///     &graph,
+///     &[(0, "/device:CPU:0"), (64, "/device:GPU:0")],
+///     &options,
+/// )?;
+/// let x = Tensor::<f32>::new(&[128, 10]);
+/// // Runs on the GPU, because the batch has at least 64 examples.
+/// let y = router.run::<f32>(&[("x", &x)], "y")?;
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+///
+/// The TensorFlow runtime places each op when a graph is first run, so the
+/// router keeps a copy of the graph and a session for each device, with every
+/// op which isn't explicitly placed pinned to that device.  Ops without a
+/// kernel for the device fail to run unless soft placement is enabled with
+/// `SessionOptions::set_allow_soft_placement`, in which case they fall back
+/// to another device.  Variables are not shared between the copies, so the
+/// graph should be frozen or its variables restored in every session.
+#[derive(Debug)]
+pub struct DeviceRouter {
+    // Sorted by minimum batch size.
+    routes: Vec<Route>,
+}
+
+impl DeviceRouter {
+    /// Creates a copy of `graph` and a session for each route, which pairs
+    /// the smallest batch size to run on a device with the device, e.g.
+    /// `(64, "/device:GPU:0")`.  Smaller batches than any route's minimum run
+    /// on the device with the smallest minimum.
+    pub fn new(graph: &Graph, routes: &[(u64, &str)], options: &SessionOptions) -> Result<Self> {
+        if routes.is_empty() {
+            return Err(invalid_arg!("A device router requires at least one route"));
+        }
+        let graph_def = graph.graph_def()?;
+        let mut pinned = Vec::with_capacity(routes.len());
+        for (min_batch_size, device) in routes {
+            if pinned.iter().any(|r: &Route| r.device == *device) {
+                return Err(invalid_arg!("Device {:?} has more than one route", device));
+            }
+            let mut import_options = ImportGraphDefOptions::new();
+            import_options.set_default_device(device)?;
+            let mut graph = Graph::new();
+            graph.import_graph_def(&graph_def, &import_options)?;
+            let session = Session::new(options, &graph)?;
+            pinned.push(Route {
+                device: device.to_string(),
+                min_batch_size: *min_batch_size,
+                graph,
+                session,
+            });
+        }
+        pinned.sort_by_key(|r| r.min_batch_size);
+        Ok(Self { routes: pinned })
+    }
+
+    /// Returns the devices, in order of their minimum batch sizes.
+    pub fn devices(&self) -> Vec<&str> {
+        self.routes.iter().map(|r| r.device.as_str()).collect()
+    }
+
+    /// Returns the device a batch of `batch_size` examples runs on.
+    pub fn device_for(&self, batch_size: u64) -> &str {
+        &self.route_for(batch_size).device
+    }
+
+    fn route_for(&self, batch_size: u64) -> &Route {
+        self.routes
+            .iter()
+            .rev()
+            .find(|r| r.min_batch_size <= batch_size)
+            .unwrap_or(&self.routes[0])
+    }
+
+    fn route(&self, device: &str) -> Result<&Route> {
+        self.routes
+            .iter()
+            .find(|r| r.device == device)
+            .ok_or_else(|| invalid_arg!("No route to device {:?}", device))
+    }
+
+    /// Returns the copy of the graph pinned to `device`.  Operations fed to
+    /// or fetched from the session for `device` must come from this graph.
+    pub fn graph(&self, device: &str) -> Result<&Graph> {
+        Ok(&self.route(device)?.graph)
+    }
+
+    /// Returns the session running the graph on `device`, for running it in
+    /// ways `run_on` doesn't support.
+    pub fn session(&self, device: &str) -> Result<&Session> {
+        Ok(&self.route(device)?.session)
+    }
+
+    /// Feeds each tensor in `inputs` to the tensor with the paired name, and
+    /// returns the value of the tensor named `output`, running on the device
+    /// routed to for the batch size, which is the first dimension of the
+    /// first input.  Names are either `"op:index"` or just `"op"` for output
+    /// 0 of the op.
+    pub fn run<U: TensorType>(
+        &self,
+        inputs: &[(&str, &dyn FeedValue)],
+        output: &str,
+    ) -> Result<Tensor<U>> {
+        let batch_size = inputs
+            .first()
+            .and_then(|(_, value)| value.feed_dims().first().cloned())
+            .unwrap_or(1);
+        self.run_route(self.route_for(batch_size), inputs, output)
+    }
+
+    /// Like `run`, but runs on `device` regardless of the batch size.
+    pub fn run_on<U: TensorType>(
+        &self,
+        device: &str,
+        inputs: &[(&str, &dyn FeedValue)],
+        output: &str,
+    ) -> Result<Tensor<U>> {
+        self.run_route(self.route(device)?, inputs, output)
+    }
+
+    fn run_route<U: TensorType>(
+        &self,
+        route: &Route,
+        inputs: &[(&str, &dyn FeedValue)],
+        output: &str,
+    ) -> Result<Tensor<U>> {
+        let find = |name: &str| route.graph.output_by_name_required(name);
+        run_by_name(&route.session, inputs, output, find, find)
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn routes_by_batch_size() {
+        let graph_def = std::fs::read("examples/addition/model.pb").unwrap();
+        let mut graph = Graph::new();
+        graph
+            .import_graph_def(&graph_def, &ImportGraphDefOptions::new())
+            .unwrap();
+        let mut options = SessionOptions::new();
+        options.set_device_count("CPU", 2).unwrap();
+        options.set_allow_soft_placement(true).unwrap();
+        let router = DeviceRouter::new(
+            &graph,
+            &[(4, "/device:CPU:1"), (0, "/device:CPU:0")],
+            &options,
+        )
+        .unwrap();
+        assert_eq!(router.devices(), vec!["/device:CPU:0", "/device:CPU:1"]);
+        assert_eq!(router.device_for(1), "/device:CPU:0");
+        assert_eq!(router.device_for(4), "/device:CPU:1");
+        let z = router
+            .graph("/device:CPU:1")
+            .unwrap()
+            .operation_by_name_required("z")
+            .unwrap();
+        assert_eq!(z.device().unwrap(), "/device:CPU:1");
+
+        let x = Tensor::new(&[1]).with_values(&[2i32]).unwrap();
+        let y = Tensor::new(&[1]).with_values(&[40i32]).unwrap();
+        let z = router.run::<i32>(&[("x", &x), ("y", &y)], "z").unwrap();
+        assert_eq!(&z[..], &[42]);
+        let z = router
+            .run_on::<i32>("/device:CPU:1", &[("x", &x), ("y", &y)], "z")
+            .unwrap();
+        assert_eq!(&z[..], &[42]);
+        assert!(router.session("/device:GPU:0").is_err());
+        assert!(DeviceRouter::new(&graph, &[], &options).is_err());
+    }
+}
@@ -0,0 +1,538 @@
+//! This module runs ops eagerly, computing their outputs immediately instead
+//! of adding them to a graph which is run by a `Session`.
+//!
+//! This module requires the `eager` feature.
+//!
+//! ```ignore
+//! let context = Context::new(&ContextOptions::new())?;
+//! let x = TensorHandle::new(&context, &Tensor::from(&[1.0f32, 2.0][..]))?;
+//! let y = TensorHandle::new(&context, &Tensor::from(&[3.0f32, 4.0][..]))?;
+//! let sum = execute_op(&context, "Add", &[&x, &y], 1)?;
+//! let sum: Tensor<f32> = sum[0].resolve()?;
+//! ```
+//!
+//! Ops are identified by their type, as in a `GraphDef`, and the type
+//! attributes of their inputs (such as "T") are inferred from the inputs.
+//! Other attributes are set with `Op`.  Eager execution is convenient for
+//! experimenting and for models whose structure depends on the data, but
+//! running each op separately is slower than running a graph.
+
+use crate::AnyTensor;
+use crate::DataType;
+use crate::Result;
+use crate::Shape;
+use crate::Status;
+use crate::Tensor;
+use crate::TensorType;
+use libc::c_int;
+use std::ffi::CStr;
+use std::ffi::CString;
+use std::marker::PhantomData;
+use std::os::raw::c_void;
+use std::ptr;
+use tensorflow_sys as tf;
+
+/// Options for creating a `Context`.
+#[derive(Debug)]
+pub struct ContextOptions {
+    inner: *mut tf::TFE_ContextOptions,
+}
+
+impl ContextOptions {
+    /// Creates the default options.
+    pub fn new() -> Self {
+        Self {
+            inner: unsafe { tf::TFE_NewContextOptions() },
+        }
+    }
+
+    /// Sets the serialized `ConfigProto` used by the context, e.g. to limit
+    /// the number of threads, like `SessionOptions::set_config`.
+    pub fn set_config(&mut self, config: &[u8]) -> Result<()> {
+        let mut status = Status::new();
+        unsafe {
+            tf::TFE_ContextOptionsSetConfig(
+                self.inner,
+                config.as_ptr() as *const c_void,
+                config.len(),
+                status.inner(),
+            );
+        }
+        status.into_result()
+    }
+
+    /// Sets whether ops are run asynchronously, so that executing an op
+    /// returns before its outputs are computed.  Resolving an output then
+    /// waits for it, and reports any error from computing it.  Default is
+    /// false.
+    pub fn set_async(&mut self, enable: bool) {
+        unsafe {
+            tf::TFE_ContextOptionsSetAsync(self.inner, enable as u8);
+        }
+    }
+}
+
+impl Default for ContextOptions {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Drop for ContextOptions {
+    fn drop(&mut self) {
+        unsafe {
+            tf::TFE_DeleteContextOptions(self.inner);
+        }
+    }
+}
+
+/// The state of eager execution, such as the available devices and their
+/// memory, which tensor handles and ops belong to.
+#[derive(Debug)]
+pub struct Context {
+    inner: *mut tf::TFE_Context,
+}
+
+impl Context {
+    /// Creates a context.
+    pub fn new(options: &ContextOptions) -> Result<Self> {
+        let mut status = Status::new();
+        let inner = unsafe { tf::TFE_NewContext(options.inner, status.inner()) };
+        if inner.is_null() {
+            Err(status)
+        } else {
+            Ok(Self { inner })
+        }
+    }
+
+    /// Returns the names of the devices ops can run on, e.g.
+    /// `/job:localhost/replica:0/task:0/device:CPU:0`.
+    pub fn device_names(&self) -> Result<Vec<String>> {
+        let mut status = Status::new();
+        unsafe {
+            let list = tf::TFE_ContextListDevices(self.inner, status.inner());
+            if !status.is_ok() {
+                return Err(status);
+            }
+            let result = (|| {
+                let n = tf::TF_DeviceListCount(list);
+                let mut names = Vec::with_capacity(n as usize);
+                for i in 0..n {
+                    let name = tf::TF_DeviceListName(list, i, status.inner());
+                    if !status.is_ok() {
+                        return Err(status);
+                    }
+                    names.push(CStr::from_ptr(name).to_str()?.to_string());
+                }
+                Ok(names)
+            })();
+            tf::TF_DeleteDeviceList(list);
+            result
+        }
+    }
+}
+
+impl Drop for Context {
+    fn drop(&mut self) {
+        unsafe {
+            tf::TFE_DeleteContext(self.inner);
+        }
+    }
+}
+
+unsafe impl Send for Context {}
+
+unsafe impl Sync for Context {}
+
+/// A tensor on a device of a `Context`, which is the input and output of
+/// eagerly executed ops.
+#[derive(Debug)]
+pub struct TensorHandle<'a> {
+    inner: *mut tf::TFE_TensorHandle,
+    context: PhantomData<&'a Context>,
+}
+
+impl<'a> TensorHandle<'a> {
+    /// Creates a handle holding a copy of `tensor`, on the host.
+    pub fn new<T: TensorType>(_context: &'a Context, tensor: &Tensor<T>) -> Result<Self> {
+        let mut status = Status::new();
+        let inner = unsafe { tf::TFE_NewTensorHandle(tensor.inner()?, status.inner()) };
+        Self::from_inner(inner, status)
+    }
+
+    fn from_inner(inner: *mut tf::TFE_TensorHandle, status: Status) -> Result<Self> {
+        if inner.is_null() || !status.is_ok() {
+            return Err(status);
+        }
+        Ok(Self {
+            inner,
+            context: PhantomData,
+        })
+    }
+
+    /// Returns the data type of the tensor.
+    pub fn data_type(&self) -> DataType {
+        DataType::from_c(unsafe { tf::TFE_TensorHandleDataType(self.inner) })
+    }
+
+    /// Returns the dimensions of the tensor.  In an asynchronous context,
+    /// this waits for the tensor to be computed.
+    pub fn dims(&self) -> Result<Vec<u64>> {
+        let mut status = Status::new();
+        let rank = unsafe { tf::TFE_TensorHandleNumDims(self.inner, status.inner()) };
+        if !status.is_ok() {
+            return Err(status);
+        }
+        let mut dims = Vec::with_capacity(rank as usize);
+        for i in 0..rank {
+            let dim = unsafe { tf::TFE_TensorHandleDim(self.inner, i, status.inner()) };
+            if !status.is_ok() {
+                return Err(status);
+            }
+            dims.push(dim as u64);
+        }
+        Ok(dims)
+    }
+
+    /// Returns the name of the device holding the tensor.
+    pub fn device_name(&self) -> Result<String> {
+        let mut status = Status::new();
+        let name = unsafe { tf::TFE_TensorHandleDeviceName(self.inner, status.inner()) };
+        if !status.is_ok() {
+            return Err(status);
+        }
+        Ok(unsafe { CStr::from_ptr(name) }.to_str()?.to_string())
+    }
+
+    /// Returns a handle to the same tensor, without copying its data.
+    pub fn try_clone(&self) -> Result<TensorHandle<'a>> {
+        let mut status = Status::new();
+        let inner = unsafe { tf::TFE_TensorHandleCopySharingTensor(self.inner, status.inner()) };
+        Self::from_inner(inner, status)
+    }
+
+    /// Returns a copy of the tensor on the device named `device`, e.g.
+    /// `/device:GPU:0`.
+    pub fn copy_to_device(&self, context: &'a Context, device: &str) -> Result<TensorHandle<'a>> {
+        let device = CString::new(device)?;
+        let mut status = Status::new();
+        let inner = unsafe {
+            tf::TFE_TensorHandleCopyToDevice(
+                self.inner,
+                context.inner,
+                device.as_ptr(),
+                status.inner(),
+            )
+        };
+        Self::from_inner(inner, status)
+    }
+
+    /// Copies the tensor to the host and returns it, waiting for it to be
+    /// computed if needed.  Fails if the tensor doesn't hold `T`s.
+    pub fn resolve<T: TensorType>(&self) -> Result<Tensor<T>> {
+        if self.data_type() != T::data_type() {
+            return Err(invalid_arg!(
+                "Can't resolve a tensor handle of type {} as {}",
+                self.data_type(),
+                T::data_type()
+            ));
+        }
+        let mut status = Status::new();
+        let tensor = unsafe { tf::TFE_TensorHandleResolve(self.inner, status.inner()) };
+        if tensor.is_null() || !status.is_ok() {
+            return Err(status);
+        }
+        // The data type was checked above.
+        Ok(unsafe { Tensor::from_tf_tensor(tensor) }.unwrap())
+    }
+}
+
+impl<'a> Drop for TensorHandle<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            tf::TFE_DeleteTensorHandle(self.inner);
+        }
+    }
+}
+
+/// An op to execute eagerly, with its inputs and attributes.
+///
+/// ```ignore
+/// let mut op = Op::new(&context, "MatMul")?;
+/// op.add_input(&a)?;
+/// op.add_input(&b)?;
+/// op.set_attr_bool("transpose_b", true)?;
+/// let product = op.execute(1)?;
+/// ```
+#[derive(Debug)]
+pub struct Op<'a> {
+    inner: *mut tf::TFE_Op,
+    context: PhantomData<&'a Context>,
+}
+
+impl<'a> Op<'a> {
+    /// Creates an op of type `op_type`, e.g. "MatMul".
+    pub fn new(context: &'a Context, op_type: &str) -> Result<Self> {
+        let op_type = CString::new(op_type)?;
+        let mut status = Status::new();
+        let inner = unsafe { tf::TFE_NewOp(context.inner, op_type.as_ptr(), status.inner()) };
+        if inner.is_null() || !status.is_ok() {
+            return Err(status);
+        }
+        Ok(Self {
+            inner,
+            context: PhantomData,
+        })
+    }
+
+    /// Adds the next input.
+    pub fn add_input(&mut self, input: &TensorHandle<'a>) -> Result<()> {
+        let mut status = Status::new();
+        unsafe {
+            tf::TFE_OpAddInput(self.inner, input.inner, status.inner());
+        }
+        status.into_result()
+    }
+
+    /// Adds a list of inputs, for an op such as "ConcatV2" which takes a
+    /// variable number of tensors as a single input.
+    pub fn add_input_list(&mut self, inputs: &[&TensorHandle<'a>]) -> Result<()> {
+        let mut handles: Vec<*mut tf::TFE_TensorHandle> = inputs.iter().map(|h| h.inner).collect();
+        let mut status = Status::new();
+        unsafe {
+            tf::TFE_OpAddInputList(
+                self.inner,
+                handles.as_mut_ptr(),
+                handles.len() as c_int,
+                status.inner(),
+            );
+        }
+        status.into_result()
+    }
+
+    /// Sets the device to run the op on.  By default, the op runs on the
+    /// device of its inputs, or on a GPU if one is available.
+    pub fn set_device(&mut self, device: &str) -> Result<()> {
+        let device = CString::new(device)?;
+        let mut status = Status::new();
+        unsafe {
+            tf::TFE_OpSetDevice(self.inner, device.as_ptr(), status.inner());
+        }
+        status.into_result()
+    }
+
+    /// Sets a string attribute.
+    pub fn set_attr_string(&mut self, attr_name: &str, value: &str) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        unsafe {
+            tf::TFE_OpSetAttrString(
+                self.inner,
+                attr_name.as_ptr(),
+                value.as_ptr() as *const c_void,
+                value.len(),
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets an int attribute.
+    pub fn set_attr_int(&mut self, attr_name: &str, value: i64) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        unsafe {
+            tf::TFE_OpSetAttrInt(self.inner, attr_name.as_ptr(), value);
+        }
+        Ok(())
+    }
+
+    /// Sets a float attribute.
+    pub fn set_attr_float(&mut self, attr_name: &str, value: f32) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        unsafe {
+            tf::TFE_OpSetAttrFloat(self.inner, attr_name.as_ptr(), value);
+        }
+        Ok(())
+    }
+
+    /// Sets a boolean attribute.
+    pub fn set_attr_bool(&mut self, attr_name: &str, value: bool) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        unsafe {
+            tf::TFE_OpSetAttrBool(self.inner, attr_name.as_ptr(), value as u8);
+        }
+        Ok(())
+    }
+
+    /// Sets a type attribute.
+    pub fn set_attr_type(&mut self, attr_name: &str, value: DataType) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        unsafe {
+            tf::TFE_OpSetAttrType(self.inner, attr_name.as_ptr(), value.to_c());
+        }
+        Ok(())
+    }
+
+    /// Sets a shape attribute.
+    pub fn set_attr_shape(&mut self, attr_name: &str, value: &Shape) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        let dims: Vec<i64> = match &value.0 {
+            None => Vec::new(),
+            Some(dims) => dims.iter().map(|d| d.unwrap_or(-1)).collect(),
+        };
+        // A rank of -1 means the rank is unknown.
+        let rank = value.0.as_ref().map_or(-1, |dims| dims.len() as c_int);
+        let mut status = Status::new();
+        unsafe {
+            tf::TFE_OpSetAttrShape(
+                self.inner,
+                attr_name.as_ptr(),
+                dims.as_ptr(),
+                rank,
+                status.inner(),
+            );
+        }
+        status.into_result()
+    }
+
+    /// Sets an attribute which holds an array of ints.
+    pub fn set_attr_int_list(&mut self, attr_name: &str, value: &[i64]) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        unsafe {
+            tf::TFE_OpSetAttrIntList(
+                self.inner,
+                attr_name.as_ptr(),
+                value.as_ptr(),
+                value.len() as c_int,
+            );
+        }
+        Ok(())
+    }
+
+    /// Sets an attribute which holds an array of types.
+    pub fn set_attr_type_list(&mut self, attr_name: &str, value: &[DataType]) -> Result<()> {
+        let attr_name = CString::new(attr_name)?;
+        let types: Vec<tf::TF_DataType> = value.iter().map(DataType::to_c).collect();
+        unsafe {
+            tf::TFE_OpSetAttrTypeList(
+                self.inner,
+                attr_name.as_ptr(),
+                types.as_ptr(),
+                types.len() as c_int,
+            );
+        }
+        Ok(())
+    }
+
+    /// Runs the op, and returns handles to its outputs.  `num_outputs` must
+    /// be at least the number of outputs the op has, or it fails; only as
+    /// many handles as the op has outputs are returned.
+    pub fn execute(self, num_outputs: usize) -> Result<Vec<TensorHandle<'a>>> {
+        let mut retvals = vec![ptr::null_mut(); num_outputs];
+        let mut num_retvals = num_outputs as c_int;
+        let mut status = Status::new();
+        unsafe {
+            tf::TFE_Execute(
+                self.inner,
+                retvals.as_mut_ptr(),
+                &mut num_retvals,
+                status.inner(),
+            );
+        }
+        if !status.is_ok() {
+            return Err(status);
+        }
+        Ok(retvals[..num_retvals as usize]
+            .iter()
+            .map(|&inner| TensorHandle {
+                inner,
+                context: PhantomData,
+            })
+            .collect())
+    }
+}
+
+impl<'a> Drop for Op<'a> {
+    fn drop(&mut self) {
+        unsafe {
+            tf::TFE_DeleteOp(self.inner);
+        }
+    }
+}
+
+/// Runs an op of type `op_type` with `inputs` and no other attributes than
+/// the types inferred from the inputs, and returns its outputs, of which
+/// there must be at most `num_outputs`.  Use `Op` to set attributes.
+pub fn execute_op<'a>(
+    context: &'a Context,
+    op_type: &str,
+    inputs: &[&TensorHandle<'a>],
+    num_outputs: usize,
+) -> Result<Vec<TensorHandle<'a>>> {
+    let mut op = Op::new(context, op_type)?;
+    for input in inputs {
+        op.add_input(input)?;
+    }
+    op.execute(num_outputs)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tensor_round_trip() {
+        let context = Context::new(&ContextOptions::new()).unwrap();
+        assert!(!context.device_names().unwrap().is_empty());
+        let tensor = Tensor::new(&[2, 3])
+            .with_values(&[1i32, 2, 3, 4, 5, 6])
+            .unwrap();
+        let handle = TensorHandle::new(&context, &tensor).unwrap();
+        assert_eq!(handle.data_type(), DataType::Int32);
+        assert_eq!(handle.dims().unwrap(), vec![2, 3]);
+        assert!(handle.device_name().unwrap().contains("CPU"));
+        let copy = handle.try_clone().unwrap();
+        assert_eq!(copy.resolve::<i32>().unwrap(), tensor);
+        assert!(handle.resolve::<f32>().is_err());
+    }
+
+    #[test]
+    fn execute() {
+        let context = Context::new(&ContextOptions::new()).unwrap();
+        let a = TensorHandle::new(
+            &context,
+            &Tensor::new(&[2, 2])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+                .unwrap(),
+        )
+        .unwrap();
+        let b = TensorHandle::new(&context, &Tensor::from(10.0f32)).unwrap();
+        let sum = execute_op(&context, "Add", &[&a, &b], 1).unwrap();
+        assert_eq!(
+            &sum[0].resolve::<f32>().unwrap()[..],
+            &[11.0, 12.0, 13.0, 14.0]
+        );
+
+        let mut op = Op::new(&context, "MatMul").unwrap();
+        op.add_input(&a).unwrap();
+        op.add_input(&sum[0]).unwrap();
+        op.set_attr_bool("transpose_b", true).unwrap();
+        let product = op.execute(1).unwrap();
+        // [[1, 2], [3, 4]] * [[11, 13], [12, 14]]
+        assert_eq!(
+            &product[0].resolve::<f32>().unwrap()[..],
+            &[35.0, 41.0, 81.0, 95.0]
+        );
+
+        assert!(Op::new(&context, "NoSuchOp").is_err());
+        let int = TensorHandle::new(&context, &Tensor::from(1i32)).unwrap();
+        assert!(execute_op(&context, "Add", &[&a, &int], 1).is_err());
+
+        // Unique has two outputs.
+        let values = TensorHandle::new(&context, &Tensor::from(&[3i32, 1, 3][..])).unwrap();
+        assert!(execute_op(&context, "Unique", &[&values], 1).is_err());
+        let unique = execute_op(&context, "Unique", &[&values], 3).unwrap();
+        assert_eq!(unique.len(), 2);
+        assert_eq!(&unique[0].resolve::<i32>().unwrap()[..], &[3, 1]);
+    }
+}
@@ -0,0 +1,239 @@
+use crate::FetchToken;
+use crate::Graph;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Tensor;
+use crate::TensorType;
+use libc::c_int;
+
+/// A set of tensors which are fed to named tensors in a graph.
+///
+/// This is usually derived with `#[derive(Feed)]`, which feeds each field (a
+/// `FeedValue`) to the tensor named by its `#[tensorflow(name = "op:index")]`
+/// attribute, or to output 0 of the op with the same name as the field.
+///
+/// ```
+/// use tensorflow::{Feed, Fetch};
+/// # use tensorflow::{DataType, Graph, Session, SessionOptions, Shape, Status, Tensor};
+///
+/// #[derive(Feed)]
+/// struct Inputs {
+///     #[tensorflow(name = "x:0")]
+///     value: Tensor<f32>,
+/// }
+///
+/// #[derive(Fetch)]
+/// struct Outputs {
+///     y: Tensor<f32>,
+///     #[tensorflow(name = "y")]
+///     values: Vec<f32>,
+/// }
+///
+/// # fn main() -> Result<(), Status> {
+/// # let mut graph = Graph::new();
+/// # let x = {
+/// #     let mut nd = graph.new_operation("Placeholder", "x")?;
+/// #     nd.set_attr_type("dtype", DataType::Float)?;
+/// #     nd.set_attr_shape("shape", &Shape::new(Some(vec![Some(2)])))?;
+/// #     nd.finish()?
+/// # };
+/// # {
+/// #     let mut nd = graph.new_operation("Square", "y")?;
+/// #     nd.add_input(x);
+/// #     nd.finish()?;
+/// # }
+/// let session = Session::new(&SessionOptions::new(), &graph)?;
+/// let inputs = Inputs {
+///     value: Tensor::new(&[2]).with_values(&[2.0, 3.0])?,
+/// };
+/// let outputs: Outputs = session.run_typed(&graph, &inputs)?;
+/// assert_eq!(outputs.values, vec![4.0, 9.0]);
+/// # Ok(())
+/// # }
+/// ```
+pub trait Feed {
+    /// Adds feeds for the tensors to `args`, looking up the tensors they're
+    /// fed to in `graph`.
+    fn add_feeds<'l>(&'l self, graph: &Graph, args: &mut SessionRunArgs<'l>) -> Result<()>;
+}
+
+/// A value which can be fed to a tensor.
+pub trait FeedValue {
+    /// Adds a feed of the value to output `index` of `operation` to `args`.
+    fn feed_value<'l>(&'l self, args: &mut SessionRunArgs<'l>, operation: &Operation, index: c_int);
+
+    /// Returns the dimensions of the tensor which is fed.
+    fn feed_dims(&self) -> Vec<u64>;
+}
+
+impl<T: TensorType> FeedValue for Tensor<T> {
+    fn feed_value<'l>(
+        &'l self,
+        args: &mut SessionRunArgs<'l>,
+        operation: &Operation,
+        index: c_int,
+    ) {
+        args.add_feed(operation, index, self);
+    }
+
+    fn feed_dims(&self) -> Vec<u64> {
+        self.dims().to_vec()
+    }
+}
+
+/// Feeds the values as a vector, copying them into a new tensor.
+impl<T: TensorType> FeedValue for Vec<T> {
+    fn feed_value<'l>(
+        &'l self,
+        args: &mut SessionRunArgs<'l>,
+        operation: &Operation,
+        index: c_int,
+    ) {
+        args.add_feed_owned(operation, index, Tensor::from(&self[..]));
+    }
+
+    fn feed_dims(&self) -> Vec<u64> {
+        vec![self.len() as u64]
+    }
+}
+
+/// A set of values which are fetched from named tensors in a graph.
+///
+/// This is usually derived with `#[derive(Fetch)]`, which fetches each field
+/// (a `FetchValue`) from the tensor named by its
+/// `#[tensorflow(name = "op:index")]` attribute, or from output 0 of the op
+/// with the same name as the field.  See `Feed` for an example.
+pub trait Fetch: Sized {
+    /// Requests fetches of the tensors in `graph` and returns their tokens.
+    fn request_fetches(graph: &Graph, args: &mut SessionRunArgs<'_>) -> Result<Vec<FetchToken>>;
+
+    /// Converts the fetched tensors to `Self`.  `tokens` must be the tokens
+    /// returned by `request_fetches`, and the session must have run `args`.
+    fn fetch(args: &mut SessionRunArgs<'_>, tokens: &[FetchToken]) -> Result<Self>;
+}
+
+/// A value which can be converted from a fetched tensor.
+pub trait FetchValue: Sized {
+    /// Takes the fetched tensor for `token` out of `args` and converts it.
+    fn fetch_value(args: &mut SessionRunArgs<'_>, token: FetchToken) -> Result<Self>;
+}
+
+impl<T: TensorType> FetchValue for Tensor<T> {
+    fn fetch_value(args: &mut SessionRunArgs<'_>, token: FetchToken) -> Result<Self> {
+        args.fetch(token)
+    }
+}
+
+/// Fetches the values of a tensor of any shape in row-major order.
+impl<T: TensorType> FetchValue for Vec<T> {
+    fn fetch_value(args: &mut SessionRunArgs<'_>, token: FetchToken) -> Result<Self> {
+        Ok(args.fetch::<T>(token)?.to_vec())
+    }
+}
+
+impl Session {
+    /// Runs the graph, feeding `inputs` and fetching the outputs.
+    ///
+    /// `graph` must be the graph the session was created with.
+    pub fn run_typed<I: Feed, O: Fetch>(&self, graph: &Graph, inputs: &I) -> Result<O> {
+        let mut args = SessionRunArgs::new();
+        inputs.add_feeds(graph, &mut args)?;
+        let tokens = O::request_fetches(graph, &mut args)?;
+        self.run(&mut args)?;
+        O::fetch(&mut args, &tokens)
+    }
+}
+
+/// Feeds each value in `inputs` to the tensor `find_input` returns for the
+/// paired name, and returns the value of the tensor `find_output` returns for
+/// `output`.  The values may have different types.
+pub(crate) fn run_by_name<U, I, O>(
+    session: &Session,
+    inputs: &[(&str, &dyn FeedValue)],
+    output: &str,
+    find_input: I,
+    find_output: O,
+) -> Result<Tensor<U>>
+where
+    U: TensorType,
+    I: Fn(&str) -> Result<Output>,
+    O: Fn(&str) -> Result<Output>,
+{
+    let mut args = SessionRunArgs::new();
+    for (name, value) in inputs {
+        let input = find_input(name)?;
+        value.feed_value(&mut args, &input.operation, input.index);
+    }
+    let output = find_output(output)?;
+    let token = args.request_fetch(&output.operation, output.index);
+    session.run(&mut args)?;
+    args.fetch(token)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::SessionOptions;
+    use crate::Shape;
+
+    struct Inputs {
+        x: Tensor<f32>,
+    }
+
+    impl Feed for Inputs {
+        fn add_feeds<'l>(&'l self, graph: &Graph, args: &mut SessionRunArgs<'l>) -> Result<()> {
+            args.add_feed(&graph.operation_by_name_required("x")?, 0, &self.x);
+            Ok(())
+        }
+    }
+
+    struct Outputs {
+        y: Vec<f32>,
+    }
+
+    impl Fetch for Outputs {
+        fn request_fetches(
+            graph: &Graph,
+            args: &mut SessionRunArgs<'_>,
+        ) -> Result<Vec<FetchToken>> {
+            Ok(vec![
+                args.request_fetch(&graph.operation_by_name_required("y")?, 0)
+            ])
+        }
+
+        fn fetch(args: &mut SessionRunArgs<'_>, tokens: &[FetchToken]) -> Result<Self> {
+            Ok(Outputs {
+                y: FetchValue::fetch_value(args, tokens[0])?,
+            })
+        }
+    }
+
+    #[test]
+    fn run_typed() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape(Some(vec![None])))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        {
+            let mut nd = g.new_operation("Square", "y").unwrap();
+            nd.add_input(x);
+            nd.finish().unwrap();
+        }
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+        let inputs = Inputs {
+            x: Tensor::new(&[3]).with_values(&[1.0f32, 2.0, 3.0]).unwrap(),
+        };
+        let outputs: Outputs = session.run_typed(&g, &inputs).unwrap();
+        assert_eq!(outputs.y, vec![1.0, 4.0, 9.0]);
+    }
+}
@@ -0,0 +1,400 @@
+use crate::fnv::Fnv1a;
+use crate::protos::ProtoReader;
+use crate::protos::ProtoValue;
+use crate::Graph;
+use crate::Result;
+use crate::Status;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::str::FromStr;
+
+/// The field numbers of the messages inspected here.
+const GRAPH_DEF_NODE: u32 = 1;
+const GRAPH_DEF_LIBRARY: u32 = 2;
+const LIBRARY_FUNCTION: u32 = 1;
+const LIBRARY_GRADIENT: u32 = 2;
+const FUNCTION_DEF_SIGNATURE: u32 = 1;
+const FUNCTION_DEF_NODE_DEF: u32 = 3;
+const FUNCTION_DEF_RET: u32 = 4;
+const FUNCTION_DEF_ATTR: u32 = 5;
+const FUNCTION_DEF_CONTROL_RET: u32 = 6;
+const NODE_DEF_NAME: u32 = 1;
+const NODE_DEF_OP: u32 = 2;
+const NODE_DEF_INPUT: u32 = 3;
+const NODE_DEF_DEVICE: u32 = 4;
+const NODE_DEF_ATTR: u32 = 5;
+const ATTR_VALUE_LIST: u32 = 1;
+const ATTR_VALUE_FUNC: u32 = 10;
+const LIST_VALUE_FUNC: u32 = 9;
+const NAME_ATTR_LIST_NAME: u32 = 1;
+const NAME_ATTR_LIST_ATTR: u32 = 2;
+const MAP_ENTRY_KEY: u32 = 1;
+const MAP_ENTRY_VALUE: u32 = 2;
+
+/// A fingerprint of the structure of a graph: the names, ops, inputs,
+/// devices and attributes of its nodes, and its function library.
+///
+/// Deployment systems can compare the fingerprint of a loaded model with
+/// the one recorded when it was exported, and tests can check that a graph
+/// is built the same way every time.
+///
+/// ```no_run
+/// # use tensorflow::Graph;
+/// # use tensorflow::GraphFingerprint;
+/// # let graph = Graph::new();
+/// let expected: GraphFingerprint = "9c1a5f0e2b7d4863".parse()?;
+/// let actual = graph.fingerprint()?;
+/// if actual != expected {
+///     panic!("Expected model {} but loaded {}", expected, actual);
+/// }
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+///
+/// The fingerprint doesn't depend on the order of the nodes, attributes or
+/// functions, which TensorFlow doesn't preserve, nor on the `GraphDef`
+/// versions.  Other releases of TensorFlow may still export a graph with
+/// different nodes or attributes, and so a different fingerprint.  It's a
+/// 64-bit FNV-1a hash, which catches accidental changes but isn't meant to
+/// detect tampering.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GraphFingerprint(u64);
+
+impl GraphFingerprint {
+    /// Fingerprints `graph`.
+    pub fn of_graph(graph: &Graph) -> Result<Self> {
+        Self::of_graph_def(&graph.graph_def()?)
+    }
+
+    /// Fingerprints the serialized `GraphDef` `graph_def`, e.g. a frozen
+    /// model read from a `.pb` file.
+    pub fn of_graph_def(graph_def: &[u8]) -> Result<Self> {
+        let mut nodes = Vec::new();
+        let mut functions = Vec::new();
+        for field in ProtoReader::new(graph_def) {
+            match field? {
+                (GRAPH_DEF_NODE, node) => nodes.push(node_hash(node.as_bytes()?)?),
+                (GRAPH_DEF_LIBRARY, library) => {
+                    for field in ProtoReader::new(library.as_bytes()?) {
+                        match field? {
+                            (LIBRARY_FUNCTION, function) => {
+                                functions.push(function_hash(function.as_bytes()?)?)
+                            }
+                            (LIBRARY_GRADIENT, gradient) => {
+                                functions.push(Fnv::hash(gradient.as_bytes()?))
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let mut hasher = Fnv::new();
+        hasher.write_sorted(nodes);
+        hasher.write_sorted(functions);
+        Ok(GraphFingerprint(hasher.finish()))
+    }
+
+    /// Returns the fingerprint as an integer.
+    pub fn value(&self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for GraphFingerprint {
+    /// Writes the fingerprint as 16 hexadecimal digits.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{:016x}", self.0)
+    }
+}
+
+impl FromStr for GraphFingerprint {
+    type Err = Status;
+
+    /// Parses a fingerprint written by `Display`.
+    fn from_str(s: &str) -> Result<Self> {
+        u64::from_str_radix(s, 16)
+            .map(GraphFingerprint)
+            .map_err(|_| invalid_arg!("Invalid graph fingerprint {:?}", s))
+    }
+}
+
+/// An `Fnv1a` hasher of length-prefixed fields.
+#[derive(Debug, Clone, Copy)]
+struct Fnv(Fnv1a);
+
+impl Fnv {
+    fn new() -> Self {
+        Fnv(Fnv1a::new())
+    }
+
+    fn hash(bytes: &[u8]) -> u64 {
+        let mut hasher = Self::new();
+        hasher.write(bytes);
+        hasher.finish()
+    }
+
+    fn finish(&self) -> u64 {
+        self.0.finish()
+    }
+
+    /// Writes `bytes` prefixed by their length, so that consecutive writes
+    /// can't be confused with each other.
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.write(&(bytes.len() as u64).to_le_bytes());
+        self.0.write(bytes);
+    }
+
+    fn write_u64(&mut self, value: u64) {
+        self.write(&value.to_le_bytes());
+    }
+
+    fn write_field(&mut self, field: u32, value: ProtoValue<'_>) {
+        self.write_u64(u64::from(field));
+        match value {
+            ProtoValue::Varint(v) | ProtoValue::Fixed64(v) => self.write_u64(v),
+            ProtoValue::Fixed32(v) => self.write(&v.to_le_bytes()),
+            ProtoValue::LengthDelimited(bytes) => self.write(bytes),
+        }
+    }
+
+    /// Writes the hashes of unordered elements.
+    fn write_sorted(&mut self, mut hashes: Vec<u64>) {
+        hashes.sort();
+        self.write_u64(hashes.len() as u64);
+        for hash in hashes {
+            self.write_u64(hash);
+        }
+    }
+}
+
+/// Hashes the entries of a map field, given as serialized map entries, with
+/// `value_hash` hashing each value.
+fn map_hash<F>(entries: &[&[u8]], value_hash: F) -> Result<Vec<u64>>
+where
+    F: Fn(&[u8]) -> Result<u64>,
+{
+    let mut hashes = Vec::with_capacity(entries.len());
+    for entry in entries {
+        let mut key: &[u8] = &[];
+        let mut value: &[u8] = &[];
+        for field in ProtoReader::new(entry) {
+            match field? {
+                (MAP_ENTRY_KEY, v) => key = v.as_bytes()?,
+                (MAP_ENTRY_VALUE, v) => value = v.as_bytes()?,
+                _ => {}
+            }
+        }
+        let mut hasher = Fnv::new();
+        hasher.write(key);
+        hasher.write_u64(value_hash(value)?);
+        hashes.push(hasher.finish());
+    }
+    Ok(hashes)
+}
+
+fn node_hash(node: &[u8]) -> Result<u64> {
+    let mut name: &[u8] = &[];
+    let mut op: &[u8] = &[];
+    let mut device: &[u8] = &[];
+    let mut inputs = Vec::new();
+    let mut control_inputs = Vec::new();
+    let mut attrs = Vec::new();
+    for field in ProtoReader::new(node) {
+        match field? {
+            (NODE_DEF_NAME, value) => name = value.as_bytes()?,
+            (NODE_DEF_OP, value) => op = value.as_bytes()?,
+            (NODE_DEF_DEVICE, value) => device = value.as_bytes()?,
+            (NODE_DEF_INPUT, value) => {
+                let input = value.as_bytes()?;
+                // The order of data inputs matters, but not that of control
+                // inputs.
+                if input.starts_with(b"^") {
+                    control_inputs.push(Fnv::hash(input));
+                } else {
+                    inputs.push(input);
+                }
+            }
+            (NODE_DEF_ATTR, value) => attrs.push(value.as_bytes()?),
+            _ => {}
+        }
+    }
+    let mut hasher = Fnv::new();
+    hasher.write(name);
+    hasher.write(op);
+    hasher.write(device);
+    hasher.write_u64(inputs.len() as u64);
+    for input in inputs {
+        hasher.write(input);
+    }
+    hasher.write_sorted(control_inputs);
+    hasher.write_sorted(map_hash(&attrs, attr_value_hash)?);
+    Ok(hasher.finish())
+}
+
+/// Hashes an `AttrValue`, whose function attributes contain maps.
+fn attr_value_hash(value: &[u8]) -> Result<u64> {
+    let mut hasher = Fnv::new();
+    for field in ProtoReader::new(value) {
+        match field? {
+            (ATTR_VALUE_FUNC, func) => {
+                let func = name_attr_list_hash(func.as_bytes()?)?;
+                hasher.write_field(ATTR_VALUE_FUNC, ProtoValue::Fixed64(func));
+            }
+            (ATTR_VALUE_LIST, list) => {
+                hasher.write_u64(u64::from(ATTR_VALUE_LIST));
+                for field in ProtoReader::new(list.as_bytes()?) {
+                    match field? {
+                        (LIST_VALUE_FUNC, func) => {
+                            let func = name_attr_list_hash(func.as_bytes()?)?;
+                            hasher.write_field(LIST_VALUE_FUNC, ProtoValue::Fixed64(func));
+                        }
+                        (field, value) => hasher.write_field(field, value),
+                    }
+                }
+            }
+            (field, value) => hasher.write_field(field, value),
+        }
+    }
+    Ok(hasher.finish())
+}
+
+fn name_attr_list_hash(list: &[u8]) -> Result<u64> {
+    let mut name: &[u8] = &[];
+    let mut attrs = Vec::new();
+    for field in ProtoReader::new(list) {
+        match field? {
+            (NAME_ATTR_LIST_NAME, value) => name = value.as_bytes()?,
+            (NAME_ATTR_LIST_ATTR, value) => attrs.push(value.as_bytes()?),
+            _ => {}
+        }
+    }
+    let mut hasher = Fnv::new();
+    hasher.write(name);
+    hasher.write_sorted(map_hash(&attrs, attr_value_hash)?);
+    Ok(hasher.finish())
+}
+
+fn function_hash(function: &[u8]) -> Result<u64> {
+    let mut signature: &[u8] = &[];
+    let mut nodes = Vec::new();
+    let mut attrs = Vec::new();
+    let mut rets = Vec::new();
+    let mut control_rets = Vec::new();
+    for field in ProtoReader::new(function) {
+        match field? {
+            (FUNCTION_DEF_SIGNATURE, value) => signature = value.as_bytes()?,
+            (FUNCTION_DEF_NODE_DEF, value) => nodes.push(node_hash(value.as_bytes()?)?),
+            (FUNCTION_DEF_ATTR, value) => attrs.push(value.as_bytes()?),
+            (FUNCTION_DEF_RET, value) => rets.push(value.as_bytes()?),
+            (FUNCTION_DEF_CONTROL_RET, value) => control_rets.push(value.as_bytes()?),
+            _ => {}
+        }
+    }
+    let mut hasher = Fnv::new();
+    hasher.write(signature);
+    hasher.write_sorted(nodes);
+    hasher.write_sorted(map_hash(&attrs, attr_value_hash)?);
+    hasher.write_sorted(map_hash(&rets, |v| Ok(Fnv::hash(v)))?);
+    hasher.write_sorted(map_hash(&control_rets, |v| Ok(Fnv::hash(v)))?);
+    Ok(hasher.finish())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::ProtoWriter;
+    use crate::DataType;
+    use crate::ImportGraphDefOptions;
+    use crate::Operation;
+
+    fn placeholder(g: &mut Graph, name: &str) -> Operation {
+        let mut nd = g.new_operation("Placeholder", name).unwrap();
+        nd.set_attr_type("dtype", DataType::Float).unwrap();
+        nd.finish().unwrap()
+    }
+
+    fn sub(g: &mut Graph, x: Operation, y: Operation) -> Operation {
+        let mut nd = g.new_operation("Sub", "z").unwrap();
+        nd.add_input(x);
+        nd.add_input(y);
+        nd.finish().unwrap()
+    }
+
+    #[test]
+    fn graphs() {
+        let mut a = Graph::new();
+        let x = placeholder(&mut a, "x");
+        let y = placeholder(&mut a, "y");
+        sub(&mut a, x, y);
+
+        // The same graph with the nodes added in another order.
+        let mut b = Graph::new();
+        let y = placeholder(&mut b, "y");
+        let x = placeholder(&mut b, "x");
+        sub(&mut b, x, y);
+        assert_eq!(a.fingerprint().unwrap(), b.fingerprint().unwrap());
+
+        let mut imported = Graph::new();
+        imported
+            .import_graph_def(&a.graph_def().unwrap(), &ImportGraphDefOptions::new())
+            .unwrap();
+        assert_eq!(a.fingerprint().unwrap(), imported.fingerprint().unwrap());
+
+        // Swapping the inputs changes the graph.
+        let mut c = Graph::new();
+        let x = placeholder(&mut c, "x");
+        let y = placeholder(&mut c, "y");
+        sub(&mut c, y, x);
+        assert_ne!(a.fingerprint().unwrap(), c.fingerprint().unwrap());
+
+        // As does placing a node.
+        let mut d = Graph::new();
+        let x = placeholder(&mut d, "x");
+        let y = placeholder(&mut d, "y");
+        let mut nd = d.new_operation("Sub", "z").unwrap();
+        nd.add_input(x);
+        nd.add_input(y);
+        nd.set_device("/cpu:0").unwrap();
+        nd.finish().unwrap();
+        assert_ne!(a.fingerprint().unwrap(), d.fingerprint().unwrap());
+    }
+
+    #[test]
+    fn attr_order() {
+        let graph_def = |attrs: &[(&str, i64)]| {
+            let mut node = ProtoWriter::new();
+            node.string_field(NODE_DEF_NAME, "n")
+                .string_field(NODE_DEF_OP, "Op");
+            for &(key, i) in attrs {
+                let mut value = ProtoWriter::new();
+                value.int_field(3, i);
+                let mut entry = ProtoWriter::new();
+                entry
+                    .string_field(MAP_ENTRY_KEY, key)
+                    .message_field(MAP_ENTRY_VALUE, &value);
+                node.message_field(NODE_DEF_ATTR, &entry);
+            }
+            let mut graph_def = ProtoWriter::new();
+            graph_def.message_field(GRAPH_DEF_NODE, &node);
+            GraphFingerprint::of_graph_def(graph_def.as_bytes()).unwrap()
+        };
+        let fingerprint = graph_def(&[("a", 1), ("b", 2)]);
+        assert_eq!(fingerprint, graph_def(&[("b", 2), ("a", 1)]));
+        assert_ne!(fingerprint, graph_def(&[("a", 2), ("b", 1)]));
+    }
+
+    #[test]
+    fn display() {
+        let fingerprint = GraphFingerprint(0x0123456789abcdef);
+        assert_eq!(fingerprint.to_string(), "0123456789abcdef");
+        assert_eq!(
+            "0123456789abcdef".parse::<GraphFingerprint>().unwrap(),
+            fingerprint
+        );
+        assert!("xyz".parse::<GraphFingerprint>().is_err());
+    }
+}
@@ -0,0 +1,199 @@
+use crate::feed::run_by_name;
+use crate::DataType;
+use crate::FeedValue;
+use crate::Graph;
+use crate::ImportGraphDefOptions;
+use crate::Output;
+use crate::Result;
+use crate::Session;
+use crate::SessionOptions;
+use crate::Shape;
+use crate::Tensor;
+use crate::TensorType;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::path::Path;
+
+/// Ops which have no outputs worth fetching even if nothing consumes them.
+const NON_OUTPUT_OPS: &[&str] = &["Const", "NoOp", "PlaceholderWithDefault"];
+
+/// A tensor which `FrozenModel` identified as a likely input or output.
+#[derive(Debug, Clone)]
+pub struct FrozenModelTensor {
+    /// The name of the tensor, e.g. `"x:0"`.
+    pub name: String,
+    /// The tensor in the model's graph.
+    pub output: Output,
+    /// The data type of the tensor.
+    pub data_type: DataType,
+    /// The shape of the tensor, as far as it's known.
+    pub shape: Shape,
+}
+
+impl FrozenModelTensor {
+    fn new(graph: &Graph, output: Output) -> Result<Self> {
+        Ok(Self {
+            name: format!("{}:{}", output.operation.name()?, output.index),
+            data_type: output.operation.output_type(output.index as usize),
+            shape: graph.tensor_shape(output.clone())?,
+            output,
+        })
+    }
+}
+
+impl Display for FrozenModelTensor {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{} ({}, {})", self.name, self.data_type, self.shape)
+    }
+}
+
+/// A model loaded from a frozen `GraphDef`, i.e. a `.pb` file with the
+/// variables converted to constants, which is run without knowing its inputs
+/// and outputs in advance.
+///
+/// ```no_run
+/// # use tensorflow::FrozenModel;
+/// # use tensorflow::Tensor;
+/// let model = FrozenModel::load("examples/addition/model.pb")?;
+/// for input in model.inputs() {
+///     println!("input: {}", input);
+/// }
+/// for output in model.outputs() {
+///     println!("output: {}", output);
+/// }
+/// let x = Tensor::new(&[1]).with_values(&[2i32])?;
+/// let y = Tensor::new(&[1]).with_values(&[40i32])?;
+/// let z = model.run::<i32>(&[("x", &x), ("y", &y)], "z")?;
+/// assert_eq!(z[0], 42);
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+///
+/// The inputs are taken to be the placeholders, and the outputs are the
+/// tensors of ops which nothing consumes, other than constants and ops with
+/// no effect.  These are heuristics; a model may have inputs with defaults
+/// or fetch intermediate tensors, which can be run through `session`.
+#[derive(Debug)]
+pub struct FrozenModel {
+    graph: Graph,
+    session: Session,
+    inputs: Vec<FrozenModelTensor>,
+    outputs: Vec<FrozenModelTensor>,
+}
+
+impl FrozenModel {
+    /// Loads a frozen `GraphDef` from `path` with the default session options.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::load_with_options(path, &SessionOptions::new())
+    }
+
+    /// Loads a frozen `GraphDef` from `path`.
+    pub fn load_with_options<P: AsRef<Path>>(path: P, options: &SessionOptions) -> Result<Self> {
+        let path = path.as_ref();
+        let graph_def = std::fs::read(path)
+            .map_err(|e| invalid_arg!("Unable to read {}: {}", path.display(), e))?;
+        Self::from_graph_def(&graph_def, options)
+    }
+
+    /// Loads a frozen model from a serialized `GraphDef`.
+    pub fn from_graph_def(graph_def: &[u8], options: &SessionOptions) -> Result<Self> {
+        let mut graph = Graph::new();
+        graph.import_graph_def(graph_def, &ImportGraphDefOptions::new())?;
+        let mut inputs = Vec::new();
+        let mut outputs = Vec::new();
+        for operation in graph.operation_iter() {
+            let op_type = operation.op_type()?;
+            if op_type == "Placeholder" {
+                inputs.push(FrozenModelTensor::new(
+                    &graph,
+                    Output {
+                        operation,
+                        index: 0,
+                    },
+                )?);
+                continue;
+            }
+            let consumed = operation.num_control_outputs() > 0
+                || (0..operation.num_outputs())
+                    .any(|index| !operation.output_consumers(index).is_empty());
+            if consumed || NON_OUTPUT_OPS.contains(&op_type.as_str()) {
+                continue;
+            }
+            for index in 0..operation.num_outputs() {
+                outputs.push(FrozenModelTensor::new(
+                    &graph,
+                    Output {
+                        operation: operation.clone(),
+                        index: index as i32,
+                    },
+                )?);
+            }
+        }
+        inputs.sort_by(|a, b| a.name.cmp(&b.name));
+        outputs.sort_by(|a, b| a.name.cmp(&b.name));
+        let session = Session::new(options, &graph)?;
+        Ok(Self {
+            graph,
+            session,
+            inputs,
+            outputs,
+        })
+    }
+
+    /// Returns the graph.
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Returns the session, for running the model in ways `run` doesn't
+    /// support.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the placeholders, sorted by name.
+    pub fn inputs(&self) -> &[FrozenModelTensor] {
+        &self.inputs
+    }
+
+    /// Returns the tensors which nothing consumes, sorted by name.
+    pub fn outputs(&self) -> &[FrozenModelTensor] {
+        &self.outputs
+    }
+
+    /// Feeds each tensor in `inputs` to the tensor with the paired name, and
+    /// returns the value of the tensor named `output`.  Names are either
+    /// `"op:index"` or just `"op"` for output 0 of the op.
+    pub fn run<U: TensorType>(
+        &self,
+        inputs: &[(&str, &dyn FeedValue)],
+        output: &str,
+    ) -> Result<Tensor<U>> {
+        let find = |name: &str| self.graph.output_by_name_required(name);
+        run_by_name(&self.session, inputs, output, find, find)
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_addition() {
+        let model = FrozenModel::load("examples/addition/model.pb").unwrap();
+        let inputs: Vec<&str> = model.inputs().iter().map(|t| t.name.as_str()).collect();
+        let outputs: Vec<&str> = model.outputs().iter().map(|t| t.name.as_str()).collect();
+        assert_eq!(inputs, vec!["x:0", "y:0"]);
+        assert_eq!(outputs, vec!["z:0"]);
+        assert_eq!(model.inputs()[0].data_type, DataType::Int32);
+
+        let x = Tensor::new(&[1]).with_values(&[2i32]).unwrap();
+        let y = Tensor::new(&[1]).with_values(&[40i32]).unwrap();
+        let z = model.run::<i32>(&[("x:0", &x), ("y", &y)], "z").unwrap();
+        assert_eq!(&z[..], &[42]);
+        assert!(model.run::<i32>(&[("x", &x)], "z:1").is_err());
+        assert!(FrozenModel::load("examples/addition/missing.pb").is_err());
+    }
+}
@@ -0,0 +1,120 @@
+use crate::protos::ProtoWriter;
+use std::collections::BTreeMap;
+
+/// Configures how a session uses GPUs, including splitting physical GPUs
+/// into several logical devices.
+///
+/// GPU configuration is process-wide and fixed when the first session is
+/// created, so it must be set on the `SessionOptions` of that session.
+///
+/// ```
+/// # use tensorflow::GpuOptions;
+/// # use tensorflow::SessionOptions;
+/// // Expose the first GPU as two logical devices with 1 GB each,
+/// // "/device:GPU:0" and "/device:GPU:1".
+/// let gpu_options = GpuOptions::new().virtual_devices(0, &[1024.0, 1024.0]);
+/// let mut options = SessionOptions::new();
+/// options.set_gpu_options(&gpu_options)?;
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct GpuOptions {
+    memory_fraction: Option<f64>,
+    allow_growth: Option<bool>,
+    visible_devices: Option<Vec<i32>>,
+    virtual_devices: BTreeMap<usize, Vec<f32>>,
+}
+
+impl GpuOptions {
+    /// Creates options which leave everything at TensorFlow's defaults.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the fraction of each GPU's memory to allocate, between 0 and 1.
+    pub fn memory_fraction(self, fraction: f64) -> Self {
+        Self {
+            memory_fraction: Some(fraction),
+            ..self
+        }
+    }
+
+    /// Sets whether GPU memory is allocated as needed rather than all at once.
+    pub fn allow_growth(self, allow_growth: bool) -> Self {
+        Self {
+            allow_growth: Some(allow_growth),
+            ..self
+        }
+    }
+
+    /// Restricts the process to the given physical GPUs, which are renumbered
+    /// from 0 in the order given.
+    pub fn visible_devices(self, devices: &[i32]) -> Self {
+        Self {
+            visible_devices: Some(devices.to_vec()),
+            ..self
+        }
+    }
+
+    /// Splits visible GPU `gpu` into one logical device per entry of
+    /// `memory_limits_mb`, each limited to that many megabytes.
+    ///
+    /// Logical devices are numbered consecutively across physical GPUs.  GPUs
+    /// which are not split become a single logical device using all of their
+    /// memory.
+    pub fn virtual_devices(mut self, gpu: usize, memory_limits_mb: &[f32]) -> Self {
+        self.virtual_devices.insert(gpu, memory_limits_mb.to_vec());
+        self
+    }
+
+    /// Returns the serialized [`GPUOptions` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto).
+    pub fn to_proto(&self) -> Vec<u8> {
+        let mut options = ProtoWriter::new();
+        if let Some(fraction) = self.memory_fraction {
+            options.double_field(1, fraction);
+        }
+        if let Some(allow_growth) = self.allow_growth {
+            options.bool_field(4, allow_growth);
+        }
+        if let Some(devices) = &self.visible_devices {
+            let list: Vec<String> = devices.iter().map(ToString::to_string).collect();
+            options.string_field(5, &list.join(","));
+        }
+        if let Some(&last) = self.virtual_devices.keys().next_back() {
+            let mut experimental = ProtoWriter::new();
+            // Every visible GPU needs an entry once any of them is split.
+            for gpu in 0..=last {
+                let mut devices = ProtoWriter::new();
+                for limit in self.virtual_devices.get(&gpu).into_iter().flatten() {
+                    devices.float_field(1, *limit);
+                }
+                experimental.message_field(1, &devices);
+            }
+            options.message_field(9, &experimental);
+        }
+        options.into_bytes()
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn to_proto() {
+        assert_eq!(GpuOptions::new().to_proto(), vec![]);
+        assert_eq!(
+            GpuOptions::new()
+                .allow_growth(true)
+                .visible_devices(&[1, 0])
+                .to_proto(),
+            vec![0x20, 0x01, 0x2a, 0x03, b'1', b',', b'0']
+        );
+        assert_eq!(
+            GpuOptions::new().virtual_devices(1, &[1.0]).to_proto(),
+            vec![0x4a, 0x09, 0x0a, 0x00, 0x0a, 0x05, 0x0d, 0x00, 0x00, 0x80, 0x3f]
+        );
+    }
+}
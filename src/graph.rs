@@ -2,6 +2,7 @@ use super::buffer::Buffer;
 use super::AnyTensor;
 use super::Code;
 use super::DataType;
+use super::GraphFingerprint;
 use super::Result;
 use super::Shape;
 use super::Status;
@@ -315,6 +316,7 @@ impl Graph {
                 ),
                 graph: self,
                 finished: false,
+                inputs: Vec::new(),
             })
         }
     }
@@ -355,6 +357,28 @@ impl Graph {
         }
     }
 
+    /// Returns the tensor named `name`, which is either `"op:index"` or just
+    /// `"op"` for output 0 of the op.
+    pub(crate) fn output_by_name_required(&self, name: &str) -> Result<Output> {
+        let (op_name, index) = match name.rfind(':') {
+            Some(colon) => match name[colon + 1..].parse() {
+                Ok(index) => (&name[..colon], index),
+                Err(_) => return Err(invalid_arg!("Invalid tensor name {:?}", name)),
+            },
+            None => (name, 0),
+        };
+        let operation = self.operation_by_name_required(op_name)?;
+        if index < 0 || index as usize >= operation.num_outputs() {
+            return Err(invalid_arg!(
+                "{} has {} outputs, so {:?} doesn't exist",
+                op_name,
+                operation.num_outputs(),
+                name
+            ));
+        }
+        Ok(Output { operation, index })
+    }
+
     /// Finds a unique operation name.  The pattern must contain exactly one
     /// '{}' placeholder to indicate where a unique ID can be inserted, e.g.
     /// 'Add_{}' or 'while_loop_{}/Merge', and the function returns an integer
@@ -404,6 +428,12 @@ impl Graph {
         }
     }
 
+    /// Returns a fingerprint of the graph's structure.  See
+    /// `GraphFingerprint`.
+    pub fn fingerprint(&self) -> Result<GraphFingerprint> {
+        GraphFingerprint::of_graph(self)
+    }
+
     /// Returns the number of dimensions of the Tensor referenced by `output`.
     ///
     /// If the number of dimensions in the shape is unknown, returns -1.
@@ -1694,6 +1724,11 @@ pub struct Output {
 }
 
 impl Output {
+    /// Returns the type of the tensor.
+    pub fn data_type(&self) -> DataType {
+        self.operation.output_type(self.index as usize)
+    }
+
     pub(crate) fn to_c(&self) -> tf::TF_Output {
         tf::TF_Output {
             oper: self.operation.inner,
@@ -1741,6 +1776,8 @@ pub struct OperationDescription<'a> {
     // the docs on TF_NewOperation.
     graph: &'a Graph,
     finished: bool,
+    // Recorded for error messages.
+    inputs: Vec<Output>,
 }
 
 impl<'a> Drop for OperationDescription<'a> {
@@ -1788,9 +1825,11 @@ impl<'a> OperationDescription<'a> {
     ///
     /// The index in the port is an index into the source operation's output array.
     pub fn add_input<I: Into<Output>>(&mut self, input: I) {
+        let input = input.into();
         unsafe {
-            tf::TF_AddInput(self.inner, input.into().to_c());
+            tf::TF_AddInput(self.inner, input.to_c());
         }
+        self.inputs.push(input);
     }
 
     /// Adds multiple inputs to this operation.
@@ -1801,6 +1840,14 @@ impl<'a> OperationDescription<'a> {
         unsafe {
             tf::TF_AddInputList(self.inner, c_inputs.as_ptr(), c_inputs.len() as c_int);
         }
+        self.inputs.extend_from_slice(inputs);
+    }
+
+    /// Returns the inputs added so far, in order, including those added as
+    /// part of a list.
+    #[cfg(feature = "experimental_training")]
+    pub(crate) fn inputs(&self) -> &[Output] {
+        &self.inputs
     }
 
     /// Adds a control input.
@@ -2690,6 +2737,54 @@ mod tests {
         assert_eq!(ops[0].index, 0);
     }
 
+    #[test]
+    fn import_graph_def_input_mapping_and_control_dependency() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Variable", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Int32).unwrap();
+            nd.set_attr_shape("shape", &Shape(None)).unwrap();
+            nd.finish().unwrap()
+        };
+        let init = g.new_operation("NoOp", "init").unwrap().finish().unwrap();
+        let mut opts = ImportGraphDefOptions::new();
+        opts.set_prefix("imported").unwrap();
+        opts.add_input_mapping("a", 0, &x.clone().into()).unwrap();
+        opts.add_control_dependency(&init);
+        opts.add_return_output("a", 0).unwrap();
+        opts.add_return_output("a_times_b", 0).unwrap();
+        let outputs = g
+            .import_graph_def_with_return_outputs(&graph_def(), &opts)
+            .unwrap();
+        // The remapped output is returned as the existing one.
+        assert_eq!(outputs[0].operation.name().unwrap(), "x");
+        let product = &outputs[1].operation;
+        assert_eq!(product.name().unwrap(), "imported/a_times_b");
+        assert_eq!(product.input(0).0.name().unwrap(), "x");
+        assert_eq!(product.input(1).0.name().unwrap(), "imported/b");
+        // Only the imported operations without inputs get the control
+        // dependency, since the others depend on them.
+        let b = g.operation_by_name_required("imported/b").unwrap();
+        let controls: Vec<_> = b
+            .control_inputs()
+            .iter()
+            .map(|op| op.name().unwrap())
+            .collect();
+        assert_eq!(controls, vec!["init"]);
+        assert_eq!(product.num_control_inputs(), 0);
+    }
+
+    #[test]
+    fn graph_def_round_trip() {
+        let mut g = Graph::new();
+        g.import_graph_def(&graph_def(), &ImportGraphDefOptions::new())
+            .unwrap();
+        let mut copy = Graph::new();
+        copy.import_graph_def(&g.graph_def().unwrap(), &ImportGraphDefOptions::new())
+            .unwrap();
+        assert_eq!(copy.fingerprint().unwrap(), g.fingerprint().unwrap());
+    }
+
     #[test]
     fn graph_get_op_def() {
         let g = Graph::new();
@@ -0,0 +1,479 @@
+use crate::protos::ProtoReader;
+use crate::protos::ProtoWriter;
+use crate::Graph;
+use crate::Result;
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+/// Ops which were deprecated in TensorFlow after being superseded by ops with
+/// the same inputs, outputs and attributes, mapped to their replacements.
+const RENAMED_OPS: &[(&str, &str)] = &[
+    ("BatchCholesky", "Cholesky"),
+    ("BatchCholeskyGrad", "CholeskyGrad"),
+    ("BatchFFT", "FFT"),
+    ("BatchFFT2D", "FFT2D"),
+    ("BatchFFT3D", "FFT3D"),
+    ("BatchIFFT", "IFFT"),
+    ("BatchIFFT2D", "IFFT2D"),
+    ("BatchIFFT3D", "IFFT3D"),
+    ("BatchMatrixBandPart", "MatrixBandPart"),
+    ("BatchMatrixDeterminant", "MatrixDeterminant"),
+    ("BatchMatrixDiag", "MatrixDiag"),
+    ("BatchMatrixDiagPart", "MatrixDiagPart"),
+    ("BatchMatrixInverse", "MatrixInverse"),
+    ("BatchMatrixSetDiag", "MatrixSetDiag"),
+    ("BatchMatrixSolve", "MatrixSolve"),
+    ("BatchMatrixSolveLs", "MatrixSolveLs"),
+    ("BatchMatrixTriangularSolve", "MatrixTriangularSolve"),
+    ("BatchSelfAdjointEigV2", "SelfAdjointEigV2"),
+    ("BatchSvd", "Svd"),
+];
+
+/// The field numbers of the messages inspected here.
+const GRAPH_DEF_NODE: u32 = 1;
+const GRAPH_DEF_LIBRARY: u32 = 2;
+const GRAPH_DEF_VERSIONS: u32 = 4;
+const LIBRARY_FUNCTION: u32 = 1;
+const FUNCTION_DEF_SIGNATURE: u32 = 1;
+const FUNCTION_DEF_NODE_DEF: u32 = 3;
+const OP_DEF_NAME: u32 = 1;
+const OP_DEF_DEPRECATION: u32 = 8;
+const OP_DEPRECATION_VERSION: u32 = 1;
+const OP_DEPRECATION_EXPLANATION: u32 = 2;
+const NODE_DEF_NAME: u32 = 1;
+const NODE_DEF_OP: u32 = 2;
+const VERSION_DEF_PRODUCER: u32 = 1;
+const VERSION_DEF_MIN_CONSUMER: u32 = 2;
+const VERSION_DEF_BAD_CONSUMERS: u32 = 3;
+
+/// The fields of a `VersionDef`.
+#[derive(Debug, Default)]
+struct Versions {
+    producer: i32,
+    min_consumer: i32,
+    bad_consumers: Vec<i32>,
+}
+
+fn parse_versions(bytes: &[u8]) -> Result<Versions> {
+    let mut versions = Versions::default();
+    for field in ProtoReader::new(bytes) {
+        match field? {
+            (VERSION_DEF_PRODUCER, value) => versions.producer = value.as_i64()? as i32,
+            (VERSION_DEF_MIN_CONSUMER, value) => versions.min_consumer = value.as_i64()? as i32,
+            (VERSION_DEF_BAD_CONSUMERS, value) => versions
+                .bad_consumers
+                .extend(value.as_varints()?.into_iter().map(|v| v as i32)),
+            _ => {}
+        }
+    }
+    Ok(versions)
+}
+
+/// Returns why `op` can't be used in a graph produced at GraphDef version
+/// `producer`, or `None` if it can.
+fn unavailable(registry: &Graph, op: &str, producer: i32) -> Result<Option<String>> {
+    let op_def = match registry.get_op_def(op) {
+        Ok(op_def) => op_def,
+        Err(_) => return Ok(Some("is not registered in the runtime".to_string())),
+    };
+    for field in ProtoReader::new(&op_def) {
+        if let (OP_DEF_DEPRECATION, deprecation) = field? {
+            let mut version = 0;
+            let mut explanation = "";
+            for field in ProtoReader::new(deprecation.as_bytes()?) {
+                match field? {
+                    (OP_DEPRECATION_VERSION, value) => version = value.as_i64()? as i32,
+                    (OP_DEPRECATION_EXPLANATION, value) => explanation = value.as_str()?,
+                    _ => {}
+                }
+            }
+            if producer >= version {
+                return Ok(Some(format!(
+                    "was removed in GraphDef version {} ({})",
+                    version, explanation
+                )));
+            }
+        }
+    }
+    Ok(None)
+}
+
+/// Calls `f` with the name and op of each `NodeDef` in the graph and its
+/// functions.
+fn for_each_node<F: FnMut(&str, &str) -> Result<()>>(graph_def: &[u8], mut f: F) -> Result<()> {
+    let mut visit = |node: &[u8]| -> Result<()> {
+        let mut name = "";
+        let mut op = "";
+        for field in ProtoReader::new(node) {
+            match field? {
+                (NODE_DEF_NAME, value) => name = value.as_str()?,
+                (NODE_DEF_OP, value) => op = value.as_str()?,
+                _ => {}
+            }
+        }
+        f(name, op)
+    };
+    for field in ProtoReader::new(graph_def) {
+        match field? {
+            (GRAPH_DEF_NODE, value) => visit(value.as_bytes()?)?,
+            (GRAPH_DEF_LIBRARY, value) => {
+                for function in functions(value.as_bytes()?) {
+                    for field in ProtoReader::new(function?) {
+                        if let (FUNCTION_DEF_NODE_DEF, value) = field? {
+                            visit(value.as_bytes()?)?;
+                        }
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+/// Iterates over the serialized `FunctionDef`s of a `FunctionDefLibrary`.
+fn functions(library: &[u8]) -> impl Iterator<Item = Result<&[u8]>> {
+    ProtoReader::new(library).filter_map(|field| match field {
+        Ok((LIBRARY_FUNCTION, value)) => Some(value.as_bytes()),
+        Ok(_) => None,
+        Err(e) => Some(Err(e)),
+    })
+}
+
+/// Returns the names of the functions defined in the graph's library.
+fn function_names(graph_def: &[u8]) -> Result<BTreeSet<String>> {
+    let mut names = BTreeSet::new();
+    for field in ProtoReader::new(graph_def) {
+        if let (GRAPH_DEF_LIBRARY, value) = field? {
+            for function in functions(value.as_bytes()?) {
+                for field in ProtoReader::new(function?) {
+                    if let (FUNCTION_DEF_SIGNATURE, signature) = field? {
+                        for field in ProtoReader::new(signature.as_bytes()?) {
+                            if let (OP_DEF_NAME, name) = field? {
+                                names.insert(name.as_str()?.to_string());
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+    Ok(names)
+}
+
+/// Copies `message`, replacing each field numbered `field` with the result of
+/// `f` on its value.
+fn rewrite_field<F: FnMut(&[u8]) -> Result<Vec<u8>>>(
+    message: &[u8],
+    field: u32,
+    mut f: F,
+) -> Result<Vec<u8>> {
+    let mut writer = ProtoWriter::new();
+    for entry in ProtoReader::new(message) {
+        let (number, value) = entry?;
+        if number == field {
+            writer.bytes_field(number, &f(value.as_bytes()?)?);
+        } else {
+            writer.value_field(number, &value);
+        }
+    }
+    Ok(writer.into_bytes())
+}
+
+/// Returns `graph_def` with the ops of nodes renamed according to `renames`.
+fn rename_ops(graph_def: &[u8], renames: &BTreeMap<String, String>) -> Result<Vec<u8>> {
+    let rename_node = |node: &[u8]| -> Result<Vec<u8>> {
+        let mut op = "";
+        for field in ProtoReader::new(node) {
+            if let (NODE_DEF_OP, value) = field? {
+                op = value.as_str()?;
+            }
+        }
+        let mut node = node.to_vec();
+        if let Some(new_op) = renames.get(op) {
+            // The last value of a singular field wins when parsing.
+            let mut writer = ProtoWriter::new();
+            writer.string_field(NODE_DEF_OP, new_op);
+            node.extend_from_slice(writer.as_bytes());
+        }
+        Ok(node)
+    };
+    let graph_def = rewrite_field(graph_def, GRAPH_DEF_NODE, rename_node)?;
+    rewrite_field(&graph_def, GRAPH_DEF_LIBRARY, |library| {
+        rewrite_field(library, LIBRARY_FUNCTION, |function| {
+            rewrite_field(function, FUNCTION_DEF_NODE_DEF, rename_node)
+        })
+    })
+}
+
+/// The result of checking whether a serialized `GraphDef` can be imported
+/// into the linked TensorFlow runtime.
+///
+/// Importing a graph which uses ops the runtime doesn't have fails with an
+/// error naming only the first such op.  Checking first reports all of them,
+/// together with the nodes which use them, and upgrades ops which have been
+/// renamed since the graph was produced.
+///
+/// ```no_run
+/// # use tensorflow::{Graph, GraphDefCompatibility, ImportGraphDefOptions};
+/// # let graph_def: Vec<u8> = vec![];
+/// let compatibility = GraphDefCompatibility::check(&graph_def)?;
+/// compatibility.to_result()?;
+/// let mut graph = Graph::new();
+/// graph.import_graph_def(compatibility.graph_def(), &ImportGraphDefOptions::new())?;
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct GraphDefCompatibility {
+    producer: i32,
+    min_consumer: i32,
+    bad_consumers: Vec<i32>,
+    runtime_producer: i32,
+    unsupported_ops: BTreeMap<String, Vec<String>>,
+    reasons: BTreeMap<String, String>,
+    renamed_ops: BTreeMap<String, String>,
+    graph_def: Vec<u8>,
+}
+
+impl GraphDefCompatibility {
+    /// Checks the serialized `GraphDef` `graph_def` against the linked
+    /// runtime.  This only fails if `graph_def` can't be parsed; problems
+    /// with the graph are reported by `to_result`.
+    pub fn check(graph_def: &[u8]) -> Result<Self> {
+        let mut versions = Versions::default();
+        for field in ProtoReader::new(graph_def) {
+            if let (GRAPH_DEF_VERSIONS, value) = field? {
+                versions = parse_versions(value.as_bytes()?)?;
+            }
+        }
+        // A new graph has the runtime's versions.
+        let registry = Graph::new();
+        let runtime = parse_versions(&registry.versions()?)?;
+
+        let functions = function_names(graph_def)?;
+        let mut unsupported_ops: BTreeMap<String, Vec<String>> = BTreeMap::new();
+        let mut reasons = BTreeMap::new();
+        let mut renamed_ops = BTreeMap::new();
+        for_each_node(graph_def, |name, op| {
+            if functions.contains(op) || renamed_ops.contains_key(op) {
+                return Ok(());
+            }
+            if !unsupported_ops.contains_key(op) {
+                let reason = match unavailable(&registry, op, versions.producer)? {
+                    None => return Ok(()),
+                    Some(reason) => reason,
+                };
+                let replacement = RENAMED_OPS.iter().find(|(old, _)| *old == op);
+                if let Some((_, new)) = replacement {
+                    if unavailable(&registry, new, versions.producer)?.is_none() {
+                        renamed_ops.insert(op.to_string(), new.to_string());
+                        return Ok(());
+                    }
+                }
+                reasons.insert(op.to_string(), reason);
+            }
+            unsupported_ops
+                .entry(op.to_string())
+                .or_default()
+                .push(name.to_string());
+            Ok(())
+        })?;
+        let graph_def = if renamed_ops.is_empty() {
+            graph_def.to_vec()
+        } else {
+            rename_ops(graph_def, &renamed_ops)?
+        };
+        Ok(Self {
+            producer: versions.producer,
+            min_consumer: versions.min_consumer,
+            bad_consumers: versions.bad_consumers,
+            runtime_producer: runtime.producer,
+            unsupported_ops,
+            reasons,
+            renamed_ops,
+            graph_def,
+        })
+    }
+
+    /// Returns the `GraphDef` version of the TensorFlow which produced the
+    /// graph.
+    pub fn producer(&self) -> i32 {
+        self.producer
+    }
+
+    /// Returns the oldest `GraphDef` version which can consume the graph.
+    pub fn min_consumer(&self) -> i32 {
+        self.min_consumer
+    }
+
+    /// Returns the `GraphDef` version of the linked runtime.
+    pub fn runtime_producer(&self) -> i32 {
+        self.runtime_producer
+    }
+
+    /// Returns the ops which the runtime doesn't have, each mapped to the
+    /// names of the nodes which use it.
+    pub fn unsupported_ops(&self) -> &BTreeMap<String, Vec<String>> {
+        &self.unsupported_ops
+    }
+
+    /// Returns the ops which were replaced in `graph_def`, each mapped to its
+    /// replacement.
+    pub fn renamed_ops(&self) -> &BTreeMap<String, String> {
+        &self.renamed_ops
+    }
+
+    /// Returns the `GraphDef` with renamed ops replaced, ready for import.
+    pub fn graph_def(&self) -> &[u8] {
+        &self.graph_def
+    }
+
+    /// Returns the problems which would prevent the graph from being
+    /// imported, each with a suggested fix.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+        if self.min_consumer > self.runtime_producer {
+            problems.push(format!(
+                "The graph requires GraphDef version {} or newer, but the runtime has version {}. \
+                 Link a newer TensorFlow, or re-export the graph with the TensorFlow version \
+                 being linked.",
+                self.min_consumer, self.runtime_producer
+            ));
+        }
+        if self.bad_consumers.contains(&self.runtime_producer) {
+            problems.push(format!(
+                "The graph is known not to work with GraphDef version {}, which the runtime \
+                 has. Link a different TensorFlow version.",
+                self.runtime_producer
+            ));
+        }
+        for (op, nodes) in &self.unsupported_ops {
+            const MAX_NODES: usize = 5;
+            let mut listed = nodes[..nodes.len().min(MAX_NODES)].join(", ");
+            if nodes.len() > MAX_NODES {
+                listed.push_str(&format!(" and {} more", nodes.len() - MAX_NODES));
+            }
+            let reason = &self.reasons[op];
+            let hint = if reason.starts_with("was removed") {
+                "Re-export the graph without it".to_string()
+            } else if self.producer > self.runtime_producer {
+                format!(
+                    "The graph was produced by a newer TensorFlow (GraphDef version {} vs. {}); \
+                     link a newer TensorFlow",
+                    self.producer, self.runtime_producer
+                )
+            } else {
+                "If it is a custom op, load its library with `Library::load` first".to_string()
+            };
+            problems.push(format!(
+                "Op {} (used by {}) {}. {}.",
+                op, listed, reason, hint
+            ));
+        }
+        problems
+    }
+
+    /// Returns an error describing all of the `problems`, if there are any.
+    pub fn to_result(&self) -> Result<()> {
+        let problems = self.problems();
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(invalid_arg!(
+                "The graph can't be imported:\n{}",
+                problems.join("\n")
+            ))
+        }
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ImportGraphDefOptions;
+
+    fn node(name: &str, op: &str) -> ProtoWriter {
+        let mut node = ProtoWriter::new();
+        node.string_field(NODE_DEF_NAME, name)
+            .string_field(NODE_DEF_OP, op);
+        node
+    }
+
+    #[test]
+    fn compatible_graph() {
+        let mut graph = Graph::new();
+        graph
+            .new_operation("NoOp", "noop")
+            .unwrap()
+            .finish()
+            .unwrap();
+        let compatibility = GraphDefCompatibility::check(&graph.graph_def().unwrap()).unwrap();
+        assert!(compatibility.to_result().is_ok());
+        assert!(compatibility.unsupported_ops().is_empty());
+        assert_eq!(compatibility.producer(), compatibility.runtime_producer());
+    }
+
+    #[test]
+    fn unsupported_and_renamed_ops() {
+        // Deprecated ops are only rejected in graphs produced after their
+        // deprecation, so use the runtime's version.
+        let versions = Graph::new().versions().unwrap();
+        let mut graph_def = ProtoWriter::new();
+        graph_def
+            .message_field(GRAPH_DEF_NODE, &node("a", "NotARealOp"))
+            .message_field(GRAPH_DEF_NODE, &node("b", "NotARealOp"))
+            .message_field(GRAPH_DEF_NODE, &node("c", "BatchMatrixDiag"))
+            .bytes_field(GRAPH_DEF_VERSIONS, &versions);
+        let compatibility = GraphDefCompatibility::check(graph_def.as_bytes()).unwrap();
+        assert_eq!(compatibility.producer(), compatibility.runtime_producer());
+        assert_eq!(
+            compatibility.unsupported_ops()["NotARealOp"],
+            vec!["a".to_string(), "b".to_string()]
+        );
+        assert_eq!(compatibility.renamed_ops()["BatchMatrixDiag"], "MatrixDiag");
+        let err = compatibility.to_result().unwrap_err();
+        assert!(
+            err.message().contains("NotARealOp (used by a, b)"),
+            "{}",
+            err
+        );
+
+        let mut ops = vec![];
+        for_each_node(compatibility.graph_def(), |_, op| {
+            ops.push(op.to_string());
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(ops, vec!["NotARealOp", "NotARealOp", "MatrixDiag"]);
+    }
+
+    #[test]
+    fn renamed_graph_imports() {
+        let mut graph = Graph::new();
+        let x = {
+            let mut nd = graph.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", crate::DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let mut nd = graph.new_operation("MatrixDiag", "diag").unwrap();
+        nd.add_input(x);
+        nd.finish().unwrap();
+        let graph_def = graph.graph_def().unwrap();
+        let renamed: BTreeMap<_, _> =
+            vec![("MatrixDiag".to_string(), "BatchMatrixDiag".to_string())]
+                .into_iter()
+                .collect();
+        let old_graph_def = rename_ops(&graph_def, &renamed).unwrap();
+
+        let compatibility = GraphDefCompatibility::check(&old_graph_def).unwrap();
+        compatibility.to_result().unwrap();
+        let mut imported = Graph::new();
+        imported
+            .import_graph_def(compatibility.graph_def(), &ImportGraphDefOptions::new())
+            .unwrap();
+        let diag = imported.operation_by_name_required("diag").unwrap();
+        assert_eq!(diag.op_type().unwrap(), "MatrixDiag");
+    }
+}
@@ -0,0 +1,119 @@
+//! The `graph!` macro.
+
+/// Builds ops in a `Scope` from a list of named statements, binding each op
+/// to a local variable with the same name as the op.
+///
+/// This macro currently requires the `experimental_training` feature.
+///
+/// ```
+/// # use tensorflow::graph;
+/// # use tensorflow::{DataType, Scope, Shape, Status};
+/// # fn main() -> Result<(), Status> {
+/// let scope = Scope::new_root_scope();
+/// graph! { scope =>
+///     x = placeholder(DataType::Float, Shape::new(Some(vec![None, Some(3)])));
+///     w = constant(&[1.0f32, 2.0, 3.0][..]);
+///     y = multiply(x, w);
+///     axis = constant(1);
+///     z = build(tensorflow::ops::Sum::new().keep_dims(true), y, axis);
+/// }
+/// assert_eq!(z.name()?, "z");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Each statement has one of the forms
+///
+/// * `name = placeholder(data_type);` or
+///   `name = placeholder(data_type, shape);`, which create a `Placeholder`,
+///   with an unknown shape by default.
+/// * `name = op(args...);`, which calls `ops::op(scope, args...)`, e.g.
+///   `constant(2.0f32)` or `mat_mul(x, w)`.
+/// * `name = build(builder, args...);`, which calls
+///   `builder.build(scope, args...)` to set attributes, e.g.
+///   `build(ops::Cast::new().dst_type(DataType::Int32), x)`.
+///
+/// Every op is named after its variable within the scope.  Arguments are
+/// cloned, so variables can be used more than once.  Errors are propagated
+/// with `?`, so the macro must be used in a function returning a compatible
+/// `Result`.
+#[macro_export]
+macro_rules! graph {
+    ($scope:expr => ) => {};
+    ($scope:expr => $name:ident = placeholder($data_type:expr) ; $($rest:tt)*) => {
+        $crate::graph!($scope =>
+            $name = placeholder($data_type, $crate::Shape::new(None)); $($rest)*);
+    };
+    ($scope:expr => $name:ident = placeholder($data_type:expr, $shape:expr) ; $($rest:tt)*) => {
+        let $name = $crate::ops::Placeholder::new()
+            .data_type($data_type)
+            .shape($shape)
+            .build(&mut $scope.with_op_name(stringify!($name)))?;
+        $crate::graph!($scope => $($rest)*);
+    };
+    ($scope:expr => $name:ident = build($builder:expr $(, $arg:expr)*) ; $($rest:tt)*) => {
+        let $name = $builder.build(
+            &mut $scope.with_op_name(stringify!($name))
+            $(, ($arg).clone())*
+        )?;
+        $crate::graph!($scope => $($rest)*);
+    };
+    ($scope:expr => $name:ident = $op:ident($($arg:expr),*) ; $($rest:tt)*) => {
+        let $name = $crate::ops::$op(
+            &mut $scope.with_op_name(stringify!($name))
+            $(, ($arg).clone())*
+        )?;
+        $crate::graph!($scope => $($rest)*);
+    };
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use crate::ops;
+    use crate::DataType;
+    use crate::Result;
+    use crate::Scope;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Shape;
+    use crate::Tensor;
+
+    #[test]
+    fn build_and_run() -> Result<()> {
+        let scope = Scope::new_root_scope().new_sub_scope("model");
+        graph! { scope =>
+            x = placeholder(DataType::Float, Shape(Some(vec![Some(2)])));
+            two = constant(2.0f32);
+            doubled = multiply(x, two);
+            squared = multiply(doubled, doubled);
+            as_int = build(ops::Cast::new().dst_type(DataType::Int32), squared);
+        }
+        assert_eq!(x.name()?, "model/x");
+        assert_eq!(as_int.name()?, "model/as_int");
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph())?;
+        let input = Tensor::new(&[2]).with_values(&[1.0f32, 3.0])?;
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &input);
+        let token = args.request_fetch(&as_int, 0);
+        session.run(&mut args)?;
+        assert_eq!(&args.fetch::<i32>(token)?[..], &[4, 36]);
+        Ok(())
+    }
+
+    #[test]
+    fn errors_propagate() {
+        fn build(scope: &Scope) -> Result<()> {
+            graph! { scope =>
+                x = placeholder(DataType::Float);
+                y = constant(1i32);
+                _sum = add(x, y);
+            }
+            Ok(())
+        }
+        assert!(build(&Scope::new_root_scope()).is_err());
+    }
+}
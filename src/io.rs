@@ -2,11 +2,22 @@
 //!
 //! See the [tensorflow docs](https://www.tensorflow.org/api_guides/python/python_io#tfrecords-format-details) for details of this format.
 
+use self::byteorder::ReadBytesExt;
 use self::byteorder::WriteBytesExt;
+use crate::protos::ProtoWriter;
 use byteorder;
 use crc::crc32;
+use std::fs;
+use std::fs::File;
 use std::io;
+use std::io::BufWriter;
+use std::io::Read;
 use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::process;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
 
 /// A type for writing bytes in the TFRecords format.
 #[derive(Debug)]
@@ -39,11 +50,11 @@ where
         let mut len_bytes = [0u8; 8];
         (&mut len_bytes[..]).write_u64::<byteorder::LittleEndian>(bytes.len() as u64)?;
 
-        let masked_len_crc32c = Self::mask(crc32::checksum_castagnoli(&len_bytes));
+        let masked_len_crc32c = mask(crc32::checksum_castagnoli(&len_bytes));
         let mut len_crc32_bytes = [0u8; 4];
         (&mut len_crc32_bytes[..]).write_u32::<byteorder::LittleEndian>(masked_len_crc32c)?;
 
-        let masked_bytes_crc32c = Self::mask(crc32::checksum_castagnoli(&bytes));
+        let masked_bytes_crc32c = mask(crc32::checksum_castagnoli(&bytes));
         let mut bytes_crc32_bytes = [0u8; 4];
         (&mut bytes_crc32_bytes[..]).write_u32::<byteorder::LittleEndian>(masked_bytes_crc32c)?;
 
@@ -53,9 +64,133 @@ where
         self.writer.write(&bytes_crc32_bytes)?;
         Ok(())
     }
+}
+
+fn mask(crc: u32) -> u32 {
+    ((crc >> 15) | (crc << 17)).wrapping_add(0xa282ead8u32)
+}
+
+/// A type for reading bytes in the TFRecords format, as written by
+/// `RecordWriter`.
+#[derive(Debug)]
+pub struct RecordReader<R: Read> {
+    reader: R,
+}
+
+impl<R> RecordReader<R>
+where
+    R: Read,
+{
+    /// Construct a new RecordReader which reads from `reader`.
+    pub fn new(reader: R) -> Self {
+        RecordReader { reader }
+    }
 
-    fn mask(crc: u32) -> u32 {
-        ((crc >> 15) | (crc << 17)).wrapping_add(0xa282ead8u32)
+    /// Read a complete TFRecord, or return `None` at the end of the input.
+    /// Fails with `io::ErrorKind::InvalidData` if a CRC doesn't match.
+    pub fn read_record(&mut self) -> io::Result<Option<Vec<u8>>> {
+        let mut len_bytes = [0u8; 8];
+        let mut read = 0;
+        while read < len_bytes.len() {
+            match self.reader.read(&mut len_bytes[read..]) {
+                Ok(0) if read == 0 => return Ok(None),
+                Ok(0) => return Err(io::ErrorKind::UnexpectedEof.into()),
+                Ok(n) => read += n,
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => return Err(e),
+            }
+        }
+        let masked_len_crc32c = self.reader.read_u32::<byteorder::LittleEndian>()?;
+        if masked_len_crc32c != mask(crc32::checksum_castagnoli(&len_bytes)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "TFRecord length doesn't match its CRC",
+            ));
+        }
+        let len = (&len_bytes[..]).read_u64::<byteorder::LittleEndian>()?;
+
+        let mut bytes = vec![0u8; len as usize];
+        self.reader.read_exact(&mut bytes)?;
+        let masked_bytes_crc32c = self.reader.read_u32::<byteorder::LittleEndian>()?;
+        if masked_bytes_crc32c != mask(crc32::checksum_castagnoli(&bytes)) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "TFRecord data doesn't match its CRC",
+            ));
+        }
+        Ok(Some(bytes))
+    }
+}
+
+/// A type for writing summaries to an event file, which TensorBoard displays
+/// when pointed at the directory containing it.
+#[derive(Debug)]
+pub struct SummaryWriter {
+    writer: RecordWriter<BufWriter<File>>,
+    path: PathBuf,
+}
+
+impl SummaryWriter {
+    /// Creates a new event file in `log_dir`, creating the directory if it
+    /// doesn't exist.
+    pub fn new<P: AsRef<Path>>(log_dir: P) -> io::Result<Self> {
+        let log_dir = log_dir.as_ref();
+        fs::create_dir_all(log_dir)?;
+        let path = log_dir.join(format!(
+            "events.out.tfevents.{}.{}",
+            Self::wall_time() as u64,
+            process::id()
+        ));
+        let mut writer = SummaryWriter {
+            writer: RecordWriter::new(BufWriter::new(File::create(&path)?)),
+            path,
+        };
+        let mut event = Self::event(0);
+        event.string_field(3, "brain.Event:2");
+        writer.writer.write_record(event.as_bytes())?;
+        Ok(writer)
+    }
+
+    /// Returns the path of the event file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Writes a scalar summary of `value` tagged `tag`, e.g. `"loss"`, for
+    /// the training step `step`.
+    pub fn add_scalar(&mut self, tag: &str, value: f32, step: i64) -> io::Result<()> {
+        let mut summary_value = ProtoWriter::new();
+        summary_value.string_field(1, tag).float_field(2, value);
+        let mut summary = ProtoWriter::new();
+        summary.message_field(1, &summary_value);
+        self.add_summary(summary.as_bytes(), step)
+    }
+
+    /// Writes a serialized `Summary` protocol buffer for the training step
+    /// `step`.
+    pub fn add_summary(&mut self, summary: &[u8], step: i64) -> io::Result<()> {
+        let mut event = Self::event(step);
+        event.bytes_field(5, summary);
+        self.writer.write_record(event.as_bytes())
+    }
+
+    /// Flushes buffered events to the file, so TensorBoard sees them.
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.writer.writer.flush()
+    }
+
+    fn wall_time() -> f64 {
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs_f64())
+            .unwrap_or(0.0)
+    }
+
+    /// Returns an `Event` with the current time and `step`.
+    fn event(step: i64) -> ProtoWriter {
+        let mut event = ProtoWriter::new();
+        event.double_field(1, Self::wall_time()).int_field(2, step);
+        event
     }
 }
 
@@ -95,4 +230,50 @@ mod tests {
         assert_eq!(actual, expected);
     }
 
+    #[test]
+    fn reader() {
+        let mut bytes = Vec::new();
+        File::open("test_resources/io/expected.tfrecord")
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        let mut reader = RecordReader::new(&bytes[..]);
+        assert_eq!(
+            reader.read_record().unwrap().unwrap(),
+            b"The Quick Brown Fox".to_vec()
+        );
+        assert!(reader.read_record().unwrap().is_none());
+
+        let mut corrupt = bytes.clone();
+        corrupt[14] ^= 1;
+        let error = RecordReader::new(&corrupt[..]).read_record().unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+        let error = RecordReader::new(&bytes[..bytes.len() - 1])
+            .read_record()
+            .unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::UnexpectedEof);
+    }
+
+    #[test]
+    fn summary_writer() {
+        let log_dir = std::env::temp_dir().join(format!("summary_writer_{}", process::id()));
+        let mut writer = SummaryWriter::new(&log_dir).unwrap();
+        writer.add_scalar("loss", 0.5, 7).unwrap();
+        writer.flush().unwrap();
+        let mut bytes = Vec::new();
+        File::open(writer.path())
+            .unwrap()
+            .read_to_end(&mut bytes)
+            .unwrap();
+        // Two records, each with a 12 byte header and 4 byte footer.
+        let first_len = bytes[0] as usize;
+        let second = &bytes[first_len + 16..];
+        let second_len = second[0] as usize;
+        assert_eq!(second.len(), second_len + 16);
+        let event = &second[12..12 + second_len];
+        // The summary value is the tag followed by the float.
+        let tail = [&b"\x0a\x04loss\x15"[..], &0.5f32.to_le_bytes()[..]].concat();
+        assert!(event.ends_with(&tail), "{:?}", event);
+        fs::remove_dir_all(&log_dir).unwrap();
+    }
 }
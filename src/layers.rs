@@ -0,0 +1,2184 @@
+//! This module provides common neural network layers.
+//!
+//! This module currently requires the `experimental_training` feature.
+//!
+//! Each layer is a builder holding its settings, whose `build` function adds
+//! the layer to the graph under a sub-scope named after the layer, and
+//! returns its output.  The variables a layer creates are recorded in the
+//! scope, so the variables of a whole model can be trained without tracking
+//! them by hand:
+//!
+//! ```ignore
+//! let hidden = Dense::new(64)
+//!     .with_activation(Activation::Relu)
+//!     .build(&mut scope, x)?;
+//! let hidden = Dropout::new(0.5).build(&mut scope, hidden, training)?;
+//! let logits = Dense::new(10).build(&mut scope, hidden)?;
+//! // ... compute the loss ...
+//! let variables = scope.trainable_variables();
+//! let (minimizer_vars, minimize) = optimizer.minimize(
+//!     &mut scope,
+//!     loss,
+//!     MinimizeOptions::default().with_variables(&variables),
+//! )?;
+//! ```
+//!
+//! Layers which behave differently during training, such as `Dropout` and
+//! `BatchNormalization`, take a scalar bool tensor saying whether the model
+//! is being trained, typically a placeholder fed with `true` by the training
+//! loop and `false` otherwise, so the same graph can be used for both.
+//!
+//! Some layers also create variables which aren't trained, such as the
+//! moving averages of `BatchNormalization`.  These are only returned by
+//! `Scope::variables`, which should be used to initialize and save the
+//! model.
+//!
+//! Layers follow the `DTypePolicy` of their scope: their variables are
+//! created in its variable data type, and their floating point inputs and
+//! weights are cast to its compute data type, so building a model under
+//! `DTypePolicy::mixed_float16()` computes in half precision while keeping
+//! the variables in single precision.  Without a policy, layers compute in
+//! float.
+
+use crate::ops;
+use crate::scope::is_floating;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Shape;
+use crate::Tensor;
+use crate::Variable;
+
+/// An activation function applied to the output of a layer.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Activation {
+    /// Leaves the output unchanged.
+    Linear,
+    /// `max(x, 0)`.
+    Relu,
+    /// `1 / (1 + exp(-x))`.
+    Sigmoid,
+    /// The hyperbolic tangent.
+    Tanh,
+    /// The softmax along the last dimension.
+    Softmax,
+}
+
+impl Activation {
+    fn apply(self, scope: &mut Scope, x: Output) -> Result<Output> {
+        Ok(match self {
+            Activation::Linear => x,
+            Activation::Relu => ops::relu(scope, x)?.into(),
+            Activation::Sigmoid => ops::sigmoid(scope, x)?.into(),
+            Activation::Tanh => ops::tanh(scope, x)?.into(),
+            Activation::Softmax => ops::softmax(scope, x)?.into(),
+        })
+    }
+}
+
+fn check_float(layer: &str, input: &Output) -> Result<()> {
+    let data_type = input.operation.output_type(input.index as usize);
+    if !is_floating(data_type) {
+        return Err(invalid_arg!(
+            "The input of {} must be floating point, but is {}",
+            layer,
+            data_type
+        ));
+    }
+    Ok(())
+}
+
+/// Returns `x` cast to `data_type`, or `x` itself if it already has it.
+fn cast(scope: &mut Scope, x: Output, data_type: DataType) -> Result<Output> {
+    if x.operation.output_type(x.index as usize) == data_type {
+        return Ok(x);
+    }
+    Ok(ops::Cast::new().dst_type(data_type).build(scope, x)?.into())
+}
+
+/// Returns the input of a layer cast to the compute data type of `scope`,
+/// so that layers follow the scope's `DTypePolicy`.
+fn compute_input(scope: &mut Scope, input: Output) -> Result<Output> {
+    let data_type = scope.compute_dtype();
+    cast(scope, input, data_type)
+}
+
+/// Returns a scalar constant of the floating point type `data_type`.
+fn scalar(scope: &mut Scope, value: f32, data_type: DataType) -> Result<Output> {
+    let value = ops::constant(scope, value)?.into();
+    cast(scope, value, data_type)
+}
+
+/// Checks that `input` is a floating point tensor of rank `rank` (or at
+/// least `rank` if `at_least`) whose last dimension is known, and returns its
+/// dimensions.
+fn input_dims(
+    scope: &Scope,
+    layer: &str,
+    input: &Output,
+    rank: usize,
+    at_least: bool,
+) -> Result<Vec<Option<i64>>> {
+    check_float(layer, input)?;
+    let dims = match ops::known_dims(scope, input)? {
+        Some(dims) => dims,
+        None => {
+            return Err(invalid_arg!(
+                "The rank of the input of {} must be known",
+                layer
+            ))
+        }
+    };
+    if dims.len() < rank || (dims.len() > rank && !at_least) {
+        return Err(invalid_arg!(
+            "The input of {} must have {}{} dimensions, but has shape {:?}",
+            layer,
+            if at_least { "at least " } else { "" },
+            rank,
+            dims
+        ));
+    }
+    if dims.last() == Some(&None) {
+        return Err(invalid_arg!(
+            "The last dimension of the input of {} must be known, but it has shape {:?}",
+            layer,
+            dims
+        ));
+    }
+    Ok(dims)
+}
+
+/// Creates a variable named `name` in the variable data type of `scope`
+/// with the given shape and initial value, and records it in `scope`.
+fn add_variable(
+    scope: &mut Scope,
+    name: &str,
+    shape: &[u64],
+    initial_value: Output,
+    trainable: bool,
+) -> Result<Variable> {
+    let dims = shape.iter().map(|&d| Some(d as i64)).collect();
+    let variable = Variable::builder()
+        .initial_value(initial_value)
+        .data_type(scope.variable_dtype())
+        .shape(Shape::from(Some(dims)))
+        .build(&mut scope.with_op_name(name))?;
+    scope.record_variable(variable.clone(), trainable);
+    Ok(variable)
+}
+
+/// Returns a constant tensor of the given shape filled with `value`, in the
+/// variable data type of `scope`.
+fn filled(scope: &mut Scope, shape: &[u64], value: f32) -> Result<Output> {
+    let len = shape.iter().product::<u64>() as usize;
+    let values = ops::constant(scope, Tensor::new(shape).with_values(&vec![value; len])?)?;
+    let data_type = scope.variable_dtype();
+    cast(scope, values.into(), data_type)
+}
+
+/// Returns random values of the given shape drawn uniformly from `[-limit,
+/// limit]`, in the variable data type of `scope`.
+fn random_uniform(scope: &mut Scope, shape: &[u64], limit: f32) -> Result<Output> {
+    let data_type = scope.variable_dtype();
+    let dims: Vec<i64> = shape.iter().map(|&d| d as i64).collect();
+    let shape = ops::constant(scope, &dims[..])?;
+    let uniform = ops::RandomUniform::new()
+        .dtype(data_type)
+        .build(scope, shape)?;
+    let width = scalar(scope, 2.0 * limit, data_type)?;
+    let scaled = ops::multiply(scope, uniform, width)?;
+    let limit = scalar(scope, limit, data_type)?;
+    Ok(ops::subtract(scope, scaled, limit)?.into())
+}
+
+/// Returns random values for a weight of the given shape drawn uniformly
+/// from `[-limit, limit]` with `limit = sqrt(6 / (fan_in + fan_out))`, the
+/// Glorot uniform initialization Keras uses by default.
+fn glorot_uniform(scope: &mut Scope, shape: &[u64], fan_in: u64, fan_out: u64) -> Result<Output> {
+    let limit = (6.0 / (fan_in + fan_out) as f64).sqrt() as f32;
+    random_uniform(scope, shape, limit)
+}
+
+/// Adds `bias` to `x` if there is one, and applies `activation`.
+fn finish(
+    scope: &mut Scope,
+    x: Output,
+    bias: Option<&Variable>,
+    activation: Activation,
+) -> Result<Output> {
+    let x = match bias {
+        Some(bias) => {
+            let bias = bias.compute_output(scope)?;
+            ops::bias_add(scope, x, bias)?.into()
+        }
+        None => x,
+    };
+    activation.apply(scope, x)
+}
+
+/// A densely connected layer, computing `activation(input * kernel + bias)`
+/// for an input of shape `[batch, features]`.
+///
+/// It creates the variables "kernel", of shape `[features, units]`, and
+/// "bias", of shape `[units]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Dense {
+    units: u64,
+    activation: Activation,
+    use_bias: bool,
+}
+
+impl Dense {
+    /// Creates a layer with `units` outputs, no activation and a bias.
+    pub fn new(units: u64) -> Self {
+        Self {
+            units,
+            activation: Activation::Linear,
+            use_bias: true,
+        }
+    }
+
+    /// Sets the activation function.  Default is `Activation::Linear`.
+    pub fn with_activation(self, activation: Activation) -> Self {
+        Self { activation, ..self }
+    }
+
+    /// Sets whether the layer adds a bias.  Default is true.
+    pub fn with_bias(self, use_bias: bool) -> Self {
+        Self { use_bias, ..self }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  The number of features of `input` must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "a dense layer", &input, 2, false)?;
+        if self.units == 0 {
+            return Err(invalid_arg!("A dense layer needs at least one unit"));
+        }
+        // input_dims checked that the last dimension is known.
+        let features = dims[1].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("dense");
+        let input = compute_input(&mut scope, input)?;
+        let shape = [features, self.units];
+        let initial_kernel = glorot_uniform(&mut scope, &shape, features, self.units)?;
+        let kernel = add_variable(&mut scope, "kernel", &shape, initial_kernel, true)?;
+        let bias = if self.use_bias {
+            let initial_bias = filled(&mut scope, &[self.units], 0.0)?;
+            Some(add_variable(
+                &mut scope,
+                "bias",
+                &[self.units],
+                initial_bias,
+                true,
+            )?)
+        } else {
+            None
+        };
+        let kernel = kernel.compute_output(&mut scope)?;
+        let product = ops::mat_mul(&mut scope, input, kernel)?;
+        finish(&mut scope, product.into(), bias.as_ref(), self.activation)
+    }
+}
+
+/// How a convolution handles the borders of its input.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Padding {
+    /// Pads the input with zeros so that the output has `ceil(size /
+    /// stride)` pixels along each dimension.
+    Same,
+    /// Only uses windows which lie entirely within the input.
+    Valid,
+}
+
+impl Padding {
+    fn as_str(self) -> &'static str {
+        match self {
+            Padding::Same => "SAME",
+            Padding::Valid => "VALID",
+        }
+    }
+}
+
+/// Checks that a convolution has a positive kernel size and strides.
+fn check_conv(layer: &str, kernel_size: [u64; 2], strides: [u64; 2]) -> Result<()> {
+    if kernel_size.contains(&0) || strides.contains(&0) {
+        return Err(invalid_arg!(
+            "The kernel size and strides of {} must be positive, but are {:?} and {:?}",
+            layer,
+            kernel_size,
+            strides
+        ));
+    }
+    Ok(())
+}
+
+/// Creates the zero-initialized "bias" variable of shape `[units]` if
+/// `use_bias`.
+fn add_bias(scope: &mut Scope, units: u64, use_bias: bool) -> Result<Option<Variable>> {
+    if !use_bias {
+        return Ok(None);
+    }
+    let initial_bias = filled(scope, &[units], 0.0)?;
+    Ok(Some(add_variable(
+        scope,
+        "bias",
+        &[units],
+        initial_bias,
+        true,
+    )?))
+}
+
+/// A 2D convolution layer, computing `activation(conv2d(input, kernel) +
+/// bias)` for NHWC images of shape `[batch, height, width, channels]`.
+///
+/// It creates the variables "kernel", of shape `[kernel_height,
+/// kernel_width, channels, filters]`, and "bias", of shape `[filters]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Conv2D {
+    filters: u64,
+    kernel_size: [u64; 2],
+    strides: [u64; 2],
+    padding: Padding,
+    activation: Activation,
+    use_bias: bool,
+}
+
+impl Conv2D {
+    /// Creates a layer with `filters` output channels and a kernel of
+    /// `[height, width]` pixels, moved one pixel at a time with
+    /// `Padding::Valid`, no activation and a bias.
+    pub fn new(filters: u64, kernel_size: [u64; 2]) -> Self {
+        Self {
+            filters,
+            kernel_size,
+            strides: [1, 1],
+            padding: Padding::Valid,
+            activation: Activation::Linear,
+            use_bias: true,
+        }
+    }
+
+    /// Sets how many pixels the kernel moves along the height and width.
+    /// Default is `[1, 1]`.
+    pub fn with_strides(self, strides: [u64; 2]) -> Self {
+        Self { strides, ..self }
+    }
+
+    /// Sets the padding.  Default is `Padding::Valid`.
+    pub fn with_padding(self, padding: Padding) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Sets the activation function.  Default is `Activation::Linear`.
+    pub fn with_activation(self, activation: Activation) -> Self {
+        Self { activation, ..self }
+    }
+
+    /// Sets whether the layer adds a bias.  Default is true.
+    pub fn with_bias(self, use_bias: bool) -> Self {
+        Self { use_bias, ..self }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  The number of channels of `input` must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "a 2D convolution", &input, 4, false)?;
+        if self.filters == 0 {
+            return Err(invalid_arg!("A 2D convolution needs at least one filter"));
+        }
+        check_conv("a 2D convolution", self.kernel_size, self.strides)?;
+        let channels = dims[3].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("conv2d");
+        let input = compute_input(&mut scope, input)?;
+        let [height, width] = self.kernel_size;
+        let shape = [height, width, channels, self.filters];
+        let receptive_field = height * width;
+        let initial_kernel = glorot_uniform(
+            &mut scope,
+            &shape,
+            receptive_field * channels,
+            receptive_field * self.filters,
+        )?;
+        let kernel = add_variable(&mut scope, "kernel", &shape, initial_kernel, true)?;
+        let bias = add_bias(&mut scope, self.filters, self.use_bias)?;
+        let kernel = kernel.compute_output(&mut scope)?;
+        let strides = [self.strides[0] as i64, self.strides[1] as i64];
+        let convolved = ops::conv2d(&mut scope, input, kernel, strides, self.padding.as_str())?;
+        finish(&mut scope, convolved, bias.as_ref(), self.activation)
+    }
+}
+
+/// A transposed 2D convolution layer, sometimes called a deconvolution,
+/// which maps NHWC images of shape `[batch, height, width, channels]` to
+/// images of shape `[batch, height * stride, width * stride, filters]` with
+/// `Padding::Same`, or `[batch, height * stride + max(kernel - stride, 0),
+/// ...]` with `Padding::Valid`.  It is typically used to upsample feature
+/// maps, e.g. in the decoder of a segmentation model.
+///
+/// It creates the variables "kernel", of shape `[kernel_height,
+/// kernel_width, filters, channels]`, and "bias", of shape `[filters]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Conv2DTranspose {
+    filters: u64,
+    kernel_size: [u64; 2],
+    strides: [u64; 2],
+    padding: Padding,
+    activation: Activation,
+    use_bias: bool,
+}
+
+impl Conv2DTranspose {
+    /// Creates a layer with `filters` output channels and a kernel of
+    /// `[height, width]` pixels, moved one pixel at a time with
+    /// `Padding::Valid`, no activation and a bias.
+    pub fn new(filters: u64, kernel_size: [u64; 2]) -> Self {
+        Self {
+            filters,
+            kernel_size,
+            strides: [1, 1],
+            padding: Padding::Valid,
+            activation: Activation::Linear,
+            use_bias: true,
+        }
+    }
+
+    /// Sets the upsampling factor along the height and width.  Default is
+    /// `[1, 1]`.
+    pub fn with_strides(self, strides: [u64; 2]) -> Self {
+        Self { strides, ..self }
+    }
+
+    /// Sets the padding.  Default is `Padding::Valid`.
+    pub fn with_padding(self, padding: Padding) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Sets the activation function.  Default is `Activation::Linear`.
+    pub fn with_activation(self, activation: Activation) -> Self {
+        Self { activation, ..self }
+    }
+
+    /// Sets whether the layer adds a bias.  Default is true.
+    pub fn with_bias(self, use_bias: bool) -> Self {
+        Self { use_bias, ..self }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  The number of channels of `input` must be known, but its
+    /// other dimensions may be unknown.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "a transposed 2D convolution", &input, 4, false)?;
+        if self.filters == 0 {
+            return Err(invalid_arg!(
+                "A transposed 2D convolution needs at least one filter"
+            ));
+        }
+        check_conv(
+            "a transposed 2D convolution",
+            self.kernel_size,
+            self.strides,
+        )?;
+        let channels = dims[3].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("conv2d_transpose");
+        let input = compute_input(&mut scope, input)?;
+        let [height, width] = self.kernel_size;
+        let shape = [height, width, self.filters, channels];
+        let receptive_field = height * width;
+        let initial_kernel = glorot_uniform(
+            &mut scope,
+            &shape,
+            receptive_field * channels,
+            receptive_field * self.filters,
+        )?;
+        let kernel = add_variable(&mut scope, "kernel", &shape, initial_kernel, true)?;
+        let bias = add_bias(&mut scope, self.filters, self.use_bias)?;
+        let kernel = kernel.compute_output(&mut scope)?;
+        let convolved = conv2d_transpose(
+            &mut scope,
+            input,
+            kernel,
+            self.kernel_size,
+            self.strides,
+            self.padding,
+        )?;
+        finish(&mut scope, convolved, bias.as_ref(), self.activation)
+    }
+}
+
+/// Computes the transposed convolution of `input` with `kernel`, of shape
+/// `[height, width, filters, channels]`.  TensorFlow has no gradient for
+/// `Conv2DBackpropInput`, so this is built from ops which have one: the
+/// pixels of `input` are spread `strides` apart with zeros, padded so that
+/// the kernel reaches every output pixel, and convolved with the kernel
+/// flipped and transposed to `[height, width, channels, filters]`.
+fn conv2d_transpose(
+    scope: &mut Scope,
+    input: Output,
+    kernel: Output,
+    kernel_size: [u64; 2],
+    strides: [u64; 2],
+    padding: Padding,
+) -> Result<Output> {
+    let [stride_height, stride_width] = strides;
+    let mut input = input;
+    if strides != [1, 1] {
+        // [batch, height, width, channels] becomes [batch, height, 1, width,
+        // 1, channels], then [batch, height, stride_height, width,
+        // stride_width, channels] with zeros after each pixel, which merges
+        // into [batch, height * stride_height, width * stride_width,
+        // channels].
+        let input_shape = ops::shape(scope, input.clone())?;
+        let axis = ops::constant(scope, 2)?;
+        let spread = ops::expand_dims(scope, input, axis)?;
+        let axis = ops::constant(scope, 4)?;
+        let spread = ops::expand_dims(scope, spread, axis)?;
+        let mut paddings = [0; 12];
+        paddings[5] = stride_height as i32 - 1;
+        paddings[9] = stride_width as i32 - 1;
+        let paddings = ops::constant(scope, Tensor::new(&[6, 2]).with_values(&paddings)?)?;
+        let spread = ops::pad(scope, spread, paddings)?;
+        let scale = ops::constant(
+            scope,
+            &[1, stride_height as i32, stride_width as i32, 1][..],
+        )?;
+        let spread_shape = ops::multiply(scope, input_shape, scale)?;
+        input = ops::reshape(scope, spread, spread_shape)?.into();
+    }
+    // The convolution being transposed pads `before` pixels ahead of the
+    // image, so input pixel `i` reaches output pixels `i * stride - before`
+    // to `i * stride - before + kernel - 1`.  The zeros after the last pixel
+    // already make up `stride - 1` pixels of the trailing padding.
+    let pad = |kernel: u64, stride: u64| {
+        let total = kernel.saturating_sub(stride) as i32;
+        let before = match padding {
+            Padding::Same => total / 2,
+            Padding::Valid => 0,
+        };
+        [kernel as i32 - 1 - before, total - before]
+    };
+    let [height, width] = kernel_size;
+    let [top, bottom] = pad(height, stride_height);
+    let [left, right] = pad(width, stride_width);
+    let paddings = ops::constant(
+        scope,
+        Tensor::new(&[4, 2]).with_values(&[0, 0, top, bottom, left, right, 0, 0])?,
+    )?;
+    let padded = ops::pad(scope, input, paddings)?;
+    let axis = ops::constant(scope, &[0, 1][..])?;
+    let flipped = ops::reverse(scope, kernel, axis)?;
+    let perm = ops::constant(scope, &[0, 1, 3, 2][..])?;
+    let flipped = ops::transpose(scope, flipped, perm)?;
+    ops::conv2d(scope, padded.into(), flipped.into(), [1, 1], "VALID")
+}
+
+/// A depthwise 2D convolution layer, which convolves each channel of NHWC
+/// images of shape `[batch, height, width, channels]` separately with
+/// `depth_multiplier` filters, producing `channels * depth_multiplier`
+/// output channels.
+///
+/// It creates the variables "depthwise_kernel", of shape `[kernel_height,
+/// kernel_width, channels, depth_multiplier]`, and "bias", of shape
+/// `[channels * depth_multiplier]`.
+#[derive(Debug, Clone, Copy)]
+pub struct DepthwiseConv2D {
+    kernel_size: [u64; 2],
+    depth_multiplier: u64,
+    strides: [u64; 2],
+    padding: Padding,
+    activation: Activation,
+    use_bias: bool,
+}
+
+impl DepthwiseConv2D {
+    /// Creates a layer with a kernel of `[height, width]` pixels and a depth
+    /// multiplier of 1, moved one pixel at a time with `Padding::Valid`, no
+    /// activation and a bias.
+    pub fn new(kernel_size: [u64; 2]) -> Self {
+        Self {
+            kernel_size,
+            depth_multiplier: 1,
+            strides: [1, 1],
+            padding: Padding::Valid,
+            activation: Activation::Linear,
+            use_bias: true,
+        }
+    }
+
+    /// Sets the number of output channels computed from each input channel.
+    /// Default is 1.
+    pub fn with_depth_multiplier(self, depth_multiplier: u64) -> Self {
+        Self {
+            depth_multiplier,
+            ..self
+        }
+    }
+
+    /// Sets how many pixels the kernel moves along the height and width.
+    /// Default is `[1, 1]`.
+    pub fn with_strides(self, strides: [u64; 2]) -> Self {
+        Self { strides, ..self }
+    }
+
+    /// Sets the padding.  Default is `Padding::Valid`.
+    pub fn with_padding(self, padding: Padding) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Sets the activation function.  Default is `Activation::Linear`.
+    pub fn with_activation(self, activation: Activation) -> Self {
+        Self { activation, ..self }
+    }
+
+    /// Sets whether the layer adds a bias.  Default is true.
+    pub fn with_bias(self, use_bias: bool) -> Self {
+        Self { use_bias, ..self }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  The number of channels of `input` must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "a depthwise 2D convolution", &input, 4, false)?;
+        if self.depth_multiplier == 0 {
+            return Err(invalid_arg!(
+                "A depthwise 2D convolution needs a positive depth multiplier"
+            ));
+        }
+        check_conv("a depthwise 2D convolution", self.kernel_size, self.strides)?;
+        let channels = dims[3].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("depthwise_conv2d");
+        let input = compute_input(&mut scope, input)?;
+        let kernel = depthwise_kernel(
+            &mut scope,
+            self.kernel_size,
+            channels,
+            self.depth_multiplier,
+        )?;
+        let bias = add_bias(&mut scope, channels * self.depth_multiplier, self.use_bias)?;
+        let kernel = kernel.compute_output(&mut scope)?;
+        let convolved = depthwise_conv2d(
+            &mut scope,
+            input,
+            kernel,
+            [
+                self.kernel_size[0],
+                self.kernel_size[1],
+                channels,
+                self.depth_multiplier,
+            ],
+            self.strides,
+            self.padding,
+        )?;
+        finish(&mut scope, convolved, bias.as_ref(), self.activation)
+    }
+}
+
+/// Creates the "depthwise_kernel" variable of a depthwise convolution.
+fn depthwise_kernel(
+    scope: &mut Scope,
+    kernel_size: [u64; 2],
+    channels: u64,
+    depth_multiplier: u64,
+) -> Result<Variable> {
+    let [height, width] = kernel_size;
+    let shape = [height, width, channels, depth_multiplier];
+    let receptive_field = height * width;
+    let initial_kernel = glorot_uniform(
+        scope,
+        &shape,
+        receptive_field * channels,
+        receptive_field * depth_multiplier,
+    )?;
+    add_variable(scope, "depthwise_kernel", &shape, initial_kernel, true)
+}
+
+/// Computes the depthwise convolution of `input` with `kernel`, of `kernel_shape`, i.e. `[height, width, channels,
+/// depth_multiplier]`.  TensorFlow has no
+/// gradient for `DepthwiseConv2dNative`, so this is a `Conv2D` with a
+/// block-diagonal kernel, in which output channel `c * depth_multiplier + k`
+/// applies filter `[.., c, k]` to input channel `c` and zeros to the others.
+/// This does `channels` times the arithmetic of the native op.
+fn depthwise_conv2d(
+    scope: &mut Scope,
+    input: Output,
+    kernel: Output,
+    kernel_shape: [u64; 4],
+    strides: [u64; 2],
+    padding: Padding,
+) -> Result<Output> {
+    let [height, width, channels, depth_multiplier] = kernel_shape;
+    let axis = ops::constant(scope, 3)?;
+    let kernel = ops::expand_dims(scope, kernel, axis)?;
+    let mut identity = vec![0.0f32; (channels * channels) as usize];
+    for channel in 0..channels as usize {
+        identity[channel * channels as usize + channel] = 1.0;
+    }
+    let identity = ops::constant(
+        scope,
+        Tensor::new(&[channels, channels, 1]).with_values(&identity)?,
+    )?;
+    let data_type = scope.compute_dtype();
+    let identity = cast(scope, identity.into(), data_type)?;
+    let block_diagonal = ops::multiply(scope, kernel, identity)?;
+    let shape = ops::constant(
+        scope,
+        &[
+            height as i64,
+            width as i64,
+            channels as i64,
+            (channels * depth_multiplier) as i64,
+        ][..],
+    )?;
+    let kernel = ops::reshape(scope, block_diagonal, shape)?;
+    let strides = [strides[0] as i64, strides[1] as i64];
+    ops::conv2d(scope, input, kernel.into(), strides, padding.as_str())
+}
+
+/// A depthwise separable 2D convolution layer, which applies a depthwise
+/// convolution to NHWC images of shape `[batch, height, width, channels]`
+/// followed by a 1x1 convolution mixing the resulting channels into
+/// `filters` output channels.  It approximates a `Conv2D` with far fewer
+/// parameters and operations, as in MobileNet and Xception.
+///
+/// It creates the variables "depthwise_kernel", of shape `[kernel_height,
+/// kernel_width, channels, depth_multiplier]`, "pointwise_kernel", of shape
+/// `[1, 1, channels * depth_multiplier, filters]`, and "bias", of shape
+/// `[filters]`.
+#[derive(Debug, Clone, Copy)]
+pub struct SeparableConv2D {
+    filters: u64,
+    kernel_size: [u64; 2],
+    depth_multiplier: u64,
+    strides: [u64; 2],
+    padding: Padding,
+    activation: Activation,
+    use_bias: bool,
+}
+
+impl SeparableConv2D {
+    /// Creates a layer with `filters` output channels and a depthwise kernel
+    /// of `[height, width]` pixels with a depth multiplier of 1, moved one
+    /// pixel at a time with `Padding::Valid`, no activation and a bias.
+    pub fn new(filters: u64, kernel_size: [u64; 2]) -> Self {
+        Self {
+            filters,
+            kernel_size,
+            depth_multiplier: 1,
+            strides: [1, 1],
+            padding: Padding::Valid,
+            activation: Activation::Linear,
+            use_bias: true,
+        }
+    }
+
+    /// Sets the number of channels the depthwise convolution computes from
+    /// each input channel.  Default is 1.
+    pub fn with_depth_multiplier(self, depth_multiplier: u64) -> Self {
+        Self {
+            depth_multiplier,
+            ..self
+        }
+    }
+
+    /// Sets how many pixels the depthwise kernel moves along the height and
+    /// width.  Default is `[1, 1]`.
+    pub fn with_strides(self, strides: [u64; 2]) -> Self {
+        Self { strides, ..self }
+    }
+
+    /// Sets the padding of the depthwise convolution.  Default is
+    /// `Padding::Valid`.
+    pub fn with_padding(self, padding: Padding) -> Self {
+        Self { padding, ..self }
+    }
+
+    /// Sets the activation function.  Default is `Activation::Linear`.
+    pub fn with_activation(self, activation: Activation) -> Self {
+        Self { activation, ..self }
+    }
+
+    /// Sets whether the layer adds a bias.  Default is true.
+    pub fn with_bias(self, use_bias: bool) -> Self {
+        Self { use_bias, ..self }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  The number of channels of `input` must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "a separable 2D convolution", &input, 4, false)?;
+        if self.filters == 0 || self.depth_multiplier == 0 {
+            return Err(invalid_arg!(
+                "A separable 2D convolution needs at least one filter and a positive depth \
+                 multiplier, but has {} filters and a depth multiplier of {}",
+                self.filters,
+                self.depth_multiplier
+            ));
+        }
+        check_conv("a separable 2D convolution", self.kernel_size, self.strides)?;
+        let channels = dims[3].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("separable_conv2d");
+        let input = compute_input(&mut scope, input)?;
+        let depthwise = depthwise_kernel(
+            &mut scope,
+            self.kernel_size,
+            channels,
+            self.depth_multiplier,
+        )?;
+        let depth = channels * self.depth_multiplier;
+        let shape = [1, 1, depth, self.filters];
+        let initial_kernel = glorot_uniform(&mut scope, &shape, depth, self.filters)?;
+        let pointwise = add_variable(&mut scope, "pointwise_kernel", &shape, initial_kernel, true)?;
+        let bias = add_bias(&mut scope, self.filters, self.use_bias)?;
+        let depthwise = depthwise.compute_output(&mut scope)?;
+        let pointwise = pointwise.compute_output(&mut scope)?;
+        let convolved = depthwise_conv2d(
+            &mut scope,
+            input,
+            depthwise,
+            [
+                self.kernel_size[0],
+                self.kernel_size[1],
+                channels,
+                self.depth_multiplier,
+            ],
+            self.strides,
+            self.padding,
+        )?;
+        let mixed = ops::conv2d(&mut scope, convolved, pointwise, [1, 1], "VALID")?;
+        finish(&mut scope, mixed, bias.as_ref(), self.activation)
+    }
+}
+
+/// A dropout layer, which during training sets each element of its input to
+/// zero with probability `rate` and scales the others by `1 / (1 - rate)`,
+/// so the expected value is unchanged.  Otherwise, it passes its input
+/// through.
+#[derive(Debug, Clone, Copy)]
+pub struct Dropout {
+    rate: f32,
+}
+
+impl Dropout {
+    /// Creates a layer dropping elements with probability `rate`.
+    pub fn new(rate: f32) -> Self {
+        Self { rate }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  `training` is a scalar bool tensor saying whether to drop
+    /// elements.
+    pub fn build(&self, scope: &mut Scope, input: Output, training: Output) -> Result<Output> {
+        check_float("dropout", &input)?;
+        if !(0.0..1.0).contains(&self.rate) {
+            return Err(invalid_arg!(
+                "The dropout rate must be in [0, 1), but is {}",
+                self.rate
+            ));
+        }
+        let mut scope = scope.new_sub_scope("dropout");
+        let input = compute_input(&mut scope, input)?;
+        let data_type = scope.compute_dtype();
+        let shape = ops::shape(&mut scope, input.clone())?;
+        let uniform = ops::RandomUniform::new()
+            .dtype(data_type)
+            .build(&mut scope, shape)?;
+        // floor(u + 1 - rate) is 1 exactly when u >= rate.
+        let keep_prob = scalar(&mut scope, 1.0 - self.rate, data_type)?;
+        let shifted = ops::add(&mut scope, uniform, keep_prob.clone())?;
+        let keep = ops::floor(&mut scope, shifted)?;
+        let mask = ops::divide(&mut scope, keep, keep_prob)?;
+        let dropped = ops::multiply(&mut scope, input.clone(), mask)?;
+        Ok(ops::select(&mut scope, training, dropped, input)?.into())
+    }
+}
+
+/// A batch normalization layer, which normalizes each channel (the last
+/// dimension) of its input to zero mean and unit variance, then scales it by
+/// `gamma` and shifts it by `beta`.
+///
+/// During training, it normalizes with the mean and variance of the batch,
+/// and updates moving averages of them, which are used instead otherwise.
+/// It creates the trainable variables "gamma" and "beta" and the variables
+/// "moving_mean" and "moving_variance", all of shape `[channels]`.
+#[derive(Debug, Clone, Copy)]
+pub struct BatchNormalization {
+    momentum: f32,
+    epsilon: f32,
+    center: bool,
+    scale: bool,
+}
+
+impl Default for BatchNormalization {
+    fn default() -> Self {
+        Self {
+            momentum: 0.99,
+            epsilon: 1e-3,
+            center: true,
+            scale: true,
+        }
+    }
+}
+
+impl BatchNormalization {
+    /// Creates a layer with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets how much of the previous moving averages is kept on each update.
+    /// Default is 0.99.
+    pub fn with_momentum(self, momentum: f32) -> Self {
+        Self { momentum, ..self }
+    }
+
+    /// Sets the small value added to the variance to avoid dividing by zero.
+    /// Default is 0.001.
+    pub fn with_epsilon(self, epsilon: f32) -> Self {
+        Self { epsilon, ..self }
+    }
+
+    /// Sets whether the layer shifts by `beta`.  Default is true.
+    pub fn with_center(self, center: bool) -> Self {
+        Self { center, ..self }
+    }
+
+    /// Sets whether the layer scales by `gamma`.  Default is true.  This can
+    /// be disabled if the next layer is linear or ReLU, since it can do the
+    /// scaling.
+    pub fn with_scale(self, scale: bool) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  `training` is a scalar bool tensor saying whether to
+    /// normalize with the batch statistics and update the moving averages.
+    /// The rank and the number of channels of `input` must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output, training: Output) -> Result<Output> {
+        let dims = input_dims(scope, "batch normalization", &input, 1, true)?;
+        if !(0.0..=1.0).contains(&self.momentum) {
+            return Err(invalid_arg!(
+                "The momentum of batch normalization must be in [0, 1], but is {}",
+                self.momentum
+            ));
+        }
+        if self.epsilon.is_nan() || self.epsilon <= 0.0 {
+            return Err(invalid_arg!(
+                "The epsilon of batch normalization must be positive, but is {}",
+                self.epsilon
+            ));
+        }
+        let channels = dims[dims.len() - 1].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("batch_normalization");
+        let scope = &mut scope;
+        let input = compute_input(scope, input)?;
+        let compute_dtype = scope.compute_dtype();
+        let variable_dtype = scope.variable_dtype();
+        let shape = [channels];
+        let gamma = if self.scale {
+            let ones = filled(scope, &shape, 1.0)?;
+            Some(add_variable(scope, "gamma", &shape, ones, true)?)
+        } else {
+            None
+        };
+        let beta = if self.center {
+            let zeros = filled(scope, &shape, 0.0)?;
+            Some(add_variable(scope, "beta", &shape, zeros, true)?)
+        } else {
+            None
+        };
+        let zeros = filled(scope, &shape, 0.0)?;
+        let moving_mean = add_variable(scope, "moving_mean", &shape, zeros, false)?;
+        let ones = filled(scope, &shape, 1.0)?;
+        let moving_variance = add_variable(scope, "moving_variance", &shape, ones, false)?;
+
+        // The statistics over all dimensions but the last.
+        let axes: Vec<i32> = (0..dims.len() as i32 - 1).collect();
+        let axes = ops::constant(scope, &axes[..])?;
+        let batch_mean: Output = ops::mean(scope, input.clone(), axes.clone())?.into();
+        let centered = ops::subtract(scope, input.clone(), batch_mean.clone())?;
+        let squared = ops::square(scope, centered)?;
+        let batch_variance: Output = ops::mean(scope, squared, axes)?.into();
+
+        // Moving the averages towards the batch statistics by (1 - momentum)
+        // during training, and not at all otherwise, avoids a conditional.
+        let one_minus_momentum = scalar(scope, 1.0 - self.momentum, variable_dtype)?;
+        let zero = scalar(scope, 0.0, variable_dtype)?;
+        let decay = ops::select(scope, training.clone(), one_minus_momentum, zero)?;
+        let mut updates = Vec::with_capacity(2);
+        for (average, batch) in &[
+            (&moving_mean, &batch_mean),
+            (&moving_variance, &batch_variance),
+        ] {
+            let batch = cast(scope, (*batch).clone(), variable_dtype)?;
+            let difference = ops::subtract(scope, average.output.clone(), batch)?;
+            let step = ops::multiply(scope, difference, decay.clone())?;
+            let updated = ops::subtract(scope, average.output.clone(), step)?;
+            updates.push(ops::assign(scope, average.output.clone(), updated)?);
+        }
+
+        let moving_mean = moving_mean.compute_output(scope)?;
+        let mean = ops::select(scope, training.clone(), batch_mean, moving_mean)?;
+        let moving_variance = moving_variance.compute_output(scope)?;
+        let variance = ops::select(scope, training, batch_variance, moving_variance)?;
+        let epsilon = scalar(scope, self.epsilon, compute_dtype)?;
+        let variance = ops::add(scope, variance, epsilon)?;
+        let mut factor: Output = ops::rsqrt(scope, variance)?.into();
+        if let Some(gamma) = &gamma {
+            let gamma = gamma.compute_output(scope)?;
+            factor = ops::multiply(scope, factor, gamma)?.into();
+        }
+        let centered = ops::subtract(scope, input, mean)?;
+        let mut normalized: Output = ops::multiply(scope, centered, factor)?.into();
+        if let Some(beta) = &beta {
+            let beta = beta.compute_output(scope)?;
+            normalized = ops::add(scope, normalized, beta)?.into();
+        }
+        // Running the layer updates the moving averages.
+        let mut identity = ops::Identity::new();
+        for update in updates {
+            identity = identity.add_control_input(update);
+        }
+        Ok(identity.build(scope, normalized)?.into())
+    }
+}
+
+/// Returns the int32 vector `dims` with its first element replaced by
+/// element `index` of the int32 vector `shape`, known only at run time, e.g.
+/// to reshape a tensor while keeping its batch size.  There is no concat op,
+/// so `dims[0]` must be 0 and the element is added to it instead.
+fn with_runtime_dim(scope: &mut Scope, shape: Output, index: i32, dims: &[i32]) -> Result<Output> {
+    let begin = ops::constant(scope, &[index][..])?;
+    let size = ops::constant(scope, &[1][..])?;
+    let dim = ops::slice(scope, shape, begin, size)?;
+    let mut mask = vec![0; dims.len()];
+    mask[0] = 1;
+    let mask = ops::constant(scope, &mask[..])?;
+    let dim = ops::multiply(scope, dim, mask)?;
+    let dims = ops::constant(scope, dims)?;
+    Ok(ops::add(scope, dim, dims)?.into())
+}
+
+/// Normalizes each of `groups` groups of consecutive channels of each
+/// example in `input` to zero mean and unit variance.
+fn normalize_groups(
+    scope: &mut Scope,
+    layer: &str,
+    input: Output,
+    channels: u64,
+    groups: u64,
+    epsilon: f32,
+) -> Result<Output> {
+    if epsilon.is_nan() || epsilon <= 0.0 {
+        return Err(invalid_arg!(
+            "The epsilon of {} must be positive, but is {}",
+            layer,
+            epsilon
+        ));
+    }
+    // Reshaping to [batch, pixels, groups, channels / groups] puts the
+    // values of each group of each example along the axes 1 and 3.
+    let input_shape: Output = ops::shape(scope, input.clone())?.into();
+    let grouped_shape = with_runtime_dim(
+        scope,
+        input_shape.clone(),
+        0,
+        &[0, -1, groups as i32, (channels / groups) as i32],
+    )?;
+    let grouped = ops::reshape(scope, input, grouped_shape)?;
+
+    let axes = ops::constant(scope, &[1, 3][..])?;
+    let mean = ops::Mean::new()
+        .keep_dims(true)
+        .build(scope, grouped.clone(), axes.clone())?;
+    let centered = ops::subtract(scope, grouped, mean)?;
+    let squared = ops::square(scope, centered.clone())?;
+    let variance = ops::Mean::new()
+        .keep_dims(true)
+        .build(scope, squared, axes)?;
+    let data_type = scope.compute_dtype();
+    let epsilon = scalar(scope, epsilon, data_type)?;
+    let variance = ops::add(scope, variance, epsilon)?;
+    let factor = ops::rsqrt(scope, variance)?;
+    let normalized = ops::multiply(scope, centered, factor)?;
+    Ok(ops::reshape(scope, normalized, input_shape)?.into())
+}
+
+/// Scales the channels (the last dimension) of `x` by a "gamma" variable if
+/// `scale`, and shifts them by a "beta" variable if `center`.
+fn scale_and_center(
+    scope: &mut Scope,
+    x: Output,
+    channels: u64,
+    center: bool,
+    scale: bool,
+) -> Result<Output> {
+    let shape = [channels];
+    let mut x = x;
+    if scale {
+        let ones = filled(scope, &shape, 1.0)?;
+        let gamma = add_variable(scope, "gamma", &shape, ones, true)?;
+        let gamma = gamma.compute_output(scope)?;
+        x = ops::multiply(scope, x, gamma)?.into();
+    }
+    if center {
+        let zeros = filled(scope, &shape, 0.0)?;
+        let beta = add_variable(scope, "beta", &shape, zeros, true)?;
+        let beta = beta.compute_output(scope)?;
+        x = ops::add(scope, x, beta)?.into();
+    }
+    Ok(x)
+}
+
+/// A group normalization layer, which splits the channels (the last
+/// dimension) of each example into groups, normalizes each group to zero
+/// mean and unit variance over the channels and all other dimensions but
+/// the first, then scales the result by `gamma` and shifts it by `beta`.
+///
+/// Unlike `BatchNormalization`, the statistics don't depend on the other
+/// examples of the batch, so it works with small batches and behaves the
+/// same during training and otherwise.  It creates the trainable variables
+/// "gamma" and "beta", of shape `[channels]`.
+#[derive(Debug, Clone, Copy)]
+pub struct GroupNormalization {
+    groups: u64,
+    epsilon: f32,
+    center: bool,
+    scale: bool,
+}
+
+impl GroupNormalization {
+    /// Creates a layer splitting the channels into `groups` groups, which
+    /// must divide the number of channels.
+    pub fn new(groups: u64) -> Self {
+        Self {
+            groups,
+            epsilon: 1e-3,
+            center: true,
+            scale: true,
+        }
+    }
+
+    /// Sets the small value added to the variance to avoid dividing by zero.
+    /// Default is 0.001.
+    pub fn with_epsilon(self, epsilon: f32) -> Self {
+        Self { epsilon, ..self }
+    }
+
+    /// Sets whether the layer shifts by `beta`.  Default is true.
+    pub fn with_center(self, center: bool) -> Self {
+        Self { center, ..self }
+    }
+
+    /// Sets whether the layer scales by `gamma`.  Default is true.
+    pub fn with_scale(self, scale: bool) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// Adds the layer applied to `input`, of shape `[batch, ..., channels]`,
+    /// to the graph, and returns its output.  The rank and the number of
+    /// channels of `input` must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "group normalization", &input, 2, true)?;
+        let channels = dims[dims.len() - 1].unwrap() as u64;
+        if self.groups == 0 || !channels.is_multiple_of(self.groups) {
+            return Err(invalid_arg!(
+                "The number of groups of group normalization must divide the {} channels, \
+                 but is {}",
+                channels,
+                self.groups
+            ));
+        }
+        let mut scope = scope.new_sub_scope("group_normalization");
+        let input = compute_input(&mut scope, input)?;
+        let normalized = normalize_groups(
+            &mut scope,
+            "group normalization",
+            input,
+            channels,
+            self.groups,
+            self.epsilon,
+        )?;
+        scale_and_center(&mut scope, normalized, channels, self.center, self.scale)
+    }
+}
+
+/// An instance normalization layer, which normalizes each channel (the last
+/// dimension) of each example to zero mean and unit variance over the other
+/// dimensions but the first, then scales the result by `gamma` and shifts it
+/// by `beta`.  This is group normalization with one channel per group, and
+/// is commonly used for style transfer and image generation.
+///
+/// It creates the trainable variables "gamma" and "beta", of shape
+/// `[channels]`.
+#[derive(Debug, Clone, Copy)]
+pub struct InstanceNormalization {
+    epsilon: f32,
+    center: bool,
+    scale: bool,
+}
+
+impl Default for InstanceNormalization {
+    fn default() -> Self {
+        Self {
+            epsilon: 1e-3,
+            center: true,
+            scale: true,
+        }
+    }
+}
+
+impl InstanceNormalization {
+    /// Creates a layer with the default settings.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the small value added to the variance to avoid dividing by zero.
+    /// Default is 0.001.
+    pub fn with_epsilon(self, epsilon: f32) -> Self {
+        Self { epsilon, ..self }
+    }
+
+    /// Sets whether the layer shifts by `beta`.  Default is true.
+    pub fn with_center(self, center: bool) -> Self {
+        Self { center, ..self }
+    }
+
+    /// Sets whether the layer scales by `gamma`.  Default is true.
+    pub fn with_scale(self, scale: bool) -> Self {
+        Self { scale, ..self }
+    }
+
+    /// Adds the layer applied to `input`, of shape `[batch, ..., channels]`
+    /// with at least one dimension between the batch and the channels, to
+    /// the graph, and returns its output.  The rank and the number of
+    /// channels of `input` must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "instance normalization", &input, 3, true)?;
+        let channels = dims[dims.len() - 1].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("instance_normalization");
+        let input = compute_input(&mut scope, input)?;
+        let normalized = normalize_groups(
+            &mut scope,
+            "instance normalization",
+            input,
+            channels,
+            channels,
+            self.epsilon,
+        )?;
+        scale_and_center(&mut scope, normalized, channels, self.center, self.scale)
+    }
+}
+
+/// A layer which reshapes its input of shape `[batch, ...]` into a matrix of
+/// shape `[batch, features]`, e.g. to pass the output of a convolution to a
+/// `Dense` layer.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Flatten {}
+
+impl Flatten {
+    /// Creates a layer.
+    pub fn new() -> Self {
+        Self {}
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  All dimensions of `input` but the first must be known.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = match ops::known_dims(scope, &input)? {
+            Some(dims) if !dims.is_empty() => dims,
+            dims => {
+                return Err(invalid_arg!(
+                    "The input of flatten must have known rank of at least one, but has shape {:?}",
+                    dims
+                ))
+            }
+        };
+        let mut features = 1;
+        for dim in &dims[1..] {
+            match dim {
+                Some(dim) => features *= dim,
+                None => {
+                    return Err(invalid_arg!(
+                        "All dimensions but the first of the input of flatten must be known, \
+                         but it has shape {:?}",
+                        dims
+                    ))
+                }
+            }
+        }
+        let mut scope = scope.new_sub_scope("flatten");
+        let shape = ops::constant(&mut scope, &[-1, features][..])?;
+        Ok(ops::reshape(&mut scope, input, shape)?.into())
+    }
+}
+
+/// Returns the sinusoidal position encoding of "Attention Is All You Need"
+/// as a constant float tensor of shape `[length, dimension]`, whose row `pos`
+/// holds `sin(pos / 10000^(2i / dimension))` in column `2i` and `cos(pos /
+/// 10000^(2i / dimension))` in column `2i + 1`.
+///
+/// Adding it to the embeddings of a sequence tells a model where each token
+/// is without adding any variables, and it generalizes to positions whose
+/// differences weren't seen during training.
+pub fn sinusoidal_position_encoding(
+    scope: &mut Scope,
+    length: u64,
+    dimension: u64,
+) -> Result<Output> {
+    if length == 0 || dimension == 0 {
+        return Err(invalid_arg!(
+            "A position encoding needs a positive length and dimension, but has length {} \
+             and dimension {}",
+            length,
+            dimension
+        ));
+    }
+    let mut values = Vec::with_capacity((length * dimension) as usize);
+    for pos in 0..length {
+        for i in 0..dimension {
+            let exponent = (i - i % 2) as f64 / dimension as f64;
+            let angle = pos as f64 / 10000f64.powf(exponent);
+            values.push(if i % 2 == 0 { angle.sin() } else { angle.cos() } as f32);
+        }
+    }
+    let encoding = Tensor::new(&[length, dimension]).with_values(&values)?;
+    Ok(ops::constant(&mut scope.with_op_name("position_encoding"), encoding)?.into())
+}
+
+/// Returns the first `length` rows of `table`, where `length` is the second
+/// dimension of `input` at run time.
+fn leading_rows(scope: &mut Scope, table: Output, input: Output) -> Result<Output> {
+    let input_shape = ops::shape(scope, input)?;
+    let size = with_runtime_dim(scope, input_shape.into(), 1, &[0, -1])?;
+    let begin = ops::constant(scope, &[0, 0][..])?;
+    Ok(ops::slice(scope, table, begin, size)?.into())
+}
+
+/// An embedding layer, which maps integer ids in `[0, input_dim)` to dense
+/// vectors of size `output_dim`, e.g. to represent the tokens of a sequence.
+/// The output has the shape of the ids with `output_dim` appended.
+///
+/// It creates the variable "embeddings", of shape `[input_dim, output_dim]`,
+/// initialized uniformly from `[-0.05, 0.05]`.
+#[derive(Debug, Clone, Copy)]
+pub struct Embedding {
+    input_dim: u64,
+    output_dim: u64,
+}
+
+impl Embedding {
+    /// Creates a layer mapping `input_dim` ids, such as the size of a
+    /// vocabulary, to vectors of size `output_dim`.
+    pub fn new(input_dim: u64, output_dim: u64) -> Self {
+        Self {
+            input_dim,
+            output_dim,
+        }
+    }
+
+    /// Adds the layer applied to the int32 or int64 tensor `ids` to the
+    /// graph, and returns its output.
+    pub fn build(&self, scope: &mut Scope, ids: Output) -> Result<Output> {
+        let data_type = ids.operation.output_type(ids.index as usize);
+        if data_type != DataType::Int32 && data_type != DataType::Int64 {
+            return Err(invalid_arg!(
+                "The ids of an embedding must be int32 or int64, but are {}",
+                data_type
+            ));
+        }
+        if self.input_dim == 0 || self.output_dim == 0 {
+            return Err(invalid_arg!(
+                "An embedding needs positive input and output dimensions, but has {} and {}",
+                self.input_dim,
+                self.output_dim
+            ));
+        }
+        let mut scope = scope.new_sub_scope("embedding");
+        let shape = [self.input_dim, self.output_dim];
+        let initial_embeddings = random_uniform(&mut scope, &shape, 0.05)?;
+        let embeddings = add_variable(&mut scope, "embeddings", &shape, initial_embeddings, true)?;
+        let axis = ops::constant(&mut scope, 0)?;
+        let embeddings = embeddings.compute_output(&mut scope)?;
+        Ok(ops::gather(&mut scope, embeddings, ids, axis)?.into())
+    }
+}
+
+/// A learned position embedding layer, which adds a trained vector to each
+/// position of a sequence of embeddings of shape `[batch, length,
+/// dimension]`.  Unlike `sinusoidal_position_encoding`, it can't handle
+/// sequences longer than `max_length`, which fail at run time.
+///
+/// It creates the variable "embeddings", of shape `[max_length,
+/// dimension]`.
+#[derive(Debug, Clone, Copy)]
+pub struct PositionEmbedding {
+    max_length: u64,
+}
+
+impl PositionEmbedding {
+    /// Creates a layer for sequences of up to `max_length` positions.
+    pub fn new(max_length: u64) -> Self {
+        Self { max_length }
+    }
+
+    /// Adds the layer applied to `input` to the graph, and returns its
+    /// output.  The dimension of `input` must be known, but its batch size
+    /// and length may vary.
+    pub fn build(&self, scope: &mut Scope, input: Output) -> Result<Output> {
+        let dims = input_dims(scope, "a position embedding", &input, 3, false)?;
+        if self.max_length == 0 {
+            return Err(invalid_arg!(
+                "A position embedding needs a positive maximum length"
+            ));
+        }
+        let dimension = dims[2].unwrap() as u64;
+        let mut scope = scope.new_sub_scope("position_embedding");
+        let input = compute_input(&mut scope, input)?;
+        let shape = [self.max_length, dimension];
+        let initial_embeddings = glorot_uniform(&mut scope, &shape, self.max_length, dimension)?;
+        let embeddings = add_variable(&mut scope, "embeddings", &shape, initial_embeddings, true)?;
+        let embeddings = embeddings.compute_output(&mut scope)?;
+        let positions = leading_rows(&mut scope, embeddings, input.clone())?;
+        Ok(ops::add(&mut scope, input, positions)?.into())
+    }
+}
+
+/// A layer which embeds a batch of token id sequences of shape `[batch,
+/// length]` with an `Embedding`, and adds either a learned
+/// `PositionEmbedding` or the `sinusoidal_position_encoding`, giving the
+/// input of a transformer encoder of shape `[batch, length, dimension]`.
+#[derive(Debug, Clone, Copy)]
+pub struct TokenAndPositionEmbedding {
+    vocabulary_size: u64,
+    max_length: u64,
+    dimension: u64,
+    sinusoidal: bool,
+}
+
+impl TokenAndPositionEmbedding {
+    /// Creates a layer for `vocabulary_size` tokens and sequences of up to
+    /// `max_length` tokens, embedded into vectors of size `dimension`, with
+    /// learned position embeddings.
+    pub fn new(vocabulary_size: u64, max_length: u64, dimension: u64) -> Self {
+        Self {
+            vocabulary_size,
+            max_length,
+            dimension,
+            sinusoidal: false,
+        }
+    }
+
+    /// Sets whether to add the fixed sinusoidal position encoding instead of
+    /// learned position embeddings.  Default is false.
+    pub fn with_sinusoidal(self, sinusoidal: bool) -> Self {
+        Self { sinusoidal, ..self }
+    }
+
+    /// Adds the layer applied to `ids` to the graph, and returns its output.
+    pub fn build(&self, scope: &mut Scope, ids: Output) -> Result<Output> {
+        match ops::known_dims(scope, &ids)? {
+            Some(ref dims) if dims.len() == 2 => {}
+            dims => {
+                return Err(invalid_arg!(
+                    "The ids of a token and position embedding must have 2 dimensions, but \
+                     have shape {:?}",
+                    dims
+                ))
+            }
+        }
+        let mut scope = scope.new_sub_scope("token_and_position_embedding");
+        let tokens = Embedding::new(self.vocabulary_size, self.dimension).build(&mut scope, ids)?;
+        if self.sinusoidal {
+            let encoding =
+                sinusoidal_position_encoding(&mut scope, self.max_length, self.dimension)?;
+            let data_type = scope.compute_dtype();
+            let encoding = cast(&mut scope, encoding, data_type)?;
+            let positions = leading_rows(&mut scope, encoding, tokens.clone())?;
+            Ok(ops::add(&mut scope, tokens, positions)?.into())
+        } else {
+            PositionEmbedding::new(self.max_length).build(&mut scope, tokens)
+        }
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::train::GradientDescentOptimizer;
+    use crate::train::MinimizeOptions;
+    use crate::train::Optimizer;
+    use crate::DTypePolicy;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    /// Initializes the variables of `scope` and fetches `outputs`, feeding
+    /// `training` if given.
+    fn run(
+        scope: &Scope,
+        outputs: &[&Output],
+        training: Option<(&Output, bool)>,
+    ) -> Vec<Tensor<f32>> {
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        for variable in scope.variables() {
+            init.add_target(variable.initializer());
+        }
+        session.run(&mut init).unwrap();
+        let training_value = training.map(|(_, value)| Tensor::from(value));
+        let mut args = SessionRunArgs::new();
+        if let (Some((placeholder, _)), Some(value)) = (training, &training_value) {
+            args.add_feed(&placeholder.operation, placeholder.index, value);
+        }
+        let tokens: Vec<_> = outputs
+            .iter()
+            .map(|output| args.request_fetch(&output.operation, output.index))
+            .collect();
+        session.run(&mut args).unwrap();
+        tokens
+            .into_iter()
+            .map(|token| args.fetch(token).unwrap())
+            .collect()
+    }
+
+    fn training_placeholder(scope: &mut Scope) -> Output {
+        ops::Placeholder::new()
+            .data_type(DataType::Bool)
+            .shape(Shape::from(Some(vec![])))
+            .build(&mut scope.with_op_name("training"))
+            .unwrap()
+            .into()
+    }
+
+    /// Takes one gradient descent step on the sum of the squares of
+    /// `output`, and checks that it changes every trainable variable of
+    /// `scope`, i.e. that the layers computing `output` can be trained.
+    fn minimize_once(scope: &mut Scope, output: Output) {
+        let squares = ops::square(scope, output).unwrap();
+        let axes = ops::constant(scope, &[0, 1, 2, 3][..]).unwrap();
+        let loss = ops::sum(scope, squares, axes).unwrap();
+        let variables = scope.trainable_variables();
+        let learning_rate = ops::constant(scope, 0.1f32).unwrap();
+        let (_, minimize) = GradientDescentOptimizer::new(learning_rate.into())
+            .minimize(
+                scope,
+                loss.into(),
+                MinimizeOptions::default().with_variables(&variables),
+            )
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        for variable in &variables {
+            init.add_target(variable.initializer());
+        }
+        session.run(&mut init).unwrap();
+        let fetch_variables = || {
+            let mut args = SessionRunArgs::new();
+            let tokens: Vec<_> = variables
+                .iter()
+                .map(|v| args.request_fetch(&v.output().operation, v.output().index))
+                .collect();
+            session.run(&mut args).unwrap();
+            tokens
+                .into_iter()
+                .map(|token| args.fetch::<f32>(token).unwrap())
+                .collect::<Vec<_>>()
+        };
+        let before = fetch_variables();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&minimize);
+        session.run(&mut args).unwrap();
+        let after = fetch_variables();
+        for (variable, (before, after)) in variables.iter().zip(before.iter().zip(&after)) {
+            assert!(
+                before.iter().zip(after.iter()).any(|(b, a)| b != a),
+                "{} wasn't trained",
+                variable.name()
+            );
+        }
+    }
+
+    #[test]
+    fn dense() {
+        let mut scope = Scope::new_root_scope();
+        let x: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, -2.0, 3.0, 0.5, 0.0, -1.0])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let y = Dense::new(2)
+            .with_activation(Activation::Relu)
+            .build(&mut scope, x)
+            .unwrap();
+        let variables = scope.trainable_variables();
+        let names: Vec<&str> = variables.iter().map(|v| v.name()).collect();
+        assert_eq!(names, vec!["dense/kernel", "dense/bias"]);
+        let values = run(&scope, &[&y, variables[0].output()], None);
+        let (y, kernel) = (&values[0], &values[1]);
+        assert_eq!(y.dims(), &[2, 2]);
+        let x = [[1.0f32, -2.0, 3.0], [0.5, 0.0, -1.0]];
+        for i in 0..2 {
+            for j in 0..2 {
+                let product: f32 = (0..3).map(|k| x[i][k] * kernel[k * 2 + j]).sum();
+                assert!((y[i * 2 + j] - product.max(0.0)).abs() < 1e-5);
+            }
+        }
+
+        let unknown: Output = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .build(&mut scope.with_op_name("unknown"))
+            .unwrap()
+            .into();
+        assert!(Dense::new(2).build(&mut scope, unknown).is_err());
+    }
+
+    #[test]
+    fn dense_mixed_precision() {
+        let mut scope = Scope::new_root_scope().with_dtype_policy(DTypePolicy::mixed_float16());
+        let x: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, -2.0, 3.0, 0.5, 0.0, -1.0])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let y = Dense::new(2).build(&mut scope, x).unwrap();
+        // The layer computes in half precision, but keeps its variables in
+        // single precision.
+        assert_eq!(y.operation.output_type(y.index as usize), DataType::Half);
+        let variables = scope.trainable_variables();
+        assert_eq!(variables.len(), 2);
+        for variable in &variables {
+            assert_eq!(variable.data_type(), DataType::Float);
+        }
+        let y: Output = ops::Cast::new()
+            .dst_type(DataType::Float)
+            .build(&mut scope, y)
+            .unwrap()
+            .into();
+        let values = run(&scope, &[&y, variables[0].output()], None);
+        let (y, kernel) = (&values[0], &values[1]);
+        let x = [[1.0f32, -2.0, 3.0], [0.5, 0.0, -1.0]];
+        for i in 0..2 {
+            for j in 0..2 {
+                let product: f32 = (0..3).map(|k| x[i][k] * kernel[k * 2 + j]).sum();
+                assert!((y[i * 2 + j] - product).abs() < 1e-2);
+            }
+        }
+    }
+
+    #[test]
+    fn conv2d() {
+        let mut scope = Scope::new_root_scope();
+        let images: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[1, 4, 4, 1])
+                .with_values(&[1.0f32; 16])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let valid = Conv2D::new(2, [3, 3])
+            .with_bias(false)
+            .build(&mut scope, images.clone())
+            .unwrap();
+        let strided = Conv2D::new(3, [2, 2])
+            .with_strides([2, 2])
+            .with_padding(Padding::Same)
+            .build(&mut scope, images.clone())
+            .unwrap();
+        let flat = Flatten::new().build(&mut scope, strided.clone()).unwrap();
+        assert_eq!(scope.trainable_variables().len(), 3);
+        let kernel = scope.trainable_variables()[0].output().clone();
+        let values = run(&scope, &[&valid, &strided, &flat, &kernel], None);
+        assert_eq!(values[0].dims(), &[1, 2, 2, 2]);
+        assert_eq!(values[1].dims(), &[1, 2, 2, 3]);
+        assert_eq!(values[2].dims(), &[1, 12]);
+        // With an input of ones, each output is the sum of the filter.
+        let sum: f32 = values[3].iter().step_by(2).sum();
+        assert!((values[0][0] - sum).abs() < 1e-5);
+
+        let vectors: Output = ops::constant(&mut scope, &[1.0f32, 2.0][..])
+            .unwrap()
+            .into();
+        assert!(Conv2D::new(2, [3, 3]).build(&mut scope, vectors).is_err());
+        assert!(Conv2D::new(2, [0, 3]).build(&mut scope, images).is_err());
+    }
+
+    #[test]
+    fn conv2d_transpose() {
+        let mut scope = Scope::new_root_scope();
+        let pixel: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[1, 1, 1, 1]).with_values(&[1.0f32]).unwrap(),
+        )
+        .unwrap()
+        .into();
+        let spread = Conv2DTranspose::new(1, [3, 3])
+            .with_bias(false)
+            .build(&mut scope, pixel)
+            .unwrap();
+        let images: Output = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None, None, None, Some(1)])))
+            .build(&mut scope.with_op_name("images"))
+            .unwrap()
+            .into();
+        let same = Conv2DTranspose::new(3, [2, 2])
+            .with_strides([2, 2])
+            .with_padding(Padding::Same)
+            .build(&mut scope, images.clone())
+            .unwrap();
+        let valid = Conv2DTranspose::new(2, [3, 3])
+            .with_strides([2, 2])
+            .build(&mut scope, images.clone())
+            .unwrap();
+        // The layers are built from ops which have gradients, so compare
+        // them with the native op.
+        let variables = scope.trainable_variables();
+        let same_shape = ops::constant(&mut scope, &[2, 6, 6, 3][..]).unwrap();
+        let native_same = ops::conv2d_transpose(
+            &mut scope,
+            images.clone(),
+            variables[1].output().clone(),
+            same_shape.into(),
+            [2, 2],
+            "SAME",
+        )
+        .unwrap();
+        let valid_shape = ops::constant(&mut scope, &[2, 7, 7, 2][..]).unwrap();
+        let native_valid = ops::conv2d_transpose(
+            &mut scope,
+            images.clone(),
+            variables[3].output().clone(),
+            valid_shape.into(),
+            [2, 2],
+            "VALID",
+        )
+        .unwrap();
+        let names: Vec<&str> = variables.iter().map(|v| v.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "conv2d_transpose/kernel",
+                "conv2d_transpose_1/kernel",
+                "conv2d_transpose_1/bias",
+                "conv2d_transpose_2/kernel",
+                "conv2d_transpose_2/bias",
+            ]
+        );
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        for variable in &variables {
+            init.add_target(variable.initializer());
+        }
+        session.run(&mut init).unwrap();
+        let values: Vec<f32> = (0..18).map(|i| i as f32).collect();
+        let input = Tensor::new(&[2, 3, 3, 1]).with_values(&values).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&images.operation, 0, &input);
+        let spread_token = args.request_fetch(&spread.operation, spread.index);
+        let kernel_token = args.request_fetch(&variables[0].output().operation, 0);
+        let same_token = args.request_fetch(&same.operation, same.index);
+        let valid_token = args.request_fetch(&valid.operation, valid.index);
+        let native_same_token = args.request_fetch(&native_same.operation, native_same.index);
+        let native_valid_token = args.request_fetch(&native_valid.operation, native_valid.index);
+        session.run(&mut args).unwrap();
+        // Transposing a single pixel of value 1 yields the kernel.
+        let spread: Tensor<f32> = args.fetch(spread_token).unwrap();
+        let kernel: Tensor<f32> = args.fetch(kernel_token).unwrap();
+        assert_eq!(spread.dims(), &[1, 3, 3, 1]);
+        assert_eq!(&spread[..], &kernel[..]);
+        let same: Tensor<f32> = args.fetch(same_token).unwrap();
+        assert_eq!(same.dims(), &[2, 6, 6, 3]);
+        let valid: Tensor<f32> = args.fetch(valid_token).unwrap();
+        assert_eq!(valid.dims(), &[2, 7, 7, 2]);
+        let native_same: Tensor<f32> = args.fetch(native_same_token).unwrap();
+        let native_valid: Tensor<f32> = args.fetch(native_valid_token).unwrap();
+        for (actual, expected) in same.iter().zip(native_same.iter()) {
+            assert!((actual - expected).abs() < 1e-4);
+        }
+        for (actual, expected) in valid.iter().zip(native_valid.iter()) {
+            assert!((actual - expected).abs() < 1e-4);
+        }
+
+        assert!(Conv2DTranspose::new(0, [3, 3])
+            .build(&mut scope, images)
+            .is_err());
+    }
+
+    #[test]
+    fn depthwise_conv2d() {
+        let mut scope = Scope::new_root_scope();
+        let images: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[1, 4, 4, 2])
+                .with_values(&[1.0f32; 32])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let depthwise = DepthwiseConv2D::new([3, 3])
+            .with_depth_multiplier(2)
+            .with_bias(false)
+            .build(&mut scope, images.clone())
+            .unwrap();
+        let separable = SeparableConv2D::new(3, [3, 3])
+            .with_strides([2, 2])
+            .with_padding(Padding::Same)
+            .with_activation(Activation::Relu)
+            .build(&mut scope, images.clone())
+            .unwrap();
+        let variables = scope.trainable_variables();
+        let native = ops::depthwise_conv2d(
+            &mut scope,
+            images.clone(),
+            variables[0].output().clone(),
+            [1, 1],
+            "VALID",
+        )
+        .unwrap();
+        let names: Vec<&str> = variables.iter().map(|v| v.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "depthwise_conv2d/depthwise_kernel",
+                "separable_conv2d/depthwise_kernel",
+                "separable_conv2d/pointwise_kernel",
+                "separable_conv2d/bias",
+            ]
+        );
+        let kernel = variables[0].output().clone();
+        let values = run(&scope, &[&depthwise, &separable, &kernel, &native], None);
+        assert_eq!(values[0].dims(), &[1, 2, 2, 4]);
+        assert_eq!(values[1].dims(), &[1, 2, 2, 3]);
+        assert!(values[1].iter().all(|&v| v >= 0.0));
+        // With an input of ones, each output channel is the sum of its
+        // filter, and output channel `c * 2 + k` uses filter `[.., c, k]`.
+        for channel in 0..4 {
+            let sum: f32 = values[2].iter().skip(channel).step_by(4).sum();
+            assert!((values[0][channel] - sum).abs() < 1e-5);
+        }
+        for (actual, expected) in values[0].iter().zip(values[3].iter()) {
+            assert!((actual - expected).abs() < 1e-5);
+        }
+
+        assert!(DepthwiseConv2D::new([3, 3])
+            .with_depth_multiplier(0)
+            .build(&mut scope, images.clone())
+            .is_err());
+        assert!(SeparableConv2D::new(3, [3, 0])
+            .build(&mut scope, images)
+            .is_err());
+    }
+
+    /// A batch of two 4x4 images with two channels.
+    fn images(scope: &mut Scope) -> Output {
+        let values: Vec<f32> = (0..64).map(|i| (i % 7) as f32 - 3.0).collect();
+        ops::constant(
+            scope,
+            Tensor::new(&[2, 4, 4, 2]).with_values(&values).unwrap(),
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn conv2d_transpose_minimize() {
+        let mut scope = Scope::new_root_scope();
+        let images = images(&mut scope);
+        let output = Conv2DTranspose::new(3, [3, 3])
+            .with_strides([2, 2])
+            .with_padding(Padding::Same)
+            .build(&mut scope, images)
+            .unwrap();
+        minimize_once(&mut scope, output);
+    }
+
+    #[test]
+    fn depthwise_conv2d_minimize() {
+        let mut scope = Scope::new_root_scope();
+        let images = images(&mut scope);
+        let output = DepthwiseConv2D::new([3, 3])
+            .with_depth_multiplier(2)
+            .build(&mut scope, images)
+            .unwrap();
+        minimize_once(&mut scope, output);
+    }
+
+    #[test]
+    fn separable_conv2d_minimize() {
+        let mut scope = Scope::new_root_scope();
+        let images = images(&mut scope);
+        let output = SeparableConv2D::new(3, [3, 3])
+            .with_strides([2, 2])
+            .with_padding(Padding::Same)
+            .build(&mut scope, images)
+            .unwrap();
+        minimize_once(&mut scope, output);
+    }
+
+    #[test]
+    fn dropout() {
+        let mut scope = Scope::new_root_scope();
+        let training = training_placeholder(&mut scope);
+        let x: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[1000]).with_values(&[1.0f32; 1000]).unwrap(),
+        )
+        .unwrap()
+        .into();
+        let y = Dropout::new(0.5)
+            .build(&mut scope, x.clone(), training.clone())
+            .unwrap();
+        let trained = &run(&scope, &[&y], Some((&training, true)))[0];
+        assert!(trained.iter().all(|&v| v == 0.0 || v == 2.0));
+        let kept = trained.iter().filter(|&&v| v == 2.0).count();
+        assert!(kept > 400 && kept < 600, "{}", kept);
+        let inferred = &run(&scope, &[&y], Some((&training, false)))[0];
+        assert!(inferred.iter().all(|&v| v == 1.0));
+
+        assert!(Dropout::new(1.0).build(&mut scope, x, training).is_err());
+    }
+
+    #[test]
+    fn batch_normalization() {
+        let mut scope = Scope::new_root_scope();
+        let training = training_placeholder(&mut scope);
+        let x: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[4, 1])
+                .with_values(&[1.0f32, 2.0, 3.0, 6.0])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let y = BatchNormalization::new()
+            .with_momentum(0.5)
+            .build(&mut scope, x.clone(), training.clone())
+            .unwrap();
+        assert_eq!(scope.trainable_variables().len(), 2);
+        let variables = scope.variables();
+        assert_eq!(variables.len(), 4);
+        assert_eq!(variables[2].name(), "batch_normalization/moving_mean");
+        let moving_mean = variables[2].output().clone();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        for variable in &variables {
+            init.add_target(variable.initializer());
+        }
+        session.run(&mut init).unwrap();
+        let is_training = Tensor::from(true);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&training.operation, 0, &is_training);
+        let y_token = args.request_fetch(&y.operation, y.index);
+        session.run(&mut args).unwrap();
+        let normalized: Tensor<f32> = args.fetch(y_token).unwrap();
+        // The batch has mean 3 and variance 3.5.
+        let expected = [-2.0f32, -1.0, 0.0, 3.0];
+        for (value, expected) in normalized.iter().zip(&expected) {
+            assert!((value - expected / (3.5f32 + 1e-3).sqrt()).abs() < 1e-4);
+        }
+        let mut args = SessionRunArgs::new();
+        let mean_token = args.request_fetch(&moving_mean.operation, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<f32>(mean_token).unwrap()[..], &[1.5]);
+
+        let bad = BatchNormalization::new().with_epsilon(0.0);
+        assert!(bad.build(&mut scope, x, training).is_err());
+    }
+
+    #[test]
+    fn group_normalization() {
+        let mut scope = Scope::new_root_scope();
+        let values: Vec<f32> = (0..16).map(|i| (i * i) as f32).collect();
+        let x: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 2, 4]).with_values(&values).unwrap(),
+        )
+        .unwrap()
+        .into();
+        let y = GroupNormalization::new(2)
+            .build(&mut scope, x.clone())
+            .unwrap();
+        let variables = scope.trainable_variables();
+        let names: Vec<&str> = variables.iter().map(|v| v.name()).collect();
+        assert_eq!(
+            names,
+            vec!["group_normalization/gamma", "group_normalization/beta"]
+        );
+        let y = &run(&scope, &[&y], None)[0];
+        assert_eq!(y.dims(), &[2, 2, 4]);
+        // Each group holds two channels of both pixels of an example.
+        for example in 0..2 {
+            for group in 0..2 {
+                let indices: Vec<usize> = (0..2)
+                    .flat_map(|pixel| (0..2).map(move |c| example * 8 + pixel * 4 + group * 2 + c))
+                    .collect();
+                let mean = indices.iter().map(|&i| values[i]).sum::<f32>() / 4.0;
+                let variance = indices
+                    .iter()
+                    .map(|&i| (values[i] - mean).powi(2))
+                    .sum::<f32>()
+                    / 4.0;
+                for &i in &indices {
+                    let expected = (values[i] - mean) / (variance + 1e-3).sqrt();
+                    assert!((y[i] - expected).abs() < 1e-4, "{} {}", y[i], expected);
+                }
+            }
+        }
+
+        assert!(GroupNormalization::new(3)
+            .build(&mut scope, x.clone())
+            .is_err());
+        assert!(GroupNormalization::new(2)
+            .with_epsilon(0.0)
+            .build(&mut scope, x)
+            .is_err());
+    }
+
+    #[test]
+    fn instance_normalization() {
+        let mut scope = Scope::new_root_scope();
+        let images: Output = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None, None, None, Some(2)])))
+            .build(&mut scope.with_op_name("images"))
+            .unwrap()
+            .into();
+        let y = InstanceNormalization::new()
+            .with_center(false)
+            .build(&mut scope, images.clone())
+            .unwrap();
+        assert_eq!(scope.trainable_variables().len(), 1);
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        for variable in scope.variables() {
+            init.add_target(variable.initializer());
+        }
+        session.run(&mut init).unwrap();
+        let values: Vec<f32> = (0..24).map(|i| ((i * 7) % 11) as f32).collect();
+        let input = Tensor::new(&[2, 2, 3, 2]).with_values(&values).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&images.operation, 0, &input);
+        let token = args.request_fetch(&y.operation, y.index);
+        session.run(&mut args).unwrap();
+        let y: Tensor<f32> = args.fetch(token).unwrap();
+        assert_eq!(y.dims(), &[2, 2, 3, 2]);
+        // Each channel of each image has zero mean and almost unit variance.
+        for example in 0..2 {
+            for channel in 0..2 {
+                let normalized: Vec<f32> =
+                    (0..6).map(|p| y[example * 12 + p * 2 + channel]).collect();
+                let mean = normalized.iter().sum::<f32>() / 6.0;
+                let variance = normalized.iter().map(|v| v * v).sum::<f32>() / 6.0;
+                assert!(mean.abs() < 1e-4);
+                assert!((variance - 1.0).abs() < 1e-2);
+            }
+        }
+
+        let vectors: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[1, 2]).with_values(&[1.0f32, 2.0]).unwrap(),
+        )
+        .unwrap()
+        .into();
+        assert!(InstanceNormalization::new()
+            .build(&mut scope, vectors)
+            .is_err());
+    }
+
+    #[test]
+    fn sinusoidal_position_encoding() {
+        let mut scope = Scope::new_root_scope();
+        let encoding = super::sinusoidal_position_encoding(&mut scope, 3, 4).unwrap();
+        let encoding = &run(&scope, &[&encoding], None)[0];
+        assert_eq!(encoding.dims(), &[3, 4]);
+        assert_eq!(&encoding[..4], &[0.0, 1.0, 0.0, 1.0]);
+        // The second pair of columns has a wavelength 100 times longer.
+        let expected = [1.0f32.sin(), 1.0f32.cos(), 0.01f32.sin(), 0.01f32.cos()];
+        for (value, expected) in encoding[4..8].iter().zip(&expected) {
+            assert!((value - expected).abs() < 1e-6);
+        }
+        assert!(super::sinusoidal_position_encoding(&mut scope, 3, 0).is_err());
+    }
+
+    #[test]
+    fn token_and_position_embedding() {
+        let mut scope = Scope::new_root_scope();
+        let ids: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 3])
+                .with_values(&[0i32, 4, 2, 4, 4, 1])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let learned = TokenAndPositionEmbedding::new(5, 10, 2)
+            .build(&mut scope, ids.clone())
+            .unwrap();
+        let fixed = TokenAndPositionEmbedding::new(5, 10, 2)
+            .with_sinusoidal(true)
+            .build(&mut scope, ids.clone())
+            .unwrap();
+        let variables = scope.trainable_variables();
+        let names: Vec<&str> = variables.iter().map(|v| v.name()).collect();
+        assert_eq!(
+            names,
+            vec![
+                "token_and_position_embedding/embedding/embeddings",
+                "token_and_position_embedding/position_embedding/embeddings",
+                "token_and_position_embedding_1/embedding/embeddings",
+            ]
+        );
+        let values = run(
+            &scope,
+            &[
+                &learned,
+                &fixed,
+                variables[0].output(),
+                variables[1].output(),
+                variables[2].output(),
+            ],
+            None,
+        );
+        let (learned, fixed) = (&values[0], &values[1]);
+        let (tokens, positions, fixed_tokens) = (&values[2], &values[3], &values[4]);
+        assert_eq!(learned.dims(), &[2, 3, 2]);
+        assert_eq!(fixed.dims(), &[2, 3, 2]);
+        assert!(tokens.iter().all(|v| v.abs() <= 0.05));
+        let ids = [0usize, 4, 2, 4, 4, 1];
+        for (i, &id) in ids.iter().enumerate() {
+            let pos = i % 3;
+            for d in 0..2 {
+                let expected = tokens[id * 2 + d] + positions[pos * 2 + d];
+                assert!((learned[i * 2 + d] - expected).abs() < 1e-6);
+            }
+            let angle = pos as f32;
+            let encoding = [angle.sin(), angle.cos()];
+            for d in 0..2 {
+                let expected = fixed_tokens[id * 2 + d] + encoding[d];
+                assert!((fixed[i * 2 + d] - expected).abs() < 1e-5);
+            }
+        }
+
+        let floats: Output = ops::constant(&mut scope, &[1.0f32, 2.0][..])
+            .unwrap()
+            .into();
+        assert!(Embedding::new(5, 2).build(&mut scope, floats).is_err());
+        let vector: Output = ops::constant(&mut scope, &[1i32, 2][..]).unwrap().into();
+        assert!(TokenAndPositionEmbedding::new(5, 10, 2)
+            .build(&mut scope, vector)
+            .is_err());
+    }
+}
@@ -21,6 +21,7 @@ use num_complex::Complex;
 use std::cell::Cell;
 use std::cell::RefCell;
 use std::cmp::Ordering;
+use std::convert::TryFrom;
 use std::error::Error;
 use std::ffi::CStr;
 use std::ffi::CString;
@@ -40,6 +41,8 @@ use std::os::raw::c_char;
 use std::ptr;
 use std::slice;
 use std::str::Utf8Error;
+use std::sync::atomic;
+use std::sync::atomic::AtomicBool;
 use tensorflow_sys as tf;
 
 ////////////////////////
@@ -175,13 +178,121 @@ pub use crate::scope::*;
 mod session;
 pub use crate::session::*;
 
+mod feed;
+pub use crate::feed::*;
+pub use tensorflow_macros::Feed;
+pub use tensorflow_macros::Fetch;
+
+mod sparse;
+pub use crate::sparse::*;
+
+mod masking;
+
+#[cfg(feature = "nalgebra")]
+mod nalgebra_conversions;
+
+#[cfg(feature = "ndarray")]
+mod ndarray_conversions;
+
+mod batched_runner;
+pub use crate::batched_runner::*;
+
+mod session_pool;
+pub use crate::session_pool::*;
+
+mod warmup;
+pub use crate::warmup::*;
+
+mod chunked_fetch;
+pub use crate::chunked_fetch::*;
+
+mod bench;
+pub use crate::bench::*;
+
+mod server;
+pub use crate::server::*;
+
+mod protos;
+
+mod fnv;
+
+mod memory_stats;
+pub use crate::memory_stats::*;
+
+mod op_cost;
+pub use crate::op_cost::*;
+
+mod gpu_options;
+pub use crate::gpu_options::*;
+
+mod determinism;
+pub use crate::determinism::*;
+
+mod frozen_model;
+pub use crate::frozen_model::*;
+
+mod graph_def_compat;
+pub use crate::graph_def_compat::*;
+
+mod fingerprint;
+pub use crate::fingerprint::*;
+
+mod device_router;
+pub use crate::device_router::*;
+
+#[cfg(feature = "pluggable_device")]
+mod pluggable_device;
+#[cfg(feature = "pluggable_device")]
+pub use crate::pluggable_device::*;
+
+mod quantization;
+pub use crate::quantization::*;
+
+/// Loading of the TensorFlow C library at runtime, enabled by the
+/// `runtime_linking` feature.  `runtime_linking::load` or
+/// `runtime_linking::Loader::load` must be called before anything else in
+/// this crate.
+#[cfg(feature = "runtime_linking")]
+pub use tensorflow_sys::runtime_linking;
+
 pub mod expr;
 
+#[cfg(feature = "eager")]
+pub mod eager;
+
 pub mod io;
 
+pub mod prelude;
+
+/// ```no_run
+/// use tensorflow::saved_model_signature;
+/// use tensorflow::{SessionOptions, Status, Tensor};
+///
+/// saved_model_signature!(
+///     Regress,
+///     path = "test_resources/regression-model",
+///     tags = ["train", "serve"],
+///     signature = "tensorflow/serving/regress",
+/// );
+///
+/// # fn main() -> Result<(), Status> {
+/// let model = Regress::load(&SessionOptions::new(), "test_resources/regression-model")?;
+/// let outputs = model.run(&RegressInputs {
+///     x: Tensor::new(&[2]).with_values(&[1.0, 2.0])?,
+///     y: Tensor::new(&[2]).with_values(&[3.0, 5.0])?,
+/// })?;
+/// println!("{:?}", outputs.out);
+/// # Ok(())
+/// # }
+/// ```
+pub use tensorflow_macros::saved_model_signature;
+
 #[cfg(feature = "experimental_training")]
 pub mod ops;
 
+#[cfg(feature = "experimental_training")]
+mod graph_macro;
+
 #[cfg(feature = "experimental_training")]
 mod variable;
 #[cfg(feature = "experimental_training")]
@@ -190,6 +301,12 @@ pub use crate::variable::*;
 #[cfg(feature = "experimental_training")]
 pub mod train;
 
+#[cfg(feature = "experimental_training")]
+pub mod layers;
+
+#[cfg(feature = "experimental_training")]
+pub mod data;
+
 ////////////////////////
 
 c_enum!("Error values that can be returned.", TF_Code, Code {
@@ -393,15 +510,37 @@ impl Default for DataType {
 /// Holds error information when communicating with back and forth with `tensorflow`.
 ///
 /// It either has an `Code::Ok` code, or otherwise an error code with an associated message.
+///
+/// Errors may also carry the name of the operation which caused them (see
+/// `op_name`) and the lower-level error they were caused by (see
+/// `Error::source`), which `with_context` uses to describe what was being done
+/// when the error occurred.
 pub struct Status {
     inner: *mut tf::TF_Status,
+    op_name: Option<String>,
+    source: Option<Box<Status>>,
+}
+
+// The TF_Status is owned by this Status, and it's only read through shared
+// references.
+unsafe impl Send for Status {}
+unsafe impl Sync for Status {}
+
+impl Status {
+    /// Creates a status with `Code::Ok` and no message.
+    pub fn new() -> Self {
+        unsafe {
+            let inner = tf::TF_NewStatus();
+            assert!(!inner.is_null());
+            Status {
+                inner,
+                op_name: None,
+                source: None,
+            }
+        }
+    }
 }
 
-impl_new!(
-    Status,
-    TF_NewStatus,
-    "Creates a status with `Code::Ok` and no message."
-);
 impl_drop!(Status, TF_DeleteStatus);
 
 impl Status {
@@ -422,6 +561,67 @@ impl Status {
         self.code() == Code::Ok
     }
 
+    /// Returns the status's message, not including the messages of any
+    /// errors it was caused by.
+    pub fn message(&self) -> &str {
+        unsafe {
+            match CStr::from_ptr(tf::TF_Message(self.inner)).to_str() {
+                Ok(s) => s,
+                Err(_) => "<invalid UTF-8 in message>",
+            }
+        }
+    }
+
+    /// Returns the name of the operation (graph node) which caused the error,
+    /// if known.
+    ///
+    /// This is either attached by the function which created or ran the
+    /// operation, or parsed from TensorFlow's message (which refers to nodes as
+    /// e.g. `{{node foo}}`).  Errors this one was caused by are checked too.
+    pub fn op_name(&self) -> Option<String> {
+        if let Some(op_name) = &self.op_name {
+            return Some(op_name.clone());
+        }
+        match parse_op_name(self.message()) {
+            Some(op_name) => Some(op_name),
+            None => self.source.as_ref().and_then(|source| source.op_name()),
+        }
+    }
+
+    /// Attaches the name of the operation which caused the error.
+    pub fn with_op_name(mut self, op_name: &str) -> Self {
+        self.op_name = Some(op_name.to_string());
+        self
+    }
+
+    /// Wraps the status in a new one with the same code, which describes what
+    /// was being done when the error occurred.  The original status is
+    /// available through `Error::source`.
+    ///
+    /// ```
+    /// # use std::error::Error;
+    /// # use tensorflow::Code;
+    /// # use tensorflow::Status;
+    /// let status = Status::new_set(Code::InvalidArgument, "bad shape").unwrap();
+    /// let status = status.with_context("computing gradients");
+    /// assert_eq!(status.code(), Code::InvalidArgument);
+    /// assert_eq!(status.message(), "computing gradients");
+    /// assert_eq!(
+    ///     status.to_string(),
+    ///     "InvalidArgument: computing gradients: bad shape"
+    /// );
+    /// assert_eq!(status.source().unwrap().to_string(), "InvalidArgument: bad shape");
+    /// ```
+    pub fn with_context(self, context: &str) -> Self {
+        let mut status = Status::new();
+        // Messages are C strings, so NULs can't be represented.
+        status
+            .set(self.code(), &context.replace('\0', ""))
+            .expect("NUL bytes were removed");
+        status.source = Some(Box::new(self));
+        status
+    }
+
     /// Turns the current `Status` into a `Result`.
     fn into_result(self) -> Result<()> {
         if self.is_ok() {
@@ -446,16 +646,41 @@ impl Status {
     }
 }
 
+/// Extracts the node name from messages such as
+/// "Incompatible shapes: [2] vs. [3]\n\t [[{{node add}}]]" or
+/// "... [[node add (defined at model.py:12) ]]".
+fn parse_op_name(message: &str) -> Option<String> {
+    let start = if let Some(i) = message.find("{{node ") {
+        i + "{{node ".len()
+    } else if let Some(i) = message.find("[[node ") {
+        i + "[[node ".len()
+    } else {
+        return None;
+    };
+    let name: String = message[start..]
+        .chars()
+        .take_while(|c| !c.is_whitespace() && *c != '}' && *c != ']')
+        .collect();
+    if name.is_empty() {
+        None
+    } else {
+        Some(name)
+    }
+}
+
 impl Display for Status {
+    /// Writes the code and the message, followed by the messages of the
+    /// errors this one was caused by, e.g. "InvalidArgument: Minimizing
+    /// loss: Incompatible shapes: [2] vs. [3]".
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{}: ", self.code())?;
-        let msg = unsafe {
-            match CStr::from_ptr(tf::TF_Message(self.inner)).to_str() {
-                Ok(s) => s,
-                Err(_) => "<invalid UTF-8 in message>",
-            }
-        };
-        f.write_str(msg)
+        f.write_str(self.message())?;
+        let mut source = self.source.as_ref();
+        while let Some(status) = source {
+            write!(f, ": {}", status.message())?;
+            source = status.source.as_ref();
+        }
+        Ok(())
     }
 }
 
@@ -463,13 +688,13 @@ impl Debug for Status {
     fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
         write!(f, "{{inner:{:?}, ", self.inner)?;
         write!(f, "{}: ", self.code())?;
-        let msg = unsafe {
-            match CStr::from_ptr(tf::TF_Message(self.inner)).to_str() {
-                Ok(s) => s,
-                Err(_) => "<invalid UTF-8 in message>",
-            }
-        };
-        f.write_str(msg)?;
+        f.write_str(self.message())?;
+        if let Some(op_name) = &self.op_name {
+            write!(f, ", op_name: {}", op_name)?;
+        }
+        if let Some(source) = &self.source {
+            write!(f, ", source: {:?}", source)?;
+        }
         write!(f, "}}")?;
         Ok(())
     }
@@ -498,16 +723,116 @@ impl From<IntoStringError> for Status {
 
 impl Error for Status {
     fn description(&self) -> &str {
-        unsafe {
-            match CStr::from_ptr(tf::TF_Message(self.inner)).to_str() {
-                Ok(s) => s,
-                Err(_) => "<invalid UTF-8 in message>",
+        self.message()
+    }
+
+    fn cause(&self) -> Option<&dyn Error> {
+        self.source()
+    }
+
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match &self.source {
+            Some(source) => {
+                let source: &Status = source;
+                Some(source)
             }
+            None => None,
         }
     }
+}
 
-    fn cause(&self) -> Option<&dyn Error> {
-        None
+/// An owned, structured form of a `Status` error, which can be matched on
+/// rather than parsing messages, and stored or sent without keeping the
+/// `TF_Status` alive.  It is created from a `Status` with `From`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StatusError {
+    /// An error reported by TensorFlow or by this crate.
+    Failed {
+        /// The error code.
+        code: Code,
+        /// TensorFlow's message.
+        message: String,
+        /// The name of the operation which caused the error, if known (see
+        /// `Status::op_name`).
+        op_name: Option<String>,
+    },
+    /// An error added by `Status::with_context`, describing what was being
+    /// done when `source` occurred.
+    Context {
+        /// What was being done.
+        context: String,
+        /// The error which occurred.
+        source: Box<StatusError>,
+    },
+}
+
+impl StatusError {
+    /// Returns the error code, which is that of the underlying error.
+    pub fn code(&self) -> Code {
+        match self {
+            StatusError::Failed { code, .. } => *code,
+            StatusError::Context { source, .. } => source.code(),
+        }
+    }
+
+    /// Returns the name of the operation which caused the underlying error,
+    /// if known.
+    pub fn op_name(&self) -> Option<&str> {
+        match self {
+            StatusError::Failed { op_name, .. } => op_name.as_deref(),
+            StatusError::Context { source, .. } => source.op_name(),
+        }
+    }
+}
+
+impl<'a> From<&'a Status> for StatusError {
+    fn from(status: &'a Status) -> Self {
+        match &status.source {
+            Some(source) => StatusError::Context {
+                context: status.message().to_string(),
+                source: Box::new(StatusError::from(&**source)),
+            },
+            None => StatusError::Failed {
+                code: status.code(),
+                message: status.message().to_string(),
+                op_name: status.op_name(),
+            },
+        }
+    }
+}
+
+impl From<Status> for StatusError {
+    fn from(status: Status) -> Self {
+        StatusError::from(&status)
+    }
+}
+
+impl Display for StatusError {
+    /// Writes the same text as the `Status` it was created from.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: ", self.code())?;
+        let mut error = self;
+        loop {
+            match error {
+                StatusError::Failed { message, .. } => return f.write_str(message),
+                StatusError::Context { context, source } => {
+                    write!(f, "{}: ", context)?;
+                    error = source;
+                }
+            }
+        }
+    }
+}
+
+impl Error for StatusError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            StatusError::Failed { .. } => None,
+            StatusError::Context { source, .. } => {
+                let source: &StatusError = source;
+                Some(source)
+            }
+        }
     }
 }
 
@@ -517,9 +842,32 @@ impl Error for Status {
 #[derive(Debug)]
 pub struct SessionOptions {
     inner: *mut tf::TF_SessionOptions,
+    /// The serialized `ConfigProto` most recently passed to `TF_SetConfig`.
+    config: Vec<u8>,
 }
 
 impl SessionOptions {
+    /// Creates a blank set of options, except that in deterministic mode (see
+    /// `set_deterministic`), independent ops are run one at a time.
+    pub fn new() -> Self {
+        unsafe {
+            let inner = tf::TF_NewSessionOptions();
+            assert!(!inner.is_null());
+            let mut options = SessionOptions {
+                inner,
+                config: Vec::new(),
+            };
+            if is_deterministic() {
+                // Run independent ops one at a time, so that their effects on
+                // shared state are ordered the same way on every run.
+                let mut config = protos::ProtoWriter::new();
+                config.int_field(5, 1);
+                options.merge_config(config.as_bytes()).unwrap();
+            }
+            options
+        }
+    }
+
     /// Set the target.
     ///
     /// `target` can be empty, a single entry, or a comma separated list of entries.
@@ -528,6 +876,8 @@ impl SessionOptions {
     /// - "local"
     /// - ip:port
     /// - host:port
+    /// - grpc://host:port, e.g. as returned by `Server::target` or
+    ///   `ClusterSpec::task_target`
     pub fn set_target(&mut self, target: &str) -> std::result::Result<(), NulError> {
         let cstr = CString::new(target)?;
         unsafe {
@@ -540,6 +890,12 @@ impl SessionOptions {
     ///
     /// `config` should be a serialized [`ConfigProto` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto).
     /// Returns an error if config was not parsed successfully as a `ConfigProto`.
+    ///
+    /// This replaces any configuration set previously, including settings made
+    /// through typed setters such as `set_cluster_spec`, and, in deterministic
+    /// mode, the setting made by `new` to run independent ops one at a time.
+    /// To keep running them one at a time, set `inter_op_parallelism_threads`
+    /// to 1 in `config`.
     pub fn set_config(&mut self, config: &[u8]) -> Result<()> {
         let mut status = Status::new();
         unsafe {
@@ -551,18 +907,59 @@ impl SessionOptions {
             );
         }
         if status.is_ok() {
+            self.config = config.to_vec();
             Ok(())
         } else {
             Err(status)
         }
     }
+
+    /// Merges a serialized partial `ConfigProto` into the current config.
+    pub(crate) fn merge_config(&mut self, config: &[u8]) -> Result<()> {
+        // Concatenated protos are merged when parsed.
+        let mut merged = self.config.clone();
+        merged.extend_from_slice(config);
+        self.set_config(&merged)
+    }
+
+    /// Sets the cluster the session will run on, as used for between-graph
+    /// replication where the session target is one of the cluster's tasks.
+    pub fn set_cluster_spec(&mut self, cluster: &ClusterSpec) -> Result<()> {
+        let mut config = protos::ProtoWriter::new();
+        config.bytes_field(14, &cluster.to_cluster_def());
+        self.merge_config(config.as_bytes())
+    }
+
+    /// Sets the GPU options, e.g. to limit memory use or split GPUs into
+    /// logical devices.  This should be called at most once.
+    pub fn set_gpu_options(&mut self, gpu_options: &GpuOptions) -> Result<()> {
+        let mut config = protos::ProtoWriter::new();
+        config.bytes_field(6, &gpu_options.to_proto());
+        self.merge_config(config.as_bytes())
+    }
+
+    /// Sets whether ops placed on a device which doesn't exist or has no
+    /// kernel for them may run on another device instead of failing.
+    pub fn set_allow_soft_placement(&mut self, allow: bool) -> Result<()> {
+        let mut config = protos::ProtoWriter::new();
+        config.bool_field(7, allow);
+        self.merge_config(config.as_bytes())
+    }
+
+    /// Sets the maximum number of devices of the given type (e.g. "CPU" or
+    /// "GPU") to use.  For "CPU", this creates that many logical CPU devices,
+    /// which is useful for testing multi-device graphs on a single machine.
+    pub fn set_device_count(&mut self, device_type: &str, count: i32) -> Result<()> {
+        let mut entry = protos::ProtoWriter::new();
+        entry
+            .string_field(1, device_type)
+            .int_field(2, i64::from(count));
+        let mut config = protos::ProtoWriter::new();
+        config.message_field(1, &entry);
+        self.merge_config(config.as_bytes())
+    }
 }
 
-impl_new!(
-    SessionOptions,
-    TF_NewSessionOptions,
-    "Creates a blank set of options."
-);
 impl_drop!(SessionOptions, TF_DeleteSessionOptions);
 
 ////////////////////////
@@ -780,6 +1177,77 @@ tensor_type!(
 
 ////////////////////////
 
+/// Decodes the elements of a `DataType::String` tensor, which are stored as
+/// a table of `count` offsets followed by the encoded strings.
+fn unpack_strings(data: &[u8], count: usize) -> Result<Vec<&[u8]>> {
+    let base_offset = mem::size_of::<u64>() * count;
+    if data.len() < base_offset {
+        return Err(invalid_arg!(
+            "String tensor data has {} bytes but {} elements",
+            data.len(),
+            count
+        ));
+    }
+    let offsets = unsafe { slice::from_raw_parts(data.as_ptr() as *const u64, count) };
+    let mut out = Vec::with_capacity(count);
+    let mut status = Status::new();
+    for offset in offsets {
+        let off = *offset as usize + base_offset;
+        if off >= data.len() {
+            return Err(invalid_arg!(
+                "String tensor offset {} is out of range",
+                offset
+            ));
+        }
+        #[allow(trivial_casts)]
+        let src = &data[off] as *const u8 as *const c_char;
+        let src_len = data.len() - off;
+        let mut dst_len: usize = 0;
+        let mut dst: *const c_char = ptr::null();
+        unsafe {
+            tf::TF_StringDecode(src, src_len, &mut dst, &mut dst_len, status.inner());
+        }
+        if !status.is_ok() {
+            return Err(status);
+        }
+        out.push(unsafe { slice::from_raw_parts(dst as *const u8, dst_len) });
+    }
+    Ok(out)
+}
+
+/// Returns the number of bytes `pack_strings` needs for `data`.
+fn packed_strings_size<S: AsRef<[u8]>>(data: &[S]) -> usize {
+    let string_data: usize = data
+        .iter()
+        .map(|s| unsafe { tf::TF_StringEncodedSize(s.as_ref().len()) })
+        .sum();
+    mem::size_of::<u64>() * data.len() + string_data
+}
+
+/// Encodes the elements of a `DataType::String` tensor into `buffer`.
+fn pack_strings<S: AsRef<[u8]>>(data: &[S], buffer: &mut [u8]) -> Result<()> {
+    let offsets: &mut [u64] =
+        unsafe { slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u64, data.len()) };
+    let base_offset = mem::size_of::<u64>() * data.len();
+    let mut offset = base_offset;
+    let mut status = Status::new();
+    for i in 0..data.len() {
+        offsets[i] = (offset - base_offset) as u64;
+        let bytes = data[i].as_ref();
+        let src = bytes.as_ptr() as *const c_char;
+        let src_len = bytes.len();
+        let dst: *mut u8 = &mut buffer[offset];
+        let dst_len = buffer.len() - offset;
+        offset += unsafe {
+            tf::TF_StringEncode(src, src_len, dst as *mut c_char, dst_len, status.inner())
+        };
+        if !status.is_ok() {
+            return Err(status);
+        }
+    }
+    Ok(())
+}
+
 impl TensorType for String {
     type InnerType = TensorDataNoCRepr<String>;
 
@@ -800,66 +1268,177 @@ impl TensorType for String {
     }
 
     fn unpack(data: &[u8], count: usize) -> Result<Vec<Self>> {
-        let offsets = unsafe { slice::from_raw_parts(data.as_ptr() as *const u64, count) };
-        let mut out = Vec::with_capacity(count);
-        let mut status = Status::new();
-        let base_offset = mem::size_of::<u64>() * count;
-        for offset in offsets {
-            let off = *offset as usize + base_offset;
-            #[allow(trivial_casts)]
-            let src = &data[off] as *const u8 as *const c_char;
-            let src_len = data.len() - off;
-            let mut dst_len: usize = 0;
-            let mut dst: *const c_char = ptr::null();
-            unsafe {
-                tf::TF_StringDecode(src, src_len, &mut dst, &mut dst_len, status.inner());
-            }
-            if !status.is_ok() {
-                return Err(status);
-            }
-            let string_data = unsafe { slice::from_raw_parts(dst as *const u8, dst_len) };
-            out.push(std::str::from_utf8(string_data)?.to_string());
-        }
-        Ok(out)
+        unpack_strings(data, count)?
+            .into_iter()
+            .map(|s| Ok(std::str::from_utf8(s)?.to_string()))
+            .collect()
     }
 
     fn packed_size(data: &[Self]) -> usize {
-        let string_data: usize = data
-            .iter()
-            .map(|s| unsafe { tf::TF_StringEncodedSize(s.len()) })
-            .sum();
-        mem::size_of::<u64>() * data.len() + string_data
+        packed_strings_size(data)
     }
 
     fn pack(data: &[Self], buffer: &mut [u8]) -> Result<()> {
-        let offsets: &mut [u64] =
-            unsafe { slice::from_raw_parts_mut(buffer.as_mut_ptr() as *mut u64, data.len()) };
-        let base_offset = mem::size_of::<u64>() * data.len();
-        let mut offset = base_offset;
-        let mut status = Status::new();
-        for i in 0..data.len() {
-            offsets[i] = (offset - base_offset) as u64;
-            let src = data[i].as_ptr() as *const c_char;
-            let src_len = data[i].len();
-            let dst: *mut u8 = &mut buffer[offset];
-            let dst_len = buffer.len() - offset;
-            offset += unsafe {
-                tf::TF_StringEncode(src, src_len, dst as *mut c_char, dst_len, status.inner())
-            };
-            if !status.is_ok() {
-                return Err(status);
+        pack_strings(data, buffer)
+    }
+}
+
+impl<'a> From<&'a str> for Tensor<String> {
+    fn from(value: &'a str) -> Self {
+        Tensor::from(value.to_string())
+    }
+}
+
+impl<'a, 'b> From<&'a [&'b str]> for Tensor<String> {
+    fn from(value: &'a [&'b str]) -> Self {
+        let mut tensor: Tensor<String> = Tensor::new(&[value.len() as u64]);
+        for (e, v) in tensor.iter_mut().zip(value) {
+            e.push_str(v);
+        }
+        tensor
+    }
+}
+
+////////////////////////
+
+/// An element of a `DataType::String` tensor as raw bytes.
+///
+/// TensorFlow strings are arbitrary bytes, such as serialized protos or
+/// encoded images, while a `String` must be valid UTF-8.  A
+/// `Tensor<ByteString>` is fed and fetched like a `Tensor<String>`, but
+/// accepts any bytes.
+///
+/// ```
+/// # use tensorflow::ByteString;
+/// # use tensorflow::Tensor;
+/// let mut tensor = Tensor::<ByteString>::new(&[2]);
+/// tensor[0] = ByteString::from(&b"\xff\x00"[..]);
+/// tensor[1] = ByteString::from("text");
+/// assert_eq!(tensor[1].to_str().unwrap(), "text");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct ByteString(Vec<u8>);
+
+impl ByteString {
+    /// Returns the bytes.
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Converts this into its bytes.
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.0
+    }
+
+    /// Returns the bytes as a `str` if they're valid UTF-8.
+    pub fn to_str(&self) -> Result<&str> {
+        Ok(std::str::from_utf8(&self.0)?)
+    }
+}
+
+impl Deref for ByteString {
+    type Target = [u8];
+
+    fn deref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl AsRef<[u8]> for ByteString {
+    fn as_ref(&self) -> &[u8] {
+        &self.0
+    }
+}
+
+impl From<Vec<u8>> for ByteString {
+    fn from(value: Vec<u8>) -> Self {
+        ByteString(value)
+    }
+}
+
+impl<'a> From<&'a [u8]> for ByteString {
+    fn from(value: &'a [u8]) -> Self {
+        ByteString(value.to_vec())
+    }
+}
+
+impl From<String> for ByteString {
+    fn from(value: String) -> Self {
+        ByteString(value.into_bytes())
+    }
+}
+
+impl<'a> From<&'a str> for ByteString {
+    fn from(value: &'a str) -> Self {
+        ByteString(value.as_bytes().to_vec())
+    }
+}
+
+impl Display for ByteString {
+    /// Writes the bytes with non-printable ASCII and non-ASCII bytes escaped.
+    fn fmt(&self, f: &mut Formatter<'_>) -> ::std::fmt::Result {
+        for &b in &self.0 {
+            for c in std::ascii::escape_default(b) {
+                write!(f, "{}", c as char)?;
             }
         }
         Ok(())
     }
 }
 
+impl TensorType for ByteString {
+    type InnerType = TensorDataNoCRepr<ByteString>;
+
+    fn data_type() -> DataType {
+        DataType::String
+    }
+
+    fn zero() -> Self {
+        ByteString::default()
+    }
+
+    fn one() -> Self {
+        ByteString(vec![1])
+    }
+
+    fn is_repr_c() -> bool {
+        false
+    }
+
+    fn unpack(data: &[u8], count: usize) -> Result<Vec<Self>> {
+        Ok(unpack_strings(data, count)?
+            .into_iter()
+            .map(ByteString::from)
+            .collect())
+    }
+
+    fn packed_size(data: &[Self]) -> usize {
+        packed_strings_size(data)
+    }
+
+    fn pack(data: &[Self], buffer: &mut [u8]) -> Result<()> {
+        pack_strings(data, buffer)
+    }
+}
+
 ////////////////////////
 
 pub(crate) trait AnyTensor: Debug {
     fn inner(&self) -> Result<*mut tf::TF_Tensor>;
 
     fn data_type(&self) -> DataType;
+
+    /// Returns the address and length in bytes of data borrowed from the
+    /// caller rather than owned by the tensor.
+    fn borrowed_data(&self) -> Option<(*const u8, usize)> {
+        None
+    }
+
+    /// For a tensor which borrows its data, returns a `TF_Tensor` for one run
+    /// to use in place of `inner`.
+    fn borrowed_tensor(&self) -> Option<Result<BorrowedTensor>> {
+        None
+    }
 }
 
 ////////////////////////
@@ -887,6 +1466,18 @@ where
 
     /// Return a mutable pointer to the C tensor.
     fn as_mut_ptr(&self, dims: &Vec<u64>) -> Result<*mut tf::TF_Tensor>;
+
+    /// Like `deref`, but returns an error instead of panicking if the data
+    /// cannot be converted to its Rust representation.
+    fn try_deref(&self) -> Result<&[T]> {
+        Ok(self.deref())
+    }
+
+    /// Like `deref_mut`, but returns an error instead of panicking if the data
+    /// cannot be converted to its Rust representation.
+    fn try_deref_mut(&mut self) -> Result<&mut [T]> {
+        Ok(self.deref_mut())
+    }
 }
 
 ////////////////////////
@@ -1084,8 +1675,25 @@ where
 
         Ok(inner)
     }
+
+    fn try_deref(&self) -> Result<&[T]> {
+        self.try_unpack()?;
+        Ok(unsafe { slice::from_raw_parts(self.data.get(), self.data_count) })
+    }
+
+    fn try_deref_mut(&mut self) -> Result<&mut [T]> {
+        self.try_unpack()?;
+        // If the slice is modified, the tensor is stale.
+        self.drop_tensor();
+        Ok(unsafe { slice::from_raw_parts_mut(self.data.get(), self.data_count) })
+    }
 }
 
+// The tensor owns both representations of its data, so it can be moved to
+// another thread.  It's not Sync because unpacking mutates it through a shared
+// reference.
+unsafe impl<T> Send for TensorDataNoCRepr<T> where T: TensorType + Send {}
+
 impl<T: TensorType> Drop for TensorDataNoCRepr<T> {
     fn drop(&mut self) {
         self.drop_tensor();
@@ -1097,8 +1705,16 @@ where
     T: TensorType,
 {
     // This will panic if `unpacked` is false and `unpacked_data` is already borrowed.
-    #[allow(trivial_numeric_casts)]
     fn unpack(&self) {
+        // The unwrap() may panic (e.g. if a string contains a 0 byte), but
+        // there's nothing we can do.  This function is called from contexts
+        // that don't allow us to return an error; see try_unpack.
+        self.try_unpack().unwrap();
+    }
+
+    // This will panic if `unpacked` is false and `unpacked_data` is already borrowed.
+    #[allow(trivial_numeric_casts)]
+    fn try_unpack(&self) -> Result<()> {
         if !self.unpacked.get() {
             let mut data = self.unpacked_data.borrow_mut();
             let tensor = self.inner.get();
@@ -1108,15 +1724,19 @@ where
                     tf::TF_TensorByteSize(tensor) as usize,
                 )
             };
-            // The unwrap() may panic (e.g. if a string contains a 0 byte),
-            // but there's nothing we can do.  This function is always
-            // called from contexts that don't allow us to return an error.
-            let mut unpacked = T::unpack(bytes, self.data_count).unwrap();
-            assert_eq!(unpacked.len(), self.data_count);
+            let mut unpacked = T::unpack(bytes, self.data_count)?;
+            if unpacked.len() != self.data_count {
+                return Err(invalid_arg!(
+                    "Unpacked {} elements, but the tensor has {}",
+                    unpacked.len(),
+                    self.data_count
+                ));
+            }
             self.data.set(unpacked.as_mut_ptr());
             *data = Some(unpacked);
             self.unpacked.set(true);
         }
+        Ok(())
     }
 
     fn drop_tensor(&self) {
@@ -1169,6 +1789,17 @@ impl<T: TensorType> Clone for TensorDataNoCRepr<T> {
 ///   element 1:   index (0, ..., 1)
 ///   ...
 /// ```
+///
+/// Dereferencing a tensor as a slice panics if its data is invalid (which is
+/// only possible for types such as strings whose representation differs from
+/// TensorFlow's), and indexing the slice panics if the index is out of
+/// bounds.  Code handling untrusted data, such as servers, can use the
+/// `try_*` methods instead, which return errors.
+///
+/// Tensors are `Send`, so they can be built on one thread and fed or read on
+/// another.  Tensors of types with the same representation in Rust and C are
+/// also `Sync`.  Other tensors, such as strings, convert their data lazily
+/// when first accessed, so they are not `Sync`.
 #[derive(Debug, Clone, Eq)]
 pub struct Tensor<T: TensorType> {
     inner: T::InnerType,
@@ -1211,11 +1842,94 @@ impl<T: TensorType> Tensor<T> {
         Ok(self)
     }
 
+    /// Creates a new tensor, returning an error instead of panicking or
+    /// aborting if the number of elements or bytes overflows.
+    ///
+    /// The data is initialized to zeros.
+    pub fn try_new(dims: &[u64]) -> Result<Self> {
+        let overflow = || invalid_arg!("Tensor with dimensions {:?} is too large", dims);
+        let count = dims
+            .iter()
+            .try_fold(1u64, |count, d| count.checked_mul(*d))
+            .ok_or_else(overflow)?;
+        let bytes = count
+            .checked_mul(mem::size_of::<T>() as u64)
+            .ok_or_else(overflow)?;
+        if bytes > isize::MAX as u64 {
+            return Err(overflow());
+        }
+        Ok(Tensor::new(dims))
+    }
+
     /// Returns the tensor's dimensions.
     pub fn dims(&self) -> &[u64] {
         &self.dims
     }
 
+    /// Returns the offset of the element at `indices` in the row major data
+    /// buffer, or an error if `indices` has the wrong length or is out of
+    /// bounds.
+    pub fn get_index(&self, indices: &[u64]) -> Result<usize> {
+        if indices.len() != self.dims.len() {
+            return Err(invalid_arg!(
+                "Expected {} indices for a tensor with dimensions {:?}, got {:?}",
+                self.dims.len(),
+                self.dims,
+                indices
+            ));
+        }
+        let mut index = 0;
+        for (i, (&idx, &dim)) in indices.iter().zip(&self.dims).enumerate() {
+            if idx >= dim {
+                return Err(Status::new_set(
+                    Code::OutOfRange,
+                    &format!(
+                        "Index {} is out of range for dimension {} of tensor with \
+                         dimensions {:?}",
+                        idx, i, self.dims
+                    ),
+                )
+                .unwrap());
+            }
+            index = index * dim + idx;
+        }
+        Ok(index as usize)
+    }
+
+    /// Returns the element at `indices`, or an error if `indices` is invalid
+    /// or the data can't be read.
+    pub fn try_get(&self, indices: &[u64]) -> Result<&T> {
+        let index = self.get_index(indices)?;
+        Ok(&self.try_as_slice()?[index])
+    }
+
+    /// Returns the element at `indices` mutably, or an error if `indices` is
+    /// invalid or the data can't be read.
+    pub fn try_get_mut(&mut self, indices: &[u64]) -> Result<&mut T> {
+        let index = self.get_index(indices)?;
+        Ok(&mut self.try_as_mut_slice()?[index])
+    }
+
+    /// Sets the element at `indices`, or returns an error if `indices` is
+    /// invalid or the data can't be read.
+    pub fn try_set(&mut self, indices: &[u64], value: T) -> Result<()> {
+        *self.try_get_mut(indices)? = value;
+        Ok(())
+    }
+
+    /// Returns the data as a slice.  Unlike dereferencing the tensor, this
+    /// returns an error instead of panicking if the data is invalid, e.g. a
+    /// string tensor received from TensorFlow which can't be decoded.
+    pub fn try_as_slice(&self) -> Result<&[T]> {
+        self.inner.try_deref()
+    }
+
+    /// Returns the data as a mutable slice.  Unlike dereferencing the tensor,
+    /// this returns an error instead of panicking if the data is invalid.
+    pub fn try_as_mut_slice(&mut self) -> Result<&mut [T]> {
+        self.inner.try_deref_mut()
+    }
+
     /// Returns the tensor's dimensions as a Shape.
     pub fn shape(&self) -> Shape {
         Shape(Some(self.dims.iter().map(|d| Some(*d as i64)).collect()))
@@ -1279,6 +1993,114 @@ impl<'a, T: TensorType> From<&'a [T]> for Tensor<T> {
     }
 }
 
+/// Records that the element of a nested `Vec` at `index` has `len`
+/// elements, which must match its siblings'.
+fn check_nested_len(dims: &mut Vec<u64>, index: &[usize], len: usize) -> Result<()> {
+    let depth = index.len();
+    if dims.len() == depth {
+        dims.push(len as u64);
+    } else if dims[depth] != len as u64 {
+        return Err(invalid_arg!(
+            "Ragged input: element {:?} has {} elements, but its siblings have {}",
+            index,
+            len,
+            dims[depth]
+        ));
+    }
+    Ok(())
+}
+
+fn flatten_nested2<T: TensorType>(
+    rows: &[Vec<T>],
+    index: &mut Vec<usize>,
+    dims: &mut Vec<u64>,
+    values: &mut Vec<T>,
+) -> Result<()> {
+    for (i, row) in rows.iter().enumerate() {
+        index.push(i);
+        check_nested_len(dims, index, row.len())?;
+        index.pop();
+        values.extend_from_slice(row);
+    }
+    Ok(())
+}
+
+fn flatten_nested3<T: TensorType>(
+    matrices: &[Vec<Vec<T>>],
+    index: &mut Vec<usize>,
+    dims: &mut Vec<u64>,
+    values: &mut Vec<T>,
+) -> Result<()> {
+    for (i, matrix) in matrices.iter().enumerate() {
+        index.push(i);
+        check_nested_len(dims, index, matrix.len())?;
+        flatten_nested2(matrix, index, dims, values)?;
+        index.pop();
+    }
+    Ok(())
+}
+
+fn flatten_nested4<T: TensorType>(
+    blocks: &[Vec<Vec<Vec<T>>>],
+    index: &mut Vec<usize>,
+    dims: &mut Vec<u64>,
+    values: &mut Vec<T>,
+) -> Result<()> {
+    for (i, block) in blocks.iter().enumerate() {
+        index.push(i);
+        check_nested_len(dims, index, block.len())?;
+        flatten_nested3(block, index, dims, values)?;
+        index.pop();
+    }
+    Ok(())
+}
+
+/// Creates a tensor from nested `Vec`s with `rank` levels, inferring its
+/// shape.  Dimensions below an empty `Vec` are 0.
+fn from_nested<T: TensorType, F>(len: usize, rank: usize, flatten: F) -> Result<Tensor<T>>
+where
+    F: FnOnce(&mut Vec<usize>, &mut Vec<u64>, &mut Vec<T>) -> Result<()>,
+{
+    let mut dims = vec![len as u64];
+    let mut values = Vec::new();
+    flatten(&mut Vec::new(), &mut dims, &mut values)?;
+    dims.resize(rank, 0);
+    Tensor::new(&dims).with_values(&values)
+}
+
+/// Creates a matrix from its rows, which must all have the same length.
+impl<T: TensorType> TryFrom<Vec<Vec<T>>> for Tensor<T> {
+    type Error = Status;
+
+    fn try_from(value: Vec<Vec<T>>) -> Result<Self> {
+        from_nested(value.len(), 2, |index, dims, values| {
+            flatten_nested2(&value, index, dims, values)
+        })
+    }
+}
+
+/// Creates a rank 3 tensor from nested `Vec`s, which must not be ragged.
+impl<T: TensorType> TryFrom<Vec<Vec<Vec<T>>>> for Tensor<T> {
+    type Error = Status;
+
+    fn try_from(value: Vec<Vec<Vec<T>>>) -> Result<Self> {
+        from_nested(value.len(), 3, |index, dims, values| {
+            flatten_nested3(&value, index, dims, values)
+        })
+    }
+}
+
+/// Creates a rank 4 tensor from nested `Vec`s, which must not be ragged.
+impl<T: TensorType> TryFrom<Vec<Vec<Vec<Vec<T>>>>> for Tensor<T> {
+    type Error = Status;
+
+    fn try_from(value: Vec<Vec<Vec<Vec<T>>>>) -> Result<Self> {
+        from_nested(value.len(), 4, |index, dims, values| {
+            flatten_nested4(&value, index, dims, values)
+        })
+    }
+}
+
 impl<T: TensorType + PartialEq> PartialEq for Tensor<T> {
     fn eq(&self, other: &Tensor<T>) -> bool {
         self.dims == other.dims && self.deref() == other.deref()
@@ -1326,6 +2148,154 @@ impl<T: TensorType> Display for Tensor<T> {
 
 ////////////////////////
 
+/// A read-only tensor whose data is borrowed from a slice, so that it can be
+/// fed to a session without copying.
+///
+/// Only types whose Rust and C representations are the same are supported.
+/// TensorFlow copies data which isn't aligned to 64 bytes, so aligned data
+/// avoids the copy entirely.
+///
+/// Any fetched outputs sharing the borrowed data (e.g. the output of an
+/// `Identity` of the fed tensor) are copied when the step runs, so they can
+/// outlive the borrow.  Views must not be fed to ops which retain their
+/// inputs beyond the step, such as queues: `Session::run` then returns an
+/// error, and the data must not be modified or freed while the session
+/// lives.
+///
+/// ```
+/// # use tensorflow::TensorView;
+/// let data = vec![1.0f32, 2.0, 3.0, 4.0];
+/// let view = TensorView::new(&[2, 2], &data)?;
+/// assert_eq!(view.dims(), &[2, 2]);
+/// assert_eq!(view[3], 4.0);
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+#[derive(Debug)]
+pub struct TensorView<'a, T: TensorType> {
+    dims: Vec<u64>,
+    data: &'a [T],
+}
+
+unsafe extern "C" fn release_borrowed_data(
+    _data: *mut std::os::raw::c_void,
+    _len: libc::size_t,
+    arg: *mut std::os::raw::c_void,
+) {
+    (*(arg as *const AtomicBool)).store(true, atomic::Ordering::SeqCst);
+}
+
+impl<'a, T: TensorType> TensorView<'a, T> {
+    /// Creates a view of `data` with the given dimensions.  Returns an error
+    /// if the number of values doesn't match the dimensions or the type can't
+    /// be borrowed.
+    pub fn new(dims: &[u64], data: &'a [T]) -> Result<Self> {
+        if !T::is_repr_c() {
+            return Err(invalid_arg!(
+                "Tensors of type {} can't be borrowed",
+                T::data_type()
+            ));
+        }
+        if product(dims) != data.len() as u64 {
+            return Err(invalid_arg!(
+                "Dimensions {:?} require {} values, but got {}",
+                dims,
+                product(dims),
+                data.len()
+            ));
+        }
+        Ok(TensorView {
+            dims: dims.to_vec(),
+            data,
+        })
+    }
+
+    /// Returns the tensor's dimensions.
+    pub fn dims(&self) -> &[u64] {
+        &self.dims
+    }
+}
+
+impl<'a, T: TensorType> Deref for TensorView<'a, T> {
+    type Target = [T];
+
+    #[inline]
+    fn deref(&self) -> &[T] {
+        self.data
+    }
+}
+
+impl<'a, T: TensorType> AnyTensor for TensorView<'a, T> {
+    fn inner(&self) -> Result<*mut tf::TF_Tensor> {
+        Err(Status::new_set(
+            Code::Internal,
+            "A TensorView is fed through a BorrowedTensor",
+        )
+        .unwrap())
+    }
+
+    fn data_type(&self) -> DataType {
+        T::data_type()
+    }
+
+    fn borrowed_data(&self) -> Option<(*const u8, usize)> {
+        Some((self.data.as_ptr() as *const u8, mem::size_of_val(self.data)))
+    }
+
+    fn borrowed_tensor(&self) -> Option<Result<BorrowedTensor>> {
+        let released = Box::new(AtomicBool::new(false));
+        let released_ptr: *const AtomicBool = &*released;
+        let inner = unsafe {
+            tf::TF_NewTensor(
+                T::data_type().to_c(),
+                self.dims.as_ptr() as *const _,
+                self.dims.len() as c_int,
+                self.data.as_ptr() as *mut _,
+                mem::size_of_val(self.data),
+                Some(release_borrowed_data),
+                released_ptr as *mut _,
+            )
+        };
+        if inner.is_null() {
+            return Some(Err(Status::new_set(
+                Code::Internal,
+                "Unable to create tensor",
+            )
+            .unwrap()));
+        }
+        Some(Ok(BorrowedTensor { inner, released }))
+    }
+}
+
+/// A `TF_Tensor` which borrows the data of a `TensorView` for one run.
+#[derive(Debug)]
+pub(crate) struct BorrowedTensor {
+    inner: *mut tf::TF_Tensor,
+    // Set by the deallocator once TensorFlow no longer references the data.
+    released: Box<AtomicBool>,
+}
+
+impl BorrowedTensor {
+    pub(crate) fn inner(&self) -> *mut tf::TF_Tensor {
+        self.inner
+    }
+
+    /// Deletes the tensor, and returns whether TensorFlow has released the
+    /// data, i.e. no op retained it.
+    pub(crate) fn release(self) -> bool {
+        unsafe {
+            tf::TF_DeleteTensor(self.inner);
+        }
+        let released = self.released.load(atomic::Ordering::SeqCst);
+        if !released {
+            // The deallocator still refers to the flag.
+            mem::forget(self.released);
+        }
+        released
+    }
+}
+
+////////////////////////
+
 /// Dynamically loaded plugins.
 /// The C API doesn't provide a way to unload libraries, so nothing happens when this
 /// goes out of scope.
@@ -1501,6 +2471,116 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_status_op_name() {
+        let status = Status::new_set(
+            Code::InvalidArgument,
+            "Incompatible shapes: [2] vs. [3]\n\t [[{{node foo/add}}]]",
+        )
+        .unwrap();
+        assert_eq!(status.op_name(), Some("foo/add".to_string()));
+        let status = Status::new_set(
+            Code::InvalidArgument,
+            "Incompatible shapes\n\t [[node add (defined at model.py:12) ]]",
+        )
+        .unwrap();
+        assert_eq!(status.op_name(), Some("add".to_string()));
+        let status = Status::new_set(Code::InvalidArgument, "bad").unwrap();
+        assert_eq!(status.op_name(), None);
+        assert_eq!(
+            status.with_op_name("mul").op_name(),
+            Some("mul".to_string())
+        );
+    }
+
+    #[test]
+    fn test_status_context() {
+        let status = Status::new_set(Code::NotFound, "no such op {{node x}}").unwrap();
+        let status = status.with_context("outer").with_context("outermost");
+        assert_eq!(status.code(), Code::NotFound);
+        assert_eq!(
+            status.to_string(),
+            "NotFound: outermost: outer: no such op {{node x}}"
+        );
+        assert_eq!(status.op_name(), Some("x".to_string()));
+        let mut messages = Vec::new();
+        let mut error: Option<&(dyn Error + 'static)> = Some(&status);
+        while let Some(e) = error {
+            messages.push(e.to_string());
+            error = e.source();
+        }
+        assert_eq!(
+            messages,
+            vec![
+                "NotFound: outermost: outer: no such op {{node x}}",
+                "NotFound: outer: no such op {{node x}}",
+                "NotFound: no such op {{node x}}"
+            ]
+        );
+
+        let error = StatusError::from(&status);
+        assert_eq!(error.to_string(), status.to_string());
+        assert_eq!(error.code(), Code::NotFound);
+        assert_eq!(error.op_name(), Some("x"));
+        let failed = StatusError::Failed {
+            code: Code::NotFound,
+            message: "no such op {{node x}}".to_string(),
+            op_name: Some("x".to_string()),
+        };
+        assert_eq!(
+            error,
+            StatusError::Context {
+                context: "outermost".to_string(),
+                source: Box::new(StatusError::Context {
+                    context: "outer".to_string(),
+                    source: Box::new(failed),
+                }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_thread_safety() {
+        fn assert_send<T: Send>() {}
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Graph>();
+        assert_send_sync::<Operation>();
+        assert_send_sync::<Session>();
+        assert_send_sync::<Status>();
+        assert_send_sync::<Tensor<f32>>();
+        assert_send::<Tensor<String>>();
+    }
+
+    #[test]
+    fn test_tensor_try_accessors() {
+        let mut tensor = Tensor::<i32>::new(&[2, 3]);
+        assert_eq!(tensor.get_index(&[1, 2]).unwrap(), 5);
+        assert_eq!(
+            tensor.get_index(&[0, 3]).unwrap_err().code(),
+            Code::OutOfRange
+        );
+        assert_eq!(
+            tensor.get_index(&[0]).unwrap_err().code(),
+            Code::InvalidArgument
+        );
+        tensor.try_set(&[1, 0], 7).unwrap();
+        assert_eq!(tensor[3], 7);
+        assert_eq!(*tensor.try_get(&[1, 0]).unwrap(), 7);
+        assert!(tensor.try_get(&[2, 0]).is_err());
+        assert!(tensor.try_set(&[2, 0], 1).is_err());
+        assert_eq!(tensor.try_as_slice().unwrap().len(), 6);
+
+        let scalar = Tensor::<f32>::try_new(&[]).unwrap();
+        assert_eq!(*scalar.try_get(&[]).unwrap(), 0.0);
+        assert!(Tensor::<f32>::try_new(&[u64::max_value(), 2]).is_err());
+        assert!(Tensor::<f32>::try_new(&[1 << 62]).is_err());
+
+        let strings = Tensor::<String>::new(&[2])
+            .with_values(&["a".to_string(), "b".to_string()])
+            .unwrap();
+        assert_eq!(strings.try_get(&[1]).unwrap(), "b");
+    }
+
     #[test]
     fn test_set_target() {
         let mut options = SessionOptions::new();
@@ -1514,6 +2594,23 @@ mod tests {
         options.set_config(&vec![]).unwrap();
     }
 
+    #[test]
+    fn test_set_device_count() {
+        let mut options = SessionOptions::new();
+        options.set_device_count("CPU", 2).unwrap();
+        options
+            .set_gpu_options(&GpuOptions::new().allow_growth(true))
+            .unwrap();
+        let session = Session::new(&options, &Graph::new()).unwrap();
+        let cpus = session
+            .device_list()
+            .unwrap()
+            .into_iter()
+            .filter(|d| d.device_type == "CPU")
+            .count();
+        assert_eq!(cpus, 2);
+    }
+
     #[test]
     fn test_run() {
         // Graph is just y = 2 * x
@@ -1607,6 +2704,76 @@ mod tests {
         assert_eq!(output_tensor[1], "YmFy");
     }
 
+    #[test]
+    fn test_byte_strings() {
+        let mut g = Graph::new();
+        let x_op = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::String).unwrap();
+            nd.finish().unwrap()
+        };
+        let y_op = {
+            let mut nd = g.new_operation("Identity", "y").unwrap();
+            nd.add_input(x_op.clone());
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+        let mut x = <Tensor<ByteString>>::new(&[3]);
+        x[0] = ByteString::from(vec![0xff, 0x00, 0x80]);
+        x[1] = ByteString::from("text");
+        let mut step = SessionRunArgs::new();
+        step.add_feed(&x_op, 0, &x);
+        let bytes_ix = step.request_fetch(&y_op, 0);
+        let string_ix = step.request_fetch(&y_op, 0);
+        session.run(&mut step).unwrap();
+        let output = step.fetch::<ByteString>(bytes_ix).unwrap();
+        assert_eq!(&output[..], &x[..]);
+        assert_eq!(output[0].to_string(), "\\xff\\x00\\x80");
+        assert!(output[0].to_str().is_err());
+        // The first element isn't UTF-8.
+        let output = step.fetch::<String>(string_ix).unwrap();
+        assert!(output.try_as_slice().is_err());
+    }
+
+    #[test]
+    fn test_string_tensor_from_str() {
+        let scalar = Tensor::<String>::from("foo");
+        assert!(scalar.dims().is_empty());
+        assert_eq!(scalar[0], "foo");
+        let vector = Tensor::<String>::from(&["a", "bc"][..]);
+        assert_eq!(vector.dims(), &[2]);
+        assert_eq!(&vector[..], &["a", "bc"]);
+    }
+
+    #[test]
+    fn tensor_try_from_nested_vecs() {
+        let matrix = Tensor::try_from(vec![vec![1i32, 2, 3], vec![4, 5, 6]]).unwrap();
+        assert_eq!(matrix.dims(), &[2, 3]);
+        assert_eq!(&matrix[..], &[1, 2, 3, 4, 5, 6]);
+
+        let cube = Tensor::try_from(vec![
+            vec![vec![1.0f32], vec![2.0]],
+            vec![vec![3.0], vec![4.0]],
+        ])
+        .unwrap();
+        assert_eq!(cube.dims(), &[2, 2, 1]);
+        assert_eq!(&cube[..], &[1.0, 2.0, 3.0, 4.0]);
+
+        let hyper = Tensor::try_from(vec![vec![vec![vec![1u8, 2]]]]).unwrap();
+        assert_eq!(hyper.dims(), &[1, 1, 1, 2]);
+
+        let empty = Tensor::<i32>::try_from(Vec::<Vec<i32>>::new()).unwrap();
+        assert_eq!(empty.dims(), &[0, 0]);
+        let empty = Tensor::<i32>::try_from(vec![vec![Vec::<i32>::new()]]).unwrap();
+        assert_eq!(empty.dims(), &[1, 1, 0]);
+
+        let error = Tensor::try_from(vec![vec![1i32, 2], vec![3]]).unwrap_err();
+        assert!(error.message().contains("[1]"), "{}", error);
+        let error = Tensor::try_from(vec![vec![vec![1i32], vec![2]], vec![vec![3], vec![4, 5]]])
+            .unwrap_err();
+        assert!(error.message().contains("[1, 1]"), "{}", error);
+    }
+
     #[test]
     fn tensor_clone() {
         let x = Tensor::<i32>::new(&[3]).with_values(&[1, 2, 3]).unwrap();
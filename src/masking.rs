@@ -0,0 +1,169 @@
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+
+impl<T: TensorType> Tensor<T> {
+    /// Returns a boolean tensor of the same shape which is true where
+    /// `predicate` holds, e.g. `scores.to_mask(|&s| s > 0.5)`.
+    pub fn to_mask<F: Fn(&T) -> bool>(&self, predicate: F) -> Tensor<bool> {
+        let values: Vec<bool> = self.iter().map(predicate).collect();
+        // The shapes match, so this can't fail.
+        Tensor::new(self.dims()).with_values(&values).unwrap()
+    }
+
+    /// Returns the elements (or slices) of this tensor where `mask` is true,
+    /// like `ops::boolean_mask` on the host.
+    ///
+    /// The dimensions of `mask` must equal the leading dimensions of this
+    /// tensor.  The result has one dimension for the selected entries of the
+    /// mask followed by the remaining dimensions of this tensor, e.g. masking
+    /// boxes of shape `[n, 4]` with a mask of shape `[n]` gives shape
+    /// `[mask.count_true(), 4]`.
+    pub fn masked(&self, mask: &Tensor<bool>) -> Result<Tensor<T>> {
+        let k = mask.dims().len();
+        if k > self.dims().len() || mask.dims() != &self.dims()[..k] {
+            return Err(invalid_arg!(
+                "A mask of shape {:?} can't be applied to a tensor of shape {:?}",
+                mask.dims(),
+                self.dims()
+            ));
+        }
+        let inner_dims = &self.dims()[k..];
+        let slice_len = inner_dims.iter().product::<u64>() as usize;
+        let count = mask.count_true();
+        let mut values = Vec::with_capacity(count * slice_len);
+        for (i, _) in mask.iter().enumerate().filter(|(_, &m)| m) {
+            values.extend_from_slice(&self[i * slice_len..(i + 1) * slice_len]);
+        }
+        let mut dims = vec![count as u64];
+        dims.extend_from_slice(inner_dims);
+        Tensor::new(&dims).with_values(&values)
+    }
+}
+
+impl Tensor<bool> {
+    /// Returns the number of true elements.
+    pub fn count_true(&self) -> usize {
+        self.iter().filter(|&&b| b).count()
+    }
+
+    /// Returns whether any element is true.
+    pub fn any(&self) -> bool {
+        self.iter().any(|&b| b)
+    }
+
+    /// Returns whether all elements are true, which is the case for an empty
+    /// tensor.
+    pub fn all(&self) -> bool {
+        self.iter().all(|&b| b)
+    }
+
+    /// Returns the indices of the true elements in row-major order, like
+    /// `ops::where_` on the host.
+    pub fn true_indices(&self) -> Vec<Vec<u64>> {
+        let dims = self.dims();
+        self.iter()
+            .enumerate()
+            .filter(|(_, &b)| b)
+            .map(|(mut i, _)| {
+                let mut index = vec![0; dims.len()];
+                for (d, &dim) in dims.iter().enumerate().rev() {
+                    index[d] = i as u64 % dim;
+                    i /= dim as usize;
+                }
+                index
+            })
+            .collect()
+    }
+
+    /// Returns the element-wise negation.
+    pub fn logical_not(&self) -> Tensor<bool> {
+        self.to_mask(|&b| !b)
+    }
+
+    /// Returns the element-wise conjunction with `other`, which must have the
+    /// same shape.
+    pub fn logical_and(&self, other: &Tensor<bool>) -> Result<Tensor<bool>> {
+        self.zip_with(other, |a, b| a && b)
+    }
+
+    /// Returns the element-wise disjunction with `other`, which must have the
+    /// same shape.
+    pub fn logical_or(&self, other: &Tensor<bool>) -> Result<Tensor<bool>> {
+        self.zip_with(other, |a, b| a || b)
+    }
+
+    fn zip_with<F: Fn(bool, bool) -> bool>(
+        &self,
+        other: &Tensor<bool>,
+        f: F,
+    ) -> Result<Tensor<bool>> {
+        if self.dims() != other.dims() {
+            return Err(invalid_arg!(
+                "Masks of shapes {:?} and {:?} can't be combined",
+                self.dims(),
+                other.dims()
+            ));
+        }
+        let values: Vec<bool> = self
+            .iter()
+            .zip(other.iter())
+            .map(|(&a, &b)| f(a, b))
+            .collect();
+        Tensor::new(self.dims()).with_values(&values)
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mask_helpers() {
+        let scores = Tensor::new(&[2, 2])
+            .with_values(&[0.9f32, 0.2, 0.4, 0.7])
+            .unwrap();
+        let mask = scores.to_mask(|&s| s > 0.5);
+        assert_eq!(&mask[..], &[true, false, false, true]);
+        assert_eq!(mask.count_true(), 2);
+        assert!(mask.any());
+        assert!(!mask.all());
+        assert_eq!(mask.true_indices(), vec![vec![0, 0], vec![1, 1]]);
+        assert_eq!(&mask.logical_not()[..], &[false, true, true, false]);
+        let other = Tensor::new(&[2, 2])
+            .with_values(&[true, true, false, false])
+            .unwrap();
+        assert_eq!(
+            &mask.logical_and(&other).unwrap()[..],
+            &[true, false, false, false]
+        );
+        assert_eq!(
+            &mask.logical_or(&other).unwrap()[..],
+            &[true, true, false, true]
+        );
+        assert!(mask.logical_and(&Tensor::new(&[4])).is_err());
+        assert!(Tensor::<bool>::new(&[0]).all());
+    }
+
+    #[test]
+    fn masked() {
+        let boxes = Tensor::new(&[3, 2])
+            .with_values(&[1i32, 2, 3, 4, 5, 6])
+            .unwrap();
+        let mask = Tensor::new(&[3]).with_values(&[true, false, true]).unwrap();
+        let selected = boxes.masked(&mask).unwrap();
+        assert_eq!(selected.dims(), &[2, 2]);
+        assert_eq!(&selected[..], &[1, 2, 5, 6]);
+
+        let full_mask = boxes.to_mask(|&x| x % 2 == 0);
+        let selected = boxes.masked(&full_mask).unwrap();
+        assert_eq!(selected.dims(), &[3]);
+        assert_eq!(&selected[..], &[2, 4, 6]);
+
+        assert!(boxes.masked(&Tensor::new(&[2])).is_err());
+        let empty = Tensor::<i32>::new(&[3, 0]).masked(&mask).unwrap();
+        assert_eq!(empty.dims(), &[2, 0]);
+    }
+}
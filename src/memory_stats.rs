@@ -0,0 +1,287 @@
+use super::Result;
+use crate::protos::ProtoReader;
+use std::collections::BTreeMap;
+
+/// Returns the serialized `DeviceStepStats` of each device in a serialized
+/// `RunMetadata`.
+fn device_step_stats(run_metadata: &[u8]) -> Result<Vec<&[u8]>> {
+    let mut devices = Vec::new();
+    for field in ProtoReader::new(run_metadata) {
+        let (field, value) = field?;
+        // RunMetadata.step_stats
+        if field == 1 {
+            for field in ProtoReader::new(value.as_bytes()?) {
+                let (field, value) = field?;
+                // StepStats.dev_stats
+                if field == 1 {
+                    devices.push(value.as_bytes()?);
+                }
+            }
+        }
+    }
+    Ok(devices)
+}
+
+/// Memory used on a single device during a traced run: how much its
+/// allocators had in use and at their peak, and how much the operations run
+/// on it allocated, split into temporary memory, which is freed when each
+/// operation finishes, and persistent memory, which operations keep across
+/// runs (e.g. for variables and lookup tables).
+///
+/// These are collected from the step stats of a run's `RunMetadata`, so the
+/// run must be traced.  The simplest way to do that is
+/// `SessionRunArgs::request_memory_stats`; a long-running service can trace a
+/// sample of its runs and export the results to its monitoring system.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DeviceMemoryStats {
+    device: String,
+    temp_bytes: i64,
+    peak_temp_bytes: i64,
+    persistent_bytes: i64,
+    largest_persistent: Option<(String, i64)>,
+    peak_bytes: i64,
+    // The bytes in use last reported by each allocator, with the end of the
+    // operation which reported them in microseconds.
+    bytes_in_use: BTreeMap<String, (i64, i64)>,
+}
+
+impl DeviceMemoryStats {
+    /// Extracts per-device memory statistics from a serialized
+    /// [`RunMetadata` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto),
+    /// sorted by device name.
+    pub fn from_run_metadata(run_metadata: &[u8]) -> Result<Vec<Self>> {
+        let mut stats: BTreeMap<String, DeviceMemoryStats> = BTreeMap::new();
+        for device_step_stats in device_step_stats(run_metadata)? {
+            let mut device = "";
+            let mut node_stats = Vec::new();
+            for field in ProtoReader::new(device_step_stats) {
+                let (field, value) = field?;
+                match field {
+                    // DeviceStepStats.device
+                    1 => device = value.as_str()?,
+                    // DeviceStepStats.node_stats
+                    2 => node_stats.push(value.as_bytes()?),
+                    _ => {}
+                }
+            }
+            let entry = stats
+                .entry(device.to_string())
+                .or_insert_with(|| DeviceMemoryStats {
+                    device: device.to_string(),
+                    ..DeviceMemoryStats::default()
+                });
+            for node in node_stats {
+                entry.add_node(node)?;
+            }
+        }
+        Ok(stats.into_values().collect())
+    }
+
+    fn add_node(&mut self, node: &[u8]) -> Result<()> {
+        let mut name = "";
+        let mut temp_bytes = 0;
+        let mut persistent_bytes = 0;
+        let mut end_micros = 0;
+        let mut memory = Vec::new();
+        for field in ProtoReader::new(node) {
+            let (field, value) = field?;
+            match field {
+                // NodeExecStats.node_name
+                1 => name = value.as_str()?,
+                // NodeExecStats.all_start_micros, NodeExecStats.all_end_rel_micros
+                2 | 5 => end_micros += value.as_i64()?,
+                // NodeExecStats.memory
+                6 => memory.push(value.as_bytes()?),
+                // NodeExecStats.memory_stats
+                12 => {
+                    for field in ProtoReader::new(value.as_bytes()?) {
+                        let (field, value) = field?;
+                        match field {
+                            // MemoryStats.temp_memory_size
+                            1 => temp_bytes = value.as_i64()?,
+                            // MemoryStats.persistent_memory_size
+                            3 => persistent_bytes = value.as_i64()?,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        self.temp_bytes += temp_bytes;
+        self.peak_temp_bytes = self.peak_temp_bytes.max(temp_bytes);
+        self.persistent_bytes += persistent_bytes;
+        let largest = self
+            .largest_persistent
+            .as_ref()
+            .map_or(0, |(_, bytes)| *bytes);
+        if persistent_bytes > largest {
+            self.largest_persistent = Some((name.to_string(), persistent_bytes));
+        }
+        for allocator_memory in memory {
+            self.add_allocator_memory(allocator_memory, end_micros)?;
+        }
+        Ok(())
+    }
+
+    fn add_allocator_memory(&mut self, buf: &[u8], end_micros: i64) -> Result<()> {
+        let mut allocator = "";
+        let mut bytes_in_use = None;
+        for field in ProtoReader::new(buf) {
+            let (field, value) = field?;
+            match field {
+                // AllocatorMemoryUsed.allocator_name
+                1 => allocator = value.as_str()?,
+                // AllocatorMemoryUsed.peak_bytes
+                3 => self.peak_bytes = self.peak_bytes.max(value.as_i64()?),
+                // AllocatorMemoryUsed.allocator_bytes_in_use
+                5 => bytes_in_use = Some(value.as_i64()?),
+                _ => {}
+            }
+        }
+        if let Some(bytes_in_use) = bytes_in_use {
+            let report = self
+                .bytes_in_use
+                .entry(allocator.to_string())
+                .or_insert((i64::MIN, 0));
+            if end_micros >= report.0 {
+                *report = (end_micros, bytes_in_use);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the full name of the device.
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
+    /// Returns the number of bytes the device's allocators had in use at the
+    /// end of the run, across all sessions sharing them.  Allocators which
+    /// don't track their usage count as 0.
+    pub fn bytes_in_use(&self) -> i64 {
+        self.bytes_in_use.values().map(|(_, bytes)| bytes).sum()
+    }
+
+    /// Returns the peak number of bytes allocated at once by any single
+    /// operation on the device during the run.
+    pub fn peak_bytes(&self) -> i64 {
+        self.peak_bytes
+    }
+
+    /// Returns the total number of bytes of temporary memory allocated by
+    /// operations during the run.
+    pub fn temp_bytes(&self) -> i64 {
+        self.temp_bytes
+    }
+
+    /// Returns the largest number of bytes of temporary memory allocated by
+    /// any single operation during the run.
+    pub fn peak_temp_bytes(&self) -> i64 {
+        self.peak_temp_bytes
+    }
+
+    /// Returns the number of bytes of persistent memory allocated by
+    /// operations during the run.
+    pub fn persistent_bytes(&self) -> i64 {
+        self.persistent_bytes
+    }
+
+    /// Returns the name of the operation which allocated the most persistent
+    /// memory during the run, with the number of bytes, if any operation
+    /// allocated persistent memory.
+    pub fn largest_persistent(&self) -> Option<(&str, i64)> {
+        self.largest_persistent
+            .as_ref()
+            .map(|(name, bytes)| (name.as_str(), *bytes))
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::ProtoWriter;
+
+    fn node(start: u64, allocator: &str, peak: u64, in_use: u64) -> ProtoWriter {
+        let mut memory = ProtoWriter::new();
+        memory
+            .string_field(1, allocator)
+            .uint_field(2, peak * 2)
+            .uint_field(3, peak)
+            .uint_field(4, peak / 2)
+            .uint_field(5, in_use);
+        let mut node = ProtoWriter::new();
+        node.string_field(1, "node")
+            .uint_field(2, start)
+            .uint_field(5, 10)
+            .message_field(6, &memory);
+        node
+    }
+
+    #[test]
+    fn allocator_memory() {
+        let mut gpu = ProtoWriter::new();
+        gpu.string_field(1, "/device:GPU:0")
+            .message_field(2, &node(200, "GPU_0_bfc", 100, 1000))
+            .message_field(2, &node(100, "GPU_0_bfc", 300, 900))
+            .message_field(2, &node(150, "cuda_host_bfc", 8, 8));
+        let mut cpu = ProtoWriter::new();
+        cpu.string_field(1, "/device:CPU:0")
+            .message_field(2, &node(0, "cpu", 4, 0));
+        let mut step_stats = ProtoWriter::new();
+        step_stats.message_field(1, &gpu).message_field(1, &cpu);
+        let mut metadata = ProtoWriter::new();
+        metadata.message_field(1, &step_stats);
+
+        let stats = DeviceMemoryStats::from_run_metadata(metadata.as_bytes()).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].device(), "/device:CPU:0");
+        assert_eq!(stats[0].peak_bytes(), 4);
+        assert_eq!(stats[0].bytes_in_use(), 0);
+        assert_eq!(stats[1].device(), "/device:GPU:0");
+        assert_eq!(stats[1].peak_bytes(), 300);
+        // GPU_0_bfc as reported by the node which finished last, plus
+        // cuda_host_bfc.
+        assert_eq!(stats[1].bytes_in_use(), 1008);
+    }
+
+    #[test]
+    fn empty() {
+        assert_eq!(DeviceMemoryStats::from_run_metadata(&[]).unwrap(), vec![]);
+        assert!(DeviceMemoryStats::from_run_metadata(&[0x0a, 0x05]).is_err());
+    }
+
+    #[test]
+    fn device_memory_stats() {
+        let node = |name: &str, temp: u64, persistent: u64| {
+            let mut memory_stats = ProtoWriter::new();
+            memory_stats.uint_field(1, temp).uint_field(3, persistent);
+            let mut node = ProtoWriter::new();
+            node.string_field(1, name).message_field(12, &memory_stats);
+            node
+        };
+        let mut gpu = ProtoWriter::new();
+        gpu.string_field(1, "/device:GPU:0")
+            .message_field(2, &node("conv", 100, 0))
+            .message_field(2, &node("table", 10, 500))
+            .message_field(2, &node("matmul", 300, 20));
+        let mut cpu = ProtoWriter::new();
+        cpu.string_field(1, "/device:CPU:0")
+            .message_field(2, &node("x", 0, 0));
+        let mut step_stats = ProtoWriter::new();
+        step_stats.message_field(1, &gpu).message_field(1, &cpu);
+        let mut metadata = ProtoWriter::new();
+        metadata.message_field(1, &step_stats);
+
+        let stats = DeviceMemoryStats::from_run_metadata(metadata.as_bytes()).unwrap();
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats[0].device(), "/device:CPU:0");
+        assert_eq!(stats[0].largest_persistent(), None);
+        assert_eq!(stats[1].temp_bytes(), 410);
+        assert_eq!(stats[1].peak_temp_bytes(), 300);
+        assert_eq!(stats[1].persistent_bytes(), 520);
+        assert_eq!(stats[1].largest_persistent(), Some(("table", 500)));
+    }
+}
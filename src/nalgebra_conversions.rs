@@ -0,0 +1,142 @@
+//! Conversions between `Tensor` and nalgebra matrices and vectors.
+//!
+//! Tensors are stored in row-major order and nalgebra matrices in
+//! column-major order, so the conversions transpose the storage; element
+//! `(i, j)` of a matrix is always element `[i, j]` of the tensor.
+
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+use nalgebra::allocator::Allocator;
+use nalgebra::storage::Storage;
+use nalgebra::DefaultAllocator;
+use nalgebra::Dim;
+use nalgebra::Matrix;
+use nalgebra::MatrixMN;
+use nalgebra::Scalar;
+use nalgebra::VectorN;
+use nalgebra::U1;
+
+/// Creates a tensor of shape `[nrows, ncols]` from a matrix.  A vector
+/// becomes a column of shape `[n, 1]`; use `Tensor::from_vector` for a
+/// tensor of shape `[n]`.
+impl<'a, N, R, C, S> From<&'a Matrix<N, R, C, S>> for Tensor<N>
+where
+    N: TensorType + Scalar,
+    R: Dim,
+    C: Dim,
+    S: Storage<N, R, C>,
+{
+    fn from(matrix: &'a Matrix<N, R, C, S>) -> Self {
+        let (nrows, ncols) = matrix.shape();
+        let mut tensor = Tensor::new(&[nrows as u64, ncols as u64]);
+        for i in 0..nrows {
+            for j in 0..ncols {
+                tensor[i * ncols + j] = matrix[(i, j)];
+            }
+        }
+        tensor
+    }
+}
+
+/// Returns the dimension of type `D` with size `size`, or an error if `D` is
+/// fixed to a different size.
+fn dim<D: Dim>(size: u64, what: &str) -> Result<D> {
+    match D::try_to_usize() {
+        Some(fixed) if fixed as u64 != size => Err(invalid_arg!(
+            "Expected {} {} but the tensor has {}",
+            fixed,
+            what,
+            size
+        )),
+        _ => Ok(D::from_usize(size as usize)),
+    }
+}
+
+impl<N: TensorType + Scalar> Tensor<N> {
+    /// Creates a tensor of shape `[n]` from a vector.
+    pub fn from_vector<R: Dim, S: Storage<N, R, U1>>(vector: &Matrix<N, R, U1, S>) -> Self {
+        let mut tensor = Tensor::new(&[vector.len() as u64]);
+        for (t, v) in tensor.iter_mut().zip(vector.iter()) {
+            *t = *v;
+        }
+        tensor
+    }
+
+    /// Converts a tensor of shape `[nrows, ncols]` to a matrix, e.g. a
+    /// `DMatrix<N>` or a `Matrix3<N>`.  Returns an error if the tensor isn't
+    /// a matrix or its shape doesn't match the fixed dimensions of the
+    /// matrix type.
+    pub fn to_matrix<R: Dim, C: Dim>(&self) -> Result<MatrixMN<N, R, C>>
+    where
+        DefaultAllocator: Allocator<N, R, C>,
+    {
+        if self.dims().len() != 2 {
+            return Err(invalid_arg!(
+                "Expected a tensor of rank 2 but its shape is {:?}",
+                self.dims()
+            ));
+        }
+        let nrows = dim::<R>(self.dims()[0], "rows")?;
+        let ncols = dim::<C>(self.dims()[1], "columns")?;
+        Ok(MatrixMN::from_row_slice_generic(nrows, ncols, self))
+    }
+
+    /// Converts a tensor of shape `[n]` to a vector, e.g. a `DVector<N>` or
+    /// a `Vector3<N>`.  Returns an error if the tensor isn't a vector or its
+    /// length doesn't match the fixed dimension of the vector type.
+    pub fn to_vector<D: Dim>(&self) -> Result<VectorN<N, D>>
+    where
+        DefaultAllocator: Allocator<N, D>,
+    {
+        if self.dims().len() != 1 {
+            return Err(invalid_arg!(
+                "Expected a tensor of rank 1 but its shape is {:?}",
+                self.dims()
+            ));
+        }
+        let n = dim::<D>(self.dims()[0], "elements")?;
+        Ok(VectorN::from_column_slice_generic(n, U1, self))
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nalgebra::DMatrix;
+    use nalgebra::DVector;
+    use nalgebra::Matrix2x3;
+    use nalgebra::Vector3;
+
+    #[test]
+    fn matrix_round_trip() {
+        let matrix = Matrix2x3::new(1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0);
+        let tensor = Tensor::from(&matrix);
+        assert_eq!(tensor.dims(), &[2, 3]);
+        assert_eq!(&tensor[..], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(tensor.to_matrix().unwrap(), matrix);
+
+        let dynamic: DMatrix<f32> = tensor.to_matrix().unwrap();
+        assert_eq!(dynamic[(1, 0)], 4.0);
+        assert_eq!(Tensor::from(&dynamic.transpose()).dims(), &[3, 2]);
+
+        assert!(tensor.to_matrix::<U1, nalgebra::U3>().is_err());
+        assert!(Tensor::<f32>::new(&[6])
+            .to_matrix::<nalgebra::Dynamic, nalgebra::Dynamic>()
+            .is_err());
+    }
+
+    #[test]
+    fn vector_round_trip() {
+        let vector = Vector3::new(1i32, 2, 3);
+        let tensor = Tensor::from_vector(&vector);
+        assert_eq!(tensor.dims(), &[3]);
+        assert_eq!(Tensor::from(&vector).dims(), &[3, 1]);
+        assert_eq!(tensor.to_vector().unwrap(), vector);
+        let dynamic: DVector<i32> = tensor.to_vector().unwrap();
+        assert_eq!(dynamic.len(), 3);
+        assert!(tensor.to_vector::<nalgebra::U2>().is_err());
+    }
+}
@@ -0,0 +1,136 @@
+//! Conversions between `Tensor` and ndarray arrays.
+//!
+//! Both tensors and standard layout arrays are stored in row-major order, so
+//! element `[i, j, ...]` of a tensor is always element `[i, j, ...]` of the
+//! array, whatever the memory layout of the array.
+
+use crate::FetchToken;
+use crate::Result;
+use crate::SessionRunArgs;
+use crate::Tensor;
+use crate::TensorType;
+use ndarray::Array;
+use ndarray::ArrayBase;
+use ndarray::Data;
+use ndarray::Dimension;
+use ndarray::IxDyn;
+
+impl<T: TensorType> Tensor<T> {
+    /// Creates a tensor with the shape and elements of an array.
+    pub fn from_array<S: Data<Elem = T>, D: Dimension>(array: &ArrayBase<S, D>) -> Self {
+        let dims: Vec<u64> = array.shape().iter().map(|&d| d as u64).collect();
+        let mut tensor = Tensor::new(&dims);
+        for (t, a) in tensor.iter_mut().zip(array.iter()) {
+            *t = a.clone();
+        }
+        tensor
+    }
+
+    /// Converts the tensor to an array of the same shape, e.g. an
+    /// `Array2<T>` or an `ArrayD<T>`.  Returns an error if the rank of the
+    /// tensor doesn't match the dimension type of the array.
+    pub fn into_array<D: Dimension>(self) -> Result<Array<T, D>> {
+        if let Some(rank) = D::NDIM {
+            if rank != self.dims().len() {
+                return Err(invalid_arg!(
+                    "Expected a tensor of rank {} but its shape is {:?}",
+                    rank,
+                    self.dims()
+                ));
+            }
+        }
+        let shape: Vec<usize> = self.dims().iter().map(|&d| d as usize).collect();
+        let values = self.iter().cloned().collect();
+        Array::from_shape_vec(IxDyn(&shape), values)
+            .and_then(|array| array.into_dimensionality::<D>())
+            .map_err(|e| {
+                invalid_arg!(
+                    "Unable to convert a tensor of shape {:?} to an array: {}",
+                    shape,
+                    e
+                )
+            })
+    }
+}
+
+impl<'l> SessionRunArgs<'l> {
+    /// Retrieves the output tensor for `token` as an array, e.g. an
+    /// `Array2<T>` or an `ArrayD<T>`.  See `SessionRunArgs::fetch` and
+    /// `Tensor::into_array`.
+    pub fn fetch_array<T: TensorType, D: Dimension>(
+        &mut self,
+        token: FetchToken,
+    ) -> Result<Array<T, D>> {
+        self.fetch::<T>(token)?.into_array()
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Graph;
+    use crate::Session;
+    use crate::SessionOptions;
+    use ndarray::arr1;
+    use ndarray::arr2;
+    use ndarray::Array3;
+    use ndarray::ArrayD;
+
+    #[test]
+    fn array_round_trip() {
+        let array = arr2(&[[1.0f32, 2.0, 3.0], [4.0, 5.0, 6.0]]);
+        let tensor = Tensor::from_array(&array);
+        assert_eq!(tensor.dims(), &[2, 3]);
+        assert_eq!(&tensor[..], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        assert_eq!(tensor.clone().into_array::<ndarray::Ix2>().unwrap(), array);
+
+        // A transposed view isn't in standard layout, but keeps its logical
+        // order.
+        let transposed = Tensor::from_array(&array.t());
+        assert_eq!(transposed.dims(), &[3, 2]);
+        assert_eq!(&transposed[..], &[1.0, 4.0, 2.0, 5.0, 3.0, 6.0]);
+
+        let dynamic: ArrayD<f32> = tensor.clone().into_array().unwrap();
+        assert_eq!(dynamic.shape(), &[2, 3]);
+        assert_eq!(dynamic[[1, 0]], 4.0);
+        assert!(tensor.into_array::<ndarray::Ix1>().is_err());
+
+        let scalar = Tensor::from(7i32).into_array::<ndarray::Ix0>().unwrap();
+        assert_eq!(scalar[()], 7);
+    }
+
+    #[test]
+    fn fetch_array() {
+        let mut graph = Graph::new();
+        let x = {
+            let mut nd = graph.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", crate::DataType::Int32).unwrap();
+            nd.finish().unwrap()
+        };
+        let perm = {
+            let mut nd = graph.new_operation("Const", "perm").unwrap();
+            nd.set_attr_type("dtype", crate::DataType::Int32).unwrap();
+            nd.set_attr_tensor("value", Tensor::from_array(&arr1(&[2, 0, 1])))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = graph.new_operation("Transpose", "y").unwrap();
+            nd.add_input(x.clone());
+            nd.add_input(perm);
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&SessionOptions::new(), &graph).unwrap();
+        let input = Array3::from_shape_fn((2, 3, 4), |(i, j, k)| (i * 100 + j * 10 + k) as i32);
+        let input_tensor = Tensor::from_array(&input);
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &input_tensor);
+        let token = args.request_fetch(&y, 0);
+        session.run(&mut args).unwrap();
+        let output: Array3<i32> = args.fetch_array(token).unwrap();
+        assert_eq!(output.shape(), &[4, 2, 3]);
+        assert_eq!(output, input.permuted_axes([2, 0, 1]));
+    }
+}
@@ -0,0 +1,384 @@
+use crate::Graph;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Shape;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::fmt::Display;
+use std::fmt::Formatter;
+
+/// Element-wise ops which take one floating point operation per output
+/// element.
+const ELEMENTWISE_OPS: &[&str] = &[
+    "Add",
+    "AddV2",
+    "BiasAdd",
+    "Div",
+    "Log",
+    "Maximum",
+    "Minimum",
+    "Mul",
+    "Neg",
+    "Pow",
+    "RealDiv",
+    "Reciprocal",
+    "Rsqrt",
+    "Sqrt",
+    "Square",
+    "SquaredDifference",
+    "Sub",
+];
+
+/// Ops which create variables from their `shape` attribute.
+const VARIABLE_OPS: &[&str] = &["Variable", "VariableV2", "VarHandleOp"];
+
+/// The estimated cost of a single operation, from a `CostReport`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OpCost {
+    name: String,
+    op_type: String,
+    flops: Option<u64>,
+    parameters: u64,
+}
+
+impl OpCost {
+    /// Returns the name of the operation.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the type of the operation, e.g. `"MatMul"`.
+    pub fn op_type(&self) -> &str {
+        &self.op_type
+    }
+
+    /// Returns the estimated number of floating point operations in a run
+    /// of the operation, or None if there's no formula for the op or the
+    /// shapes it needs aren't fully known.  Ops which don't compute
+    /// anything, such as `Reshape`, have no formula.
+    pub fn flops(&self) -> Option<u64> {
+        self.flops
+    }
+
+    /// Returns the number of elements in the variable created by the
+    /// operation, or 0 if it doesn't create one.
+    pub fn parameters(&self) -> u64 {
+        self.parameters
+    }
+}
+
+/// Estimated floating point operations and parameter counts of the
+/// operations in a graph, from their static shapes.
+///
+/// ```no_run
+/// # use tensorflow::CostReport;
+/// # use tensorflow::Graph;
+/// # let graph = Graph::new();
+/// let report = CostReport::analyze(&graph)?;
+/// println!("{}", report);
+/// for op in report.ops().iter().filter(|op| op.flops().is_none()) {
+///     println!("No estimate for {} ({})", op.name(), op.op_type());
+/// }
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+///
+/// The formulas follow those TensorFlow registers for `tf.profiler`'s
+/// `float_ops` view: a multiply-add counts as two operations, element-wise
+/// ops count one per output element, and ops which only move data count
+/// none.  Placeholders usually have an unknown batch dimension, so give
+/// them a fixed shape to get estimates for the ops which depend on them.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CostReport {
+    ops: Vec<OpCost>,
+}
+
+impl CostReport {
+    /// Estimates the cost of each operation in `graph`.
+    pub fn analyze(graph: &Graph) -> Result<Self> {
+        let mut ops = Vec::new();
+        for operation in graph.operation_iter() {
+            let op_type = operation.op_type()?;
+            let parameters = if VARIABLE_OPS.contains(&op_type.as_str()) {
+                elements(&operation.get_attr_shape("shape")?).unwrap_or(0)
+            } else {
+                0
+            };
+            ops.push(OpCost {
+                name: operation.name()?,
+                flops: flops(graph, &operation, &op_type)?,
+                op_type,
+                parameters,
+            });
+        }
+        ops.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(Self { ops })
+    }
+
+    /// Returns the costs of the operations, sorted by name.
+    pub fn ops(&self) -> &[OpCost] {
+        &self.ops
+    }
+
+    /// Returns the estimated number of floating point operations in a run
+    /// of the whole graph, counting only the operations with estimates.
+    pub fn total_flops(&self) -> u64 {
+        self.ops.iter().filter_map(|op| op.flops).sum()
+    }
+
+    /// Returns the number of elements in the graph's variables.
+    pub fn total_parameters(&self) -> u64 {
+        self.ops.iter().map(|op| op.parameters).sum()
+    }
+
+    /// Returns the estimated floating point operations summed by op type.
+    pub fn flops_by_op_type(&self) -> BTreeMap<String, u64> {
+        let mut flops = BTreeMap::new();
+        for op in &self.ops {
+            if let Some(f) = op.flops {
+                *flops.entry(op.op_type.clone()).or_insert(0) += f;
+            }
+        }
+        flops
+    }
+}
+
+impl Display for CostReport {
+    /// Writes the operations with a nonzero estimate, most expensive first,
+    /// in the layout of `tf.profiler`'s `float_ops` view.
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let mut ops: Vec<_> = self
+            .ops
+            .iter()
+            .filter(|op| op.flops.unwrap_or(0) > 0)
+            .collect();
+        ops.sort_by(|a, b| b.flops.cmp(&a.flops).then_with(|| a.name.cmp(&b.name)));
+        writeln!(f, "node name | # float_ops")?;
+        writeln!(
+            f,
+            "_TFProfRoot (--/{} flops)",
+            format_count(self.total_flops())
+        )?;
+        for op in ops {
+            let flops = format_count(op.flops.unwrap_or(0));
+            writeln!(f, "  {} ({}/{} flops)", op.name, flops, flops)?;
+        }
+        write!(
+            f,
+            "Total parameters: {}",
+            format_count(self.total_parameters())
+        )
+    }
+}
+
+/// Formats a count the way `tf.profiler` does, e.g. `"2.10m"`.
+fn format_count(count: u64) -> String {
+    let count = count as f64;
+    if count < 1e3 {
+        format!("{}", count)
+    } else if count < 1e6 {
+        format!("{:.2}k", count / 1e3)
+    } else if count < 1e9 {
+        format!("{:.2}m", count / 1e6)
+    } else {
+        format!("{:.2}b", count / 1e9)
+    }
+}
+
+/// Returns the dimensions of `shape` if they're all known.
+fn known_dims(shape: &Shape) -> Option<Vec<u64>> {
+    let rank = shape.dims()?;
+    (0..rank)
+        .map(|i| shape[i].filter(|&d| d >= 0).map(|d| d as u64))
+        .collect()
+}
+
+/// Returns the number of elements in a tensor of shape `shape` if it's
+/// fully known.
+fn elements(shape: &Shape) -> Option<u64> {
+    known_dims(shape).map(|dims| dims.iter().product())
+}
+
+fn input_dims(graph: &Graph, operation: &Operation, index: usize) -> Result<Option<Vec<u64>>> {
+    let (input, input_index) = operation.input(index);
+    let shape = graph.tensor_shape(Output {
+        operation: input,
+        index: input_index as i32,
+    })?;
+    Ok(known_dims(&shape))
+}
+
+fn output_elements(graph: &Graph, operation: &Operation) -> Result<Option<u64>> {
+    let shape = graph.tensor_shape(Output {
+        operation: operation.clone(),
+        index: 0,
+    })?;
+    Ok(elements(&shape))
+}
+
+/// Estimates the floating point operations of `operation`, if there's a
+/// formula for its type.
+fn flops(graph: &Graph, operation: &Operation, op_type: &str) -> Result<Option<u64>> {
+    if ELEMENTWISE_OPS.contains(&op_type) {
+        return output_elements(graph, operation);
+    }
+    Ok(match op_type {
+        "AddN" => output_elements(graph, operation)?
+            .map(|n| n * (operation.num_inputs() as u64).saturating_sub(1)),
+        "AssignAdd" | "AssignSub" => input_dims(graph, operation, 1)?.map(|d| d.iter().product()),
+        // Squares, their sum and the halving.
+        "L2Loss" => input_dims(graph, operation, 0)?
+            .map(|d| (3 * d.iter().product::<u64>()).saturating_sub(1)),
+        // Finding the maximum, subtracting it, exponentiating, summing and
+        // dividing.
+        "Softmax" | "LogSoftmax" => output_elements(graph, operation)?.map(|n| 5 * n),
+        "Sum" | "Prod" | "Max" | "Min" | "ArgMax" | "ArgMin" => {
+            input_dims(graph, operation, 0)?.map(|d| d.iter().product())
+        }
+        // The reduction plus a division per output element.
+        "Mean" => match (
+            input_dims(graph, operation, 0)?,
+            output_elements(graph, operation)?,
+        ) {
+            (Some(d), Some(n)) => Some(d.iter().product::<u64>() + n),
+            _ => None,
+        },
+        "AvgPool" | "MaxPool" => {
+            let ksize = operation.get_attr_int_list("ksize")?;
+            output_elements(graph, operation)?
+                .map(|n| n * ksize.iter().map(|&k| k as u64).product::<u64>())
+        }
+        "MatMul" => {
+            let transpose_a = operation.get_attr_bool("transpose_a")?;
+            match (
+                input_dims(graph, operation, 0)?,
+                output_elements(graph, operation)?,
+            ) {
+                (Some(a), Some(n)) if a.len() == 2 => {
+                    let k = if transpose_a { a[0] } else { a[1] };
+                    Some(2 * k * n)
+                }
+                _ => None,
+            }
+        }
+        "BatchMatMul" | "BatchMatMulV2" => {
+            let adj_x = operation.get_attr_bool("adj_x")?;
+            match (
+                input_dims(graph, operation, 0)?,
+                output_elements(graph, operation)?,
+            ) {
+                (Some(x), Some(n)) if x.len() >= 2 => {
+                    let k = x[x.len() - if adj_x { 2 } else { 1 }];
+                    Some(2 * k * n)
+                }
+                _ => None,
+            }
+        }
+        // The filter is [height, width, in_channels, out_channels] for
+        // Conv2D, and [height, width, in_channels, multiplier] for depthwise
+        // convolutions, whose output elements each use one input channel.
+        "Conv2D" => match (
+            input_dims(graph, operation, 1)?,
+            output_elements(graph, operation)?,
+        ) {
+            (Some(f), Some(n)) if f.len() == 4 => Some(2 * n * f[0] * f[1] * f[2]),
+            _ => None,
+        },
+        "DepthwiseConv2dNative" => match (
+            input_dims(graph, operation, 1)?,
+            output_elements(graph, operation)?,
+        ) {
+            (Some(f), Some(n)) if f.len() == 4 => Some(2 * n * f[0] * f[1]),
+            _ => None,
+        },
+        _ => None,
+    })
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+
+    fn placeholder(g: &mut Graph, name: &str, dims: &[Option<i64>]) -> Operation {
+        let mut nd = g.new_operation("Placeholder", name).unwrap();
+        nd.set_attr_type("dtype", DataType::Float).unwrap();
+        nd.set_attr_shape("shape", &Shape::from(Some(dims.to_vec())))
+            .unwrap();
+        nd.finish().unwrap()
+    }
+
+    fn binary(g: &mut Graph, op_type: &str, name: &str, x: Operation, y: Operation) -> Operation {
+        let mut nd = g.new_operation(op_type, name).unwrap();
+        nd.add_input(x);
+        nd.add_input(y);
+        nd.finish().unwrap()
+    }
+
+    #[test]
+    fn dense() {
+        let mut g = Graph::new();
+        let x = placeholder(&mut g, "x", &[Some(2), Some(3)]);
+        let w = {
+            let mut nd = g.new_operation("VariableV2", "w").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_shape("shape", &Shape::from(Some(vec![Some(3), Some(4)])))
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let b = placeholder(&mut g, "b", &[Some(4)]);
+        let y = binary(&mut g, "MatMul", "y", x, w);
+        binary(&mut g, "BiasAdd", "z", y, b);
+        let unknown = placeholder(&mut g, "unknown", &[None, Some(3)]);
+        let u = placeholder(&mut g, "u", &[Some(3), Some(4)]);
+        binary(&mut g, "MatMul", "v", unknown, u);
+
+        let report = CostReport::analyze(&g).unwrap();
+        let cost = |name: &str| {
+            report
+                .ops()
+                .iter()
+                .find(|op| op.name() == name)
+                .unwrap()
+                .clone()
+        };
+        assert_eq!(cost("y").flops(), Some(2 * 2 * 3 * 4));
+        assert_eq!(cost("z").flops(), Some(2 * 4));
+        assert_eq!(cost("v").flops(), None);
+        assert_eq!(cost("x").flops(), None);
+        assert_eq!(cost("w").parameters(), 12);
+        assert_eq!(report.total_flops(), 56);
+        assert_eq!(report.total_parameters(), 12);
+        assert_eq!(report.flops_by_op_type()["MatMul"], 48);
+        assert_eq!(
+            report.to_string(),
+            "node name | # float_ops\n\
+             _TFProfRoot (--/56 flops)\n  \
+             y (48/48 flops)\n  \
+             z (8/8 flops)\n\
+             Total parameters: 12"
+        );
+    }
+
+    #[test]
+    fn conv2d() {
+        let mut g = Graph::new();
+        let input = placeholder(&mut g, "input", &[Some(1), Some(8), Some(8), Some(3)]);
+        let filter = placeholder(&mut g, "filter", &[Some(3), Some(3), Some(3), Some(16)]);
+        {
+            let mut nd = g.new_operation("Conv2D", "conv").unwrap();
+            nd.add_input(input);
+            nd.add_input(filter);
+            nd.set_attr_int_list("strides", &[1, 1, 1, 1]).unwrap();
+            nd.set_attr_string("padding", "SAME").unwrap();
+            nd.finish().unwrap();
+        }
+        let report = CostReport::analyze(&g).unwrap();
+        let op = report.ops().iter().find(|op| op.name() == "conv").unwrap();
+        assert_eq!(op.flops(), Some(2 * (8 * 8 * 16) * (3 * 3 * 3)));
+        assert_eq!(format_count(op.flops().unwrap()), "55.30k");
+        assert_eq!(format_count(2_100_000), "2.10m");
+    }
+}
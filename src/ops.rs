@@ -14,19 +14,122 @@
 //! ```ignore
 //! mat_mul(&mut scope, a, b)
 //! ```
+//!
+//! Functions which validate their arguments check the types and shapes that
+//! are known while building the graph, so mistakes are reported when the
+//! operation is added rather than when the graph runs.  Sizes which aren't
+//! known until then are left to TensorFlow.
 
 use tensorflow_macros::define_op;
 
 mod array_ops;
 pub use array_ops::*;
 
+pub mod audio;
+
+mod audio_ops;
+pub use audio_ops::*;
+
+mod beam_search;
+pub use beam_search::*;
+
+mod collective_ops;
+pub use collective_ops::*;
+
+mod control_flow_ops;
+pub use control_flow_ops::*;
+
+mod debug_ops;
+pub use debug_ops::*;
+
+mod fake_quant_ops;
+pub use fake_quant_ops::*;
+
+mod histogram_ops;
+pub use histogram_ops::*;
+
+pub mod image;
+
+mod image_ops;
+pub use image_ops::*;
+
+pub mod linalg;
+
+mod linalg_ops;
+pub use linalg_ops::*;
+
+mod logging_ops;
+pub use logging_ops::*;
+
+mod lookup_ops;
+pub use lookup_ops::*;
+
 mod math_ops;
 pub use math_ops::*;
 
+mod nccl_ops;
+pub use nccl_ops::*;
+
+mod nn_ops;
+pub use nn_ops::*;
+
 mod random_ops;
 pub use random_ops::*;
 
+mod segment_ops;
+pub use segment_ops::*;
+
+mod set_ops;
+pub use set_ops::*;
+
+pub mod signal;
+
+mod sort_ops;
+pub use sort_ops::*;
+
+mod sparse_ops;
+pub use sparse_ops::*;
+
+mod spectral_ops;
+pub use spectral_ops::*;
+
 mod state_ops;
 pub use state_ops::*;
 
+pub mod summary;
+
+mod summary_ops;
+pub use summary_ops::*;
+
 define_op!(no_op, NoOp, "NoOp");
+
+/// Runs the graph of `scope`, feeding each value to output 0 of the paired
+/// operation, and returns the values of `outputs`.
+#[cfg(test)]
+pub(crate) fn run<T: crate::TensorType>(
+    scope: &crate::Scope,
+    feeds: &[(&crate::Operation, &dyn crate::FeedValue)],
+    outputs: &[&crate::Output],
+) -> crate::Result<Vec<crate::Tensor<T>>> {
+    let session = crate::Session::new(&crate::SessionOptions::new(), &scope.graph())?;
+    let mut args = crate::SessionRunArgs::new();
+    for (operation, value) in feeds {
+        value.feed_value(&mut args, operation, 0);
+    }
+    let tokens: Vec<_> = outputs
+        .iter()
+        .map(|output| args.request_fetch(&output.operation, output.index))
+        .collect();
+    session.run(&mut args)?;
+    tokens.into_iter().map(|token| args.fetch(token)).collect()
+}
+
+/// Runs the graph of `scope` and returns the value of `output`, panicking if
+/// the run fails.
+#[cfg(test)]
+pub(crate) fn fetch<T: crate::TensorType>(
+    scope: &crate::Scope,
+    output: &crate::Output,
+) -> crate::Tensor<T> {
+    run(scope, &[], &[output]).unwrap().remove(0)
+}
@@ -1,3 +1,414 @@
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::TensorType;
 use tensorflow_macros::define_op;
 
+/// Integer types which TensorFlow uses for indices, i.e. `i32` and `i64`.
+pub trait IndexType: TensorType {}
+
+impl IndexType for i32 {}
+
+impl IndexType for i64 {}
+
+define_op!(identity, Identity, "Identity", args { x });
+
+define_op!(shape, Shape, "Shape", args { x }, attrs {
+    out_type?: DataType => "out_type",
+});
+
+define_op!(rank, Rank, "Rank", args { input });
+
+define_op!(reshape, Reshape, "Reshape", args { tensor, shape });
+
+define_op!(expand_dims, ExpandDims, "ExpandDims", args { input, axis });
+
+define_op!(split, Split, "Split", args { axis, value }, attrs {
+    num_split: i64 => "num_split",
+});
+
 define_op!(zeros_like, ZerosLike, "ZerosLike", args { x });
+
+define_op!(ones_like, OnesLike, "OnesLike", args { x });
+
+define_op!(slice, Slice, "Slice", args { input, begin, size });
+
+define_op!(pad, Pad, "Pad", args { input, paddings });
+
+define_op!(reverse, Reverse, "ReverseV2", args { tensor, axis });
+
+define_op!(transpose, Transpose, "Transpose", args { x, perm });
+
+define_op!(gather, Gather, "GatherV2", args { params, indices, axis }, attrs {
+    batch_dims?: i64 => "batch_dims",
+});
+
+define_op!(one_hot_op, OneHot, "OneHot", args { indices, depth, on_value, off_value }, attrs {
+    axis?: i64 => "axis",
+});
+
+/// Returns a one-hot encoding of `indices` with `depth` classes, as a tensor
+/// of `T` with a new innermost dimension of size `depth`.  Positions matching
+/// the index are `on_value` and all others are `off_value`; indices outside
+/// `0..depth` produce rows of only `off_value`.
+///
+/// ```ignore
+/// let labels = one_hot(&mut scope, indices, 10, 1.0f32, 0.0)?;
+/// ```
+pub fn one_hot<T: TensorType>(
+    scope: &mut Scope,
+    indices: Output,
+    depth: i32,
+    on_value: T,
+    off_value: T,
+) -> Result<Output> {
+    if depth < 0 {
+        return Err(invalid_arg!(
+            "One-hot depth must not be negative, but was {}",
+            depth
+        ));
+    }
+    let mut scope = scope.new_sub_scope("one_hot");
+    let depth = ops::constant(&mut scope, depth)?;
+    let on_value = ops::constant(&mut scope, on_value)?;
+    let off_value = ops::constant(&mut scope, off_value)?;
+    Ok(one_hot_op(&mut scope, indices, depth, on_value, off_value)?.into())
+}
+
+define_op!(gather_nd_op, GatherNd, "GatherNd", args { params, indices });
+
+define_op!(
+    scatter_nd_op,
+    ScatterNd,
+    "ScatterNd",
+    args {
+        indices,
+        updates,
+        shape
+    }
+);
+
+define_op!(where_, Where, "Where", args { input });
+
+/// Returns the elements (or slices) of `tensor` where the boolean `mask` is
+/// true.
+///
+/// The shape of `mask` must match the leading dimensions of `tensor`.  The
+/// result has one dimension for the true entries of the mask followed by the
+/// remaining dimensions of `tensor`, e.g. masking boxes of shape `[n, 4]`
+/// with `scores > threshold` of shape `[n]` gives the boxes above the
+/// threshold, with shape `[?, 4]`.  `Tensor::masked` does the same on the
+/// host.
+pub fn boolean_mask(scope: &mut Scope, tensor: Output, mask: Output) -> Result<Output> {
+    let mask_type = mask.operation.output_type(mask.index as usize);
+    if mask_type != DataType::Bool {
+        return Err(invalid_arg!(
+            "The mask must be bool, but {:?} is {}",
+            mask,
+            mask_type
+        ));
+    }
+    let tensor_dims = known_dims(scope, &tensor)?;
+    let mask_dims = known_dims(scope, &mask)?;
+    if let (Some(tensor_dims), Some(mask_dims)) = (&tensor_dims, &mask_dims) {
+        let compatible = mask_dims.len() <= tensor_dims.len()
+            && mask_dims
+                .iter()
+                .zip(tensor_dims)
+                .all(|(a, b)| a.is_none() || b.is_none() || a == b);
+        if !compatible {
+            return Err(invalid_arg!(
+                "A mask of shape {:?} can't be applied to a tensor of shape {:?}",
+                mask_dims,
+                tensor_dims
+            ));
+        }
+    }
+    let mut scope = scope.new_sub_scope("boolean_mask");
+    // The coordinates of the true entries index the leading dimensions.
+    let indices = where_(&mut scope, mask)?;
+    Ok(gather_nd_op(&mut scope, tensor, indices)?.into())
+}
+
+/// Returns the dimensions of `output` if its rank is known.
+pub(crate) fn known_dims(scope: &Scope, output: &Output) -> Result<Option<Vec<Option<i64>>>> {
+    // `Shape` is the op here, not the type.
+    let crate::Shape(dims) = scope.graph().tensor_shape(output.clone())?;
+    Ok(dims)
+}
+
+pub(crate) fn check_index_type(output: &Output, name: &str) -> Result<()> {
+    let data_type = output.operation.output_type(output.index as usize);
+    match data_type {
+        DataType::Int32 | DataType::Int64 => Ok(()),
+        _ => Err(invalid_arg!(
+            "{} must be int32 or int64, but {:?} is {}",
+            name,
+            output,
+            data_type
+        )),
+    }
+}
+
+/// Returns the dimension of `dims` at `i` if it is known.
+fn dim(dims: &Option<Vec<Option<i64>>>, i: usize) -> Option<i64> {
+    dims.as_ref()
+        .and_then(|dims| dims.get(i).cloned().flatten())
+}
+
+/// Gathers slices of `params` at the multi-dimensional `indices`.
+///
+/// The last dimension of `indices` (of size `k`) indexes into the first `k`
+/// dimensions of `params`, so the result has shape
+/// `indices.shape[..-1] + params.shape[k..]`.  For example, with
+/// `indices = [[0, 1], [1, 0]]` the result is `[params[0][1], params[1][0]]`.
+///
+/// `indices` must have at least one dimension, and its last dimension may not
+/// exceed the rank of `params`.
+pub fn gather_nd(scope: &mut Scope, params: Output, indices: Output) -> Result<Output> {
+    check_index_type(&indices, "Indices")?;
+    let params_dims = known_dims(scope, &params)?;
+    let indices_dims = known_dims(scope, &indices)?;
+    if let Some(dims) = &indices_dims {
+        if dims.is_empty() {
+            return Err(invalid_arg!(
+                "Indices for gather_nd must have at least one dimension"
+            ));
+        }
+        if let (Some(Some(k)), Some(params_dims)) = (dims.last(), &params_dims) {
+            if *k as usize > params_dims.len() {
+                return Err(invalid_arg!(
+                    "Indices of shape {:?} index {} dimensions, but params of shape {:?} have only {}",
+                    dims,
+                    k,
+                    params_dims,
+                    params_dims.len()
+                ));
+            }
+        }
+    }
+    Ok(gather_nd_op(&mut scope.new_sub_scope("gather_nd"), params, indices)?.into())
+}
+
+/// Creates a tensor of the given `shape`, filled with zeros except for
+/// `updates` scattered to the multi-dimensional `indices`.  This is the
+/// inverse of `gather_nd`; values at duplicate indices are summed.
+///
+/// `updates` must have shape `indices.shape[..-1] + shape[k..]`, where `k` is
+/// the last dimension of `indices`.
+pub fn scatter_nd(
+    scope: &mut Scope,
+    indices: Output,
+    updates: Output,
+    shape: &[i64],
+) -> Result<Output> {
+    check_index_type(&indices, "Indices")?;
+    let indices_dims = known_dims(scope, &indices)?;
+    let updates_dims = known_dims(scope, &updates)?;
+    if let Some(dims) = &indices_dims {
+        if dims.is_empty() {
+            return Err(invalid_arg!(
+                "Indices for scatter_nd must have at least one dimension"
+            ));
+        }
+        let batch_dims = &dims[..dims.len() - 1];
+        if let Some(Some(k)) = dims.last() {
+            let k = *k as usize;
+            if k > shape.len() {
+                return Err(invalid_arg!(
+                    "Indices of shape {:?} index {} dimensions, but the output shape {:?} has only {}",
+                    dims,
+                    k,
+                    shape,
+                    shape.len()
+                ));
+            }
+            let expected: Vec<Option<i64>> = batch_dims
+                .iter()
+                .cloned()
+                .chain(shape[k..].iter().map(|&d| Some(d)))
+                .collect();
+            if let Some(updates_dims) = &updates_dims {
+                let compatible = updates_dims.len() == expected.len()
+                    && updates_dims
+                        .iter()
+                        .zip(&expected)
+                        .all(|(a, b)| a.is_none() || b.is_none() || a == b);
+                if !compatible {
+                    return Err(invalid_arg!(
+                        "Updates must have shape {:?} for indices of shape {:?} and output shape {:?}, but have shape {:?}",
+                        expected,
+                        dims,
+                        shape,
+                        updates_dims
+                    ));
+                }
+            }
+        }
+    }
+    let mut scope = scope.new_sub_scope("scatter_nd");
+    let shape = if indices.operation.output_type(indices.index as usize) == DataType::Int64 {
+        ops::constant(&mut scope, shape)?
+    } else {
+        let shape: Vec<i32> = shape.iter().map(|&d| d as i32).collect();
+        ops::constant(&mut scope, &shape[..])?
+    };
+    Ok(scatter_nd_op(&mut scope, indices, updates, shape)?.into())
+}
+
+/// Gathers from each example of a batch separately: for every position of
+/// the leading `indices.rank - 1` (batch) dimensions, which must match the
+/// leading dimensions of `params`, the innermost indices select along the
+/// next dimension of `params`.
+///
+/// For example, with `params` of shape `[batch, n, d]` and `indices` of
+/// shape `[batch, m]`, the result has shape `[batch, m, d]`, where
+/// `result[b][i] = params[b][indices[b][i]]`.  The rank of `indices` must be
+/// known.
+pub fn batch_gather(scope: &mut Scope, params: Output, indices: Output) -> Result<Output> {
+    check_index_type(&indices, "Indices")?;
+    let params_dims = known_dims(scope, &params)?;
+    let indices_dims = known_dims(scope, &indices)?
+        .ok_or_else(|| invalid_arg!("The rank of the indices for batch_gather must be known"))?;
+    if indices_dims.is_empty() {
+        return Err(invalid_arg!(
+            "Indices for batch_gather must have at least one dimension"
+        ));
+    }
+    let batch_dims = indices_dims.len() - 1;
+    if let Some(dims) = &params_dims {
+        if dims.len() <= batch_dims {
+            return Err(invalid_arg!(
+                "Params of shape {:?} need more than the {} batch dimensions of indices of shape {:?}",
+                dims,
+                batch_dims,
+                indices_dims
+            ));
+        }
+    }
+    for i in 0..batch_dims {
+        if let (Some(a), Some(b)) = (dim(&params_dims, i), indices_dims[i]) {
+            if a != b {
+                return Err(invalid_arg!(
+                    "Batch dimension {} of params ({:?}) and indices ({:?}) must match",
+                    i,
+                    params_dims,
+                    indices_dims
+                ));
+            }
+        }
+    }
+    let mut scope = scope.new_sub_scope("batch_gather");
+    let axis = ops::constant(&mut scope, batch_dims as i32)?;
+    Ok(Gather::new()
+        .batch_dims(batch_dims as i64)
+        .build(&mut scope, params, indices, axis)?
+        .into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    fn matrix(scope: &mut Scope, dims: &[u64], values: &[f32]) -> Output {
+        ops::constant(scope, Tensor::new(dims).with_values(values).unwrap())
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn gather_and_scatter_nd() {
+        let mut scope = Scope::new_root_scope();
+        let params = matrix(&mut scope, &[2, 2], &[1.0, 2.0, 3.0, 4.0]);
+        let indices: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 2]).with_values(&[0i32, 1, 1, 0]).unwrap(),
+        )
+        .unwrap()
+        .into();
+        let gathered = gather_nd(&mut scope, params.clone(), indices.clone()).unwrap();
+        let scattered = scatter_nd(&mut scope, indices.clone(), gathered.clone(), &[2, 2]).unwrap();
+        assert_eq!(&fetch::<f32>(&scope, &gathered)[..], &[2.0, 3.0]);
+        assert_eq!(&fetch::<f32>(&scope, &scattered)[..], &[0.0, 2.0, 3.0, 0.0]);
+
+        // Indexing three dimensions of a matrix.
+        let too_many: Output = ops::constant(&mut scope, Tensor::<i32>::new(&[1, 3]))
+            .unwrap()
+            .into();
+        let error = gather_nd(&mut scope, params.clone(), too_many).unwrap_err();
+        assert!(error.message().contains("only 2"), "{}", error);
+        // Float indices.
+        assert!(gather_nd(&mut scope, params.clone(), params.clone()).is_err());
+        // Updates with the wrong shape.
+        assert!(scatter_nd(&mut scope, indices, params, &[2, 2]).is_err());
+    }
+
+    #[test]
+    fn batched_gather() {
+        let mut scope = Scope::new_root_scope();
+        let params = matrix(&mut scope, &[2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let indices: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 2]).with_values(&[2i64, 0, 1, 1]).unwrap(),
+        )
+        .unwrap()
+        .into();
+        let gathered = batch_gather(&mut scope, params.clone(), indices).unwrap();
+        let result = fetch::<f32>(&scope, &gathered);
+        assert_eq!(result.dims(), &[2, 2]);
+        assert_eq!(&result[..], &[3.0, 1.0, 5.0, 5.0]);
+
+        let mismatched: Output = ops::constant(&mut scope, Tensor::<i32>::new(&[3, 1]))
+            .unwrap()
+            .into();
+        assert!(batch_gather(&mut scope, params, mismatched).is_err());
+    }
+
+    #[test]
+    fn masking() {
+        let mut scope = Scope::new_root_scope();
+        let boxes = matrix(&mut scope, &[3, 2], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let mask: Output = ops::constant(&mut scope, &[true, false, true][..])
+            .unwrap()
+            .into();
+        let indices: Output = where_(&mut scope, mask.clone()).unwrap().into();
+        let selected = boolean_mask(&mut scope, boxes.clone(), mask).unwrap();
+        assert_eq!(&fetch::<i64>(&scope, &indices)[..], &[0, 2]);
+        let result = fetch::<f32>(&scope, &selected);
+        assert_eq!(result.dims(), &[2, 2]);
+        assert_eq!(&result[..], &[1.0, 2.0, 5.0, 6.0]);
+
+        assert!(boolean_mask(&mut scope, boxes.clone(), boxes.clone()).is_err());
+        let wrong_shape: Output = ops::constant(&mut scope, &[true, false][..])
+            .unwrap()
+            .into();
+        assert!(boolean_mask(&mut scope, boxes, wrong_shape).is_err());
+    }
+
+    #[test]
+    fn one_hot_encoding() {
+        let mut scope = Scope::new_root_scope();
+        let indices = ops::constant(&mut scope, &[2i64, 0, 5][..]).unwrap();
+        let encoded = one_hot(&mut scope, indices.into(), 3, 1.0f32, -1.0).unwrap();
+        assert!(one_hot(&mut scope, encoded.clone(), -1, 1.0f32, 0.0).is_err());
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&encoded.operation, encoded.index);
+        session.run(&mut args).unwrap();
+        let result = args.fetch::<f32>(token).unwrap();
+        assert_eq!(result.dims(), &[3, 3]);
+        assert_eq!(
+            &result[..],
+            &[-1.0, -1.0, 1.0, 1.0, -1.0, -1.0, -1.0, -1.0, -1.0]
+        );
+    }
+}
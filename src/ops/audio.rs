@@ -0,0 +1,375 @@
+//! Audio decoding and feature extraction for speech pipelines.
+//!
+//! A typical pipeline decodes a WAV file with `decode_wav`, turns the audio
+//! into a `spectrogram` and then computes `mfcc` features from it, all inside
+//! the graph.
+
+use super::known_dims;
+use super::AudioSpectrogram;
+use super::AudioSummary;
+use super::DecodeWav;
+use super::Mfcc;
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+fn check_input(
+    scope: &Scope,
+    input: &Output,
+    data_type: DataType,
+    ranks: &[usize],
+    name: &str,
+) -> Result<()> {
+    let actual = input.operation.output_type(input.index as usize);
+    if actual != data_type {
+        return Err(invalid_arg!(
+            "{} must be {}, but is {}",
+            name,
+            data_type,
+            actual
+        ));
+    }
+    if let Some(dims) = known_dims(scope, input)? {
+        if !ranks.contains(&dims.len()) {
+            return Err(invalid_arg!(
+                "{} must have rank {:?}, but has shape {:?}",
+                name,
+                ranks,
+                dims
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Options for `decode_wav`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DecodeWavOptions {
+    channels: Option<u32>,
+    samples: Option<u64>,
+}
+
+impl DecodeWavOptions {
+    /// Returns this many channels, duplicating or dropping channels of the
+    /// file as needed.  By default, all channels of the file are returned.
+    pub fn with_channels(self, channels: u32) -> Self {
+        Self {
+            channels: Some(channels),
+            ..self
+        }
+    }
+
+    /// Returns this many samples, truncating or zero-padding the audio of the
+    /// file as needed.  By default, all samples of the file are returned.
+    pub fn with_samples(self, samples: u64) -> Self {
+        Self {
+            samples: Some(samples),
+            ..self
+        }
+    }
+}
+
+/// The result of `decode_wav`.
+#[derive(Debug, Clone)]
+pub struct DecodedWav {
+    /// The samples, as floats between -1 and 1, of shape
+    /// `[samples, channels]`.
+    pub audio: Output,
+    /// The sample rate in Hz, as an int32 scalar.
+    pub sample_rate: Output,
+}
+
+/// Decodes the 16-bit PCM WAV file in the string scalar `contents`, e.g. from
+/// `ReadFile`.
+pub fn decode_wav(
+    scope: &mut Scope,
+    contents: Output,
+    opts: DecodeWavOptions,
+) -> Result<DecodedWav> {
+    check_input(
+        scope,
+        &contents,
+        DataType::String,
+        &[0],
+        "The contents of a WAV file",
+    )?;
+    let mut decode = DecodeWav::new();
+    if let Some(channels) = opts.channels {
+        decode = decode.desired_channels(i64::from(channels));
+    }
+    if let Some(samples) = opts.samples {
+        decode = decode.desired_samples(samples as i64);
+    }
+    let op = decode.build(scope, contents)?;
+    Ok(DecodedWav {
+        audio: Output {
+            operation: op.clone(),
+            index: 0,
+        },
+        sample_rate: Output {
+            operation: op,
+            index: 1,
+        },
+    })
+}
+
+/// Encodes `audio`, a float tensor of shape `[samples, channels]`, as a
+/// 16-bit PCM WAV file with the sample rate given by the int32 scalar
+/// `sample_rate`.  Returns the file as a string scalar.
+pub fn encode_wav(scope: &mut Scope, audio: Output, sample_rate: Output) -> Result<Output> {
+    check_input(scope, &audio, DataType::Float, &[2], "The audio to encode")?;
+    check_input(
+        scope,
+        &sample_rate,
+        DataType::Int32,
+        &[0],
+        "The sample rate",
+    )?;
+    Ok(super::encode_wav_op(scope, audio, sample_rate)?.into())
+}
+
+/// Computes the spectrogram of `audio`, a float tensor of shape
+/// `[samples, channels]`, using windows of `window_size` samples which start
+/// every `stride` samples.
+///
+/// The result has shape `[channels, windows, bins]`, where the number of
+/// bins is half the window size rounded up to a power of two, plus one.  It
+/// holds magnitudes, or their squares with `magnitude_squared`.
+pub fn spectrogram(
+    scope: &mut Scope,
+    audio: Output,
+    window_size: u32,
+    stride: u32,
+    magnitude_squared: bool,
+) -> Result<Output> {
+    check_input(scope, &audio, DataType::Float, &[2], "The audio")?;
+    if window_size == 0 || stride == 0 {
+        return Err(invalid_arg!(
+            "The window size and stride of a spectrogram must be positive, but are {} and {}",
+            window_size,
+            stride
+        ));
+    }
+    Ok(AudioSpectrogram::new()
+        .window_size(i64::from(window_size))
+        .stride(i64::from(stride))
+        .magnitude_squared(magnitude_squared)
+        .build(scope, audio)?
+        .into())
+}
+
+/// Options for `mfcc`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MfccOptions {
+    lower_frequency_limit: f32,
+    upper_frequency_limit: f32,
+    filterbank_channel_count: u32,
+    dct_coefficient_count: u32,
+}
+
+impl Default for MfccOptions {
+    fn default() -> Self {
+        Self {
+            lower_frequency_limit: 20.0,
+            upper_frequency_limit: 4000.0,
+            filterbank_channel_count: 40,
+            dct_coefficient_count: 13,
+        }
+    }
+}
+
+impl MfccOptions {
+    /// Sets the lowest frequency, in Hz, of the mel filterbank.  Defaults to
+    /// 20.
+    pub fn with_lower_frequency_limit(self, lower_frequency_limit: f32) -> Self {
+        Self {
+            lower_frequency_limit,
+            ..self
+        }
+    }
+
+    /// Sets the highest frequency, in Hz, of the mel filterbank.  Defaults to
+    /// 4000.
+    pub fn with_upper_frequency_limit(self, upper_frequency_limit: f32) -> Self {
+        Self {
+            upper_frequency_limit,
+            ..self
+        }
+    }
+
+    /// Sets the number of mel filterbank channels.  Defaults to 40.
+    pub fn with_filterbank_channel_count(self, filterbank_channel_count: u32) -> Self {
+        Self {
+            filterbank_channel_count,
+            ..self
+        }
+    }
+
+    /// Sets the number of coefficients returned for each window.  Defaults
+    /// to 13, and can't exceed the number of filterbank channels.
+    pub fn with_dct_coefficient_count(self, dct_coefficient_count: u32) -> Self {
+        Self {
+            dct_coefficient_count,
+            ..self
+        }
+    }
+}
+
+/// Computes mel-frequency cepstral coefficients from a squared-magnitude
+/// `spectrogram` (see `spectrogram`) of audio with the given int32 scalar
+/// `sample_rate`.
+///
+/// The result has shape `[channels, windows, dct_coefficient_count]`.
+pub fn mfcc(
+    scope: &mut Scope,
+    spectrogram: Output,
+    sample_rate: Output,
+    opts: MfccOptions,
+) -> Result<Output> {
+    check_input(
+        scope,
+        &spectrogram,
+        DataType::Float,
+        &[3],
+        "The spectrogram",
+    )?;
+    check_input(
+        scope,
+        &sample_rate,
+        DataType::Int32,
+        &[0],
+        "The sample rate",
+    )?;
+    if !(0.0 <= opts.lower_frequency_limit
+        && opts.lower_frequency_limit < opts.upper_frequency_limit)
+    {
+        return Err(invalid_arg!(
+            "MFCC frequency limits must satisfy 0 <= lower < upper, but are {} and {}",
+            opts.lower_frequency_limit,
+            opts.upper_frequency_limit
+        ));
+    }
+    if opts.dct_coefficient_count == 0 || opts.dct_coefficient_count > opts.filterbank_channel_count
+    {
+        return Err(invalid_arg!(
+            "The number of MFCC coefficients must be between 1 and the number of filterbank channels ({}), but is {}",
+            opts.filterbank_channel_count,
+            opts.dct_coefficient_count
+        ));
+    }
+    Ok(Mfcc::new()
+        .lower_frequency_limit(opts.lower_frequency_limit)
+        .upper_frequency_limit(opts.upper_frequency_limit)
+        .filterbank_channel_count(i64::from(opts.filterbank_channel_count))
+        .dct_coefficient_count(i64::from(opts.dct_coefficient_count))
+        .build(scope, spectrogram, sample_rate)?
+        .into())
+}
+
+/// Returns a serialized `Summary` protocol buffer with up to `max_outputs`
+/// clips from `audio`, a float tensor of shape `[batch, samples]` or
+/// `[batch, samples, channels]` with values between -1 and 1, for display in
+/// TensorBoard.
+pub fn audio_summary(
+    scope: &mut Scope,
+    tag: &str,
+    audio: Output,
+    sample_rate: f32,
+    max_outputs: u32,
+) -> Result<Output> {
+    check_input(scope, &audio, DataType::Float, &[2, 3], "The audio")?;
+    if sample_rate <= 0.0 || max_outputs == 0 {
+        return Err(invalid_arg!(
+            "An audio summary needs a positive sample rate and number of outputs, but got {} and {}",
+            sample_rate,
+            max_outputs
+        ));
+    }
+    let tag = ops::constant(scope, tag.to_string())?;
+    let sample_rate = ops::constant(scope, sample_rate)?;
+    Ok(AudioSummary::new()
+        .max_outputs(i64::from(max_outputs))
+        .build(scope, tag, audio, sample_rate)?
+        .into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    #[test]
+    fn wav_to_mfcc() {
+        let mut scope = Scope::new_root_scope();
+        let samples: Vec<f32> = (0..1024).map(|i| (i as f32 * 0.3).sin() * 0.5).collect();
+        let audio = ops::constant(
+            &mut scope,
+            Tensor::new(&[1024, 1]).with_values(&samples).unwrap(),
+        )
+        .unwrap();
+        let rate = ops::constant(&mut scope, 16000i32).unwrap();
+        let wav = encode_wav(&mut scope, audio.into(), rate.into()).unwrap();
+        let decoded = decode_wav(&mut scope, wav, DecodeWavOptions::default()).unwrap();
+        let spectrum = spectrogram(&mut scope, decoded.audio.clone(), 256, 128, true).unwrap();
+        let features = mfcc(
+            &mut scope,
+            spectrum.clone(),
+            decoded.sample_rate.clone(),
+            MfccOptions::default(),
+        )
+        .unwrap();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let audio_token = args.request_fetch(&decoded.audio.operation, decoded.audio.index);
+        let rate_token =
+            args.request_fetch(&decoded.sample_rate.operation, decoded.sample_rate.index);
+        let spectrum_token = args.request_fetch(&spectrum.operation, spectrum.index);
+        let features_token = args.request_fetch(&features.operation, features.index);
+        session.run(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(audio_token).unwrap().dims(), &[1024, 1]);
+        assert_eq!(&args.fetch::<i32>(rate_token).unwrap()[..], &[16000]);
+        assert_eq!(
+            args.fetch::<f32>(spectrum_token).unwrap().dims(),
+            &[1, 7, 129]
+        );
+        assert_eq!(
+            args.fetch::<f32>(features_token).unwrap().dims(),
+            &[1, 7, 13]
+        );
+    }
+
+    #[test]
+    fn validation() {
+        let mut scope = Scope::new_root_scope();
+        let audio: Output = ops::constant(&mut scope, &[0.0f32; 4][..]).unwrap().into();
+        assert!(spectrogram(&mut scope, audio.clone(), 2, 1, false).is_err());
+        let rate: Output = ops::constant(&mut scope, 16000i32).unwrap().into();
+        assert!(decode_wav(&mut scope, rate.clone(), DecodeWavOptions::default()).is_err());
+        let spectrum: Output = ops::constant(&mut scope, Tensor::<f32>::new(&[1, 2, 3]))
+            .unwrap()
+            .into();
+        assert!(mfcc(
+            &mut scope,
+            spectrum.clone(),
+            rate.clone(),
+            MfccOptions::default().with_dct_coefficient_count(41)
+        )
+        .is_err());
+        assert!(mfcc(
+            &mut scope,
+            spectrum,
+            rate,
+            MfccOptions::default().with_upper_frequency_limit(10.0)
+        )
+        .is_err());
+        assert!(audio_summary(&mut scope, "audio", audio, 16000.0, 1).is_err());
+    }
+}
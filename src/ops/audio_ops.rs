@@ -0,0 +1,30 @@
+use tensorflow_macros::define_op;
+
+define_op!(decode_wav_op, DecodeWav, "DecodeWav", args { contents }, attrs {
+    desired_channels?: i64 => "desired_channels",
+    desired_samples?: i64 => "desired_samples",
+});
+
+define_op!(
+    encode_wav_op,
+    EncodeWav,
+    "EncodeWav",
+    args { audio, sample_rate }
+);
+
+define_op!(audio_spectrogram_op, AudioSpectrogram, "AudioSpectrogram", args { input }, attrs {
+    window_size: i64 => "window_size",
+    stride: i64 => "stride",
+    magnitude_squared?: bool => "magnitude_squared",
+});
+
+define_op!(mfcc_op, Mfcc, "Mfcc", args { spectrogram, sample_rate }, attrs {
+    upper_frequency_limit?: f32 => "upper_frequency_limit",
+    lower_frequency_limit?: f32 => "lower_frequency_limit",
+    filterbank_channel_count?: i64 => "filterbank_channel_count",
+    dct_coefficient_count?: i64 => "dct_coefficient_count",
+});
+
+define_op!(audio_summary_op, AudioSummary, "AudioSummaryV2", args { tag, tensor, sample_rate }, attrs {
+    max_outputs?: i64 => "max_outputs",
+});
@@ -0,0 +1,387 @@
+use super::control_flow_ops::graph_constant;
+use super::control_flow_ops::graph_op;
+use super::control_flow_ops::trace;
+use super::known_dims;
+use crate::ops;
+use crate::DataType;
+use crate::Graph;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Shape;
+use crate::Tensor;
+use crate::WhileBuilder;
+
+/// The result of `BeamSearch::build`, with the beams sorted by decreasing
+/// score.
+#[derive(Debug, Clone)]
+pub struct BeamSearchOutput {
+    /// The int32 tokens of each beam, of shape `[beam_width, max_length]`.
+    /// Once a beam has emitted the end token, it is followed by more end
+    /// tokens until all beams have ended, and by zeros after that, so only
+    /// the first `lengths` tokens of each beam are meaningful.
+    pub sequences: Output,
+    /// The float sum of the log probabilities of the tokens of each beam, of
+    /// shape `[beam_width]`.
+    pub scores: Output,
+    /// The int32 number of tokens of each beam, including the end token if
+    /// the beam ended before `max_length`, of shape `[beam_width]`.
+    pub lengths: Output,
+    /// The final value of each state of the decoder for each beam, with a
+    /// leading dimension of size `beam_width`.
+    pub states: Vec<Output>,
+}
+
+/// A beam search decoder, which generates the most likely sequences of
+/// tokens from a model predicting the next token from the previous one and
+/// some state, e.g. the decoder of a sequence to sequence model.
+///
+/// At each step, every beam is extended with every token, and the
+/// `beam_width` extensions with the highest total log probability are kept.
+/// The decoding runs in a while loop in the graph, so a single session run
+/// decodes a whole sequence, stopping after `max_length` steps or once all
+/// beams have emitted the end token.
+///
+/// ```ignore
+/// let decoded = BeamSearch::new(START, END, 50)
+///     .with_beam_width(5)
+///     .build(&mut scope, &[encoded], |scope, tokens, states| {
+///         let embedded = ops::gather(scope, embeddings.clone(), tokens, axis.clone())?;
+///         // ... compute the log probabilities of the next tokens and the
+///         // next hidden state ...
+///         Ok((log_probs.into(), vec![hidden]))
+///     })?;
+/// ```
+#[derive(Debug, Clone, Copy)]
+pub struct BeamSearch {
+    start_token: i32,
+    end_token: i32,
+    max_length: i32,
+    beam_width: i32,
+}
+
+impl BeamSearch {
+    /// Creates a decoder starting every sequence after `start_token` and
+    /// ending it with `end_token`, generating at most `max_length` tokens
+    /// with a beam width of 4.
+    pub fn new(start_token: i32, end_token: i32, max_length: i32) -> Self {
+        Self {
+            start_token,
+            end_token,
+            max_length,
+            beam_width: 4,
+        }
+    }
+
+    /// Sets the number of sequences kept at each step.  Default is 4.
+    pub fn with_beam_width(self, beam_width: i32) -> Self {
+        Self { beam_width, ..self }
+    }
+
+    /// Adds the decoder to the graph, and returns the decoded sequences.
+    ///
+    /// `initial_state` holds the initial value of each state of the model
+    /// for the sequence to decode, such as an encoded input sentence, and is
+    /// copied for each beam.  `step` is traced once, while building the
+    /// graph, on a fresh scope which gets placeholders standing for the
+    /// previous int32 token of each beam, of shape `[beam_width]`, and each
+    /// state with a leading dimension of size `beam_width`.  It must return
+    /// the float log probabilities of the next token for each beam, of shape
+    /// `[beam_width, vocabulary_size]`, and the new value of each state.
+    pub fn build<F>(
+        &self,
+        scope: &mut Scope,
+        initial_state: &[Output],
+        step: F,
+    ) -> Result<BeamSearchOutput>
+    where
+        F: FnOnce(&mut Scope, Output, &[Output]) -> Result<(Output, Vec<Output>)>,
+    {
+        if self.beam_width <= 0 || self.max_length <= 0 {
+            return Err(invalid_arg!(
+                "Beam search needs a positive beam width and maximum length, but has {} and {}",
+                self.beam_width,
+                self.max_length
+            ));
+        }
+        if self.start_token < 0 || self.end_token < 0 {
+            return Err(invalid_arg!(
+                "The start and end tokens of beam search must not be negative, but are {} \
+                 and {}",
+                self.start_token,
+                self.end_token
+            ));
+        }
+        let beam = self.beam_width as usize;
+        let beam_dim = Some(i64::from(self.beam_width));
+        let mut inputs = vec![
+            (DataType::Int32, Shape(Some(vec![]))),
+            (DataType::Float, Shape(Some(vec![beam_dim]))),
+            (DataType::Bool, Shape(Some(vec![beam_dim]))),
+            (DataType::Int32, Shape(Some(vec![beam_dim]))),
+            (DataType::Int32, Shape(Some(vec![beam_dim]))),
+            (
+                DataType::Int32,
+                Shape(Some(vec![beam_dim, Some(i64::from(self.max_length))])),
+            ),
+        ];
+        for state in initial_state {
+            let shape = match known_dims(scope, state)? {
+                Some(dims) => Shape(Some(Some(beam_dim).into_iter().chain(dims).collect())),
+                None => Shape(None),
+            };
+            inputs.push((state.operation.output_type(state.index as usize), shape));
+        }
+        let traced = trace("beam_search_step", &inputs, |scope, args| {
+            self.step(scope, args, step)
+        })?;
+        scope.graph_mut().copy_function(&traced.function, None)?;
+
+        let mut scope = scope.new_sub_scope("beam_search");
+        let scope = &mut scope;
+        // Only the first beam is alive at first, so that the copies of the
+        // initial state don't all pick the same tokens.
+        let mut initial_scores = vec![f32::NEG_INFINITY; beam];
+        initial_scores[0] = 0.0;
+        let mut loop_vars: Vec<Output> = vec![
+            ops::constant(scope, 0i32)?.into(),
+            ops::constant(scope, false)?.into(),
+            ops::constant(scope, &initial_scores[..])?.into(),
+            ops::constant(scope, &vec![false; beam][..])?.into(),
+            ops::constant(scope, &vec![self.start_token; beam][..])?.into(),
+            ops::constant(scope, &vec![0i32; beam][..])?.into(),
+            ops::constant(
+                scope,
+                Tensor::<i32>::new(&[beam as u64, self.max_length as u64]),
+            )?
+            .into(),
+        ];
+        let zero = ops::constant(scope, 0i32)?;
+        let copies = ops::constant(scope, &vec![0i32; beam][..])?;
+        for state in initial_state {
+            let expanded = ops::expand_dims(scope, state.clone(), zero.clone())?;
+            let tiled = ops::gather(scope, expanded, copies.clone(), zero.clone())?;
+            loop_vars.push(tiled.into());
+        }
+
+        // The loop variables are the step, whether all beams have ended, the
+        // scores, whether each beam has ended, the last tokens, the lengths,
+        // the sequences and the states.  All but the second are passed to
+        // the traced step, which returns new values for all of them.
+        let max_length = self.max_length;
+        let cond = |graph: &mut Graph, vars: &[Output]| -> Result<Output> {
+            let max_length = graph_constant(graph, "max_length", max_length)?;
+            let less = graph_op(graph, "Less", "less", |nd| {
+                nd.add_input(vars[0].clone());
+                nd.add_input(max_length);
+                Ok(())
+            })?;
+            let running = graph_op(graph, "LogicalNot", "running", |nd| {
+                nd.add_input(vars[1].clone());
+                Ok(())
+            })?;
+            let cond = graph_op(graph, "LogicalAnd", "cond", |nd| {
+                nd.add_input(less);
+                nd.add_input(running);
+                Ok(())
+            })?;
+            Ok(cond.into())
+        };
+        let body = |graph: &mut Graph, vars: &[Output]| -> Result<Vec<Output>> {
+            graph.copy_function(&traced.function, None)?;
+            let op = graph_op(graph, &traced.name, "step", |nd| {
+                nd.add_input(vars[0].clone());
+                for var in &vars[2..] {
+                    nd.add_input(var.clone());
+                }
+                Ok(())
+            })?;
+            Ok((0..vars.len())
+                .map(|index| Output {
+                    operation: op.clone(),
+                    index: index as i32,
+                })
+                .collect())
+        };
+        let loop_name = scope.get_unique_name_for_op("while");
+        let outputs = {
+            let mut graph = scope.graph_mut();
+            WhileBuilder::new(&mut graph, cond, body, &loop_vars)?
+                .name(&loop_name)?
+                .finish()?
+        };
+        Ok(BeamSearchOutput {
+            sequences: outputs[6].clone(),
+            scores: outputs[2].clone(),
+            lengths: outputs[5].clone(),
+            states: outputs[7..].to_vec(),
+        })
+    }
+
+    /// Builds the body of the loop, which calls `step` and keeps the best
+    /// `beam_width` extensions of the beams.
+    fn step<F>(&self, scope: &mut Scope, args: &[Output], step: F) -> Result<Vec<Output>>
+    where
+        F: FnOnce(&mut Scope, Output, &[Output]) -> Result<(Output, Vec<Output>)>,
+    {
+        let (index, scores, finished, tokens, lengths, sequences) =
+            (&args[0], &args[1], &args[2], &args[3], &args[4], &args[5]);
+        let states = &args[6..];
+        let (log_probs, new_states) = step(scope, tokens.clone(), states)?;
+        let data_type = log_probs.operation.output_type(log_probs.index as usize);
+        if data_type != DataType::Float {
+            return Err(invalid_arg!(
+                "The log probabilities of beam search must be float, but are {}",
+                data_type
+            ));
+        }
+        if new_states.len() != states.len() {
+            return Err(invalid_arg!(
+                "The step of beam search must return {} states, but returned {}",
+                states.len(),
+                new_states.len()
+            ));
+        }
+
+        let zero = ops::constant(scope, 0i32)?;
+        let one = ops::constant(scope, 1i32)?;
+        let shape = ops::Shape::new()
+            .out_type(DataType::Int32)
+            .build(scope, log_probs.clone())?;
+        let vocabulary_size = ops::gather(scope, shape, one.clone(), zero.clone())?;
+        // A beam which has ended can only be extended with the end token,
+        // which doesn't change its score.
+        let end_token = ops::constant(scope, self.end_token)?;
+        let no_change = ops::constant(scope, 0.0f32)?;
+        let impossible = ops::constant(scope, f32::NEG_INFINITY)?;
+        let ended = ops::OneHot::new().build(
+            scope,
+            end_token.clone(),
+            vocabulary_size.clone(),
+            no_change,
+            impossible,
+        )?;
+        // Select needs both branches to have the same shape.
+        let zeros = ops::zeros_like(scope, log_probs.clone())?;
+        let ended = ops::add(scope, zeros, ended)?;
+        let log_probs = ops::select(scope, finished.clone(), ended, log_probs)?;
+        let column = ops::constant(scope, &[-1, 1][..])?;
+        let scores = ops::reshape(scope, scores.clone(), column.clone())?;
+        let candidates = ops::add(scope, scores, log_probs)?;
+        let flat = ops::constant(scope, &[-1][..])?;
+        let candidates = ops::reshape(scope, candidates, flat)?;
+        let best = ops::top_k(scope, candidates.into(), self.beam_width, true)?;
+        let parents = ops::floor_div(scope, best.indices.clone(), vocabulary_size.clone())?;
+        let new_tokens = ops::floor_mod(scope, best.indices, vocabulary_size)?;
+
+        let was_finished = ops::gather(scope, finished.clone(), parents.clone(), zero.clone())?;
+        let is_end = ops::equal(scope, new_tokens.clone(), end_token)?;
+        let finished = ops::logical_or(scope, was_finished.clone(), is_end)?;
+        let running = ops::logical_not(scope, was_finished)?;
+        let running = ops::Cast::new()
+            .dst_type(DataType::Int32)
+            .build(scope, running)?;
+        let lengths = ops::gather(scope, lengths.clone(), parents.clone(), zero.clone())?;
+        let lengths = ops::add(scope, lengths, running)?;
+        // The new tokens are written to column `index` of the sequences,
+        // which is still zero.
+        let max_length = ops::constant(scope, self.max_length)?;
+        let position = ops::OneHot::new().build(
+            scope,
+            index.clone(),
+            max_length,
+            one.clone(),
+            zero.clone(),
+        )?;
+        let token_column = ops::reshape(scope, new_tokens.clone(), column)?;
+        let written = ops::multiply(scope, token_column, position)?;
+        let sequences = ops::gather(scope, sequences.clone(), parents.clone(), zero.clone())?;
+        let sequences = ops::add(scope, sequences, written)?;
+        let all_finished = ops::all(scope, finished.clone(), zero.clone())?;
+        let next = ops::add(scope, index.clone(), one)?;
+
+        let mut outputs: Vec<Output> = vec![
+            next.into(),
+            all_finished.into(),
+            best.values,
+            finished.into(),
+            new_tokens.into(),
+            lengths.into(),
+            sequences.into(),
+        ];
+        for state in new_states {
+            outputs.push(ops::gather(scope, state, parents.clone(), zero.clone())?.into());
+        }
+        Ok(outputs)
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    #[test]
+    fn decode() {
+        let mut scope = Scope::new_root_scope();
+        let initial_sum: Output = ops::constant(&mut scope, 0i32).unwrap().into();
+        // Token 0 is the end token.  Row `t` holds the probabilities of the
+        // tokens following token `t`.
+        let probabilities = [
+            1.0f32 / 3.0,
+            1.0 / 3.0,
+            1.0 / 3.0,
+            0.1,
+            0.3,
+            0.6,
+            0.7,
+            0.2,
+            0.1,
+        ];
+        let log_probabilities: Vec<f32> = probabilities.iter().map(|p| p.ln()).collect();
+        let output = BeamSearch::new(1, 0, 3)
+            .with_beam_width(2)
+            .build(&mut scope, &[initial_sum], |scope, tokens, states| {
+                let table =
+                    ops::constant(scope, Tensor::new(&[3, 3]).with_values(&log_probabilities)?)?;
+                let axis = ops::constant(scope, 0i32)?;
+                let log_probs = ops::gather(scope, table, tokens.clone(), axis)?;
+                // The state sums the tokens fed to each beam.
+                let sum = ops::add(scope, states[0].clone(), tokens)?;
+                Ok((log_probs.into(), vec![sum.into()]))
+            })
+            .unwrap();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let sequences = args.request_fetch(&output.sequences.operation, output.sequences.index);
+        let scores = args.request_fetch(&output.scores.operation, output.scores.index);
+        let lengths = args.request_fetch(&output.lengths.operation, output.lengths.index);
+        let sums = args.request_fetch(&output.states[0].operation, output.states[0].index);
+        session.run(&mut args).unwrap();
+        // The best beams are 1 -> 2 -> 0 with probability 0.6 * 0.7, and
+        // 1 -> 1 -> 2 -> 0 with probability 0.3 * 0.6 * 0.7.
+        let sequences: Tensor<i32> = args.fetch(sequences).unwrap();
+        assert_eq!(sequences.dims(), &[2, 3]);
+        assert_eq!(&sequences[..], &[2, 0, 0, 1, 2, 0]);
+        let scores: Tensor<f32> = args.fetch(scores).unwrap();
+        for (score, expected) in scores.iter().zip(&[0.42f32, 0.126]) {
+            assert!((score - expected.ln()).abs() < 1e-5, "{}", score);
+        }
+        assert_eq!(&args.fetch::<i32>(lengths).unwrap()[..], &[2, 3]);
+        assert_eq!(&args.fetch::<i32>(sums).unwrap()[..], &[3, 4]);
+
+        let decoder = BeamSearch::new(1, 0, 3).with_beam_width(0);
+        assert!(decoder
+            .build(&mut scope, &[], |_, _, _| unreachable!())
+            .is_err());
+        let decoder = BeamSearch::new(1, 0, 3);
+        assert!(decoder
+            .build(&mut scope, &[], |_, tokens, _| Ok((tokens, vec![])))
+            .is_err());
+    }
+}
@@ -0,0 +1,310 @@
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use std::collections::HashMap;
+use tensorflow_macros::define_op;
+
+define_op!(collective_reduce, CollectiveReduce, "CollectiveReduceV2", args {
+    input,
+    group_size,
+    group_key,
+    instance_key,
+}, attrs {
+    merge_op: String => "merge_op",
+    final_op: String => "final_op",
+    communication_hint?: String => "communication_hint",
+    timeout_seconds?: f32 => "timeout_seconds",
+});
+
+define_op!(collective_bcast_send, CollectiveBcastSend, "CollectiveBcastSendV2", args {
+    input,
+    group_size,
+    group_key,
+    instance_key,
+}, attrs {
+    communication_hint?: String => "communication_hint",
+    timeout_seconds?: f32 => "timeout_seconds",
+});
+
+define_op!(collective_bcast_recv, CollectiveBcastRecv, "CollectiveBcastRecvV2", args {
+    group_size,
+    group_key,
+    instance_key,
+    shape,
+}, attrs {
+    data_type: DataType => "T",
+    communication_hint?: String => "communication_hint",
+    timeout_seconds?: f32 => "timeout_seconds",
+});
+
+/// Allocates the group and instance keys which tie together the collective
+/// operations of a single graph.
+///
+/// All participants in a collective must agree on a group key (identifying the
+/// set of devices) and an instance key (identifying the particular collective
+/// among those devices).  Use a single `CollectiveKeys` per graph so that keys
+/// are never reused by accident.
+#[derive(Debug)]
+pub struct CollectiveKeys {
+    group_keys: HashMap<Vec<String>, i32>,
+    next_group_key: i32,
+    next_instance_key: i32,
+}
+
+impl Default for CollectiveKeys {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CollectiveKeys {
+    /// Creates a new key allocator.
+    pub fn new() -> Self {
+        Self {
+            group_keys: HashMap::new(),
+            next_group_key: 1,
+            next_instance_key: 1,
+        }
+    }
+
+    /// Returns the group key for a set of devices.  The same set of devices
+    /// always receives the same key, regardless of order.
+    pub fn group_key<S: AsRef<str>>(&mut self, devices: &[S]) -> i32 {
+        let mut devices: Vec<String> = devices.iter().map(|d| d.as_ref().to_string()).collect();
+        devices.sort();
+        devices.dedup();
+        let next_group_key = &mut self.next_group_key;
+        *self.group_keys.entry(devices).or_insert_with(|| {
+            let key = *next_group_key;
+            *next_group_key += 1;
+            key
+        })
+    }
+
+    /// Returns a new instance key which has not been returned before.
+    pub fn next_instance_key(&mut self) -> i32 {
+        let key = self.next_instance_key;
+        self.next_instance_key += 1;
+        key
+    }
+}
+
+pub(crate) fn replica_devices(inputs: &[Output]) -> Result<Vec<String>> {
+    let mut devices = Vec::with_capacity(inputs.len());
+    for input in inputs {
+        let device = input.operation.device()?;
+        if device.is_empty() {
+            return Err(invalid_arg!(
+                "Collective input {} must be placed on a device",
+                input.operation.name()?
+            ));
+        }
+        if devices.contains(&device) {
+            return Err(invalid_arg!(
+                "Collective inputs must be on distinct devices, but found {} twice",
+                device
+            ));
+        }
+        devices.push(device);
+    }
+    Ok(devices)
+}
+
+/// Reduces `inputs`, each of which lives on a different device, and returns
+/// one copy of the result per device, in the same order as `inputs`.
+///
+/// `merge_op` is one of "Add", "Mul", "Min" or "Max", and `final_op` is "Id"
+/// or "Div" (divide by the number of replicas).  To average gradients across
+/// replicas, use `all_reduce_mean`.
+pub fn all_reduce(
+    scope: &mut Scope,
+    keys: &mut CollectiveKeys,
+    inputs: &[Output],
+    merge_op: &str,
+    final_op: &str,
+) -> Result<Vec<Output>> {
+    let devices = replica_devices(inputs)?;
+    let group_key = keys.group_key(&devices);
+    let instance_key = keys.next_instance_key();
+    let scope = scope.new_sub_scope("all_reduce");
+    let mut outputs = Vec::with_capacity(inputs.len());
+    for (input, device) in inputs.iter().zip(&devices) {
+        let mut scope = scope.with_device(device);
+        let group_size = ops::constant(&mut scope, inputs.len() as i32)?;
+        let group_key = ops::constant(&mut scope, group_key)?;
+        let instance_key = ops::constant(&mut scope, instance_key)?;
+        let reduce = CollectiveReduce::new()
+            .merge_op(merge_op)
+            .final_op(final_op)
+            .build(
+                &mut scope,
+                input.clone(),
+                group_size,
+                group_key,
+                instance_key,
+            )?;
+        outputs.push(reduce.into());
+    }
+    Ok(outputs)
+}
+
+/// Sums `inputs` across devices.  See `all_reduce`.
+pub fn all_reduce_sum(
+    scope: &mut Scope,
+    keys: &mut CollectiveKeys,
+    inputs: &[Output],
+) -> Result<Vec<Output>> {
+    all_reduce(scope, keys, inputs, "Add", "Id")
+}
+
+/// Averages `inputs` across devices, e.g. to combine the gradients computed by
+/// data-parallel replicas.  See `all_reduce`.
+pub fn all_reduce_mean(
+    scope: &mut Scope,
+    keys: &mut CollectiveKeys,
+    inputs: &[Output],
+) -> Result<Vec<Output>> {
+    all_reduce(scope, keys, inputs, "Add", "Div")
+}
+
+/// Broadcasts `source` from its device to each of `devices`.  The source
+/// device must not be included in `devices`.
+///
+/// The first returned output is the source device's copy, followed by one
+/// output per element of `devices`.  Like all collectives, every participant
+/// must run in the same step, so all returned outputs should be fetched (or
+/// depended on) together.
+pub fn broadcast<S: AsRef<str>>(
+    scope: &mut Scope,
+    keys: &mut CollectiveKeys,
+    source: Output,
+    devices: &[S],
+) -> Result<Vec<Output>> {
+    let source_device = replica_devices(std::slice::from_ref(&source))?.remove(0);
+    let mut group: Vec<&str> = vec![&source_device];
+    for device in devices {
+        let device = device.as_ref();
+        if group.contains(&device) {
+            return Err(invalid_arg!(
+                "Broadcast devices must be distinct, but found {} twice",
+                device
+            ));
+        }
+        group.push(device);
+    }
+    let group_size = group.len() as i32;
+    let group_key = keys.group_key(&group);
+    let instance_key = keys.next_instance_key();
+    let data_type = source.operation.output_type(source.index as usize);
+    let scope = scope.new_sub_scope("broadcast");
+    let (shape, send) = {
+        let mut scope = scope.with_device(&source_device);
+        let shape = ops::shape(&mut scope, source.clone())?;
+        let group_size = ops::constant(&mut scope, group_size)?;
+        let group_key = ops::constant(&mut scope, group_key)?;
+        let instance_key = ops::constant(&mut scope, instance_key)?;
+        let send = collective_bcast_send(&mut scope, source, group_size, group_key, instance_key)?;
+        (shape, send)
+    };
+    let mut outputs = Vec::with_capacity(devices.len() + 1);
+    outputs.push(send.into());
+    for device in devices {
+        let mut scope = scope.with_device(device.as_ref());
+        let group_size = ops::constant(&mut scope, group_size)?;
+        let group_key = ops::constant(&mut scope, group_key)?;
+        let instance_key = ops::constant(&mut scope, instance_key)?;
+        let recv = CollectiveBcastRecv::new().data_type(data_type).build(
+            &mut scope,
+            group_size,
+            group_key,
+            instance_key,
+            shape.clone(),
+        )?;
+        outputs.push(recv.into());
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::ProtoWriter;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    fn two_cpu_session(scope: &Scope) -> Session {
+        // ConfigProto.device_count = {"CPU": 2}
+        let mut entry = ProtoWriter::new();
+        entry.string_field(1, "CPU").int_field(2, 2);
+        let mut config = ProtoWriter::new();
+        config.message_field(1, &entry);
+        let mut options = SessionOptions::new();
+        options.set_config(config.as_bytes()).unwrap();
+        Session::new(&options, &scope.graph()).unwrap()
+    }
+
+    #[test]
+    fn collective_keys() {
+        let mut keys = CollectiveKeys::new();
+        let a = keys.group_key(&["/device:GPU:0", "/device:GPU:1"]);
+        let b = keys.group_key(&["/device:GPU:1", "/device:GPU:0"]);
+        let c = keys.group_key(&["/device:GPU:0"]);
+        assert_eq!(a, b);
+        assert_ne!(a, c);
+        assert_ne!(keys.next_instance_key(), keys.next_instance_key());
+    }
+
+    #[test]
+    fn all_reduce_requires_devices() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::constant(&mut scope, 1.0f32).unwrap();
+        let mut keys = CollectiveKeys::new();
+        assert!(all_reduce_sum(&mut scope, &mut keys, &[x.into()]).is_err());
+    }
+
+    #[test]
+    fn all_reduce_mean_across_cpus() {
+        let mut scope = Scope::new_root_scope();
+        let a = ops::constant(&mut scope.with_device("/device:CPU:0"), &[1.0f32, 2.0][..]).unwrap();
+        let b = ops::constant(&mut scope.with_device("/device:CPU:1"), &[3.0f32, 6.0][..]).unwrap();
+        let mut keys = CollectiveKeys::new();
+        let means = all_reduce_mean(&mut scope, &mut keys, &[a.into(), b.into()]).unwrap();
+        assert_eq!(means[1].operation.device().unwrap(), "/device:CPU:1");
+        let session = two_cpu_session(&scope);
+        let mut run_args = SessionRunArgs::new();
+        let tokens: Vec<_> = means
+            .iter()
+            .map(|m| run_args.request_fetch(&m.operation, m.index))
+            .collect();
+        session.run(&mut run_args).unwrap();
+        for token in tokens {
+            let result: Tensor<f32> = run_args.fetch(token).unwrap();
+            assert_eq!(&result[..], &[2.0, 4.0]);
+        }
+    }
+
+    #[test]
+    fn broadcast_across_cpus() {
+        let mut scope = Scope::new_root_scope();
+        let a = ops::constant(&mut scope.with_device("/device:CPU:0"), &[1.0f32, 2.0][..]).unwrap();
+        let mut keys = CollectiveKeys::new();
+        let copies = broadcast(&mut scope, &mut keys, a.into(), &["/device:CPU:1"]).unwrap();
+        assert_eq!(copies.len(), 2);
+        assert_eq!(copies[1].operation.device().unwrap(), "/device:CPU:1");
+        let session = two_cpu_session(&scope);
+        let mut run_args = SessionRunArgs::new();
+        let tokens: Vec<_> = copies
+            .iter()
+            .map(|c| run_args.request_fetch(&c.operation, c.index))
+            .collect();
+        session.run(&mut run_args).unwrap();
+        for token in tokens {
+            let result: Tensor<f32> = run_args.fetch(token).unwrap();
+            assert_eq!(&result[..], &[1.0, 2.0]);
+        }
+    }
+}
@@ -0,0 +1,380 @@
+use super::known_dims;
+use super::Placeholder;
+use crate::ops;
+use crate::protos::ProtoWriter;
+use crate::DataType;
+use crate::Function;
+use crate::FunctionOptions;
+use crate::Graph;
+use crate::Operation;
+use crate::OperationDescription;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Shape;
+use crate::Tensor;
+use crate::WhileBuilder;
+
+/// A function traced from Rust code, ready to be called from a graph.
+#[derive(Debug)]
+pub(crate) struct TracedFunction {
+    pub(crate) function: Function,
+    pub(crate) name: String,
+    pub(crate) output_types: Vec<DataType>,
+    pub(crate) output_shapes: Vec<Shape>,
+}
+
+/// Builds a function named `name` (plus a hash) by calling `f` on a new scope
+/// with a placeholder for each of `inputs`.
+pub(crate) fn trace<F>(name: &str, inputs: &[(DataType, Shape)], f: F) -> Result<TracedFunction>
+where
+    F: FnOnce(&mut Scope, &[Output]) -> Result<Vec<Output>>,
+{
+    let mut scope = Scope::new_root_scope();
+    let mut args = Vec::with_capacity(inputs.len());
+    for (i, (data_type, shape)) in inputs.iter().enumerate() {
+        let arg = Placeholder::new()
+            .data_type(*data_type)
+            .shape(shape.clone())
+            .build(&mut scope.with_op_name(&format!("arg_{}", i)))?;
+        args.push(arg.into());
+    }
+    let outputs = f(&mut scope, &args)?;
+    let graph = scope.graph();
+    let output_types = outputs
+        .iter()
+        .map(|output| output.operation.output_type(output.index as usize))
+        .collect();
+    let output_shapes = outputs
+        .iter()
+        .map(|output| graph.tensor_shape(output.clone()))
+        .collect::<Result<_>>()?;
+    let function = graph.to_function(
+        name,
+        true,
+        None,
+        &args,
+        &outputs,
+        None::<&[&str]>,
+        &FunctionOptions::new(),
+        None,
+    )?;
+    let name = function.get_name()?;
+    Ok(TracedFunction {
+        function,
+        name,
+        output_types,
+        output_shapes,
+    })
+}
+
+/// A branch of `case`, called with placeholders standing for its inputs.
+pub type CaseBranch<'a> = &'a dyn Fn(&mut Scope, &[Output]) -> Result<Vec<Output>>;
+
+/// Runs the branch at `branch_index` (an int32 scalar) on `inputs` and
+/// returns its outputs.  If the index is out of range, the last branch runs,
+/// so it can serve as the default.
+///
+/// Each branch is traced once, while building the graph, on a fresh scope
+/// which gets placeholders standing for the inputs.  All branches must return
+/// the same number and types of outputs.  Only the selected branch is
+/// executed.
+pub fn case(
+    scope: &mut Scope,
+    branch_index: Output,
+    inputs: &[Output],
+    branches: &[CaseBranch<'_>],
+) -> Result<Vec<Output>> {
+    if branches.is_empty() {
+        return Err(invalid_arg!("case needs at least one branch"));
+    }
+    if branch_index.data_type() != DataType::Int32 {
+        return Err(invalid_arg!(
+            "The branch index of case must be int32, but is {}",
+            branch_index.data_type()
+        ));
+    }
+    let input_signature = inputs
+        .iter()
+        .map(|input| {
+            Ok((
+                input.data_type(),
+                scope.graph().tensor_shape(input.clone())?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let mut functions = Vec::with_capacity(branches.len());
+    for (i, branch) in branches.iter().enumerate() {
+        let traced = trace(&format!("case_branch_{}", i), &input_signature, branch)?;
+        if let Some(first) = functions.first() {
+            let first: &TracedFunction = first;
+            if traced.output_types != first.output_types {
+                return Err(invalid_arg!(
+                    "Branch {} of case returns {:?}, but branch 0 returns {:?}",
+                    i,
+                    traced.output_types,
+                    first.output_types
+                ));
+            }
+        }
+        scope.graph_mut().copy_function(&traced.function, None)?;
+        functions.push(traced);
+    }
+    // AttrValue { list: ListValue { func: [NameAttrList { name }] } }
+    let mut list = ProtoWriter::new();
+    for function in &functions {
+        let mut func = ProtoWriter::new();
+        func.string_field(1, &function.name);
+        list.message_field(9, &func);
+    }
+    let mut branches_attr = ProtoWriter::new();
+    branches_attr.message_field(1, &list);
+    let input_types: Vec<DataType> = input_signature.iter().map(|(t, _)| *t).collect();
+    let output_types = functions[0].output_types.clone();
+    let op = scope.new_operation("Case", |nd| {
+        nd.add_input(branch_index);
+        nd.add_input_list(inputs);
+        nd.set_attr_type_list("Tin", &input_types)?;
+        nd.set_attr_type_list("Tout", &output_types)?;
+        nd.set_attr_value_proto("branches", branches_attr.as_bytes())?;
+        Ok(())
+    })?;
+    Ok((0..output_types.len())
+        .map(|index| Output {
+            operation: op.clone(),
+            index: index as i32,
+        })
+        .collect())
+}
+
+/// Forwards `data` to the second output if the bool scalar `pred` is true,
+/// and to the first output otherwise, and returns both outputs.  The output
+/// which isn't taken is dead: ops which use it, directly or through other
+/// ops, don't run, and fetching them fails.
+pub fn switch(scope: &mut Scope, data: Output, pred: Output) -> Result<(Output, Output)> {
+    let op = scope.new_operation("Switch", |nd| {
+        nd.add_input(data);
+        nd.add_input(pred);
+        Ok(())
+    })?;
+    Ok((
+        Output {
+            operation: op.clone(),
+            index: 0,
+        },
+        Output {
+            operation: op,
+            index: 1,
+        },
+    ))
+}
+
+/// Adds an operation to a loop's condition or body graph.
+pub(crate) fn graph_op<F>(graph: &mut Graph, op_type: &str, name: &str, f: F) -> Result<Operation>
+where
+    F: FnOnce(&mut OperationDescription<'_>) -> Result<()>,
+{
+    let mut nd = graph.new_operation(op_type, name)?;
+    f(&mut nd)?;
+    nd.finish()
+}
+
+pub(crate) fn graph_constant(graph: &mut Graph, name: &str, value: i32) -> Result<Operation> {
+    graph_op(graph, "Const", name, |nd| {
+        nd.set_attr_type("dtype", DataType::Int32)?;
+        nd.set_attr_tensor("value", Tensor::from(value))?;
+        Ok(())
+    })
+}
+
+/// Applies `f` to each slice of `elems` along its first dimension and stacks
+/// the results, like Python's `tf.map_fn`.
+///
+/// `f` is traced once, while building the graph, on a fresh scope which gets
+/// a placeholder standing for one element.  The mapping runs in a while loop
+/// (see `WhileBuilder`), with the results collected in a `TensorArray`, so the
+/// number of elements only needs to be known when the graph runs.
+pub fn map_fn<F>(scope: &mut Scope, elems: Output, f: F) -> Result<Output>
+where
+    F: FnOnce(&mut Scope, Output) -> Result<Output>,
+{
+    let element_shape = match known_dims(scope, &elems)? {
+        Some(dims) if dims.is_empty() => {
+            return Err(invalid_arg!("map_fn can't map over a scalar"));
+        }
+        Some(dims) => Shape(Some(dims[1..].to_vec())),
+        None => Shape(None),
+    };
+    let traced = trace(
+        "map_fn_body",
+        &[(elems.data_type(), element_shape)],
+        |scope, args| Ok(vec![f(scope, args[0].clone())?]),
+    )?;
+    scope.graph_mut().copy_function(&traced.function, None)?;
+    let output_type = traced.output_types[0];
+
+    let mut scope = scope.new_sub_scope("map_fn");
+    let shape = ops::Shape::new()
+        .out_type(DataType::Int32)
+        .build(&mut scope, elems.clone())?;
+    let zero = ops::constant(&mut scope, 0i32)?;
+    let n: Output = ops::gather(&mut scope, shape, zero.clone(), zero.clone())?.into();
+    let output_shape = traced.output_shapes[0].clone();
+    let tensor_array = scope.new_operation("TensorArrayV3", |nd| {
+        nd.add_input(n.clone());
+        nd.set_attr_type("dtype", output_type)?;
+        nd.set_attr_shape("element_shape", &output_shape)?;
+        nd.set_attr_bool("identical_element_shapes", true)?;
+        Ok(())
+    })?;
+    let handle = Output {
+        operation: tensor_array.clone(),
+        index: 0,
+    };
+    let flow = Output {
+        operation: tensor_array,
+        index: 1,
+    };
+
+    // The loop variables are the index, the number of elements, the
+    // elements, and the TensorArray's handle and flow.
+    let cond = |graph: &mut Graph, vars: &[Output]| -> Result<Output> {
+        let less = graph_op(graph, "Less", "less", |nd| {
+            nd.add_input(vars[0].clone());
+            nd.add_input(vars[1].clone());
+            Ok(())
+        })?;
+        Ok(less.into())
+    };
+    let body = |graph: &mut Graph, vars: &[Output]| -> Result<Vec<Output>> {
+        graph.copy_function(&traced.function, None)?;
+        let axis = graph_constant(graph, "axis", 0)?;
+        let element = graph_op(graph, "GatherV2", "element", |nd| {
+            nd.add_input(vars[2].clone());
+            nd.add_input(vars[0].clone());
+            nd.add_input(axis);
+            Ok(())
+        })?;
+        let result = graph_op(graph, &traced.name, "fn", |nd| {
+            nd.add_input(element);
+            Ok(())
+        })?;
+        let flow = graph_op(graph, "TensorArrayWriteV3", "write", |nd| {
+            nd.add_input(vars[3].clone());
+            nd.add_input(vars[0].clone());
+            nd.add_input(result);
+            nd.add_input(vars[4].clone());
+            Ok(())
+        })?;
+        let one = graph_constant(graph, "one", 1)?;
+        let next = graph_op(graph, "Add", "next", |nd| {
+            nd.add_input(vars[0].clone());
+            nd.add_input(one);
+            Ok(())
+        })?;
+        Ok(vec![
+            next.into(),
+            vars[1].clone(),
+            vars[2].clone(),
+            vars[3].clone(),
+            flow.into(),
+        ])
+    };
+    let loop_name = scope.get_unique_name_for_op("while");
+    let outputs = {
+        let mut graph = scope.graph_mut();
+        WhileBuilder::new(
+            &mut graph,
+            cond,
+            body,
+            &[zero.into(), n.clone(), elems, handle.clone(), flow],
+        )?
+        .name(&loop_name)?
+        .finish()?
+    };
+
+    let start = ops::constant(&mut scope, 0i32)?;
+    let delta = ops::constant(&mut scope, 1i32)?;
+    let indices = scope.new_operation("Range", |nd| {
+        nd.add_input(start);
+        nd.add_input(n);
+        nd.add_input(delta);
+        Ok(())
+    })?;
+    let stacked = scope.new_operation("TensorArrayGatherV3", |nd| {
+        nd.add_input(handle);
+        nd.add_input(indices);
+        nd.add_input(outputs[4].clone());
+        nd.set_attr_type("dtype", output_type)?;
+        nd.set_attr_shape("element_shape", &output_shape)?;
+        Ok(())
+    })?;
+    Ok(stacked.into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+
+    #[test]
+    fn case_selects_branch() {
+        let mut scope = Scope::new_root_scope();
+        let x: Output = ops::constant(&mut scope, 3.0f32).unwrap().into();
+        let double = |scope: &mut Scope, args: &[Output]| -> Result<Vec<Output>> {
+            Ok(vec![
+                ops::add(scope, args[0].clone(), args[0].clone())?.into()
+            ])
+        };
+        let square = |scope: &mut Scope, args: &[Output]| -> Result<Vec<Output>> {
+            Ok(vec![ops::multiply(
+                scope,
+                args[0].clone(),
+                args[0].clone(),
+            )?
+            .into()])
+        };
+        let index: Output = ops::constant(&mut scope, 1i32).unwrap().into();
+        let out_of_range: Output = ops::constant(&mut scope, 7i32).unwrap().into();
+        let selected = case(&mut scope, index, &[x.clone()], &[&double, &square]).unwrap();
+        let default = case(&mut scope, out_of_range, &[x.clone()], &[&square, &double]).unwrap();
+        assert_eq!(&fetch::<f32>(&scope, &selected[0])[..], &[9.0]);
+        assert_eq!(&fetch::<f32>(&scope, &default[0])[..], &[6.0]);
+
+        let to_int = |scope: &mut Scope, args: &[Output]| -> Result<Vec<Output>> {
+            Ok(vec![ops::Cast::new()
+                .dst_type(DataType::Int32)
+                .build(scope, args[0].clone())?
+                .into()])
+        };
+        let index: Output = ops::constant(&mut scope, 0i32).unwrap().into();
+        assert!(case(&mut scope, index.clone(), &[x.clone()], &[&double, &to_int]).is_err());
+        assert!(case(&mut scope, x.clone(), &[x], &[&double]).is_err());
+    }
+
+    #[test]
+    fn map_rows() {
+        let mut scope = Scope::new_root_scope();
+        let elems: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[3, 2])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let sums = map_fn(&mut scope, elems, |scope, row| {
+            let axis = ops::constant(scope, 0i32)?;
+            Ok(ops::sum(scope, row, axis)?.into())
+        })
+        .unwrap();
+        let sums = fetch::<f32>(&scope, &sums);
+        assert_eq!(sums.dims(), &[3]);
+        assert_eq!(&sums[..], &[3.0, 7.0, 11.0]);
+
+        let scalar: Output = ops::constant(&mut scope, 1.0f32).unwrap().into();
+        assert!(map_fn(&mut scope, scalar, |_, x| Ok(x)).is_err());
+    }
+}
@@ -0,0 +1,268 @@
+use super::known_dims;
+use crate::ops;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use tensorflow_macros::define_op;
+
+define_op!(check_numerics_op, CheckNumerics, "CheckNumerics", args { tensor }, attrs {
+    message: String => "message",
+});
+
+/// Adds an `Assert` op which fails with `data` when run if the boolean scalar
+/// `condition` is false.  At most `summarize` entries of each tensor in `data`
+/// are printed.
+///
+/// Unlike `assert_that`, this always adds the op, regardless of the scope's
+/// debug mode.  Nothing depends on the op, so it only runs if it is a target
+/// or a control input of something which runs.
+pub fn assert_op(
+    scope: &mut Scope,
+    condition: Output,
+    data: &[Output],
+    summarize: u32,
+) -> Result<Operation> {
+    let types: Vec<DataType> = data
+        .iter()
+        .map(|output| output.operation.output_type(output.index as usize))
+        .collect();
+    scope.new_operation("Assert", |nd| {
+        nd.add_input(condition);
+        nd.add_input_list(data);
+        nd.set_attr_type_list("T", &types)?;
+        nd.set_attr_int("summarize", i64::from(summarize))?;
+        Ok(())
+    })
+}
+
+/// Returns `tensor`, which doesn't run until `checks` have passed.
+fn guard(scope: &mut Scope, tensor: Output, checks: &[Operation]) -> Result<Output> {
+    let mut identity = ops::Identity::new();
+    for check in checks {
+        identity = identity.add_control_input(check.clone());
+    }
+    Ok(identity.build(scope, tensor)?.into())
+}
+
+/// In debug mode (see `Scope::set_debug_mode`), returns `tensor` checked for
+/// NaN and infinite values, failing with `message` and the op's name if any
+/// are found.  Otherwise, returns `tensor` unchanged.
+///
+/// Wrapping intermediate results during training narrows down where NaNs
+/// first appear.
+pub fn check_numerics(scope: &mut Scope, tensor: Output, message: &str) -> Result<Output> {
+    let data_type = tensor.operation.output_type(tensor.index as usize);
+    match data_type {
+        DataType::BFloat16 | DataType::Half | DataType::Float | DataType::Double => {}
+        _ => {
+            return Err(invalid_arg!(
+                "check_numerics needs a floating point tensor, but got {}",
+                data_type
+            ))
+        }
+    }
+    if !scope.debug_mode() {
+        return Ok(tensor);
+    }
+    Ok(CheckNumerics::new()
+        .message(message)
+        .build(scope, tensor)?
+        .into())
+}
+
+/// In debug mode (see `Scope::set_debug_mode`), returns `tensor`, which
+/// fails with `message` when run if the boolean scalar `condition` is false.
+/// Otherwise, returns `tensor` unchanged.
+pub fn assert_that(
+    scope: &mut Scope,
+    tensor: Output,
+    condition: Output,
+    message: &str,
+) -> Result<Output> {
+    let data_type = condition.operation.output_type(condition.index as usize);
+    if data_type != DataType::Bool {
+        return Err(invalid_arg!(
+            "The condition of an assertion must be bool, but is {}",
+            data_type
+        ));
+    }
+    if !scope.debug_mode() {
+        return Ok(tensor);
+    }
+    let mut scope = scope.new_sub_scope("assert_that");
+    let message = ops::constant(&mut scope, message.to_string())?;
+    let check = assert_op(&mut scope, condition, &[message.into()], 3)?;
+    guard(&mut scope, tensor, &[check])
+}
+
+/// Checks that each tensor has the paired shape, where `None` matches any
+/// size, and returns the tensors in the same order.
+///
+/// A known size which doesn't match is an error.  In debug mode (see
+/// `Scope::set_debug_mode`), the returned tensors also check the remaining
+/// sizes when run.
+///
+/// ```ignore
+/// let checked = assert_shapes(&mut scope, &[(x, &[None, Some(3)]), (y, &[None])])?;
+/// ```
+pub fn assert_shapes(
+    scope: &mut Scope,
+    checks: &[(Output, &[Option<i64>])],
+) -> Result<Vec<Output>> {
+    let mut outputs = Vec::with_capacity(checks.len());
+    for (tensor, expected) in checks {
+        let dims = known_dims(scope, tensor)?;
+        if let Some(dims) = &dims {
+            let matches = dims.len() == expected.len()
+                && dims.iter().zip(expected.iter()).all(
+                    |(dim, expected)| !matches!((dim, expected), (Some(d), Some(e)) if d != e),
+                );
+            if !matches {
+                return Err(invalid_arg!(
+                    "Expected {} to have shape {:?}, but it has shape {:?}",
+                    describe(tensor),
+                    expected,
+                    dims
+                ));
+            }
+        }
+        // The sizes which can only be checked at runtime, with their axes.
+        let unknown: Vec<(i32, i64)> = expected
+            .iter()
+            .enumerate()
+            .filter_map(|(axis, size)| {
+                let size = (*size)?;
+                match &dims {
+                    Some(dims) if dims[axis].is_some() => None,
+                    _ => Some((axis as i32, size)),
+                }
+            })
+            .collect();
+        if !scope.debug_mode() || (dims.is_some() && unknown.is_empty()) {
+            outputs.push(tensor.clone());
+            continue;
+        }
+        let mut scope = scope.new_sub_scope("assert_shapes");
+        let message = ops::constant(
+            &mut scope,
+            format!(
+                "Expected {} to have shape {:?}, but it has shape:",
+                describe(tensor),
+                expected
+            ),
+        )?;
+        let shape = ops::Shape::new()
+            .out_type(DataType::Int64)
+            .build(&mut scope, tensor.clone())?;
+        let summarize = expected.len() as u32;
+        let rank = ops::rank(&mut scope, tensor.clone())?;
+        let expected_rank = ops::constant(&mut scope, expected.len() as i32)?;
+        let rank_matches = ops::equal(&mut scope, rank, expected_rank)?;
+        let mut checks = vec![assert_op(
+            &mut scope,
+            rank_matches.into(),
+            &[message.clone().into(), shape.clone().into()],
+            summarize,
+        )?];
+        if !unknown.is_empty() {
+            let (axes, sizes): (Vec<i32>, Vec<i64>) = unknown.into_iter().unzip();
+            let axes = ops::constant(&mut scope, &axes[..])?;
+            let sizes = ops::constant(&mut scope, &sizes[..])?;
+            let zero = ops::constant(&mut scope, 0i32)?;
+            let actual = ops::gather(&mut scope, shape.clone(), axes, zero.clone())?;
+            let equal = ops::equal(&mut scope, actual, sizes)?;
+            let sizes_match = ops::all(&mut scope, equal, zero)?;
+            checks.push(assert_op(
+                &mut scope,
+                sizes_match.into(),
+                &[message.into(), shape.into()],
+                summarize,
+            )?);
+        }
+        outputs.push(guard(&mut scope, tensor.clone(), &checks)?);
+    }
+    Ok(outputs)
+}
+
+fn describe(output: &Output) -> String {
+    match output.operation.name() {
+        Ok(name) => format!("{}:{}", name, output.index),
+        Err(_) => "a tensor".to_string(),
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::run;
+    use crate::Shape;
+    use crate::Tensor;
+
+    #[test]
+    fn check_numerics_in_debug_mode() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::constant(&mut scope, &[1.0f32, 0.0][..]).unwrap();
+        let y = ops::constant(&mut scope, &[0.0f32, 0.0][..]).unwrap();
+        let z: Output = ops::divide(&mut scope, x, y).unwrap().into();
+        let unchecked = check_numerics(&mut scope, z.clone(), "z").unwrap();
+        assert_eq!(unchecked.operation.name().unwrap(), "RealDiv");
+        scope.set_debug_mode(true);
+        let checked = check_numerics(&mut scope, z, "z").unwrap();
+        assert_eq!(checked.operation.op_type().unwrap(), "CheckNumerics");
+        assert!(run::<f32>(&scope, &[], &[&unchecked]).is_ok());
+        let err = run::<f32>(&scope, &[], &[&checked]).unwrap_err();
+        assert!(err.message().contains('z'), "{}", err.message());
+
+        let i: Output = ops::constant(&mut scope, 1i32).unwrap().into();
+        assert!(check_numerics(&mut scope, i, "i").is_err());
+    }
+
+    #[test]
+    fn assert_that_in_debug_mode() {
+        let mut scope = Scope::new_root_scope();
+        scope.set_debug_mode(true);
+        let x: Output = ops::constant(&mut scope, 1.0f32).unwrap().into();
+        let yes: Output = ops::constant(&mut scope, true).unwrap().into();
+        let no: Output = ops::constant(&mut scope, false).unwrap().into();
+        let passed = assert_that(&mut scope, x.clone(), yes, "fine").unwrap();
+        let failed = assert_that(&mut scope, x.clone(), no.clone(), "x is bad").unwrap();
+        assert!(run::<f32>(&scope, &[], &[&passed]).is_ok());
+        let err = run::<f32>(&scope, &[], &[&failed]).unwrap_err();
+        assert!(err.message().contains("x is bad"), "{}", err.message());
+        assert!(assert_that(&mut scope, x.clone(), x.clone(), "not bool").is_err());
+
+        scope.set_debug_mode(false);
+        let unchecked = assert_that(&mut scope, x.clone(), no, "x is bad").unwrap();
+        assert_eq!(
+            unchecked.operation.name().unwrap(),
+            x.operation.name().unwrap()
+        );
+    }
+
+    #[test]
+    fn assert_shapes_static_and_dynamic() {
+        let mut scope = Scope::new_root_scope();
+        let x: Output = ops::constant(&mut scope, Tensor::<f32>::new(&[2, 3]))
+            .unwrap()
+            .into();
+        assert!(assert_shapes(&mut scope, &[(x.clone(), &[None, Some(3)])]).is_ok());
+        assert!(assert_shapes(&mut scope, &[(x.clone(), &[None, Some(4)])]).is_err());
+        assert!(assert_shapes(&mut scope, &[(x, &[None])]).is_err());
+
+        scope.set_debug_mode(true);
+        let p = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape(Some(vec![None, None])))
+            .build(&mut scope)
+            .unwrap();
+        let checked = assert_shapes(&mut scope, &[(p.clone().into(), &[None, Some(3)])]).unwrap();
+        let good = Tensor::<f32>::new(&[4, 3]);
+        let bad = Tensor::<f32>::new(&[4, 2]);
+        assert!(run::<f32>(&scope, &[(&p, &good)], &[&checked[0]]).is_ok());
+        assert!(run::<f32>(&scope, &[(&p, &bad)], &[&checked[0]]).is_err());
+    }
+}
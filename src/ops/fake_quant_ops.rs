@@ -0,0 +1,88 @@
+use tensorflow_macros::define_op;
+
+define_op!(
+    fake_quant_with_min_max_args,
+    FakeQuantWithMinMaxArgs,
+    "FakeQuantWithMinMaxArgs",
+    args { inputs },
+    attrs {
+        min?: f32 => "min",
+        max?: f32 => "max",
+        num_bits?: i64 => "num_bits",
+        narrow_range?: bool => "narrow_range",
+    }
+);
+
+define_op!(
+    fake_quant_with_min_max_vars,
+    FakeQuantWithMinMaxVars,
+    "FakeQuantWithMinMaxVars",
+    args { inputs, min, max },
+    attrs {
+        num_bits?: i64 => "num_bits",
+        narrow_range?: bool => "narrow_range",
+    }
+);
+
+define_op!(
+    fake_quant_with_min_max_vars_per_channel,
+    FakeQuantWithMinMaxVarsPerChannel,
+    "FakeQuantWithMinMaxVarsPerChannel",
+    args { inputs, min, max },
+    attrs {
+        num_bits?: i64 => "num_bits",
+        narrow_range?: bool => "narrow_range",
+    }
+);
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::Scope;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    #[test]
+    fn fake_quant() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::constant(&mut scope, &[-1.0f32, 0.1, 0.52, 2.0][..]).unwrap();
+        // With 2 bits, [0, 0.75] is quantized in steps of 0.25.
+        let args = FakeQuantWithMinMaxArgs::new()
+            .min(0.0)
+            .max(0.75)
+            .num_bits(2)
+            .build(&mut scope, x.clone())
+            .unwrap();
+        let min = ops::constant(&mut scope, 0.0f32).unwrap();
+        let max = ops::constant(&mut scope, 0.75f32).unwrap();
+        let vars = FakeQuantWithMinMaxVars::new()
+            .num_bits(2)
+            .build(&mut scope, x.clone(), min, max)
+            .unwrap();
+        let min = ops::constant(&mut scope, &[0.0f32, 0.0, 0.0, -3.0][..]).unwrap();
+        let max = ops::constant(&mut scope, &[0.75f32, 0.75, 0.75, 0.0][..]).unwrap();
+        let per_channel = FakeQuantWithMinMaxVarsPerChannel::new()
+            .num_bits(2)
+            .build(&mut scope, x, min, max)
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let tokens: Vec<_> = [&args, &vars, &per_channel]
+            .iter()
+            .map(|op| run_args.request_fetch(op, 0))
+            .collect();
+        session.run(&mut run_args).unwrap();
+        let expected = [0.0, 0.0, 0.5, 0.75];
+        for &token in &tokens[..2] {
+            assert_eq!(&run_args.fetch::<f32>(token).unwrap()[..], &expected);
+        }
+        assert_eq!(
+            &run_args.fetch::<f32>(tokens[2]).unwrap()[..],
+            &[0.0, 0.0, 0.5, 0.0]
+        );
+    }
+}
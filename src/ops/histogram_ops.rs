@@ -0,0 +1,268 @@
+use super::check_index_type;
+use super::known_dims;
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Tensor;
+use tensorflow_macros::define_op;
+
+define_op!(
+    bincount_op,
+    Bincount,
+    "Bincount",
+    args { arr, size, weights }
+);
+
+define_op!(
+    dense_bincount_op,
+    DenseBincount,
+    "DenseBincount",
+    args { input, size, weights },
+    attrs {
+        binary_output?: bool => "binary_output",
+    }
+);
+
+define_op!(
+    histogram_fixed_width_op,
+    HistogramFixedWidth,
+    "HistogramFixedWidth",
+    args { values, value_range, nbins },
+    attrs {
+        dtype?: DataType => "dtype",
+    }
+);
+
+fn check_size(function: &str, size: i32) -> Result<()> {
+    if size < 0 {
+        return Err(invalid_arg!(
+            "The size of {} must not be negative, but is {}",
+            function,
+            size
+        ));
+    }
+    Ok(())
+}
+
+/// Checks that `weights` can weight the counts of `input`, and returns them,
+/// or empty int32 weights to count occurrences if there are none.
+fn bincount_weights(
+    scope: &mut Scope,
+    function: &str,
+    input: &Output,
+    weights: Option<Output>,
+) -> Result<Output> {
+    let weights = match weights {
+        Some(weights) => weights,
+        None => return Ok(ops::constant(scope, Tensor::<i32>::new(&[0]))?.into()),
+    };
+    match weights.data_type() {
+        DataType::Int32 | DataType::Int64 | DataType::Float | DataType::Double => {}
+        data_type => {
+            return Err(invalid_arg!(
+                "The weights of {} must be int32, int64, float or double, but are {}",
+                function,
+                data_type
+            ))
+        }
+    }
+    if let (Some(input_dims), Some(weights_dims)) =
+        (known_dims(scope, input)?, known_dims(scope, &weights)?)
+    {
+        let compatible = input_dims.len() == weights_dims.len()
+            && input_dims
+                .iter()
+                .zip(&weights_dims)
+                .all(|(a, b)| a.is_none() || b.is_none() || a == b);
+        if !compatible {
+            return Err(invalid_arg!(
+                "The weights of {} must have the shape of the input {:?}, but have shape {:?}",
+                function,
+                input_dims,
+                weights_dims
+            ));
+        }
+    }
+    Ok(weights)
+}
+
+/// Counts the occurrences of each value in `0..size` in the int32 tensor
+/// `input`, e.g. the number of examples of each class in a batch of labels:
+///
+/// ```ignore
+/// let class_counts = bincount(&mut scope, labels, num_classes, None)?;
+/// ```
+///
+/// The result is an int32 vector of length `size`; values outside the range
+/// are ignored.  With `weights` of the same shape as `input`, each occurrence
+/// adds its weight instead of one, and the result has the type of the
+/// weights.
+pub fn bincount(
+    scope: &mut Scope,
+    input: Output,
+    size: i32,
+    weights: Option<Output>,
+) -> Result<Output> {
+    if input.data_type() != DataType::Int32 {
+        return Err(invalid_arg!(
+            "The input of bincount must be int32, but is {}",
+            input.data_type()
+        ));
+    }
+    check_size("bincount", size)?;
+    let mut scope = scope.new_sub_scope("bincount");
+    let weights = bincount_weights(&mut scope, "bincount", &input, weights)?;
+    let size = ops::constant(&mut scope, size)?;
+    Ok(bincount_op(&mut scope, input, size, weights)?.into())
+}
+
+/// Like `bincount`, but counts each row separately if `input` is a matrix,
+/// giving a result of shape `[rows, size]`, and accepts int64 input.  With
+/// `binary_output`, the result only records whether each value occurs, as 1
+/// or 0.
+pub fn dense_bincount(
+    scope: &mut Scope,
+    input: Output,
+    size: i32,
+    weights: Option<Output>,
+    binary_output: bool,
+) -> Result<Output> {
+    check_index_type(&input, "The input of dense_bincount")?;
+    check_size("dense_bincount", size)?;
+    if let Some(dims) = known_dims(scope, &input)? {
+        if dims.is_empty() || dims.len() > 2 {
+            return Err(invalid_arg!(
+                "The input of dense_bincount must be a vector or a matrix, but has shape {:?}",
+                dims
+            ));
+        }
+    }
+    let mut scope = scope.new_sub_scope("dense_bincount");
+    let weights = bincount_weights(&mut scope, "dense_bincount", &input, weights)?;
+    // The size has the type of the input.
+    let size: Output = if input.data_type() == DataType::Int64 {
+        ops::constant(&mut scope, i64::from(size))?.into()
+    } else {
+        ops::constant(&mut scope, size)?.into()
+    };
+    Ok(DenseBincount::new()
+        .binary_output(binary_output)
+        .build(&mut scope, input, size, weights)?
+        .into())
+}
+
+/// Returns an int32 histogram of `values` with `nbins` equal width bins
+/// spanning `value_range`, a vector `[min, max]` of the same type as
+/// `values`.
+///
+/// Values below `min` are counted in the first bin and values at or above
+/// `max` in the last, so every value is counted.
+pub fn histogram_fixed_width(
+    scope: &mut Scope,
+    values: Output,
+    value_range: Output,
+    nbins: i32,
+) -> Result<Output> {
+    match values.data_type() {
+        DataType::Int32 | DataType::Int64 | DataType::Float | DataType::Double => {}
+        data_type => {
+            return Err(invalid_arg!(
+            "The values of histogram_fixed_width must be int32, int64, float or double, but are {}",
+            data_type
+        ))
+        }
+    }
+    if value_range.data_type() != values.data_type() {
+        return Err(invalid_arg!(
+            "The value range of histogram_fixed_width has type {}, but the values have type {}",
+            value_range.data_type(),
+            values.data_type()
+        ));
+    }
+    if let Some(dims) = known_dims(scope, &value_range)? {
+        if dims.len() != 1 || matches!(dims[0], Some(d) if d != 2) {
+            return Err(invalid_arg!(
+                "The value range of histogram_fixed_width must have shape [2], but has shape {:?}",
+                dims
+            ));
+        }
+    }
+    if nbins <= 0 {
+        return Err(invalid_arg!(
+            "histogram_fixed_width needs a positive number of bins, but got {}",
+            nbins
+        ));
+    }
+    let mut scope = scope.new_sub_scope("histogram_fixed_width");
+    let nbins = ops::constant(&mut scope, nbins)?;
+    Ok(HistogramFixedWidth::new()
+        .dtype(DataType::Int32)
+        .build(&mut scope, values, value_range, nbins)?
+        .into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+
+    #[test]
+    fn counts() {
+        let mut scope = Scope::new_root_scope();
+        let labels: Output = ops::constant(&mut scope, &[1i32, 1, 3, 7][..])
+            .unwrap()
+            .into();
+        let weights: Output = ops::constant(&mut scope, &[0.5f32, 1.0, 2.0, 1.0][..])
+            .unwrap()
+            .into();
+        let counts = bincount(&mut scope, labels.clone(), 5, None).unwrap();
+        let weighted = bincount(&mut scope, labels.clone(), 4, Some(weights)).unwrap();
+        assert_eq!(&fetch::<i32>(&scope, &counts)[..], &[0, 2, 0, 1, 0]);
+        assert_eq!(&fetch::<f32>(&scope, &weighted)[..], &[0.0, 1.5, 0.0, 2.0]);
+
+        let rows: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 2]).with_values(&[0i64, 1, 1, 1]).unwrap(),
+        )
+        .unwrap()
+        .into();
+        let per_row = dense_bincount(&mut scope, rows.clone(), 2, None, false).unwrap();
+        let binary = dense_bincount(&mut scope, rows, 2, None, true).unwrap();
+        let per_row = fetch::<i32>(&scope, &per_row);
+        assert_eq!(per_row.dims(), &[2, 2]);
+        assert_eq!(&per_row[..], &[1, 1, 0, 2]);
+        assert_eq!(&fetch::<i32>(&scope, &binary)[..], &[1, 1, 0, 1]);
+
+        assert!(bincount(&mut scope, labels.clone(), -1, None).is_err());
+        let short_weights: Output = ops::constant(&mut scope, &[1.0f32][..]).unwrap().into();
+        assert!(bincount(&mut scope, labels, 4, Some(short_weights)).is_err());
+        let floats: Output = ops::constant(&mut scope, &[1.0f32][..]).unwrap().into();
+        assert!(dense_bincount(&mut scope, floats, 4, None, false).is_err());
+    }
+
+    #[test]
+    fn histogram() {
+        let mut scope = Scope::new_root_scope();
+        let values: Output = ops::constant(&mut scope, &[-1.0f32, 0.0, 1.5, 2.0, 5.0, 15.0][..])
+            .unwrap()
+            .into();
+        let range: Output = ops::constant(&mut scope, &[0.0f32, 5.0][..])
+            .unwrap()
+            .into();
+        let histogram =
+            histogram_fixed_width(&mut scope, values.clone(), range.clone(), 5).unwrap();
+        assert_eq!(&fetch::<i32>(&scope, &histogram)[..], &[2, 1, 1, 0, 2]);
+
+        assert!(histogram_fixed_width(&mut scope, values.clone(), range, 0).is_err());
+        let int_range: Output = ops::constant(&mut scope, &[0i32, 5][..]).unwrap().into();
+        assert!(histogram_fixed_width(&mut scope, values.clone(), int_range, 5).is_err());
+        let long_range: Output = ops::constant(&mut scope, &[0.0f32, 1.0, 2.0][..])
+            .unwrap()
+            .into();
+        assert!(histogram_fixed_width(&mut scope, values, long_range, 5).is_err());
+    }
+}
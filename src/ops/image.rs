@@ -0,0 +1,467 @@
+//! Resizing, cropping and augmentation of batches of images.
+//!
+//! Every function here takes images in NHWC layout, i.e. a tensor of shape
+//! `[batch, height, width, channels]`.  Cropping and padding compute their
+//! offsets while building the graph, so they need the height and width to be
+//! known.
+
+use super::known_dims;
+use super::AdjustContrast;
+use super::AdjustHue;
+use super::AdjustSaturation;
+use super::Cast;
+use super::NonMaxSuppression;
+use super::RandomUniform;
+use super::ResizeArea;
+use super::ResizeBicubic;
+use super::ResizeBilinear;
+use super::ResizeNearestNeighbor;
+use super::ScaleAndTranslate;
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+/// The known dimensions of a batch of images.
+type ImageDims = [Option<i64>; 4];
+
+/// Returns the known dimensions of `images`, checking that they are NHWC.
+fn image_dims(scope: &Scope, images: &Output, name: &str) -> Result<ImageDims> {
+    match known_dims(scope, images)? {
+        Some(dims) if dims.len() == 4 => Ok([dims[0], dims[1], dims[2], dims[3]]),
+        Some(dims) => Err(invalid_arg!(
+            "{} must have shape [batch, height, width, channels], but has shape {:?}",
+            name,
+            dims
+        )),
+        None => Ok([None; 4]),
+    }
+}
+
+/// Returns the height and width of `images`, which must be known.
+fn known_size(scope: &Scope, images: &Output, name: &str) -> Result<(i64, i64)> {
+    match image_dims(scope, images, name)? {
+        [_, Some(height), Some(width), _] => Ok((height, width)),
+        dims => Err(invalid_arg!(
+            "{} needs images of known height and width, but they have shape {:?}",
+            name,
+            dims
+        )),
+    }
+}
+
+fn check_float(images: &Output, name: &str) -> Result<()> {
+    let data_type = images.operation.output_type(images.index as usize);
+    match data_type {
+        DataType::Float | DataType::Double | DataType::Half | DataType::BFloat16 => Ok(()),
+        _ => Err(invalid_arg!(
+            "{} needs floating point images, but they are {}",
+            name,
+            data_type
+        )),
+    }
+}
+
+/// Takes the `[height, width]` window of `images` starting at `offset`, for
+/// every image and channel.
+fn crop(
+    scope: &mut Scope,
+    images: Output,
+    offset: Output,
+    height: i64,
+    width: i64,
+) -> Result<Output> {
+    let size = ops::constant(scope, &[-1, height as i32, width as i32, -1][..])?;
+    Ok(ops::slice(scope, images, offset, size)?.into())
+}
+
+/// How `resize` interpolates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ResizeMethod {
+    /// Bilinear interpolation.
+    Bilinear,
+    /// Nearest neighbor interpolation, which keeps the type of the images.
+    NearestNeighbor,
+    /// Bicubic interpolation.
+    Bicubic,
+    /// Averages the pixels covered by each output pixel.
+    Area,
+    /// A Lanczos kernel with radius 3.
+    Lanczos3,
+    /// A Lanczos kernel with radius 5.
+    Lanczos5,
+    /// A Gaussian kernel with radius 3 and sigma 1.5.
+    Gaussian,
+    /// A Mitchell-Netravali cubic filter.
+    MitchellCubic,
+}
+
+impl ResizeMethod {
+    /// Returns the kernel for `ScaleAndTranslate`, for the methods which need
+    /// it.
+    fn kernel(self) -> Option<&'static str> {
+        match self {
+            ResizeMethod::Lanczos3 => Some("lanczos3"),
+            ResizeMethod::Lanczos5 => Some("lanczos5"),
+            ResizeMethod::Gaussian => Some("gaussian"),
+            ResizeMethod::MitchellCubic => Some("mitchellcubic"),
+            _ => None,
+        }
+    }
+}
+
+/// Resizes `images` to `[height, width]` with the given method.  The result
+/// is float, except with `ResizeMethod::NearestNeighbor`.
+///
+/// The kernel-based methods (`Lanczos3`, `Lanczos5`, `Gaussian` and
+/// `MitchellCubic`) need the height and width of the images to be known.
+pub fn resize(
+    scope: &mut Scope,
+    images: Output,
+    size: [i32; 2],
+    method: ResizeMethod,
+) -> Result<Output> {
+    image_dims(scope, &images, "The images to resize")?;
+    if size[0] <= 0 || size[1] <= 0 {
+        return Err(invalid_arg!("Images can't be resized to {:?}", size));
+    }
+    let size_tensor = ops::constant(scope, &size[..])?;
+    let resized = match method {
+        ResizeMethod::Bilinear => {
+            ResizeBilinear::new()
+                .half_pixel_centers(true)
+                .build(scope, images, size_tensor)?
+        }
+        ResizeMethod::NearestNeighbor => ResizeNearestNeighbor::new()
+            .half_pixel_centers(true)
+            .build(scope, images, size_tensor)?,
+        ResizeMethod::Bicubic => {
+            ResizeBicubic::new()
+                .half_pixel_centers(true)
+                .build(scope, images, size_tensor)?
+        }
+        ResizeMethod::Area => ResizeArea::new().build(scope, images, size_tensor)?,
+        _ => {
+            let (height, width) = known_size(scope, &images, "Kernel-based resizing")?;
+            let scale = ops::constant(
+                scope,
+                &[
+                    size[0] as f32 / height as f32,
+                    size[1] as f32 / width as f32,
+                ][..],
+            )?;
+            let translation = ops::constant(scope, &[0.0f32, 0.0][..])?;
+            ScaleAndTranslate::new()
+                .kernel_type(method.kernel().unwrap_or_default())
+                .build(scope, images, size_tensor, scale, translation)?
+        }
+    };
+    Ok(resized.into())
+}
+
+/// Crops the central `fraction` (between 0 and 1) of the height and width of
+/// `images`.
+pub fn central_crop(scope: &mut Scope, images: Output, fraction: f64) -> Result<Output> {
+    if !(fraction > 0.0 && fraction <= 1.0) {
+        return Err(invalid_arg!(
+            "The central crop fraction must be in (0, 1], but is {}",
+            fraction
+        ));
+    }
+    let (height, width) = known_size(scope, &images, "central_crop")?;
+    let crop_height = ((height as f64 * fraction) as i64).max(1);
+    let crop_width = ((width as f64 * fraction) as i64).max(1);
+    let offset = ops::constant(
+        scope,
+        &[
+            0,
+            ((height - crop_height) / 2) as i32,
+            ((width - crop_width) / 2) as i32,
+            0,
+        ][..],
+    )?;
+    crop(scope, images, offset.into(), crop_height, crop_width)
+}
+
+/// Crops a randomly placed `[height, width]` window of `images`.  The same
+/// window is used for every image of the batch.  With a `seed`, the sequence
+/// of windows is reproducible.
+pub fn random_crop(
+    scope: &mut Scope,
+    images: Output,
+    size: [i64; 2],
+    seed: Option<i64>,
+) -> Result<Output> {
+    let (height, width) = known_size(scope, &images, "random_crop")?;
+    if size[0] <= 0 || size[1] <= 0 || size[0] > height || size[1] > width {
+        return Err(invalid_arg!(
+            "Cannot randomly crop {:?} from images of size [{}, {}]",
+            size,
+            height,
+            width
+        ));
+    }
+    // Scaling uniform values in [0, 1) by the number of possible offsets in
+    // each dimension and rounding down gives a uniform offset, which is
+    // always zero for the batch and channel dimensions.
+    let shape = ops::constant(scope, &[4i32][..])?;
+    let mut uniform = RandomUniform::new().dtype(DataType::Float);
+    if let Some(seed) = seed {
+        uniform = uniform.seed(seed);
+    }
+    let uniform = uniform.build(scope, shape)?;
+    let limits = ops::constant(
+        scope,
+        &[
+            1.0f32,
+            (height - size[0] + 1) as f32,
+            (width - size[1] + 1) as f32,
+            1.0,
+        ][..],
+    )?;
+    let scaled = ops::multiply(scope, uniform, limits)?;
+    let floored = ops::floor(scope, scaled)?;
+    let offset = Cast::new()
+        .dst_type(DataType::Int32)
+        .build(scope, floored)?;
+    crop(scope, images, offset.into(), size[0], size[1])
+}
+
+/// Mirrors `images` horizontally.
+pub fn flip_left_right(scope: &mut Scope, images: Output) -> Result<Output> {
+    image_dims(scope, &images, "The images to flip")?;
+    let axis = ops::constant(scope, &[2i32][..])?;
+    Ok(ops::reverse(scope, images, axis)?.into())
+}
+
+/// Mirrors `images` vertically.
+pub fn flip_up_down(scope: &mut Scope, images: Output) -> Result<Output> {
+    image_dims(scope, &images, "The images to flip")?;
+    let axis = ops::constant(scope, &[1i32][..])?;
+    Ok(ops::reverse(scope, images, axis)?.into())
+}
+
+/// Adds `delta` to every pixel of the float images `images`.
+pub fn adjust_brightness(scope: &mut Scope, images: Output, delta: f32) -> Result<Output> {
+    image_dims(scope, &images, "The images to brighten")?;
+    check_float(&images, "adjust_brightness")?;
+    let delta = ops::constant(scope, delta)?;
+    let delta = Cast::new()
+        .dst_type(images.operation.output_type(images.index as usize))
+        .build(scope, delta)?;
+    Ok(ops::add(scope, images, delta)?.into())
+}
+
+/// Scales the distance of every pixel of the float images `images` from the
+/// mean of its image and channel by `factor`.
+pub fn adjust_contrast(scope: &mut Scope, images: Output, factor: f32) -> Result<Output> {
+    image_dims(scope, &images, "The images to adjust")?;
+    check_float(&images, "adjust_contrast")?;
+    let factor = ops::constant(scope, factor)?;
+    Ok(AdjustContrast::new().build(scope, images, factor)?.into())
+}
+
+/// Rotates the hue of the RGB images `images` by `delta`, which is a
+/// fraction of a full turn between -1 and 1.
+pub fn adjust_hue(scope: &mut Scope, images: Output, delta: f32) -> Result<Output> {
+    check_rgb(scope, &images, "adjust_hue")?;
+    if !(-1.0..=1.0).contains(&delta) {
+        return Err(invalid_arg!(
+            "The hue delta must be between -1 and 1, but is {}",
+            delta
+        ));
+    }
+    let delta = ops::constant(scope, delta)?;
+    Ok(AdjustHue::new().build(scope, images, delta)?.into())
+}
+
+/// Multiplies the saturation of the RGB images `images` by `factor`.
+pub fn adjust_saturation(scope: &mut Scope, images: Output, factor: f32) -> Result<Output> {
+    check_rgb(scope, &images, "adjust_saturation")?;
+    if factor < 0.0 {
+        return Err(invalid_arg!(
+            "The saturation factor can't be negative, but is {}",
+            factor
+        ));
+    }
+    let factor = ops::constant(scope, factor)?;
+    Ok(AdjustSaturation::new().build(scope, images, factor)?.into())
+}
+
+fn check_rgb(scope: &Scope, images: &Output, name: &str) -> Result<()> {
+    check_float(images, name)?;
+    match image_dims(scope, images, name)?[3] {
+        Some(channels) if channels != 3 => Err(invalid_arg!(
+            "{} needs RGB images, but they have {} channels",
+            name,
+            channels
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Places `images` at (`offset_height`, `offset_width`) in images of size
+/// `[target_height, target_width]`, padding with zeros.
+pub fn pad_to_bounding_box(
+    scope: &mut Scope,
+    images: Output,
+    offset_height: i64,
+    offset_width: i64,
+    target_height: i64,
+    target_width: i64,
+) -> Result<Output> {
+    let (height, width) = known_size(scope, &images, "pad_to_bounding_box")?;
+    let bottom = target_height - offset_height - height;
+    let right = target_width - offset_width - width;
+    if offset_height < 0 || offset_width < 0 || bottom < 0 || right < 0 {
+        return Err(invalid_arg!(
+            "Images of size [{}, {}] at offset [{}, {}] don't fit in [{}, {}]",
+            height,
+            width,
+            offset_height,
+            offset_width,
+            target_height,
+            target_width
+        ));
+    }
+    let paddings = ops::constant(
+        scope,
+        crate::Tensor::new(&[4, 2]).with_values(&[
+            0,
+            0,
+            offset_height as i32,
+            bottom as i32,
+            offset_width as i32,
+            right as i32,
+            0,
+            0,
+        ])?,
+    )?;
+    Ok(ops::pad(scope, images, paddings)?.into())
+}
+
+/// Greedily selects up to `max_output_size` of the `boxes` (of shape
+/// `[num_boxes, 4]`, as `[y1, x1, y2, x2]`) in decreasing order of `scores`
+/// (of shape `[num_boxes]`), skipping boxes which overlap an already selected
+/// box by more than `iou_threshold` or score below `score_threshold`.
+///
+/// Returns the int32 indices of the selected boxes.
+pub fn non_max_suppression(
+    scope: &mut Scope,
+    boxes: Output,
+    scores: Output,
+    max_output_size: u32,
+    iou_threshold: f32,
+    score_threshold: f32,
+) -> Result<Output> {
+    let boxes_dims = known_dims(scope, &boxes)?;
+    if let Some(dims) = &boxes_dims {
+        if dims.len() != 2 || matches!(dims[1], Some(d) if d != 4) {
+            return Err(invalid_arg!(
+                "Boxes must have shape [num_boxes, 4], but have shape {:?}",
+                dims
+            ));
+        }
+    }
+    if let Some(dims) = known_dims(scope, &scores)? {
+        let num_boxes = boxes_dims.and_then(|d| d[0]);
+        if dims.len() != 1 || (dims[0].is_some() && num_boxes.is_some() && dims[0] != num_boxes) {
+            return Err(invalid_arg!(
+                "Scores must have shape [num_boxes], but have shape {:?} for {:?} boxes",
+                dims,
+                num_boxes
+            ));
+        }
+    }
+    if !(0.0..=1.0).contains(&iou_threshold) {
+        return Err(invalid_arg!(
+            "The IOU threshold must be between 0 and 1, but is {}",
+            iou_threshold
+        ));
+    }
+    let max_output_size = ops::constant(scope, max_output_size as i32)?;
+    let iou_threshold = ops::constant(scope, iou_threshold)?;
+    let score_threshold = ops::constant(scope, score_threshold)?;
+    Ok(NonMaxSuppression::new()
+        .build(
+            scope,
+            boxes,
+            scores,
+            max_output_size,
+            iou_threshold,
+            score_threshold,
+        )?
+        .into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+    use crate::Tensor;
+
+    fn image(scope: &mut Scope) -> Output {
+        // A 1x4x4x1 image with pixels 0..16.
+        let values: Vec<f32> = (0..16).map(|i| i as f32).collect();
+        ops::constant(
+            scope,
+            Tensor::new(&[1, 4, 4, 1]).with_values(&values).unwrap(),
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn crop_flip_and_pad() {
+        let mut scope = Scope::new_root_scope();
+        let images = image(&mut scope);
+        let center = central_crop(&mut scope, images.clone(), 0.5).unwrap();
+        assert_eq!(&fetch::<f32>(&scope, &center)[..], &[5.0, 6.0, 9.0, 10.0]);
+        let flipped = flip_left_right(&mut scope, center).unwrap();
+        assert_eq!(&fetch::<f32>(&scope, &flipped)[..], &[6.0, 5.0, 10.0, 9.0]);
+        let random = random_crop(&mut scope, images.clone(), [2, 3], Some(7)).unwrap();
+        assert_eq!(fetch::<f32>(&scope, &random).dims(), &[1, 2, 3, 1]);
+        let padded = pad_to_bounding_box(&mut scope, images.clone(), 1, 0, 6, 5).unwrap();
+        let padded = fetch::<f32>(&scope, &padded);
+        assert_eq!(padded.dims(), &[1, 6, 5, 1]);
+        assert_eq!(&padded[5..8], &[0.0, 1.0, 2.0]);
+        let resized = resize(&mut scope, images.clone(), [2, 2], ResizeMethod::Area).unwrap();
+        assert_eq!(&fetch::<f32>(&scope, &resized)[..], &[2.5, 4.5, 10.5, 12.5]);
+        let lanczos = resize(&mut scope, images.clone(), [8, 8], ResizeMethod::Lanczos3).unwrap();
+        assert_eq!(fetch::<f32>(&scope, &lanczos).dims(), &[1, 8, 8, 1]);
+
+        assert!(random_crop(&mut scope, images.clone(), [5, 1], None).is_err());
+        assert!(pad_to_bounding_box(&mut scope, images.clone(), 1, 0, 4, 4).is_err());
+        assert!(central_crop(&mut scope, images.clone(), 0.0).is_err());
+        assert!(adjust_hue(&mut scope, images, 0.1).is_err());
+        let vector: Output = ops::constant(&mut scope, &[1.0f32][..]).unwrap().into();
+        assert!(flip_up_down(&mut scope, vector).is_err());
+    }
+
+    #[test]
+    fn suppression() {
+        let mut scope = Scope::new_root_scope();
+        let boxes: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[3, 4])
+                .with_values(&[
+                    0.0f32, 0.0, 1.0, 1.0, 0.0, 0.1, 1.0, 1.1, 0.0, 10.0, 1.0, 11.0,
+                ])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let scores: Output = ops::constant(&mut scope, &[0.9f32, 0.8, 0.7][..])
+            .unwrap()
+            .into();
+        let selected = non_max_suppression(&mut scope, boxes.clone(), scores, 3, 0.5, 0.0).unwrap();
+        assert_eq!(&fetch::<i32>(&scope, &selected)[..], &[0, 2]);
+        let wrong: Output = ops::constant(&mut scope, &[0.9f32, 0.8][..])
+            .unwrap()
+            .into();
+        assert!(non_max_suppression(&mut scope, boxes, wrong, 3, 0.5, 0.0).is_err());
+    }
+}
@@ -0,0 +1,74 @@
+use tensorflow_macros::define_op;
+
+define_op!(resize_bilinear_op, ResizeBilinear, "ResizeBilinear", args { images, size }, attrs {
+    align_corners?: bool => "align_corners",
+    half_pixel_centers?: bool => "half_pixel_centers",
+});
+
+define_op!(
+    resize_nearest_neighbor_op,
+    ResizeNearestNeighbor,
+    "ResizeNearestNeighbor",
+    args { images, size },
+    attrs {
+        align_corners?: bool => "align_corners",
+        half_pixel_centers?: bool => "half_pixel_centers",
+    }
+);
+
+define_op!(resize_bicubic_op, ResizeBicubic, "ResizeBicubic", args { images, size }, attrs {
+    align_corners?: bool => "align_corners",
+    half_pixel_centers?: bool => "half_pixel_centers",
+});
+
+define_op!(resize_area_op, ResizeArea, "ResizeArea", args { images, size }, attrs {
+    align_corners?: bool => "align_corners",
+});
+
+define_op!(
+    scale_and_translate_op,
+    ScaleAndTranslate,
+    "ScaleAndTranslate",
+    args { images, size, scale, translation },
+    attrs {
+        kernel_type?: String => "kernel_type",
+        antialias?: bool => "antialias",
+    }
+);
+
+define_op!(
+    adjust_contrast_op,
+    AdjustContrast,
+    "AdjustContrastv2",
+    args {
+        images,
+        contrast_factor,
+    }
+);
+
+define_op!(
+    adjust_hue_op,
+    AdjustHue,
+    "AdjustHue",
+    args { images, delta }
+);
+
+define_op!(
+    adjust_saturation_op,
+    AdjustSaturation,
+    "AdjustSaturation",
+    args { images, scale }
+);
+
+define_op!(
+    non_max_suppression_op,
+    NonMaxSuppression,
+    "NonMaxSuppressionV3",
+    args {
+        boxes,
+        scores,
+        max_output_size,
+        iou_threshold,
+        score_threshold
+    }
+);
@@ -0,0 +1,345 @@
+//! Linear algebra on matrices and batches of matrices.
+//!
+//! Every function here works on tensors whose two innermost dimensions are
+//! the rows and columns of a matrix, with any leading dimensions treated as
+//! a batch.
+
+use super::known_dims;
+use super::BatchMatMul;
+use super::MatMul;
+use super::MatrixInverse;
+use super::MatrixTriangularSolve;
+use super::Qr;
+use super::Svd;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+pub use super::einsum;
+
+/// The known dimensions of a matrix (or batch of matrices), or None if its
+/// rank is unknown.
+type Dims = Option<Vec<Option<i64>>>;
+
+/// Returns the known dimensions of `input`, checking that it has at least two
+/// dimensions.
+fn matrix_dims(scope: &Scope, input: &Output, name: &str) -> Result<Dims> {
+    let dims = known_dims(scope, input)?;
+    if let Some(d) = &dims {
+        if d.len() < 2 {
+            return Err(invalid_arg!(
+                "{} must be a matrix or a batch of matrices, but has shape {:?}",
+                name,
+                d
+            ));
+        }
+    }
+    Ok(dims)
+}
+
+/// Returns the dimension `from_end` places from the end, if it is known.
+fn inner_dim(dims: &Dims, from_end: usize) -> Option<i64> {
+    dims.as_ref()
+        .and_then(|d| d.get(d.len().wrapping_sub(from_end)).cloned().flatten())
+}
+
+fn check_square(scope: &Scope, input: &Output, name: &str) -> Result<Dims> {
+    let dims = matrix_dims(scope, input, name)?;
+    if let (Some(rows), Some(columns)) = (inner_dim(&dims, 2), inner_dim(&dims, 1)) {
+        if rows != columns {
+            return Err(invalid_arg!(
+                "{} must be square, but has shape {:?}",
+                name,
+                dims.unwrap_or_default()
+            ));
+        }
+    }
+    Ok(dims)
+}
+
+fn is_complex(input: &Output) -> bool {
+    matches!(
+        input.operation.output_type(input.index as usize),
+        DataType::Complex64 | DataType::Complex128
+    )
+}
+
+/// Options for `matmul`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct MatMulOptions {
+    transpose_a: bool,
+    transpose_b: bool,
+    adjoint_a: bool,
+    adjoint_b: bool,
+}
+
+impl MatMulOptions {
+    /// Transposes `a` before multiplying.
+    pub fn with_transpose_a(self, transpose_a: bool) -> Self {
+        Self {
+            transpose_a,
+            ..self
+        }
+    }
+
+    /// Transposes `b` before multiplying.
+    pub fn with_transpose_b(self, transpose_b: bool) -> Self {
+        Self {
+            transpose_b,
+            ..self
+        }
+    }
+
+    /// Uses the conjugate transpose of `a`.  For real matrices, this is the
+    /// same as transposing.
+    pub fn with_adjoint_a(self, adjoint_a: bool) -> Self {
+        Self { adjoint_a, ..self }
+    }
+
+    /// Uses the conjugate transpose of `b`.  For real matrices, this is the
+    /// same as transposing.
+    pub fn with_adjoint_b(self, adjoint_b: bool) -> Self {
+        Self { adjoint_b, ..self }
+    }
+}
+
+/// Multiplies the matrices `a` and `b`, or batches of matrices whose batch
+/// dimensions broadcast against each other.
+///
+/// Plain matrices use `MatMul` and batches use `BatchMatMulV2`.
+pub fn matmul(scope: &mut Scope, a: Output, b: Output, opts: MatMulOptions) -> Result<Output> {
+    if (opts.transpose_a && opts.adjoint_a) || (opts.transpose_b && opts.adjoint_b) {
+        return Err(invalid_arg!(
+            "A matmul operand can be transposed or adjointed, but not both"
+        ));
+    }
+    let a_dims = matrix_dims(scope, &a, "The left operand of matmul")?;
+    let b_dims = matrix_dims(scope, &b, "The right operand of matmul")?;
+    let flip_a = opts.transpose_a || opts.adjoint_a;
+    let flip_b = opts.transpose_b || opts.adjoint_b;
+    let a_inner = inner_dim(&a_dims, if flip_a { 2 } else { 1 });
+    let b_inner = inner_dim(&b_dims, if flip_b { 1 } else { 2 });
+    if let (Some(x), Some(y)) = (a_inner, b_inner) {
+        if x != y {
+            return Err(invalid_arg!(
+                "Cannot multiply matrices of shapes {:?} and {:?} with {:?}",
+                a_dims.unwrap_or_default(),
+                b_dims.unwrap_or_default(),
+                opts
+            ));
+        }
+    }
+    let rank = |dims: &Dims| dims.as_ref().map(Vec::len);
+    let conjugate = (opts.adjoint_a || opts.adjoint_b) && is_complex(&a);
+    if rank(&a_dims) == Some(2) && rank(&b_dims) == Some(2) && !conjugate {
+        return Ok(MatMul::new()
+            .transpose_a(flip_a)
+            .transpose_b(flip_b)
+            .build(scope, a, b)?
+            .into());
+    }
+    if (opts.transpose_a || opts.transpose_b) && is_complex(&a) {
+        return Err(invalid_arg!(
+            "Transposing complex batches of matrices without conjugating isn't supported; use the adjoint instead"
+        ));
+    }
+    Ok(BatchMatMul::new()
+        .adj_x(flip_a)
+        .adj_y(flip_b)
+        .build(scope, a, b)?
+        .into())
+}
+
+/// Returns the inverse of the square matrix (or batch of matrices) `input`.
+pub fn inv(scope: &mut Scope, input: Output) -> Result<Output> {
+    check_square(scope, &input, "The matrix to invert")?;
+    Ok(MatrixInverse::new().build(scope, input)?.into())
+}
+
+/// Returns the lower triangular Cholesky factor `l` of the symmetric (or
+/// Hermitian) positive definite matrix `input`, such that
+/// `input = l * adjoint(l)`.
+pub fn cholesky(scope: &mut Scope, input: Output) -> Result<Output> {
+    check_square(scope, &input, "The matrix to factor")?;
+    Ok(super::cholesky_op(scope, input)?.into())
+}
+
+/// The result of `qr`.
+#[derive(Debug, Clone)]
+pub struct QrDecomposition {
+    /// The orthonormal (or unitary) factor.
+    pub q: Output,
+    /// The upper triangular factor.
+    pub r: Output,
+}
+
+/// Computes the QR decomposition of `input`, i.e. `input = q * r`.  With
+/// `full_matrices`, `q` is square; otherwise only its first
+/// `min(rows, columns)` columns are computed.
+pub fn qr(scope: &mut Scope, input: Output, full_matrices: bool) -> Result<QrDecomposition> {
+    matrix_dims(scope, &input, "The matrix to decompose")?;
+    let op = Qr::new().full_matrices(full_matrices).build(scope, input)?;
+    Ok(QrDecomposition {
+        q: Output {
+            operation: op.clone(),
+            index: 0,
+        },
+        r: Output {
+            operation: op,
+            index: 1,
+        },
+    })
+}
+
+/// The result of `svd`.
+#[derive(Debug, Clone)]
+pub struct SvdDecomposition {
+    /// The singular values, in decreasing order.
+    pub s: Output,
+    /// The left singular vectors.
+    pub u: Output,
+    /// The right singular vectors.
+    pub v: Output,
+}
+
+/// Computes the singular value decomposition of `input`, i.e.
+/// `input = u * diag(s) * adjoint(v)`.  With `full_matrices`, `u` and `v`
+/// are square; otherwise only their first `min(rows, columns)` columns are
+/// computed.
+pub fn svd(scope: &mut Scope, input: Output, full_matrices: bool) -> Result<SvdDecomposition> {
+    matrix_dims(scope, &input, "The matrix to decompose")?;
+    let op = Svd::new()
+        .compute_uv(true)
+        .full_matrices(full_matrices)
+        .build(scope, input)?;
+    let output = |index| Output {
+        operation: op.clone(),
+        index,
+    };
+    Ok(SvdDecomposition {
+        s: output(0),
+        u: output(1),
+        v: output(2),
+    })
+}
+
+/// Solves `matrix * x = rhs` for `x`, where `matrix` is lower triangular (or
+/// upper triangular if `lower` is false).  Only the relevant triangle of
+/// `matrix` is read.
+pub fn triangular_solve(
+    scope: &mut Scope,
+    matrix: Output,
+    rhs: Output,
+    lower: bool,
+) -> Result<Output> {
+    let matrix_dims = check_square(scope, &matrix, "The triangular matrix")?;
+    let rhs_dims = self::matrix_dims(scope, &rhs, "The right hand side")?;
+    if let (Some(n), Some(rows)) = (inner_dim(&matrix_dims, 1), inner_dim(&rhs_dims, 2)) {
+        if n != rows {
+            return Err(invalid_arg!(
+                "A triangular matrix of shape {:?} can't solve for a right hand side of shape {:?}",
+                matrix_dims.unwrap_or_default(),
+                rhs_dims.unwrap_or_default()
+            ));
+        }
+    }
+    Ok(MatrixTriangularSolve::new()
+        .lower(lower)
+        .build(scope, matrix, rhs)?
+        .into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::ops::run;
+    use crate::Tensor;
+
+    fn constant(scope: &mut Scope, dims: &[u64], values: &[f32]) -> Output {
+        ops::constant(scope, Tensor::new(dims).with_values(values).unwrap())
+            .unwrap()
+            .into()
+    }
+
+    #[test]
+    fn matmul_shapes() {
+        let mut scope = Scope::new_root_scope();
+        let a = constant(&mut scope, &[2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let b = constant(&mut scope, &[2, 1], &[1.0, 1.0]);
+        assert!(matmul(&mut scope, a.clone(), b.clone(), MatMulOptions::default()).is_err());
+        assert!(matmul(
+            &mut scope,
+            a.clone(),
+            b.clone(),
+            MatMulOptions::default()
+                .with_transpose_a(true)
+                .with_adjoint_a(true)
+        )
+        .is_err());
+        let product = matmul(
+            &mut scope,
+            a.clone(),
+            b.clone(),
+            MatMulOptions::default().with_transpose_a(true),
+        )
+        .unwrap();
+        assert_eq!(product.operation.op_type().unwrap(), "MatMul");
+        let batch = constant(&mut scope, &[1, 2, 3], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]);
+        let batched = matmul(
+            &mut scope,
+            batch,
+            b,
+            MatMulOptions::default().with_adjoint_a(true),
+        )
+        .unwrap();
+        assert_eq!(batched.operation.op_type().unwrap(), "BatchMatMulV2");
+        let results = run::<f32>(&scope, &[], &[&product, &batched]).unwrap();
+        assert_eq!(&results[0][..], &[5.0, 7.0, 9.0]);
+        assert_eq!(results[1].dims(), &[1, 3, 1]);
+        assert_eq!(&results[1][..], &[5.0, 7.0, 9.0]);
+        assert!(inv(&mut scope, a).is_err());
+    }
+
+    #[test]
+    fn solve_and_decompose() {
+        let mut scope = Scope::new_root_scope();
+        let m = constant(&mut scope, &[2, 2], &[4.0, 2.0, 2.0, 3.0]);
+        let rhs = constant(&mut scope, &[2, 1], &[2.0, 5.0]);
+        let l = cholesky(&mut scope, m.clone()).unwrap();
+        let y = triangular_solve(&mut scope, l.clone(), rhs.clone(), true).unwrap();
+        let inverse = inv(&mut scope, m.clone()).unwrap();
+        let decomposition = qr(&mut scope, m.clone(), false).unwrap();
+        let singular = svd(&mut scope, m.clone(), false).unwrap();
+        let results = run::<f32>(
+            &scope,
+            &[],
+            &[&l, &y, &inverse, &decomposition.r, &singular.s],
+        )
+        .unwrap();
+        let close = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(x, y)| (x - y).abs() < 1e-4);
+        assert!(
+            close(&results[0], &[2.0, 0.0, 1.0, 2f32.sqrt()]),
+            "{:?}",
+            &results[0][..]
+        );
+        assert!(
+            close(&results[1], &[1.0, 4.0 / 2f32.sqrt()]),
+            "{:?}",
+            &results[1][..]
+        );
+        assert!(
+            close(&results[2], &[0.375, -0.25, -0.25, 0.5]),
+            "{:?}",
+            &results[2][..]
+        );
+        assert_eq!(results[3].dims(), &[2, 2]);
+        assert_eq!(results[4].dims(), &[2]);
+
+        let wrong = constant(&mut scope, &[3, 1], &[1.0, 2.0, 3.0]);
+        assert!(triangular_solve(&mut scope, l, wrong, true).is_err());
+    }
+}
@@ -0,0 +1,295 @@
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use std::collections::HashMap;
+use tensorflow_macros::define_op;
+
+define_op!(batch_mat_mul_op, BatchMatMul, "BatchMatMulV2", args { x, y }, attrs {
+    adj_x?: bool => "adj_x",
+    adj_y?: bool => "adj_y",
+});
+
+define_op!(matrix_inverse_op, MatrixInverse, "MatrixInverse", args { input }, attrs {
+    adjoint?: bool => "adjoint",
+});
+
+define_op!(cholesky_op, Cholesky, "Cholesky", args { input });
+
+define_op!(qr_op, Qr, "Qr", args { input }, attrs {
+    full_matrices?: bool => "full_matrices",
+});
+
+define_op!(svd_op, Svd, "Svd", args { input }, attrs {
+    compute_uv?: bool => "compute_uv",
+    full_matrices?: bool => "full_matrices",
+});
+
+define_op!(
+    matrix_triangular_solve_op,
+    MatrixTriangularSolve,
+    "MatrixTriangularSolve",
+    args { matrix, rhs },
+    attrs {
+        lower?: bool => "lower",
+        adjoint?: bool => "adjoint",
+    }
+);
+
+/// The subscripts of one operand of an einsum equation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct EinsumTerm {
+    text: String,
+    labels: Vec<char>,
+    // The number of labels before the ellipsis, if there is one.
+    ellipsis: Option<usize>,
+}
+
+impl EinsumTerm {
+    fn parse(equation: &str, term: &str) -> Result<Self> {
+        let mut labels = Vec::new();
+        let mut ellipsis = None;
+        let mut rest = term.trim();
+        while let Some(c) = rest.chars().next() {
+            if rest.starts_with("...") {
+                if ellipsis.is_some() {
+                    return Err(invalid_arg!(
+                        "Einsum term '{}' in '{}' has more than one ellipsis",
+                        term,
+                        equation
+                    ));
+                }
+                ellipsis = Some(labels.len());
+                rest = &rest[3..];
+            } else if c.is_ascii_alphabetic() {
+                labels.push(c);
+                rest = &rest[1..];
+            } else {
+                return Err(invalid_arg!(
+                    "Einsum term '{}' in '{}' contains '{}', but only letters and '...' are allowed",
+                    term,
+                    equation,
+                    c
+                ));
+            }
+        }
+        Ok(Self {
+            text: term.trim().to_string(),
+            labels,
+            ellipsis,
+        })
+    }
+
+    /// Returns each label with the index of its dimension in a tensor of the
+    /// given rank.
+    fn dims(&self, rank: usize) -> Vec<(char, usize)> {
+        let after_ellipsis = self.labels.len() - self.ellipsis.unwrap_or(self.labels.len());
+        self.labels
+            .iter()
+            .enumerate()
+            .map(|(i, &label)| match self.ellipsis {
+                Some(e) if i >= e => (label, rank - after_ellipsis + (i - e)),
+                _ => (label, i),
+            })
+            .collect()
+    }
+}
+
+/// Parses `equation`, returning the input terms and the output term, if it
+/// is explicit.
+fn parse_einsum(
+    equation: &str,
+    num_inputs: usize,
+) -> Result<(Vec<EinsumTerm>, Option<EinsumTerm>)> {
+    let (inputs, output) = match equation.find("->") {
+        Some(i) => (&equation[..i], Some(&equation[i + 2..])),
+        None => (equation, None),
+    };
+    let inputs = inputs
+        .split(',')
+        .map(|term| EinsumTerm::parse(equation, term))
+        .collect::<Result<Vec<_>>>()?;
+    if inputs.len() != num_inputs {
+        return Err(invalid_arg!(
+            "Einsum equation '{}' has {} input terms, but {} inputs were given",
+            equation,
+            inputs.len(),
+            num_inputs
+        ));
+    }
+    if inputs.len() > 2 {
+        return Err(invalid_arg!(
+            "Einsum equation '{}' has {} inputs, but at most 2 are supported",
+            equation,
+            inputs.len()
+        ));
+    }
+    let output = match output {
+        Some(output) => Some(EinsumTerm::parse(equation, output)?),
+        None => None,
+    };
+    if let Some(output) = &output {
+        for (i, label) in output.labels.iter().enumerate() {
+            if output.labels[..i].contains(label) {
+                return Err(invalid_arg!(
+                    "Einsum equation '{}' repeats '{}' in its output",
+                    equation,
+                    label
+                ));
+            }
+            if !inputs.iter().any(|term| term.labels.contains(label)) {
+                return Err(invalid_arg!(
+                    "Einsum equation '{}' has '{}' in its output, but not in any input",
+                    equation,
+                    label
+                ));
+            }
+        }
+        if output.ellipsis.is_some() && inputs.iter().all(|term| term.ellipsis.is_none()) {
+            return Err(invalid_arg!(
+                "Einsum equation '{}' has an ellipsis in its output, but not in any input",
+                equation
+            ));
+        }
+    }
+    Ok((inputs, output))
+}
+
+/// Computes a sum of products of the elements of `inputs`, described by an
+/// Einstein summation `equation` such as "bij,bjk->bik" (batched matrix
+/// multiplication) or "ij->ji" (transposition).
+///
+/// Each input is labeled with one letter per dimension, and "..." stands
+/// for any number of leading (or broadcast) dimensions.  Labels which appear
+/// in the output (after "->") are kept and all others are summed over; if
+/// the output is omitted, it consists of the labels which appear exactly
+/// once, in alphabetical order.  One or two inputs are supported.
+///
+/// The equation must have one term per input, and each term one label per
+/// dimension of its input, with repeated labels of the same size.
+pub fn einsum(scope: &mut Scope, equation: &str, inputs: &[Output]) -> Result<Output> {
+    let (terms, _) = parse_einsum(equation, inputs.len())?;
+    let mut sizes: HashMap<char, (i64, usize)> = HashMap::new();
+    for (i, (term, input)) in terms.iter().zip(inputs).enumerate() {
+        let crate::Shape(dims) = scope.graph().tensor_shape(input.clone())?;
+        let dims = match dims {
+            Some(dims) => dims,
+            None => continue,
+        };
+        let rank_matches = match term.ellipsis {
+            Some(_) => dims.len() >= term.labels.len(),
+            None => dims.len() == term.labels.len(),
+        };
+        if !rank_matches {
+            return Err(invalid_arg!(
+                "Einsum input {} has shape {:?}, which doesn't match its term '{}' in '{}'",
+                i,
+                dims,
+                term.text,
+                equation
+            ));
+        }
+        for (label, d) in term.dims(dims.len()) {
+            if let Some(size) = dims[d] {
+                match sizes.get(&label) {
+                    Some(&(other, j)) if other != size => {
+                        return Err(invalid_arg!(
+                            "Einsum equation '{}' labels dimensions of size {} (input {}) and {} (input {}) with '{}'",
+                            equation,
+                            other,
+                            j,
+                            size,
+                            i,
+                            label
+                        ))
+                    }
+                    Some(_) => {}
+                    None => {
+                        sizes.insert(label, (size, i));
+                    }
+                }
+            }
+        }
+    }
+    // The op doesn't accept whitespace.
+    let equation: String = equation.chars().filter(|c| !c.is_whitespace()).collect();
+    let op = scope
+        .new_sub_scope("einsum")
+        .new_operation("Einsum", |nd| {
+            nd.add_input_list(inputs);
+            nd.set_attr_string("equation", &equation)?;
+            Ok(())
+        })?;
+    Ok(op.into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    #[test]
+    fn parse() {
+        let (inputs, output) = parse_einsum("b...ij, bjk -> b...ik", 2).unwrap();
+        assert_eq!(inputs[0].labels, vec!['b', 'i', 'j']);
+        assert_eq!(inputs[0].ellipsis, Some(1));
+        assert_eq!(inputs[0].dims(5), vec![('b', 0), ('i', 3), ('j', 4)]);
+        assert_eq!(inputs[1].ellipsis, None);
+        assert_eq!(output.unwrap().labels, vec!['b', 'i', 'k']);
+        assert!(parse_einsum("ij", 1).unwrap().1.is_none());
+
+        assert!(parse_einsum("ij,jk->ik", 1).is_err());
+        assert!(parse_einsum("i,j,k->ijk", 3).is_err());
+        assert!(parse_einsum("ij->ii", 1).is_err());
+        assert!(parse_einsum("ij->k", 1).is_err());
+        assert!(parse_einsum("ij->...i", 1).is_err());
+        assert!(parse_einsum("i1->i", 1).is_err());
+        assert!(parse_einsum("......i->i", 1).is_err());
+    }
+
+    #[test]
+    fn batched_matmul() {
+        let mut scope = Scope::new_root_scope();
+        let a = ops::constant(
+            &mut scope,
+            Tensor::new(&[1, 2, 2])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+                .unwrap(),
+        )
+        .unwrap();
+        let b = ops::constant(
+            &mut scope,
+            Tensor::new(&[1, 2, 1]).with_values(&[1.0f32, 1.0]).unwrap(),
+        )
+        .unwrap();
+        let c = einsum(
+            &mut scope,
+            "bij,bjk->bik",
+            &[a.clone().into(), b.clone().into()],
+        )
+        .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&c.operation, c.index);
+        session.run(&mut args).unwrap();
+        let result = args.fetch::<f32>(token).unwrap();
+        assert_eq!(result.dims(), &[1, 2, 1]);
+        assert_eq!(&result[..], &[3.0, 7.0]);
+
+        // Wrong rank.
+        assert!(einsum(
+            &mut scope,
+            "ij,jk->ik",
+            &[a.clone().into(), b.clone().into()]
+        )
+        .is_err());
+        // 'j' is 2 in a but 1 in b.
+        let error = einsum(&mut scope, "bij,bkj->bik", &[a.into(), b.into()]).unwrap_err();
+        assert!(error.message().contains("'j'"), "{}", error);
+    }
+}
@@ -0,0 +1,294 @@
+use crate::DataType;
+use crate::FetchToken;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::SessionRunArgs;
+use log::Level;
+use tensorflow_macros::define_op;
+
+define_op!(print_op, Print, "PrintV2", args { input }, attrs {
+    output_stream?: String => "output_stream",
+    end?: String => "end",
+});
+
+/// The default number of entries printed from the start and end of each
+/// dimension of a tensor.
+const DEFAULT_SUMMARIZE: u32 = 3;
+
+/// Formats `inputs` into a string scalar using `template`, in which each `{}`
+/// is replaced by the next input.  Only the first and last `summarize`
+/// entries of each dimension are included; the rest are elided with `...`.
+pub fn string_format(
+    scope: &mut Scope,
+    template: &str,
+    inputs: &[Output],
+    summarize: u32,
+) -> Result<Output> {
+    let placeholders = template.matches("{}").count();
+    if placeholders != inputs.len() {
+        return Err(invalid_arg!(
+            "The template {:?} has {} placeholders, but {} inputs were given",
+            template,
+            placeholders,
+            inputs.len()
+        ));
+    }
+    let types: Vec<DataType> = inputs
+        .iter()
+        .map(|output| output.operation.output_type(output.index as usize))
+        .collect();
+    let op = scope.new_operation("StringFormat", |nd| {
+        nd.add_input_list(inputs);
+        nd.set_attr_type_list("T", &types)?;
+        nd.set_attr_string("template", template)?;
+        nd.set_attr_string("placeholder", "{}")?;
+        nd.set_attr_int("summarize", i64::from(summarize))?;
+        Ok(())
+    })?;
+    Ok(op.into())
+}
+
+/// Where `print` writes to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrintStream {
+    /// The process's standard error.
+    Stderr,
+    /// The process's standard output.
+    Stdout,
+    /// TensorFlow's own log at INFO level.
+    LogInfo,
+    /// TensorFlow's own log at WARNING level.
+    LogWarning,
+    /// TensorFlow's own log at ERROR level.
+    LogError,
+    /// The end of the file with the given path.
+    File(String),
+}
+
+impl PrintStream {
+    fn attr_value(&self) -> String {
+        match self {
+            PrintStream::Stderr => "stderr".to_string(),
+            PrintStream::Stdout => "stdout".to_string(),
+            PrintStream::LogInfo => "log(info)".to_string(),
+            PrintStream::LogWarning => "log(warning)".to_string(),
+            PrintStream::LogError => "log(error)".to_string(),
+            PrintStream::File(path) => format!("file://{}", path),
+        }
+    }
+}
+
+/// Returns an op which prints `inputs` formatted with `template` (see
+/// `string_format`) to `stream`, followed by a newline, whenever it runs.
+///
+/// The op must be run as a target or made a control input of an op which
+/// runs.  Output written by TensorFlow bypasses the `log` crate; use
+/// `PrintLog` to route in-graph prints through it instead.
+pub fn print(
+    scope: &mut Scope,
+    template: &str,
+    inputs: &[Output],
+    stream: &PrintStream,
+) -> Result<Operation> {
+    let mut scope = scope.new_sub_scope("print");
+    let message = string_format(&mut scope, template, inputs, DEFAULT_SUMMARIZE)?;
+    Print::new()
+        .output_stream(&stream.attr_value())
+        .build(&mut scope, message)
+}
+
+/// Collects in-graph prints which are fetched when the graph runs and written
+/// to the `log` crate, so they appear in the application's logs rather than
+/// on stderr.
+///
+/// ```ignore
+/// let mut prints = PrintLog::new();
+/// prints.print(&mut scope, Level::Debug, "loss: {}", &[loss.clone()])?;
+/// // ...
+/// let mut args = SessionRunArgs::new();
+/// let fetches = prints.request_fetches(&mut args);
+/// session.run(&mut args)?;
+/// fetches.log(&mut args)?;
+/// ```
+///
+/// Messages are logged with the target `tensorflow::print`.
+#[derive(Debug, Default)]
+pub struct PrintLog {
+    prints: Vec<(Output, Level)>,
+}
+
+impl PrintLog {
+    /// Creates an empty collection.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a print of `inputs` formatted with `template` (see
+    /// `string_format`), logged at `level`.
+    pub fn print(
+        &mut self,
+        scope: &mut Scope,
+        level: Level,
+        template: &str,
+        inputs: &[Output],
+    ) -> Result<()> {
+        let message = string_format(
+            &mut scope.new_sub_scope("print"),
+            template,
+            inputs,
+            DEFAULT_SUMMARIZE,
+        )?;
+        self.prints.push((message, level));
+        Ok(())
+    }
+
+    /// Returns the number of prints.
+    pub fn len(&self) -> usize {
+        self.prints.len()
+    }
+
+    /// Returns true if there are no prints.
+    pub fn is_empty(&self) -> bool {
+        self.prints.is_empty()
+    }
+
+    /// Requests fetches of all prints enabled at their level.  Prints whose
+    /// level is disabled aren't computed at all.
+    pub fn request_fetches(&self, args: &mut SessionRunArgs<'_>) -> PrintLogFetches {
+        let tokens = self
+            .prints
+            .iter()
+            .filter(|(_, level)| log::log_enabled!(target: "tensorflow::print", *level))
+            .map(|(message, level)| {
+                (
+                    args.request_fetch(&message.operation, message.index),
+                    *level,
+                )
+            })
+            .collect();
+        PrintLogFetches { tokens }
+    }
+}
+
+/// The prints requested by `PrintLog::request_fetches`.
+#[derive(Debug)]
+pub struct PrintLogFetches {
+    tokens: Vec<(FetchToken, Level)>,
+}
+
+impl PrintLogFetches {
+    /// Logs the fetched prints, in the order they were added to the
+    /// `PrintLog`.  Must be called after the session has run `args`.
+    pub fn log(&self, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        for (token, level) in &self.tokens {
+            let message = args.fetch::<String>(*token)?;
+            log::log!(target: "tensorflow::print", *level, "{}", message[0]);
+        }
+        Ok(())
+    }
+}
+
+/// Convenience for `print` to `PrintStream::Stderr` of a single tensor,
+/// prefixed with `label`.
+pub fn print_tensor(scope: &mut Scope, label: &str, tensor: Output) -> Result<Operation> {
+    let template = format!("{}{{}}", label.replace("{}", "{ }"));
+    print(scope, &template, &[tensor], &PrintStream::Stderr)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::Session;
+    use crate::SessionOptions;
+    use log::Log;
+    use log::Metadata;
+    use log::Record;
+    use std::sync::Mutex;
+
+    /// Records the messages logged by `PrintLogFetches::log`.
+    struct CapturingLogger {
+        messages: Mutex<Vec<(Level, String)>>,
+    }
+
+    impl Log for CapturingLogger {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.target() == "tensorflow::print"
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            if self.enabled(record.metadata()) {
+                self.messages
+                    .lock()
+                    .unwrap()
+                    .push((record.level(), record.args().to_string()));
+            }
+        }
+
+        fn flush(&self) {}
+    }
+
+    static LOGGER: CapturingLogger = CapturingLogger {
+        messages: Mutex::new(Vec::new()),
+    };
+
+    #[test]
+    fn format_and_print() {
+        let mut scope = Scope::new_root_scope();
+        let x: Output = ops::constant(&mut scope, &[1i32, 2, 3, 4, 5][..])
+            .unwrap()
+            .into();
+        let y: Output = ops::constant(&mut scope, 2.5f32).unwrap().into();
+        let formatted = string_format(&mut scope, "x={} y={}", &[x.clone(), y], 2).unwrap();
+        let printed = print_tensor(&mut scope, "x: ", x.clone()).unwrap();
+        assert!(string_format(&mut scope, "{}", &[], 3).is_err());
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&printed);
+        let token = args.request_fetch(&formatted.operation, formatted.index);
+        session.run(&mut args).unwrap();
+        assert_eq!(
+            args.fetch::<String>(token).unwrap()[0],
+            "x=[1 2 ... 4 5] y=2.5"
+        );
+    }
+
+    #[test]
+    fn print_log() {
+        let mut scope = Scope::new_root_scope();
+        let x: Output = ops::constant(&mut scope, 7i64).unwrap().into();
+        let mut prints = PrintLog::new();
+        assert!(prints.is_empty());
+        prints
+            .print(&mut scope, Level::Error, "x is {}", &[x])
+            .unwrap();
+        assert_eq!(prints.len(), 1);
+
+        log::set_logger(&LOGGER).unwrap();
+        log::set_max_level(log::LevelFilter::Trace);
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let fetches = prints.request_fetches(&mut args);
+        session.run(&mut args).unwrap();
+        fetches.log(&mut args).unwrap();
+        assert_eq!(
+            *LOGGER.messages.lock().unwrap(),
+            vec![(Level::Error, "x is 7".to_string())]
+        );
+    }
+
+    #[test]
+    fn stream_attr_values() {
+        assert_eq!(PrintStream::Stderr.attr_value(), "stderr");
+        assert_eq!(PrintStream::LogWarning.attr_value(), "log(warning)");
+        assert_eq!(
+            PrintStream::File("/tmp/out".to_string()).attr_value(),
+            "file:///tmp/out"
+        );
+    }
+}
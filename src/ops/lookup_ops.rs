@@ -0,0 +1,471 @@
+use super::known_dims;
+use crate::ops;
+use crate::DataType;
+use crate::Graph;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Session;
+use crate::SessionRunArgs;
+use tensorflow_macros::define_op;
+
+define_op!(hash_table_op, HashTable, "HashTableV2", attrs {
+    key_dtype: DataType => "key_dtype",
+    value_dtype: DataType => "value_dtype",
+    container?: String => "container",
+    shared_name?: String => "shared_name",
+    use_node_name_sharing?: bool => "use_node_name_sharing",
+});
+
+define_op!(
+    initialize_table_op,
+    InitializeTable,
+    "InitializeTableV2",
+    args {
+        table_handle,
+        keys,
+        values
+    }
+);
+
+define_op!(
+    initialize_table_from_text_file_op,
+    InitializeTableFromTextFile,
+    "InitializeTableFromTextFileV2",
+    args { table_handle, filename },
+    attrs {
+        key_index: i64 => "key_index",
+        value_index: i64 => "value_index",
+        vocab_size?: i64 => "vocab_size",
+        delimiter?: String => "delimiter",
+    }
+);
+
+define_op!(
+    lookup_table_find_op,
+    LookupTableFind,
+    "LookupTableFindV2",
+    args {
+        table_handle,
+        keys,
+        default_value
+    }
+);
+
+define_op!(
+    lookup_table_size_op,
+    LookupTableSize,
+    "LookupTableSizeV2",
+    args { table_handle }
+);
+
+/// The op types which initialize lookup tables, for `initialize_tables`.
+const TABLE_INITIALIZER_TYPES: &[&str] = &[
+    "InitializeTable",
+    "InitializeTableV2",
+    "InitializeTableFromTextFile",
+    "InitializeTableFromTextFileV2",
+    "LookupTableImport",
+    "LookupTableImportV2",
+];
+
+/// Which part of each line of a text file a table takes its keys or values
+/// from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum TextFileColumn {
+    /// The (zero-based) line number, as an int64.
+    LineNumber,
+    /// The whole line, as a string.
+    WholeLine,
+    /// The given (zero-based) column, after splitting the line at the
+    /// delimiter.
+    Column(u32),
+}
+
+impl TextFileColumn {
+    fn index(self) -> i64 {
+        match self {
+            TextFileColumn::LineNumber => -2,
+            TextFileColumn::WholeLine => -1,
+            TextFileColumn::Column(column) => i64::from(column),
+        }
+    }
+}
+
+/// Describes how to initialize a table from a text file, for
+/// `StaticHashTable::from_text_file`.
+#[derive(Debug, Clone)]
+pub struct TextFileInitializer {
+    filename: String,
+    key: TextFileColumn,
+    value: TextFileColumn,
+    delimiter: String,
+    vocab_size: Option<u64>,
+}
+
+impl TextFileInitializer {
+    /// Maps each line of `filename` to its line number, which is the usual
+    /// format of vocabulary files.
+    pub fn new(filename: &str) -> Self {
+        Self {
+            filename: filename.to_string(),
+            key: TextFileColumn::WholeLine,
+            value: TextFileColumn::LineNumber,
+            delimiter: "\t".to_string(),
+            vocab_size: None,
+        }
+    }
+
+    /// Sets where the keys come from.
+    pub fn with_key(self, key: TextFileColumn) -> Self {
+        Self { key, ..self }
+    }
+
+    /// Sets where the values come from.
+    pub fn with_value(self, value: TextFileColumn) -> Self {
+        Self { value, ..self }
+    }
+
+    /// Sets the delimiter between columns.  Defaults to a tab.
+    pub fn with_delimiter(self, delimiter: &str) -> Self {
+        Self {
+            delimiter: delimiter.to_string(),
+            ..self
+        }
+    }
+
+    /// Only reads the first `vocab_size` lines.  Initialization fails if the
+    /// file is shorter.
+    pub fn with_vocab_size(self, vocab_size: u64) -> Self {
+        Self {
+            vocab_size: Some(vocab_size),
+            ..self
+        }
+    }
+}
+
+/// An immutable hash table in the graph, which maps keys to values once it
+/// has been initialized.
+///
+/// The table is empty until its initializer runs, either through
+/// `initialize`, as part of `tables_initializer`, or (for imported graphs)
+/// through `initialize_tables`.
+#[derive(Debug, Clone)]
+pub struct StaticHashTable {
+    handle: Output,
+    initializer: Operation,
+    key_type: DataType,
+    value_type: DataType,
+    default_value: Output,
+}
+
+impl StaticHashTable {
+    /// Creates a table mapping each element of the vector `keys` to the
+    /// corresponding element of `values`.  Lookups of missing keys return the
+    /// scalar `default_value`.
+    pub fn new(
+        scope: &mut Scope,
+        keys: Output,
+        values: Output,
+        default_value: Output,
+    ) -> Result<Self> {
+        let scope = scope.new_sub_scope("hash_table");
+        for (output, name) in &[(&keys, "keys"), (&values, "values")] {
+            if let Some(dims) = known_dims(&scope, output)? {
+                if dims.len() != 1 {
+                    return Err(invalid_arg!(
+                        "The {} of a hash table must be a vector, but have shape {:?}",
+                        name,
+                        dims
+                    ));
+                }
+            }
+        }
+        let (key_type, value_type) = (keys.data_type(), values.data_type());
+        let handle: Output = HashTable::new()
+            .key_dtype(key_type)
+            .value_dtype(value_type)
+            .build(&mut scope.with_op_name("table"))?
+            .into();
+        let initializer = InitializeTable::new().build(
+            &mut scope.with_op_name("table_init"),
+            handle.clone(),
+            keys,
+            values,
+        )?;
+        Self::with_initializer(
+            &scope,
+            handle,
+            initializer,
+            key_type,
+            value_type,
+            default_value,
+        )
+    }
+
+    /// Creates a table initialized from a text file.  The keys and values
+    /// have type `key_type` and `value_type`, which must be int64 for line
+    /// numbers and string for whole lines.  Lookups of missing keys return the
+    /// scalar `default_value`.
+    pub fn from_text_file(
+        scope: &mut Scope,
+        file: &TextFileInitializer,
+        key_type: DataType,
+        value_type: DataType,
+        default_value: Output,
+    ) -> Result<Self> {
+        let mut scope = scope.new_sub_scope("hash_table");
+        for &(column, data_type, name) in &[
+            (file.key, key_type, "key"),
+            (file.value, value_type, "value"),
+        ] {
+            let expected = match column {
+                TextFileColumn::LineNumber => Some(DataType::Int64),
+                TextFileColumn::WholeLine => Some(DataType::String),
+                TextFileColumn::Column(_) => None,
+            };
+            if let Some(expected) = expected {
+                if data_type != expected {
+                    return Err(invalid_arg!(
+                        "A table {} taken from {:?} must be {}, but was given as {}",
+                        name,
+                        column,
+                        expected,
+                        data_type
+                    ));
+                }
+            }
+        }
+        let handle: Output = HashTable::new()
+            .key_dtype(key_type)
+            .value_dtype(value_type)
+            .build(&mut scope.with_op_name("table"))?
+            .into();
+        let filename = ops::constant(&mut scope, file.filename.clone())?;
+        let mut init = InitializeTableFromTextFile::new()
+            .key_index(file.key.index())
+            .value_index(file.value.index())
+            .delimiter(&file.delimiter);
+        if let Some(vocab_size) = file.vocab_size {
+            init = init.vocab_size(vocab_size as i64);
+        }
+        let initializer = init.build(
+            &mut scope.with_op_name("table_init"),
+            handle.clone(),
+            filename,
+        )?;
+        Self::with_initializer(
+            &scope,
+            handle,
+            initializer,
+            key_type,
+            value_type,
+            default_value,
+        )
+    }
+
+    fn with_initializer(
+        scope: &Scope,
+        handle: Output,
+        initializer: Operation,
+        key_type: DataType,
+        value_type: DataType,
+        default_value: Output,
+    ) -> Result<Self> {
+        if default_value.data_type() != value_type {
+            return Err(invalid_arg!(
+                "The default value of a table with {} values has type {}",
+                value_type,
+                default_value.data_type()
+            ));
+        }
+        if let Some(dims) = known_dims(scope, &default_value)? {
+            if !dims.is_empty() {
+                return Err(invalid_arg!(
+                    "The default value of a table must be a scalar, but has shape {:?}",
+                    dims
+                ));
+            }
+        }
+        Ok(Self {
+            handle,
+            initializer,
+            key_type,
+            value_type,
+            default_value,
+        })
+    }
+
+    /// Looks up each element of `keys`, returning a tensor of the same shape
+    /// with the corresponding values.
+    pub fn lookup(&self, scope: &mut Scope, keys: Output) -> Result<Output> {
+        if keys.data_type() != self.key_type {
+            return Err(invalid_arg!(
+                "Cannot look up keys of type {} in a table with {} keys",
+                keys.data_type(),
+                self.key_type
+            ));
+        }
+        Ok(LookupTableFind::new()
+            .build(scope, self.handle.clone(), keys, self.default_value.clone())?
+            .into())
+    }
+
+    /// Returns the number of entries in the table, as an int64 scalar.
+    pub fn size(&self, scope: &mut Scope) -> Result<Output> {
+        Ok(LookupTableSize::new()
+            .build(scope, self.handle.clone())?
+            .into())
+    }
+
+    /// Returns the resource handle of the table.
+    pub fn handle(&self) -> &Output {
+        &self.handle
+    }
+
+    /// Returns the operation which fills the table.
+    pub fn initializer(&self) -> &Operation {
+        &self.initializer
+    }
+
+    /// Returns the type of the keys.
+    pub fn key_type(&self) -> DataType {
+        self.key_type
+    }
+
+    /// Returns the type of the values.
+    pub fn value_type(&self) -> DataType {
+        self.value_type
+    }
+
+    /// Runs the initializer in `session`.  A table can only be initialized
+    /// once per session.
+    pub fn initialize(&self, session: &Session) -> Result<()> {
+        let mut args = SessionRunArgs::new();
+        args.add_target(&self.initializer);
+        session.run(&mut args)
+    }
+}
+
+/// Creates a table mapping each line of the vocabulary file `filename` to its
+/// (zero-based) line number.  Lookups of words which aren't in the file
+/// return `default_value`, typically -1 or the index of an unknown-word
+/// bucket.
+pub fn index_table_from_file(
+    scope: &mut Scope,
+    filename: &str,
+    default_value: i64,
+) -> Result<StaticHashTable> {
+    let default_value = ops::constant(scope, default_value)?;
+    StaticHashTable::from_text_file(
+        scope,
+        &TextFileInitializer::new(filename),
+        DataType::String,
+        DataType::Int64,
+        default_value.into(),
+    )
+}
+
+/// Returns an operation which initializes all of `tables` when run.
+pub fn tables_initializer(scope: &mut Scope, tables: &[&StaticHashTable]) -> Result<Operation> {
+    let mut no_op = ops::NoOp::new();
+    for table in tables {
+        no_op = no_op.add_control_input(table.initializer.clone());
+    }
+    no_op.build(&mut scope.with_op_name("init_all_tables"))
+}
+
+/// Runs every table initializer in `graph`, which is useful for graphs
+/// imported from a `GraphDef` whose tables would otherwise be empty.  Graphs
+/// loaded through `SavedModelBundle` normally have their initializers run by
+/// the loader.
+pub fn initialize_tables(session: &Session, graph: &Graph) -> Result<()> {
+    let mut args = SessionRunArgs::new();
+    let mut any = false;
+    for operation in graph.operation_iter() {
+        let op_type = operation.op_type()?;
+        if TABLE_INITIALIZER_TYPES.contains(&op_type.as_str()) {
+            args.add_target(&operation);
+            any = true;
+        }
+    }
+    if any {
+        session.run(&mut args)?;
+    }
+    Ok(())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SessionOptions;
+    use std::env;
+    use std::fs;
+
+    fn fetch_i64(session: &Session, output: &Output) -> Vec<i64> {
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&output.operation, output.index);
+        session.run(&mut args).unwrap();
+        args.fetch::<i64>(token).unwrap().to_vec()
+    }
+
+    #[test]
+    fn static_table() {
+        let mut scope = Scope::new_root_scope();
+        let keys = ops::constant(&mut scope, &["a".to_string(), "b".to_string()][..]).unwrap();
+        let values = ops::constant(&mut scope, &[1i64, 2][..]).unwrap();
+        let default_value = ops::constant(&mut scope, -1i64).unwrap();
+        let table =
+            StaticHashTable::new(&mut scope, keys.into(), values.into(), default_value.into())
+                .unwrap();
+        let query = ops::constant(
+            &mut scope,
+            &["b".to_string(), "c".to_string(), "a".to_string()][..],
+        )
+        .unwrap();
+        let found = table.lookup(&mut scope, query.into()).unwrap();
+        let size = table.size(&mut scope).unwrap();
+        let init = tables_initializer(&mut scope, &[&table]).unwrap();
+        let ints = ops::constant(&mut scope, &[1i64][..]).unwrap();
+        assert!(table.lookup(&mut scope, ints.into()).is_err());
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&init);
+        session.run(&mut args).unwrap();
+        assert_eq!(fetch_i64(&session, &found), vec![2, -1, 1]);
+        assert_eq!(fetch_i64(&session, &size), vec![2]);
+    }
+
+    #[test]
+    fn vocab_file() {
+        let path = env::temp_dir().join("tensorflow_lookup_ops_test_vocab.txt");
+        fs::write(&path, "the\nquick\nfox\n").unwrap();
+        let mut scope = Scope::new_root_scope();
+        let table = index_table_from_file(&mut scope, path.to_str().unwrap(), -1).unwrap();
+        let words = ops::constant(
+            &mut scope,
+            &["fox".to_string(), "dog".to_string(), "the".to_string()][..],
+        )
+        .unwrap();
+        let ids = table.lookup(&mut scope, words.into()).unwrap();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        initialize_tables(&session, &scope.graph()).unwrap();
+        assert_eq!(fetch_i64(&session, &ids), vec![2, -1, 0]);
+        fs::remove_file(&path).unwrap();
+
+        let default_value = ops::constant(&mut scope, 0i32).unwrap();
+        assert!(StaticHashTable::from_text_file(
+            &mut scope,
+            &TextFileInitializer::new("vocab.txt"),
+            DataType::String,
+            DataType::Int32,
+            default_value.into(),
+        )
+        .is_err());
+    }
+}
@@ -1,5 +1,9 @@
+use crate::ops::IndexType;
+use crate::scope::is_floating;
 use crate::AnyTensor;
+use crate::DataType;
 use crate::Operation;
+use crate::Output;
 use crate::Result;
 use crate::Scope;
 use crate::Tensor;
@@ -8,6 +12,28 @@ use tensorflow_macros::define_op;
 
 define_op!(add, Add, "Add", args { a, b });
 
+define_op!(cast, Cast, "Cast", args { x }, attrs {
+    dst_type: DataType => "DstT",
+});
+
+/// Casts the floating point tensor `x` to the compute data type of the
+/// scope's `DTypePolicy`.  Returns `x` unchanged if the scope has no policy,
+/// if `x` isn't floating point, or if it already has the compute data type.
+pub fn cast_to_compute_dtype(scope: &mut Scope, x: Output) -> Result<Output> {
+    let policy = match scope.dtype_policy() {
+        Some(policy) => policy,
+        None => return Ok(x),
+    };
+    let data_type = x.operation.output_type(x.index as usize);
+    if !is_floating(data_type) || data_type == policy.compute_dtype() {
+        return Ok(x);
+    }
+    Ok(Cast::new()
+        .dst_type(policy.compute_dtype())
+        .build(scope, x)?
+        .into())
+}
+
 /// Creates a constant.
 ///
 /// The value can be anything convertible to a tensor, so possibilities include:
@@ -29,25 +55,33 @@ define_op!(add, Add, "Add", args { a, b });
 /// fixed-size array.  This is necessary because `Into<Tensor>` is implemented
 /// for slices but not arrays, and the compiler doesn't automatically fall back
 /// to treating the array reference as a slice.
+///
+/// Scalar constants may be shared; see `Scope::cache_scalar_constants`.
 pub fn constant<T: TensorType, TT: Into<Tensor<T>>>(
     scope: &mut Scope,
     value: TT,
 ) -> Result<Operation> {
-    let name = scope.get_unique_name_for_op("Const");
-    let mut graph = scope.graph_mut();
-    let mut c = graph.new_operation("Const", &name)?;
-    c.set_attr_tensor("value", value.into())?;
-    c.set_attr_type("dtype", T::data_type())?;
-    c.finish()
+    let value = value.into();
+    if value.dims().is_empty() {
+        return scope.cached_constant(value, new_constant);
+    }
+    new_constant(scope, value)
+}
+
+fn new_constant<T: TensorType>(scope: &mut Scope, value: Tensor<T>) -> Result<Operation> {
+    scope.new_operation("Const", |nd| {
+        nd.set_attr_tensor("value", value)?;
+        nd.set_attr_type("dtype", T::data_type())?;
+        Ok(())
+    })
 }
 
 pub(crate) fn any_constant(scope: &mut Scope, value: &AnyTensor) -> Result<Operation> {
-    let name = scope.get_unique_name_for_op("Const");
-    let mut graph = scope.graph_mut();
-    let mut c = graph.new_operation("Const", &name)?;
-    c.set_attr_any_tensor("value", value)?;
-    c.set_attr_type("dtype", value.data_type())?;
-    c.finish()
+    scope.new_operation("Const", |nd| {
+        nd.set_attr_any_tensor("value", value)?;
+        nd.set_attr_type("dtype", value.data_type())?;
+        Ok(())
+    })
 }
 
 define_op!(mat_mul, MatMul, "MatMul", args {a, b}, attrs {
@@ -60,3 +94,149 @@ define_op!(multiply, Multiply, "Mul", args { a, b });
 define_op!(subtract, Subtract, "Sub", args { a, b });
 
 define_op!(tanh, Tanh, "Tanh", args { x });
+
+define_op!(sigmoid, Sigmoid, "Sigmoid", args { x });
+
+define_op!(floor, Floor, "Floor", args { x });
+
+define_op!(ceil, Ceil, "Ceil", args { x });
+
+define_op!(cos, Cos, "Cos", args { x });
+
+define_op!(negate, Negate, "Neg", args { x });
+
+define_op!(invert, Invert, "Invert", args { x });
+
+define_op!(sqrt, Sqrt, "Sqrt", args { x });
+
+define_op!(rsqrt, Rsqrt, "Rsqrt", args { x });
+
+define_op!(square, Square, "Square", args { x });
+
+define_op!(pow, Pow, "Pow", args { x, y });
+
+define_op!(greater, Greater, "Greater", args { x, y });
+
+define_op!(greater_equal, GreaterEqual, "GreaterEqual", args { x, y });
+
+define_op!(less, Less, "Less", args { x, y });
+
+define_op!(maximum, Maximum, "Maximum", args { x, y });
+
+define_op!(minimum, Minimum, "Minimum", args { x, y });
+
+define_op!(
+    clip_by_value,
+    ClipByValue,
+    "ClipByValue",
+    args {
+        t,
+        clip_value_min,
+        clip_value_max
+    }
+);
+
+define_op!(select, Select, "Select", args { condition, t, e });
+
+define_op!(divide, Divide, "RealDiv", args { x, y });
+
+define_op!(floor_div, FloorDiv, "FloorDiv", args { x, y });
+
+define_op!(floor_mod, FloorMod, "FloorMod", args { x, y });
+
+define_op!(div_no_nan, DivNoNan, "DivNoNan", args { x, y });
+
+define_op!(sum, Sum, "Sum", args { input, axis }, attrs {
+    keep_dims?: bool => "keep_dims",
+});
+
+define_op!(mean, Mean, "Mean", args { input, axis }, attrs {
+    keep_dims?: bool => "keep_dims",
+});
+
+define_op!(all, All, "All", args { input, axis }, attrs {
+    keep_dims?: bool => "keep_dims",
+});
+
+define_op!(min, Min, "Min", args { input, axis }, attrs {
+    keep_dims?: bool => "keep_dims",
+});
+
+define_op!(max, Max, "Max", args { input, axis }, attrs {
+    keep_dims?: bool => "keep_dims",
+});
+
+define_op!(equal, Equal, "Equal", args { x, y });
+
+define_op!(is_finite, IsFinite, "IsFinite", args { x });
+
+define_op!(logical_and, LogicalAnd, "LogicalAnd", args { x, y });
+
+define_op!(logical_or, LogicalOr, "LogicalOr", args { x, y });
+
+define_op!(logical_not, LogicalNot, "LogicalNot", args { x });
+
+define_op!(arg_max_op, ArgMax, "ArgMax", args { input, dimension }, attrs {
+    output_type?: DataType => "output_type",
+});
+
+define_op!(arg_min_op, ArgMin, "ArgMin", args { input, dimension }, attrs {
+    output_type?: DataType => "output_type",
+});
+
+/// Returns the indices of the largest values of `input` along `axis`, as a
+/// tensor of `I`, e.g. to turn logits into predicted classes.
+///
+/// ```ignore
+/// let classes = argmax::<i64>(&mut scope, logits, -1)?;
+/// ```
+pub fn argmax<I: IndexType>(scope: &mut Scope, input: Output, axis: i32) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("argmax");
+    let axis = constant(&mut scope, axis)?;
+    Ok(ArgMax::new()
+        .output_type(I::data_type())
+        .build(&mut scope, input, axis)?
+        .into())
+}
+
+/// Returns the indices of the smallest values of `input` along `axis`, as a
+/// tensor of `I`.
+pub fn argmin<I: IndexType>(scope: &mut Scope, input: Output, axis: i32) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("argmin");
+    let axis = constant(&mut scope, axis)?;
+    Ok(ArgMin::new()
+        .output_type(I::data_type())
+        .build(&mut scope, input, axis)?
+        .into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    #[test]
+    fn arg_max_and_min() {
+        let mut scope = Scope::new_root_scope();
+        let x = constant(
+            &mut scope,
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, 5.0, 3.0, 4.0, 2.0, 0.0])
+                .unwrap(),
+        )
+        .unwrap();
+        let max = argmax::<i64>(&mut scope, x.clone().into(), -1).unwrap();
+        let min = argmin::<i32>(&mut scope, x.into(), 0).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let max_token = args.request_fetch(&max.operation, max.index);
+        let min_token = args.request_fetch(&min.operation, min.index);
+        session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<i64>(max_token).unwrap()[..], &[1, 0]);
+        assert_eq!(&args.fetch::<i32>(min_token).unwrap()[..], &[0, 1, 1]);
+    }
+}
@@ -0,0 +1,134 @@
+use super::collective_ops::replica_devices;
+use crate::ops;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Shape;
+use tensorflow_macros::define_op;
+
+define_op!(nccl_all_reduce_op, NcclAllReduce, "NcclAllReduce", args { input }, attrs {
+    reduction: String => "reduction",
+    num_devices: i64 => "num_devices",
+    shared_name: String => "shared_name",
+});
+
+define_op!(nccl_broadcast_op, NcclBroadcast, "NcclBroadcast", args { input }, attrs {
+    shape: Shape => "shape",
+});
+
+/// Reduces `inputs` across GPUs with NCCL, returning one copy of the result
+/// per input, in the same order.
+///
+/// Each input must be placed on a different GPU.  `reduction` is one of
+/// "sum", "prod", "min" or "max".  All of the created ops share a
+/// `shared_name` and know the number of participating devices, so they must
+/// all be run in the same step.
+pub fn nccl_all_reduce(
+    scope: &mut Scope,
+    inputs: &[Output],
+    reduction: &str,
+) -> Result<Vec<Output>> {
+    let devices = replica_devices(inputs)?;
+    let scope = scope.new_sub_scope("nccl_all_reduce");
+    // Op names are unique within the graph, so this is too.
+    let shared_name = scope.get_unique_name_for_op("shared");
+    let mut outputs = Vec::with_capacity(inputs.len());
+    for (input, device) in inputs.iter().zip(&devices) {
+        let op = NcclAllReduce::new()
+            .reduction(reduction)
+            .num_devices(inputs.len() as i64)
+            .shared_name(&shared_name)
+            .build(&mut scope.with_device(device), input.clone())?;
+        outputs.push(op.into());
+    }
+    Ok(outputs)
+}
+
+/// Broadcasts `source` from its GPU to each of `devices` with NCCL, returning
+/// one output per device in the same order.
+///
+/// The shape of `source` must be fully known when the graph is built.
+pub fn nccl_broadcast<S: AsRef<str>>(
+    scope: &mut Scope,
+    source: Output,
+    devices: &[S],
+) -> Result<Vec<Output>> {
+    let source_device = replica_devices(std::slice::from_ref(&source))?.remove(0);
+    let shape = scope.graph().tensor_shape(source.clone())?;
+    match &shape.0 {
+        Some(dims) if dims.iter().all(Option::is_some) => {}
+        _ => {
+            return Err(invalid_arg!(
+                "The shape of the broadcast source must be fully known, but was {:?}",
+                shape
+            ))
+        }
+    }
+    let scope = scope.new_sub_scope("nccl_broadcast");
+    let broadcast = NcclBroadcast::new()
+        .shape(shape)
+        .build(&mut scope.with_device(&source_device), source)?;
+    // The runtime replaces NcclBroadcast with a send and one receive per
+    // consumer on another device, so each destination needs its own consumer.
+    let mut outputs = Vec::with_capacity(devices.len());
+    for device in devices {
+        let copy = ops::identity(&mut scope.with_device(device.as_ref()), broadcast.clone())?;
+        outputs.push(copy.into());
+    }
+    Ok(outputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn all_reduce_shares_name() {
+        let mut scope = Scope::new_root_scope();
+        let a = ops::constant(&mut scope.with_device("/device:GPU:0"), 1.0f32).unwrap();
+        let b = ops::constant(&mut scope.with_device("/device:GPU:1"), 2.0f32).unwrap();
+        let outputs = nccl_all_reduce(&mut scope, &[a.into(), b.into()], "sum").unwrap();
+        assert_eq!(outputs.len(), 2);
+        let (x, y) = (&outputs[0].operation, &outputs[1].operation);
+        assert_eq!(x.device().unwrap(), "/device:GPU:0");
+        assert_eq!(y.device().unwrap(), "/device:GPU:1");
+        assert_eq!(x.get_attr_int("num_devices").unwrap(), 2);
+        assert_eq!(x.get_attr_string("reduction").unwrap(), "sum");
+        assert_eq!(
+            x.get_attr_string("shared_name").unwrap(),
+            y.get_attr_string("shared_name").unwrap()
+        );
+
+        // A second reduction gets a different shared name.
+        let c = ops::constant(&mut scope.with_device("/device:GPU:0"), 1.0f32).unwrap();
+        let d = ops::constant(&mut scope.with_device("/device:GPU:1"), 2.0f32).unwrap();
+        let more = nccl_all_reduce(&mut scope, &[c.into(), d.into()], "sum").unwrap();
+        assert_ne!(
+            x.get_attr_string("shared_name").unwrap(),
+            more[0].operation.get_attr_string("shared_name").unwrap()
+        );
+    }
+
+    #[test]
+    fn all_reduce_requires_distinct_devices() {
+        let mut scope = Scope::new_root_scope();
+        let mut gpu = scope.with_device("/device:GPU:0");
+        let a = ops::constant(&mut gpu, 1.0f32).unwrap();
+        let b = ops::constant(&mut gpu, 2.0f32).unwrap();
+        assert!(nccl_all_reduce(&mut scope, &[a.into(), b.into()], "sum").is_err());
+    }
+
+    #[test]
+    fn broadcast() {
+        let mut scope = Scope::new_root_scope();
+        let a = ops::constant(&mut scope.with_device("/device:GPU:0"), &[1.0f32, 2.0][..]).unwrap();
+        let copies = nccl_broadcast(&mut scope, a.into(), &["/device:GPU:1"]).unwrap();
+        assert_eq!(copies[0].operation.device().unwrap(), "/device:GPU:1");
+        let (broadcast, _) = copies[0].operation.input(0);
+        assert_eq!(broadcast.op_type().unwrap(), "NcclBroadcast");
+        assert_eq!(
+            broadcast.get_attr_shape("shape").unwrap(),
+            Shape::from(Some(vec![Some(2)]))
+        );
+    }
+}
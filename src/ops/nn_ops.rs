@@ -0,0 +1,117 @@
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use tensorflow_macros::define_op;
+
+define_op!(softmax, Softmax, "Softmax", args { logits });
+
+define_op!(
+    softmax_cross_entropy_with_logits,
+    SoftmaxCrossEntropyWithLogits,
+    "SoftmaxCrossEntropyWithLogits",
+    args { features, labels }
+);
+
+define_op!(
+    sparse_softmax_cross_entropy_with_logits,
+    SparseSoftmaxCrossEntropyWithLogits,
+    "SparseSoftmaxCrossEntropyWithLogits",
+    args { features, labels }
+);
+
+define_op!(relu, Relu, "Relu", args { features });
+
+define_op!(bias_add, BiasAdd, "BiasAdd", args { value, bias });
+
+fn check_conv_args(op: &str, strides: [i64; 2], padding: &str) -> Result<()> {
+    if padding != "SAME" && padding != "VALID" {
+        return Err(invalid_arg!(
+            "The padding of {} must be \"SAME\" or \"VALID\", but is {:?}",
+            op,
+            padding
+        ));
+    }
+    if strides.iter().any(|&stride| stride <= 0) {
+        return Err(invalid_arg!(
+            "The strides of {} must be positive, but are {:?}",
+            op,
+            strides
+        ));
+    }
+    Ok(())
+}
+
+/// Convolves the NHWC images in `input` with the `filter` of shape
+/// `[height, width, in_channels, out_channels]`, moving it by `strides`
+/// pixels along the height and width.  `padding` is either "SAME", which
+/// pads the images so that the output has `ceil(size / stride)` pixels, or
+/// "VALID", which doesn't pad.
+pub fn conv2d(
+    scope: &mut Scope,
+    input: Output,
+    filter: Output,
+    strides: [i64; 2],
+    padding: &str,
+) -> Result<Output> {
+    check_conv_args("conv2d", strides, padding)?;
+    let op = scope.new_operation("Conv2D", |nd| {
+        nd.add_input(input);
+        nd.add_input(filter);
+        nd.set_attr_int_list("strides", &[1, strides[0], strides[1], 1])?;
+        nd.set_attr_string("padding", padding)?;
+        Ok(())
+    })?;
+    Ok(op.into())
+}
+
+/// Computes the transpose of `conv2d`, i.e. its gradient with respect to its
+/// input, mapping the NHWC images in `input` to images of shape
+/// `output_shape`, an int32 vector of length 4.  `filter` has shape
+/// `[height, width, out_channels, in_channels]`, and `strides` and `padding`
+/// are those of the convolution being transposed, so a stride greater than
+/// one upsamples the images.  TensorFlow has no gradient for this op, so
+/// `layers::Conv2DTranspose` builds the same computation from ops which
+/// have one.
+pub fn conv2d_transpose(
+    scope: &mut Scope,
+    input: Output,
+    filter: Output,
+    output_shape: Output,
+    strides: [i64; 2],
+    padding: &str,
+) -> Result<Output> {
+    check_conv_args("conv2d_transpose", strides, padding)?;
+    let op = scope.new_operation("Conv2DBackpropInput", |nd| {
+        nd.add_input(output_shape);
+        nd.add_input(filter);
+        nd.add_input(input);
+        nd.set_attr_int_list("strides", &[1, strides[0], strides[1], 1])?;
+        nd.set_attr_string("padding", padding)?;
+        Ok(())
+    })?;
+    Ok(op.into())
+}
+
+/// Convolves each channel of the NHWC images in `input` separately with the
+/// `filter` of shape `[height, width, in_channels, channel_multiplier]`,
+/// producing `in_channels * channel_multiplier` output channels.  `strides`
+/// and `padding` are as for `conv2d`.  TensorFlow has no gradient for this
+/// op, so `layers::DepthwiseConv2D` builds the same computation from ops
+/// which have one.
+pub fn depthwise_conv2d(
+    scope: &mut Scope,
+    input: Output,
+    filter: Output,
+    strides: [i64; 2],
+    padding: &str,
+) -> Result<Output> {
+    check_conv_args("depthwise_conv2d", strides, padding)?;
+    let op = scope.new_operation("DepthwiseConv2dNative", |nd| {
+        nd.add_input(input);
+        nd.add_input(filter);
+        nd.set_attr_int_list("strides", &[1, strides[0], strides[1], 1])?;
+        nd.set_attr_string("padding", padding)?;
+        Ok(())
+    })?;
+    Ok(op.into())
+}
@@ -1,4 +1,11 @@
+use super::known_dims;
+use crate::ops;
+use crate::ops::IndexType;
 use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
 use tensorflow_macros::define_op;
 
 define_op!(random_normal, RandomNormal, "RandomStandardNormal", args{x}, attrs {
@@ -6,3 +13,254 @@ define_op!(random_normal, RandomNormal, "RandomStandardNormal", args{x}, attrs {
     seed?: i64 => "seed",
     seed2?: i64 => "seed2",
 });
+
+define_op!(random_uniform, RandomUniform, "RandomUniform", args { shape }, attrs {
+    dtype: DataType => "dtype",
+    seed?: i64 => "seed",
+    seed2?: i64 => "seed2",
+});
+
+define_op!(
+    stateless_random_uniform_op,
+    StatelessRandomUniform,
+    "StatelessRandomUniform",
+    args { shape, seed },
+    attrs {
+        dtype?: DataType => "dtype",
+    }
+);
+
+define_op!(
+    stateless_random_normal_op,
+    StatelessRandomNormal,
+    "StatelessRandomNormal",
+    args { shape, seed },
+    attrs {
+        dtype?: DataType => "dtype",
+    }
+);
+
+define_op!(
+    stateless_truncated_normal_op,
+    StatelessTruncatedNormal,
+    "StatelessTruncatedNormal",
+    args { shape, seed },
+    attrs {
+        dtype?: DataType => "dtype",
+    }
+);
+
+define_op!(
+    stateless_random_uniform_int_op,
+    StatelessRandomUniformInt,
+    "StatelessRandomUniformInt",
+    args {
+        shape,
+        seed,
+        minval,
+        maxval
+    }
+);
+
+/// Checks that `seed` is a pair of int32 or int64 values, as the stateless
+/// random ops expect.
+fn check_seed(scope: &Scope, seed: &Output) -> Result<()> {
+    let data_type = seed.operation.output_type(seed.index as usize);
+    if data_type != DataType::Int32 && data_type != DataType::Int64 {
+        return Err(invalid_arg!(
+            "A stateless random seed must be int32 or int64, but is {}",
+            data_type
+        ));
+    }
+    if let Some(dims) = known_dims(scope, seed)? {
+        if dims.len() != 1 || matches!(dims[0], Some(d) if d != 2) {
+            return Err(invalid_arg!(
+                "A stateless random seed must have shape [2], but has shape {:?}",
+                dims
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn shape_constant(scope: &mut Scope, shape: &[i64]) -> Result<Operation> {
+    if let Some(&dim) = shape.iter().find(|&&dim| dim < 0) {
+        return Err(invalid_arg!(
+            "Random tensors can't have a dimension of {} in shape {:?}",
+            dim,
+            shape
+        ));
+    }
+    ops::constant(scope, shape)
+}
+
+fn check_float(data_type: DataType) -> Result<()> {
+    match data_type {
+        DataType::Float | DataType::Double | DataType::Half | DataType::BFloat16 => Ok(()),
+        _ => Err(invalid_arg!(
+            "Random values must be floating point, but {} was requested",
+            data_type
+        )),
+    }
+}
+
+/// Returns a tensor of the given shape and floating point type with values
+/// drawn uniformly from `[0, 1)`.
+///
+/// The values are determined by `seed`, an int32 or int64 tensor of shape
+/// `[2]`, so the same seed gives the same values on every run and every
+/// device, unlike the stateful random ops.
+pub fn stateless_random_uniform(
+    scope: &mut Scope,
+    shape: &[i64],
+    seed: Output,
+    data_type: DataType,
+) -> Result<Output> {
+    check_seed(scope, &seed)?;
+    check_float(data_type)?;
+    let shape = shape_constant(scope, shape)?;
+    Ok(StatelessRandomUniform::new()
+        .dtype(data_type)
+        .build(scope, shape, seed)?
+        .into())
+}
+
+/// Like `stateless_random_uniform`, but with values drawn from a standard
+/// normal distribution.
+pub fn stateless_random_normal(
+    scope: &mut Scope,
+    shape: &[i64],
+    seed: Output,
+    data_type: DataType,
+) -> Result<Output> {
+    check_seed(scope, &seed)?;
+    check_float(data_type)?;
+    let shape = shape_constant(scope, shape)?;
+    Ok(StatelessRandomNormal::new()
+        .dtype(data_type)
+        .build(scope, shape, seed)?
+        .into())
+}
+
+/// Like `stateless_random_normal`, but values more than two standard
+/// deviations from the mean are redrawn.
+pub fn stateless_truncated_normal(
+    scope: &mut Scope,
+    shape: &[i64],
+    seed: Output,
+    data_type: DataType,
+) -> Result<Output> {
+    check_seed(scope, &seed)?;
+    check_float(data_type)?;
+    let shape = shape_constant(scope, shape)?;
+    Ok(StatelessTruncatedNormal::new()
+        .dtype(data_type)
+        .build(scope, shape, seed)?
+        .into())
+}
+
+/// Returns a tensor of the given shape with integers drawn uniformly from
+/// `[minval, maxval)`, determined by `seed` as for
+/// `stateless_random_uniform`.
+pub fn stateless_random_uniform_int<I: IndexType + PartialOrd>(
+    scope: &mut Scope,
+    shape: &[i64],
+    seed: Output,
+    minval: I,
+    maxval: I,
+) -> Result<Output> {
+    check_seed(scope, &seed)?;
+    if minval >= maxval {
+        return Err(invalid_arg!(
+            "The range of random integers [{:?}, {:?}) is empty",
+            minval,
+            maxval
+        ));
+    }
+    let shape = shape_constant(scope, shape)?;
+    let minval = ops::constant(scope, minval)?;
+    let maxval = ops::constant(scope, maxval)?;
+    Ok(StatelessRandomUniformInt::new()
+        .build(scope, shape, seed, minval, maxval)?
+        .into())
+}
+
+/// Returns a dropout mask of the given shape and floating point type, whose
+/// values are 0 with probability `rate` and `1 / (1 - rate)` otherwise, so
+/// multiplying by it keeps the expected value unchanged.  The mask is
+/// determined by `seed` as for `stateless_random_uniform`.
+pub fn stateless_dropout_mask(
+    scope: &mut Scope,
+    shape: &[i64],
+    seed: Output,
+    rate: f32,
+    data_type: DataType,
+) -> Result<Output> {
+    if !(0.0..1.0).contains(&rate) {
+        return Err(invalid_arg!(
+            "The dropout rate must be in [0, 1), but is {}",
+            rate
+        ));
+    }
+    let mut scope = scope.new_sub_scope("stateless_dropout_mask");
+    let uniform = stateless_random_uniform(&mut scope, shape, seed, data_type)?;
+    // floor(u + 1 - rate) is 1 exactly when u >= rate.
+    let keep_prob = ops::constant(&mut scope, 1.0 - rate)?;
+    let keep_prob = ops::Cast::new()
+        .dst_type(data_type)
+        .build(&mut scope, keep_prob)?;
+    let shifted = ops::add(&mut scope, uniform, keep_prob.clone())?;
+    let keep = ops::floor(&mut scope, shifted)?;
+    Ok(ops::divide(&mut scope, keep, keep_prob)?.into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+    use crate::ops::run;
+
+    #[test]
+    fn reproducible() {
+        let mut scope = Scope::new_root_scope();
+        let seed: Output = ops::constant(&mut scope, &[1i64, 2][..]).unwrap().into();
+        let other_seed: Output = ops::constant(&mut scope, &[1i64, 3][..]).unwrap().into();
+        let a = stateless_random_uniform(&mut scope, &[10], seed.clone(), DataType::Float).unwrap();
+        let b = stateless_random_uniform(&mut scope, &[10], seed.clone(), DataType::Float).unwrap();
+        let c = stateless_random_uniform(&mut scope, &[10], other_seed, DataType::Float).unwrap();
+        let mask = stateless_dropout_mask(&mut scope, &[1000], seed.clone(), 0.5, DataType::Float)
+            .unwrap();
+        let values = run::<f32>(&scope, &[], &[&a, &b, &c, &mask]).unwrap();
+        assert_eq!(values[0], values[1]);
+        assert_ne!(values[0], values[2]);
+        assert!(values[0].iter().all(|&v| (0.0..1.0).contains(&v)));
+        assert!(values[3].iter().all(|&v| v == 0.0 || v == 2.0));
+        let kept = values[3].iter().filter(|&&v| v == 2.0).count();
+        assert!(kept > 400 && kept < 600, "{}", kept);
+
+        let ints = stateless_random_uniform_int(&mut scope, &[10], seed.clone(), 3i32, 5).unwrap();
+        assert!(fetch::<i32>(&scope, &ints)
+            .iter()
+            .all(|&v| v == 3 || v == 4));
+    }
+
+    #[test]
+    fn validation() {
+        let mut scope = Scope::new_root_scope();
+        let seed: Output = ops::constant(&mut scope, &[1i64, 2][..]).unwrap().into();
+        let float_seed: Output = ops::constant(&mut scope, &[1.0f32, 2.0][..])
+            .unwrap()
+            .into();
+        let long_seed: Output = ops::constant(&mut scope, &[1i32, 2, 3][..]).unwrap().into();
+        assert!(stateless_random_normal(&mut scope, &[2], float_seed, DataType::Float).is_err());
+        assert!(stateless_random_normal(&mut scope, &[2], long_seed, DataType::Float).is_err());
+        assert!(stateless_random_normal(&mut scope, &[2], seed.clone(), DataType::Int32).is_err());
+        assert!(
+            stateless_truncated_normal(&mut scope, &[-1], seed.clone(), DataType::Float).is_err()
+        );
+        assert!(stateless_random_uniform_int(&mut scope, &[2], seed.clone(), 5i64, 5).is_err());
+        assert!(stateless_dropout_mask(&mut scope, &[2], seed, 1.0, DataType::Float).is_err());
+    }
+}
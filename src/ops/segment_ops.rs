@@ -0,0 +1,428 @@
+use super::check_index_type;
+use super::known_dims;
+use crate::ops;
+use crate::scope::is_floating;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use tensorflow_macros::define_op;
+
+define_op!(
+    segment_sum_op,
+    SegmentSum,
+    "SegmentSum",
+    args { data, segment_ids }
+);
+
+define_op!(
+    segment_mean_op,
+    SegmentMean,
+    "SegmentMean",
+    args { data, segment_ids }
+);
+
+define_op!(
+    segment_max_op,
+    SegmentMax,
+    "SegmentMax",
+    args { data, segment_ids }
+);
+
+define_op!(
+    unsorted_segment_sum_op,
+    UnsortedSegmentSum,
+    "UnsortedSegmentSum",
+    args {
+        data,
+        segment_ids,
+        num_segments
+    }
+);
+
+define_op!(
+    unsorted_segment_max_op,
+    UnsortedSegmentMax,
+    "UnsortedSegmentMax",
+    args {
+        data,
+        segment_ids,
+        num_segments
+    }
+);
+
+/// Checks the segment ids of `function` against `data`.  The shape of the
+/// ids must be a prefix of the shape of `data`, and sorted ids must be a
+/// vector.
+fn check_segment_ids(
+    scope: &Scope,
+    function: &str,
+    data: &Output,
+    segment_ids: &Output,
+    sorted: bool,
+) -> Result<()> {
+    check_index_type(segment_ids, "Segment ids")?;
+    let ids_dims = match known_dims(scope, segment_ids)? {
+        Some(dims) => dims,
+        None => return Ok(()),
+    };
+    if sorted && ids_dims.len() != 1 {
+        return Err(invalid_arg!(
+            "Segment ids for {} must be a vector, but have shape {:?}",
+            function,
+            ids_dims
+        ));
+    }
+    if let Some(data_dims) = known_dims(scope, data)? {
+        let compatible = ids_dims.len() <= data_dims.len()
+            && ids_dims
+                .iter()
+                .zip(&data_dims)
+                .all(|(a, b)| a.is_none() || b.is_none() || a == b);
+        if !compatible {
+            return Err(invalid_arg!(
+                "Segment ids of shape {:?} don't match the leading dimensions of data of shape {:?} for {}",
+                ids_dims,
+                data_dims,
+                function
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn check_num_segments(scope: &Scope, function: &str, num_segments: &Output) -> Result<()> {
+    check_index_type(num_segments, "The number of segments")?;
+    if let Some(dims) = known_dims(scope, num_segments)? {
+        if !dims.is_empty() {
+            return Err(invalid_arg!(
+                "The number of segments for {} must be a scalar, but has shape {:?}",
+                function,
+                dims
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns a zero of the index type of `ids`.
+fn zero_index(scope: &mut Scope, ids: &Output) -> Result<Output> {
+    Ok(if ids.data_type() == DataType::Int64 {
+        ops::constant(scope, 0i64)?.into()
+    } else {
+        ops::constant(scope, 0i32)?.into()
+    })
+}
+
+/// In debug mode (see `Scope::set_debug_mode`), returns `segment_ids`, which
+/// fail when run unless they are all non-negative, less than `num_segments`
+/// if it is given, and in ascending order if `sorted` is true.  Otherwise,
+/// returns `segment_ids` unchanged.
+///
+/// The segment functions in this module already check their ids this way,
+/// so this is only needed for ids which are used elsewhere too, e.g. to
+/// gather the aggregated values back.
+pub fn assert_segment_ids(
+    scope: &mut Scope,
+    segment_ids: Output,
+    num_segments: Option<Output>,
+    sorted: bool,
+) -> Result<Output> {
+    check_index_type(&segment_ids, "Segment ids")?;
+    if let Some(num_segments) = &num_segments {
+        check_num_segments(scope, "assert_segment_ids", num_segments)?;
+    }
+    if !scope.debug_mode() {
+        return Ok(segment_ids);
+    }
+    let mut scope = scope.new_sub_scope("assert_segment_ids");
+    let scope = &mut scope;
+    let flat_shape = ops::constant(scope, &[-1i32][..])?;
+    let flat = ops::reshape(scope, segment_ids.clone(), flat_shape)?;
+    let axis = ops::constant(scope, 0i32)?;
+    let zero = zero_index(scope, &segment_ids)?;
+    // Reducing no ids gives the largest (resp. smallest) value of the type,
+    // so empty ids pass.
+    let smallest = ops::min(scope, flat.clone(), axis.clone())?;
+    let mut condition: Output = ops::greater_equal(scope, smallest, zero)?.into();
+    let mut requirements = vec!["non-negative"];
+    if let Some(num_segments) = num_segments {
+        let num_segments = ops::Cast::new()
+            .dst_type(segment_ids.data_type())
+            .build(scope, num_segments)?;
+        let largest = ops::max(scope, flat.clone(), axis.clone())?;
+        let in_range = ops::less(scope, largest, num_segments)?;
+        condition = ops::logical_and(scope, condition, in_range)?.into();
+        requirements.push("less than the number of segments");
+    }
+    if sorted {
+        // Compares each id to the previous one, clamping the slice bounds so
+        // that no ids compare nothing.
+        let size = ops::Shape::new()
+            .out_type(DataType::Int32)
+            .build(scope, flat.clone())?;
+        let one = ops::constant(scope, &[1i32][..])?;
+        let zero = ops::constant(scope, &[0i32][..])?;
+        let rest_size = ops::constant(scope, &[-1i32][..])?;
+        let start = ops::minimum(scope, one.clone(), size.clone())?;
+        let rest = ops::slice(scope, flat.clone(), start, rest_size)?;
+        let previous_size = ops::subtract(scope, size, one)?;
+        let previous_size = ops::maximum(scope, previous_size, zero.clone())?;
+        let previous = ops::slice(scope, flat, zero, previous_size)?;
+        let ascending = ops::greater_equal(scope, rest, previous)?;
+        let ascending = ops::all(scope, ascending, axis)?;
+        condition = ops::logical_and(scope, condition, ascending)?.into();
+        requirements.push("sorted");
+    }
+    let message = format!("Segment ids must be {}", requirements.join(", "));
+    ops::assert_that(scope, segment_ids, condition, &message)
+}
+
+/// Validates the arguments of a sorted segment reduction and returns the
+/// checked ids.
+fn sorted_segment_ids(
+    scope: &mut Scope,
+    function: &str,
+    data: &Output,
+    segment_ids: Output,
+) -> Result<Output> {
+    check_segment_ids(scope, function, data, &segment_ids, true)?;
+    assert_segment_ids(scope, segment_ids, None, true)
+}
+
+/// Validates the arguments of an unsorted segment reduction and returns the
+/// checked ids.
+fn unsorted_segment_ids(
+    scope: &mut Scope,
+    function: &str,
+    data: &Output,
+    segment_ids: Output,
+    num_segments: &Output,
+) -> Result<Output> {
+    check_segment_ids(scope, function, data, &segment_ids, false)?;
+    check_num_segments(scope, function, num_segments)?;
+    assert_segment_ids(scope, segment_ids, Some(num_segments.clone()), false)
+}
+
+/// Sums the slices of `data` in each segment: `result[i]` is the sum of the
+/// `data[j]` with `segment_ids[j] == i`.
+///
+/// `segment_ids` is a vector with an id for each slice along the first
+/// dimension of `data`, and must be sorted and non-negative.  The result has
+/// a slice for each id up to the largest, and segments without any slices
+/// are zero.  Use `unsorted_segment_sum` for ids in any order.
+pub fn segment_sum(scope: &mut Scope, data: Output, segment_ids: Output) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("segment_sum");
+    let segment_ids = sorted_segment_ids(&mut scope, "segment_sum", &data, segment_ids)?;
+    Ok(segment_sum_op(&mut scope, data, segment_ids)?.into())
+}
+
+/// Averages the slices of `data` in each segment, like `segment_sum`.
+/// Segments without any slices are zero.
+pub fn segment_mean(scope: &mut Scope, data: Output, segment_ids: Output) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("segment_mean");
+    let segment_ids = sorted_segment_ids(&mut scope, "segment_mean", &data, segment_ids)?;
+    Ok(segment_mean_op(&mut scope, data, segment_ids)?.into())
+}
+
+/// Takes the elementwise maximum of the slices of `data` in each segment,
+/// like `segment_sum`.  Segments without any slices are zero.
+pub fn segment_max(scope: &mut Scope, data: Output, segment_ids: Output) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("segment_max");
+    let segment_ids = sorted_segment_ids(&mut scope, "segment_max", &data, segment_ids)?;
+    Ok(segment_max_op(&mut scope, data, segment_ids)?.into())
+}
+
+/// Sums the slices of `data` in each of `num_segments` segments:
+/// `result[i]` is the sum of the `data[j...]` with `segment_ids[j...] == i`.
+///
+/// The shape of `segment_ids` must be a prefix of the shape of `data`, and
+/// the ids may be in any order, but must be in `0..num_segments`.  The
+/// integer scalar `num_segments` may be computed in the graph, e.g. the
+/// number of nodes when summing messages along the edges of a graph:
+///
+/// ```ignore
+/// let messages = ops::gather(&mut scope, node_features, senders, axis)?;
+/// let aggregated = unsorted_segment_sum(&mut scope, messages, receivers, num_nodes)?;
+/// ```
+///
+/// The result has shape `[num_segments] + data.shape[segment_ids.rank..]`,
+/// and segments without any slices are zero.
+pub fn unsorted_segment_sum(
+    scope: &mut Scope,
+    data: Output,
+    segment_ids: Output,
+    num_segments: Output,
+) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("unsorted_segment_sum");
+    let segment_ids = unsorted_segment_ids(
+        &mut scope,
+        "unsorted_segment_sum",
+        &data,
+        segment_ids,
+        &num_segments,
+    )?;
+    Ok(unsorted_segment_sum_op(&mut scope, data, segment_ids, num_segments)?.into())
+}
+
+/// Averages the slices of the floating point `data` in each of
+/// `num_segments` segments, like `unsorted_segment_sum`.  Segments without
+/// any slices are zero.
+pub fn unsorted_segment_mean(
+    scope: &mut Scope,
+    data: Output,
+    segment_ids: Output,
+    num_segments: Output,
+) -> Result<Output> {
+    if !is_floating(data.data_type()) {
+        return Err(invalid_arg!(
+            "unsorted_segment_mean needs floating point data, but got {}",
+            data.data_type()
+        ));
+    }
+    let mut scope = scope.new_sub_scope("unsorted_segment_mean");
+    let scope = &mut scope;
+    let segment_ids = unsorted_segment_ids(
+        scope,
+        "unsorted_segment_mean",
+        &data,
+        segment_ids,
+        &num_segments,
+    )?;
+    // There's no op for the mean, so divide the sum by the number of slices,
+    // which is zero for empty segments.
+    let ones = ops::ones_like(scope, data.clone())?;
+    let sum = unsorted_segment_sum_op(scope, data, segment_ids.clone(), num_segments.clone())?;
+    let count = unsorted_segment_sum_op(scope, ones, segment_ids, num_segments)?;
+    Ok(ops::div_no_nan(scope, sum, count)?.into())
+}
+
+/// Takes the elementwise maximum of the slices of `data` in each of
+/// `num_segments` segments, like `unsorted_segment_sum`.  Segments without
+/// any slices are the lowest value of the data type, e.g. `f32::MIN`.
+pub fn unsorted_segment_max(
+    scope: &mut Scope,
+    data: Output,
+    segment_ids: Output,
+    num_segments: Output,
+) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("unsorted_segment_max");
+    let segment_ids = unsorted_segment_ids(
+        &mut scope,
+        "unsorted_segment_max",
+        &data,
+        segment_ids,
+        &num_segments,
+    )?;
+    Ok(unsorted_segment_max_op(&mut scope, data, segment_ids, num_segments)?.into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::run;
+    use crate::Tensor;
+
+    fn example_data(scope: &mut Scope) -> Output {
+        ops::constant(
+            scope,
+            Tensor::new(&[3, 2])
+                .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+                .unwrap(),
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn sorted_segments() {
+        let mut scope = Scope::new_root_scope();
+        let data = example_data(&mut scope);
+        let ids: Output = ops::constant(&mut scope, &[0i32, 0, 2][..]).unwrap().into();
+        let sum = segment_sum(&mut scope, data.clone(), ids.clone()).unwrap();
+        let mean = segment_mean(&mut scope, data.clone(), ids.clone()).unwrap();
+        let max = segment_max(&mut scope, data, ids).unwrap();
+        let results = run::<f32>(&scope, &[], &[&sum, &mean, &max]).unwrap();
+        assert_eq!(results[0].dims(), &[3, 2]);
+        assert_eq!(&results[0][..], &[4.0, 6.0, 0.0, 0.0, 5.0, 6.0]);
+        assert_eq!(&results[1][..], &[2.0, 3.0, 0.0, 0.0, 5.0, 6.0]);
+        assert_eq!(&results[2][..], &[3.0, 4.0, 0.0, 0.0, 5.0, 6.0]);
+    }
+
+    #[test]
+    fn unsorted_segments() {
+        let mut scope = Scope::new_root_scope();
+        let data = example_data(&mut scope);
+        let ids: Output = ops::constant(&mut scope, &[2i64, 0, 2][..]).unwrap().into();
+        let num_segments: Output = ops::constant(&mut scope, 4i32).unwrap().into();
+        let sum = unsorted_segment_sum(&mut scope, data.clone(), ids.clone(), num_segments.clone())
+            .unwrap();
+        let mean =
+            unsorted_segment_mean(&mut scope, data.clone(), ids.clone(), num_segments.clone())
+                .unwrap();
+        let max = unsorted_segment_max(&mut scope, data, ids, num_segments).unwrap();
+        let results = run::<f32>(&scope, &[], &[&sum, &mean, &max]).unwrap();
+        assert_eq!(results[0].dims(), &[4, 2]);
+        assert_eq!(&results[0][..], &[3.0, 4.0, 0.0, 0.0, 6.0, 8.0, 0.0, 0.0]);
+        assert_eq!(&results[1][..], &[3.0, 4.0, 0.0, 0.0, 3.0, 4.0, 0.0, 0.0]);
+        assert_eq!(&results[2][..2], &[3.0, 4.0]);
+        assert_eq!(&results[2][2..4], &[f32::MIN, f32::MIN]);
+        assert_eq!(&results[2][4..6], &[5.0, 6.0]);
+    }
+
+    #[test]
+    fn validation() {
+        let mut scope = Scope::new_root_scope();
+        let data = example_data(&mut scope);
+        let num_segments: Output = ops::constant(&mut scope, 2i32).unwrap().into();
+        // Float ids.
+        let float_ids: Output = ops::constant(&mut scope, &[0.0f32, 1.0, 1.0][..])
+            .unwrap()
+            .into();
+        assert!(segment_sum(&mut scope, data.clone(), float_ids).is_err());
+        // Ids which don't match the first dimension of the data.
+        let short_ids: Output = ops::constant(&mut scope, &[0i32, 1][..]).unwrap().into();
+        assert!(segment_max(&mut scope, data.clone(), short_ids.clone()).is_err());
+        assert!(
+            unsorted_segment_sum(&mut scope, data.clone(), short_ids, num_segments.clone())
+                .is_err()
+        );
+        // Sorted ids must be a vector.
+        let matrix_ids: Output = ops::constant(&mut scope, Tensor::<i32>::new(&[3, 2]))
+            .unwrap()
+            .into();
+        assert!(segment_mean(&mut scope, data.clone(), matrix_ids).is_err());
+        let ids: Output = ops::constant(&mut scope, &[1i32, 0, 1][..]).unwrap().into();
+        let vector: Output = ops::constant(&mut scope, &[2i32][..]).unwrap().into();
+        assert!(unsorted_segment_sum(&mut scope, data.clone(), ids.clone(), vector).is_err());
+        let int_data: Output = ops::constant(&mut scope, &[1i32, 2, 3][..]).unwrap().into();
+        assert!(unsorted_segment_mean(&mut scope, int_data, ids, num_segments).is_err());
+    }
+
+    #[test]
+    fn runtime_validation() {
+        let mut scope = Scope::new_root_scope();
+        scope.set_debug_mode(true);
+        let data = example_data(&mut scope);
+        let unsorted: Output = ops::constant(&mut scope, &[1i32, 0, 1][..]).unwrap().into();
+        let sum = segment_sum(&mut scope, data.clone(), unsorted.clone()).unwrap();
+        assert!(run::<f32>(&scope, &[], &[&sum]).is_err());
+
+        let mut scope = Scope::new_root_scope();
+        scope.set_debug_mode(true);
+        let data = example_data(&mut scope);
+        let unsorted: Output = ops::constant(&mut scope, &[1i32, 0, 1][..]).unwrap().into();
+        let num_segments: Output = ops::constant(&mut scope, 2i64).unwrap().into();
+        let sum = unsorted_segment_sum(&mut scope, data.clone(), unsorted, num_segments).unwrap();
+        assert_eq!(
+            &run::<f32>(&scope, &[], &[&sum]).unwrap()[0][..],
+            &[3.0, 4.0, 6.0, 8.0]
+        );
+        let out_of_range: Output = ops::constant(&mut scope, &[0i32, 2, 1][..]).unwrap().into();
+        let too_few: Output = ops::constant(&mut scope, 2i32).unwrap().into();
+        let sum = unsorted_segment_sum(&mut scope, data, out_of_range, too_few).unwrap();
+        assert!(run::<f32>(&scope, &[], &[&sum]).is_err());
+    }
+}
@@ -0,0 +1,290 @@
+use super::known_dims;
+use crate::ops::IndexType;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::SparseOutput;
+use tensorflow_macros::define_op;
+
+define_op!(unique_op, Unique, "Unique", args { x }, attrs {
+    out_idx?: DataType => "out_idx",
+});
+
+define_op!(
+    unique_with_counts_op,
+    UniqueWithCounts,
+    "UniqueWithCounts",
+    args { x },
+    attrs {
+        out_idx?: DataType => "out_idx",
+    }
+);
+
+define_op!(
+    dense_to_dense_set_operation_op,
+    DenseToDenseSetOperation,
+    "DenseToDenseSetOperation",
+    args { set1, set2 },
+    attrs {
+        set_operation: String => "set_operation",
+        validate_indices?: bool => "validate_indices",
+    }
+);
+
+fn output(operation: &Operation, index: i32) -> Output {
+    Output {
+        operation: operation.clone(),
+        index,
+    }
+}
+
+/// The result of `unique`.
+#[derive(Debug, Clone)]
+pub struct UniqueValues {
+    /// The distinct values, in order of their first occurrence.
+    pub values: Output,
+    /// For each element of the input, the index of its value in `values`.
+    pub indices: Output,
+}
+
+/// The result of `unique_with_counts`.
+#[derive(Debug, Clone)]
+pub struct UniqueValuesWithCounts {
+    /// The distinct values, in order of their first occurrence.
+    pub values: Output,
+    /// For each element of the input, the index of its value in `values`.
+    pub indices: Output,
+    /// The number of occurrences of each value in `values`.
+    pub counts: Output,
+}
+
+fn check_vector(scope: &Scope, x: &Output, function: &str) -> Result<()> {
+    if let Some(dims) = known_dims(scope, x)? {
+        if dims.len() != 1 {
+            return Err(invalid_arg!(
+                "The input of {} must be a vector, but has shape {:?}",
+                function,
+                dims
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Returns the distinct values of the vector `x`, and the index of each
+/// element's value among them as a tensor of `I`, so that gathering the
+/// values at the indices gives `x` back.  This dedups a batch of ids, e.g.
+/// to look up each embedding only once.
+///
+/// ```ignore
+/// let distinct = unique::<i32>(&mut scope, ids)?;
+/// let embeddings = ops::gather(&mut scope, table, distinct.values, axis)?;
+/// let per_id = ops::gather(&mut scope, embeddings, distinct.indices, axis)?;
+/// ```
+pub fn unique<I: IndexType>(scope: &mut Scope, x: Output) -> Result<UniqueValues> {
+    check_vector(scope, &x, "unique")?;
+    let op = Unique::new()
+        .out_idx(I::data_type())
+        .build(&mut scope.new_sub_scope("unique"), x)?;
+    Ok(UniqueValues {
+        values: output(&op, 0),
+        indices: output(&op, 1),
+    })
+}
+
+/// Like `unique`, but also counts the occurrences of each distinct value, as
+/// a tensor of `I`, e.g. to build a vocabulary with token frequencies.
+pub fn unique_with_counts<I: IndexType>(
+    scope: &mut Scope,
+    x: Output,
+) -> Result<UniqueValuesWithCounts> {
+    check_vector(scope, &x, "unique_with_counts")?;
+    let op = UniqueWithCounts::new()
+        .out_idx(I::data_type())
+        .build(&mut scope.new_sub_scope("unique_with_counts"), x)?;
+    Ok(UniqueValuesWithCounts {
+        values: output(&op, 0),
+        indices: output(&op, 1),
+        counts: output(&op, 2),
+    })
+}
+
+/// Applies `set_operation` to the sets in the last dimension of `a` and `b`.
+fn set_operation(
+    scope: &mut Scope,
+    function: &str,
+    set_operation: &str,
+    a: Output,
+    b: Output,
+) -> Result<SparseOutput> {
+    match a.data_type() {
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::String => {}
+        data_type => {
+            return Err(invalid_arg!(
+                "{} needs integer or string sets, but got {}",
+                function,
+                data_type
+            ))
+        }
+    }
+    if a.data_type() != b.data_type() {
+        return Err(invalid_arg!(
+            "The sets of {} have different types {} and {}",
+            function,
+            a.data_type(),
+            b.data_type()
+        ));
+    }
+    let a_dims = known_dims(scope, &a)?;
+    let b_dims = known_dims(scope, &b)?;
+    for dims in a_dims.iter().chain(&b_dims) {
+        if dims.len() < 2 {
+            return Err(invalid_arg!(
+                "The sets of {} must have at least two dimensions, but one has shape {:?}",
+                function,
+                dims
+            ));
+        }
+    }
+    if let (Some(a_dims), Some(b_dims)) = (&a_dims, &b_dims) {
+        let compatible = a_dims.len() == b_dims.len()
+            && a_dims[..a_dims.len() - 1]
+                .iter()
+                .zip(b_dims)
+                .all(|(x, y)| x.is_none() || y.is_none() || x == y);
+        if !compatible {
+            return Err(invalid_arg!(
+                "The sets of {} must match in all but the last dimension, but have shapes {:?} and {:?}",
+                function,
+                a_dims,
+                b_dims
+            ));
+        }
+    }
+    let op = DenseToDenseSetOperation::new()
+        .set_operation(set_operation)
+        .build(&mut scope.new_sub_scope(function), a, b)?;
+    Ok(SparseOutput {
+        indices: output(&op, 0),
+        values: output(&op, 1),
+        dense_shape: output(&op, 2),
+    })
+}
+
+/// Returns the intersection of the sets in the last dimension of `a` and
+/// `b`, whose other dimensions must match, e.g. the items each user both
+/// viewed and bought.
+///
+/// The result is sparse, since the sets may have different sizes.  Its last
+/// dimension is as large as the largest set, and each set is sorted.
+pub fn set_intersection(scope: &mut Scope, a: Output, b: Output) -> Result<SparseOutput> {
+    set_operation(scope, "set_intersection", "intersection", a, b)
+}
+
+/// Returns the union of the sets in the last dimension of `a` and `b`, like
+/// `set_intersection`.
+pub fn set_union(scope: &mut Scope, a: Output, b: Output) -> Result<SparseOutput> {
+    set_operation(scope, "set_union", "union", a, b)
+}
+
+/// Returns the elements of the sets in the last dimension of `a` which
+/// aren't in the corresponding sets of `b`, like `set_intersection`.
+pub fn set_difference(scope: &mut Scope, a: Output, b: Output) -> Result<SparseOutput> {
+    set_operation(scope, "set_difference", "a-b", a, b)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    #[test]
+    fn unique_values() {
+        let mut scope = Scope::new_root_scope();
+        let x: Output = ops::constant(&mut scope, &[1i32, 1, 2, 4, 4, 4, 7, 8, 8][..])
+            .unwrap()
+            .into();
+        let distinct = unique::<i64>(&mut scope, x.clone()).unwrap();
+        let counted = unique_with_counts::<i32>(&mut scope, x).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let values = args.request_fetch(&distinct.values.operation, distinct.values.index);
+        let indices = args.request_fetch(&distinct.indices.operation, distinct.indices.index);
+        let counts = args.request_fetch(&counted.counts.operation, counted.counts.index);
+        session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<i32>(values).unwrap()[..], &[1, 2, 4, 7, 8]);
+        assert_eq!(
+            &args.fetch::<i64>(indices).unwrap()[..],
+            &[0, 0, 1, 2, 2, 2, 3, 4, 4]
+        );
+        assert_eq!(&args.fetch::<i32>(counts).unwrap()[..], &[2, 1, 3, 1, 2]);
+
+        let matrix: Output = ops::constant(&mut scope, Tensor::<i32>::new(&[2, 2]))
+            .unwrap()
+            .into();
+        assert!(unique::<i32>(&mut scope, matrix).is_err());
+    }
+
+    #[test]
+    fn set_operations() {
+        let mut scope = Scope::new_root_scope();
+        let a: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 3])
+                .with_values(&[3i32, 1, 2, 4, 5, 6])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let b: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 3])
+                .with_values(&[1i32, 3, 5, 4, 4, 4])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let intersection = set_intersection(&mut scope, a.clone(), b.clone()).unwrap();
+        let difference = set_difference(&mut scope, a.clone(), b.clone()).unwrap();
+        let union = set_union(&mut scope, a.clone(), b).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let intersection = args.request_sparse_fetch(&intersection);
+        let difference = args.request_sparse_fetch(&difference);
+        let union = args.request_sparse_fetch(&union);
+        session.run(&mut args).unwrap();
+        let intersection = args.fetch_sparse::<i32>(intersection).unwrap();
+        assert_eq!(&intersection.indices()[..], &[0, 0, 0, 1, 1, 0]);
+        assert_eq!(&intersection.values()[..], &[1, 3, 4]);
+        assert_eq!(intersection.dims(), &[2, 2]);
+        let difference = args.fetch_sparse::<i32>(difference).unwrap();
+        assert_eq!(&difference.values()[..], &[2, 5, 6]);
+        let union = args.fetch_sparse::<i32>(union).unwrap();
+        assert_eq!(&union.values()[..], &[1, 2, 3, 5, 4, 5, 6]);
+
+        let floats: Output = ops::constant(&mut scope, Tensor::<f32>::new(&[2, 3]))
+            .unwrap()
+            .into();
+        assert!(set_union(&mut scope, floats.clone(), floats).is_err());
+        let vector: Output = ops::constant(&mut scope, &[1i32, 2][..]).unwrap().into();
+        assert!(set_intersection(&mut scope, vector.clone(), vector).is_err());
+        let taller: Output = ops::constant(&mut scope, Tensor::<i32>::new(&[3, 3]))
+            .unwrap()
+            .into();
+        assert!(set_difference(&mut scope, a, taller).is_err());
+    }
+}
@@ -0,0 +1,320 @@
+//! Fast Fourier transforms and helpers for complex signals.
+//!
+//! The transforms operate on the innermost one, two or three dimensions of
+//! their input, with any leading dimensions treated as a batch.  The complex
+//! transforms take `Complex<f32>` or `Complex<f64>` tensors; the real
+//! transforms (`rfft*`) take `f32` or `f64` tensors and return only the
+//! non-redundant half of the spectrum, which the inverse real transforms
+//! (`irfft*`) turn back into real signals.
+
+use super::known_dims;
+use super::Cast;
+use super::ComplexAbs;
+use super::Fft;
+use super::Fft2d;
+use super::Fft3d;
+use super::Ifft;
+use super::Ifft2d;
+use super::Ifft3d;
+use super::Imag;
+use super::Irfft;
+use super::Irfft2d;
+use super::Irfft3d;
+use super::Real;
+use super::Rfft;
+use super::Rfft2d;
+use super::Rfft3d;
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+/// Returns the real type with the same precision as the complex type of
+/// `input`, or an error if it isn't complex.
+fn complex_to_real(input: &Output, name: &str) -> Result<DataType> {
+    match input.data_type() {
+        DataType::Complex64 => Ok(DataType::Float),
+        DataType::Complex128 => Ok(DataType::Double),
+        data_type => Err(invalid_arg!(
+            "{} must be complex64 or complex128, but is {}",
+            name,
+            data_type
+        )),
+    }
+}
+
+/// Returns the complex type with the same precision as the real type of
+/// `input`, or an error if it isn't float or double.
+fn real_to_complex(input: &Output, name: &str) -> Result<DataType> {
+    match input.data_type() {
+        DataType::Float => Ok(DataType::Complex64),
+        DataType::Double => Ok(DataType::Complex128),
+        data_type => Err(invalid_arg!(
+            "{} must be float or double, but is {}",
+            name,
+            data_type
+        )),
+    }
+}
+
+/// Checks that `input` has at least `rank` dimensions, if its rank is known.
+fn check_rank(scope: &Scope, input: &Output, rank: usize, name: &str) -> Result<()> {
+    if let Some(dims) = known_dims(scope, input)? {
+        if dims.len() < rank {
+            return Err(invalid_arg!(
+                "{} needs at least {} dimensions, but has shape {:?}",
+                name,
+                rank,
+                dims
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn fft_lengths(scope: &mut Scope, fft_length: &[i32]) -> Result<Output> {
+    if let Some(&length) = fft_length.iter().find(|&&length| length <= 0) {
+        return Err(invalid_arg!(
+            "FFT lengths must be positive, but got {}",
+            length
+        ));
+    }
+    Ok(ops::constant(scope, fft_length)?.into())
+}
+
+/// Computes the 1-dimensional discrete Fourier transform over the innermost
+/// dimension of the complex tensor `input`.
+pub fn fft(scope: &mut Scope, input: Output) -> Result<Output> {
+    complex_to_real(&input, "The input of fft")?;
+    check_rank(scope, &input, 1, "The input of fft")?;
+    Ok(Fft::new().build(scope, input)?.into())
+}
+
+/// Computes the inverse of `fft`.
+pub fn ifft(scope: &mut Scope, input: Output) -> Result<Output> {
+    complex_to_real(&input, "The input of ifft")?;
+    check_rank(scope, &input, 1, "The input of ifft")?;
+    Ok(Ifft::new().build(scope, input)?.into())
+}
+
+/// Computes the 2-dimensional discrete Fourier transform over the two
+/// innermost dimensions of the complex tensor `input`.
+pub fn fft2d(scope: &mut Scope, input: Output) -> Result<Output> {
+    complex_to_real(&input, "The input of fft2d")?;
+    check_rank(scope, &input, 2, "The input of fft2d")?;
+    Ok(Fft2d::new().build(scope, input)?.into())
+}
+
+/// Computes the inverse of `fft2d`.
+pub fn ifft2d(scope: &mut Scope, input: Output) -> Result<Output> {
+    complex_to_real(&input, "The input of ifft2d")?;
+    check_rank(scope, &input, 2, "The input of ifft2d")?;
+    Ok(Ifft2d::new().build(scope, input)?.into())
+}
+
+/// Computes the 3-dimensional discrete Fourier transform over the three
+/// innermost dimensions of the complex tensor `input`.
+pub fn fft3d(scope: &mut Scope, input: Output) -> Result<Output> {
+    complex_to_real(&input, "The input of fft3d")?;
+    check_rank(scope, &input, 3, "The input of fft3d")?;
+    Ok(Fft3d::new().build(scope, input)?.into())
+}
+
+/// Computes the inverse of `fft3d`.
+pub fn ifft3d(scope: &mut Scope, input: Output) -> Result<Output> {
+    complex_to_real(&input, "The input of ifft3d")?;
+    check_rank(scope, &input, 3, "The input of ifft3d")?;
+    Ok(Ifft3d::new().build(scope, input)?.into())
+}
+
+/// Computes the 1-dimensional discrete Fourier transform over the innermost
+/// dimension of the real tensor `input`.
+///
+/// The input is cropped or zero-padded to `fft_length`, and only the
+/// `fft_length / 2 + 1` unique frequencies are returned.
+pub fn rfft(scope: &mut Scope, input: Output, fft_length: i32) -> Result<Output> {
+    let complex = real_to_complex(&input, "The input of rfft")?;
+    check_rank(scope, &input, 1, "The input of rfft")?;
+    let fft_length = fft_lengths(scope, &[fft_length])?;
+    Ok(Rfft::new()
+        .treal(input.data_type())
+        .tcomplex(complex)
+        .build(scope, input, fft_length)?
+        .into())
+}
+
+/// Computes the inverse of `rfft`, returning a real signal of `fft_length`
+/// samples.
+pub fn irfft(scope: &mut Scope, input: Output, fft_length: i32) -> Result<Output> {
+    let real = complex_to_real(&input, "The input of irfft")?;
+    check_rank(scope, &input, 1, "The input of irfft")?;
+    let fft_length = fft_lengths(scope, &[fft_length])?;
+    Ok(Irfft::new()
+        .treal(real)
+        .tcomplex(input.data_type())
+        .build(scope, input, fft_length)?
+        .into())
+}
+
+/// Computes the 2-dimensional discrete Fourier transform over the two
+/// innermost dimensions of the real tensor `input`, cropped or zero-padded to
+/// `fft_length`.  Only the unique frequencies of the innermost dimension are
+/// returned.
+pub fn rfft2d(scope: &mut Scope, input: Output, fft_length: [i32; 2]) -> Result<Output> {
+    let complex = real_to_complex(&input, "The input of rfft2d")?;
+    check_rank(scope, &input, 2, "The input of rfft2d")?;
+    let fft_length = fft_lengths(scope, &fft_length)?;
+    Ok(Rfft2d::new()
+        .treal(input.data_type())
+        .tcomplex(complex)
+        .build(scope, input, fft_length)?
+        .into())
+}
+
+/// Computes the inverse of `rfft2d`.
+pub fn irfft2d(scope: &mut Scope, input: Output, fft_length: [i32; 2]) -> Result<Output> {
+    let real = complex_to_real(&input, "The input of irfft2d")?;
+    check_rank(scope, &input, 2, "The input of irfft2d")?;
+    let fft_length = fft_lengths(scope, &fft_length)?;
+    Ok(Irfft2d::new()
+        .treal(real)
+        .tcomplex(input.data_type())
+        .build(scope, input, fft_length)?
+        .into())
+}
+
+/// Computes the 3-dimensional discrete Fourier transform over the three
+/// innermost dimensions of the real tensor `input`, cropped or zero-padded to
+/// `fft_length`.  Only the unique frequencies of the innermost dimension are
+/// returned.
+pub fn rfft3d(scope: &mut Scope, input: Output, fft_length: [i32; 3]) -> Result<Output> {
+    let complex = real_to_complex(&input, "The input of rfft3d")?;
+    check_rank(scope, &input, 3, "The input of rfft3d")?;
+    let fft_length = fft_lengths(scope, &fft_length)?;
+    Ok(Rfft3d::new()
+        .treal(input.data_type())
+        .tcomplex(complex)
+        .build(scope, input, fft_length)?
+        .into())
+}
+
+/// Computes the inverse of `rfft3d`.
+pub fn irfft3d(scope: &mut Scope, input: Output, fft_length: [i32; 3]) -> Result<Output> {
+    let real = complex_to_real(&input, "The input of irfft3d")?;
+    check_rank(scope, &input, 3, "The input of irfft3d")?;
+    let fft_length = fft_lengths(scope, &fft_length)?;
+    Ok(Irfft3d::new()
+        .treal(real)
+        .tcomplex(input.data_type())
+        .build(scope, input, fft_length)?
+        .into())
+}
+
+/// Converts the real tensor `input` to a complex tensor of the same
+/// precision, with zero imaginary parts.
+pub fn to_complex(scope: &mut Scope, input: Output) -> Result<Output> {
+    let complex = real_to_complex(&input, "The input of to_complex")?;
+    Ok(Cast::new().dst_type(complex).build(scope, input)?.into())
+}
+
+/// Returns the real parts of the complex tensor `input`.
+pub fn real(scope: &mut Scope, input: Output) -> Result<Output> {
+    let real = complex_to_real(&input, "The input of real")?;
+    Ok(Real::new().tout(real).build(scope, input)?.into())
+}
+
+/// Returns the imaginary parts of the complex tensor `input`.
+pub fn imag(scope: &mut Scope, input: Output) -> Result<Output> {
+    let real = complex_to_real(&input, "The input of imag")?;
+    Ok(Imag::new().tout(real).build(scope, input)?.into())
+}
+
+/// Returns the magnitudes of the complex tensor `input`, e.g. to turn a
+/// spectrum into a magnitude spectrogram.
+pub fn magnitude(scope: &mut Scope, input: Output) -> Result<Output> {
+    let real = complex_to_real(&input, "The input of magnitude")?;
+    Ok(ComplexAbs::new().tout(real).build(scope, input)?.into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+    use num_complex::Complex;
+
+    #[test]
+    fn complex_round_trip() {
+        let mut scope = Scope::new_root_scope();
+        let signal: Output = ops::constant(
+            &mut scope,
+            &[
+                Complex::new(1.0f32, 0.0),
+                Complex::new(0.0, 1.0),
+                Complex::new(-1.0, 0.0),
+                Complex::new(0.0, -1.0),
+            ][..],
+        )
+        .unwrap()
+        .into();
+        let spectrum = fft(&mut scope, signal.clone()).unwrap();
+        let magnitudes = magnitude(&mut scope, spectrum.clone()).unwrap();
+        let restored = ifft(&mut scope, spectrum).unwrap();
+        let restored = real(&mut scope, restored).unwrap();
+        // A complex exponential has all its energy in one frequency.
+        let magnitudes = fetch::<f32>(&scope, &magnitudes);
+        assert!(
+            magnitudes
+                .iter()
+                .zip(&[0.0, 4.0, 0.0, 0.0])
+                .all(|(x, y)| (x - y).abs() < 1e-5),
+            "{:?}",
+            &magnitudes[..]
+        );
+        let restored = fetch::<f32>(&scope, &restored);
+        assert!(
+            restored
+                .iter()
+                .zip(&[1.0, 0.0, -1.0, 0.0])
+                .all(|(x, y)| (x - y).abs() < 1e-5),
+            "{:?}",
+            &restored[..]
+        );
+        assert!(fft2d(&mut scope, signal).is_err());
+    }
+
+    #[test]
+    fn real_round_trip() {
+        let mut scope = Scope::new_root_scope();
+        let signal: Output = ops::constant(&mut scope, &[1.0f32, 2.0, 3.0, 4.0][..])
+            .unwrap()
+            .into();
+        let spectrum = rfft(&mut scope, signal.clone(), 4).unwrap();
+        assert_eq!(
+            spectrum.operation.output_type(spectrum.index as usize),
+            DataType::Complex64
+        );
+        let restored = irfft(&mut scope, spectrum.clone(), 4).unwrap();
+        let dc = fetch::<Complex<f32>>(&scope, &spectrum);
+        assert_eq!(dc.dims(), &[3]);
+        assert!((dc[0].re - 10.0).abs() < 1e-5);
+        let restored = fetch::<f32>(&scope, &restored);
+        assert!(
+            restored
+                .iter()
+                .zip(&[1.0, 2.0, 3.0, 4.0])
+                .all(|(x, y)| (x - y).abs() < 1e-5),
+            "{:?}",
+            &restored[..]
+        );
+
+        assert!(fft(&mut scope, signal.clone()).is_err());
+        assert!(irfft(&mut scope, signal.clone(), 4).is_err());
+        assert!(rfft(&mut scope, signal.clone(), 0).is_err());
+        assert!(rfft2d(&mut scope, signal.clone(), [2, 2]).is_err());
+        let complex = to_complex(&mut scope, signal).unwrap();
+        assert!(fft(&mut scope, complex).is_ok());
+    }
+}
@@ -0,0 +1,289 @@
+use super::known_dims;
+use crate::ops;
+use crate::scope::is_floating;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use tensorflow_macros::define_op;
+
+define_op!(top_k_op, TopK, "TopKV2", args { input, k }, attrs {
+    sorted?: bool => "sorted",
+});
+
+/// The order in which `sort_with_indices` and friends sort.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortDirection {
+    /// Smallest first.
+    Ascending,
+    /// Largest first.
+    Descending,
+}
+
+/// Sorted values and their int32 indices along the sorted axis, as returned
+/// by `top_k` and `sort_with_indices`.
+#[derive(Debug, Clone)]
+pub struct ValuesAndIndices {
+    /// The values.
+    pub values: Output,
+    /// The index of each value in the input, along the sorted axis.
+    pub indices: Output,
+}
+
+impl ValuesAndIndices {
+    fn from_op(op: Operation) -> Self {
+        Self {
+            values: Output {
+                operation: op.clone(),
+                index: 0,
+            },
+            indices: Output {
+                operation: op,
+                index: 1,
+            },
+        }
+    }
+}
+
+fn check_sortable(input: &Output, function: &str) -> Result<()> {
+    let data_type = input.operation.output_type(input.index as usize);
+    match data_type {
+        DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64 => Ok(()),
+        _ if is_floating(data_type) => Ok(()),
+        _ => Err(invalid_arg!(
+            "{} needs real numbers, but got {}",
+            function,
+            data_type
+        )),
+    }
+}
+
+/// Returns the `k` largest entries along the last dimension of `input` and
+/// their indices, e.g. the best scoring items for each user in a batch.
+///
+/// With `sorted`, the entries are in descending order; otherwise their order
+/// is unspecified.  Equal entries are ordered by index.
+pub fn top_k(scope: &mut Scope, input: Output, k: i32, sorted: bool) -> Result<ValuesAndIndices> {
+    check_sortable(&input, "top_k")?;
+    if k < 0 {
+        return Err(invalid_arg!("top_k needs a non-negative k, but got {}", k));
+    }
+    if let Some(dims) = known_dims(scope, &input)? {
+        match dims.last() {
+            None => {
+                return Err(invalid_arg!(
+                    "The input of top_k must have at least one dimension"
+                ))
+            }
+            Some(Some(size)) if *size < i64::from(k) => {
+                return Err(invalid_arg!(
+                    "Can't take the top {} entries of an input of shape {:?}",
+                    k,
+                    dims
+                ))
+            }
+            _ => {}
+        }
+    }
+    let mut scope = scope.new_sub_scope("top_k");
+    let k = ops::constant(&mut scope, k)?;
+    let op = TopK::new().sorted(sorted).build(&mut scope, input, k)?;
+    Ok(ValuesAndIndices::from_op(op))
+}
+
+/// Maps `input` to values in the reverse order, so that sorting them in
+/// descending order sorts `input` in ascending order.  This is its own
+/// inverse.
+fn reverse_order(scope: &mut Scope, input: Output) -> Result<Output> {
+    if is_floating(input.operation.output_type(input.index as usize)) {
+        Ok(ops::negate(scope, input)?.into())
+    } else {
+        // Unlike negation, ~x doesn't overflow for the smallest integer and
+        // works for unsigned integers.
+        Ok(ops::invert(scope, input)?.into())
+    }
+}
+
+/// Sorts `input` along `axis` and returns the sorted values with their
+/// indices along that axis.  The sort is stable, so equal values keep the
+/// order of their indices.
+///
+/// `axis` may be negative to count from the end.  Sorting along any axis but
+/// the last needs the rank of `input` to be known.
+pub fn sort_with_indices(
+    scope: &mut Scope,
+    input: Output,
+    axis: i32,
+    direction: SortDirection,
+) -> Result<ValuesAndIndices> {
+    check_sortable(&input, "sort")?;
+    let rank = known_dims(scope, &input)?.map(|dims| dims.len() as i32);
+    // The permutation moving `axis` to the end, if it isn't already there.
+    let perm = match rank {
+        Some(0) => return Err(invalid_arg!("Can't sort a scalar")),
+        Some(rank) => {
+            if axis < -rank || axis >= rank {
+                return Err(invalid_arg!(
+                    "Axis {} is out of range for sorting a tensor of rank {}",
+                    axis,
+                    rank
+                ));
+            }
+            let axis = (axis + rank) % rank;
+            if axis == rank - 1 {
+                None
+            } else {
+                let mut perm: Vec<i32> = (0..rank).collect();
+                perm.swap(axis as usize, (rank - 1) as usize);
+                Some(perm)
+            }
+        }
+        None if axis == -1 => None,
+        None => {
+            return Err(invalid_arg!(
+                "The rank of the input must be known to sort along axis {}",
+                axis
+            ))
+        }
+    };
+    let mut scope = scope.new_sub_scope("sort");
+    let scope = &mut scope;
+    let ascending = direction == SortDirection::Ascending;
+    let mut input = input;
+    if ascending {
+        input = reverse_order(scope, input)?;
+    }
+    let perm = match perm {
+        Some(perm) => {
+            let perm: Output = ops::constant(scope, &perm[..])?.into();
+            input = ops::transpose(scope, input, perm.clone())?.into();
+            Some(perm)
+        }
+        None => None,
+    };
+    // Taking the top k for all k entries of the last dimension sorts it.
+    let shape = ops::shape(scope, input.clone())?;
+    let rank = ops::rank(scope, input.clone())?;
+    let one = ops::constant(scope, 1i32)?;
+    let last = ops::subtract(scope, rank, one)?;
+    let zero = ops::constant(scope, 0i32)?;
+    let k = ops::gather(scope, shape, last, zero)?;
+    let op = TopK::new().sorted(true).build(scope, input, k)?;
+    let ValuesAndIndices {
+        mut values,
+        mut indices,
+    } = ValuesAndIndices::from_op(op);
+    if let Some(perm) = perm {
+        // Swapping two axes is its own inverse.
+        values = ops::transpose(scope, values, perm.clone())?.into();
+        indices = ops::transpose(scope, indices, perm)?.into();
+    }
+    if ascending {
+        values = reverse_order(scope, values)?;
+    }
+    Ok(ValuesAndIndices { values, indices })
+}
+
+/// Returns `input` sorted along `axis`.  See `sort_with_indices`.
+pub fn sort(
+    scope: &mut Scope,
+    input: Output,
+    axis: i32,
+    direction: SortDirection,
+) -> Result<Output> {
+    Ok(sort_with_indices(scope, input, axis, direction)?.values)
+}
+
+/// Returns the int32 indices which sort `input` along `axis`.  See
+/// `sort_with_indices`.
+pub fn argsort(
+    scope: &mut Scope,
+    input: Output,
+    axis: i32,
+    direction: SortDirection,
+) -> Result<Output> {
+    Ok(sort_with_indices(scope, input, axis, direction)?.indices)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+    use crate::Tensor;
+
+    fn matrix(scope: &mut Scope) -> Output {
+        ops::constant(
+            scope,
+            Tensor::new(&[2, 3])
+                .with_values(&[1.0f32, 5.0, 3.0, 4.0, 2.0, 6.0])
+                .unwrap(),
+        )
+        .unwrap()
+        .into()
+    }
+
+    #[test]
+    fn top_k_entries() {
+        let mut scope = Scope::new_root_scope();
+        let input = matrix(&mut scope);
+        let top = top_k(&mut scope, input.clone(), 2, true).unwrap();
+        let values = fetch::<f32>(&scope, &top.values);
+        assert_eq!(values.dims(), &[2, 2]);
+        assert_eq!(&values[..], &[5.0, 3.0, 6.0, 4.0]);
+        assert_eq!(&fetch::<i32>(&scope, &top.indices)[..], &[1, 2, 2, 0]);
+
+        assert!(top_k(&mut scope, input.clone(), -1, true).is_err());
+        assert!(top_k(&mut scope, input, 4, true).is_err());
+        let flag: Output = ops::constant(&mut scope, &[true, false][..])
+            .unwrap()
+            .into();
+        assert!(top_k(&mut scope, flag, 1, true).is_err());
+    }
+
+    #[test]
+    fn sorting() {
+        let mut scope = Scope::new_root_scope();
+        let input = matrix(&mut scope);
+        let by_column =
+            sort_with_indices(&mut scope, input.clone(), 0, SortDirection::Ascending).unwrap();
+        let by_row = sort(&mut scope, input.clone(), -1, SortDirection::Descending).unwrap();
+        assert_eq!(
+            &fetch::<f32>(&scope, &by_column.values)[..],
+            &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]
+        );
+        assert_eq!(
+            &fetch::<i32>(&scope, &by_column.indices)[..],
+            &[0, 1, 0, 1, 0, 1]
+        );
+        assert_eq!(
+            &fetch::<f32>(&scope, &by_row)[..],
+            &[5.0, 3.0, 1.0, 6.0, 4.0, 2.0]
+        );
+
+        // The smallest integer can't be negated.
+        let ints: Output = ops::constant(&mut scope, &[0i32, std::i32::MIN, 5, 0][..])
+            .unwrap()
+            .into();
+        let ascending = sort(&mut scope, ints.clone(), 0, SortDirection::Ascending).unwrap();
+        let order = argsort(&mut scope, ints.clone(), 0, SortDirection::Ascending).unwrap();
+        assert_eq!(
+            &fetch::<i32>(&scope, &ascending)[..],
+            &[std::i32::MIN, 0, 0, 5]
+        );
+        assert_eq!(&fetch::<i32>(&scope, &order)[..], &[1, 0, 3, 2]);
+
+        assert!(sort(&mut scope, input, 2, SortDirection::Ascending).is_err());
+        let scalar: Output = ops::constant(&mut scope, 1.0f32).unwrap().into();
+        assert!(sort(&mut scope, scalar, -1, SortDirection::Ascending).is_err());
+    }
+}
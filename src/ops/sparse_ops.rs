@@ -0,0 +1,309 @@
+use super::known_dims;
+use super::Placeholder;
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Shape;
+use crate::SparseOutput;
+use tensorflow_macros::define_op;
+
+define_op!(
+    sparse_to_dense_op,
+    SparseToDense,
+    "SparseToDense",
+    args { sparse_indices, output_shape, sparse_values, default_value },
+    attrs {
+        validate_indices?: bool => "validate_indices",
+    }
+);
+
+define_op!(
+    sparse_tensor_dense_mat_mul_op,
+    SparseTensorDenseMatMul,
+    "SparseTensorDenseMatMul",
+    args { a_indices, a_values, a_shape, b },
+    attrs {
+        adjoint_a?: bool => "adjoint_a",
+        adjoint_b?: bool => "adjoint_b",
+    }
+);
+
+define_op!(
+    sparse_reduce_sum_op,
+    SparseReduceSum,
+    "SparseReduceSum",
+    args { input_indices, input_values, input_shape, reduction_axes },
+    attrs {
+        keep_dims?: bool => "keep_dims",
+    }
+);
+
+define_op!(
+    sparse_reduce_max_op,
+    SparseReduceMax,
+    "SparseReduceMax",
+    args { input_indices, input_values, input_shape, reduction_axes },
+    attrs {
+        keep_dims?: bool => "keep_dims",
+    }
+);
+
+/// Returns the rank of `sparse`, if it is known from the shape of its
+/// indices.
+fn sparse_rank(scope: &Scope, sparse: &SparseOutput) -> Result<Option<i64>> {
+    Ok(known_dims(scope, &sparse.indices)?
+        .and_then(|dims| dims.get(1).cloned())
+        .flatten())
+}
+
+/// Creates the three placeholders for feeding a sparse tensor with values of
+/// type `data_type`.  The rank may be left unknown.
+///
+/// Feed them with `SessionRunArgs::add_sparse_feed`.
+pub fn sparse_placeholder(
+    scope: &mut Scope,
+    data_type: DataType,
+    rank: Option<u64>,
+) -> Result<SparseOutput> {
+    let scope = scope.new_sub_scope("sparse_placeholder");
+    let rank = rank.map(|rank| rank as i64);
+    let indices = Placeholder::new()
+        .data_type(DataType::Int64)
+        .shape(Shape(Some(vec![None, rank])))
+        .build(&mut scope.with_op_name("indices"))?;
+    let values = Placeholder::new()
+        .data_type(data_type)
+        .shape(Shape(Some(vec![None])))
+        .build(&mut scope.with_op_name("values"))?;
+    let dense_shape = Placeholder::new()
+        .data_type(DataType::Int64)
+        .shape(Shape(Some(vec![rank])))
+        .build(&mut scope.with_op_name("dense_shape"))?;
+    Ok(SparseOutput {
+        indices: indices.into(),
+        values: values.into(),
+        dense_shape: dense_shape.into(),
+    })
+}
+
+/// Converts `sparse` to a dense tensor, filling the elements which aren't
+/// stored with the scalar `default_value`.
+///
+/// The indices must be in row-major order without duplicates.
+pub fn sparse_to_dense(
+    scope: &mut Scope,
+    sparse: &SparseOutput,
+    default_value: Output,
+) -> Result<Output> {
+    if default_value.data_type() != sparse.values.data_type() {
+        return Err(invalid_arg!(
+            "The default value of sparse_to_dense has type {}, but the sparse values have type {}",
+            default_value.data_type(),
+            sparse.values.data_type()
+        ));
+    }
+    if let Some(dims) = known_dims(scope, &default_value)? {
+        if !dims.is_empty() {
+            return Err(invalid_arg!(
+                "The default value of sparse_to_dense must be a scalar, but has shape {:?}",
+                dims
+            ));
+        }
+    }
+    Ok(SparseToDense::new()
+        .build(
+            scope,
+            sparse.indices.clone(),
+            sparse.dense_shape.clone(),
+            sparse.values.clone(),
+            default_value,
+        )?
+        .into())
+}
+
+/// Multiplies the sparse matrix `a` by the dense matrix `b`, returning a
+/// dense matrix.  With `adjoint_a` or `adjoint_b`, the conjugate transpose of
+/// the corresponding operand is used.
+pub fn sparse_dense_matmul(
+    scope: &mut Scope,
+    a: &SparseOutput,
+    b: Output,
+    adjoint_a: bool,
+    adjoint_b: bool,
+) -> Result<Output> {
+    if let Some(rank) = sparse_rank(scope, a)? {
+        if rank != 2 {
+            return Err(invalid_arg!(
+                "The sparse operand of sparse_dense_matmul must be a matrix, but has rank {}",
+                rank
+            ));
+        }
+    }
+    if let Some(dims) = known_dims(scope, &b)? {
+        if dims.len() != 2 {
+            return Err(invalid_arg!(
+                "The dense operand of sparse_dense_matmul must be a matrix, but has shape {:?}",
+                dims
+            ));
+        }
+    }
+    if a.values.data_type() != b.data_type() {
+        return Err(invalid_arg!(
+            "Cannot multiply a sparse matrix of type {} by a dense matrix of type {}",
+            a.values.data_type(),
+            b.data_type()
+        ));
+    }
+    Ok(SparseTensorDenseMatMul::new()
+        .adjoint_a(adjoint_a)
+        .adjoint_b(adjoint_b)
+        .build(
+            scope,
+            a.indices.clone(),
+            a.values.clone(),
+            a.dense_shape.clone(),
+            b,
+        )?
+        .into())
+}
+
+/// Checks `axes` against the rank of `sparse`, if it is known, and returns
+/// them as a constant.
+fn reduction_axes(scope: &mut Scope, sparse: &SparseOutput, axes: &[i32]) -> Result<Output> {
+    if let Some(rank) = sparse_rank(scope, sparse)? {
+        let mut seen = Vec::new();
+        for &axis in axes {
+            if i64::from(axis) < -rank || i64::from(axis) >= rank {
+                return Err(invalid_arg!(
+                    "Axis {} is out of range for a sparse tensor of rank {}",
+                    axis,
+                    rank
+                ));
+            }
+            let axis = (i64::from(axis) + rank) % rank;
+            if seen.contains(&axis) {
+                return Err(invalid_arg!(
+                    "Axis {} is reduced more than once in {:?}",
+                    axis,
+                    axes
+                ));
+            }
+            seen.push(axis);
+        }
+    }
+    Ok(ops::constant(scope, axes)?.into())
+}
+
+/// Sums the elements of `sparse` along `axes`, returning a dense tensor.
+/// Elements which aren't stored count as zero.  Reduced dimensions are
+/// removed unless `keep_dims` is true.
+pub fn sparse_reduce_sum(
+    scope: &mut Scope,
+    sparse: &SparseOutput,
+    axes: &[i32],
+    keep_dims: bool,
+) -> Result<Output> {
+    let axes = reduction_axes(scope, sparse, axes)?;
+    Ok(SparseReduceSum::new()
+        .keep_dims(keep_dims)
+        .build(
+            scope,
+            sparse.indices.clone(),
+            sparse.values.clone(),
+            sparse.dense_shape.clone(),
+            axes,
+        )?
+        .into())
+}
+
+/// Takes the maximum of the elements of `sparse` along `axes`, returning a
+/// dense tensor.  Only stored elements are considered.  Reduced dimensions
+/// are removed unless `keep_dims` is true.
+pub fn sparse_reduce_max(
+    scope: &mut Scope,
+    sparse: &SparseOutput,
+    axes: &[i32],
+    keep_dims: bool,
+) -> Result<Output> {
+    let axes = reduction_axes(scope, sparse, axes)?;
+    Ok(SparseReduceMax::new()
+        .keep_dims(keep_dims)
+        .build(
+            scope,
+            sparse.indices.clone(),
+            sparse.values.clone(),
+            sparse.dense_shape.clone(),
+            axes,
+        )?
+        .into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::SparseTensor;
+    use crate::Tensor;
+
+    #[test]
+    fn feed_and_compute() {
+        let mut scope = Scope::new_root_scope();
+        let sparse = sparse_placeholder(&mut scope, DataType::Float, Some(2)).unwrap();
+        let zero: Output = ops::constant(&mut scope, 0.0f32).unwrap().into();
+        let dense = sparse_to_dense(&mut scope, &sparse, zero).unwrap();
+        let ones: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[3, 1]).with_values(&[1.0f32; 3]).unwrap(),
+        )
+        .unwrap()
+        .into();
+        let product = sparse_dense_matmul(&mut scope, &sparse, ones, false, false).unwrap();
+        let sum = sparse_reduce_sum(&mut scope, &sparse, &[0], false).unwrap();
+        let max = sparse_reduce_max(&mut scope, &sparse, &[-1], true).unwrap();
+
+        let value = SparseTensor::from_dense(
+            &Tensor::new(&[2, 3])
+                .with_values(&[0.0f32, 1.0, 0.0, 2.0, 0.0, 3.0])
+                .unwrap(),
+        )
+        .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_sparse_feed(&sparse, &value);
+        let dense_token = args.request_fetch(&dense.operation, dense.index);
+        let product_token = args.request_fetch(&product.operation, product.index);
+        let sum_token = args.request_fetch(&sum.operation, sum.index);
+        let max_token = args.request_fetch(&max.operation, max.index);
+        let sparse_token = args.request_sparse_fetch(&sparse);
+        session.run(&mut args).unwrap();
+        let dense = args.fetch::<f32>(dense_token).unwrap();
+        assert_eq!(&dense[..], &[0.0, 1.0, 0.0, 2.0, 0.0, 3.0]);
+        assert_eq!(&args.fetch::<f32>(product_token).unwrap()[..], &[1.0, 5.0]);
+        assert_eq!(&args.fetch::<f32>(sum_token).unwrap()[..], &[2.0, 1.0, 3.0]);
+        let max = args.fetch::<f32>(max_token).unwrap();
+        assert_eq!(max.dims(), &[2, 1]);
+        assert_eq!(&max[..], &[1.0, 3.0]);
+        let fetched = args.fetch_sparse::<f32>(sparse_token).unwrap();
+        assert_eq!(fetched.to_dense().unwrap(), dense);
+    }
+
+    #[test]
+    fn validation() {
+        let mut scope = Scope::new_root_scope();
+        let sparse = sparse_placeholder(&mut scope, DataType::Float, Some(2)).unwrap();
+        let int_zero: Output = ops::constant(&mut scope, 0i32).unwrap().into();
+        assert!(sparse_to_dense(&mut scope, &sparse, int_zero).is_err());
+        let vector: Output = ops::constant(&mut scope, &[1.0f32, 2.0][..])
+            .unwrap()
+            .into();
+        assert!(sparse_dense_matmul(&mut scope, &sparse, vector, false, false).is_err());
+        assert!(sparse_reduce_sum(&mut scope, &sparse, &[2], false).is_err());
+        assert!(sparse_reduce_sum(&mut scope, &sparse, &[1, -1], false).is_err());
+    }
+}
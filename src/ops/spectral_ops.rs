@@ -0,0 +1,56 @@
+use crate::DataType;
+use tensorflow_macros::define_op;
+
+define_op!(fft_op, Fft, "FFT", args { input });
+
+define_op!(ifft_op, Ifft, "IFFT", args { input });
+
+define_op!(fft2d_op, Fft2d, "FFT2D", args { input });
+
+define_op!(ifft2d_op, Ifft2d, "IFFT2D", args { input });
+
+define_op!(fft3d_op, Fft3d, "FFT3D", args { input });
+
+define_op!(ifft3d_op, Ifft3d, "IFFT3D", args { input });
+
+define_op!(rfft_op, Rfft, "RFFT", args { input, fft_length }, attrs {
+    treal?: DataType => "Treal",
+    tcomplex?: DataType => "Tcomplex",
+});
+
+define_op!(irfft_op, Irfft, "IRFFT", args { input, fft_length }, attrs {
+    treal?: DataType => "Treal",
+    tcomplex?: DataType => "Tcomplex",
+});
+
+define_op!(rfft2d_op, Rfft2d, "RFFT2D", args { input, fft_length }, attrs {
+    treal?: DataType => "Treal",
+    tcomplex?: DataType => "Tcomplex",
+});
+
+define_op!(irfft2d_op, Irfft2d, "IRFFT2D", args { input, fft_length }, attrs {
+    treal?: DataType => "Treal",
+    tcomplex?: DataType => "Tcomplex",
+});
+
+define_op!(rfft3d_op, Rfft3d, "RFFT3D", args { input, fft_length }, attrs {
+    treal?: DataType => "Treal",
+    tcomplex?: DataType => "Tcomplex",
+});
+
+define_op!(irfft3d_op, Irfft3d, "IRFFT3D", args { input, fft_length }, attrs {
+    treal?: DataType => "Treal",
+    tcomplex?: DataType => "Tcomplex",
+});
+
+define_op!(real_op, Real, "Real", args { input }, attrs {
+    tout?: DataType => "Tout",
+});
+
+define_op!(imag_op, Imag, "Imag", args { input }, attrs {
+    tout?: DataType => "Tout",
+});
+
+define_op!(complex_abs_op, ComplexAbs, "ComplexAbs", args { x }, attrs {
+    tout?: DataType => "Tout",
+});
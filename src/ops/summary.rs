@@ -0,0 +1,231 @@
+//! Summaries of tensors for display in TensorBoard.
+//!
+//! Each function here returns a string scalar holding a serialized `Summary`
+//! protocol buffer, computed when the graph runs.  It can be fetched as a
+//! `ByteString` and written to an event file with a `SummaryWriter`:
+//!
+//! ```ignore
+//! let loss_summary = summary::scalar(&mut scope, "loss", loss.clone())?;
+//! let weights_summary = summary::histogram(&mut scope, "weights", weights.output().clone())?;
+//! let summaries = summary::merge(&mut scope, &[loss_summary, weights_summary])?;
+//! let mut writer = SummaryWriter::new("/tmp/logs")?;
+//! // ... in the training loop ...
+//! let token = args.request_fetch(&summaries.operation, summaries.index);
+//! session.run(&mut args)?;
+//! let summary: Tensor<ByteString> = args.fetch(token)?;
+//! writer.add_summary(&summary[0], step)?;
+//! ```
+//!
+//! Audio summaries are built by `audio::audio_summary`.
+
+use super::known_dims;
+use super::HistogramSummary;
+use super::ImageSummary;
+use super::ScalarSummary;
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+pub use crate::io::SummaryWriter;
+
+fn check_real(input: &Output, name: &str) -> Result<()> {
+    match input.data_type() {
+        DataType::Float
+        | DataType::Double
+        | DataType::Half
+        | DataType::BFloat16
+        | DataType::Int8
+        | DataType::Int16
+        | DataType::Int32
+        | DataType::Int64
+        | DataType::UInt8
+        | DataType::UInt16
+        | DataType::UInt32
+        | DataType::UInt64 => Ok(()),
+        data_type => Err(invalid_arg!(
+            "{} must be real numbers, but is {}",
+            name,
+            data_type
+        )),
+    }
+}
+
+/// Returns a serialized `Summary` protocol buffer with the real scalar
+/// `value`, e.g. the loss, tagged `tag`.  TensorBoard plots it against the
+/// training step.
+pub fn scalar(scope: &mut Scope, tag: &str, value: Output) -> Result<Output> {
+    check_real(&value, "The value of a scalar summary")?;
+    if let Some(dims) = known_dims(scope, &value)? {
+        if !dims.is_empty() {
+            return Err(invalid_arg!(
+                "The value of a scalar summary must be a scalar, but has shape {:?}",
+                dims
+            ));
+        }
+    }
+    let tag = ops::constant(scope, tag.to_string())?;
+    Ok(ScalarSummary::new().build(scope, tag, value)?.into())
+}
+
+/// Returns a serialized `Summary` protocol buffer with a histogram of the
+/// real tensor `values`, e.g. the weights of a layer, tagged `tag`.
+/// TensorBoard shows how the distribution changes during training.
+pub fn histogram(scope: &mut Scope, tag: &str, values: Output) -> Result<Output> {
+    check_real(&values, "The values of a histogram summary")?;
+    let tag = ops::constant(scope, tag.to_string())?;
+    Ok(HistogramSummary::new().build(scope, tag, values)?.into())
+}
+
+/// Returns a serialized `Summary` protocol buffer with up to `max_outputs`
+/// images from `images`, tagged `tag/image/0`, `tag/image/1` and so on, or
+/// just `tag/image` if there is only one.
+///
+/// `images` has shape `[batch, height, width, channels]` with 1 (grayscale),
+/// 3 (RGB) or 4 (RGBA) channels.  It is either uint8, or floating point, in
+/// which case each image is rescaled to uint8: if all its values are
+/// non-negative, they are scaled so that the largest becomes 255, and
+/// otherwise they are shifted so that 0 becomes 127 and scaled so that the
+/// value farthest from 0 becomes 0 or 255.
+pub fn image(scope: &mut Scope, tag: &str, images: Output, max_outputs: u32) -> Result<Output> {
+    match images.data_type() {
+        DataType::UInt8 | DataType::Float | DataType::Double | DataType::Half => {}
+        data_type => {
+            return Err(invalid_arg!(
+                "The images of an image summary must be uint8 or floating point, but are {}",
+                data_type
+            ))
+        }
+    }
+    if let Some(dims) = known_dims(scope, &images)? {
+        let channels_ok = match dims.last() {
+            Some(Some(channels)) => [1, 3, 4].contains(channels),
+            _ => true,
+        };
+        if dims.len() != 4 || !channels_ok {
+            return Err(invalid_arg!(
+                "The images of an image summary must have shape [batch, height, width, \
+                 channels] with 1, 3 or 4 channels, but have shape {:?}",
+                dims
+            ));
+        }
+    }
+    if max_outputs == 0 {
+        return Err(invalid_arg!(
+            "An image summary needs a positive number of outputs"
+        ));
+    }
+    let tag = ops::constant(scope, tag.to_string())?;
+    Ok(ImageSummary::new()
+        .max_images(i64::from(max_outputs))
+        .build(scope, tag, images)?
+        .into())
+}
+
+/// Returns a serialized `Summary` protocol buffer with the values of all of
+/// `summaries`, so that they can be fetched and written together.  The tags
+/// of the summaries must be distinct.
+pub fn merge(scope: &mut Scope, summaries: &[Output]) -> Result<Output> {
+    if summaries.is_empty() {
+        return Err(invalid_arg!("There are no summaries to merge"));
+    }
+    if let Some(summary) = summaries
+        .iter()
+        .find(|summary| summary.data_type() != DataType::String)
+    {
+        return Err(invalid_arg!(
+            "Summaries must be strings, but one is {}",
+            summary.data_type()
+        ));
+    }
+    let op = scope.new_operation("MergeSummary", |nd| {
+        nd.add_input_list(summaries);
+        Ok(())
+    })?;
+    Ok(op.into())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::RecordReader;
+    use crate::protos::ProtoReader;
+    use crate::ByteString;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+    use std::fs;
+    use std::fs::File;
+    use std::process;
+
+    /// Returns the tags of the values of a serialized `Summary`.
+    fn tags(summary: &[u8]) -> Vec<String> {
+        ProtoReader::new(summary)
+            .filter_map(|field| match field.unwrap() {
+                (1, value) => {
+                    let value = value.as_bytes().unwrap();
+                    ProtoReader::new(value).find_map(|field| match field.unwrap() {
+                        (1, tag) => Some(tag.as_str().unwrap().to_string()),
+                        _ => None,
+                    })
+                }
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn summaries() {
+        let mut scope = Scope::new_root_scope();
+        let loss: Output = ops::constant(&mut scope, 0.25f32).unwrap().into();
+        let weights: Output = ops::constant(&mut scope, &[1.0f32, 2.0, 2.0, 3.0][..])
+            .unwrap()
+            .into();
+        let images: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 2, 2, 1])
+                .with_values(&[0.0f32, 0.5, 1.0, 0.25, 1.0, 0.0, 0.5, 0.75])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let summaries = [
+            scalar(&mut scope, "loss", loss.clone()).unwrap(),
+            histogram(&mut scope, "weights", weights.clone()).unwrap(),
+            image(&mut scope, "inputs", images.clone(), 1).unwrap(),
+        ];
+        let merged = merge(&mut scope, &summaries).unwrap();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&merged.operation, merged.index);
+        session.run(&mut args).unwrap();
+        let summary: Tensor<ByteString> = args.fetch(token).unwrap();
+        assert_eq!(tags(&summary[0]), vec!["loss", "weights", "inputs/image"]);
+
+        let log_dir = std::env::temp_dir().join(format!("summaries_{}", process::id()));
+        let mut writer = SummaryWriter::new(&log_dir).unwrap();
+        writer.add_summary(&summary[0], 3).unwrap();
+        writer.flush().unwrap();
+        let mut reader = RecordReader::new(File::open(writer.path()).unwrap());
+        let mut records = 0;
+        while reader.read_record().unwrap().is_some() {
+            records += 1;
+        }
+        // The file version event and the summary.
+        assert_eq!(records, 2);
+        fs::remove_dir_all(&log_dir).unwrap();
+
+        assert!(scalar(&mut scope, "weights", weights.clone()).is_err());
+        assert!(image(&mut scope, "weights", weights.clone(), 1).is_err());
+        assert!(image(&mut scope, "inputs", images, 0).is_err());
+        let flag: Output = ops::constant(&mut scope, true).unwrap().into();
+        assert!(histogram(&mut scope, "flag", flag).is_err());
+        assert!(merge(&mut scope, &[]).is_err());
+        assert!(merge(&mut scope, &[loss]).is_err());
+    }
+}
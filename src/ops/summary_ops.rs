@@ -0,0 +1,19 @@
+use tensorflow_macros::define_op;
+
+define_op!(
+    scalar_summary_op,
+    ScalarSummary,
+    "ScalarSummary",
+    args { tags, values }
+);
+
+define_op!(
+    histogram_summary_op,
+    HistogramSummary,
+    "HistogramSummary",
+    args { tag, values }
+);
+
+define_op!(image_summary_op, ImageSummary, "ImageSummary", args { tag, tensor }, attrs {
+    max_images?: i64 => "max_images",
+});
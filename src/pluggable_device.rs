@@ -0,0 +1,69 @@
+use crate::list_devices;
+use crate::Device;
+use crate::Result;
+use crate::SessionOptions;
+use crate::Status;
+use std::ffi::CString;
+use tensorflow_sys as tf;
+
+/// A PluggableDevice plugin, which adds a device type (e.g. Apple's Metal
+/// or Microsoft's DirectML) to the TensorFlow runtime.  This needs
+/// TensorFlow 2.5 or later, and is enabled by the `pluggable_device` feature.
+///
+/// ```no_run
+/// # use tensorflow::PluggableDeviceLibrary;
+/// let plugin = PluggableDeviceLibrary::load("libmetal_plugin.dylib")?;
+/// for device in plugin.devices() {
+///     println!("{} ({})", device.name, device.device_type);
+/// }
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+///
+/// Once loaded, the plugin's devices are used like built-in ones: they are
+/// listed by `Session::device_list`, and ops are placed on them with
+/// `Scope::with_device` or `OperationDescription::set_device`.  A plugin
+/// must be loaded before creating the sessions which use it.  Like Python,
+/// plugins are never unloaded, so nothing happens when this goes out of
+/// scope.
+#[derive(Debug)]
+pub struct PluggableDeviceLibrary {
+    devices: Vec<Device>,
+}
+
+impl PluggableDeviceLibrary {
+    /// Loads the plugin in `library_filename` and registers its devices.
+    pub fn load(library_filename: &str) -> Result<Self> {
+        let c_filename = CString::new(library_filename)?;
+        let options = SessionOptions::new();
+        let before = list_devices(&options)?;
+        let mut status = Status::new();
+        let inner =
+            unsafe { tf::TF_LoadPluggableDeviceLibrary(c_filename.as_ptr(), status.inner()) };
+        if inner.is_null() {
+            return Err(status);
+        }
+        let devices = list_devices(&options)?
+            .into_iter()
+            .filter(|d| !before.iter().any(|b| b.name == d.name))
+            .collect();
+        Ok(Self { devices })
+    }
+
+    /// Returns the devices which the plugin added, i.e. those which were not
+    /// available before it was loaded.
+    pub fn devices(&self) -> &[Device] {
+        &self.devices
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn load_missing() {
+        assert!(PluggableDeviceLibrary::load("does_not_exist.so").is_err());
+    }
+}
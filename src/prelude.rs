@@ -0,0 +1,50 @@
+//! Re-exports the types which almost every program using this crate needs.
+//!
+//! ```
+//! use tensorflow::prelude::*;
+//! ```
+//!
+//! `Scope`, `Variable` and `ops` are only included with the
+//! `experimental_training` feature.  `Result` is deliberately left out, since
+//! it would shadow `std::result::Result`.
+
+pub use crate::DataType;
+pub use crate::Graph;
+pub use crate::Operation;
+pub use crate::Output;
+pub use crate::SavedModelBundle;
+pub use crate::Session;
+pub use crate::SessionOptions;
+pub use crate::SessionRunArgs;
+pub use crate::Shape;
+pub use crate::Status;
+pub use crate::Tensor;
+pub use crate::TensorType;
+
+#[cfg(feature = "experimental_training")]
+pub use crate::ops;
+#[cfg(feature = "experimental_training")]
+pub use crate::Scope;
+#[cfg(feature = "experimental_training")]
+pub use crate::Variable;
+
+////////////////////////
+
+#[cfg(all(test, feature = "experimental_training"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn build_and_run() -> Result<(), Status> {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::constant(&mut scope, 2.0f32)?;
+        let y: Output = ops::multiply(&mut scope, x.clone(), x)?.into();
+        let session = Session::new(&SessionOptions::new(), &scope.graph())?;
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&y.operation, y.index);
+        session.run(&mut args)?;
+        let result: Tensor<f32> = args.fetch(token)?;
+        assert_eq!(result[0], 4.0);
+        Ok(())
+    }
+}
@@ -0,0 +1,577 @@
+//! Minimal support for the protocol buffer wire format.
+//!
+//! The C API exchanges a number of configuration messages (`ConfigProto`,
+//! `ServerDef`, `RunOptions`, ...) as serialized protos.  Only the handful of
+//! fields we actually populate are encoded here, so this avoids pulling in a
+//! full protobuf implementation.
+//!
+//! Note that concatenating two serialized messages is equivalent to merging
+//! them, which is what allows independently built pieces of e.g. a
+//! `ConfigProto` to be combined.
+
+use crate::DataType;
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+use std::mem::size_of;
+
+const WIRE_TYPE_VARINT: u32 = 0;
+const WIRE_TYPE_FIXED64: u32 = 1;
+const WIRE_TYPE_LENGTH_DELIMITED: u32 = 2;
+const WIRE_TYPE_FIXED32: u32 = 5;
+
+/// The field numbers of `TensorProto`, `TensorShapeProto` and its `Dim`.
+pub(crate) const TENSOR_DTYPE: u32 = 1;
+pub(crate) const TENSOR_SHAPE: u32 = 2;
+pub(crate) const TENSOR_CONTENT: u32 = 4;
+pub(crate) const TENSOR_FLOAT_VAL: u32 = 5;
+pub(crate) const TENSOR_DOUBLE_VAL: u32 = 6;
+pub(crate) const TENSOR_INT_VAL: u32 = 7;
+pub(crate) const TENSOR_STRING_VAL: u32 = 8;
+pub(crate) const TENSOR_INT64_VAL: u32 = 10;
+pub(crate) const TENSOR_BOOL_VAL: u32 = 11;
+pub(crate) const SHAPE_DIM: u32 = 2;
+pub(crate) const SHAPE_UNKNOWN_RANK: u32 = 3;
+pub(crate) const DIM_SIZE: u32 = 1;
+
+/// Serializes fields of a single message.
+#[derive(Debug, Default, Clone)]
+pub(crate) struct ProtoWriter {
+    buf: Vec<u8>,
+}
+
+impl ProtoWriter {
+    pub(crate) fn new() -> Self {
+        Self::default()
+    }
+
+    fn write_varint(&mut self, mut value: u64) {
+        while value >= 0x80 {
+            self.buf.push((value as u8 & 0x7f) | 0x80);
+            value >>= 7;
+        }
+        self.buf.push(value as u8);
+    }
+
+    fn write_key(&mut self, field: u32, wire_type: u32) {
+        self.write_varint(u64::from(field << 3 | wire_type));
+    }
+
+    /// Writes an unsigned integer field (uint32, uint64).
+    pub(crate) fn uint_field(&mut self, field: u32, value: u64) -> &mut Self {
+        self.write_key(field, WIRE_TYPE_VARINT);
+        self.write_varint(value);
+        self
+    }
+
+    /// Writes a signed, non-zigzag integer field (int32, int64, enums).
+    pub(crate) fn int_field(&mut self, field: u32, value: i64) -> &mut Self {
+        self.uint_field(field, value as u64)
+    }
+
+    /// Writes a bool field.
+    pub(crate) fn bool_field(&mut self, field: u32, value: bool) -> &mut Self {
+        self.uint_field(field, value as u64)
+    }
+
+    /// Writes a float field.
+    pub(crate) fn float_field(&mut self, field: u32, value: f32) -> &mut Self {
+        self.write_key(field, WIRE_TYPE_FIXED32);
+        self.buf.extend_from_slice(&value.to_bits().to_le_bytes());
+        self
+    }
+
+    /// Writes a double field.
+    pub(crate) fn double_field(&mut self, field: u32, value: f64) -> &mut Self {
+        self.write_key(field, WIRE_TYPE_FIXED64);
+        self.buf.extend_from_slice(&value.to_bits().to_le_bytes());
+        self
+    }
+
+    /// Writes a bytes field.
+    pub(crate) fn bytes_field(&mut self, field: u32, value: &[u8]) -> &mut Self {
+        self.write_key(field, WIRE_TYPE_LENGTH_DELIMITED);
+        self.write_varint(value.len() as u64);
+        self.buf.extend_from_slice(value);
+        self
+    }
+
+    /// Writes a string field.
+    pub(crate) fn string_field(&mut self, field: u32, value: &str) -> &mut Self {
+        self.bytes_field(field, value.as_bytes())
+    }
+
+    /// Writes an embedded message field.
+    pub(crate) fn message_field(&mut self, field: u32, value: &ProtoWriter) -> &mut Self {
+        self.bytes_field(field, &value.buf)
+    }
+
+    /// Writes a field read by `ProtoReader`, e.g. to copy it unchanged.
+    pub(crate) fn value_field(&mut self, field: u32, value: &ProtoValue<'_>) -> &mut Self {
+        match *value {
+            ProtoValue::Varint(v) => self.uint_field(field, v),
+            ProtoValue::Fixed64(v) => {
+                self.write_key(field, WIRE_TYPE_FIXED64);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+                self
+            }
+            ProtoValue::LengthDelimited(v) => self.bytes_field(field, v),
+            ProtoValue::Fixed32(v) => {
+                self.write_key(field, WIRE_TYPE_FIXED32);
+                self.buf.extend_from_slice(&v.to_le_bytes());
+                self
+            }
+        }
+    }
+
+    /// Returns the serialized message.
+    pub(crate) fn into_bytes(self) -> Vec<u8> {
+        self.buf
+    }
+
+    /// Returns the serialized message.
+    pub(crate) fn as_bytes(&self) -> &[u8] {
+        &self.buf
+    }
+}
+
+/// The value of a single field read by `ProtoReader`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ProtoValue<'a> {
+    Varint(u64),
+    Fixed64(u64),
+    LengthDelimited(&'a [u8]),
+    Fixed32(u32),
+}
+
+impl<'a> ProtoValue<'a> {
+    /// Interprets the value as a signed, non-zigzag integer (int32, int64,
+    /// enums).
+    pub(crate) fn as_i64(&self) -> Result<i64> {
+        match self {
+            ProtoValue::Varint(v) => Ok(*v as i64),
+            _ => Err(invalid_arg!("Expected a varint field, found {:?}", self)),
+        }
+    }
+
+    /// Interprets the value as bytes or an embedded message.
+    pub(crate) fn as_bytes(&self) -> Result<&'a [u8]> {
+        match self {
+            ProtoValue::LengthDelimited(v) => Ok(v),
+            _ => Err(invalid_arg!(
+                "Expected a length-delimited field, found {:?}",
+                self
+            )),
+        }
+    }
+
+    /// Interprets the value as a string.
+    pub(crate) fn as_str(&self) -> Result<&'a str> {
+        Ok(std::str::from_utf8(self.as_bytes()?)?)
+    }
+
+    /// Interprets the value as one or more entries of a repeated integer
+    /// field, which may be packed.
+    pub(crate) fn as_varints(&self) -> Result<Vec<u64>> {
+        match self {
+            ProtoValue::Varint(v) => Ok(vec![*v]),
+            ProtoValue::LengthDelimited(packed) => {
+                let mut reader = ProtoReader::new(packed);
+                let mut values = Vec::new();
+                while !reader.buf.is_empty() {
+                    values.push(reader.read_varint()?);
+                }
+                Ok(values)
+            }
+            _ => Err(invalid_arg!(
+                "Expected a repeated varint field, found {:?}",
+                self
+            )),
+        }
+    }
+}
+
+/// Iterates over the `(field number, value)` pairs of a serialized message.
+#[derive(Debug, Clone)]
+pub(crate) struct ProtoReader<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> ProtoReader<'a> {
+    pub(crate) fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn read_varint(&mut self) -> Result<u64> {
+        let mut value = 0u64;
+        for (i, byte) in self.buf.iter().enumerate().take(10) {
+            value |= u64::from(byte & 0x7f) << (7 * i);
+            if byte & 0x80 == 0 {
+                self.buf = &self.buf[i + 1..];
+                return Ok(value);
+            }
+        }
+        Err(invalid_arg!("Truncated or overlong varint in proto"))
+    }
+
+    fn read_bytes(&mut self, len: usize) -> Result<&'a [u8]> {
+        if self.buf.len() < len {
+            return Err(invalid_arg!("Truncated field in proto"));
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    fn read_field(&mut self) -> Result<(u32, ProtoValue<'a>)> {
+        let key = self.read_varint()?;
+        let field = (key >> 3) as u32;
+        let value = match key as u32 & 0x7 {
+            WIRE_TYPE_VARINT => ProtoValue::Varint(self.read_varint()?),
+            WIRE_TYPE_FIXED64 => {
+                let mut bytes = [0u8; 8];
+                bytes.copy_from_slice(self.read_bytes(8)?);
+                ProtoValue::Fixed64(u64::from_le_bytes(bytes))
+            }
+            WIRE_TYPE_LENGTH_DELIMITED => {
+                let len = self.read_varint()? as usize;
+                ProtoValue::LengthDelimited(self.read_bytes(len)?)
+            }
+            WIRE_TYPE_FIXED32 => {
+                let mut bytes = [0u8; 4];
+                bytes.copy_from_slice(self.read_bytes(4)?);
+                ProtoValue::Fixed32(u32::from_le_bytes(bytes))
+            }
+            wire_type => return Err(invalid_arg!("Unsupported wire type {} in proto", wire_type)),
+        };
+        Ok((field, value))
+    }
+}
+
+impl<'a> Iterator for ProtoReader<'a> {
+    type Item = Result<(u32, ProtoValue<'a>)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            return None;
+        }
+        let field = self.read_field();
+        if field.is_err() {
+            // Don't keep returning errors for the rest of the buffer.
+            self.buf = &[];
+        }
+        Some(field)
+    }
+}
+
+/// The fields of a serialized `TensorProto`, with the values of each
+/// repeated field in their wire representation.
+#[derive(Debug, Default)]
+pub(crate) struct TensorProtoFields<'a> {
+    pub(crate) dtype: i64,
+    pub(crate) dims: Vec<u64>,
+    pub(crate) content: &'a [u8],
+    pub(crate) float_val: Vec<u32>,
+    pub(crate) double_val: Vec<u64>,
+    pub(crate) int_val: Vec<u64>,
+    pub(crate) int64_val: Vec<u64>,
+    pub(crate) bool_val: Vec<u64>,
+    pub(crate) string_val: Vec<&'a [u8]>,
+}
+
+impl<'a> TensorProtoFields<'a> {
+    /// Reads the fields of a serialized `TensorProto`, which must have a
+    /// known shape.  Malformed input, e.g. read from a file, is an error.
+    pub(crate) fn parse(tensor: &'a [u8]) -> Result<Self> {
+        let mut fields = Self::default();
+        for field in ProtoReader::new(tensor) {
+            match field? {
+                (TENSOR_DTYPE, value) => fields.dtype = value.as_i64()?,
+                (TENSOR_SHAPE, value) => {
+                    for field in ProtoReader::new(value.as_bytes()?) {
+                        match field? {
+                            (SHAPE_DIM, dim) => {
+                                for field in ProtoReader::new(dim.as_bytes()?) {
+                                    if let (DIM_SIZE, size) = field? {
+                                        let size = size.as_i64()?;
+                                        if size < 0 {
+                                            return Err(invalid_arg!(
+                                                "Tensor protos must have known shapes"
+                                            ));
+                                        }
+                                        fields.dims.push(size as u64);
+                                    }
+                                }
+                            }
+                            (SHAPE_UNKNOWN_RANK, unknown) if unknown.as_i64()? != 0 => {
+                                return Err(invalid_arg!("Tensor protos must have known shapes"));
+                            }
+                            _ => {}
+                        }
+                    }
+                }
+                (TENSOR_CONTENT, value) => fields.content = value.as_bytes()?,
+                (TENSOR_FLOAT_VAL, value) => {
+                    push_fixed(value, &mut fields.float_val, u32_from_le_bytes)?
+                }
+                (TENSOR_DOUBLE_VAL, value) => {
+                    push_fixed(value, &mut fields.double_val, u64_from_le_bytes)?
+                }
+                (TENSOR_INT_VAL, value) => fields.int_val.extend(value.as_varints()?),
+                (TENSOR_INT64_VAL, value) => fields.int64_val.extend(value.as_varints()?),
+                (TENSOR_BOOL_VAL, value) => fields.bool_val.extend(value.as_varints()?),
+                (TENSOR_STRING_VAL, value) => fields.string_val.push(value.as_bytes()?),
+                _ => {}
+            }
+        }
+        Ok(fields)
+    }
+
+    /// Returns the data type of the tensor.
+    pub(crate) fn data_type(&self) -> DataType {
+        DataType::from_int(self.dtype as _)
+    }
+
+    /// Builds a tensor from `tensor_content` if it's set, and otherwise from
+    /// `values`, the typed values of the tensor.
+    pub(crate) fn decode<T: TensorType>(
+        &self,
+        from_le_bytes: fn(&[u8]) -> T,
+        values: Vec<T>,
+    ) -> Result<Tensor<T>> {
+        let mut tensor = Tensor::new(&self.dims);
+        if !self.content.is_empty() {
+            let width = size_of::<T>();
+            if self.content.len() != width * tensor.len() {
+                return Err(invalid_arg!(
+                    "Tensor content has {} bytes but shape {:?}",
+                    self.content.len(),
+                    self.dims
+                ));
+            }
+            for (x, b) in tensor.iter_mut().zip(self.content.chunks(width)) {
+                *x = from_le_bytes(b);
+            }
+        } else if let Some(last) = values.last() {
+            if values.len() > tensor.len() {
+                return Err(invalid_arg!(
+                    "Tensor has {} values but shape {:?}",
+                    values.len(),
+                    self.dims
+                ));
+            }
+            // Trailing values equal to the last one may be omitted.
+            for (i, x) in tensor.iter_mut().enumerate() {
+                *x = values.get(i).unwrap_or(last).clone();
+            }
+        }
+        Ok(tensor)
+    }
+
+    /// Returns the float tensor, or an error if the tensor isn't float.
+    pub(crate) fn decode_f32(&self) -> Result<Tensor<f32>> {
+        if self.data_type() != DataType::Float {
+            return Err(invalid_arg!(
+                "Expected a float tensor, found {}",
+                self.data_type()
+            ));
+        }
+        self.decode(
+            |b| f32::from_bits(u32_from_le_bytes(b)),
+            self.float_val.iter().cloned().map(f32::from_bits).collect(),
+        )
+    }
+}
+
+/// Appends the fixed-width values of a repeated field, which may be packed.
+fn push_fixed<T>(
+    value: ProtoValue<'_>,
+    values: &mut Vec<T>,
+    from_le_bytes: fn(&[u8]) -> T,
+) -> Result<()> {
+    let width = size_of::<T>();
+    match value {
+        ProtoValue::Fixed32(v) if width == 4 => values.push(from_le_bytes(&v.to_le_bytes())),
+        ProtoValue::Fixed64(v) if width == 8 => values.push(from_le_bytes(&v.to_le_bytes())),
+        ProtoValue::LengthDelimited(packed) => {
+            let chunks = packed.chunks_exact(width);
+            if !chunks.remainder().is_empty() {
+                return Err(invalid_arg!(
+                    "Packed field of {} bytes doesn't hold a whole number of {}-byte values",
+                    packed.len(),
+                    width
+                ));
+            }
+            values.extend(chunks.map(from_le_bytes))
+        }
+        _ => {
+            return Err(invalid_arg!(
+                "Expected a {}-byte fixed-width field, found {:?}",
+                width,
+                value
+            ))
+        }
+    }
+    Ok(())
+}
+
+/// Reads a `u32` from the first 4 bytes of `b`.
+pub(crate) fn u32_from_le_bytes(b: &[u8]) -> u32 {
+    u32::from_le_bytes([b[0], b[1], b[2], b[3]])
+}
+
+/// Reads a `u64` from the first 8 bytes of `b`.
+pub(crate) fn u64_from_le_bytes(b: &[u8]) -> u64 {
+    u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn varint() {
+        let mut w = ProtoWriter::new();
+        w.uint_field(1, 150);
+        assert_eq!(w.into_bytes(), vec![0x08, 0x96, 0x01]);
+    }
+
+    #[test]
+    fn negative_int() {
+        let mut w = ProtoWriter::new();
+        w.int_field(1, -1);
+        assert_eq!(
+            w.into_bytes(),
+            vec![0x08, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01]
+        );
+    }
+
+    #[test]
+    fn floats() {
+        let mut w = ProtoWriter::new();
+        w.float_field(1, 1.0)
+            .double_field(2, 1.0)
+            .bool_field(3, true);
+        assert_eq!(
+            w.into_bytes(),
+            vec![
+                0x0d, 0x00, 0x00, 0x80, 0x3f, 0x11, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0xf0, 0x3f,
+                0x18, 0x01
+            ]
+        );
+    }
+
+    #[test]
+    fn nested() {
+        let mut inner = ProtoWriter::new();
+        inner.string_field(1, "ab");
+        let mut outer = ProtoWriter::new();
+        outer.message_field(3, &inner).uint_field(4, 1);
+        assert_eq!(
+            outer.into_bytes(),
+            vec![0x1a, 0x04, 0x0a, 0x02, b'a', b'b', 0x20, 0x01]
+        );
+    }
+
+    #[test]
+    fn read() {
+        let mut inner = ProtoWriter::new();
+        inner.string_field(1, "ab");
+        let mut outer = ProtoWriter::new();
+        outer
+            .message_field(3, &inner)
+            .uint_field(4, 300)
+            .int_field(5, -2);
+        let bytes = outer.into_bytes();
+        let fields: Vec<_> = ProtoReader::new(&bytes).collect::<Result<_>>().unwrap();
+        assert_eq!(fields.len(), 3);
+        assert_eq!(fields[0].0, 3);
+        let (field, value) = ProtoReader::new(fields[0].1.as_bytes().unwrap())
+            .next()
+            .unwrap()
+            .unwrap();
+        assert_eq!((field, value.as_str().unwrap()), (1, "ab"));
+        assert_eq!(fields[1], (4, ProtoValue::Varint(300)));
+        assert_eq!(fields[2].1.as_i64().unwrap(), -2);
+        assert!(fields[2].1.as_bytes().is_err());
+    }
+
+    #[test]
+    fn read_truncated() {
+        let fields: Vec<_> = ProtoReader::new(&[0x0a, 0x05, b'a']).collect();
+        assert_eq!(fields.len(), 1);
+        assert!(fields[0].is_err());
+    }
+
+    #[test]
+    fn copy_fields() {
+        let mut original = ProtoWriter::new();
+        original
+            .uint_field(1, 7)
+            .double_field(2, 0.5)
+            .string_field(3, "x")
+            .float_field(4, 1.5);
+        let mut copy = ProtoWriter::new();
+        for field in ProtoReader::new(original.as_bytes()) {
+            let (field, value) = field.unwrap();
+            copy.value_field(field, &value);
+        }
+        assert_eq!(copy.into_bytes(), original.into_bytes());
+    }
+
+    #[test]
+    fn packed_varints() {
+        assert_eq!(ProtoValue::Varint(3).as_varints().unwrap(), vec![3]);
+        assert_eq!(
+            ProtoValue::LengthDelimited(&[0x01, 0x96, 0x01])
+                .as_varints()
+                .unwrap(),
+            vec![1, 150]
+        );
+        assert!(ProtoValue::LengthDelimited(&[0x96]).as_varints().is_err());
+    }
+
+    fn float_tensor_proto(dims: &[i64]) -> ProtoWriter {
+        let mut shape = ProtoWriter::new();
+        for &d in dims {
+            let mut dim = ProtoWriter::new();
+            dim.int_field(DIM_SIZE, d);
+            shape.message_field(SHAPE_DIM, &dim);
+        }
+        let mut tensor = ProtoWriter::new();
+        tensor
+            .int_field(TENSOR_DTYPE, i64::from(DataType::Float.to_int()))
+            .message_field(TENSOR_SHAPE, &shape);
+        tensor
+    }
+
+    #[test]
+    fn tensor_proto() {
+        let mut tensor = float_tensor_proto(&[3]);
+        tensor.float_field(TENSOR_FLOAT_VAL, 1.5).bytes_field(
+            TENSOR_FLOAT_VAL,
+            &[2.0f32.to_le_bytes(), 3.0f32.to_le_bytes()].concat(),
+        );
+        let fields = TensorProtoFields::parse(tensor.as_bytes()).unwrap();
+        assert_eq!(fields.data_type(), DataType::Float);
+        assert_eq!(fields.dims, vec![3]);
+        assert_eq!(&fields.decode_f32().unwrap()[..], &[1.5, 2.0, 3.0]);
+
+        // Packed values must be whole, and have the field's width.
+        let mut tensor = float_tensor_proto(&[1]);
+        tensor.bytes_field(TENSOR_FLOAT_VAL, &[0, 0, 0x80, 0x3f, 0]);
+        assert!(TensorProtoFields::parse(tensor.as_bytes()).is_err());
+        let mut tensor = float_tensor_proto(&[1]);
+        tensor.double_field(TENSOR_FLOAT_VAL, 1.0);
+        assert!(TensorProtoFields::parse(tensor.as_bytes()).is_err());
+        let mut tensor = float_tensor_proto(&[1]);
+        tensor.float_field(TENSOR_DOUBLE_VAL, 1.0);
+        assert!(TensorProtoFields::parse(tensor.as_bytes()).is_err());
+
+        let mut tensor = float_tensor_proto(&[2]);
+        tensor.bytes_field(TENSOR_CONTENT, &[0; 4]);
+        let fields = TensorProtoFields::parse(tensor.as_bytes()).unwrap();
+        assert!(fields.decode_f32().is_err());
+        assert!(TensorProtoFields::parse(float_tensor_proto(&[-1]).as_bytes()).is_err());
+    }
+}
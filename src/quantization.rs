@@ -0,0 +1,829 @@
+use crate::protos::ProtoReader;
+use crate::protos::ProtoValue;
+use crate::protos::ProtoWriter;
+use crate::protos::TensorProtoFields;
+use crate::protos::DIM_SIZE;
+use crate::protos::SHAPE_DIM;
+use crate::protos::TENSOR_CONTENT;
+use crate::protos::TENSOR_DTYPE;
+use crate::protos::TENSOR_SHAPE;
+use crate::DataType;
+use crate::Graph;
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Tensor;
+use crate::TensorType;
+use std::collections::BTreeMap;
+
+/// The field numbers of the messages read and written here.
+const GRAPH_DEF_NODE: u32 = 1;
+const NODE_DEF_NAME: u32 = 1;
+const NODE_DEF_OP: u32 = 2;
+const NODE_DEF_INPUT: u32 = 3;
+const NODE_DEF_DEVICE: u32 = 4;
+const NODE_DEF_ATTR: u32 = 5;
+const ATTR_ENTRY_KEY: u32 = 1;
+const ATTR_ENTRY_VALUE: u32 = 2;
+const ATTR_VALUE_S: u32 = 2;
+const ATTR_VALUE_I: u32 = 3;
+const ATTR_VALUE_B: u32 = 5;
+const ATTR_VALUE_TYPE: u32 = 6;
+const ATTR_VALUE_TENSOR: u32 = 8;
+
+/// The range of values of a tensor, either as a whole or for each slice
+/// (channel) along an axis.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QuantizationRange {
+    axis: Option<usize>,
+    min: Vec<f32>,
+    max: Vec<f32>,
+}
+
+impl QuantizationRange {
+    /// Returns the range of all elements of `tensor`.  The range always
+    /// includes zero, which must be exactly representable after
+    /// quantization.
+    pub fn per_tensor(tensor: &Tensor<f32>) -> Self {
+        let (min, max) = tensor
+            .iter()
+            .fold((0.0f32, 0.0f32), |(min, max), &x| (min.min(x), max.max(x)));
+        Self {
+            axis: None,
+            min: vec![min],
+            max: vec![max],
+        }
+    }
+
+    /// Returns the range of each slice of `tensor` along `axis`, e.g. of each
+    /// output channel of a convolution's filter.
+    pub fn per_channel(tensor: &Tensor<f32>, axis: usize) -> Result<Self> {
+        let dims = tensor.dims();
+        if axis >= dims.len() {
+            return Err(invalid_arg!(
+                "Axis {} is out of range for a tensor of shape {:?}",
+                axis,
+                dims
+            ));
+        }
+        let channels = dims[axis] as usize;
+        let inner = dims[axis + 1..].iter().product::<u64>() as usize;
+        let mut min = vec![0.0f32; channels];
+        let mut max = vec![0.0f32; channels];
+        for (i, &x) in tensor.iter().enumerate() {
+            let c = (i / inner) % channels;
+            min[c] = min[c].min(x);
+            max[c] = max[c].max(x);
+        }
+        Ok(Self {
+            axis: Some(axis),
+            min,
+            max,
+        })
+    }
+
+    /// Returns the axis of the channels, or `None` for a per-tensor range.
+    pub fn axis(&self) -> Option<usize> {
+        self.axis
+    }
+
+    /// Returns the minimum of each channel, or of the whole tensor.
+    pub fn min(&self) -> &[f32] {
+        &self.min
+    }
+
+    /// Returns the maximum of each channel, or of the whole tensor.
+    pub fn max(&self) -> &[f32] {
+        &self.max
+    }
+
+    /// Widens this range to include `other`, which must have the same axis
+    /// and number of channels.
+    pub fn merge(&mut self, other: &QuantizationRange) -> Result<()> {
+        if self.axis != other.axis || self.min.len() != other.min.len() {
+            return Err(invalid_arg!(
+                "Can't merge ranges with axes {:?} and {:?} and {} and {} channels",
+                self.axis,
+                other.axis,
+                self.min.len(),
+                other.min.len()
+            ));
+        }
+        for (a, b) in self.min.iter_mut().zip(&other.min) {
+            *a = a.min(*b);
+        }
+        for (a, b) in self.max.iter_mut().zip(&other.max) {
+            *a = a.max(*b);
+        }
+        Ok(())
+    }
+
+    /// Returns the largest magnitude of each channel, for symmetric
+    /// quantization.
+    fn magnitudes(&self) -> Vec<f32> {
+        self.min
+            .iter()
+            .zip(&self.max)
+            .map(|(min, max)| min.abs().max(max.abs()))
+            .collect()
+    }
+}
+
+/// Collects the ranges of activations over calibration runs, for quantizing
+/// them with `quantize_graph_def`.
+///
+/// ```no_run
+/// # use tensorflow::{Calibrator, Graph, Session, Tensor};
+/// # fn f(graph: &Graph, session: &Session, batches: &[Tensor<f32>]) -> tensorflow::Result<()> {
+/// let mut calibrator = Calibrator::new()
+///     .with_tensor("dense/Relu")
+///     .with_per_channel_tensor("conv/Relu", 3);
+/// for batch in batches {
+///     calibrator.calibrate(graph, session, &[("input", batch)])?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Calibration batches should be representative of the data the model will
+/// run on, since values outside the collected ranges are clipped.
+#[derive(Debug, Clone, Default)]
+pub struct Calibrator {
+    tensors: Vec<(String, Option<usize>)>,
+    ranges: BTreeMap<String, QuantizationRange>,
+}
+
+impl Calibrator {
+    /// Creates a calibrator which collects no ranges.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Collects the range of the float tensor named `name`, which is either
+    /// `"op:index"` or just `"op"` for output 0 of the op.
+    pub fn with_tensor(mut self, name: &str) -> Self {
+        self.tensors.push((tensor_name(name), None));
+        self
+    }
+
+    /// Collects the range of each channel along `axis` of the float tensor
+    /// named `name`.  Quantizing with per-channel ranges requires TensorFlow
+    /// 2.3 or later, whose `QuantizeAndDequantizeV2` op has the `axis`
+    /// attribute.
+    pub fn with_per_channel_tensor(mut self, name: &str, axis: usize) -> Self {
+        self.tensors.push((tensor_name(name), Some(axis)));
+        self
+    }
+
+    /// Runs `session` with each tensor in `inputs` fed to the tensor with
+    /// the paired name, and widens the ranges to include the values of the
+    /// collected tensors.
+    pub fn calibrate<T: TensorType>(
+        &mut self,
+        graph: &Graph,
+        session: &Session,
+        inputs: &[(&str, &Tensor<T>)],
+    ) -> Result<()> {
+        let mut args = SessionRunArgs::new();
+        for (name, tensor) in inputs {
+            let input = graph.output_by_name_required(name)?;
+            args.add_feed(&input.operation, input.index, tensor);
+        }
+        let mut tokens = Vec::with_capacity(self.tensors.len());
+        for (name, _) in &self.tensors {
+            let output = graph.output_by_name_required(name)?;
+            tokens.push(args.request_fetch(&output.operation, output.index));
+        }
+        session.run(&mut args)?;
+        let tensors = self.tensors.clone();
+        for ((name, _), token) in tensors.iter().zip(tokens) {
+            self.observe(name, &args.fetch(token)?)?;
+        }
+        Ok(())
+    }
+
+    /// Widens the range of the tensor named `name` to include `values`, for
+    /// collecting ranges from runs made elsewhere.
+    pub fn observe(&mut self, name: &str, values: &Tensor<f32>) -> Result<()> {
+        let name = tensor_name(name);
+        let axis = match self.tensors.iter().find(|(n, _)| *n == name) {
+            Some(&(_, axis)) => axis,
+            None => return Err(invalid_arg!("Tensor {} is not calibrated", name)),
+        };
+        let range = match axis {
+            Some(axis) => QuantizationRange::per_channel(values, axis)?,
+            None => QuantizationRange::per_tensor(values),
+        };
+        match self.ranges.get_mut(&name) {
+            Some(existing) => existing.merge(&range)?,
+            None => {
+                self.ranges.insert(name, range);
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the collected ranges by tensor name, in the form `"op:index"`.
+    pub fn ranges(&self) -> &BTreeMap<String, QuantizationRange> {
+        &self.ranges
+    }
+
+    /// Returns the collected range of the tensor named `name`.
+    pub fn range(&self, name: &str) -> Option<&QuantizationRange> {
+        self.ranges.get(&tensor_name(name))
+    }
+}
+
+/// Returns `name` in the form `"op:index"`.
+fn tensor_name(name: &str) -> String {
+    if name.contains(':') {
+        name.to_string()
+    } else {
+        format!("{}:0", name)
+    }
+}
+
+/// Options for `quantize_graph_def`.
+#[derive(Debug, Clone)]
+pub struct QuantizeOptions<'a> {
+    min_weight_elements: usize,
+    per_channel_weights: bool,
+    activations: Option<&'a Calibrator>,
+}
+
+impl<'a> Default for QuantizeOptions<'a> {
+    fn default() -> Self {
+        Self {
+            min_weight_elements: 1024,
+            per_channel_weights: false,
+            activations: None,
+        }
+    }
+}
+
+impl<'a> QuantizeOptions<'a> {
+    /// Sets the number of elements a float constant must have to be
+    /// quantized.  Smaller constants, such as biases and hyperparameters,
+    /// are kept as floats.  Default is 1024.
+    pub fn with_min_weight_elements(self, min_weight_elements: usize) -> Self {
+        Self {
+            min_weight_elements,
+            ..self
+        }
+    }
+
+    /// Quantizes each slice of a weight along its last dimension, i.e. each
+    /// output channel of a dense or convolution layer, with its own scale.
+    /// This is more accurate than one scale per weight.  Requires TensorFlow
+    /// 2.3 or later, whose `Dequantize` op has the `axis` attribute.
+    pub fn with_per_channel_weights(self, per_channel_weights: bool) -> Self {
+        Self {
+            per_channel_weights,
+            ..self
+        }
+    }
+
+    /// Quantizes the activations whose ranges `calibrator` collected.
+    pub fn with_activation_ranges(self, calibrator: &'a Calibrator) -> Self {
+        Self {
+            activations: Some(calibrator),
+            ..self
+        }
+    }
+}
+
+/// Quantizes a frozen graph to 8 bits, returning the rewritten graph.
+///
+/// Each float constant with at least `min_weight_elements` elements is
+/// stored as symmetric `qint8` values and converted back to floats by a
+/// `Dequantize` op with the constant's name, so weights take a quarter of
+/// the space.  Each activation with a calibrated range is passed through a
+/// `QuantizeAndDequantizeV2` op with that range before it is used, which
+/// simulates 8-bit inference and records the range for converters such as
+/// TensorFlow Lite's, which produce fully quantized models.
+///
+/// ```no_run
+/// # use tensorflow::{quantize_graph_def, Calibrator, QuantizeOptions};
+/// # let graph_def: Vec<u8> = vec![];
+/// # let calibrator = Calibrator::new();
+/// let quantized = quantize_graph_def(
+///     &graph_def,
+///     &QuantizeOptions::default()
+///         .with_per_channel_weights(true)
+///         .with_activation_ranges(&calibrator),
+/// )?;
+/// std::fs::write("model_quantized.pb", quantized)?;
+/// # Ok::<(), Box<dyn std::error::Error>>(())
+/// ```
+pub fn quantize_graph_def(graph_def: &[u8], options: &QuantizeOptions<'_>) -> Result<Vec<u8>> {
+    let empty = BTreeMap::new();
+    let activations = options.activations.map(|c| c.ranges()).unwrap_or(&empty);
+    let renames: BTreeMap<&str, String> = activations
+        .keys()
+        .map(|name| (name.as_str(), quantized_activation_name(name)))
+        .collect();
+    let mut writer = ProtoWriter::new();
+    for field in ProtoReader::new(graph_def) {
+        let (number, value) = field?;
+        if number != GRAPH_DEF_NODE {
+            writer.value_field(number, &value);
+            continue;
+        }
+        let node = Node::parse(value.as_bytes()?)?;
+        let node_def = node.rewire(&renames);
+        match quantize_weight(&node, options)? {
+            Some(nodes) => {
+                for n in nodes {
+                    writer.bytes_field(GRAPH_DEF_NODE, &n);
+                }
+            }
+            None => {
+                writer.bytes_field(GRAPH_DEF_NODE, &node_def);
+            }
+        }
+    }
+    for (name, range) in activations {
+        for n in quantize_activation(name, range) {
+            writer.bytes_field(GRAPH_DEF_NODE, &n);
+        }
+    }
+    Ok(writer.into_bytes())
+}
+
+/// Returns the name of the op which quantizes the activation `name`, which
+/// has the form `"op:index"`.
+fn quantized_activation_name(name: &str) -> String {
+    match name.rfind(':') {
+        Some(colon) if &name[colon + 1..] != "0" => format!(
+            "{}/quantize_and_dequantize_{}",
+            &name[..colon],
+            &name[colon + 1..]
+        ),
+        Some(colon) => format!("{}/quantize_and_dequantize", &name[..colon]),
+        None => format!("{}/quantize_and_dequantize", name),
+    }
+}
+
+/// The fields of a `NodeDef` which are inspected here.
+#[derive(Debug)]
+struct Node<'a> {
+    bytes: &'a [u8],
+    name: &'a str,
+    op: &'a str,
+    device: &'a str,
+    attrs: Vec<(&'a str, &'a [u8])>,
+}
+
+impl<'a> Node<'a> {
+    fn parse(bytes: &'a [u8]) -> Result<Self> {
+        let mut node = Node {
+            bytes,
+            name: "",
+            op: "",
+            device: "",
+            attrs: Vec::new(),
+        };
+        for field in ProtoReader::new(bytes) {
+            match field? {
+                (NODE_DEF_NAME, value) => node.name = value.as_str()?,
+                (NODE_DEF_OP, value) => node.op = value.as_str()?,
+                (NODE_DEF_DEVICE, value) => node.device = value.as_str()?,
+                (NODE_DEF_ATTR, value) => {
+                    let mut key = "";
+                    let mut attr: &[u8] = &[];
+                    for field in ProtoReader::new(value.as_bytes()?) {
+                        match field? {
+                            (ATTR_ENTRY_KEY, value) => key = value.as_str()?,
+                            (ATTR_ENTRY_VALUE, value) => attr = value.as_bytes()?,
+                            _ => {}
+                        }
+                    }
+                    node.attrs.push((key, attr));
+                }
+                _ => {}
+            }
+        }
+        Ok(node)
+    }
+
+    fn attr(&self, key: &str) -> Option<&'a [u8]> {
+        self.attrs.iter().find(|(k, _)| *k == key).map(|&(_, v)| v)
+    }
+
+    /// Returns the `NodeDef` with inputs which are keys of `renames` replaced
+    /// by the paired ops.  Control inputs are kept.
+    fn rewire(&self, renames: &BTreeMap<&str, String>) -> Vec<u8> {
+        let mut writer = ProtoWriter::new();
+        for field in ProtoReader::new(self.bytes) {
+            // The node was already parsed, so reading it again can't fail.
+            let (number, value) = field.unwrap();
+            let input = match (number, value) {
+                (NODE_DEF_INPUT, ProtoValue::LengthDelimited(input)) => {
+                    std::str::from_utf8(input).ok()
+                }
+                _ => None,
+            };
+            match input.and_then(|i| renames.get(tensor_name(i).as_str())) {
+                Some(renamed) if !input.unwrap().starts_with('^') => {
+                    writer.string_field(NODE_DEF_INPUT, renamed);
+                }
+                _ => {
+                    writer.value_field(number, &value);
+                }
+            }
+        }
+        writer.into_bytes()
+    }
+}
+
+/// Returns the nodes which replace `node` if it's a float constant which
+/// should be quantized.
+fn quantize_weight(node: &Node<'_>, options: &QuantizeOptions<'_>) -> Result<Option<Vec<Vec<u8>>>> {
+    if node.op != "Const" {
+        return Ok(None);
+    }
+    let tensor = match node.attr("value") {
+        Some(attr) => match attr_tensor(attr)? {
+            Some(tensor) => tensor,
+            None => return Ok(None),
+        },
+        None => return Ok(None),
+    };
+    let weight = match decode_float_tensor(tensor)? {
+        Some(weight) => weight,
+        None => return Ok(None),
+    };
+    if weight.len() < options.min_weight_elements {
+        return Ok(None);
+    }
+    let rank = weight.dims().len();
+    let range = if options.per_channel_weights && rank >= 2 {
+        QuantizationRange::per_channel(&weight, rank - 1)?
+    } else {
+        QuantizationRange::per_tensor(&weight)
+    };
+    let magnitudes = range.magnitudes();
+    let inner = match range.axis() {
+        Some(_) => 1,
+        None => weight.len(),
+    };
+    let quantized: Vec<u8> = weight
+        .iter()
+        .enumerate()
+        .map(|(i, &x)| {
+            let m = magnitudes[(i / inner) % magnitudes.len()];
+            let q = if m > 0.0 {
+                (x / m * 127.0).round()
+            } else {
+                0.0
+            };
+            q.clamp(-127.0, 127.0) as i8 as u8
+        })
+        .collect();
+    let min: Vec<f32> = magnitudes.iter().map(|m| -m).collect();
+    let quantized_name = format!("{}/quantized", node.name);
+    let min_name = format!("{}/min", node.name);
+    let max_name = format!("{}/max", node.name);
+    let range_dims: Vec<u64> = match range.axis() {
+        Some(_) => vec![magnitudes.len() as u64],
+        None => vec![],
+    };
+    let mut attrs = vec![
+        ("T", type_attr(DataType::QInt8)),
+        ("mode", string_attr("SCALED")),
+    ];
+    // Older releases don't have these attributes, but their SCALED mode
+    // already uses the narrow range for per-tensor ranges.
+    if let Some(axis) = range.axis() {
+        attrs.push(("narrow_range", bool_attr(true)));
+        attrs.push(("axis", int_attr(axis as i64)));
+    }
+    Ok(Some(vec![
+        const_node(
+            &quantized_name,
+            node.device,
+            tensor_proto(DataType::QInt8, weight.dims(), &quantized),
+        ),
+        const_node(&min_name, node.device, float_tensor(&range_dims, &min)),
+        const_node(
+            &max_name,
+            node.device,
+            float_tensor(&range_dims, &magnitudes),
+        ),
+        node_def(
+            node.name,
+            "Dequantize",
+            &[&quantized_name, &min_name, &max_name],
+            node.device,
+            &attrs,
+        ),
+    ]))
+}
+
+/// Returns the nodes which quantize the activation `name` with `range`.
+fn quantize_activation(name: &str, range: &QuantizationRange) -> Vec<Vec<u8>> {
+    let op = quantized_activation_name(name);
+    let min_name = format!("{}/input_min", op);
+    let max_name = format!("{}/input_max", op);
+    let range_dims: Vec<u64> = match range.axis() {
+        Some(_) => vec![range.min().len() as u64],
+        None => vec![],
+    };
+    let signed = range.min().iter().any(|&m| m < 0.0);
+    let mut attrs = vec![
+        ("T", type_attr(DataType::Float)),
+        ("signed_input", bool_attr(signed)),
+        ("num_bits", int_attr(8)),
+        ("range_given", bool_attr(true)),
+    ];
+    if let Some(axis) = range.axis() {
+        attrs.push(("axis", int_attr(axis as i64)));
+    }
+    vec![
+        const_node(&min_name, "", float_tensor(&range_dims, range.min())),
+        const_node(&max_name, "", float_tensor(&range_dims, range.max())),
+        node_def(
+            &op,
+            "QuantizeAndDequantizeV2",
+            &[name, &min_name, &max_name],
+            "",
+            &attrs,
+        ),
+    ]
+}
+
+/// Returns the `TensorProto` of an `AttrValue`, if it holds one.
+fn attr_tensor(attr: &[u8]) -> Result<Option<&[u8]>> {
+    for field in ProtoReader::new(attr) {
+        if let (ATTR_VALUE_TENSOR, value) = field? {
+            return Ok(Some(value.as_bytes()?));
+        }
+    }
+    Ok(None)
+}
+
+/// Decodes a `TensorProto` if it holds floats.
+fn decode_float_tensor(tensor: &[u8]) -> Result<Option<Tensor<f32>>> {
+    let fields = TensorProtoFields::parse(tensor)?;
+    if fields.data_type() != DataType::Float {
+        return Ok(None);
+    }
+    Ok(Some(fields.decode_f32()?))
+}
+
+fn tensor_proto(dtype: DataType, dims: &[u64], content: &[u8]) -> ProtoWriter {
+    let mut shape = ProtoWriter::new();
+    for &d in dims {
+        let mut dim = ProtoWriter::new();
+        dim.int_field(DIM_SIZE, d as i64);
+        shape.message_field(SHAPE_DIM, &dim);
+    }
+    let mut tensor = ProtoWriter::new();
+    tensor
+        .int_field(TENSOR_DTYPE, i64::from(dtype.to_int()))
+        .message_field(TENSOR_SHAPE, &shape)
+        .bytes_field(TENSOR_CONTENT, content);
+    tensor
+}
+
+fn float_tensor(dims: &[u64], values: &[f32]) -> ProtoWriter {
+    let content: Vec<u8> = values
+        .iter()
+        .flat_map(|v| v.to_le_bytes().to_vec())
+        .collect();
+    tensor_proto(DataType::Float, dims, &content)
+}
+
+fn type_attr(dtype: DataType) -> ProtoWriter {
+    let mut attr = ProtoWriter::new();
+    attr.int_field(ATTR_VALUE_TYPE, i64::from(dtype.to_int()));
+    attr
+}
+
+fn string_attr(value: &str) -> ProtoWriter {
+    let mut attr = ProtoWriter::new();
+    attr.string_field(ATTR_VALUE_S, value);
+    attr
+}
+
+fn int_attr(value: i64) -> ProtoWriter {
+    let mut attr = ProtoWriter::new();
+    attr.int_field(ATTR_VALUE_I, value);
+    attr
+}
+
+fn bool_attr(value: bool) -> ProtoWriter {
+    let mut attr = ProtoWriter::new();
+    attr.bool_field(ATTR_VALUE_B, value);
+    attr
+}
+
+fn const_node(name: &str, device: &str, tensor: ProtoWriter) -> Vec<u8> {
+    let dtype = match ProtoReader::new(tensor.as_bytes()).next() {
+        Some(Ok((TENSOR_DTYPE, ProtoValue::Varint(dtype)))) => dtype as i64,
+        _ => unreachable!("tensor_proto writes the dtype first"),
+    };
+    let mut dtype_attr = ProtoWriter::new();
+    dtype_attr.int_field(ATTR_VALUE_TYPE, dtype);
+    let mut value_attr = ProtoWriter::new();
+    value_attr.message_field(ATTR_VALUE_TENSOR, &tensor);
+    node_def(
+        name,
+        "Const",
+        &[],
+        device,
+        &[("dtype", dtype_attr), ("value", value_attr)],
+    )
+}
+
+fn node_def(
+    name: &str,
+    op: &str,
+    inputs: &[&str],
+    device: &str,
+    attrs: &[(&str, ProtoWriter)],
+) -> Vec<u8> {
+    let mut node = ProtoWriter::new();
+    node.string_field(NODE_DEF_NAME, name)
+        .string_field(NODE_DEF_OP, op);
+    for input in inputs {
+        node.string_field(NODE_DEF_INPUT, input);
+    }
+    if !device.is_empty() {
+        node.string_field(NODE_DEF_DEVICE, device);
+    }
+    for (key, value) in attrs {
+        let mut entry = ProtoWriter::new();
+        entry
+            .string_field(ATTR_ENTRY_KEY, key)
+            .message_field(ATTR_ENTRY_VALUE, value);
+        node.message_field(NODE_DEF_ATTR, &entry);
+    }
+    node.into_bytes()
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::feed::run_by_name;
+    use crate::protos::TENSOR_FLOAT_VAL;
+    use crate::ImportGraphDefOptions;
+    use crate::Operation;
+    use crate::SessionOptions;
+    use crate::Shape;
+
+    #[test]
+    fn ranges() {
+        let t = Tensor::new(&[2, 3])
+            .with_values(&[1.0f32, -2.0, 3.0, 4.0, 0.5, -6.0])
+            .unwrap();
+        let range = QuantizationRange::per_tensor(&t);
+        assert_eq!(range.axis(), None);
+        assert_eq!((range.min(), range.max()), (&[-6.0][..], &[4.0][..]));
+        let mut range = QuantizationRange::per_channel(&t, 1).unwrap();
+        assert_eq!(range.min(), &[0.0, -2.0, -6.0]);
+        assert_eq!(range.max(), &[4.0, 0.5, 3.0]);
+        assert_eq!(range.magnitudes(), vec![4.0, 2.0, 6.0]);
+        let rows = QuantizationRange::per_channel(&t, 0).unwrap();
+        assert_eq!(rows.max(), &[3.0, 4.0]);
+        assert!(range.merge(&rows).is_err());
+        let other = Tensor::new(&[1, 3])
+            .with_values(&[-1.0f32, 1.0, 7.0])
+            .unwrap();
+        range
+            .merge(&QuantizationRange::per_channel(&other, 1).unwrap())
+            .unwrap();
+        assert_eq!(range.min(), &[-1.0, -2.0, -6.0]);
+        assert_eq!(range.max(), &[4.0, 1.0, 7.0]);
+        assert!(QuantizationRange::per_channel(&t, 2).is_err());
+    }
+
+    fn placeholder(graph: &mut Graph, name: &str) -> Operation {
+        let mut nd = graph.new_operation("Placeholder", name).unwrap();
+        nd.set_attr_type("dtype", DataType::Float).unwrap();
+        nd.set_attr_shape("shape", &Shape::from(None)).unwrap();
+        nd.finish().unwrap()
+    }
+
+    fn run(graph: &Graph, x: &Tensor<f32>, output: &str) -> Tensor<f32> {
+        let session = Session::new(&SessionOptions::new(), graph).unwrap();
+        let find = |name: &str| graph.output_by_name_required(name);
+        run_by_name(&session, &[("x", x)], output, find, find).unwrap()
+    }
+
+    fn import(graph_def: &[u8]) -> Graph {
+        let mut graph = Graph::new();
+        graph
+            .import_graph_def(graph_def, &ImportGraphDefOptions::new())
+            .unwrap();
+        graph
+    }
+
+    #[test]
+    fn decode_float_tensors() {
+        let content = [1.5f32.to_le_bytes(), 2.5f32.to_le_bytes()].concat();
+        let tensor = tensor_proto(DataType::Float, &[2], &content);
+        let decoded = decode_float_tensor(tensor.as_bytes()).unwrap().unwrap();
+        assert_eq!(&decoded[..], &[1.5, 2.5]);
+        let ints = tensor_proto(DataType::Int32, &[2], &[0; 8]);
+        assert!(decode_float_tensor(ints.as_bytes()).unwrap().is_none());
+
+        // A malformed tensor is an error rather than a panic.
+        let mut tensor = tensor_proto(DataType::Float, &[1], &[]);
+        tensor.bytes_field(TENSOR_FLOAT_VAL, &[0, 0, 0x80]);
+        assert!(decode_float_tensor(tensor.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn quantize_weights() {
+        let mut graph = Graph::new();
+        let x = placeholder(&mut graph, "x");
+        let w = {
+            let mut nd = graph.new_operation("Const", "w").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            let value = Tensor::new(&[2, 2])
+                .with_values(&[1.0f32, -0.5, 0.25, 2.0])
+                .unwrap();
+            nd.set_attr_tensor("value", value).unwrap();
+            nd.finish().unwrap()
+        };
+        let mut nd = graph.new_operation("MatMul", "y").unwrap();
+        nd.add_input(x);
+        nd.add_input(w);
+        nd.finish().unwrap();
+        let graph_def = graph.graph_def().unwrap();
+
+        let unchanged =
+            import(&quantize_graph_def(&graph_def, &QuantizeOptions::default()).unwrap());
+        assert_eq!(
+            unchanged
+                .operation_by_name_required("w")
+                .unwrap()
+                .op_type()
+                .unwrap(),
+            "Const"
+        );
+        let x = Tensor::new(&[1, 2]).with_values(&[1.0f32, 1.0]).unwrap();
+        let options = QuantizeOptions::default().with_min_weight_elements(4);
+        let per_tensor = quantize_graph_def(&graph_def, &options).unwrap();
+        let quantized = import(&per_tensor);
+        assert_eq!(
+            quantized
+                .operation_by_name_required("w")
+                .unwrap()
+                .op_type()
+                .unwrap(),
+            "Dequantize"
+        );
+        let y = run(&quantized, &x, "y");
+        assert!((y[0] - 1.25).abs() < 0.02, "y = {:?}", &y[..]);
+        assert!((y[1] - 1.5).abs() < 0.02, "y = {:?}", &y[..]);
+
+        // Only per-channel weights set the attributes which older releases
+        // lack, so they're checked without importing the graph.
+        let has_axis = |graph_def: &[u8]| graph_def.windows(4).any(|w| w == b"axis");
+        assert!(!has_axis(&per_tensor));
+        let per_channel =
+            quantize_graph_def(&graph_def, &options.with_per_channel_weights(true)).unwrap();
+        assert!(has_axis(&per_channel));
+    }
+
+    #[test]
+    fn quantize_activations() {
+        let mut graph = Graph::new();
+        let x = placeholder(&mut graph, "x");
+        let relu = {
+            let mut nd = graph.new_operation("Relu", "relu").unwrap();
+            nd.add_input(x);
+            nd.finish().unwrap()
+        };
+        let mut nd = graph.new_operation("Identity", "out").unwrap();
+        nd.add_input(relu);
+        nd.finish().unwrap();
+        let session = Session::new(&SessionOptions::new(), &graph).unwrap();
+
+        let mut calibrator = Calibrator::new().with_tensor("relu");
+        let batch = Tensor::new(&[3]).with_values(&[-1.0f32, 0.5, 2.0]).unwrap();
+        calibrator
+            .calibrate(&graph, &session, &[("x", &batch)])
+            .unwrap();
+        let range = calibrator.range("relu:0").unwrap();
+        assert_eq!((range.min(), range.max()), (&[0.0][..], &[2.0][..]));
+        assert!(calibrator.observe("missing", &batch).is_err());
+
+        let options = QuantizeOptions::default().with_activation_ranges(&calibrator);
+        let quantized = import(&quantize_graph_def(&graph.graph_def().unwrap(), &options).unwrap());
+        let out = quantized.operation_by_name_required("out").unwrap();
+        assert_eq!(
+            out.input(0).0.name().unwrap(),
+            "relu/quantize_and_dequantize"
+        );
+        // Values above the calibrated range are clipped.
+        let x = Tensor::new(&[2]).with_values(&[1.0f32, 3.0]).unwrap();
+        let y = run(&quantized, &x, "out");
+        assert!((y[0] - 1.0).abs() < 0.01, "y = {:?}", &y[..]);
+        assert!((y[1] - 2.0).abs() < 0.01, "y = {:?}", &y[..]);
+    }
+}
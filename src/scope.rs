@@ -1,13 +1,23 @@
+use crate::determinism::default_op_seeds;
+use crate::DataType;
 use crate::Graph;
 use crate::Operation;
-use std::borrow::Borrow;
-use std::cell::RefCell;
+use crate::OperationDescription;
+use crate::Output;
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+use crate::Variable;
+use std::backtrace::Backtrace;
 use std::collections::hash_map::Entry;
 use std::collections::HashMap;
-use std::collections::HashSet;
 use std::ops::Deref;
 use std::ops::DerefMut;
-use std::rc::Rc;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::RwLock;
 
 /// Joins left and right using the separator.  If either left or right is the
 /// empty string, the separator is left out.
@@ -19,6 +29,122 @@ fn join(sep: &str, left: &str, right: &str) -> String {
     }
 }
 
+/// Names which have been used, lowercased, each mapped to the next suffix to
+/// try when the name is requested again.
+type UsedNames = HashMap<String, usize>;
+
+/// Returns `name` if it hasn't been used, or else `name_<i>` for the smallest
+/// unused `i` greater than any previously returned suffix for `name`, and
+/// marks the result as used.  Like Python's `Graph.unique_name`, names are
+/// compared ignoring case, so that checkpoint files named after variables
+/// don't collide on case-insensitive filesystems, but the result keeps the
+/// capitalization of `name`.
+///
+/// Remembering the next suffix keeps the cost of requesting the same name
+/// many times linear rather than quadratic.
+fn unique_name(used: &mut UsedNames, name: &str) -> String {
+    let key = name.to_lowercase();
+    let mut i = match used.get_mut(&key) {
+        None => {
+            used.insert(key, 1);
+            return name.to_string();
+        }
+        Some(next) => *next,
+    };
+    loop {
+        let candidate = format!("{}_{}", key, i);
+        i += 1;
+        if let Entry::Vacant(entry) = used.entry(candidate) {
+            entry.insert(1);
+            // The entry for key was present above and is never removed.
+            *used.get_mut(&key).unwrap() = i;
+            return format!("{}_{}", name, i - 1);
+        }
+    }
+}
+
+/// Cached scalar constants, keyed by data type name, device and value.
+type ConstantCache = HashMap<(String, String, String), Operation>;
+
+/// Backtraces of op creation, keyed by op name.
+type OpBacktraces = HashMap<String, Arc<Backtrace>>;
+
+/// Tensors registered as the inputs and outputs of a model, and variables
+/// recorded with whether they are trainable, in registration order.
+#[derive(Debug, Default)]
+struct Registrations {
+    inputs: Vec<(String, Output)>,
+    outputs: Vec<(String, Output)>,
+    variables: Vec<(Variable, bool)>,
+}
+
+/// The data types used for the computations and variables of a model, for
+/// mixed-precision training.
+///
+/// This type currently requires the `experimental_training` feature.
+///
+/// Only floating point tensors are affected; integer and other tensors keep
+/// their data types.  See `Scope::with_dtype_policy`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DTypePolicy {
+    compute_dtype: DataType,
+    variable_dtype: DataType,
+}
+
+impl DTypePolicy {
+    /// Creates a policy which computes in `compute_dtype` and stores
+    /// variables in `variable_dtype`.  Both must be floating point types.
+    pub fn new(compute_dtype: DataType, variable_dtype: DataType) -> Result<Self> {
+        for data_type in &[compute_dtype, variable_dtype] {
+            if !is_floating(*data_type) {
+                return Err(invalid_arg!(
+                    "A dtype policy needs floating point types, but got {}",
+                    data_type
+                ));
+            }
+        }
+        Ok(Self {
+            compute_dtype,
+            variable_dtype,
+        })
+    }
+
+    /// Computes in half precision with variables in single precision.
+    pub fn mixed_float16() -> Self {
+        Self {
+            compute_dtype: DataType::Half,
+            variable_dtype: DataType::Float,
+        }
+    }
+
+    /// Computes in bfloat16 with variables in single precision.
+    pub fn mixed_bfloat16() -> Self {
+        Self {
+            compute_dtype: DataType::BFloat16,
+            variable_dtype: DataType::Float,
+        }
+    }
+
+    /// Returns the data type computations are done in.
+    pub fn compute_dtype(&self) -> DataType {
+        self.compute_dtype
+    }
+
+    /// Returns the data type variables are stored in.
+    pub fn variable_dtype(&self) -> DataType {
+        self.variable_dtype
+    }
+}
+
+/// Returns true if `data_type` is a floating point type, which are the types
+/// a `DTypePolicy` applies to.
+pub(crate) fn is_floating(data_type: DataType) -> bool {
+    matches!(
+        data_type,
+        DataType::BFloat16 | DataType::Half | DataType::Float | DataType::Double
+    )
+}
+
 // TODO: Include other with_* functions
 /// A `Scope` object represents a set of related TensorFlow ops that have the
 /// same properties such as a common name prefix.
@@ -72,13 +198,56 @@ fn join(sep: &str, left: &str, right: &str) -> String {
 /// scope, directly or transitively. For instance, a new scope creates a new
 /// Graph object to which operations are added when the new scope or its
 /// children are used by an Op constructor.
+///
+/// # Thread safety
+///
+/// Scopes are `Send` and `Sync`, and all scopes derived from the same root
+/// share their graph and name tables behind locks, so parts of a model can be
+/// built concurrently, e.g. by giving each thread its own sub-scope.  Creating
+/// an op locks the graph for writing, so a thread must not hold the guard
+/// returned by `graph()` or `graph_mut()` while creating ops.
+///
+/// # Diagnostics
+///
+/// If an op can't be created, the error includes the op's name, type, scope,
+/// device, and the types and shapes of its inputs.  To trace errors which
+/// occur later, when the graph is run, back to the code which built the
+/// failing op, call `record_backtraces(true)` before building the graph and
+/// look up the op named in the error with `op_backtrace`.
+///
+/// Runtime checks such as `ops::check_numerics` and `ops::assert_that` are
+/// only added to the graph in debug mode, which is enabled with
+/// `set_debug_mode(true)`, so they can be left in model code and turned on
+/// to localize the source of NaNs or bad shapes.
+///
+/// # Mixed precision
+///
+/// A scope created with `with_dtype_policy` builds floating point variables
+/// in the policy's variable data type.  The layers of the `layers` module
+/// (with the `experimental_training` feature) also cast their inputs and
+/// weights to its compute data type, so a model built from them computes in
+/// half precision while keeping its variables in single precision.  Other
+/// ops don't apply the policy: code building them directly casts tensors to
+/// the compute data type with `ops::cast_to_compute_dtype` and
+/// `Variable::compute_output`.
 #[derive(Debug)]
 pub struct Scope {
-    graph: Rc<RefCell<Graph>>,
+    graph: Arc<RwLock<Graph>>,
     name: String,
-    children_names: Rc<RefCell<HashSet<String>>>,
     op_name: String,
-    op_names: Rc<RefCell<HashMap<String, i32>>>,
+    // Shared by all scopes of the graph, since ops and sub-scopes share one
+    // namespace.
+    names: Arc<Mutex<UsedNames>>,
+    device: String,
+    // Shared by all scopes of the graph.  None unless recording is enabled.
+    backtraces: Arc<Mutex<Option<OpBacktraces>>>,
+    // Shared by all scopes of the graph.  None unless caching is enabled.
+    constants: Arc<Mutex<Option<ConstantCache>>>,
+    // Shared by all scopes of the graph.
+    debug_mode: Arc<AtomicBool>,
+    // Shared by all scopes of the graph.
+    registrations: Arc<Mutex<Registrations>>,
+    dtype_policy: Option<DTypePolicy>,
 }
 
 impl Scope {
@@ -87,28 +256,16 @@ impl Scope {
     /// should use the returned object as the "root" scope.
     pub fn new_root_scope() -> Scope {
         Scope {
-            graph: Rc::new(RefCell::new(Graph::new())),
+            graph: Arc::new(RwLock::new(Graph::new())),
             name: "".to_string(),
-            children_names: Rc::new(RefCell::new(HashSet::new())),
             op_name: "".to_string(),
-            op_names: Rc::new(RefCell::new(HashMap::new())),
-        }
-    }
-
-    /// Adds a suffix if necessary to create a unique subscope name.
-    fn uniquify(&self, name: &str) -> String {
-        let refcell: &RefCell<_> = self.children_names.borrow();
-        let mut set = refcell.borrow_mut();
-        if set.insert(name.to_string()) {
-            return name.to_string();
-        }
-        let mut i = 1;
-        loop {
-            let unique_name = format!("{}_{}", &name, i);
-            if set.insert(unique_name.clone()) {
-                return unique_name;
-            }
-            i += 1;
+            names: Arc::new(Mutex::new(HashMap::new())),
+            device: "".to_string(),
+            backtraces: Arc::new(Mutex::new(None)),
+            constants: Arc::new(Mutex::new(None)),
+            debug_mode: Arc::new(AtomicBool::new(false)),
+            registrations: Arc::new(Mutex::new(Registrations::default())),
+            dtype_policy: None,
         }
     }
 
@@ -116,23 +273,36 @@ impl Scope {
     /// `name/child_scope_name` as the prefix. The actual name will be unique
     /// in the current scope. All other properties are inherited from the current
     /// scope. If `child_scope_name` is empty, the `/` is elided.
+    ///
+    /// Names follow the rules of Python's `tf.name_scope`, so a graph built
+    /// with the same sequence of scopes and ops has the same node names in
+    /// both languages.  Sub-scopes and ops share a namespace, so a sub-scope
+    /// named like an existing op gets a suffix, and vice versa.  A name
+    /// ending in `/` is the full name of an existing scope to re-enter, e.g.
+    /// `"encoder/"`, and is used as is rather than being made unique or
+    /// appended to this scope's name.
     pub fn new_sub_scope(&self, name: &str) -> Scope {
-        let self_name: &str = &self.name;
-        let (new_name, copy_names) = match (self_name, name) {
-            (_, "") => (self.name.clone(), true),
-            ("", _) => (self.uniquify(name), false),
-            _ => (format!("{}/{}", self.name, self.uniquify(name)), false),
+        let new_name = if name.is_empty() {
+            self.name.clone()
+        } else if let Some(full_name) = name.strip_suffix('/') {
+            full_name.to_string()
+        } else {
+            unique_name(
+                &mut self.names.lock().unwrap(),
+                &join("/", &self.name, name),
+            )
         };
         Scope {
             graph: self.graph.clone(),
             name: new_name,
-            children_names: Rc::new(RefCell::new(HashSet::new())),
             op_name: self.op_name.clone(),
-            op_names: if copy_names {
-                self.op_names.clone()
-            } else {
-                Rc::new(RefCell::new(HashMap::new()))
-            },
+            names: self.names.clone(),
+            device: self.device.clone(),
+            backtraces: self.backtraces.clone(),
+            constants: self.constants.clone(),
+            debug_mode: self.debug_mode.clone(),
+            registrations: self.registrations.clone(),
+            dtype_policy: self.dtype_policy,
         }
     }
 
@@ -142,54 +312,352 @@ impl Scope {
         Scope {
             graph: self.graph.clone(),
             name: self.name.clone(),
-            children_names: self.children_names.clone(),
             op_name: name.to_string(),
-            op_names: self.op_names.clone(),
+            names: self.names.clone(),
+            device: self.device.clone(),
+            backtraces: self.backtraces.clone(),
+            constants: self.constants.clone(),
+            debug_mode: self.debug_mode.clone(),
+            registrations: self.registrations.clone(),
+            dtype_policy: self.dtype_policy,
+        }
+    }
+
+    /// Return a new scope. All ops created within the returned scope will have
+    /// the device field set to `device`, e.g. "/device:GPU:1" or
+    /// "/job:ps/task:0".  The empty string means unconstrained.
+    pub fn with_device(&self, device: &str) -> Scope {
+        Scope {
+            graph: self.graph.clone(),
+            name: self.name.clone(),
+            op_name: self.op_name.clone(),
+            names: self.names.clone(),
+            device: device.to_string(),
+            backtraces: self.backtraces.clone(),
+            constants: self.constants.clone(),
+            debug_mode: self.debug_mode.clone(),
+            registrations: self.registrations.clone(),
+            dtype_policy: self.dtype_policy,
         }
     }
 
+    /// Return a new scope whose variables and computations use the data types
+    /// of `policy`.  See `DTypePolicy`.
+    pub fn with_dtype_policy(&self, policy: DTypePolicy) -> Scope {
+        Scope {
+            graph: self.graph.clone(),
+            name: self.name.clone(),
+            op_name: self.op_name.clone(),
+            names: self.names.clone(),
+            device: self.device.clone(),
+            backtraces: self.backtraces.clone(),
+            constants: self.constants.clone(),
+            debug_mode: self.debug_mode.clone(),
+            registrations: self.registrations.clone(),
+            dtype_policy: Some(policy),
+        }
+    }
+
+    /// Returns the data type policy of this scope, if one has been set with
+    /// `with_dtype_policy`.
+    pub fn dtype_policy(&self) -> Option<DTypePolicy> {
+        self.dtype_policy
+    }
+
+    /// Returns the data type floating point computations in this scope use:
+    /// the compute data type of its `DTypePolicy`, or float without one.
+    pub fn compute_dtype(&self) -> DataType {
+        self.dtype_policy
+            .map_or(DataType::Float, |policy| policy.compute_dtype())
+    }
+
+    /// Returns the data type floating point variables in this scope are
+    /// stored in: the variable data type of its `DTypePolicy`, or float
+    /// without one.
+    pub fn variable_dtype(&self) -> DataType {
+        self.dtype_policy
+            .map_or(DataType::Float, |policy| policy.variable_dtype())
+    }
+
+    /// Returns the device that ops created within this scope are placed on, or
+    /// the empty string if unconstrained.
+    pub fn device(&self) -> &str {
+        &self.device
+    }
+
     /// Return a unique name, using default_name if an op name has not been
-    /// specified.
+    /// specified.  As in Python, an op name ending in `/` is used as is,
+    /// without the trailing `/`, rather than being made unique.
     pub fn get_unique_name_for_op(&self, default_name: &str) -> String {
         let name = if self.op_name == "" {
             default_name
         } else {
             &self.op_name
         };
-        let map: &RefCell<_> = self.op_names.borrow();
-        let mut map = map.borrow_mut();
-        let mut name_string = name.to_string();
-        loop {
-            match map.entry(name_string.clone()) {
-                Entry::Vacant(e) => {
-                    e.insert(0);
-                    return join("/", &self.name, &name_string);
+        if let Some(full_name) = name.strip_suffix('/') {
+            return full_name.to_string();
+        }
+        unique_name(
+            &mut self.names.lock().unwrap(),
+            &join("/", &self.name, name),
+        )
+    }
+
+    /// Creates an operation of type `op_type` with a unique name and the
+    /// properties of this scope (such as the device) applied.  `f` is called to
+    /// add inputs and set attributes before the operation is finished.
+    pub(crate) fn new_operation<F: FnOnce(&mut OperationDescription<'_>) -> Result<()>>(
+        &mut self,
+        op_type: &str,
+        f: F,
+    ) -> Result<Operation> {
+        let name = self.get_unique_name_for_op(op_type);
+        let mut graph = self.graph.write().unwrap();
+        let mut nd = graph.new_operation(op_type, &name)?;
+        if !self.device.is_empty() {
+            nd.set_device(&self.device)?;
+        }
+        // Set before `f`, so that explicit seeds take precedence.
+        if let Some((seed, seed2)) = default_op_seeds(op_type, &name) {
+            nd.set_attr_int("seed", seed)?;
+            nd.set_attr_int("seed2", seed2)?;
+        }
+        let result = f(&mut nd);
+        let inputs = nd.inputs().to_vec();
+        match result.and_then(|()| nd.finish()) {
+            Ok(operation) => {
+                if let Some(backtraces) = self.backtraces.lock().unwrap().as_mut() {
+                    backtraces.insert(name, Arc::new(Backtrace::force_capture()));
                 }
-                Entry::Occupied(mut e) => {
-                    *e.get_mut() += 1;
-                    name_string = format!("{}_{}", name, *e.get());
+                Ok(operation)
+            }
+            Err(e) => {
+                let context = format!(
+                    "Failed to create operation '{}' of type {} in scope '{}' on device '{}' with inputs [{}]: {}",
+                    name,
+                    op_type,
+                    self.name,
+                    self.device,
+                    describe_inputs(&graph, &inputs),
+                    e.message()
+                );
+                Err(e.with_context(&context).with_op_name(&name))
+            }
+        }
+    }
+
+    /// Enables or disables recording a backtrace of the call site for every op
+    /// subsequently created through this scope or any other scope of the same
+    /// graph.  Disabling recording discards the backtraces recorded so far.
+    ///
+    /// Capturing backtraces is slow, so this is meant for debugging.
+    pub fn record_backtraces(&self, enabled: bool) {
+        let mut backtraces = self.backtraces.lock().unwrap();
+        match (enabled, backtraces.is_some()) {
+            (true, false) => *backtraces = Some(HashMap::new()),
+            (false, true) => *backtraces = None,
+            _ => {}
+        }
+    }
+
+    /// Returns the backtrace recorded when the op named `op_name` was created,
+    /// if recording was enabled at the time.
+    ///
+    /// The op named in an error returned when running the graph is available
+    /// from `Status::op_name`.
+    pub fn op_backtrace(&self, op_name: &str) -> Option<Arc<Backtrace>> {
+        let backtraces = self.backtraces.lock().unwrap();
+        backtraces.as_ref()?.get(op_name).cloned()
+    }
+
+    /// Enables or disables sharing scalar constants across the graph.
+    ///
+    /// While enabled, `ops::constant` returns the existing op for a scalar
+    /// with the same type, value and device instead of creating a new one,
+    /// unless an op name has been set with `with_op_name`.  This keeps graphs
+    /// with many repeated scalars, such as hyperparameters, small.  The
+    /// shared op keeps the name of the scope it was first created in.
+    pub fn cache_scalar_constants(&self, enabled: bool) {
+        let mut constants = self.constants.lock().unwrap();
+        match (enabled, constants.is_some()) {
+            (true, false) => *constants = Some(HashMap::new()),
+            (false, true) => *constants = None,
+            _ => {}
+        }
+    }
+
+    /// Enables or disables debug mode for this scope and every other scope of
+    /// the same graph.  Only ops created while debug mode is enabled get
+    /// runtime checks.
+    pub fn set_debug_mode(&self, enabled: bool) {
+        self.debug_mode.store(enabled, Ordering::SeqCst);
+    }
+
+    /// Returns whether debug mode is enabled.
+    pub fn debug_mode(&self) -> bool {
+        self.debug_mode.load(Ordering::SeqCst)
+    }
+
+    /// Registers `input` as an input of the model under `key`, for this scope
+    /// and every other scope of the same graph.  `SavedModelBuilder` exports
+    /// the registered inputs and outputs as the default serving signature.
+    pub fn register_input<T: Into<Output>>(&self, key: &str, input: T) -> Result<()> {
+        let mut registrations = self.registrations.lock().unwrap();
+        register(&mut registrations.inputs, "input", key, input.into())
+    }
+
+    /// Registers `output` as an output of the model under `key`.  See
+    /// `register_input`.
+    pub fn register_output<T: Into<Output>>(&self, key: &str, output: T) -> Result<()> {
+        let mut registrations = self.registrations.lock().unwrap();
+        register(&mut registrations.outputs, "output", key, output.into())
+    }
+
+    /// Returns the registered inputs with their keys, in registration order.
+    pub fn registered_inputs(&self) -> Vec<(String, Output)> {
+        self.registrations.lock().unwrap().inputs.clone()
+    }
+
+    /// Returns the registered outputs with their keys, in registration order.
+    pub fn registered_outputs(&self) -> Vec<(String, Output)> {
+        self.registrations.lock().unwrap().outputs.clone()
+    }
+
+    /// Records `variable` as part of the model, and as trainable if
+    /// `trainable` is true, so that it is returned by `variables` and
+    /// `trainable_variables` of the scopes whose name it starts with.  The
+    /// layers in `layers` record the variables they create.
+    pub fn record_variable(&self, variable: Variable, trainable: bool) {
+        let mut registrations = self.registrations.lock().unwrap();
+        registrations.variables.push((variable, trainable));
+    }
+
+    /// Returns the recorded variables created in this scope or its
+    /// sub-scopes, in the order they were recorded, e.g. to initialize or
+    /// save them.
+    pub fn variables(&self) -> Vec<Variable> {
+        self.recorded_variables(false)
+    }
+
+    /// Returns the recorded trainable variables created in this scope or its
+    /// sub-scopes, e.g. to pass to `MinimizeOptions::with_variables`.
+    pub fn trainable_variables(&self) -> Vec<Variable> {
+        self.recorded_variables(true)
+    }
+
+    fn recorded_variables(&self, only_trainable: bool) -> Vec<Variable> {
+        let prefix = format!("{}/", self.name);
+        self.registrations
+            .lock()
+            .unwrap()
+            .variables
+            .iter()
+            .filter(|(variable, trainable)| {
+                (*trainable || !only_trainable)
+                    && (self.name.is_empty() || variable.name.starts_with(&prefix))
+            })
+            .map(|(variable, _)| variable.clone())
+            .collect()
+    }
+
+    /// Returns the cached constant with the data type and value of the
+    /// scalar `value` on this scope's device, or creates it with `create` and
+    /// caches it.  Values are compared as formatted by `Debug`, which is
+    /// skipped when caching is disabled.
+    pub(crate) fn cached_constant<T, F>(&mut self, value: Tensor<T>, create: F) -> Result<Operation>
+    where
+        T: TensorType,
+        F: FnOnce(&mut Scope, Tensor<T>) -> Result<Operation>,
+    {
+        if !self.op_name.is_empty() {
+            return create(self, value);
+        }
+        let cached = self.constants.lock().unwrap().as_ref().map(|constants| {
+            let key = (
+                T::data_type().to_string(),
+                self.device.clone(),
+                format!("{:?}", value[0]),
+            );
+            (constants.get(&key).cloned(), key)
+        });
+        match cached {
+            // Caching is disabled.
+            None => create(self, value),
+            Some((Some(op), _)) => Ok(op),
+            Some((None, key)) => {
+                let op = create(self, value)?;
+                if let Some(constants) = self.constants.lock().unwrap().as_mut() {
+                    constants.insert(key, op.clone());
                 }
+                Ok(op)
             }
         }
     }
 
     /// Returns the graph being built by the scope.
     pub fn graph(&self) -> impl Deref<Target = Graph> + '_ {
-        let r: &RefCell<Graph> = self.graph.borrow();
-        r.borrow()
+        self.graph.read().unwrap()
     }
 
     /// Returns the graph being built by the scope.
     pub fn graph_mut(&mut self) -> impl DerefMut<Target = Graph> + '_ {
-        let r: &RefCell<Graph> = self.graph.borrow();
-        r.borrow_mut()
+        self.graph.write().unwrap()
     }
 }
 
+/// Adds `output` to `registered` under `key`, which must not already be
+/// registered.  `kind` is "input" or "output", for the error message.
+fn register(
+    registered: &mut Vec<(String, Output)>,
+    kind: &str,
+    key: &str,
+    output: Output,
+) -> Result<()> {
+    if registered.iter().any(|(k, _)| k == key) {
+        return Err(invalid_arg!(
+            "An {} is already registered as {:?}",
+            kind,
+            key
+        ));
+    }
+    registered.push((key.to_string(), output));
+    Ok(())
+}
+
+/// Describes each input as `name:index (type, shape)` for error messages.
+fn describe_inputs(graph: &Graph, inputs: &[Output]) -> String {
+    let descriptions: Vec<String> = inputs
+        .iter()
+        .map(|input| {
+            let name = input
+                .operation
+                .name()
+                .unwrap_or_else(|_| "<unknown>".to_string());
+            let dtype = input.operation.output_type(input.index as usize);
+            let shape = match graph.tensor_shape(input.clone()) {
+                Ok(shape) => match shape.0 {
+                    Some(dims) => {
+                        let dims: Vec<String> = dims
+                            .iter()
+                            .map(|d| d.map_or("?".to_string(), |d| d.to_string()))
+                            .collect();
+                        format!("[{}]", dims.join(", "))
+                    }
+                    None => "<unknown rank>".to_string(),
+                },
+                Err(_) => "<unknown>".to_string(),
+            };
+            format!("{}:{} ({:?}, {})", name, input.index, dtype, shape)
+        })
+        .collect();
+    descriptions.join(", ")
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::DataType;
+    use std::collections::HashSet;
+    use std::error::Error;
 
     #[test]
     fn smoke() {
@@ -225,4 +693,266 @@ mod tests {
         assert_eq!(bar.get_unique_name_for_op("Add"), "foo/bar");
         assert_eq!(bar.get_unique_name_for_op("Add"), "foo/bar_1");
     }
+
+    #[test]
+    fn unique_name_skips_used_names() {
+        let scope = Scope::new_root_scope();
+        assert_eq!(
+            scope.with_op_name("Add_1").get_unique_name_for_op("x"),
+            "Add_1"
+        );
+        assert_eq!(scope.get_unique_name_for_op("Add"), "Add");
+        assert_eq!(scope.get_unique_name_for_op("Add"), "Add_2");
+        assert_eq!(scope.get_unique_name_for_op("Add"), "Add_3");
+        assert_eq!(scope.get_unique_name_for_op("Add_1"), "Add_1_1");
+    }
+
+    #[test]
+    fn python_compatibility() {
+        // Each name is the one Python gives the same op or tf.name_scope in a
+        // new graph.
+        let root = Scope::new_root_scope();
+        let op = |scope: &Scope, name: &str| scope.with_op_name(name).get_unique_name_for_op("x");
+        assert_eq!(op(&root, "foo"), "foo");
+        let foo = root.new_sub_scope("foo");
+        assert_eq!(&foo.name, "foo_1");
+        assert_eq!(foo.get_unique_name_for_op("Const"), "foo_1/Const");
+        assert_eq!(op(&root, "foo"), "foo_2");
+
+        // Names differing only in case collide, but keep their case.
+        assert_eq!(&root.new_sub_scope("Dense").name, "Dense");
+        let dense = root.new_sub_scope("dense");
+        assert_eq!(&dense.name, "dense_1");
+        assert_eq!(op(&root, "DENSE_1"), "DENSE_1_1");
+
+        // A trailing slash re-enters a scope, or names an op exactly.
+        let reentered = root.new_sub_scope("foo_1/");
+        assert_eq!(&reentered.name, "foo_1");
+        assert_eq!(reentered.get_unique_name_for_op("Const"), "foo_1/Const_1");
+        assert_eq!(op(&dense, "exact/name/"), "exact/name");
+
+        let outer = root.new_sub_scope("outer");
+        assert_eq!(&outer.new_sub_scope("inner").name, "outer/inner");
+        assert_eq!(&outer.new_sub_scope("inner").name, "outer/inner_1");
+        let outer_1 = root.new_sub_scope("outer");
+        assert_eq!(&outer_1.new_sub_scope("inner").name, "outer_1/inner");
+        assert_eq!(op(&outer_1, "inner"), "outer_1/inner_1");
+        // An op's name comes from the innermost scope, even if the scope was
+        // entered with a slash-separated name.
+        let nested = root.new_sub_scope("a/b");
+        assert_eq!(&nested.name, "a/b");
+        assert_eq!(nested.get_unique_name_for_op("Const"), "a/b/Const");
+        assert_eq!(&root.new_sub_scope("a").name, "a");
+    }
+
+    #[test]
+    fn thread_safety() {
+        fn assert_send_sync<T: Send + Sync>() {}
+        assert_send_sync::<Scope>();
+    }
+
+    #[test]
+    fn parallel_construction() {
+        let root = Scope::new_root_scope();
+        let threads: Vec<_> = (0..4)
+            .map(|_| {
+                let mut scope = root.new_sub_scope("tower");
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        scope.new_operation("NoOp", |_| Ok(())).unwrap();
+                    }
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        let graph = root.graph();
+        assert_eq!(graph.operation_iter().count(), 400);
+        assert!(graph
+            .operation_by_name("tower_3/NoOp_99")
+            .unwrap()
+            .is_some());
+    }
+
+    #[test]
+    fn cached_constants() {
+        let mut scope = Scope::new_root_scope();
+        let a = crate::ops::constant(&mut scope, 1.0f32).unwrap();
+        let b = crate::ops::constant(&mut scope, 1.0f32).unwrap();
+        assert_ne!(a.name().unwrap(), b.name().unwrap());
+
+        scope.cache_scalar_constants(true);
+        let mut child = scope.new_sub_scope("child");
+        let c = crate::ops::constant(&mut child, 1.0f32).unwrap();
+        let d = crate::ops::constant(&mut scope, 1.0f32).unwrap();
+        assert_eq!(c.name().unwrap(), "child/Const");
+        assert_eq!(d.name().unwrap(), "child/Const");
+        // Different types, values, devices and explicit names aren't shared.
+        let e = crate::ops::constant(&mut scope, 1.0f64).unwrap();
+        let f = crate::ops::constant(&mut scope, 2.0f32).unwrap();
+        let g = crate::ops::constant(&mut scope.with_device("/device:CPU:0"), 1.0f32).unwrap();
+        let h = crate::ops::constant(&mut scope.with_op_name("h"), 1.0f32).unwrap();
+        let names: HashSet<_> = [&c, &e, &f, &g, &h]
+            .iter()
+            .map(|op| op.name().unwrap())
+            .collect();
+        assert_eq!(names.len(), 5);
+        // Non-scalars aren't shared.
+        let i = crate::ops::constant(&mut scope, &[1.0f32][..]).unwrap();
+        let j = crate::ops::constant(&mut scope, &[1.0f32][..]).unwrap();
+        assert_ne!(i.name().unwrap(), j.name().unwrap());
+    }
+
+    #[test]
+    fn many_names() {
+        // Quadratic uniquification would make this take minutes.
+        let scope = Scope::new_root_scope();
+        for i in 0..100_000 {
+            let expected = if i == 0 {
+                "foo".to_string()
+            } else {
+                format!("foo_{}", i)
+            };
+            assert_eq!(scope.get_unique_name_for_op("foo"), expected);
+        }
+        for _ in 0..100_000 {
+            scope.new_sub_scope("bar");
+        }
+        assert_eq!(&scope.new_sub_scope("bar").name, "bar_100000");
+    }
+
+    #[test]
+    fn device() {
+        let mut scope = Scope::new_root_scope();
+        let default = scope.new_operation("NoOp", |_| Ok(())).unwrap();
+        assert_eq!(default.device().unwrap(), "");
+        let mut cpu = scope.new_sub_scope("foo").with_device("/device:CPU:0");
+        assert_eq!(cpu.device(), "/device:CPU:0");
+        let op = cpu.new_operation("NoOp", |_| Ok(())).unwrap();
+        assert_eq!(op.device().unwrap(), "/device:CPU:0");
+        assert_eq!(op.name().unwrap(), "foo/NoOp");
+        let child = cpu.new_sub_scope("bar");
+        assert_eq!(child.device(), "/device:CPU:0");
+    }
+
+    #[test]
+    fn new_operation_error_has_op_name() {
+        let mut scope = Scope::new_root_scope().with_op_name("bad");
+        let err = scope.new_operation("NoSuchOp", |_| Ok(())).unwrap_err();
+        assert_eq!(err.op_name(), Some("bad".to_string()));
+    }
+
+    #[test]
+    fn new_operation_error_describes_inputs() {
+        let scope = Scope::new_root_scope();
+        let mut foo = scope.new_sub_scope("foo");
+        let x = crate::ops::constant(&mut foo, &[1.0f32, 2.0][..]).unwrap();
+        let y = crate::ops::constant(&mut foo, 3i32).unwrap();
+        let err = crate::ops::add(&mut foo.with_device("/device:CPU:0"), x, y).unwrap_err();
+        assert_eq!(err.op_name(), Some("foo/Add".to_string()));
+        let message = err.message();
+        assert!(message.contains("'foo/Add' of type Add"), "{}", message);
+        assert!(message.contains("in scope 'foo'"), "{}", message);
+        assert!(message.contains("on device '/device:CPU:0'"), "{}", message);
+        assert!(
+            message.contains("foo/Const:0 (Float, [2]), foo/Const_1:0 (Int32, [])"),
+            "{}",
+            message
+        );
+        assert!(err.source().is_some());
+    }
+
+    #[test]
+    fn op_backtrace() {
+        let mut scope = Scope::new_root_scope();
+        scope.new_operation("NoOp", |_| Ok(())).unwrap();
+        assert!(scope.op_backtrace("NoOp").is_none());
+        scope.new_sub_scope("").record_backtraces(true);
+        scope.new_operation("NoOp", |_| Ok(())).unwrap();
+        assert!(scope.op_backtrace("NoOp").is_none());
+        assert!(scope.op_backtrace("NoOp_1").is_some());
+        scope.record_backtraces(false);
+        assert!(scope.op_backtrace("NoOp_1").is_none());
+    }
+
+    #[test]
+    fn debug_mode() {
+        let scope = Scope::new_root_scope();
+        let child = scope.new_sub_scope("child").with_device("/device:CPU:0");
+        assert!(!child.debug_mode());
+        scope.set_debug_mode(true);
+        assert!(child.debug_mode());
+        child.with_op_name("foo").set_debug_mode(false);
+        assert!(!scope.debug_mode());
+    }
+
+    #[test]
+    fn dtype_policy() {
+        let scope = Scope::new_root_scope();
+        assert_eq!(scope.dtype_policy(), None);
+        let mixed = scope.with_dtype_policy(DTypePolicy::mixed_float16());
+        let child = mixed.new_sub_scope("child").with_device("/device:CPU:0");
+        assert_eq!(child.dtype_policy(), Some(DTypePolicy::mixed_float16()));
+        assert_eq!(scope.dtype_policy(), None);
+        assert_eq!(scope.compute_dtype(), DataType::Float);
+        assert_eq!(child.compute_dtype(), DataType::Half);
+        assert_eq!(child.variable_dtype(), DataType::Float);
+
+        let policy = DTypePolicy::new(DataType::BFloat16, DataType::Double).unwrap();
+        assert_eq!(policy.compute_dtype(), DataType::BFloat16);
+        assert_eq!(policy.variable_dtype(), DataType::Double);
+        assert!(DTypePolicy::new(DataType::Int32, DataType::Float).is_err());
+    }
+
+    #[test]
+    fn registrations() {
+        let mut scope = Scope::new_root_scope();
+        let x = scope.new_operation("NoOp", |_| Ok(())).unwrap();
+        let child = scope.new_sub_scope("child");
+        child.register_input("x", x.clone()).unwrap();
+        scope.register_output("y", x.clone()).unwrap();
+        assert!(scope.register_input("x", x.clone()).is_err());
+        child.register_output("x", x).unwrap();
+        let inputs = scope.registered_inputs();
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].0, "x");
+        let outputs: Vec<String> = child
+            .registered_outputs()
+            .into_iter()
+            .map(|(k, _)| k)
+            .collect();
+        assert_eq!(outputs, vec!["y", "x"]);
+    }
+
+    #[test]
+    fn recorded_variables() {
+        let scope = Scope::new_root_scope();
+        let child = scope.new_sub_scope("child");
+        let a = Variable::builder()
+            .const_initial_value(1.0f32)
+            .build(&mut child.with_op_name("a"))
+            .unwrap();
+        let b = Variable::builder()
+            .const_initial_value(2.0f32)
+            .build(&mut scope.with_op_name("b"))
+            .unwrap();
+        // Not in "child", although its name starts with it.
+        let c = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("child_c"))
+            .unwrap();
+        child.record_variable(a, true);
+        scope.record_variable(b, false);
+        scope.record_variable(c, true);
+        let names = |variables: Vec<Variable>| -> Vec<String> {
+            variables.iter().map(|v| v.name().to_string()).collect()
+        };
+        assert_eq!(names(scope.variables()), vec!["child/a", "b", "child_c"]);
+        assert_eq!(
+            names(scope.trainable_variables()),
+            vec!["child/a", "child_c"]
+        );
+        assert_eq!(names(child.variables()), vec!["child/a"]);
+    }
 }
@@ -0,0 +1,388 @@
+use super::Result;
+use super::Status;
+use crate::protos::ProtoWriter;
+use crate::tf;
+use std::collections::BTreeMap;
+use std::ffi::CStr;
+use std::os::raw::c_void;
+
+/// Describes a cluster as a set of named jobs, each consisting of a list of
+/// tasks with network addresses.
+///
+/// ```
+/// # use tensorflow::ClusterSpec;
+/// let cluster = ClusterSpec::new()
+///     .job("ps", &["ps0.example.com:2222"])
+///     .job("worker", &["worker0.example.com:2222", "worker1.example.com:2222"]);
+/// assert_eq!(cluster.num_tasks("worker"), 2);
+/// assert_eq!(
+///     cluster.task_target("worker", 1).unwrap(),
+///     "grpc://worker1.example.com:2222"
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClusterSpec {
+    jobs: BTreeMap<String, BTreeMap<i32, String>>,
+}
+
+impl ClusterSpec {
+    /// Creates an empty cluster.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a job whose tasks are numbered consecutively from zero.
+    pub fn job<S: AsRef<str>>(mut self, name: &str, addresses: &[S]) -> Self {
+        let tasks = self.jobs.entry(name.to_string()).or_default();
+        for (i, address) in addresses.iter().enumerate() {
+            tasks.insert(i as i32, address.as_ref().to_string());
+        }
+        self
+    }
+
+    /// Adds a single task to a job, creating the job if necessary.  This allows
+    /// sparse task indices.
+    pub fn task(mut self, job: &str, index: i32, address: &str) -> Self {
+        self.jobs
+            .entry(job.to_string())
+            .or_default()
+            .insert(index, address.to_string());
+        self
+    }
+
+    /// Returns the names of the jobs in the cluster, in sorted order.
+    pub fn jobs(&self) -> Vec<&str> {
+        self.jobs.keys().map(String::as_str).collect()
+    }
+
+    /// Returns the number of tasks in the given job.
+    pub fn num_tasks(&self, job: &str) -> usize {
+        self.jobs.get(job).map_or(0, BTreeMap::len)
+    }
+
+    /// Returns the indices of the tasks in the given job, in increasing order.
+    pub fn task_indices(&self, job: &str) -> Vec<i32> {
+        self.jobs
+            .get(job)
+            .map_or_else(Vec::new, |tasks| tasks.keys().cloned().collect())
+    }
+
+    /// Returns the address of a task, if it exists.
+    pub fn task_address(&self, job: &str, index: i32) -> Option<&str> {
+        self.jobs
+            .get(job)
+            .and_then(|tasks| tasks.get(&index))
+            .map(String::as_str)
+    }
+
+    /// Returns the gRPC session target for a task, e.g.
+    /// "grpc://localhost:2222", if the task exists.
+    pub fn task_target(&self, job: &str, index: i32) -> Option<String> {
+        self.task_address(job, index)
+            .map(|address| format!("grpc://{}", address))
+    }
+
+    /// Returns the serialized [`ClusterDef` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/cluster.proto).
+    pub fn to_cluster_def(&self) -> Vec<u8> {
+        let mut cluster = ProtoWriter::new();
+        for (name, tasks) in &self.jobs {
+            let mut job = ProtoWriter::new();
+            job.string_field(1, name);
+            for (index, address) in tasks {
+                let mut entry = ProtoWriter::new();
+                entry
+                    .int_field(1, i64::from(*index))
+                    .string_field(2, address);
+                job.message_field(2, &entry);
+            }
+            cluster.message_field(1, &job);
+        }
+        cluster.into_bytes()
+    }
+}
+
+/// Describes the configuration of a `Server`: the cluster it belongs to and
+/// the task within that cluster it serves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServerDef {
+    cluster: ClusterSpec,
+    job_name: String,
+    task_index: i32,
+    protocol: String,
+}
+
+impl ServerDef {
+    /// Creates a definition for task `task_index` of job `job_name` in
+    /// `cluster`, using the "grpc" protocol.
+    pub fn new(cluster: ClusterSpec, job_name: &str, task_index: i32) -> Self {
+        Self {
+            cluster,
+            job_name: job_name.to_string(),
+            task_index,
+            protocol: "grpc".to_string(),
+        }
+    }
+
+    /// Sets the protocol used by the server, e.g. "grpc" or "grpc+verbs".
+    pub fn with_protocol(self, protocol: &str) -> Self {
+        Self {
+            protocol: protocol.to_string(),
+            ..self
+        }
+    }
+
+    /// Returns the cluster.
+    pub fn cluster(&self) -> &ClusterSpec {
+        &self.cluster
+    }
+
+    /// Returns the gRPC session target of the server's own task, if the task is
+    /// part of the cluster.
+    pub fn target(&self) -> Option<String> {
+        self.cluster.task_target(&self.job_name, self.task_index)
+    }
+
+    /// Returns the serialized [`ServerDef` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/tensorflow_server.proto).
+    pub fn to_proto(&self) -> Vec<u8> {
+        let mut def = ProtoWriter::new();
+        def.bytes_field(1, &self.cluster.to_cluster_def())
+            .string_field(2, &self.job_name);
+        if self.task_index != 0 {
+            def.int_field(3, i64::from(self.task_index));
+        }
+        def.string_field(5, &self.protocol);
+        def.into_bytes()
+    }
+}
+
+/// An in-process TensorFlow server, for use in distributed training.
+///
+/// A `Server` instance encapsulates a set of devices and a session target that
+/// can participate in distributed training.  A server belongs to a cluster
+/// (specified by a `ClusterSpec` in its `ServerDef`), and corresponds to a
+/// particular task in a named job.  The server can communicate with any other
+/// server in the same cluster.
+#[derive(Debug)]
+pub struct Server {
+    inner: *mut tf::TF_Server,
+}
+
+impl Server {
+    /// Creates a new in-process TensorFlow server configured using a serialized
+    /// [`ServerDef` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/tensorflow_server.proto).
+    ///
+    /// The server will not serve any requests until `start` is called.
+    pub fn new(server_def: &[u8]) -> Result<Self> {
+        let mut status = Status::new();
+        let inner = unsafe {
+            tf::TF_NewServer(
+                server_def.as_ptr() as *const c_void,
+                server_def.len(),
+                status.inner(),
+            )
+        };
+        if inner.is_null() {
+            Err(status)
+        } else {
+            Ok(Server { inner })
+        }
+    }
+
+    /// Creates a new in-process TensorFlow server from a `ServerDef`.
+    pub fn from_server_def(server_def: &ServerDef) -> Result<Self> {
+        Server::new(&server_def.to_proto())
+    }
+
+    /// Starts the server.
+    pub fn start(&self) -> Result<()> {
+        let mut status = Status::new();
+        unsafe {
+            tf::TF_ServerStart(self.inner, status.inner());
+        }
+        status.into_result()
+    }
+
+    /// Stops the server.
+    pub fn stop(&self) -> Result<()> {
+        let mut status = Status::new();
+        unsafe {
+            tf::TF_ServerStop(self.inner, status.inner());
+        }
+        status.into_result()
+    }
+
+    /// Blocks until the server has been successfully stopped (via `stop`).
+    pub fn join(&self) -> Result<()> {
+        let mut status = Status::new();
+        unsafe {
+            tf::TF_ServerJoin(self.inner, status.inner());
+        }
+        status.into_result()
+    }
+
+    /// Returns the target string that can be provided to
+    /// `SessionOptions::set_target` to connect a session to the server, e.g.
+    /// "grpc://localhost:2222".
+    pub fn target(&self) -> Result<String> {
+        unsafe {
+            Ok(CStr::from_ptr(tf::TF_ServerTarget(self.inner))
+                .to_str()?
+                .to_string())
+        }
+    }
+}
+
+impl Drop for Server {
+    /// Stops and joins the server if it is running, then frees its resources.
+    fn drop(&mut self) {
+        unsafe {
+            tf::TF_DeleteServer(self.inner);
+        }
+    }
+}
+
+unsafe impl Send for Server {}
+
+unsafe impl Sync for Server {}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::Graph;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+    use std::net::TcpListener;
+
+    fn unused_port() -> u16 {
+        TcpListener::bind("localhost:0")
+            .unwrap()
+            .local_addr()
+            .unwrap()
+            .port()
+    }
+
+    // Hand-encoded ServerDef for a single-task "localhost" job.
+    fn server_def(port: u16) -> Vec<u8> {
+        let address = format!("localhost:{}", port);
+        let mut task = vec![0x08, 0x00, 0x12, address.len() as u8];
+        task.extend(address.as_bytes());
+        let mut job = vec![0x0a, 9];
+        job.extend(b"localhost");
+        job.extend(&[0x12, task.len() as u8]);
+        job.extend(task);
+        let mut def = vec![0x0a, job.len() as u8 + 2, 0x0a, job.len() as u8];
+        def.extend(job);
+        def.extend(&[0x12, 9]);
+        def.extend(b"localhost");
+        def.extend(&[0x2a, 4]);
+        def.extend(b"grpc");
+        def
+    }
+
+    #[test]
+    fn smoke() {
+        let server = Server::new(&server_def(unused_port())).unwrap();
+        server.start().unwrap();
+        server.stop().unwrap();
+    }
+
+    #[test]
+    fn invalid_server_def() {
+        assert!(Server::new(&[0xff, 0xff]).is_err());
+    }
+
+    #[test]
+    fn server_def_to_proto() {
+        let cluster = ClusterSpec::new().job("localhost", &["localhost:1234"]);
+        assert_eq!(
+            ServerDef::new(cluster, "localhost", 0).to_proto(),
+            server_def(1234)
+        );
+    }
+
+    #[test]
+    fn cluster_spec() {
+        let cluster = ClusterSpec::new()
+            .job("worker", &["w0:2222", "w1:2222"])
+            .task("ps", 3, "ps3:2222");
+        assert_eq!(cluster.jobs(), vec!["ps", "worker"]);
+        assert_eq!(cluster.num_tasks("worker"), 2);
+        assert_eq!(cluster.num_tasks("ps"), 1);
+        assert_eq!(cluster.num_tasks("chief"), 0);
+        assert_eq!(cluster.task_indices("ps"), vec![3]);
+        assert_eq!(cluster.task_indices("worker"), vec![0, 1]);
+        assert_eq!(cluster.task_address("ps", 3), Some("ps3:2222"));
+        assert_eq!(cluster.task_address("ps", 0), None);
+        assert_eq!(
+            cluster.task_target("worker", 1),
+            Some("grpc://w1:2222".to_string())
+        );
+    }
+
+    #[test]
+    fn run_across_cluster() {
+        let cluster = ClusterSpec::new().job(
+            "worker",
+            &[
+                format!("localhost:{}", unused_port()),
+                format!("localhost:{}", unused_port()),
+            ],
+        );
+        let servers: Vec<_> = (0..2)
+            .map(|i| {
+                Server::from_server_def(&ServerDef::new(cluster.clone(), "worker", i)).unwrap()
+            })
+            .collect();
+        for server in &servers {
+            server.start().unwrap();
+        }
+
+        let mut g = Graph::new();
+        let two = {
+            let mut nd = g.new_operation("Const", "two").unwrap();
+            nd.set_device("/job:worker/task:1").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::from(2.0f32)).unwrap();
+            nd.finish().unwrap()
+        };
+        let mut options = SessionOptions::new();
+        options
+            .set_target(&cluster.task_target("worker", 0).unwrap())
+            .unwrap();
+        options.set_cluster_spec(&cluster).unwrap();
+        let session = Session::new(&options, &g).unwrap();
+        let mut step = SessionRunArgs::new();
+        let token = step.request_fetch(&two, 0);
+        session.run(&mut step).unwrap();
+        assert_eq!(step.fetch::<f32>(token).unwrap()[0], 2.0);
+    }
+
+    #[test]
+    fn run_on_server() {
+        let port = unused_port();
+        let server = Server::new(&server_def(port)).unwrap();
+        server.start().unwrap();
+        let target = server.target().unwrap();
+        assert_eq!(target, format!("grpc://localhost:{}", port));
+
+        let mut g = Graph::new();
+        let two = {
+            let mut nd = g.new_operation("Const", "two").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::from(2.0f32)).unwrap();
+            nd.finish().unwrap()
+        };
+        let mut options = SessionOptions::new();
+        options.set_target(&target).unwrap();
+        let session = Session::new(&options, &g).unwrap();
+        let mut step = SessionRunArgs::new();
+        let token = step.request_fetch(&two, 0);
+        session.run(&mut step).unwrap();
+        assert_eq!(step.fetch::<f32>(token).unwrap()[0], 2.0);
+    }
+}
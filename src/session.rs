@@ -2,20 +2,30 @@ use super::AnyTensor;
 use super::Buffer;
 use super::Code;
 use super::DataType;
+use super::DeviceMemoryStats;
 use super::Graph;
 use super::Operation;
 use super::Result;
 use super::SessionOptions;
+use super::SparseOutput;
+use super::SparseTensor;
 use super::Status;
 use super::Tensor;
 use super::TensorType;
+use super::TensorView;
+use crate::protos::ProtoReader;
+use crate::protos::ProtoWriter;
 use crate::tf;
 use libc::{c_char, c_int};
 use std::ffi::CStr;
 use std::ffi::CString;
 use std::marker;
+use std::ops::Deref;
 use std::path::Path;
 use std::ptr;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
 
 /// Aggregation type for a saved model bundle.
 #[derive(Debug)]
@@ -67,7 +77,7 @@ impl SavedModelBundle {
         if inner.is_null() {
             Err(status)
         } else {
-            let session = Session { inner: inner };
+            let session = Session::from_inner(inner);
             Ok(SavedModelBundle {
                 session: session,
                 meta_graph_def: Vec::from(meta.as_ref()),
@@ -77,12 +87,26 @@ impl SavedModelBundle {
 }
 
 /// Manages a single graph and execution.
+///
+/// Sessions are `Send` and `Sync`, and `run` may be called concurrently from
+/// multiple threads, each with its own `SessionRunArgs`.
 #[derive(Debug)]
 pub struct Session {
     inner: *mut tf::TF_Session,
+    // Shared with the session's closers.
+    closer: Arc<SharedSession>,
 }
 
 impl Session {
+    fn from_inner(inner: *mut tf::TF_Session) -> Self {
+        Session {
+            inner,
+            closer: Arc::new(SharedSession {
+                session: Mutex::new(inner),
+            }),
+        }
+    }
+
     /// Creates a session.
     /// `graph` will be be kept alive for the lifetime of the returned session.
     /// New nodes can still be added to `graph` after this call.
@@ -92,7 +116,7 @@ impl Session {
         if inner.is_null() {
             Err(status)
         } else {
-            Ok(Session { inner: inner })
+            Ok(Session::from_inner(inner))
         }
     }
 
@@ -134,7 +158,7 @@ impl Session {
         if inner.is_null() {
             Err(status)
         } else {
-            Ok(Session { inner: inner })
+            Ok(Session::from_inner(inner))
         }
     }
 
@@ -158,8 +182,20 @@ impl Session {
         step.maybe_reset_run_metadata();
 
         let mut status = Status::new();
-        let maybe_tensors: Result<_> = step.input_tensors.iter().map(|t| t.inner()).collect();
-        let input_tensors: Vec<_> = maybe_tensors?;
+        // Views get a tensor for just this run, so that it can be checked
+        // that no op retained their data once the run is done.
+        let mut borrowed = Vec::new();
+        let mut input_tensors = Vec::with_capacity(step.input_tensors.len());
+        for tensor in &step.input_tensors {
+            match tensor.borrowed_tensor() {
+                Some(view) => {
+                    let view = view?;
+                    input_tensors.push(view.inner());
+                    borrowed.push(view);
+                }
+                None => input_tensors.push(tensor.inner()?),
+            }
+        }
         let run_options_ptr = match step.run_options.as_ref() {
             Some(buf) => buf.inner(),
             None => ptr::null(),
@@ -190,9 +226,36 @@ impl Session {
                 status.inner(),
             );
             step.run_metadata = run_metadata_buf.map(Into::into);
+            step.copy_borrowed_outputs();
         }
+        let retained = borrowed
+            .into_iter()
+            .map(|view| view.release())
+            .filter(|&released| !released)
+            .count();
 
-        status.into_result()
+        status.into_result()?;
+        if retained > 0 {
+            return Err(Status::new_set(
+                Code::FailedPrecondition,
+                &format!(
+                    "{} TensorView feeds were retained by ops beyond the run; their data \
+                     must not be modified or freed while the session lives",
+                    retained
+                ),
+            )
+            .unwrap());
+        }
+        Ok(())
+    }
+
+    /// Returns a handle which closes this session from any thread, e.g. to
+    /// shut down a server which is waiting on slow requests.  See
+    /// `SessionCloser`.
+    pub fn closer(&self) -> SessionCloser {
+        SessionCloser {
+            session: self.closer.clone(),
+        }
     }
 
     /// Lists all devices in a session.
@@ -240,6 +303,8 @@ impl Session {
 
 impl Drop for Session {
     fn drop(&mut self) {
+        // Closers must not close the session once it has been deleted.
+        *self.closer.session.lock().unwrap() = ptr::null_mut();
         let mut status = Status::new();
         unsafe {
             tf::TF_DeleteSession(self.inner, status.inner());
@@ -252,6 +317,48 @@ unsafe impl Send for Session {}
 
 unsafe impl Sync for Session {}
 
+/// The state shared by a session and its closers: the session, or null once
+/// it has been deleted.
+#[derive(Debug)]
+struct SharedSession {
+    session: Mutex<*mut tf::TF_Session>,
+}
+
+unsafe impl Send for SharedSession {}
+
+unsafe impl Sync for SharedSession {}
+
+/// Closes a session from another thread, returned by `Session::closer`.
+///
+/// This closes the whole session, like `Session::close`, rather than
+/// cancelling a single run: runs in progress stop as soon as possible and
+/// fail with `Code::Cancelled`, and all later runs fail.  To abort individual
+/// requests which take too long, set a deadline with
+/// `SessionRunArgs::set_timeout` instead.
+///
+/// Closers can be cloned and sent to other threads, and may outlive the
+/// session, in which case closing does nothing.
+#[derive(Debug, Clone)]
+pub struct SessionCloser {
+    session: Arc<SharedSession>,
+}
+
+impl SessionCloser {
+    /// Closes the session.  Closing a session which is already closed or
+    /// deleted does nothing.
+    pub fn close(&self) -> Result<()> {
+        let session = self.session.session.lock().unwrap();
+        if session.is_null() {
+            return Ok(());
+        }
+        let mut status = Status::new();
+        unsafe {
+            tf::TF_CloseSession(*session, status.inner());
+        }
+        status.into_result()
+    }
+}
+
 ////////////////////////
 
 /// An opaque token for retrieving an output from a computation.
@@ -260,6 +367,15 @@ pub struct FetchToken {
     index: usize,
 }
 
+/// An opaque token for retrieving a sparse tensor from a computation, returned
+/// by `SessionRunArgs::request_sparse_fetch`.
+#[derive(Copy, Clone, Debug)]
+pub struct SparseFetchToken {
+    indices: FetchToken,
+    values: FetchToken,
+    dense_shape: FetchToken,
+}
+
 /// Deprecated alias for FetchToken.
 #[deprecated(note = "Use FetchToken instead.", since = "0.10.0")]
 pub type OutputToken = FetchToken;
@@ -285,7 +401,7 @@ pub type OutputToken = FetchToken;
 #[derive(Debug)]
 pub struct SessionRunArgs<'l> {
     input_ports: Vec<tf::TF_Output>,
-    input_tensors: Vec<&'l dyn AnyTensor>,
+    input_tensors: Vec<FeedTensor<'l>>,
 
     output_ports: Vec<tf::TF_Output>,
     output_tensors: Vec<*mut tf::TF_Tensor>,
@@ -299,6 +415,25 @@ pub struct SessionRunArgs<'l> {
     phantom: marker::PhantomData<&'l ()>,
 }
 
+/// A tensor fed to a session, either borrowed from the caller or owned by
+/// the `SessionRunArgs`.
+#[derive(Debug)]
+enum FeedTensor<'l> {
+    Borrowed(&'l dyn AnyTensor),
+    Owned(Box<dyn AnyTensor>),
+}
+
+impl<'l> Deref for FeedTensor<'l> {
+    type Target = dyn AnyTensor + 'l;
+
+    fn deref(&self) -> &Self::Target {
+        match self {
+            FeedTensor::Borrowed(tensor) => *tensor,
+            FeedTensor::Owned(tensor) => &**tensor,
+        }
+    }
+}
+
 impl<'l> SessionRunArgs<'l> {
     /// Creates a SessionRunArgs.
     pub fn new() -> Self {
@@ -322,6 +457,9 @@ impl<'l> SessionRunArgs<'l> {
     /// Adds an input to be fed to the graph. The index selects which output of
     /// the operation to feed. For most operations, there is only one output,
     /// so the index should be 0.
+    ///
+    /// The tensor is borrowed for as long as these args, and its data is not
+    /// copied.
     pub fn add_feed<T: TensorType>(
         &mut self,
         operation: &Operation,
@@ -332,7 +470,53 @@ impl<'l> SessionRunArgs<'l> {
             oper: operation.inner(),
             index: index,
         });
-        self.input_tensors.push(tensor);
+        self.input_tensors.push(FeedTensor::Borrowed(tensor));
+    }
+
+    /// Like `add_feed`, but takes ownership of the tensor, which lives as
+    /// long as these args.  This is useful for feeding values which must be
+    /// converted to a `Tensor` first.
+    pub fn add_feed_owned<T: TensorType>(
+        &mut self,
+        operation: &Operation,
+        index: c_int,
+        tensor: Tensor<T>,
+    ) {
+        self.input_ports.push(tf::TF_Output {
+            oper: operation.inner(),
+            index: index,
+        });
+        self.input_tensors.push(FeedTensor::Owned(Box::new(tensor)));
+    }
+
+    /// Like `add_feed`, but feeds data borrowed from a slice through a
+    /// `TensorView` rather than a `Tensor`.
+    pub fn add_feed_view<T: TensorType>(
+        &mut self,
+        operation: &Operation,
+        index: c_int,
+        view: &'l TensorView<'_, T>,
+    ) {
+        self.input_ports.push(tf::TF_Output {
+            oper: operation.inner(),
+            index: index,
+        });
+        self.input_tensors.push(FeedTensor::Borrowed(view));
+    }
+
+    /// Like `add_feed`, but for a tensor whose type is only known at runtime.
+    #[cfg(feature = "experimental_training")]
+    pub(crate) fn add_any_feed(
+        &mut self,
+        operation: &Operation,
+        index: c_int,
+        tensor: &'l dyn AnyTensor,
+    ) {
+        self.input_ports.push(tf::TF_Output {
+            oper: operation.inner(),
+            index: index,
+        });
+        self.input_tensors.push(FeedTensor::Borrowed(tensor));
     }
 
     /// Deprecated alias for add_feed.
@@ -395,20 +579,74 @@ impl<'l> SessionRunArgs<'l> {
             )
             .unwrap());
         }
-        let actual_data_type = self.output_data_type(output_idx).unwrap();
-        if actual_data_type != T::data_type() {
-            return Err(invalid_arg!(
-                "Requested tensor type does not match actual tensor type: \
-                 {} vs {}",
-                actual_data_type,
-                T::data_type()
-            ));
-        }
-        let tensor = unsafe { Tensor::from_tf_tensor(self.output_tensors[output_idx]).unwrap() };
+        let tensor = match unsafe { Tensor::from_tf_tensor(self.output_tensors[output_idx]) } {
+            Some(tensor) => tensor,
+            None => {
+                return Err(invalid_arg!(
+                    "Requested tensor type does not match actual tensor type: \
+                     {} vs {}",
+                    self.output_data_type(output_idx)
+                        .map_or_else(|| "unknown".to_string(), |t| t.to_string()),
+                    T::data_type()
+                ));
+            }
+        };
         self.output_tensors[output_idx] = ptr::null_mut();
         Ok(tensor)
     }
 
+    /// Feeds a sparse tensor to the three outputs making up `output`, e.g.
+    /// from `ops::sparse_placeholder`.
+    ///
+    /// The tensor is borrowed for as long as these args, and its data is not
+    /// copied.
+    pub fn add_sparse_feed<T: TensorType>(
+        &mut self,
+        output: &SparseOutput,
+        tensor: &'l SparseTensor<T>,
+    ) {
+        self.add_feed(
+            &output.indices.operation,
+            output.indices.index,
+            tensor.indices(),
+        );
+        self.add_feed(
+            &output.values.operation,
+            output.values.index,
+            tensor.values(),
+        );
+        self.add_feed(
+            &output.dense_shape.operation,
+            output.dense_shape.index,
+            tensor.dense_shape(),
+        );
+    }
+
+    /// Requests that the three outputs making up a sparse tensor are fetched
+    /// after running this step.  Returns a token for `fetch_sparse`.
+    pub fn request_sparse_fetch(&mut self, output: &SparseOutput) -> SparseFetchToken {
+        SparseFetchToken {
+            indices: self.request_fetch(&output.indices.operation, output.indices.index),
+            values: self.request_fetch(&output.values.operation, output.values.index),
+            dense_shape: self
+                .request_fetch(&output.dense_shape.operation, output.dense_shape.index),
+        }
+    }
+
+    /// Extracts a sparse tensor given a token from `request_sparse_fetch`.
+    /// Like `fetch`, this can only be done once per `Session::run`.  Returns
+    /// an error if any of the components is unavailable or has the wrong type,
+    /// or if they don't form a valid sparse tensor.
+    pub fn fetch_sparse<T: TensorType>(
+        &mut self,
+        token: SparseFetchToken,
+    ) -> Result<SparseTensor<T>> {
+        let indices = self.fetch(token.indices)?;
+        let values = self.fetch(token.values)?;
+        let dense_shape = self.fetch(token.dense_shape)?;
+        SparseTensor::new(indices, values, dense_shape)
+    }
+
     /// Deprecated alias for fetch.
     #[deprecated(note = "Use fetch instead.", since = "0.10.0")]
     #[allow(deprecated)]
@@ -442,6 +680,11 @@ impl<'l> SessionRunArgs<'l> {
         self.run_options = Some(Buffer::from(run_options))
     }
 
+    /// Removes any run options set with `set_run_options`.
+    pub(crate) fn clear_run_options(&mut self) {
+        self.run_options = None;
+    }
+
     /// Returns the serialized [`RunOptions` proto](https://github.com/tensorflow/tensorflow/blob/master/tensorflow/core/protobuf/config.proto)
     /// Returns none if `RunOption` are not set.
     pub fn get_run_options(&self) -> Option<&[u8]> {
@@ -465,6 +708,59 @@ impl<'l> SessionRunArgs<'l> {
         self.request_metadata
     }
 
+    /// Sets a deadline for the run, after which it is cancelled and
+    /// `Session::run` fails with `Code::DeadlineExceeded`.  This sets
+    /// `RunOptions.timeout_in_ms`, preserving the other run options.
+    pub fn set_timeout(&mut self, timeout: Duration) {
+        let mut run_options = self.get_run_options().unwrap_or(&[]).to_vec();
+        // The last occurrence of a field wins when parsing, so this overrides
+        // any earlier timeout.
+        let millis = timeout.as_millis().min(i64::MAX as u128) as i64;
+        let mut timeout = ProtoWriter::new();
+        timeout.int_field(2, millis.max(1));
+        run_options.extend_from_slice(timeout.as_bytes());
+        self.set_run_options(&run_options);
+    }
+
+    /// Requests memory statistics for the run, which can be retrieved via
+    /// `self::device_memory_stats` after calling `Session::run`.
+    ///
+    /// This enables tracing (unless the `RunOptions` already request it) and
+    /// requests `RunMetadata`, both of which add overhead, so services should
+    /// only do this for a sample of their runs.
+    pub fn request_memory_stats(&mut self) -> Result<()> {
+        let mut run_options = self.get_run_options().unwrap_or(&[]).to_vec();
+        let mut traced = false;
+        for field in ProtoReader::new(&run_options) {
+            let (field, value) = field?;
+            // RunOptions.trace_level
+            if field == 1 && value.as_i64()? != 0 {
+                traced = true;
+            }
+        }
+        if !traced {
+            // Concatenating messages merges them, so this sets trace_level to
+            // SOFTWARE_TRACE while preserving the other options.
+            run_options.extend(&[0x08, 0x01]);
+            self.set_run_options(&run_options);
+        }
+        self.set_request_metadata(true);
+        Ok(())
+    }
+
+    /// Returns per-device memory statistics for the last run.
+    ///
+    /// Returns an error if `self::request_memory_stats` was not called before
+    /// the run.
+    pub fn device_memory_stats(&self) -> Result<Vec<DeviceMemoryStats>> {
+        match &self.run_metadata {
+            Some(run_metadata) => DeviceMemoryStats::from_run_metadata(run_metadata),
+            None => Err(invalid_arg!(
+                "No run metadata available; call request_memory_stats before running"
+            )),
+        }
+    }
+
     fn drop_output_tensors(&mut self) {
         for tensor in &mut self.output_tensors {
             // TODO: Is TF_DeleteTensor NULL safe?
@@ -477,6 +773,69 @@ impl<'l> SessionRunArgs<'l> {
         }
     }
 
+    /// Replaces fetched outputs which share data borrowed by a `TensorView`
+    /// feed with copies, so that they can outlive the borrow.
+    unsafe fn copy_borrowed_outputs(&mut self) {
+        let borrowed: Vec<_> = self
+            .input_tensors
+            .iter()
+            .filter_map(|t| t.borrowed_data())
+            .collect();
+        if borrowed.is_empty() {
+            return;
+        }
+        for tensor in &mut self.output_tensors {
+            if tensor.is_null() {
+                continue;
+            }
+            let data = tf::TF_TensorData(*tensor) as *const u8;
+            let len = tf::TF_TensorByteSize(*tensor);
+            let shared = borrowed
+                .iter()
+                .any(|&(start, n)| data < start.add(n) && start < data.add(len));
+            if shared {
+                let dims: Vec<i64> = (0..tf::TF_NumDims(*tensor))
+                    .map(|i| tf::TF_Dim(*tensor, i))
+                    .collect();
+                let copy = tf::TF_AllocateTensor(
+                    tf::TF_TensorType(*tensor),
+                    dims.as_ptr(),
+                    dims.len() as c_int,
+                    len,
+                );
+                ptr::copy_nonoverlapping(data, tf::TF_TensorData(copy) as *mut u8, len);
+                tf::TF_DeleteTensor(*tensor);
+                *tensor = copy;
+            }
+        }
+    }
+
+    /// Returns the number of fetches and targets requested so far, for
+    /// passing to `truncate`.
+    #[cfg(feature = "experimental_training")]
+    pub(crate) fn request_counts(&self) -> (usize, usize) {
+        (self.output_ports.len(), self.target_operations.len())
+    }
+
+    /// Removes the fetches and targets requested after `request_counts`
+    /// returned `counts`, deleting any of their outputs which weren't taken.
+    #[cfg(feature = "experimental_training")]
+    pub(crate) fn truncate(&mut self, counts: (usize, usize)) {
+        let (fetches, targets) = counts;
+        for tensor in self
+            .output_tensors
+            .drain(fetches.min(self.output_tensors.len())..)
+        {
+            if !tensor.is_null() {
+                unsafe {
+                    tf::TF_DeleteTensor(tensor);
+                }
+            }
+        }
+        self.output_ports.truncate(fetches);
+        self.target_operations.truncate(targets);
+    }
+
     fn maybe_reset_run_metadata(&mut self) {
         self.run_metadata = None;
     }
@@ -510,6 +869,13 @@ pub struct Device {
     pub incarnation: u64,
 }
 
+/// Returns the devices available to sessions created with `options`, e.g.
+/// `/job:localhost/replica:0/task:0/device:CPU:0`.
+pub fn list_devices(options: &SessionOptions) -> Result<Vec<Device>> {
+    let session = Session::new(options, &Graph::new())?;
+    session.device_list()
+}
+
 ////////////////////////
 
 #[cfg(test)]
@@ -520,6 +886,7 @@ mod tests {
     use super::super::SessionOptions;
     use super::super::Shape;
     use super::super::Tensor;
+    use super::super::TensorView;
     use super::*;
     use std::fs;
 
@@ -580,6 +947,67 @@ mod tests {
         assert_eq!(output_tensor[1], 6.0);
     }
 
+    #[test]
+    fn test_run_view() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = {
+            let data = vec![2.0f32, 3.0];
+            let view = TensorView::new(&[2], &data).unwrap();
+            let mut step = SessionRunArgs::new();
+            step.add_feed_view(&x_operation, 0, &view);
+            let y_token = step.request_fetch(&y_operation, 0);
+            // Fetching a fed tensor returns the same data.
+            let x_token = step.request_fetch(&x_operation, 0);
+            session.run(&mut step).unwrap();
+            assert_eq!(&step.fetch::<f32>(y_token).unwrap()[..], &[4.0, 6.0]);
+            step.fetch::<f32>(x_token).unwrap()
+        };
+        assert_eq!(&x[..], &[2.0, 3.0]);
+
+        assert!(TensorView::new(&[3], &[1.0f32, 2.0]).is_err());
+        assert!(TensorView::new(&[1], &["a".to_string()]).is_err());
+    }
+
+    #[test]
+    fn test_run_view_retained() {
+        // Declared before the session, so that it outlives the queue holding
+        // it.  TensorFlow copies data which isn't aligned, so the view is
+        // aligned to 64 bytes.
+        let data = vec![0.0f32; 32];
+        let offset = (0..16)
+            .find(|&i| data[i..].as_ptr() as usize % 64 == 0)
+            .unwrap();
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let queue = {
+            let mut nd = g.new_operation("FIFOQueueV2", "queue").unwrap();
+            nd.set_attr_type_list("component_types", &[DataType::Float])
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let enqueue = {
+            let mut nd = g.new_operation("QueueEnqueueV2", "enqueue").unwrap();
+            nd.add_input(queue);
+            nd.add_input_list(&[x.clone().into()]);
+            nd.set_attr_type_list("Tcomponents", &[DataType::Float])
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+        let view = TensorView::new(&[2], &data[offset..offset + 2]).unwrap();
+        let mut step = SessionRunArgs::new();
+        step.add_feed_view(&x, 0, &view);
+        step.add_target(&enqueue);
+        assert_eq!(
+            session.run(&mut step).unwrap_err().code(),
+            Code::FailedPrecondition
+        );
+    }
+
     #[test]
     fn test_run_metadata() {
         let (session, x_operation, y_operation) = create_session();
@@ -624,6 +1052,83 @@ mod tests {
         assert_eq!(output_tensor[1], 6.0);
     }
 
+    #[test]
+    fn test_set_timeout() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = Tensor::<f32>::from(&[2.0, 3.0][..]);
+        let mut step = SessionRunArgs::new();
+        step.add_feed(&x_operation, 0, &x);
+        step.set_run_options(&[8u8, 3u8]);
+        step.set_timeout(Duration::from_millis(1500));
+        assert_eq!(step.get_run_options().unwrap(), &[8u8, 3, 0x10, 0xdc, 0x0b]);
+        let output_token = step.request_fetch(&y_operation, 0);
+        session.run(&mut step).unwrap();
+        assert_eq!(&step.fetch::<f32>(output_token).unwrap()[..], &[4.0, 6.0]);
+    }
+
+    #[test]
+    fn test_timeout_exceeded() {
+        // Dequeueing from an empty queue blocks until the deadline.
+        let mut g = Graph::new();
+        let queue = {
+            let mut nd = g.new_operation("FIFOQueueV2", "queue").unwrap();
+            nd.set_attr_type_list("component_types", &[DataType::Float])
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let dequeue = {
+            let mut nd = g.new_operation("QueueDequeueV2", "dequeue").unwrap();
+            nd.add_input(queue);
+            nd.set_attr_type_list("component_types", &[DataType::Float])
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+        let mut step = SessionRunArgs::new();
+        step.request_fetch(&dequeue, 0);
+        step.set_timeout(Duration::from_millis(100));
+        assert_eq!(
+            session.run(&mut step).unwrap_err().code(),
+            Code::DeadlineExceeded
+        );
+    }
+
+    #[test]
+    fn test_session_closer() {
+        let (session, x_operation, y_operation) = create_session();
+        let closer = session.closer();
+        closer.close().unwrap();
+        let x = Tensor::<f32>::from(&[2.0][..]);
+        let mut step = SessionRunArgs::new();
+        step.add_feed(&x_operation, 0, &x);
+        step.request_fetch(&y_operation, 0);
+        assert_eq!(session.run(&mut step).unwrap_err().code(), Code::Cancelled);
+        drop(session);
+        closer.clone().close().unwrap();
+    }
+
+    #[test]
+    fn test_memory_stats() {
+        let (session, x_operation, y_operation) = create_session();
+        let x = Tensor::<f32>::from(&[2.0, 3.0][..]);
+        let mut step = SessionRunArgs::new();
+        step.add_feed(&x_operation, 0, &x);
+        assert!(step.device_memory_stats().is_err());
+        step.request_memory_stats().unwrap();
+        assert_eq!(step.get_run_options(), Some(&[8u8, 1u8][..]));
+        let output_token = step.request_fetch(&y_operation, 0);
+        session.run(&mut step).unwrap();
+        let device_stats = step.device_memory_stats().unwrap();
+        assert!(device_stats.iter().any(|s| s.device().contains("CPU")));
+        assert_eq!(step.fetch::<f32>(output_token).unwrap()[0], 4.0);
+
+        // Explicit trace levels are kept.
+        let mut step = SessionRunArgs::new();
+        step.set_run_options(&[8u8, 3u8]);
+        step.request_memory_stats().unwrap();
+        assert_eq!(step.get_run_options(), Some(&[8u8, 3u8][..]));
+    }
+
     #[test]
     fn test_run_metadata_no_run_options() {
         let (session, x_operation, y_operation) = create_session();
@@ -686,4 +1191,10 @@ mod tests {
             devices
         );
     }
+
+    #[test]
+    fn test_list_devices() {
+        let devices = list_devices(&SessionOptions::new()).unwrap();
+        assert!(devices.iter().any(|d| d.device_type == "CPU"));
+    }
 }
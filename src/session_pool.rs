@@ -0,0 +1,280 @@
+use crate::Code;
+use crate::Graph;
+use crate::Result;
+use crate::Session;
+use crate::SessionOptions;
+use crate::SessionRunArgs;
+use crate::Status;
+use std::ops::Deref;
+use std::sync::Condvar;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+/// A fixed number of sessions running the same graph, which threads check
+/// out for exclusive use and check back in when done.
+///
+/// ```no_run
+/// # use tensorflow::Graph;
+/// # use tensorflow::SessionOptions;
+/// # use tensorflow::SessionPool;
+/// # use tensorflow::SessionRunArgs;
+/// # let graph = Graph::new();
+/// let pool = SessionPool::new(&graph, 4, &SessionOptions::new())?;
+/// // The first runs of a graph are slow, so run each session before serving.
+/// pool.warm_up(2, |session| {
+///     let mut args = SessionRunArgs::new();
+///     // ... feed an example request ...
+///     session.run(&mut args)
+/// })?;
+/// // In each request handler:
+/// let session = pool.check_out();
+/// let mut args = SessionRunArgs::new();
+/// // ... feed the request and request fetches ...
+/// session.run(&mut args)?;
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+///
+/// A single session can be run from several threads at once, but
+/// concurrent runs then compete for its thread pools, so latency varies
+/// with load.  A pool bounds the number of concurrent runs and gives each
+/// its own session, so with small per-session thread pools (see
+/// `SessionOptions`) a multi-threaded server gets predictable latency.  Runs
+/// beyond the pool size wait for a session to be checked in.
+///
+/// Each session has its own copy of the graph's variables, so they must be
+/// initialized or restored in every session, e.g. with `warm_up`.
+#[derive(Debug)]
+pub struct SessionPool {
+    idle: Mutex<Vec<Session>>,
+    checked_in: Condvar,
+    size: usize,
+}
+
+impl SessionPool {
+    /// Creates a pool of `size` sessions running `graph`.
+    pub fn new(graph: &Graph, size: usize, options: &SessionOptions) -> Result<Self> {
+        if size == 0 {
+            return Err(invalid_arg!("A session pool needs at least one session"));
+        }
+        let sessions = (0..size)
+            .map(|_| Session::new(options, graph))
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            idle: Mutex::new(sessions),
+            checked_in: Condvar::new(),
+            size,
+        })
+    }
+
+    /// Returns the number of sessions in the pool.
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Returns the number of sessions which aren't checked out.
+    pub fn available(&self) -> usize {
+        self.idle.lock().unwrap().len()
+    }
+
+    /// Calls `run` `runs` times with each session of the pool, stopping at
+    /// the first error.  This waits until every session is checked in.
+    ///
+    /// Running a few representative requests before serving moves the cost
+    /// of the first runs, which optimize the graph and allocate memory, out
+    /// of the serving path.  With `runs` of 1, this can also initialize or
+    /// restore the variables of every session.
+    ///
+    /// Every session stays checked out until warming up finishes, so `run`
+    /// must not check out a session from the pool, which would wait forever.
+    pub fn warm_up<F: FnMut(&Session) -> Result<()>>(&self, runs: usize, mut run: F) -> Result<()> {
+        let sessions: Vec<_> = {
+            let mut idle = self.idle.lock().unwrap();
+            while idle.len() < self.size {
+                idle = self.checked_in.wait(idle).unwrap();
+            }
+            idle.drain(..).map(|session| self.guard(session)).collect()
+        };
+        for session in &sessions {
+            for _ in 0..runs {
+                run(session)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks out a session, waiting until one is available.  The session is
+    /// checked back in when the returned guard is dropped.
+    pub fn check_out(&self) -> PooledSession<'_> {
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(session) = idle.pop() {
+                return self.guard(session);
+            }
+            idle = self.checked_in.wait(idle).unwrap();
+        }
+    }
+
+    /// Checks out a session if one is available without waiting.
+    pub fn try_check_out(&self) -> Option<PooledSession<'_>> {
+        let session = self.idle.lock().unwrap().pop()?;
+        Some(self.guard(session))
+    }
+
+    /// Checks out a session, waiting at most `timeout` for one to be
+    /// available, and otherwise fails with `Code::DeadlineExceeded`.
+    pub fn check_out_timeout(&self, timeout: Duration) -> Result<PooledSession<'_>> {
+        let deadline = Instant::now() + timeout;
+        let mut idle = self.idle.lock().unwrap();
+        loop {
+            if let Some(session) = idle.pop() {
+                return Ok(self.guard(session));
+            }
+            let now = Instant::now();
+            if now >= deadline {
+                return Err(Status::new_set(
+                    Code::DeadlineExceeded,
+                    &format!("No session was checked in within {:?}", timeout),
+                )
+                .unwrap());
+            }
+            idle = self
+                .checked_in
+                .wait_timeout(idle, deadline - now)
+                .unwrap()
+                .0;
+        }
+    }
+
+    /// Runs `args` with a session checked out for the run.
+    pub fn run(&self, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        self.check_out().run(args)
+    }
+
+    fn guard(&self, session: Session) -> PooledSession<'_> {
+        PooledSession {
+            pool: self,
+            session: Some(session),
+        }
+    }
+}
+
+/// A session checked out of a `SessionPool`, which is checked back in when
+/// this is dropped.
+#[derive(Debug)]
+pub struct PooledSession<'a> {
+    pool: &'a SessionPool,
+    // Only None while being dropped.
+    session: Option<Session>,
+}
+
+impl<'a> Deref for PooledSession<'a> {
+    type Target = Session;
+
+    fn deref(&self) -> &Session {
+        self.session.as_ref().unwrap()
+    }
+}
+
+impl<'a> Drop for PooledSession<'a> {
+    fn drop(&mut self) {
+        if let Some(session) = self.session.take() {
+            self.pool.idle.lock().unwrap().push(session);
+            self.pool.checked_in.notify_all();
+        }
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::DataType;
+    use crate::Operation;
+    use crate::Tensor;
+    use std::sync::Arc;
+    use std::thread;
+
+    /// Returns a graph computing `y = 2 * x` for the float placeholder `x`.
+    fn double() -> (Graph, Operation, Operation) {
+        let mut g = Graph::new();
+        let two = {
+            let mut nd = g.new_operation("Const", "two").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.set_attr_tensor("value", Tensor::new(&[]).with_values(&[2.0f32]).unwrap())
+                .unwrap();
+            nd.finish().unwrap()
+        };
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        let y = {
+            let mut nd = g.new_operation("Mul", "y").unwrap();
+            nd.add_input(two);
+            nd.add_input(x.clone());
+            nd.finish().unwrap()
+        };
+        (g, x, y)
+    }
+
+    #[test]
+    fn check_out_and_in() {
+        let (g, _, _) = double();
+        let pool = SessionPool::new(&g, 2, &SessionOptions::new()).unwrap();
+        assert_eq!(pool.size(), 2);
+        let mut runs = 0;
+        pool.warm_up(3, |_| {
+            // The pool isn't locked while warming up.
+            assert_eq!(pool.available(), 0);
+            runs += 1;
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(runs, 6);
+        assert_eq!(pool.available(), 2);
+
+        let a = pool.check_out();
+        let b = pool.try_check_out().unwrap();
+        assert_eq!(pool.available(), 0);
+        assert!(pool.try_check_out().is_none());
+        let error = pool
+            .check_out_timeout(Duration::from_millis(10))
+            .unwrap_err();
+        assert_eq!(error.code(), Code::DeadlineExceeded);
+        drop(a);
+        assert_eq!(pool.available(), 1);
+        assert!(pool.check_out_timeout(Duration::from_millis(10)).is_ok());
+        drop(b);
+        assert_eq!(pool.available(), 2);
+
+        assert!(SessionPool::new(&g, 0, &SessionOptions::new()).is_err());
+    }
+
+    #[test]
+    fn concurrent() {
+        let (g, x, y) = double();
+        let pool = Arc::new(SessionPool::new(&g, 2, &SessionOptions::new()).unwrap());
+        let threads: Vec<_> = (0..8)
+            .map(|i| {
+                let pool = pool.clone();
+                let x = x.clone();
+                let y = y.clone();
+                thread::spawn(move || {
+                    let input = Tensor::from(i as f32);
+                    let mut args = SessionRunArgs::new();
+                    args.add_feed(&x, 0, &input);
+                    let token = args.request_fetch(&y, 0);
+                    pool.run(&mut args).unwrap();
+                    assert_eq!(args.fetch::<f32>(token).unwrap()[0], 2.0 * i as f32);
+                })
+            })
+            .collect();
+        for thread in threads {
+            thread.join().unwrap();
+        }
+        assert_eq!(pool.available(), 2);
+    }
+}
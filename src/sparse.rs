@@ -0,0 +1,208 @@
+use crate::Output;
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+
+/// A sparse tensor, stored as the indices and values of its nonzero
+/// elements, in the same format as TensorFlow's `SparseTensor`.
+///
+/// A sparse tensor of rank `r` with `n` stored elements consists of:
+///
+/// * `indices`, an int64 tensor of shape `[n, r]` whose rows are the
+///   coordinates of the elements,
+/// * `values`, a tensor of shape `[n]` with the value of each element, and
+/// * `dense_shape`, an int64 vector of length `r` with the shape of the
+///   tensor.
+///
+/// Elements which aren't stored are zero.  Most ops expect the indices to be
+/// in row-major order without duplicates; `from_dense` produces them that
+/// way.
+#[derive(Debug, Clone)]
+pub struct SparseTensor<T: TensorType> {
+    indices: Tensor<i64>,
+    values: Tensor<T>,
+    dense_shape: Tensor<i64>,
+}
+
+impl<T: TensorType> SparseTensor<T> {
+    /// Creates a sparse tensor from its components, checking that their
+    /// shapes are consistent and that the indices are within `dense_shape`.
+    pub fn new(indices: Tensor<i64>, values: Tensor<T>, dense_shape: Tensor<i64>) -> Result<Self> {
+        if dense_shape.dims().len() != 1 {
+            return Err(invalid_arg!(
+                "The dense shape of a sparse tensor must be a vector, but has shape {:?}",
+                dense_shape.dims()
+            ));
+        }
+        if values.dims().len() != 1 {
+            return Err(invalid_arg!(
+                "The values of a sparse tensor must be a vector, but have shape {:?}",
+                values.dims()
+            ));
+        }
+        let expected = [values.dims()[0], dense_shape.len() as u64];
+        if indices.dims() != expected {
+            return Err(invalid_arg!(
+                "The indices of a sparse tensor with {} values and rank {} must have shape {:?}, but have shape {:?}",
+                expected[0],
+                expected[1],
+                expected,
+                indices.dims()
+            ));
+        }
+        if let Some(&dim) = dense_shape.iter().find(|&&dim| dim < 0) {
+            return Err(invalid_arg!(
+                "The dense shape of a sparse tensor can't contain {}",
+                dim
+            ));
+        }
+        let rank = dense_shape.len();
+        if rank > 0 {
+            for index in indices.chunks(rank) {
+                if index
+                    .iter()
+                    .zip(&dense_shape[..])
+                    .any(|(&i, &d)| i < 0 || i >= d)
+                {
+                    return Err(invalid_arg!(
+                        "Index {:?} is out of range for a sparse tensor of shape {:?}",
+                        index,
+                        &dense_shape[..]
+                    ));
+                }
+            }
+        }
+        Ok(Self {
+            indices,
+            values,
+            dense_shape,
+        })
+    }
+
+    /// Creates a sparse tensor with the nonzero elements of `dense`.
+    pub fn from_dense(dense: &Tensor<T>) -> Result<Self>
+    where
+        T: PartialEq,
+    {
+        let dims = dense.dims();
+        let zero = T::zero();
+        let nonzero: Vec<usize> = (0..dense.len()).filter(|&i| dense[i] != zero).collect();
+        let mut indices = Tensor::new(&[nonzero.len() as u64, dims.len() as u64]);
+        let mut values = Tensor::new(&[nonzero.len() as u64]);
+        for (n, &i) in nonzero.iter().enumerate() {
+            let mut rest = i as u64;
+            for (d, &dim) in dims.iter().enumerate().rev() {
+                indices[n * dims.len() + d] = (rest % dim) as i64;
+                rest /= dim;
+            }
+            values[n] = dense[i].clone();
+        }
+        let dense_shape = dims.iter().map(|&dim| dim as i64).collect::<Vec<_>>();
+        Self::new(indices, values, Tensor::from(&dense_shape[..]))
+    }
+
+    /// Returns the indices of the stored elements, of shape `[nnz, rank]`.
+    pub fn indices(&self) -> &Tensor<i64> {
+        &self.indices
+    }
+
+    /// Returns the values of the stored elements.
+    pub fn values(&self) -> &Tensor<T> {
+        &self.values
+    }
+
+    /// Returns the shape of the tensor as an int64 vector.
+    pub fn dense_shape(&self) -> &Tensor<i64> {
+        &self.dense_shape
+    }
+
+    /// Returns the shape of the tensor.
+    pub fn dims(&self) -> Vec<u64> {
+        self.dense_shape.iter().map(|&dim| dim as u64).collect()
+    }
+
+    /// Returns the number of stored elements.
+    pub fn nnz(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns the dense equivalent of this tensor.  Duplicate indices are
+    /// an error.
+    pub fn to_dense(&self) -> Result<Tensor<T>> {
+        let dims = self.dims();
+        let mut dense = Tensor::<T>::new(&dims);
+        let mut stored = vec![false; dense.len()];
+        for (n, value) in self.values.iter().enumerate() {
+            let index = &self.indices[n * dims.len()..(n + 1) * dims.len()];
+            let offset = index
+                .iter()
+                .zip(&dims)
+                .fold(0, |offset, (&i, &dim)| offset * dim + i as u64)
+                as usize;
+            if stored[offset] {
+                return Err(invalid_arg!(
+                    "Index {:?} occurs more than once in a sparse tensor",
+                    index
+                ));
+            }
+            stored[offset] = true;
+            dense[offset] = value.clone();
+        }
+        Ok(dense)
+    }
+}
+
+/// The three graph outputs which together make up a sparse tensor, e.g. from
+/// `ops::sparse_placeholder` or an op with sparse outputs.
+///
+/// Sparse tensors are fed and fetched with
+/// `SessionRunArgs::add_sparse_feed` and `SessionRunArgs::request_sparse_fetch`.
+#[derive(Debug, Clone)]
+pub struct SparseOutput {
+    /// The int64 indices of the stored elements, of shape `[nnz, rank]`.
+    pub indices: Output,
+    /// The values of the stored elements, of shape `[nnz]`.
+    pub values: Output,
+    /// The int64 shape of the tensor, of shape `[rank]`.
+    pub dense_shape: Output,
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dense_round_trip() {
+        let dense = Tensor::new(&[2, 3])
+            .with_values(&[0.0f32, 1.0, 0.0, 2.0, 0.0, 3.0])
+            .unwrap();
+        let sparse = SparseTensor::from_dense(&dense).unwrap();
+        assert_eq!(sparse.nnz(), 3);
+        assert_eq!(&sparse.indices()[..], &[0, 1, 1, 0, 1, 2]);
+        assert_eq!(&sparse.values()[..], &[1.0, 2.0, 3.0]);
+        assert_eq!(sparse.dims(), vec![2, 3]);
+        assert_eq!(sparse.to_dense().unwrap(), dense);
+    }
+
+    #[test]
+    fn validation() {
+        let indices = Tensor::new(&[2, 2]).with_values(&[0, 0, 1, 1]).unwrap();
+        let values = Tensor::from(&[1, 2][..]);
+        let shape = Tensor::from(&[2i64, 2][..]);
+        assert!(SparseTensor::new(indices.clone(), values.clone(), shape.clone()).is_ok());
+        assert!(SparseTensor::new(indices.clone(), Tensor::from(&[1][..]), shape.clone()).is_err());
+        assert!(SparseTensor::new(
+            indices.clone(),
+            values.clone(),
+            Tensor::from(&[2i64, 1][..])
+        )
+        .is_err());
+        assert!(SparseTensor::new(indices, values.clone(), Tensor::from(&[4i64][..])).is_err());
+
+        let duplicates = Tensor::new(&[2, 2]).with_values(&[1, 1, 1, 1]).unwrap();
+        let sparse = SparseTensor::new(duplicates, values, shape).unwrap();
+        assert!(sparse.to_dense().is_err());
+    }
+}
@@ -12,16 +12,107 @@ use crate::Tensor;
 use crate::TensorType;
 use crate::Variable;
 
+mod device_setter;
+pub use device_setter::*;
+
+mod distribute;
+pub use distribute::*;
+
+mod dataset;
+pub use dataset::*;
+
+mod gradient_transforms;
+pub use gradient_transforms::*;
+
+mod gradient_norms;
+pub use gradient_norms::*;
+
+mod gradient_check;
+pub use gradient_check::*;
+
+mod losses;
+pub use losses::*;
+
+mod distillation;
+pub use distillation::*;
+
+mod metrics;
+pub use metrics::*;
+
+mod trainer;
+pub use trainer::*;
+
+mod model;
+pub use model::*;
+
+mod fine_tuning;
+pub use fine_tuning::*;
+
+mod pruning;
+pub use pruning::*;
+
+mod fake_quant;
+pub use fake_quant::*;
+
+mod saver;
+pub use saver::*;
+
+mod saved_model_builder;
+pub use saved_model_builder::*;
+
+mod callbacks;
+pub use callbacks::*;
+
+mod schedules;
+pub use schedules::*;
+
+mod monitored_session;
+pub use monitored_session::*;
+
+mod progress;
+pub use progress::*;
+
 /// Options for `Optimizer::minimize`.
 #[derive(Default, Debug, Clone)]
 pub struct MinimizeOptions<'a> {
     variables: &'a [Variable],
+    gradient_noise: Option<GradientNoise>,
+    gradient_clipping: Option<GradientClipping>,
+    global_step: Option<&'a Variable>,
 }
 
 impl<'a> MinimizeOptions<'a> {
     /// Sets the variables which will be optimized.
     pub fn with_variables(self, variables: &'a [Variable]) -> Self {
-        Self { variables }
+        Self { variables, ..self }
+    }
+
+    /// Adds annealed Gaussian noise to the gradients before applying them.
+    /// See `GradientNoise`.
+    pub fn with_gradient_noise(self, gradient_noise: GradientNoise) -> Self {
+        Self {
+            gradient_noise: Some(gradient_noise),
+            ..self
+        }
+    }
+
+    /// Clips the gradients before applying them, after adding any gradient
+    /// noise.  See `GradientClipping`.
+    pub fn with_gradient_clipping(self, gradient_clipping: GradientClipping) -> Self {
+        Self {
+            gradient_clipping: Some(gradient_clipping),
+            ..self
+        }
+    }
+
+    /// Increments the integer scalar `global_step`, e.g. one created by
+    /// `create_global_step`, after each step of minimization, so learning
+    /// rate schedules computed from it advance automatically.
+    pub fn with_global_step(self, global_step: &'a Variable) -> Self {
+        Self {
+            global_step: Some(global_step),
+            ..self
+        }
     }
 }
 
@@ -51,12 +142,116 @@ impl<'a> ApplyGradientsOptions<'a> {
     }
 }
 
+/// Annealed Gaussian noise for gradients, which helps to train very deep
+/// networks.
+///
+/// At step `t`, the noise has mean 0 and variance `eta / (1 + t)^gamma`, so
+/// it decays as training progresses.  Good values for `eta` are typically
+/// 0.01, 0.3 or 1.0.
+///
+/// See [Neelakantan et al.](https://arxiv.org/abs/1511.06807).
+#[derive(Debug, Clone)]
+pub struct GradientNoise {
+    eta: f32,
+    gamma: f32,
+    global_step: Output,
+    seed: Option<i64>,
+}
+
+impl GradientNoise {
+    /// Creates noise with the given initial variance, annealed according to
+    /// `global_step`, a numeric scalar counting from zero which the caller is
+    /// responsible for incrementing.
+    pub fn new(eta: f32, global_step: Output) -> Self {
+        Self {
+            eta,
+            gamma: 0.55,
+            global_step,
+            seed: None,
+        }
+    }
+
+    /// Sets the decay rate of the variance.  Default is 0.55.
+    pub fn with_gamma(self, gamma: f32) -> Self {
+        Self { gamma, ..self }
+    }
+
+    /// Sets the seed of the random noise, to make it deterministic.
+    pub fn with_seed(self, seed: i64) -> Self {
+        Self {
+            seed: Some(seed),
+            ..self
+        }
+    }
+}
+
+/// Returns `grads_and_vars` with `noise` added to each gradient.  This is
+/// done by `Optimizer::minimize` if `MinimizeOptions::with_gradient_noise` is
+/// set, and can be used between `compute_gradients` and `apply_gradients`
+/// otherwise.
+pub fn add_gradient_noise(
+    scope: &mut Scope,
+    grads_and_vars: &[(Option<Output>, Variable)],
+    noise: &GradientNoise,
+) -> Result<Vec<(Option<Output>, Variable)>> {
+    if !(noise.eta >= 0.0 && noise.gamma >= 0.0) {
+        return Err(invalid_arg!(
+            "Gradient noise needs non-negative eta and gamma, but got {} and {}",
+            noise.eta,
+            noise.gamma
+        ));
+    }
+    let mut scope = scope.new_sub_scope("gradient_noise");
+    let scope = &mut scope;
+    // stddev = sqrt(eta / (1 + t)^gamma)
+    let t = ops::Cast::new()
+        .dst_type(DataType::Float)
+        .build(scope, noise.global_step.clone())?;
+    let one = ops::constant(scope, 1.0f32)?;
+    let t = ops::add(scope, one, t)?;
+    let gamma = ops::constant(scope, noise.gamma)?;
+    let decay = ops::pow(scope, t, gamma)?;
+    let eta = ops::constant(scope, noise.eta)?;
+    let variance = ops::divide(scope, eta, decay)?;
+    let stddev: Output = ops::sqrt(scope, variance)?.into();
+    let mut noisy = Vec::with_capacity(grads_and_vars.len());
+    for (i, (grad, var)) in grads_and_vars.iter().enumerate() {
+        let grad = match grad {
+            Some(grad) => grad,
+            None => {
+                noisy.push((None, var.clone()));
+                continue;
+            }
+        };
+        let dtype = grad.operation.output_type(grad.index as usize);
+        let stddev = if dtype == DataType::Float {
+            stddev.clone()
+        } else {
+            ops::Cast::new()
+                .dst_type(dtype)
+                .build(scope, stddev.clone())?
+                .into()
+        };
+        let shape = ops::shape(scope, grad.clone())?;
+        let mut normal = ops::RandomNormal::new().dtype(dtype);
+        if let Some(seed) = noise.seed {
+            // Each gradient gets its own stream.
+            normal = normal.seed(seed).seed2(i as i64);
+        }
+        let normal = normal.build(scope, shape)?;
+        let scaled = ops::multiply(scope, normal, stddev)?;
+        let grad = ops::add(scope, grad.clone(), scaled)?;
+        noisy.push((Some(grad.into()), var.clone()));
+    }
+    Ok(noisy)
+}
+
 /// An optimizer adjusts variables to minimize some specified value.
 ///
 /// Basic usage only requires calling `minimize`, which calls
 /// `compute_gradients` and `apply_gradients` internally.  Advanced users may
 /// want to call `compute_gradients` and `apply_gradients` manually to allow
-/// them to modify the gradients, e.g. for clipping.
+/// them to modify the gradients in ways `MinimizeOptions` doesn't cover.
 pub trait Optimizer {
     /// Computes the gradient of a value with respect to the given variables.
     /// This adds nodes to the graph, so reuse its results if possible.
@@ -73,7 +268,14 @@ pub trait Optimizer {
         let variable_outputs: Vec<_> = opts.variables.iter().map(|v| v.output.clone()).collect();
         let gradients = scope
             .graph_mut()
-            .add_gradients(None, &[loss], &variable_outputs, None)?;
+            .add_gradients(None, std::slice::from_ref(&loss), &variable_outputs, None)
+            .map_err(|e| {
+                let context = format!(
+                    "Computing gradients of {}",
+                    loss.operation.name().unwrap_or_default()
+                );
+                e.with_context(&context)
+            })?;
         let mut output = Vec::with_capacity(opts.variables.len());
         for (i, gradient) in gradients.into_iter().enumerate() {
             output.push((gradient, opts.variables[i].clone()));
@@ -107,22 +309,57 @@ pub trait Optimizer {
         loss: Output,
         opts: MinimizeOptions,
     ) -> Result<(Vec<Variable>, Operation)> {
-        let grads_and_vars = self.compute_gradients(
-            scope,
-            loss.clone(),
-            ComputeGradientsOptions {
-                variables: opts.variables,
-            },
-        )?;
-        self.apply_gradients(
-            scope,
-            ApplyGradientsOptions {
-                grads_and_vars: &grads_and_vars,
-            },
-        )
+        let grads_and_vars = self
+            .compute_gradients(
+                scope,
+                loss.clone(),
+                ComputeGradientsOptions {
+                    variables: opts.variables,
+                },
+            )
+            .map_err(|e| e.with_context("Minimizing loss"))?;
+        let grads_and_vars = match &opts.gradient_noise {
+            Some(noise) => add_gradient_noise(scope, &grads_and_vars, noise)?,
+            None => grads_and_vars,
+        };
+        let grads_and_vars = match &opts.gradient_clipping {
+            Some(clipping) => clipping.transform(scope, &grads_and_vars)?.1,
+            None => grads_and_vars,
+        };
+        let (variables, apply) = self
+            .apply_gradients(
+                scope,
+                ApplyGradientsOptions {
+                    grads_and_vars: &grads_and_vars,
+                },
+            )
+            .map_err(|e| e.with_context("Applying gradients while minimizing loss"))?;
+        match opts.global_step {
+            Some(global_step) => Ok((variables, increment_after(scope, global_step, apply)?)),
+            None => Ok((variables, apply)),
+        }
     }
 }
 
+/// Returns an op which runs `op` and then increments `global_step`.
+fn increment_after(scope: &mut Scope, global_step: &Variable, op: Operation) -> Result<Operation> {
+    let one: Output = match global_step.dtype {
+        DataType::Int64 => ops::constant(scope, 1i64)?.into(),
+        DataType::Int32 => ops::constant(scope, 1i32)?.into(),
+        dtype => {
+            return Err(invalid_arg!(
+                "The global step must be int32 or int64, but {} is {}",
+                global_step.name,
+                dtype
+            ))
+        }
+    };
+    let next = ops::add(scope, global_step.output.clone(), one)?;
+    ops::Assign::new()
+        .add_control_input(op)
+        .build(scope, global_step.output.clone(), next)
+}
+
 /// Optimizer that implements the gradient descent algorithm.
 #[derive(Debug)]
 pub struct GradientDescentOptimizer {
@@ -146,13 +383,13 @@ impl Optimizer for GradientDescentOptimizer {
         for (grad, var) in opts.grads_and_vars {
             if let Some(grad) = grad {
                 // TODO: use standard op
-                let name = scope.get_unique_name_for_op("ApplyGradientDescent");
-                let mut graph = scope.graph_mut();
-                let mut nd = graph.new_operation("ApplyGradientDescent", &name)?;
-                nd.add_input(var.output.clone());
-                nd.add_input(self.learning_rate.clone());
-                nd.add_input(grad.clone());
-                apply_ops.push(nd.finish()?);
+                let mut scope = colocated_scope(scope, var)?;
+                apply_ops.push(scope.new_operation("ApplyGradientDescent", |nd| {
+                    nd.add_input(var.output.clone());
+                    nd.add_input(self.learning_rate.clone());
+                    nd.add_input(grad.clone());
+                    Ok(())
+                })?);
             }
         }
         let mut nop = ops::NoOp::new();
@@ -163,6 +400,76 @@ impl Optimizer for GradientDescentOptimizer {
     }
 }
 
+/// Optimizer that implements gradient descent with momentum, which
+/// accumulates a decaying sum of the past gradients and steps along it,
+/// speeding up progress along directions where the gradient is consistent.
+///
+/// With Nesterov momentum, the step also looks ahead along the accumulated
+/// direction.  See [Sutskever et al.](http://proceedings.mlr.press/v28/sutskever13.html).
+#[derive(Debug)]
+pub struct MomentumOptimizer {
+    learning_rate: Output,
+    momentum: Option<Output>,
+    use_nesterov: bool,
+}
+
+impl MomentumOptimizer {
+    /// Creates a new optimizer with the given learning rate and default
+    /// parameters (momentum=0.9, use_nesterov=false).
+    pub fn new(learning_rate: Output) -> Self {
+        Self {
+            learning_rate,
+            momentum: None,
+            use_nesterov: false,
+        }
+    }
+
+    /// Sets the momentum, the decay rate of the accumulated gradients.
+    /// Default is 0.9.
+    pub fn set_momentum<T: Into<Output>>(&mut self, momentum: T) {
+        self.momentum = Some(momentum.into());
+    }
+
+    /// Sets whether to use Nesterov momentum.  Default is false.
+    pub fn set_use_nesterov(&mut self, use_nesterov: bool) {
+        self.use_nesterov = use_nesterov;
+    }
+}
+
+impl Optimizer for MomentumOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let momentum = or_constant(scope, &self.momentum, 0.9f32)?;
+        let mut apply_ops = Vec::new();
+        let mut variables = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
+                let accum = create_zeros_slot(&mut scope.new_sub_scope("momentum"), var, None)?;
+                // TODO: use standard op
+                apply_ops.push(scope.new_operation("ApplyMomentum", |nd| {
+                    nd.add_input(var.output.clone());
+                    nd.add_input(accum.output.clone());
+                    nd.add_input(self.learning_rate.clone());
+                    nd.add_input(grad.clone());
+                    nd.add_input(momentum.clone());
+                    nd.set_attr_bool("use_nesterov", self.use_nesterov)?;
+                    Ok(())
+                })?);
+                variables.push(accum.clone());
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((variables, no_op.build(scope)?))
+    }
+}
+
 /// Optimizer that implements the Adadelta algorithm.
 ///
 /// See [M. D. Zeiler](https://arxiv.org/abs/1212.5701).
@@ -210,6 +517,17 @@ fn or_constant<T: TensorType, TT: Into<Tensor<T>>>(
     }
 }
 
+/// Returns a scope which places ops on the same device as `var`, so that
+/// optimizer state and updates live alongside the variable they belong to.
+fn colocated_scope(scope: &Scope, var: &Variable) -> Result<Scope> {
+    let device = var.output.operation.device()?;
+    if device.is_empty() {
+        Ok(scope.with_device(scope.device()))
+    } else {
+        Ok(scope.with_device(&device))
+    }
+}
+
 fn create_zeros_slot(
     scope: &mut Scope,
     primary: &Variable,
@@ -217,14 +535,11 @@ fn create_zeros_slot(
 ) -> Result<Variable> {
     let dtype = dtype.unwrap_or_else(|| primary.dtype);
     // TODO: use standard op
-    let zeros = {
-        let name = scope.get_unique_name_for_op("ZerosLike");
-        let mut graph = scope.graph_mut();
-        let mut nd = graph.new_operation("ZerosLike", &name)?;
+    let zeros = scope.new_operation("ZerosLike", |nd| {
         nd.add_input(primary.output.clone());
         nd.add_control_input(&primary.initializer);
-        nd.finish()?
-    };
+        Ok(())
+    })?;
     Variable::builder()
         .initial_value(zeros)
         .shape(primary.shape.clone())
@@ -245,22 +560,21 @@ impl Optimizer for AdadeltaOptimizer {
         let mut variables = Vec::new();
         for (grad, var) in opts.grads_and_vars {
             if let Some(grad) = grad {
-                let mut scope = scope.new_sub_scope(&var.name);
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
                 let accum = create_zeros_slot(&mut scope.new_sub_scope("accum"), var, None)?;
                 let accum_update =
                     create_zeros_slot(&mut scope.new_sub_scope("accum_update"), var, None)?;
                 // TODO: use standard op
-                let name = scope.get_unique_name_for_op("ApplyAdadelta");
-                let mut graph = scope.graph_mut();
-                let mut nd = graph.new_operation("ApplyAdadelta", &name)?;
-                nd.add_input(var.output.clone());
-                nd.add_input(accum.output.clone());
-                nd.add_input(accum_update.output.clone());
-                nd.add_input(learning_rate.clone());
-                nd.add_input(rho.clone());
-                nd.add_input(epsilon.clone());
-                nd.add_input(grad.clone());
-                apply_ops.push(nd.finish()?);
+                apply_ops.push(scope.new_operation("ApplyAdadelta", |nd| {
+                    nd.add_input(var.output.clone());
+                    nd.add_input(accum.output.clone());
+                    nd.add_input(accum_update.output.clone());
+                    nd.add_input(learning_rate.clone());
+                    nd.add_input(rho.clone());
+                    nd.add_input(epsilon.clone());
+                    nd.add_input(grad.clone());
+                    Ok(())
+                })?);
                 variables.push(accum.clone());
                 variables.push(accum_update.clone());
             }
@@ -273,126 +587,924 @@ impl Optimizer for AdadeltaOptimizer {
     }
 }
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Session;
-    use crate::SessionOptions;
-    use crate::SessionRunArgs;
-
-    #[test]
-    fn simple_gradient_descent() {
-        let mut scope = Scope::new_root_scope();
-        let x_var = Variable::builder()
-            .const_initial_value::<_, f32>(3.0)
-            .build(&mut scope.with_op_name("x"))
-            .unwrap();
-        let x_squared =
-            ops::multiply(&mut scope, x_var.output.clone(), x_var.output.clone()).unwrap();
-        let sgd = GradientDescentOptimizer {
-            learning_rate: Output {
-                operation: ops::constant(&mut scope, 0.1f32).unwrap(),
-                index: 0,
-            },
-        };
-        let (minimizer_vars, minimize) = sgd
-            .minimize(
-                &mut scope,
-                x_squared.into(),
-                MinimizeOptions::default().with_variables(&[x_var.clone()]),
-            )
-            .unwrap();
-        let options = SessionOptions::new();
-        let session = Session::new(&options, &scope.graph()).unwrap();
+/// Optimizer that implements the proximal gradient descent algorithm, which
+/// applies l1 and l2 regularization proximally, i.e. after each gradient step.
+/// Unlike adding the penalties to the loss, the l1 penalty drives weights to
+/// exactly zero, which is useful for feature selection in sparse linear
+/// models.
+///
+/// See [Duchi & Singer](https://papers.nips.cc/paper/3793-efficient-learning-using-forward-backward-splitting.pdf).
+#[derive(Debug)]
+pub struct ProximalGradientDescentOptimizer {
+    learning_rate: Output,
+    l1_regularization_strength: Option<Output>,
+    l2_regularization_strength: Option<Output>,
+}
 
-        let mut run_args = SessionRunArgs::new();
-        run_args.add_target(&x_var.initializer);
-        for var in &minimizer_vars {
-            run_args.add_target(&var.initializer);
+impl ProximalGradientDescentOptimizer {
+    /// Creates a new optimizer with the given learning rate and no
+    /// regularization.
+    pub fn new(learning_rate: Output) -> Self {
+        Self {
+            learning_rate,
+            l1_regularization_strength: None,
+            l2_regularization_strength: None,
         }
-        session.run(&mut run_args).unwrap();
-
-        let mut run_args = SessionRunArgs::new();
-        run_args.add_target(&minimize);
-        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+    }
 
-        session.run(&mut run_args).unwrap();
-        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
-        assert_eq!(x_output.len(), 1);
-        assert!(
-            x_output[0] >= 2.39 && x_output[0] <= 2.41,
-            "x_output[0] = {}",
-            x_output[0]
-        );
+    /// Sets the l1 regularization strength.  Default is 0.
+    pub fn set_l1_regularization_strength<T: Into<Output>>(&mut self, l1: T) {
+        self.l1_regularization_strength = Some(l1.into());
+    }
 
-        session.run(&mut run_args).unwrap();
-        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
-        assert_eq!(x_output.len(), 1);
-        assert!(
-            x_output[0] >= 1.91 && x_output[0] <= 1.93,
-            "x_output[0] = {}",
-            x_output[0]
-        );
+    /// Sets the l2 regularization strength.  Default is 0.
+    pub fn set_l2_regularization_strength<T: Into<Output>>(&mut self, l2: T) {
+        self.l2_regularization_strength = Some(l2.into());
+    }
+}
 
-        session.run(&mut run_args).unwrap();
-        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
-        assert_eq!(x_output.len(), 1);
-        assert!(
-            x_output[0] >= 1.52 && x_output[0] <= 1.54,
-            "x_output[0] = {}",
-            x_output[0]
-        );
+impl Optimizer for ProximalGradientDescentOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let l1 = or_constant(scope, &self.l1_regularization_strength, 0.0f32)?;
+        let l2 = or_constant(scope, &self.l2_regularization_strength, 0.0f32)?;
+        let mut apply_ops = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?;
+                // TODO: use standard op
+                apply_ops.push(scope.new_operation("ApplyProximalGradientDescent", |nd| {
+                    nd.add_input(var.output.clone());
+                    nd.add_input(self.learning_rate.clone());
+                    nd.add_input(l1.clone());
+                    nd.add_input(l2.clone());
+                    nd.add_input(grad.clone());
+                    Ok(())
+                })?);
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((Vec::new(), no_op.build(scope)?))
     }
+}
 
-    #[test]
-    fn simple_adadelta() {
-        let mut scope = Scope::new_root_scope();
-        let x_var = Variable::builder()
-            .const_initial_value(3.0f32)
-            .build(&mut scope.with_op_name("x"))
-            .unwrap();
-        let x_squared =
-            ops::multiply(&mut scope, x_var.output.clone(), x_var.output.clone()).unwrap();
-        let mut optimizer = AdadeltaOptimizer::new();
-        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
-        let (minimizer_vars, minimize) = optimizer
-            .minimize(
-                &mut scope,
-                x_squared.into(),
-                MinimizeOptions::default().with_variables(&[x_var.clone()]),
-            )
-            .unwrap();
-        let options = SessionOptions::new();
-        let session = Session::new(&options, &scope.graph()).unwrap();
+/// Optimizer that implements the Adagrad algorithm with l1 and l2
+/// regularization applied proximally, as in `ProximalGradientDescentOptimizer`.
+///
+/// See [Duchi, Hazan & Singer](http://www.jmlr.org/papers/volume12/duchi11a/duchi11a.pdf).
+#[derive(Debug)]
+pub struct ProximalAdagradOptimizer {
+    learning_rate: Output,
+    initial_accumulator_value: Option<Output>,
+    l1_regularization_strength: Option<Output>,
+    l2_regularization_strength: Option<Output>,
+}
 
-        let mut run_args = SessionRunArgs::new();
-        run_args.add_target(&x_var.initializer);
-        for var in &minimizer_vars {
-            run_args.add_target(&var.initializer);
+impl ProximalAdagradOptimizer {
+    /// Creates a new optimizer with the given learning rate and no
+    /// regularization.
+    pub fn new(learning_rate: Output) -> Self {
+        Self {
+            learning_rate,
+            initial_accumulator_value: None,
+            l1_regularization_strength: None,
+            l2_regularization_strength: None,
         }
-        session.run(&mut run_args).unwrap();
+    }
 
-        let mut run_args = SessionRunArgs::new();
-        run_args.add_target(&minimize);
-        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+    /// Sets the starting value of the accumulators of squared gradients,
+    /// which must be positive.  Default is 0.1.
+    pub fn set_initial_accumulator_value<T: Into<Output>>(&mut self, value: T) {
+        self.initial_accumulator_value = Some(value.into());
+    }
 
-        session.run(&mut run_args).unwrap();
-        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
-        assert_eq!(x_output.len(), 1);
-        assert!(
-            x_output[0] >= 2.99994 && x_output[0] <= 2.99996,
-            "x_output[0] = {}",
-            x_output[0]
-        );
+    /// Sets the l1 regularization strength.  Default is 0.
+    pub fn set_l1_regularization_strength<T: Into<Output>>(&mut self, l1: T) {
+        self.l1_regularization_strength = Some(l1.into());
+    }
 
-        session.run(&mut run_args).unwrap();
-        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
-        assert_eq!(x_output.len(), 1);
-        assert!(
-            x_output[0] >= 2.99990 && x_output[0] <= 2.99992,
-            "x_output[0] = {}",
-            x_output[0]
-        );
+    /// Sets the l2 regularization strength.  Default is 0.
+    pub fn set_l2_regularization_strength<T: Into<Output>>(&mut self, l2: T) {
+        self.l2_regularization_strength = Some(l2.into());
+    }
+}
+
+/// Like `create_zeros_slot`, but fills the slot with the scalar `value`.
+fn create_filled_slot(scope: &mut Scope, primary: &Variable, value: Output) -> Result<Variable> {
+    let zeros = scope.new_operation("ZerosLike", |nd| {
+        nd.add_input(primary.output.clone());
+        nd.add_control_input(&primary.initializer);
+        Ok(())
+    })?;
+    let filled = ops::add(scope, zeros, value)?;
+    Variable::builder()
+        .initial_value(filled)
+        .shape(primary.shape.clone())
+        .data_type(primary.dtype)
+        .build(scope)
+}
+
+impl Optimizer for ProximalAdagradOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let initial_accumulator_value =
+            or_constant(scope, &self.initial_accumulator_value, 0.1f32)?;
+        let l1 = or_constant(scope, &self.l1_regularization_strength, 0.0f32)?;
+        let l2 = or_constant(scope, &self.l2_regularization_strength, 0.0f32)?;
+        let mut apply_ops = Vec::new();
+        let mut variables = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
+                let accum = create_filled_slot(
+                    &mut scope.new_sub_scope("accum"),
+                    var,
+                    initial_accumulator_value.clone(),
+                )?;
+                // TODO: use standard op
+                apply_ops.push(scope.new_operation("ApplyProximalAdagrad", |nd| {
+                    nd.add_input(var.output.clone());
+                    nd.add_input(accum.output.clone());
+                    nd.add_input(self.learning_rate.clone());
+                    nd.add_input(l1.clone());
+                    nd.add_input(l2.clone());
+                    nd.add_input(grad.clone());
+                    Ok(())
+                })?);
+                variables.push(accum.clone());
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((variables, no_op.build(scope)?))
+    }
+}
+
+/// Optimizer that implements the Adagrad Dual Averaging algorithm, which
+/// induces much stronger sparsity through its l1 regularization than
+/// `ProximalAdagradOptimizer`.
+///
+/// Rather than moving from its current value, each variable is recomputed
+/// from the sum of all its gradients so far, so the optimizer needs the
+/// int64 scalar global step, counting from zero, which the caller is
+/// responsible for incrementing.
+///
+/// See [Duchi, Hazan & Singer](http://www.jmlr.org/papers/volume12/duchi11a/duchi11a.pdf).
+#[derive(Debug)]
+pub struct AdagradDAOptimizer {
+    learning_rate: Output,
+    global_step: Output,
+    initial_gradient_squared_accumulator_value: Option<Output>,
+    l1_regularization_strength: Option<Output>,
+    l2_regularization_strength: Option<Output>,
+}
+
+impl AdagradDAOptimizer {
+    /// Creates a new optimizer with the given learning rate and global step,
+    /// and no regularization.
+    pub fn new(learning_rate: Output, global_step: Output) -> Self {
+        Self {
+            learning_rate,
+            global_step,
+            initial_gradient_squared_accumulator_value: None,
+            l1_regularization_strength: None,
+            l2_regularization_strength: None,
+        }
+    }
+
+    /// Sets the starting value of the accumulators of squared gradients,
+    /// which must be positive.  Default is 0.1.
+    pub fn set_initial_gradient_squared_accumulator_value<T: Into<Output>>(&mut self, value: T) {
+        self.initial_gradient_squared_accumulator_value = Some(value.into());
+    }
+
+    /// Sets the l1 regularization strength.  Default is 0.
+    pub fn set_l1_regularization_strength<T: Into<Output>>(&mut self, l1: T) {
+        self.l1_regularization_strength = Some(l1.into());
+    }
+
+    /// Sets the l2 regularization strength.  Default is 0.
+    pub fn set_l2_regularization_strength<T: Into<Output>>(&mut self, l2: T) {
+        self.l2_regularization_strength = Some(l2.into());
+    }
+}
+
+impl Optimizer for AdagradDAOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let initial_gradient_squared_accumulator_value = or_constant(
+            scope,
+            &self.initial_gradient_squared_accumulator_value,
+            0.1f32,
+        )?;
+        let l1 = or_constant(scope, &self.l1_regularization_strength, 0.0f32)?;
+        let l2 = or_constant(scope, &self.l2_regularization_strength, 0.0f32)?;
+        // The op counts steps from one.
+        let one = ops::constant(scope, 1i64)?;
+        let global_step: Output = ops::add(scope, self.global_step.clone(), one)?.into();
+        let mut apply_ops = Vec::new();
+        let mut variables = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
+                let gradient_accumulator =
+                    create_zeros_slot(&mut scope.new_sub_scope("gradient_accumulator"), var, None)?;
+                let gradient_squared_accumulator = create_filled_slot(
+                    &mut scope.new_sub_scope("gradient_squared_accumulator"),
+                    var,
+                    initial_gradient_squared_accumulator_value.clone(),
+                )?;
+                // TODO: use standard op
+                apply_ops.push(scope.new_operation("ApplyAdagradDA", |nd| {
+                    nd.add_input(var.output.clone());
+                    nd.add_input(gradient_accumulator.output.clone());
+                    nd.add_input(gradient_squared_accumulator.output.clone());
+                    nd.add_input(grad.clone());
+                    nd.add_input(self.learning_rate.clone());
+                    nd.add_input(l1.clone());
+                    nd.add_input(l2.clone());
+                    nd.add_input(global_step.clone());
+                    Ok(())
+                })?);
+                variables.push(gradient_accumulator.clone());
+                variables.push(gradient_squared_accumulator.clone());
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((variables, no_op.build(scope)?))
+    }
+}
+
+/// Returns a float scalar variable counting the updates applied by an
+/// optimizer, and the number of the current update, counting from one, which
+/// increments the variable when evaluated.
+fn create_step_counter(scope: &mut Scope) -> Result<(Variable, Output)> {
+    let step = Variable::builder()
+        .const_initial_value(0.0f32)
+        .build(&mut scope.with_op_name("step"))?;
+    let one = ops::constant(scope, 1.0f32)?;
+    let next = ops::add(scope, step.output.clone(), one)?;
+    let t = ops::assign(scope, step.output.clone(), next)?;
+    Ok((step, t.into()))
+}
+
+/// For AMSGrad, keeps the maximum of the second moment estimates of `var` in
+/// a slot named `name`, and returns the updated maximum to use in place of
+/// `estimate`.
+fn amsgrad_max(
+    scope: &mut Scope,
+    name: &str,
+    var: &Variable,
+    estimate: Output,
+    variables: &mut Vec<Variable>,
+) -> Result<Output> {
+    let max = create_zeros_slot(&mut scope.new_sub_scope(name), var, None)?;
+    let max_t = ops::maximum(scope, max.output.clone(), estimate)?;
+    let max_t = ops::assign(scope, max.output.clone(), max_t)?;
+    variables.push(max);
+    Ok(max_t.into())
+}
+
+/// Optimizer that implements the Adam algorithm.
+///
+/// Adam scales each step by running estimates of the first and second
+/// moments of the gradient, so every variable gets its own effective learning
+/// rate.
+///
+/// See [Kingma & Ba](https://arxiv.org/abs/1412.6980).
+#[derive(Debug, Default)]
+pub struct AdamOptimizer {
+    learning_rate: Option<Output>,
+    beta1: Option<Output>,
+    beta2: Option<Output>,
+    epsilon: Option<Output>,
+    amsgrad: bool,
+}
+
+impl AdamOptimizer {
+    /// Creates a new optimizer with default parameters (learning_rate=0.001,
+    /// beta1=0.9, beta2=0.999, epsilon=1e-8).
+    pub fn new() -> Self {
+        Self {
+            learning_rate: None,
+            beta1: None,
+            beta2: None,
+            epsilon: None,
+            amsgrad: false,
+        }
+    }
+
+    /// Sets the learning rate.  Default is 0.001.
+    pub fn set_learning_rate<T: Into<Output>>(&mut self, learning_rate: T) {
+        self.learning_rate = Some(learning_rate.into());
+    }
+
+    /// Sets beta1, the decay rate of the first moment estimates.  Default is
+    /// 0.9.
+    pub fn set_beta1<T: Into<Output>>(&mut self, beta1: T) {
+        self.beta1 = Some(beta1.into());
+    }
+
+    /// Sets beta2, the decay rate of the second moment estimates.  Default is
+    /// 0.999.
+    pub fn set_beta2<T: Into<Output>>(&mut self, beta2: T) {
+        self.beta2 = Some(beta2.into());
+    }
+
+    /// Sets epsilon, the conditioning.  Default is 1e-8.
+    pub fn set_epsilon<T: Into<Output>>(&mut self, epsilon: T) {
+        self.epsilon = Some(epsilon.into());
+    }
+
+    /// Enables the AMSGrad variant, which divides by the maximum of the
+    /// second moment estimates so far rather than the current estimate.
+    /// Default is false.  See `RAdamOptimizer::set_amsgrad`.
+    pub fn set_amsgrad(&mut self, amsgrad: bool) {
+        self.amsgrad = amsgrad;
+    }
+}
+
+impl Optimizer for AdamOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let learning_rate = or_constant(scope, &self.learning_rate, 0.001f32)?;
+        let beta1 = or_constant(scope, &self.beta1, 0.9f32)?;
+        let beta2 = or_constant(scope, &self.beta2, 0.999f32)?;
+        let epsilon = or_constant(scope, &self.epsilon, 1e-8f32)?;
+        let (step, t) = create_step_counter(scope)?;
+        let mut variables = vec![step];
+
+        // lr_t = learning_rate * sqrt(1 - beta2^t) / (1 - beta1^t)
+        let one = ops::constant(scope, 1.0f32)?;
+        let one_minus_beta1 = ops::subtract(scope, one.clone(), beta1.clone())?;
+        let one_minus_beta2 = ops::subtract(scope, one.clone(), beta2.clone())?;
+        let beta1_power = ops::pow(scope, beta1.clone(), t.clone())?;
+        let beta1_correction = ops::subtract(scope, one.clone(), beta1_power)?;
+        let beta2_power = ops::pow(scope, beta2.clone(), t)?;
+        let beta2_correction = ops::subtract(scope, one, beta2_power)?;
+        let beta2_correction = ops::sqrt(scope, beta2_correction)?;
+        let lr_t = ops::multiply(scope, learning_rate, beta2_correction)?;
+        let lr_t = ops::divide(scope, lr_t, beta1_correction)?;
+
+        let mut apply_ops = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
+                let m = create_zeros_slot(&mut scope.new_sub_scope("m"), var, None)?;
+                let v = create_zeros_slot(&mut scope.new_sub_scope("v"), var, None)?;
+                let scope = &mut scope;
+                // m_t = beta1 m + (1 - beta1) g
+                let decayed = ops::multiply(scope, beta1.clone(), m.output.clone())?;
+                let scaled = ops::multiply(scope, one_minus_beta1.clone(), grad.clone())?;
+                let m_t = ops::add(scope, decayed, scaled)?;
+                let m_t = ops::assign(scope, m.output.clone(), m_t)?;
+                // v_t = beta2 v + (1 - beta2) g^2
+                let decayed = ops::multiply(scope, beta2.clone(), v.output.clone())?;
+                let squared = ops::square(scope, grad.clone())?;
+                let scaled = ops::multiply(scope, one_minus_beta2.clone(), squared)?;
+                let v_t = ops::add(scope, decayed, scaled)?;
+                let v_t = ops::assign(scope, v.output.clone(), v_t)?;
+                let v_t = if self.amsgrad {
+                    amsgrad_max(scope, "v_max", var, v_t.into(), &mut variables)?
+                } else {
+                    v_t.into()
+                };
+                // var -= lr_t m_t / (sqrt(v_t) + epsilon)
+                let denominator = ops::sqrt(scope, v_t)?;
+                let denominator = ops::add(scope, denominator, epsilon.clone())?;
+                let update = ops::divide(scope, m_t, denominator)?;
+                let update = ops::multiply(scope, lr_t.clone(), update)?;
+                let new_value = ops::subtract(scope, var.output.clone(), update)?;
+                apply_ops.push(ops::assign(scope, var.output.clone(), new_value)?);
+                variables.push(m.clone());
+                variables.push(v.clone());
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((variables, no_op.build(scope)?))
+    }
+}
+
+/// Optimizer that implements the RMSProp algorithm.
+///
+/// RMSProp divides each step by a running root mean square of the gradient,
+/// and optionally accumulates the steps with momentum.
+///
+/// See [Hinton](http://www.cs.toronto.edu/~tijmen/csc321/slides/lecture_slides_lec6.pdf).
+#[derive(Debug, Default)]
+pub struct RMSPropOptimizer {
+    learning_rate: Option<Output>,
+    decay: Option<Output>,
+    momentum: Option<Output>,
+    epsilon: Option<Output>,
+}
+
+impl RMSPropOptimizer {
+    /// Creates a new optimizer with default parameters (learning_rate=0.001,
+    /// decay=0.9, momentum=0, epsilon=1e-10).
+    pub fn new() -> Self {
+        Self {
+            learning_rate: None,
+            decay: None,
+            momentum: None,
+            epsilon: None,
+        }
+    }
+
+    /// Sets the learning rate.  Default is 0.001.
+    pub fn set_learning_rate<T: Into<Output>>(&mut self, learning_rate: T) {
+        self.learning_rate = Some(learning_rate.into());
+    }
+
+    /// Sets the decay rate of the mean square estimates.  Default is 0.9.
+    pub fn set_decay<T: Into<Output>>(&mut self, decay: T) {
+        self.decay = Some(decay.into());
+    }
+
+    /// Sets the momentum.  Default is 0.
+    pub fn set_momentum<T: Into<Output>>(&mut self, momentum: T) {
+        self.momentum = Some(momentum.into());
+    }
+
+    /// Sets epsilon, the conditioning.  Default is 1e-10.
+    pub fn set_epsilon<T: Into<Output>>(&mut self, epsilon: T) {
+        self.epsilon = Some(epsilon.into());
+    }
+}
+
+impl Optimizer for RMSPropOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let learning_rate = or_constant(scope, &self.learning_rate, 0.001f32)?;
+        let decay = or_constant(scope, &self.decay, 0.9f32)?;
+        let momentum = or_constant(scope, &self.momentum, 0.0f32)?;
+        let epsilon = or_constant(scope, &self.epsilon, 1e-10f32)?;
+        let one = ops::constant(scope, 1.0f32)?;
+        let one_minus_decay = ops::subtract(scope, one, decay.clone())?;
+        let mut apply_ops = Vec::new();
+        let mut variables = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
+                let rms = create_zeros_slot(&mut scope.new_sub_scope("rms"), var, None)?;
+                let mom = create_zeros_slot(&mut scope.new_sub_scope("momentum"), var, None)?;
+                let scope = &mut scope;
+                // ms_t = decay ms + (1 - decay) g^2
+                let decayed = ops::multiply(scope, decay.clone(), rms.output.clone())?;
+                let squared = ops::square(scope, grad.clone())?;
+                let scaled = ops::multiply(scope, one_minus_decay.clone(), squared)?;
+                let ms_t = ops::add(scope, decayed, scaled)?;
+                let ms_t = ops::assign(scope, rms.output.clone(), ms_t)?;
+                // mom_t = momentum mom + learning_rate g / sqrt(ms_t + epsilon)
+                let denominator = ops::add(scope, ms_t, epsilon.clone())?;
+                let denominator = ops::sqrt(scope, denominator)?;
+                let step = ops::multiply(scope, learning_rate.clone(), grad.clone())?;
+                let step = ops::divide(scope, step, denominator)?;
+                let decayed = ops::multiply(scope, momentum.clone(), mom.output.clone())?;
+                let mom_t = ops::add(scope, decayed, step)?;
+                let mom_t = ops::assign(scope, mom.output.clone(), mom_t)?;
+                let new_value = ops::subtract(scope, var.output.clone(), mom_t)?;
+                apply_ops.push(ops::assign(scope, var.output.clone(), new_value)?);
+                variables.push(rms.clone());
+                variables.push(mom.clone());
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((variables, no_op.build(scope)?))
+    }
+}
+
+/// Optimizer that implements the Rectified Adam (RAdam) algorithm.
+///
+/// RAdam behaves like Adam, but corrects the variance of the adaptive
+/// learning rate, which is large in the first steps.  Until the variance is
+/// tractable, it falls back to SGD with momentum, which removes the need for a
+/// learning rate warmup.
+///
+/// See [Liu et al.](https://arxiv.org/abs/1908.03265).
+#[derive(Debug, Default)]
+pub struct RAdamOptimizer {
+    learning_rate: Option<Output>,
+    beta1: Option<Output>,
+    beta2: Option<Output>,
+    epsilon: Option<Output>,
+    amsgrad: bool,
+}
+
+impl RAdamOptimizer {
+    /// Creates a new optimizer with default parameters (learning_rate=0.001,
+    /// beta1=0.9, beta2=0.999, epsilon=1e-7).
+    pub fn new() -> Self {
+        Self {
+            learning_rate: None,
+            beta1: None,
+            beta2: None,
+            epsilon: None,
+            amsgrad: false,
+        }
+    }
+
+    /// Sets the learning rate.  Default is 0.001.
+    pub fn set_learning_rate<T: Into<Output>>(&mut self, learning_rate: T) {
+        self.learning_rate = Some(learning_rate.into());
+    }
+
+    /// Sets beta1, the decay rate of the first moment estimates.  Default is
+    /// 0.9.
+    pub fn set_beta1<T: Into<Output>>(&mut self, beta1: T) {
+        self.beta1 = Some(beta1.into());
+    }
+
+    /// Sets beta2, the decay rate of the second moment estimates.  Default is
+    /// 0.999.
+    pub fn set_beta2<T: Into<Output>>(&mut self, beta2: T) {
+        self.beta2 = Some(beta2.into());
+    }
+
+    /// Sets epsilon, the conditioning.  Default is 1e-7.
+    pub fn set_epsilon<T: Into<Output>>(&mut self, epsilon: T) {
+        self.epsilon = Some(epsilon.into());
+    }
+
+    /// Enables the AMSGrad variant, which divides by the maximum of the
+    /// second moment estimates so far rather than the current estimate.
+    /// This keeps the effective learning rate from growing when gradients
+    /// shrink, which some models need to converge.  Default is false.
+    ///
+    /// See [Reddi et al.](https://openreview.net/forum?id=ryQu7f-RZ).
+    pub fn set_amsgrad(&mut self, amsgrad: bool) {
+        self.amsgrad = amsgrad;
+    }
+}
+
+impl Optimizer for RAdamOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let learning_rate = or_constant(scope, &self.learning_rate, 0.001f32)?;
+        let beta1 = or_constant(scope, &self.beta1, 0.9f32)?;
+        let beta2 = or_constant(scope, &self.beta2, 0.999f32)?;
+        let epsilon = or_constant(scope, &self.epsilon, 1e-7f32)?;
+        let (step, t) = create_step_counter(scope)?;
+        let mut variables = vec![step];
+
+        let one = ops::constant(scope, 1.0f32)?;
+        let two = ops::constant(scope, 2.0f32)?;
+        let four = ops::constant(scope, 4.0f32)?;
+        let one_minus_beta1 = ops::subtract(scope, one.clone(), beta1.clone())?;
+        let one_minus_beta2 = ops::subtract(scope, one.clone(), beta2.clone())?;
+        let beta1_power = ops::pow(scope, beta1.clone(), t.clone())?;
+        let beta1_correction = ops::subtract(scope, one.clone(), beta1_power)?;
+        let beta2_power = ops::pow(scope, beta2.clone(), t.clone())?;
+        let beta2_correction = ops::subtract(scope, one.clone(), beta2_power.clone())?;
+        // The maximum and current length of the approximated simple moving
+        // average, 2 / (1 - beta2) - 1 and rho_inf - 2 t beta2^t / (1 - beta2^t).
+        let rho_inf = ops::divide(scope, two.clone(), one_minus_beta2.clone())?;
+        let rho_inf = ops::subtract(scope, rho_inf, one)?;
+        let rho_t = ops::multiply(scope, t, beta2_power)?;
+        let rho_t = ops::multiply(scope, two.clone(), rho_t)?;
+        let rho_t = ops::divide(scope, rho_t, beta2_correction.clone())?;
+        let rho_t = ops::subtract(scope, rho_inf.clone(), rho_t)?;
+        // The variance rectification term,
+        // sqrt((rho_t - 4) (rho_t - 2) rho_inf / ((rho_inf - 4) (rho_inf - 2) rho_t)).
+        let numerator = ops::subtract(scope, rho_t.clone(), four.clone())?;
+        let rho_t_minus_two = ops::subtract(scope, rho_t.clone(), two.clone())?;
+        let numerator = ops::multiply(scope, numerator, rho_t_minus_two)?;
+        let numerator = ops::multiply(scope, numerator, rho_inf.clone())?;
+        let denominator = ops::subtract(scope, rho_inf.clone(), four)?;
+        let rho_inf_minus_two = ops::subtract(scope, rho_inf, two)?;
+        let denominator = ops::multiply(scope, denominator, rho_inf_minus_two)?;
+        let denominator = ops::multiply(scope, denominator, rho_t.clone())?;
+        let rectification = ops::divide(scope, numerator, denominator)?;
+        let rectification = ops::sqrt(scope, rectification)?;
+        let threshold = ops::constant(scope, 5.0f32)?;
+        let rectified = ops::greater(scope, rho_t, threshold)?;
+
+        let mut apply_ops = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
+                let m = create_zeros_slot(&mut scope.new_sub_scope("m"), var, None)?;
+                let v = create_zeros_slot(&mut scope.new_sub_scope("v"), var, None)?;
+                let scope = &mut scope;
+                // m_t = beta1 m + (1 - beta1) g
+                let decayed = ops::multiply(scope, beta1.clone(), m.output.clone())?;
+                let scaled = ops::multiply(scope, one_minus_beta1.clone(), grad.clone())?;
+                let m_t = ops::add(scope, decayed, scaled)?;
+                let m_t = ops::assign(scope, m.output.clone(), m_t)?;
+                // v_t = beta2 v + (1 - beta2) g^2
+                let decayed = ops::multiply(scope, beta2.clone(), v.output.clone())?;
+                let squared = ops::square(scope, grad.clone())?;
+                let scaled = ops::multiply(scope, one_minus_beta2.clone(), squared)?;
+                let v_t = ops::add(scope, decayed, scaled)?;
+                let v_t = ops::assign(scope, v.output.clone(), v_t)?;
+                let v_t = if self.amsgrad {
+                    amsgrad_max(scope, "v_max", var, v_t.into(), &mut variables)?
+                } else {
+                    v_t.into()
+                };
+                // Bias correction.
+                let m_hat = ops::divide(scope, m_t, beta1_correction.clone())?;
+                let v_hat = ops::divide(scope, v_t, beta2_correction.clone())?;
+                let v_hat = ops::sqrt(scope, v_hat)?;
+                let adaptive = ops::multiply(scope, rectification.clone(), m_hat.clone())?;
+                let denominator = ops::add(scope, v_hat, epsilon.clone())?;
+                let adaptive = ops::divide(scope, adaptive, denominator)?;
+                let update = ops::select(scope, rectified.clone(), adaptive, m_hat)?;
+                let update = ops::multiply(scope, learning_rate.clone(), update)?;
+                let new_value = ops::subtract(scope, var.output.clone(), update)?;
+                apply_ops.push(ops::assign(scope, var.output.clone(), new_value)?);
+                variables.push(m.clone());
+                variables.push(v.clone());
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((variables, no_op.build(scope)?))
+    }
+}
+
+/// Optimizer that implements the AdaBelief algorithm.
+///
+/// AdaBelief behaves like Adam, but scales steps by the variance of the
+/// gradient around its exponential moving average, i.e. by how far the
+/// gradient deviates from the predicted direction, rather than by the second
+/// moment of the gradient.  It takes large steps while the gradient is
+/// consistent and small ones when it is not.
+///
+/// See [Zhuang et al.](https://arxiv.org/abs/2010.07468).
+#[derive(Debug, Default)]
+pub struct AdaBeliefOptimizer {
+    learning_rate: Option<Output>,
+    beta1: Option<Output>,
+    beta2: Option<Output>,
+    epsilon: Option<Output>,
+    amsgrad: bool,
+}
+
+impl AdaBeliefOptimizer {
+    /// Creates a new optimizer with default parameters (learning_rate=0.001,
+    /// beta1=0.9, beta2=0.999, epsilon=1e-14).
+    pub fn new() -> Self {
+        Self {
+            learning_rate: None,
+            beta1: None,
+            beta2: None,
+            epsilon: None,
+            amsgrad: false,
+        }
+    }
+
+    /// Sets the learning rate.  Default is 0.001.
+    pub fn set_learning_rate<T: Into<Output>>(&mut self, learning_rate: T) {
+        self.learning_rate = Some(learning_rate.into());
+    }
+
+    /// Sets beta1, the decay rate of the first moment estimates.  Default is
+    /// 0.9.
+    pub fn set_beta1<T: Into<Output>>(&mut self, beta1: T) {
+        self.beta1 = Some(beta1.into());
+    }
+
+    /// Sets beta2, the decay rate of the gradient variance estimates.
+    /// Default is 0.999.
+    pub fn set_beta2<T: Into<Output>>(&mut self, beta2: T) {
+        self.beta2 = Some(beta2.into());
+    }
+
+    /// Sets epsilon, the conditioning.  Default is 1e-14.
+    pub fn set_epsilon<T: Into<Output>>(&mut self, epsilon: T) {
+        self.epsilon = Some(epsilon.into());
+    }
+
+    /// Enables the AMSGrad variant, which divides by the maximum of the
+    /// gradient variance estimates so far rather than the current estimate.
+    /// Default is false.
+    pub fn set_amsgrad(&mut self, amsgrad: bool) {
+        self.amsgrad = amsgrad;
+    }
+}
+
+impl Optimizer for AdaBeliefOptimizer {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let learning_rate = or_constant(scope, &self.learning_rate, 0.001f32)?;
+        let beta1 = or_constant(scope, &self.beta1, 0.9f32)?;
+        let beta2 = or_constant(scope, &self.beta2, 0.999f32)?;
+        let epsilon = or_constant(scope, &self.epsilon, 1e-14f32)?;
+        let (step, t) = create_step_counter(scope)?;
+        let mut variables = vec![step];
+
+        let one = ops::constant(scope, 1.0f32)?;
+        let one_minus_beta1 = ops::subtract(scope, one.clone(), beta1.clone())?;
+        let one_minus_beta2 = ops::subtract(scope, one.clone(), beta2.clone())?;
+        let beta1_power = ops::pow(scope, beta1.clone(), t.clone())?;
+        let beta1_correction = ops::subtract(scope, one.clone(), beta1_power)?;
+        let beta2_power = ops::pow(scope, beta2.clone(), t)?;
+        let beta2_correction = ops::subtract(scope, one, beta2_power)?;
+
+        let mut apply_ops = Vec::new();
+        for (grad, var) in opts.grads_and_vars {
+            if let Some(grad) = grad {
+                let mut scope = colocated_scope(scope, var)?.new_sub_scope(&var.name);
+                let m = create_zeros_slot(&mut scope.new_sub_scope("m"), var, None)?;
+                let s = create_zeros_slot(&mut scope.new_sub_scope("s"), var, None)?;
+                let scope = &mut scope;
+                // m_t = beta1 m + (1 - beta1) g
+                let decayed = ops::multiply(scope, beta1.clone(), m.output.clone())?;
+                let scaled = ops::multiply(scope, one_minus_beta1.clone(), grad.clone())?;
+                let m_t = ops::add(scope, decayed, scaled)?;
+                let m_t = ops::assign(scope, m.output.clone(), m_t)?;
+                // s_t = beta2 s + (1 - beta2) (g - m_t)^2 + epsilon
+                let decayed = ops::multiply(scope, beta2.clone(), s.output.clone())?;
+                let deviation = ops::subtract(scope, grad.clone(), m_t.clone())?;
+                let squared = ops::square(scope, deviation)?;
+                let scaled = ops::multiply(scope, one_minus_beta2.clone(), squared)?;
+                let s_t = ops::add(scope, decayed, scaled)?;
+                let s_t = ops::add(scope, s_t, epsilon.clone())?;
+                let s_t = ops::assign(scope, s.output.clone(), s_t)?;
+                let s_t = if self.amsgrad {
+                    amsgrad_max(scope, "s_max", var, s_t.into(), &mut variables)?
+                } else {
+                    s_t.into()
+                };
+                // Bias correction.
+                let m_hat = ops::divide(scope, m_t, beta1_correction.clone())?;
+                let s_hat = ops::divide(scope, s_t, beta2_correction.clone())?;
+                let s_hat = ops::sqrt(scope, s_hat)?;
+                let denominator = ops::add(scope, s_hat, epsilon.clone())?;
+                let update = ops::divide(scope, m_hat, denominator)?;
+                let update = ops::multiply(scope, learning_rate.clone(), update)?;
+                let new_value = ops::subtract(scope, var.output.clone(), update)?;
+                apply_ops.push(ops::assign(scope, var.output.clone(), new_value)?);
+                variables.push(m.clone());
+                variables.push(s.clone());
+            }
+        }
+        let mut no_op = ops::NoOp::new();
+        for apply_op in &apply_ops {
+            no_op = no_op.add_control_input(apply_op.clone());
+        }
+        Ok((variables, no_op.build(scope)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    #[test]
+    fn simple_gradient_descent() {
+        let mut scope = Scope::new_root_scope();
+        let x_var = Variable::builder()
+            .const_initial_value::<_, f32>(3.0)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let x_squared =
+            ops::multiply(&mut scope, x_var.output.clone(), x_var.output.clone()).unwrap();
+        let sgd = GradientDescentOptimizer {
+            learning_rate: Output {
+                operation: ops::constant(&mut scope, 0.1f32).unwrap(),
+                index: 0,
+            },
+        };
+        let (minimizer_vars, minimize) = sgd
+            .minimize(
+                &mut scope,
+                x_squared.into(),
+                MinimizeOptions::default().with_variables(&[x_var.clone()]),
+            )
+            .unwrap();
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        for var in &minimizer_vars {
+            run_args.add_target(&var.initializer);
+        }
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&minimize);
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+
+        session.run(&mut run_args).unwrap();
+        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
+        assert_eq!(x_output.len(), 1);
+        assert!(
+            x_output[0] >= 2.39 && x_output[0] <= 2.41,
+            "x_output[0] = {}",
+            x_output[0]
+        );
+
+        session.run(&mut run_args).unwrap();
+        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
+        assert_eq!(x_output.len(), 1);
+        assert!(
+            x_output[0] >= 1.91 && x_output[0] <= 1.93,
+            "x_output[0] = {}",
+            x_output[0]
+        );
+
+        session.run(&mut run_args).unwrap();
+        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
+        assert_eq!(x_output.len(), 1);
+        assert!(
+            x_output[0] >= 1.52 && x_output[0] <= 1.54,
+            "x_output[0] = {}",
+            x_output[0]
+        );
+    }
+
+    #[test]
+    fn simple_adadelta() {
+        let mut scope = Scope::new_root_scope();
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let x_squared =
+            ops::multiply(&mut scope, x_var.output.clone(), x_var.output.clone()).unwrap();
+        let mut optimizer = AdadeltaOptimizer::new();
+        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
+        let (minimizer_vars, minimize) = optimizer
+            .minimize(
+                &mut scope,
+                x_squared.into(),
+                MinimizeOptions::default().with_variables(&[x_var.clone()]),
+            )
+            .unwrap();
+        let options = SessionOptions::new();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        for var in &minimizer_vars {
+            run_args.add_target(&var.initializer);
+        }
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&minimize);
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+
+        session.run(&mut run_args).unwrap();
+        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
+        assert_eq!(x_output.len(), 1);
+        assert!(
+            x_output[0] >= 2.99994 && x_output[0] <= 2.99996,
+            "x_output[0] = {}",
+            x_output[0]
+        );
+
+        session.run(&mut run_args).unwrap();
+        let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
+        assert_eq!(x_output.len(), 1);
+        assert!(
+            x_output[0] >= 2.99990 && x_output[0] <= 2.99992,
+            "x_output[0] = {}",
+            x_output[0]
+        );
 
         session.run(&mut run_args).unwrap();
         let x_output = run_args.fetch::<f32>(x_fetch).unwrap();
@@ -403,4 +1515,334 @@ mod tests {
             x_output[0]
         );
     }
+
+    /// Minimizes x^2 from x = 3 with `optimizer` and returns x after each
+    /// step.
+    pub(super) fn minimize_x_squared<O: Optimizer>(
+        scope: &mut Scope,
+        optimizer: &O,
+        steps: usize,
+    ) -> Vec<f32> {
+        minimize_x_squared_with(scope, optimizer, steps, |options| options)
+    }
+
+    /// Like `minimize_x_squared`, but passes the options for `minimize`
+    /// through `options` first.
+    fn minimize_x_squared_with<O, F>(
+        scope: &mut Scope,
+        optimizer: &O,
+        steps: usize,
+        options: F,
+    ) -> Vec<f32>
+    where
+        O: Optimizer,
+        F: for<'a> FnOnce(MinimizeOptions<'a>) -> MinimizeOptions<'a>,
+    {
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let x_squared = ops::multiply(scope, x_var.output.clone(), x_var.output.clone()).unwrap();
+        let (minimizer_vars, minimize) = optimizer
+            .minimize(
+                scope,
+                x_squared.into(),
+                options(MinimizeOptions::default().with_variables(&[x_var.clone()])),
+            )
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        for var in &minimizer_vars {
+            run_args.add_target(&var.initializer);
+        }
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&minimize);
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+        (0..steps)
+            .map(|_| {
+                session.run(&mut run_args).unwrap();
+                run_args.fetch::<f32>(x_fetch).unwrap()[0]
+            })
+            .collect()
+    }
+
+    #[test]
+    fn momentum() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer =
+            MomentumOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        optimizer.set_momentum(ops::constant(&mut scope, 0.5f32).unwrap());
+        let xs = minimize_x_squared(&mut scope, &optimizer, 2);
+        // accum = 6, x = 3 - 0.1 * 6, then accum = 0.5 * 6 + 4.8 and
+        // x = 2.4 - 0.1 * 7.8.
+        for (x, expected) in xs.iter().zip(&[2.4f32, 1.62]) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn momentum_nesterov() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer =
+            MomentumOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        optimizer.set_momentum(ops::constant(&mut scope, 0.5f32).unwrap());
+        optimizer.set_use_nesterov(true);
+        let xs = minimize_x_squared(&mut scope, &optimizer, 2);
+        // Each step also moves by learning_rate * momentum * accum, so
+        // x = 3 - 0.1 * 6 - 0.05 * 6, then accum = 0.5 * 6 + 4.2 and
+        // x = 2.1 - 0.1 * 4.2 - 0.05 * 7.2.
+        for (x, expected) in xs.iter().zip(&[2.1f32, 1.32]) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn proximal_gradient_descent() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer = ProximalGradientDescentOptimizer::new(
+            ops::constant(&mut scope, 0.1f32).unwrap().into(),
+        );
+        optimizer.set_l1_regularization_strength(ops::constant(&mut scope, 0.1f32).unwrap());
+        let xs = minimize_x_squared(&mut scope, &optimizer, 3);
+        // Each step is a gradient step followed by shrinking towards zero by
+        // learning_rate * l1.
+        for (x, expected) in xs.iter().zip(&[2.39f32, 1.902, 1.5116]) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn proximal_adagrad() {
+        let mut scope = Scope::new_root_scope();
+        let optimizer =
+            ProximalAdagradOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        let xs = minimize_x_squared(&mut scope, &optimizer, 1);
+        // accum = 0.1 + 6^2, x = 3 - 0.1 * 6 / sqrt(accum)
+        assert!(xs[0] >= 2.9001 && xs[0] <= 2.9002, "x = {}", xs[0]);
+    }
+
+    #[test]
+    fn adagrad_da() {
+        let mut scope = Scope::new_root_scope();
+        let global_step = ops::constant(&mut scope, 0i64).unwrap();
+        let optimizer = AdagradDAOptimizer::new(
+            ops::constant(&mut scope, 0.1f32).unwrap().into(),
+            global_step.into(),
+        );
+        let xs = minimize_x_squared(&mut scope, &optimizer, 1);
+        // x = -0.1 * 6 / sqrt(0.1 + 6^2), regardless of the initial value.
+        assert!(xs[0] >= -0.0999 && xs[0] <= -0.0998, "x = {}", xs[0]);
+    }
+
+    #[test]
+    fn adam() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer = AdamOptimizer::new();
+        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
+        let xs = minimize_x_squared(&mut scope, &optimizer, 2);
+        // The first step is always the learning rate, since m_hat / sqrt(v_hat)
+        // is the sign of the gradient.
+        for (x, expected) in xs.iter().zip(&[2.9f32, 2.800103]) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn rms_prop() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer = RMSPropOptimizer::new();
+        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
+        optimizer.set_momentum(ops::constant(&mut scope, 0.5f32).unwrap());
+        let xs = minimize_x_squared(&mut scope, &optimizer, 2);
+        // ms = 0.1 * 6^2, x = 3 - 0.1 * 6 / sqrt(ms), and the second step
+        // adds half of the first.
+        for (x, expected) in xs.iter().zip(&[2.683772f32, 2.308707]) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn radam() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer = RAdamOptimizer::new();
+        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
+        let xs = minimize_x_squared(&mut scope, &optimizer, 2);
+        // The variance isn't tractable yet, so these are momentum steps with
+        // bias-corrected first moments.
+        for (x, expected) in xs.iter().zip(&[2.4f32, 1.863158]) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn radam_amsgrad() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer = RAdamOptimizer::new();
+        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
+        optimizer.set_beta2(ops::constant(&mut scope, 0.9f32).unwrap());
+        optimizer.set_amsgrad(true);
+        let xs = minimize_x_squared(&mut scope, &optimizer, 7);
+        // The first five steps are momentum steps, as for plain RAdam.  From
+        // the sixth, rho_t > 5 and the steps are divided by the maximum of the
+        // shrinking second moments, which gives x = 0.583599 and 0.557957
+        // rather than 0.582247 and 0.553879.
+        let expected = [
+            2.4f32, 1.863158, 1.386910, 0.968489, 0.604944, 0.583599, 0.557957,
+        ];
+        for (x, expected) in xs.iter().zip(&expected) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn ada_belief() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer = AdaBeliefOptimizer::new();
+        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
+        let xs = minimize_x_squared(&mut scope, &optimizer, 1);
+        // m_hat = 6, s_hat = (6 - 0.6)^2, x = 3 - 0.1 * 6 / 5.4
+        assert!((xs[0] - 2.888889).abs() < 1e-4, "x = {}", xs[0]);
+    }
+
+    #[test]
+    fn ada_belief_amsgrad() {
+        let mut scope = Scope::new_root_scope();
+        let mut optimizer = AdaBeliefOptimizer::new();
+        optimizer.set_learning_rate(ops::constant(&mut scope, 0.1f32).unwrap());
+        optimizer.set_beta2(ops::constant(&mut scope, 0.0f32).unwrap());
+        optimizer.set_amsgrad(true);
+        let xs = minimize_x_squared(&mut scope, &optimizer, 2);
+        // The second step's s_t = (5.78 - 1.12)^2 is smaller than the first
+        // step's 5.4^2, which is used instead, giving
+        // x = 2.888889 - 0.1 * 5.883041 / 5.4 rather than 2.762644.
+        for (x, expected) in xs.iter().zip(&[2.888889f32, 2.779944]) {
+            assert!(
+                (x - expected).abs() < 1e-4,
+                "x = {}, expected {}",
+                x,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn gradient_noise() {
+        let mut scope = Scope::new_root_scope();
+        let learning_rate = ops::constant(&mut scope, 0.1f32).unwrap();
+        let step = ops::constant(&mut scope, 0i64).unwrap();
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let x_squared =
+            ops::multiply(&mut scope, x_var.output.clone(), x_var.output.clone()).unwrap();
+        let optimizer = GradientDescentOptimizer::new(learning_rate.into());
+        let silent = GradientNoise::new(0.0, step.clone().into()).with_seed(1);
+        let loud = GradientNoise::new(1.0, step.clone().into()).with_seed(1);
+        let (_, quiet_step) = optimizer
+            .minimize(
+                &mut scope,
+                x_squared.clone().into(),
+                MinimizeOptions::default()
+                    .with_variables(&[x_var.clone()])
+                    .with_gradient_noise(silent),
+            )
+            .unwrap();
+        let (_, noisy_step) = optimizer
+            .minimize(
+                &mut scope,
+                x_squared.into(),
+                MinimizeOptions::default()
+                    .with_variables(&[x_var.clone()])
+                    .with_gradient_noise(loud),
+            )
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let run = |step: &Operation| {
+            let mut run_args = SessionRunArgs::new();
+            run_args.add_target(&x_var.initializer);
+            session.run(&mut run_args).unwrap();
+            let mut run_args = SessionRunArgs::new();
+            run_args.add_target(step);
+            let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+            session.run(&mut run_args).unwrap();
+            run_args.fetch::<f32>(x_fetch).unwrap()[0]
+        };
+        let quiet = run(&quiet_step);
+        assert!((quiet - 2.4).abs() < 1e-5, "x = {}", quiet);
+        let noisy = run(&noisy_step);
+        assert!((noisy - 2.4).abs() > 1e-5, "x = {}", noisy);
+
+        let bad = GradientNoise::new(-1.0, step.into());
+        assert!(add_gradient_noise(&mut scope, &[], &bad).is_err());
+    }
+
+    #[test]
+    fn gradient_clipping() {
+        let mut scope = Scope::new_root_scope();
+        let learning_rate = ops::constant(&mut scope, 0.1f32).unwrap();
+        let optimizer = GradientDescentOptimizer::new(learning_rate.into());
+        let xs = minimize_x_squared_with(&mut scope, &optimizer, 1, |options| {
+            options.with_gradient_clipping(GradientClipping::ClipByGlobalNorm(1.0))
+        });
+        // The gradient 6 is clipped to 1.
+        assert!((xs[0] - 2.9).abs() < 1e-5, "x = {}", xs[0]);
+    }
+
+    /// Returns constant gradients with the given values for new variables
+    /// of the same values, named by the paired names.
+    pub(super) fn constant_gradients(
+        scope: &mut Scope,
+        values: &[(&str, [f32; 2])],
+    ) -> Vec<(Option<Output>, Variable)> {
+        values
+            .iter()
+            .map(|(name, values)| {
+                let var = Variable::builder()
+                    .const_initial_value(Tensor::new(&[2]).with_values(values).unwrap())
+                    .build(&mut scope.with_op_name(name))
+                    .unwrap();
+                let grad = ops::constant(scope, &values[..]).unwrap();
+                (Some(grad.into()), var)
+            })
+            .collect()
+    }
 }
@@ -0,0 +1,505 @@
+use super::EpochMetrics;
+use super::Saver;
+use crate::Output;
+use crate::Result;
+use crate::Session;
+use std::ops::Range;
+
+/// Hooks which a `Trainer` calls at epoch and batch boundaries, e.g. to stop
+/// training early, save checkpoints or adjust hyperparameters.
+///
+/// All methods do nothing by default.  An error returned by a callback stops
+/// training and is returned from `Trainer::fit_with_callbacks`.
+pub trait Callback {
+    /// Called before each epoch.
+    fn on_epoch_begin(&mut self, _context: &mut CallbackContext<'_>) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after each training step with the index of the batch within
+    /// the epoch and its average loss.
+    fn on_batch_end(
+        &mut self,
+        _context: &mut CallbackContext<'_>,
+        _batch: usize,
+        _loss: f64,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after each epoch with its metrics.
+    fn on_epoch_end(
+        &mut self,
+        _context: &mut CallbackContext<'_>,
+        _metrics: &EpochMetrics,
+    ) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The state of training which is visible to a `Callback`.
+#[derive(Debug)]
+pub struct CallbackContext<'a> {
+    pub(crate) session: &'a Session,
+    pub(crate) epoch: usize,
+    pub(crate) epochs: Range<usize>,
+    pub(crate) batches_per_epoch: usize,
+    pub(crate) hyperparameters: &'a mut Vec<(Output, f32)>,
+    pub(crate) stop_training: &'a mut bool,
+}
+
+impl<'a> CallbackContext<'a> {
+    /// Returns the session being trained.
+    pub fn session(&self) -> &Session {
+        self.session
+    }
+
+    /// Returns the index of the current epoch, counting every epoch trained
+    /// by the trainer.
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Returns the epochs trained by the current call to
+    /// `Trainer::fit_with_callbacks`, unless training is stopped early.
+    pub fn epochs(&self) -> Range<usize> {
+        self.epochs.clone()
+    }
+
+    /// Returns the number of batches in each epoch.
+    pub fn batches_per_epoch(&self) -> usize {
+        self.batches_per_epoch
+    }
+
+    /// Returns the value fed to the placeholder `placeholder`, if it was set
+    /// with `Trainer::set_hyperparameter`.
+    pub fn hyperparameter(&self, placeholder: &Output) -> Option<f32> {
+        find_hyperparameter(self.hyperparameters, placeholder).map(|i| self.hyperparameters[i].1)
+    }
+
+    /// Sets the value fed to the placeholder `placeholder` from the next step
+    /// on.
+    pub fn set_hyperparameter(&mut self, placeholder: &Output, value: f32) {
+        set_hyperparameter(self.hyperparameters, placeholder, value);
+    }
+
+    /// Stops training at the end of the current epoch.
+    pub fn stop_training(&mut self) {
+        *self.stop_training = true;
+    }
+}
+
+pub(crate) fn find_hyperparameter(
+    hyperparameters: &[(Output, f32)],
+    placeholder: &Output,
+) -> Option<usize> {
+    hyperparameters.iter().position(|(output, _)| {
+        output.operation.inner() == placeholder.operation.inner()
+            && output.index == placeholder.index
+    })
+}
+
+pub(crate) fn set_hyperparameter(
+    hyperparameters: &mut Vec<(Output, f32)>,
+    placeholder: &Output,
+    value: f32,
+) {
+    match find_hyperparameter(hyperparameters, placeholder) {
+        Some(i) => hyperparameters[i].1 = value,
+        None => hyperparameters.push((placeholder.clone(), value)),
+    }
+}
+
+/// Returns the monitored value, which is either "loss" or the name of a
+/// metric.
+fn monitored_value(metrics: &EpochMetrics, monitor: &str) -> Result<f64> {
+    metrics
+        .get(monitor)
+        .ok_or_else(|| invalid_arg!("No metric named {} to monitor", monitor))
+}
+
+/// Tracks the best value of a monitored quantity.
+#[derive(Debug, Clone)]
+struct Best {
+    monitor: String,
+    maximize: bool,
+    min_delta: f64,
+    best: Option<f64>,
+}
+
+impl Best {
+    fn new(monitor: &str) -> Self {
+        Self {
+            monitor: monitor.to_string(),
+            maximize: false,
+            min_delta: 0.0,
+            best: None,
+        }
+    }
+
+    /// Returns whether the epoch improved on the best value so far.
+    fn update(&mut self, metrics: &EpochMetrics) -> Result<bool> {
+        let value = monitored_value(metrics, &self.monitor)?;
+        let improved = match self.best {
+            None => true,
+            Some(best) if self.maximize => value > best + self.min_delta,
+            Some(best) => value < best - self.min_delta,
+        };
+        if improved {
+            self.best = Some(value);
+        }
+        Ok(improved)
+    }
+}
+
+/// Stops training once a monitored quantity has stopped improving.
+#[derive(Debug, Clone)]
+pub struct EarlyStopping {
+    best: Best,
+    patience: usize,
+    wait: usize,
+    stopped_epoch: Option<usize>,
+}
+
+impl EarlyStopping {
+    /// Stops training after `patience` epochs without improvement in
+    /// `monitor`, which is either "loss" or the name of a metric.  By default,
+    /// lower values are better.
+    pub fn new(monitor: &str, patience: usize) -> Self {
+        Self {
+            best: Best::new(monitor),
+            patience,
+            wait: 0,
+            stopped_epoch: None,
+        }
+    }
+
+    /// Treats higher values as better, e.g. for accuracy.
+    pub fn maximize(mut self) -> Self {
+        self.best.maximize = true;
+        self
+    }
+
+    /// Sets the minimum change which counts as an improvement.
+    pub fn min_delta(mut self, min_delta: f64) -> Self {
+        self.best.min_delta = min_delta;
+        self
+    }
+
+    /// Returns the epoch after which training was stopped, if it was.
+    pub fn stopped_epoch(&self) -> Option<usize> {
+        self.stopped_epoch
+    }
+}
+
+impl Callback for EarlyStopping {
+    fn on_epoch_end(
+        &mut self,
+        context: &mut CallbackContext<'_>,
+        metrics: &EpochMetrics,
+    ) -> Result<()> {
+        if self.best.update(metrics)? {
+            self.wait = 0;
+        } else {
+            self.wait += 1;
+            if self.wait >= self.patience {
+                self.stopped_epoch = Some(context.epoch());
+                context.stop_training();
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Saves checkpoints periodically during training.
+#[derive(Debug, Clone)]
+pub struct ModelCheckpoint {
+    saver: Saver,
+    path: String,
+    every_epochs: usize,
+    epochs_since_save: usize,
+    best: Option<Best>,
+}
+
+impl ModelCheckpoint {
+    /// Saves a checkpoint with `saver` after every epoch.  `path` is the path
+    /// prefix of the checkpoint, where "{epoch}" is replaced with the index of
+    /// the epoch, e.g. "/tmp/model-{epoch}.ckpt".
+    pub fn new(saver: Saver, path: &str) -> Self {
+        Self {
+            saver,
+            path: path.to_string(),
+            every_epochs: 1,
+            epochs_since_save: 0,
+            best: None,
+        }
+    }
+
+    /// Saves a checkpoint only after every `n`th epoch.
+    pub fn every_epochs(self, n: usize) -> Self {
+        Self {
+            every_epochs: n.max(1),
+            ..self
+        }
+    }
+
+    /// Saves a checkpoint only when `monitor` (either "loss" or the name of a
+    /// metric) reaches a new minimum.
+    pub fn save_best_only(self, monitor: &str) -> Self {
+        Self {
+            best: Some(Best::new(monitor)),
+            ..self
+        }
+    }
+}
+
+impl Callback for ModelCheckpoint {
+    fn on_epoch_end(
+        &mut self,
+        context: &mut CallbackContext<'_>,
+        metrics: &EpochMetrics,
+    ) -> Result<()> {
+        self.epochs_since_save += 1;
+        if self.epochs_since_save < self.every_epochs {
+            return Ok(());
+        }
+        self.epochs_since_save = 0;
+        if let Some(best) = &mut self.best {
+            if !best.update(metrics)? {
+                return Ok(());
+            }
+        }
+        let path = self.path.replace("{epoch}", &context.epoch().to_string());
+        self.saver.save(context.session(), &path)
+    }
+}
+
+/// Reduces the learning rate when a monitored quantity has stopped improving.
+///
+/// The learning rate must be a placeholder whose value is set with
+/// `Trainer::set_hyperparameter`.
+#[derive(Debug, Clone)]
+pub struct ReduceLROnPlateau {
+    learning_rate: Output,
+    best: Best,
+    factor: f32,
+    patience: usize,
+    min_learning_rate: f32,
+    wait: usize,
+}
+
+impl ReduceLROnPlateau {
+    /// Multiplies the learning rate fed to `learning_rate` by 0.1 after 10
+    /// epochs without improvement in `monitor`, which is either "loss" or the
+    /// name of a metric.  Lower values are better.
+    pub fn new(learning_rate: Output, monitor: &str) -> Self {
+        Self {
+            learning_rate,
+            best: Best::new(monitor),
+            factor: 0.1,
+            patience: 10,
+            min_learning_rate: 0.0,
+            wait: 0,
+        }
+    }
+
+    /// Sets the factor the learning rate is multiplied by.
+    pub fn factor(self, factor: f32) -> Self {
+        Self { factor, ..self }
+    }
+
+    /// Sets the number of epochs without improvement before the learning rate
+    /// is reduced.
+    pub fn patience(self, patience: usize) -> Self {
+        Self { patience, ..self }
+    }
+
+    /// Sets the lowest learning rate to reduce to.
+    pub fn min_learning_rate(self, min_learning_rate: f32) -> Self {
+        Self {
+            min_learning_rate,
+            ..self
+        }
+    }
+}
+
+impl Callback for ReduceLROnPlateau {
+    fn on_epoch_end(
+        &mut self,
+        context: &mut CallbackContext<'_>,
+        metrics: &EpochMetrics,
+    ) -> Result<()> {
+        if self.best.update(metrics)? {
+            self.wait = 0;
+            return Ok(());
+        }
+        self.wait += 1;
+        if self.wait >= self.patience {
+            let learning_rate = context.hyperparameter(&self.learning_rate).ok_or_else(|| {
+                invalid_arg!(
+                    "The learning rate {:?} has not been set with Trainer::set_hyperparameter",
+                    self.learning_rate
+                )
+            })?;
+            let reduced = (learning_rate * self.factor).max(self.min_learning_rate);
+            context.set_hyperparameter(&self.learning_rate, reduced);
+            self.wait = 0;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::DataType;
+    use crate::Graph;
+    use crate::Scope;
+    use crate::SessionOptions;
+
+    fn epoch_end<C: Callback>(
+        callback: &mut C,
+        session: &Session,
+        epoch: usize,
+        hyperparameters: &mut Vec<(Output, f32)>,
+        loss: f64,
+    ) -> bool {
+        let mut stop_training = false;
+        let metrics = EpochMetrics {
+            loss,
+            metrics: vec![("accuracy".to_string(), -loss)],
+        };
+        callback
+            .on_epoch_end(
+                &mut CallbackContext {
+                    session,
+                    epoch,
+                    epochs: 0..10,
+                    batches_per_epoch: 1,
+                    hyperparameters,
+                    stop_training: &mut stop_training,
+                },
+                &metrics,
+            )
+            .unwrap();
+        stop_training
+    }
+
+    #[test]
+    fn early_stopping() {
+        let session = Session::new(&SessionOptions::new(), &Graph::new()).unwrap();
+        let mut hyperparameters = vec![];
+        let mut callback = EarlyStopping::new("loss", 2).min_delta(0.1);
+        for (epoch, &loss) in [1.0, 0.5, 0.45, 0.3, 0.3].iter().enumerate() {
+            assert!(!epoch_end(
+                &mut callback,
+                &session,
+                epoch,
+                &mut hyperparameters,
+                loss
+            ));
+        }
+        assert!(epoch_end(
+            &mut callback,
+            &session,
+            5,
+            &mut hyperparameters,
+            0.25
+        ));
+        assert_eq!(callback.stopped_epoch(), Some(5));
+
+        let mut callback = EarlyStopping::new("accuracy", 1).maximize();
+        assert!(!epoch_end(
+            &mut callback,
+            &session,
+            0,
+            &mut hyperparameters,
+            1.0
+        ));
+        assert!(!epoch_end(
+            &mut callback,
+            &session,
+            1,
+            &mut hyperparameters,
+            0.5
+        ));
+        assert!(epoch_end(
+            &mut callback,
+            &session,
+            2,
+            &mut hyperparameters,
+            0.7
+        ));
+
+        let mut callback = EarlyStopping::new("missing", 1);
+        let mut stop_training = false;
+        let metrics = EpochMetrics {
+            loss: 1.0,
+            metrics: vec![],
+        };
+        assert!(callback
+            .on_epoch_end(
+                &mut CallbackContext {
+                    session: &session,
+                    epoch: 0,
+                    epochs: 0..10,
+                    batches_per_epoch: 1,
+                    hyperparameters: &mut hyperparameters,
+                    stop_training: &mut stop_training,
+                },
+                &metrics,
+            )
+            .is_err());
+    }
+
+    #[test]
+    fn reduce_lr_on_plateau() {
+        let scope = Scope::new_root_scope();
+        let learning_rate: Output = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .build(&mut scope.with_op_name("learning_rate"))
+            .unwrap()
+            .into();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut callback = ReduceLROnPlateau::new(learning_rate.clone(), "loss")
+            .factor(0.5)
+            .patience(1)
+            .min_learning_rate(0.3);
+        let mut hyperparameters = vec![];
+        assert!(!epoch_end(
+            &mut callback,
+            &session,
+            0,
+            &mut hyperparameters,
+            1.0
+        ));
+        // The learning rate hasn't been set.
+        let mut stop_training = false;
+        let metrics = EpochMetrics {
+            loss: 1.0,
+            metrics: vec![],
+        };
+        assert!(callback
+            .on_epoch_end(
+                &mut CallbackContext {
+                    session: &session,
+                    epoch: 1,
+                    epochs: 0..10,
+                    batches_per_epoch: 1,
+                    hyperparameters: &mut hyperparameters,
+                    stop_training: &mut stop_training,
+                },
+                &metrics,
+            )
+            .is_err());
+
+        set_hyperparameter(&mut hyperparameters, &learning_rate, 1.0);
+        epoch_end(&mut callback, &session, 2, &mut hyperparameters, 2.0);
+        assert_eq!(hyperparameters[0].1, 0.5);
+        epoch_end(&mut callback, &session, 3, &mut hyperparameters, 0.5);
+        assert_eq!(hyperparameters[0].1, 0.5);
+        epoch_end(&mut callback, &session, 4, &mut hyperparameters, 0.5);
+        assert_eq!(hyperparameters[0].1, 0.3);
+    }
+}
@@ -0,0 +1,455 @@
+use crate::Result;
+use crate::Tensor;
+use crate::TensorType;
+
+/// A small, fast, seedable pseudorandom number generator (xorshift64*) for
+/// shuffling and splitting data reproducibly.
+///
+/// It is not suitable for cryptography or statistically demanding uses.
+// Not Copy, because copying a generator by accident repeats its sequence.
+#[allow(missing_copy_implementations)]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    /// Creates a generator whose sequence is determined by `seed`.
+    pub fn new(seed: u64) -> Self {
+        // xorshift requires a nonzero state.
+        let state = seed ^ 0x9e37_79b9_7f4a_7c15;
+        Self {
+            state: if state == 0 { 1 } else { state },
+        }
+    }
+
+    /// Returns the next number in the sequence.
+    pub fn next_u64(&mut self) -> u64 {
+        self.state ^= self.state >> 12;
+        self.state ^= self.state << 25;
+        self.state ^= self.state >> 27;
+        self.state.wrapping_mul(0x2545_f491_4f6c_dd1d)
+    }
+
+    /// Returns a number in `0..n`.  `n` must be positive.
+    pub fn below(&mut self, n: usize) -> usize {
+        (self.next_u64() % n as u64) as usize
+    }
+
+    /// Shuffles `values` with a Fisher-Yates shuffle.
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            let j = self.below(i + 1);
+            values.swap(i, j);
+        }
+    }
+
+    /// Returns the numbers `0..n` in random order.
+    pub fn permutation(&mut self, n: usize) -> Vec<usize> {
+        let mut indices: Vec<usize> = (0..n).collect();
+        self.shuffle(&mut indices);
+        indices
+    }
+}
+
+/// Randomly splits the indices `0..n` into training and validation indices,
+/// with `validation_fraction` (between 0 and 1) of them, rounded down, used
+/// for validation.  Both sets of indices are returned in increasing order.
+pub fn split_indices(
+    n: usize,
+    validation_fraction: f64,
+    rng: &mut Rng,
+) -> Result<(Vec<usize>, Vec<usize>)> {
+    if !(0.0..=1.0).contains(&validation_fraction) {
+        return Err(invalid_arg!(
+            "Validation fraction must be between 0 and 1, but was {}",
+            validation_fraction
+        ));
+    }
+    let mut indices = rng.permutation(n);
+    let validation_len = (n as f64 * validation_fraction) as usize;
+    let mut validation = indices.split_off(n - validation_len);
+    indices.sort_unstable();
+    validation.sort_unstable();
+    Ok((indices, validation))
+}
+
+/// Returns the rows of `tensor` (i.e. its slices along the first dimension)
+/// at `indices`, in that order.
+pub fn select_rows<T: TensorType>(tensor: &Tensor<T>, indices: &[usize]) -> Result<Tensor<T>> {
+    let mut dims = tensor.dims().to_vec();
+    if dims.is_empty() {
+        return Err(invalid_arg!("Cannot select rows of a scalar"));
+    }
+    let rows = dims[0] as usize;
+    let len = dims[1..].iter().product::<u64>() as usize;
+    dims[0] = indices.len() as u64;
+    let values = tensor.try_as_slice()?;
+    let mut selected = Tensor::<T>::new(&dims);
+    for (i, &index) in indices.iter().enumerate() {
+        if index >= rows {
+            return Err(invalid_arg!(
+                "Row {} is out of range for a tensor with {} rows",
+                index,
+                rows
+            ));
+        }
+        selected[i * len..(i + 1) * len].clone_from_slice(&values[index * len..(index + 1) * len]);
+    }
+    Ok(selected)
+}
+
+/// An iterator over batches of rows of a tensor, returned by `batches`.
+#[derive(Debug)]
+pub struct Batches<'a, T: TensorType> {
+    tensor: &'a Tensor<T>,
+    order: Vec<usize>,
+    batch_size: usize,
+    position: usize,
+}
+
+/// Returns batches of `batch_size` rows of `tensor`, taking the rows in the
+/// given order.  The last batch may be smaller.
+///
+/// To batch several tensors (e.g. features and labels) consistently, use the
+/// same order for each:
+///
+/// ```ignore
+/// let order = rng.permutation(num_examples);
+/// for (x, y) in batches(&features, &order, 32)?.zip(batches(&labels, &order, 32)?) {
+///     let (x, y) = (x?, y?);
+///     // Feed x and y.
+/// }
+/// ```
+pub fn batches<'a, T: TensorType>(
+    tensor: &'a Tensor<T>,
+    order: &[usize],
+    batch_size: usize,
+) -> Result<Batches<'a, T>> {
+    if batch_size == 0 {
+        return Err(invalid_arg!("Batch size must be positive"));
+    }
+    Ok(Batches {
+        tensor,
+        order: order.to_vec(),
+        batch_size,
+        position: 0,
+    })
+}
+
+impl<'a, T: TensorType> Iterator for Batches<'a, T> {
+    type Item = Result<Tensor<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.order.len() {
+            return None;
+        }
+        let end = (self.position + self.batch_size).min(self.order.len());
+        let batch = select_rows(self.tensor, &self.order[self.position..end]);
+        self.position = end;
+        Some(batch)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let batches = self.order[self.position..].chunks(self.batch_size).len();
+        (batches, Some(batches))
+    }
+}
+
+impl<'a, T: TensorType> ExactSizeIterator for Batches<'a, T> {}
+
+/// How `pad_and_stack` and `padded_batches` pad examples of different shapes.
+#[derive(Debug, Clone)]
+pub struct Padding<T: TensorType> {
+    shape: Option<Vec<u64>>,
+    value: T,
+}
+
+impl<T: TensorType> Padding<T> {
+    /// Pads with `value` to the largest size of each dimension in the batch.
+    pub fn new(value: T) -> Self {
+        Self { shape: None, value }
+    }
+
+    /// Pads every example to `shape` instead, so that all batches have the
+    /// same shape.  Examples larger than `shape` are an error.
+    pub fn with_shape(self, shape: &[u64]) -> Self {
+        Self {
+            shape: Some(shape.to_vec()),
+            ..self
+        }
+    }
+}
+
+/// Pads `examples`, which must all have the same rank, to a common shape and
+/// stacks them into a tensor with a new outer dimension, e.g. turning
+/// sequences of shape `[length_i, features]` into a batch of shape
+/// `[examples.len(), max_length, features]`.
+pub fn pad_and_stack<T: TensorType>(
+    examples: &[&Tensor<T>],
+    padding: &Padding<T>,
+) -> Result<Tensor<T>> {
+    let rank = match examples.first() {
+        Some(example) => example.dims().len(),
+        None => return Err(invalid_arg!("Cannot stack an empty list of examples")),
+    };
+    if let Some(example) = examples.iter().find(|e| e.dims().len() != rank) {
+        return Err(invalid_arg!(
+            "Cannot stack examples of shapes {:?} and {:?}",
+            examples[0].dims(),
+            example.dims()
+        ));
+    }
+    let padded = match &padding.shape {
+        Some(shape) => {
+            if shape.len() != rank {
+                return Err(invalid_arg!(
+                    "Cannot pad examples of rank {} to shape {:?}",
+                    rank,
+                    shape
+                ));
+            }
+            if let Some(example) = examples
+                .iter()
+                .find(|e| e.dims().iter().zip(shape).any(|(d, s)| d > s))
+            {
+                return Err(invalid_arg!(
+                    "An example of shape {:?} doesn't fit in the padded shape {:?}",
+                    example.dims(),
+                    shape
+                ));
+            }
+            shape.clone()
+        }
+        None => (0..rank)
+            .map(|i| examples.iter().map(|e| e.dims()[i]).max().unwrap_or(0))
+            .collect(),
+    };
+    let mut dims = vec![examples.len() as u64];
+    dims.extend(&padded);
+    let mut stacked = Tensor::<T>::new(&dims);
+    for value in stacked.iter_mut() {
+        *value = padding.value.clone();
+    }
+    let example_len = padded.iter().product::<u64>() as usize;
+    for (n, example) in examples.iter().enumerate() {
+        let example_dims = example.dims();
+        for (i, value) in example.iter().enumerate() {
+            // Convert the index within the example to an index within the
+            // padded example, innermost dimension first.
+            let mut rest = i as u64;
+            let mut offset = 0;
+            let mut stride = 1;
+            for (&dim, &padded_dim) in example_dims.iter().zip(&padded).rev() {
+                offset += (rest % dim) * stride;
+                rest /= dim;
+                stride *= padded_dim;
+            }
+            stacked[n * example_len + offset as usize] = value.clone();
+        }
+    }
+    Ok(stacked)
+}
+
+/// An iterator over padded batches of examples, returned by `padded_batches`.
+#[derive(Debug)]
+pub struct PaddedBatches<'a, T: TensorType> {
+    examples: &'a [Tensor<T>],
+    order: Vec<usize>,
+    batch_size: usize,
+    padding: Padding<T>,
+    position: usize,
+}
+
+/// Returns batches of `batch_size` examples, taken in the given order and
+/// combined with `pad_and_stack`.  The last batch may be smaller.
+///
+/// This is the counterpart of `batches` for examples of different shapes,
+/// such as variable-length sequences.  To batch several components of the
+/// examples (e.g. tokens and labels), use the same order for each, with its
+/// own padding.
+pub fn padded_batches<'a, T: TensorType>(
+    examples: &'a [Tensor<T>],
+    order: &[usize],
+    batch_size: usize,
+    padding: Padding<T>,
+) -> Result<PaddedBatches<'a, T>> {
+    if batch_size == 0 {
+        return Err(invalid_arg!("Batch size must be positive"));
+    }
+    if let Some(&index) = order.iter().find(|&&i| i >= examples.len()) {
+        return Err(invalid_arg!(
+            "Example {} is out of range for {} examples",
+            index,
+            examples.len()
+        ));
+    }
+    Ok(PaddedBatches {
+        examples,
+        order: order.to_vec(),
+        batch_size,
+        padding,
+        position: 0,
+    })
+}
+
+impl<'a, T: TensorType> Iterator for PaddedBatches<'a, T> {
+    type Item = Result<Tensor<T>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.position >= self.order.len() {
+            return None;
+        }
+        let end = (self.position + self.batch_size).min(self.order.len());
+        let batch: Vec<&Tensor<T>> = self.order[self.position..end]
+            .iter()
+            .map(|&i| &self.examples[i])
+            .collect();
+        self.position = end;
+        Some(pad_and_stack(&batch, &self.padding))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let batches = self.order[self.position..].chunks(self.batch_size).len();
+        (batches, Some(batches))
+    }
+}
+
+impl<'a, T: TensorType> ExactSizeIterator for PaddedBatches<'a, T> {}
+
+/// Groups examples of similar length into batches, to reduce the amount of
+/// padding needed for variable-length sequences.
+///
+/// Example `i` has length `lengths[i]` and goes into bucket `b` if
+/// `boundaries[b - 1] <= lengths[i] < boundaries[b]`, so there is one more
+/// bucket than boundaries.  Examples are taken in the given order, and a
+/// bucket becomes a batch whenever it holds the number of examples given by
+/// `batch_sizes` for that bucket.  Partial batches are returned at the end.
+///
+/// Returns the indices of the examples in each batch, e.g. for
+/// `pad_and_stack`.
+pub fn bucket_by_sequence_length(
+    lengths: &[usize],
+    order: &[usize],
+    boundaries: &[usize],
+    batch_sizes: &[usize],
+) -> Result<Vec<Vec<usize>>> {
+    if batch_sizes.len() != boundaries.len() + 1 {
+        return Err(invalid_arg!(
+            "There must be one more batch size than bucket boundaries, but got {} and {}",
+            batch_sizes.len(),
+            boundaries.len()
+        ));
+    }
+    if boundaries.windows(2).any(|w| w[0] >= w[1]) {
+        return Err(invalid_arg!(
+            "Bucket boundaries must be increasing, but are {:?}",
+            boundaries
+        ));
+    }
+    if batch_sizes.contains(&0) {
+        return Err(invalid_arg!("Batch sizes must be positive"));
+    }
+    let mut buckets: Vec<Vec<usize>> = vec![Vec::new(); batch_sizes.len()];
+    let mut batches = Vec::new();
+    for &index in order {
+        let length = match lengths.get(index) {
+            Some(&length) => length,
+            None => {
+                return Err(invalid_arg!(
+                    "Example {} is out of range for {} examples",
+                    index,
+                    lengths.len()
+                ))
+            }
+        };
+        let bucket = boundaries.iter().take_while(|&&b| b <= length).count();
+        buckets[bucket].push(index);
+        if buckets[bucket].len() == batch_sizes[bucket] {
+            batches.push(std::mem::take(&mut buckets[bucket]));
+        }
+    }
+    batches.extend(buckets.into_iter().filter(|bucket| !bucket.is_empty()));
+    Ok(batches)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn shuffle_is_a_permutation() {
+        let mut values: Vec<usize> = (0..100).collect();
+        Rng::new(1).shuffle(&mut values);
+        assert_ne!(values, (0..100).collect::<Vec<_>>());
+        values.sort();
+        assert_eq!(values, (0..100).collect::<Vec<_>>());
+        assert_eq!(Rng::new(5).permutation(10), Rng::new(5).permutation(10));
+        assert_ne!(Rng::new(5).permutation(10), Rng::new(6).permutation(10));
+    }
+
+    #[test]
+    fn split() {
+        let (train, validation) = split_indices(10, 0.25, &mut Rng::new(3)).unwrap();
+        assert_eq!(train.len(), 8);
+        assert_eq!(validation.len(), 2);
+        let mut all: Vec<usize> = train.iter().chain(&validation).cloned().collect();
+        all.sort();
+        assert_eq!(all, (0..10).collect::<Vec<_>>());
+        assert!(split_indices(10, 1.5, &mut Rng::new(3)).is_err());
+    }
+
+    #[test]
+    fn select_and_batch() {
+        let x = Tensor::new(&[3, 2])
+            .with_values(&[0, 1, 2, 3, 4, 5])
+            .unwrap();
+        let selected = select_rows(&x, &[2, 0]).unwrap();
+        assert_eq!(selected.dims(), &[2, 2]);
+        assert_eq!(&selected[..], &[4, 5, 0, 1]);
+        assert!(select_rows(&x, &[3]).is_err());
+        assert!(select_rows(&Tensor::from(1), &[0]).is_err());
+
+        let values: Vec<_> = batches(&x, &[1, 2, 0], 2)
+            .unwrap()
+            .map(|batch| batch.unwrap().to_vec())
+            .collect();
+        assert_eq!(values, vec![vec![2, 3, 4, 5], vec![0, 1]]);
+        assert_eq!(batches(&x, &[0, 1, 2], 2).unwrap().len(), 2);
+        assert!(batches(&x, &[0], 0).is_err());
+    }
+
+    #[test]
+    fn padding() {
+        let a = Tensor::new(&[2, 1]).with_values(&[1, 2]).unwrap();
+        let b = Tensor::new(&[3, 1]).with_values(&[3, 4, 5]).unwrap();
+        let stacked = pad_and_stack(&[&a, &b], &Padding::new(-1)).unwrap();
+        assert_eq!(stacked.dims(), &[2, 3, 1]);
+        assert_eq!(&stacked[..], &[1, 2, -1, 3, 4, 5]);
+        let fixed = pad_and_stack(&[&a], &Padding::new(0).with_shape(&[2, 2])).unwrap();
+        assert_eq!(&fixed[..], &[1, 0, 2, 0]);
+        assert!(pad_and_stack(&[&b], &Padding::new(0).with_shape(&[2, 1])).is_err());
+        assert!(pad_and_stack(&[&a, &Tensor::from(1)], &Padding::new(0)).is_err());
+
+        let examples = vec![a, b];
+        let values: Vec<_> = padded_batches(&examples, &[1, 0], 1, Padding::new(0))
+            .unwrap()
+            .map(|batch| batch.unwrap().to_vec())
+            .collect();
+        assert_eq!(values, vec![vec![3, 4, 5], vec![1, 2]]);
+        assert!(padded_batches(&examples, &[2], 1, Padding::new(0)).is_err());
+    }
+
+    #[test]
+    fn bucketing() {
+        let lengths = [1, 8, 2, 9, 3, 20];
+        let batches =
+            bucket_by_sequence_length(&lengths, &[0, 1, 2, 3, 4, 5], &[5, 10], &[2, 2, 2]).unwrap();
+        assert_eq!(batches, vec![vec![0, 2], vec![1, 3], vec![4], vec![5]]);
+        assert!(bucket_by_sequence_length(&lengths, &[0], &[5], &[2]).is_err());
+        assert!(bucket_by_sequence_length(&lengths, &[0], &[5, 5], &[1, 1, 1]).is_err());
+        assert!(bucket_by_sequence_length(&lengths, &[6], &[], &[1]).is_err());
+    }
+}
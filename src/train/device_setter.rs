@@ -0,0 +1,158 @@
+use crate::ClusterSpec;
+use crate::Result;
+use crate::Scope;
+use crate::Variable;
+use crate::VariableBuilder;
+
+/// Assigns devices for between-graph replicated training with parameter
+/// servers.
+///
+/// Each worker process builds its own copy of the graph.  Variables are
+/// placed on the parameter server ("ps") tasks in round-robin order, so every
+/// worker shares them, while all other ops run on the worker's own task.
+/// Optimizers place their state and updates alongside each variable, so the
+/// parameter servers apply the updates sent by each worker asynchronously.
+///
+/// Because variables are assigned in creation order, every worker must create
+/// its variables in the same order to agree on their placement.
+///
+/// ```
+/// # use tensorflow::ClusterSpec;
+/// # use tensorflow::train::ReplicaDeviceSetter;
+/// let cluster = ClusterSpec::new()
+///     .job("ps", &["ps0:2222", "ps1:2222"])
+///     .job("worker", &["worker0:2222", "worker1:2222"]);
+/// let mut setter = ReplicaDeviceSetter::new(&cluster, 1)?;
+/// assert_eq!(setter.worker_device(), "/job:worker/task:1");
+/// assert_eq!(setter.next_ps_device(), "/job:ps/task:0");
+/// assert_eq!(setter.next_ps_device(), "/job:ps/task:1");
+/// assert_eq!(setter.next_ps_device(), "/job:ps/task:0");
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+#[derive(Debug, Clone)]
+pub struct ReplicaDeviceSetter {
+    ps_devices: Vec<String>,
+    worker_device: String,
+    next_ps: usize,
+}
+
+impl ReplicaDeviceSetter {
+    /// Creates a device setter for task `worker_task` of the "worker" job,
+    /// placing variables on the tasks of the "ps" job of `cluster`.
+    pub fn new(cluster: &ClusterSpec, worker_task: i32) -> Result<Self> {
+        if cluster.task_address("worker", worker_task).is_none() {
+            return Err(invalid_arg!(
+                "Cluster has no task {} in job worker",
+                worker_task
+            ));
+        }
+        let ps_devices: Vec<String> = cluster
+            .task_indices("ps")
+            .into_iter()
+            .map(|task| format!("/job:ps/task:{}", task))
+            .collect();
+        Self::from_devices(ps_devices, &format!("/job:worker/task:{}", worker_task))
+    }
+
+    /// Creates a device setter from explicit device names, e.g. to place
+    /// variables on specific devices of the parameter servers.
+    pub fn from_devices(ps_devices: Vec<String>, worker_device: &str) -> Result<Self> {
+        if ps_devices.is_empty() {
+            return Err(invalid_arg!("At least one parameter server is required"));
+        }
+        Ok(Self {
+            ps_devices,
+            worker_device: worker_device.to_string(),
+            next_ps: 0,
+        })
+    }
+
+    /// Returns the device of this worker.
+    pub fn worker_device(&self) -> &str {
+        &self.worker_device
+    }
+
+    /// Returns a scope which places ops on this worker.
+    pub fn worker_scope(&self, scope: &Scope) -> Scope {
+        scope.with_device(&self.worker_device)
+    }
+
+    /// Returns the parameter server device the next variable should be placed
+    /// on.
+    pub fn next_ps_device(&mut self) -> &str {
+        let index = self.next_ps;
+        self.next_ps = (self.next_ps + 1) % self.ps_devices.len();
+        &self.ps_devices[index]
+    }
+
+    /// Builds a variable named `name` on the next parameter server.  Its
+    /// initializer runs on the same parameter server.
+    pub fn variable(
+        &mut self,
+        scope: &Scope,
+        name: &str,
+        builder: VariableBuilder<'_>,
+    ) -> Result<Variable> {
+        let device = self.next_ps_device().to_string();
+        builder.build(&mut scope.with_device(&device).with_op_name(name))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::train::GradientDescentOptimizer;
+    use crate::train::MinimizeOptions;
+    use crate::train::Optimizer;
+
+    #[test]
+    fn round_robin() {
+        let cluster = ClusterSpec::new()
+            .job("ps", &["ps0:2222", "ps1:2222"])
+            .job("worker", &["worker0:2222"]);
+        let mut setter = ReplicaDeviceSetter::new(&cluster, 0).unwrap();
+        let root = Scope::new_root_scope();
+        let a = setter
+            .variable(&root, "a", Variable::builder().const_initial_value(1.0f32))
+            .unwrap();
+        let b = setter
+            .variable(&root, "b", Variable::builder().const_initial_value(2.0f32))
+            .unwrap();
+        let c = setter
+            .variable(&root, "c", Variable::builder().const_initial_value(3.0f32))
+            .unwrap();
+        assert_eq!(a.output().operation.device().unwrap(), "/job:ps/task:0");
+        assert_eq!(a.initializer().device().unwrap(), "/job:ps/task:0");
+        assert_eq!(b.output().operation.device().unwrap(), "/job:ps/task:1");
+        assert_eq!(c.output().operation.device().unwrap(), "/job:ps/task:0");
+
+        let mut scope = setter.worker_scope(&root);
+        let loss = ops::multiply(&mut scope, a.output().clone(), b.output().clone()).unwrap();
+        assert_eq!(loss.device().unwrap(), "/job:worker/task:0");
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        optimizer
+            .minimize(
+                &mut scope,
+                loss.into(),
+                MinimizeOptions::default().with_variables(&[a, b]),
+            )
+            .unwrap();
+        let graph = scope.graph();
+        let apply_devices: Vec<_> = graph
+            .operation_iter()
+            .filter(|op| op.op_type().unwrap() == "ApplyGradientDescent")
+            .map(|op| op.device().unwrap())
+            .collect();
+        assert_eq!(apply_devices, vec!["/job:ps/task:0", "/job:ps/task:1"]);
+    }
+
+    #[test]
+    fn requires_ps() {
+        let cluster = ClusterSpec::new().job("worker", &["worker0:2222"]);
+        assert!(ReplicaDeviceSetter::new(&cluster, 0).is_err());
+        let cluster = cluster.job("ps", &["ps0:2222"]);
+        assert!(ReplicaDeviceSetter::new(&cluster, 1).is_err());
+    }
+}
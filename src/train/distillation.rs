@@ -0,0 +1,303 @@
+use super::sparse_softmax_cross_entropy;
+use super::LossOptions;
+use crate::ops;
+use crate::Graph;
+use crate::Output;
+use crate::Result;
+use crate::SavedModelBundle;
+use crate::Scope;
+use crate::Session;
+use crate::SessionOptions;
+use crate::SessionRunArgs;
+use crate::Tensor;
+use crate::TensorType;
+use std::path::Path;
+
+/// Options for `distillation_loss`.
+#[derive(Debug, Clone)]
+pub struct DistillationOptions {
+    temperature: f32,
+    soft_weight: f32,
+    loss_options: LossOptions,
+}
+
+impl Default for DistillationOptions {
+    fn default() -> Self {
+        Self {
+            temperature: 2.0,
+            soft_weight: 0.5,
+            loss_options: LossOptions::default(),
+        }
+    }
+}
+
+impl DistillationOptions {
+    /// Sets the temperature which softens both models' distributions, so
+    /// the student learns how the teacher ranks unlikely classes.  Default
+    /// is 2.
+    pub fn with_temperature(self, temperature: f32) -> Self {
+        Self {
+            temperature,
+            ..self
+        }
+    }
+
+    /// Sets the weight of the soft-target loss, between 0 and 1.  The
+    /// hard-label loss has weight `1 - soft_weight`.  Default is 0.5.
+    pub fn with_soft_weight(self, soft_weight: f32) -> Self {
+        Self {
+            soft_weight,
+            ..self
+        }
+    }
+
+    /// Sets the options of the hard-label cross-entropy, e.g. class weights.
+    pub fn with_loss_options(self, loss_options: LossOptions) -> Self {
+        Self {
+            loss_options,
+            ..self
+        }
+    }
+}
+
+/// Returns the loss for training a student model to match a teacher model.
+///
+/// The loss is a weighted sum of two scalars: the KL divergence from the
+/// teacher's to the student's distribution, both computed from logits
+/// divided by the temperature, and the cross-entropy of the student's logits
+/// with the true labels.  The divergence is multiplied by the square of the
+/// temperature, which keeps its gradients on the same scale as those of the
+/// cross-entropy.  `student_logits` and `teacher_logits` have shape
+/// `[batch_size, num_classes]`, and `labels` is an int32 or int64 vector of
+/// class indices.
+///
+/// The teacher's logits are usually fed from a placeholder, with values
+/// computed by a `Teacher`.
+pub fn distillation_loss(
+    scope: &mut Scope,
+    student_logits: Output,
+    teacher_logits: Output,
+    labels: Output,
+    opts: DistillationOptions,
+) -> Result<Output> {
+    if opts.temperature <= 0.0 {
+        return Err(invalid_arg!(
+            "Temperature must be positive, got {}",
+            opts.temperature
+        ));
+    }
+    if !(0.0..=1.0).contains(&opts.soft_weight) {
+        return Err(invalid_arg!(
+            "Soft weight must be in [0, 1], got {}",
+            opts.soft_weight
+        ));
+    }
+    let mut scope = scope.new_sub_scope("distillation_loss");
+    let temperature = ops::constant(&mut scope, opts.temperature)?;
+    let student = ops::divide(&mut scope, student_logits.clone(), temperature.clone())?;
+    let teacher = ops::divide(&mut scope, teacher_logits, temperature)?;
+    let soft_targets = ops::softmax(&mut scope, teacher.clone())?;
+    // KL(p || q) is the cross-entropy of q with p minus the entropy of p.
+    let cross_entropy =
+        ops::softmax_cross_entropy_with_logits(&mut scope, student, soft_targets.clone())?;
+    let entropy = ops::softmax_cross_entropy_with_logits(&mut scope, teacher, soft_targets)?;
+    let divergence = ops::subtract(&mut scope, cross_entropy, entropy)?;
+    let axis = ops::constant(&mut scope, 0i32)?;
+    let divergence = ops::mean(&mut scope, divergence, axis)?;
+    let hard = sparse_softmax_cross_entropy(&mut scope, student_logits, labels, opts.loss_options)?;
+    let soft_scale = ops::constant(
+        &mut scope,
+        opts.soft_weight * opts.temperature * opts.temperature,
+    )?;
+    let hard_scale = ops::constant(&mut scope, 1.0 - opts.soft_weight)?;
+    let soft = ops::multiply(&mut scope, divergence, soft_scale)?;
+    let hard = ops::multiply(&mut scope, hard, hard_scale)?;
+    Ok(ops::add(&mut scope, soft, hard)?.into())
+}
+
+/// A trained model whose logits a student model learns from.
+///
+/// ```ignore
+/// let teacher = Teacher::load("/models/teacher/1", &["serve"], "input", "logits")?;
+/// let data = TrainingData::new()
+///     .with_input(x.into(), examples.clone())
+///     .with_input(labels.into(), label_values)
+///     .with_input(teacher_logits.into(), teacher.logits(&examples)?);
+/// ```
+///
+/// The teacher runs in its own graph and session, so its variables are never
+/// trained.  Its logits are computed once for all examples, rather than for
+/// every batch of every epoch.
+#[derive(Debug)]
+pub struct Teacher {
+    graph: Graph,
+    session: Session,
+    input: Output,
+    logits: Output,
+    batch_size: u64,
+}
+
+impl Teacher {
+    /// Loads the teacher from the SavedModel in `export_dir` with the given
+    /// tags.  `input` and `logits` are the names of the tensors fed with
+    /// examples and fetched as logits, either `"op:index"` or just `"op"`
+    /// for output 0 of the op.
+    pub fn load<P: AsRef<Path>>(
+        export_dir: P,
+        tags: &[&str],
+        input: &str,
+        logits: &str,
+    ) -> Result<Self> {
+        let mut graph = Graph::new();
+        let bundle = SavedModelBundle::load(&SessionOptions::new(), tags, &mut graph, export_dir)?;
+        Self::new(graph, bundle.session, input, logits)
+    }
+
+    /// Creates a teacher which runs `graph` in `session`.
+    pub fn new(graph: Graph, session: Session, input: &str, logits: &str) -> Result<Self> {
+        let input = graph.output_by_name_required(input)?;
+        let logits = graph.output_by_name_required(logits)?;
+        Ok(Self {
+            graph,
+            session,
+            input,
+            logits,
+            batch_size: 256,
+        })
+    }
+
+    /// Sets the number of examples the teacher runs on at a time.  Default
+    /// is 256.
+    pub fn with_batch_size(self, batch_size: u64) -> Self {
+        Self {
+            batch_size: batch_size.max(1),
+            ..self
+        }
+    }
+
+    /// Returns the teacher's graph.
+    pub fn graph(&self) -> &Graph {
+        &self.graph
+    }
+
+    /// Returns the teacher's logits for `examples`, whose first dimension
+    /// indexes the examples.
+    pub fn logits<T: TensorType>(&self, examples: &Tensor<T>) -> Result<Tensor<f32>> {
+        let dims = examples.dims();
+        if dims.is_empty() {
+            return Err(invalid_arg!("Examples must have a batch dimension"));
+        }
+        let row_len = dims[1..].iter().product::<u64>() as usize;
+        let mut values = Vec::new();
+        let mut logits_dims = None;
+        let mut start = 0;
+        while start < dims[0] {
+            let rows = self.batch_size.min(dims[0] - start);
+            let mut batch_dims = dims.to_vec();
+            batch_dims[0] = rows;
+            let begin = start as usize * row_len;
+            let batch = Tensor::new(&batch_dims)
+                .with_values(&examples[begin..begin + rows as usize * row_len])?;
+            let mut args = SessionRunArgs::new();
+            args.add_feed(&self.input.operation, self.input.index, &batch);
+            let token = args.request_fetch(&self.logits.operation, self.logits.index);
+            self.session.run(&mut args)?;
+            let logits = args.fetch::<f32>(token)?;
+            logits_dims.get_or_insert_with(|| logits.dims()[1..].to_vec());
+            values.extend_from_slice(&logits);
+            start += rows;
+        }
+        let mut result_dims = vec![dims[0]];
+        result_dims.extend(logits_dims.unwrap_or_default());
+        Tensor::new(&result_dims).with_values(&values)
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+    use crate::DataType;
+    use crate::ImportGraphDefOptions;
+    use crate::Shape;
+
+    #[test]
+    fn loss() {
+        let mut scope = Scope::new_root_scope();
+        let logits = |scope: &mut Scope, values: &[f32]| -> Output {
+            ops::constant(scope, Tensor::new(&[1, 2]).with_values(values).unwrap())
+                .unwrap()
+                .into()
+        };
+        let uniform = logits(&mut scope, &[0.0, 0.0]);
+        let confident = logits(&mut scope, &[4.0, 0.0]);
+        let labels: Output = ops::constant(&mut scope, &[0i32][..]).unwrap().into();
+        // Matching the teacher gives no divergence, so only the hard loss of
+        // ln(2) remains.
+        let matching = distillation_loss(
+            &mut scope,
+            uniform.clone(),
+            uniform.clone(),
+            labels.clone(),
+            DistillationOptions::default(),
+        )
+        .unwrap();
+        let soft_only = distillation_loss(
+            &mut scope,
+            uniform.clone(),
+            confident,
+            labels.clone(),
+            DistillationOptions::default()
+                .with_temperature(1.0)
+                .with_soft_weight(1.0),
+        )
+        .unwrap();
+        assert!(distillation_loss(
+            &mut scope,
+            uniform.clone(),
+            uniform.clone(),
+            labels.clone(),
+            DistillationOptions::default().with_temperature(0.0),
+        )
+        .is_err());
+        assert!((fetch::<f32>(&scope, &matching)[0] - 0.5 * 2.0f32.ln()).abs() < 1e-5);
+        // KL(p || uniform) = ln(2) - H(p).
+        let p = 1.0 / (1.0 + (-4.0f32).exp());
+        let entropy = -p * p.ln() - (1.0 - p) * (1.0 - p).ln();
+        let expected = 2.0f32.ln() - entropy;
+        let actual = fetch::<f32>(&scope, &soft_only)[0];
+        assert!((actual - expected).abs() < 1e-5, "loss = {}", actual);
+    }
+
+    #[test]
+    fn teacher_logits() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None, Some(2)])))
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let two = ops::constant(&mut scope, 2.0f32).unwrap();
+        ops::multiply(&mut scope.with_op_name("logits"), x, two).unwrap();
+        let mut graph = Graph::new();
+        graph
+            .import_graph_def(
+                &scope.graph().graph_def().unwrap(),
+                &ImportGraphDefOptions::new(),
+            )
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &graph).unwrap();
+        let teacher = Teacher::new(graph, session, "x", "logits")
+            .unwrap()
+            .with_batch_size(2);
+        let examples = Tensor::new(&[3, 2])
+            .with_values(&[1.0f32, 2.0, 3.0, 4.0, 5.0, 6.0])
+            .unwrap();
+        let logits = teacher.logits(&examples).unwrap();
+        assert_eq!(logits.dims(), &[3, 2]);
+        assert_eq!(&logits[..], &[2.0, 4.0, 6.0, 8.0, 10.0, 12.0]);
+        assert!(teacher.logits(&Tensor::from(1.0f32)).is_err());
+    }
+}
@@ -0,0 +1,446 @@
+use super::ApplyGradientsOptions;
+use super::ComputeGradientsOptions;
+use super::Optimizer;
+use crate::ops;
+use crate::ops::CollectiveKeys;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Variable;
+use crate::VariableBuilder;
+
+/// Where the variables of a replicated model live.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum VariablePlacement {
+    /// Each replica has its own copy of every variable, on its own device.
+    /// Gradients are averaged with an all-reduce and applied to every copy,
+    /// which keeps the copies in sync.
+    #[default]
+    Mirrored,
+
+    /// A single copy of every variable lives on the given device (e.g.
+    /// "/device:CPU:0"), and gradients are averaged there.
+    Device(String),
+}
+
+/// Options for `distribute`.
+#[derive(Default, Debug)]
+pub struct DistributeOptions<'a> {
+    devices: &'a [&'a str],
+    inputs: &'a [Output],
+    variable_placement: VariablePlacement,
+    keys: Option<&'a mut CollectiveKeys>,
+}
+
+impl<'a> DistributeOptions<'a> {
+    /// Sets the devices the model is replicated across, e.g.
+    /// `&["/device:GPU:0", "/device:GPU:1"]`.
+    pub fn with_devices(self, devices: &'a [&'a str]) -> Self {
+        Self { devices, ..self }
+    }
+
+    /// Sets the input batches.  Each is split along its first dimension into
+    /// one equal part per replica, so the batch size must be divisible by the
+    /// number of devices.
+    pub fn with_inputs(self, inputs: &'a [Output]) -> Self {
+        Self { inputs, ..self }
+    }
+
+    /// Sets where variables live.  Default is `VariablePlacement::Mirrored`.
+    pub fn with_variable_placement(self, variable_placement: VariablePlacement) -> Self {
+        Self {
+            variable_placement,
+            ..self
+        }
+    }
+
+    /// Sets the keys used for collective operations.  This only needs to be
+    /// set if the graph contains other collectives; by default a fresh
+    /// `CollectiveKeys` is used.
+    pub fn with_collective_keys(self, keys: &'a mut CollectiveKeys) -> Self {
+        Self {
+            keys: Some(keys),
+            ..self
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ReplicaVariables {
+    scope: Scope,
+    placement: VariablePlacement,
+    // Variables of the first replica and the names they were requested with,
+    // in creation order.
+    primaries: Vec<Variable>,
+    primary_names: Vec<String>,
+    // Variables of each replica, in creation order.  With
+    // `VariablePlacement::Device`, these are the primaries themselves.
+    replicas: Vec<Vec<Variable>>,
+}
+
+/// Gives the model-building closure passed to `distribute` access to the
+/// replica being built.
+#[derive(Debug)]
+pub struct Replica<'a> {
+    index: usize,
+    device: &'a str,
+    inputs: Vec<Output>,
+    variables: &'a mut ReplicaVariables,
+}
+
+impl<'a> Replica<'a> {
+    /// Returns the index of the replica.
+    pub fn index(&self) -> usize {
+        self.index
+    }
+
+    /// Returns the device the replica is placed on.
+    pub fn device(&self) -> &str {
+        self.device
+    }
+
+    /// Returns the replica's share of the inputs, in the same order as
+    /// `DistributeOptions::with_inputs`.
+    pub fn inputs(&self) -> &[Output] {
+        &self.inputs
+    }
+
+    /// Returns the variable named `name`, creating it from `builder` on the
+    /// first replica.  Every replica must request the same variables in the
+    /// same order.
+    ///
+    /// With mirrored variables, the copies on later replicas are initialized
+    /// from the initial value of the first replica's copy, so all variable
+    /// initializers must be run in the same step for the copies to agree.
+    pub fn variable(&mut self, name: &str, builder: VariableBuilder<'_>) -> Result<Variable> {
+        let vars = &mut *self.variables;
+        let position = vars.replicas[self.index].len();
+        let variable = if self.index == 0 {
+            let mut scope = match &vars.placement {
+                VariablePlacement::Mirrored => vars.scope.with_device(self.device),
+                VariablePlacement::Device(device) => vars.scope.with_device(device),
+            }
+            .with_op_name(name);
+            let variable = builder.build(&mut scope)?;
+            vars.primaries.push(variable.clone());
+            vars.primary_names.push(name.to_string());
+            variable
+        } else {
+            let primary = match vars.primary_names.get(position) {
+                Some(primary_name) if primary_name == name => vars.primaries[position].clone(),
+                _ => {
+                    return Err(invalid_arg!(
+                        "Replica {} requested variable {} which replica 0 did not create \
+                         at the same position",
+                        self.index,
+                        name
+                    ))
+                }
+            };
+            match &vars.placement {
+                VariablePlacement::Device(_) => primary,
+                VariablePlacement::Mirrored => {
+                    let (init_op, init_index) = primary.initializer.input(1);
+                    let mut scope = vars
+                        .scope
+                        .new_sub_scope(&format!("replica_{}", self.index))
+                        .with_device(self.device)
+                        .with_op_name(name);
+                    Variable::builder()
+                        .initial_value(Output {
+                            operation: init_op,
+                            index: init_index as i32,
+                        })
+                        .data_type(primary.dtype)
+                        .shape(primary.shape.clone())
+                        .build(&mut scope)?
+                }
+            }
+        };
+        vars.replicas[self.index].push(variable.clone());
+        Ok(variable)
+    }
+}
+
+/// The result of `distribute`.
+#[derive(Debug)]
+pub struct DistributedModel {
+    losses: Vec<Output>,
+    variables: Vec<Variable>,
+    train_op: Operation,
+}
+
+impl DistributedModel {
+    /// Returns the loss computed by each replica.
+    pub fn losses(&self) -> &[Output] {
+        &self.losses
+    }
+
+    /// Returns every variable created, including mirrored copies and optimizer
+    /// state.  All of their initializers need to be run before training.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    /// Returns the operation which performs a single training step across all
+    /// replicas.
+    pub fn train_op(&self) -> &Operation {
+        &self.train_op
+    }
+}
+
+fn mean(scope: &mut Scope, values: &[Output]) -> Result<Output> {
+    let mut sum = values[0].clone();
+    for value in &values[1..] {
+        sum = ops::add(scope, sum, value.clone())?.into();
+    }
+    let dtype = sum.operation.output_type(sum.index as usize);
+    let scale = ops::constant(scope, 1.0f32 / values.len() as f32)?;
+    let scale = ops::Cast::new().dst_type(dtype).build(scope, scale)?;
+    Ok(ops::multiply(scope, sum, scale)?.into())
+}
+
+/// Replicates a model across several devices for synchronous data-parallel
+/// training, in the style of TensorFlow's `MirroredStrategy`.
+///
+/// `model_fn` is called once per device with a scope placed on that device and
+/// a `Replica` which provides the replica's share of the input batch.  It must
+/// create its variables with `Replica::variable` and return the replica's
+/// loss.  The gradients of all replicas are averaged and applied with
+/// `optimizer`, and the returned `DistributedModel` holds the single operation
+/// which runs a training step.
+pub fn distribute<O, F>(
+    scope: &mut Scope,
+    optimizer: &O,
+    opts: DistributeOptions,
+    mut model_fn: F,
+) -> Result<DistributedModel>
+where
+    O: Optimizer + ?Sized,
+    F: FnMut(&mut Scope, &mut Replica) -> Result<Output>,
+{
+    let devices = opts.devices;
+    if devices.is_empty() {
+        return Err(invalid_arg!("At least one device is required"));
+    }
+    let num_replicas = devices.len();
+
+    let mut splits = Vec::with_capacity(opts.inputs.len());
+    {
+        let mut scope = scope.new_sub_scope("split");
+        for input in opts.inputs {
+            let axis = ops::constant(&mut scope, 0i32)?;
+            splits.push(ops::Split::new().num_split(num_replicas as i64).build(
+                &mut scope,
+                axis,
+                input.clone(),
+            )?);
+        }
+    }
+
+    let mut variables = ReplicaVariables {
+        scope: scope.with_device(scope.device()),
+        placement: opts.variable_placement.clone(),
+        primaries: Vec::new(),
+        primary_names: Vec::new(),
+        replicas: vec![Vec::new(); num_replicas],
+    };
+    let mut losses = Vec::with_capacity(num_replicas);
+    let mut grads = Vec::with_capacity(num_replicas);
+    for (index, device) in devices.iter().enumerate() {
+        let mut replica_scope = scope
+            .new_sub_scope(&format!("replica_{}", index))
+            .with_device(device);
+        let mut replica = Replica {
+            index,
+            device,
+            inputs: splits
+                .iter()
+                .map(|split| Output {
+                    operation: split.clone(),
+                    index: index as i32,
+                })
+                .collect(),
+            variables: &mut variables,
+        };
+        let loss = model_fn(&mut replica_scope, &mut replica)?;
+        let replica_vars = &variables.replicas[index];
+        if replica_vars.len() != variables.primaries.len() {
+            return Err(invalid_arg!(
+                "Replica {} created {} variables, but replica 0 created {}",
+                index,
+                replica_vars.len(),
+                variables.primaries.len()
+            ));
+        }
+        grads.push(optimizer.compute_gradients(
+            &mut replica_scope,
+            loss.clone(),
+            ComputeGradientsOptions::default().with_variables(replica_vars),
+        )?);
+        losses.push(loss);
+    }
+
+    let mut all_variables: Vec<Variable> = variables.replicas.iter().flatten().cloned().collect();
+    let mut train_ops = Vec::new();
+    match &variables.placement {
+        VariablePlacement::Device(device) => {
+            let mut scope = scope.new_sub_scope("average").with_device(device);
+            let mut grads_and_vars = Vec::with_capacity(variables.primaries.len());
+            for (i, var) in variables.primaries.iter().enumerate() {
+                let var_grads: Option<Vec<Output>> = grads.iter().map(|g| g[i].0.clone()).collect();
+                let grad = match var_grads {
+                    Some(var_grads) => Some(mean(&mut scope, &var_grads)?),
+                    None => None,
+                };
+                grads_and_vars.push((grad, var.clone()));
+            }
+            all_variables = variables.primaries.clone();
+            let (vars, train_op) = optimizer.apply_gradients(
+                &mut scope,
+                ApplyGradientsOptions::default().with_grads_and_vars(&grads_and_vars),
+            )?;
+            all_variables.extend(vars);
+            train_ops.push(train_op);
+        }
+        VariablePlacement::Mirrored => {
+            let mut default_keys = CollectiveKeys::new();
+            let keys = match opts.keys {
+                Some(keys) => keys,
+                None => &mut default_keys,
+            };
+            let mut scope = scope.new_sub_scope("average");
+            let mut grads_and_vars: Vec<Vec<(Option<Output>, Variable)>> =
+                variables.replicas.iter().map(|_| Vec::new()).collect();
+            for i in 0..variables.primaries.len() {
+                let var_grads: Option<Vec<Output>> = grads.iter().map(|g| g[i].0.clone()).collect();
+                let averaged: Vec<Option<Output>> = match var_grads {
+                    Some(var_grads) => {
+                        // Gradient ops are not placed, so pin a copy of each to
+                        // its replica's device for the all-reduce.
+                        let mut placed = Vec::with_capacity(num_replicas);
+                        for (grad, device) in var_grads.into_iter().zip(devices) {
+                            let mut scope = scope.with_device(device);
+                            placed.push(ops::identity(&mut scope, grad)?.into());
+                        }
+                        ops::all_reduce_mean(&mut scope, keys, &placed)?
+                            .into_iter()
+                            .map(Some)
+                            .collect()
+                    }
+                    None => vec![None; num_replicas],
+                };
+                for (index, grad) in averaged.into_iter().enumerate() {
+                    grads_and_vars[index].push((grad, variables.replicas[index][i].clone()));
+                }
+            }
+            for (index, device) in devices.iter().enumerate() {
+                let mut scope = scope
+                    .new_sub_scope(&format!("replica_{}", index))
+                    .with_device(device);
+                let (vars, train_op) = optimizer.apply_gradients(
+                    &mut scope,
+                    ApplyGradientsOptions::default().with_grads_and_vars(&grads_and_vars[index]),
+                )?;
+                all_variables.extend(vars);
+                train_ops.push(train_op);
+            }
+        }
+    }
+
+    let mut no_op = ops::NoOp::new();
+    for train_op in train_ops {
+        no_op = no_op.add_control_input(train_op);
+    }
+    Ok(DistributedModel {
+        losses,
+        variables: all_variables,
+        train_op: no_op.build(&mut scope.with_op_name("train"))?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protos::ProtoWriter;
+    use crate::train::GradientDescentOptimizer;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    fn train_once(placement: VariablePlacement) -> Vec<f32> {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::constant(&mut scope, &[1.0f32, 2.0][..]).unwrap();
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        let model = distribute(
+            &mut scope,
+            &optimizer,
+            DistributeOptions::default()
+                .with_devices(&["/device:CPU:0", "/device:CPU:1"])
+                .with_inputs(&[x.into()])
+                .with_variable_placement(placement),
+            |scope, replica| {
+                let w = replica.variable("w", Variable::builder().const_initial_value(3.0f32))?;
+                let x = replica.inputs()[0].clone();
+                Ok(ops::multiply(scope, w.output().clone(), x)?.into())
+            },
+        )
+        .unwrap();
+        assert_eq!(model.losses().len(), 2);
+
+        // ConfigProto.device_count = {"CPU": 2}
+        let mut entry = ProtoWriter::new();
+        entry.string_field(1, "CPU").int_field(2, 2);
+        let mut config = ProtoWriter::new();
+        config.message_field(1, &entry);
+        let mut options = SessionOptions::new();
+        options.set_config(config.as_bytes()).unwrap();
+        let session = Session::new(&options, &scope.graph()).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        for var in model.variables() {
+            run_args.add_target(var.initializer());
+        }
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(model.train_op());
+        session.run(&mut run_args).unwrap();
+
+        let mut run_args = SessionRunArgs::new();
+        let tokens: Vec<_> = model
+            .variables()
+            .iter()
+            .filter(|v| v.name.ends_with('w'))
+            .map(|v| run_args.request_fetch(&v.output().operation, 0))
+            .collect();
+        session.run(&mut run_args).unwrap();
+        tokens
+            .into_iter()
+            .map(|t| run_args.fetch::<f32>(t).unwrap()[0])
+            .collect()
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn mirrored() {
+        // The gradients are 1 and 2, so w = 3 - 0.1 * 1.5.
+        assert_close(&train_once(VariablePlacement::Mirrored), &[2.85, 2.85]);
+    }
+
+    #[test]
+    fn parameter_device() {
+        assert_close(
+            &train_once(VariablePlacement::Device("/device:CPU:0".to_string())),
+            &[2.85],
+        );
+    }
+}
@@ -0,0 +1,249 @@
+use crate::ops;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Variable;
+
+/// Inserts fake quantization into a model for quantization-aware training,
+/// so the model learns to tolerate the rounding of 8-bit (or narrower)
+/// inference.
+///
+/// ```ignore
+/// let mut quantization = FakeQuantization::new();
+/// let w = quantization.quantize_weight(&mut scope, &w)?;
+/// let y = ops::mat_mul(&mut scope, x, w)?;
+/// let y = quantization.quantize_activation(&mut scope, y.into(), -6.0, 6.0)?;
+/// let variables: Vec<Variable> = model_variables
+///     .iter()
+///     .chain(quantization.variables())
+///     .cloned()
+///     .collect();
+/// ```
+///
+/// Weights are quantized with the range of their current values.
+/// Activations are quantized with a range held in a pair of variables, which
+/// are trained along with the model's other variables, so they must be
+/// included in the variables passed to the optimizer or `Trainer`.  The
+/// learned ranges can be read from the variables after training, e.g. for a
+/// converter which produces a quantized model.
+#[derive(Debug, Clone)]
+pub struct FakeQuantization {
+    num_bits: i64,
+    narrow_range: bool,
+    per_channel_weights: bool,
+    variables: Vec<Variable>,
+}
+
+impl Default for FakeQuantization {
+    fn default() -> Self {
+        Self {
+            num_bits: 8,
+            narrow_range: false,
+            per_channel_weights: false,
+            variables: Vec::new(),
+        }
+    }
+}
+
+impl FakeQuantization {
+    /// Creates fake quantization to 8 bits with one range per weight.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the number of bits, between 2 and 16.  Default is 8.
+    pub fn with_num_bits(self, num_bits: u32) -> Self {
+        Self {
+            num_bits: i64::from(num_bits),
+            ..self
+        }
+    }
+
+    /// Uses the range `[1, 2^num_bits - 1]` instead of `[0, 2^num_bits - 1]`,
+    /// which makes signed quantization symmetric.
+    pub fn with_narrow_range(self, narrow_range: bool) -> Self {
+        Self {
+            narrow_range,
+            ..self
+        }
+    }
+
+    /// Quantizes each slice of a weight along its last dimension, i.e. each
+    /// output channel of a dense or convolution layer, with its own range.
+    pub fn with_per_channel_weights(self, per_channel_weights: bool) -> Self {
+        Self {
+            per_channel_weights,
+            ..self
+        }
+    }
+
+    /// Returns `weight` fake quantized with the range of its values, which
+    /// the model should use instead of the weight.
+    pub fn quantize_weight(&self, scope: &mut Scope, weight: &Variable) -> Result<Output> {
+        let mut scope = scope.new_sub_scope(&format!("{}_fake_quant", weight.name()));
+        let input = weight.output().clone();
+        if self.per_channel_weights {
+            let rank = match weight.shape().0 {
+                Some(ref dims) if !dims.is_empty() => dims.len(),
+                _ => {
+                    return Err(invalid_arg!(
+                        "The rank of {} must be known and positive to quantize it per channel",
+                        weight.name()
+                    ))
+                }
+            };
+            let axes: Vec<i32> = (0..rank as i32 - 1).collect();
+            let axes = ops::constant(&mut scope, &axes[..])?;
+            let min = ops::min(&mut scope, input.clone(), axes.clone())?;
+            let max = ops::max(&mut scope, input.clone(), axes)?;
+            return Ok(ops::FakeQuantWithMinMaxVarsPerChannel::new()
+                .num_bits(self.num_bits)
+                .narrow_range(self.narrow_range)
+                .build(&mut scope, input, min, max)?
+                .into());
+        }
+        let shape = ops::constant(&mut scope, &[-1][..])?;
+        let flat = ops::reshape(&mut scope, input.clone(), shape)?;
+        let axis = ops::constant(&mut scope, 0)?;
+        let min = ops::min(&mut scope, flat.clone(), axis.clone())?;
+        let max = ops::max(&mut scope, flat, axis)?;
+        self.fake_quant(&mut scope, input, min.into(), max.into())
+    }
+
+    /// Returns `activation` fake quantized with a trained range, which starts
+    /// as `[initial_min, initial_max]`.
+    pub fn quantize_activation(
+        &mut self,
+        scope: &mut Scope,
+        activation: Output,
+        initial_min: f32,
+        initial_max: f32,
+    ) -> Result<Output> {
+        if initial_min > 0.0 || initial_max < 0.0 || initial_min == initial_max {
+            return Err(invalid_arg!(
+                "The range [{}, {}] must contain 0 and be non-empty",
+                initial_min,
+                initial_max
+            ));
+        }
+        let mut scope = scope.new_sub_scope("fake_quant");
+        let min = Variable::builder()
+            .const_initial_value(initial_min)
+            .build(&mut scope.with_op_name("min"))?;
+        let max = Variable::builder()
+            .const_initial_value(initial_max)
+            .build(&mut scope.with_op_name("max"))?;
+        let output = self.fake_quant(
+            &mut scope,
+            activation,
+            min.output().clone(),
+            max.output().clone(),
+        )?;
+        self.variables.push(min);
+        self.variables.push(max);
+        Ok(output)
+    }
+
+    fn fake_quant(
+        &self,
+        scope: &mut Scope,
+        input: Output,
+        min: Output,
+        max: Output,
+    ) -> Result<Output> {
+        Ok(ops::FakeQuantWithMinMaxVars::new()
+            .num_bits(self.num_bits)
+            .narrow_range(self.narrow_range)
+            .build(scope, input, min, max)?
+            .into())
+    }
+
+    /// Returns the variables holding the ranges of the activations, which
+    /// must be trained and initialized along with the model's variables.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::train::GradientDescentOptimizer;
+    use crate::train::MinimizeOptions;
+    use crate::train::Optimizer;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+    use crate::Tensor;
+
+    #[test]
+    fn quantization_aware_training() {
+        let mut scope = Scope::new_root_scope();
+        let w = Variable::builder()
+            .const_initial_value(
+                Tensor::new(&[2, 2])
+                    .with_values(&[1.0f32, -0.5, 0.26, 2.0])
+                    .unwrap(),
+            )
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        let mut quantization = FakeQuantization::new().with_num_bits(4);
+        let quantized_w = quantization.quantize_weight(&mut scope, &w).unwrap();
+        let per_channel_w = FakeQuantization::new()
+            .with_per_channel_weights(true)
+            .quantize_weight(&mut scope, &w)
+            .unwrap();
+        let x = ops::constant(&mut scope, &[10.0f32, -10.0][..]).unwrap();
+        let y = quantization
+            .quantize_activation(&mut scope, x.into(), -1.0, 1.0)
+            .unwrap();
+        assert!(quantization
+            .quantize_activation(&mut scope, y.clone(), 1.0, 2.0)
+            .is_err());
+        assert_eq!(quantization.variables().len(), 2);
+
+        // Minimizing the error of the clipped activation widens the range.
+        let target = ops::constant(&mut scope, &[10.0f32, -10.0][..]).unwrap();
+        let error = ops::subtract(&mut scope, y.clone(), target).unwrap();
+        let loss = ops::multiply(&mut scope, error.clone(), error).unwrap();
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        let (_, train) = optimizer
+            .minimize(
+                &mut scope,
+                loss.into(),
+                MinimizeOptions::default().with_variables(quantization.variables()),
+            )
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        for var in quantization.variables().iter().chain(&[w]) {
+            init.add_target(var.initializer());
+        }
+        session.run(&mut init).unwrap();
+        for _ in 0..5 {
+            let mut step = SessionRunArgs::new();
+            step.add_target(&train);
+            session.run(&mut step).unwrap();
+        }
+
+        let mut args = SessionRunArgs::new();
+        let w_token = args.request_fetch(&quantized_w.operation, quantized_w.index);
+        let per_channel_token = args.request_fetch(&per_channel_w.operation, per_channel_w.index);
+        let max = quantization.variables()[1].output();
+        let max_token = args.request_fetch(&max.operation, max.index);
+        session.run(&mut args).unwrap();
+        // With 4 bits, [-0.5, 2.0] is quantized in steps of 1/6.
+        let w_value = args.fetch::<f32>(w_token).unwrap();
+        assert!(
+            (w_value[2] - 1.0 / 3.0).abs() < 1e-5,
+            "w = {:?}",
+            &w_value[..]
+        );
+        let per_channel = args.fetch::<f32>(per_channel_token).unwrap();
+        assert!((per_channel[2] - 0.26).abs() < 0.01);
+        assert!(args.fetch::<f32>(max_token).unwrap()[0] > 1.0);
+    }
+}
@@ -0,0 +1,216 @@
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Variable;
+use regex::Regex;
+
+/// Compiles `pattern`, returning an error if it isn't a valid regular
+/// expression.
+fn compile(pattern: &str) -> Result<Regex> {
+    Regex::new(pattern).map_err(|e| invalid_arg!("Invalid variable name pattern: {}", e))
+}
+
+/// Returns the variables whose names match the regular expression `pattern`
+/// (in the syntax of the `regex` crate), which matches anywhere in the name
+/// unless it's anchored with `^` or `$`.  For example, `"^encoder/"` selects
+/// the variables created in the `encoder` sub-scope, and `"/bias$"` selects
+/// the biases of all layers.  Returns an error if `pattern` is invalid.
+pub fn select_variables(variables: &[Variable], pattern: &str) -> Result<Vec<Variable>> {
+    let regex = compile(pattern)?;
+    Ok(variables
+        .iter()
+        .filter(|v| regex.is_match(v.name()))
+        .cloned()
+        .collect())
+}
+
+/// Returns the variables whose names start with `prefix`, taken literally
+/// rather than as a regular expression.
+pub fn select_variables_by_prefix(variables: &[Variable], prefix: &str) -> Vec<Variable> {
+    variables
+        .iter()
+        .filter(|v| v.name().starts_with(prefix))
+        .cloned()
+        .collect()
+}
+
+/// Runs the initializers of `variables`, resetting them to their initial
+/// values while leaving the other variables in the session unchanged, e.g.
+/// to reset a new head before fine-tuning a model restored from a checkpoint.
+pub fn reinitialize_variables(session: &Session, variables: &[Variable]) -> Result<()> {
+    let mut args = SessionRunArgs::new();
+    for variable in variables {
+        args.add_target(variable.initializer());
+    }
+    session.run(&mut args)
+}
+
+/// Tracks which of a model's variables are trained, for fine-tuning part of
+/// a pretrained model.
+///
+/// ```ignore
+/// let variables = TrainableVariables::new(&all_variables)
+///     .freeze("^encoder/")?
+///     .unfreeze("^encoder/layer_11/")?;
+/// let trainable = variables.trainable();
+/// let (_, train_op) = optimizer.minimize(
+///     &mut scope,
+///     loss,
+///     MinimizeOptions::default().with_variables(&trainable),
+/// )?;
+/// ```
+///
+/// Freezing only excludes variables from the optimizer; frozen variables
+/// still need to be restored or initialized.
+#[derive(Debug, Clone)]
+pub struct TrainableVariables {
+    variables: Vec<(Variable, bool)>,
+}
+
+impl TrainableVariables {
+    /// Creates a set of variables which are all trainable.
+    pub fn new(variables: &[Variable]) -> Self {
+        Self {
+            variables: variables.iter().map(|v| (v.clone(), true)).collect(),
+        }
+    }
+
+    /// Marks the variables whose names match the regular expression
+    /// `pattern` as non-trainable.  See `select_variables` for the pattern
+    /// syntax.  Returns an error if `pattern` is invalid.
+    pub fn freeze(self, pattern: &str) -> Result<Self> {
+        let regex = compile(pattern)?;
+        Ok(self.set_trainable(|name| regex.is_match(name), false))
+    }
+
+    /// Marks the variables whose names match the regular expression
+    /// `pattern` as trainable again, e.g. to fine-tune the last layers of a
+    /// frozen encoder.  Returns an error if `pattern` is invalid.
+    pub fn unfreeze(self, pattern: &str) -> Result<Self> {
+        let regex = compile(pattern)?;
+        Ok(self.set_trainable(|name| regex.is_match(name), true))
+    }
+
+    /// Marks the variables whose names start with `prefix` as non-trainable.
+    pub fn freeze_prefix(self, prefix: &str) -> Self {
+        self.set_trainable(|name| name.starts_with(prefix), false)
+    }
+
+    /// Marks the variables whose names start with `prefix` as trainable
+    /// again.
+    pub fn unfreeze_prefix(self, prefix: &str) -> Self {
+        self.set_trainable(|name| name.starts_with(prefix), true)
+    }
+
+    fn set_trainable<F: Fn(&str) -> bool>(mut self, selected: F, trainable: bool) -> Self {
+        for (variable, t) in &mut self.variables {
+            if selected(variable.name()) {
+                *t = trainable;
+            }
+        }
+        self
+    }
+
+    /// Returns whether the variable named `name` is trainable, or `None` if
+    /// there is no such variable.
+    pub fn is_trainable(&self, name: &str) -> Option<bool> {
+        self.variables
+            .iter()
+            .find(|(v, _)| v.name() == name)
+            .map(|&(_, t)| t)
+    }
+
+    /// Returns the trainable variables, for `MinimizeOptions::with_variables`
+    /// or `TrainerOptions::with_variables`.
+    pub fn trainable(&self) -> Vec<Variable> {
+        self.filter(true)
+    }
+
+    /// Returns the frozen variables.
+    pub fn frozen(&self) -> Vec<Variable> {
+        self.filter(false)
+    }
+
+    fn filter(&self, trainable: bool) -> Vec<Variable> {
+        self.variables
+            .iter()
+            .filter(|&&(_, t)| t == trainable)
+            .map(|(v, _)| v.clone())
+            .collect()
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::Scope;
+    use crate::SessionOptions;
+
+    #[test]
+    fn freeze_and_reinitialize() {
+        let mut scope = Scope::new_root_scope();
+        let variables: Vec<Variable> = ["encoder/layer_0/w", "encoder/layer_1/w", "head/w"]
+            .iter()
+            .map(|name| {
+                Variable::builder()
+                    .const_initial_value(1.0f32)
+                    .build(&mut scope.with_op_name(name))
+                    .unwrap()
+            })
+            .collect();
+        let names = |vars: Vec<Variable>| -> Vec<String> {
+            vars.iter().map(|v| v.name().to_string()).collect()
+        };
+        assert_eq!(
+            names(select_variables(&variables, "^encoder/").unwrap()),
+            vec!["encoder/layer_0/w", "encoder/layer_1/w"]
+        );
+        assert_eq!(
+            names(select_variables(&variables, r"layer_\d/w$").unwrap()),
+            vec!["encoder/layer_0/w", "encoder/layer_1/w"]
+        );
+        assert!(select_variables(&variables, "^w$").unwrap().is_empty());
+        assert!(select_variables(&variables, "layer_(").is_err());
+        let trainable = TrainableVariables::new(&variables)
+            .freeze("^encoder/")
+            .unwrap()
+            .unfreeze("/layer_1/")
+            .unwrap();
+        assert_eq!(
+            names(trainable.trainable()),
+            vec!["encoder/layer_1/w", "head/w"]
+        );
+        assert_eq!(names(trainable.frozen()), vec!["encoder/layer_0/w"]);
+        assert_eq!(trainable.is_trainable("encoder/layer_0/w"), Some(false));
+        assert_eq!(trainable.is_trainable("missing"), None);
+        assert_eq!(
+            names(select_variables_by_prefix(&variables, "encoder/layer_")),
+            vec!["encoder/layer_0/w", "encoder/layer_1/w"]
+        );
+        assert!(select_variables_by_prefix(&variables, "^encoder/").is_empty());
+        let trainable = TrainableVariables::new(&variables)
+            .freeze_prefix("encoder/")
+            .unfreeze_prefix("encoder/layer_1");
+        assert_eq!(
+            names(trainable.trainable()),
+            vec!["encoder/layer_1/w", "head/w"]
+        );
+
+        let head = select_variables(&variables, "^head/").unwrap();
+        let five = ops::constant(&mut scope, 5.0f32).unwrap();
+        let assign = ops::assign(&mut scope, head[0].output().clone(), five).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        reinitialize_variables(&session, &variables).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&assign);
+        session.run(&mut args).unwrap();
+        reinitialize_variables(&session, &head).unwrap();
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&head[0].output().operation, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(token).unwrap()[0], 1.0);
+    }
+}
@@ -0,0 +1,265 @@
+use crate::ops;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Tensor;
+use crate::Variable;
+
+/// Options for `check_gradients`.
+#[derive(Debug, Clone, Copy)]
+pub struct GradientCheckOptions {
+    delta: f32,
+    max_elements: usize,
+}
+
+impl Default for GradientCheckOptions {
+    fn default() -> Self {
+        Self {
+            delta: 1e-3,
+            max_elements: 20,
+        }
+    }
+}
+
+impl GradientCheckOptions {
+    /// Sets how far each element is moved in each direction for the finite
+    /// differences.  Default is 0.001.
+    pub fn with_delta(self, delta: f32) -> Self {
+        Self { delta, ..self }
+    }
+
+    /// Sets the maximum number of elements of each variable to check.  If a
+    /// variable has more, evenly spaced elements are checked.  Default is 20.
+    pub fn with_max_elements(self, max_elements: usize) -> Self {
+        Self {
+            max_elements,
+            ..self
+        }
+    }
+}
+
+/// How far the gradient of a variable computed by `Graph::add_gradients` is
+/// from its finite difference approximation.  Returned by `check_gradients`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GradientError {
+    /// The name of the variable.
+    pub variable: String,
+    /// The number of elements which were checked.
+    pub elements: usize,
+    /// The largest absolute difference between the computed gradient and the
+    /// approximation of any checked element.
+    pub max_error: f32,
+}
+
+/// Returns the indices of at most `max` evenly spaced elements out of `len`.
+fn sample_indices(len: usize, max: usize) -> Vec<usize> {
+    if len <= max {
+        (0..len).collect()
+    } else {
+        (0..max).map(|i| i * len / max).collect()
+    }
+}
+
+/// Compares the gradients of the float scalar `loss` with respect to
+/// `variables`, as computed by `Graph::add_gradients`, with central finite
+/// differences at the variables' current values in `session`, and returns
+/// the largest error for each variable.
+///
+/// This is useful for testing custom gradients or unusual compositions of
+/// ops.  The variables must be float and initialized, and `loss` must not
+/// need any feeds.  Each checked element is moved by `delta` in both
+/// directions and the loss is computed again, so this runs the loss twice
+/// per element.  The variables are restored to their values afterwards.
+///
+/// ```ignore
+/// let errors = check_gradients(&mut scope, &session, loss, &variables, Default::default())?;
+/// for error in &errors {
+///     assert!(error.max_error < 1e-2, "{:?}", error);
+/// }
+/// ```
+///
+/// Since the loss is computed in single precision, errors around 1e-3 are
+/// normal; a wrong gradient typically shows up as a much larger error.
+pub fn check_gradients(
+    scope: &mut Scope,
+    session: &Session,
+    loss: Output,
+    variables: &[Variable],
+    opts: GradientCheckOptions,
+) -> Result<Vec<GradientError>> {
+    if loss.operation.output_type(loss.index as usize) != DataType::Float {
+        return Err(invalid_arg!("The loss to check gradients of must be float"));
+    }
+    if let Some(dims) = ops::known_dims(scope, &loss)? {
+        if !dims.is_empty() {
+            return Err(invalid_arg!(
+                "The loss to check gradients of must be a scalar, but has shape {:?}",
+                dims
+            ));
+        }
+    }
+    if opts.delta.is_nan() || opts.delta <= 0.0 {
+        return Err(invalid_arg!(
+            "The finite difference delta must be positive, but is {}",
+            opts.delta
+        ));
+    }
+    for variable in variables {
+        if variable.dtype != DataType::Float {
+            return Err(invalid_arg!(
+                "Gradients can only be checked for float variables, but {} is {}",
+                variable.name,
+                variable.dtype
+            ));
+        }
+    }
+    let outputs: Vec<Output> = variables.iter().map(|v| v.output.clone()).collect();
+    let gradients =
+        scope
+            .graph_mut()
+            .add_gradients(None, std::slice::from_ref(&loss), &outputs, None)?;
+    let mut scope = scope.new_sub_scope("check_gradients");
+    let mut errors = Vec::with_capacity(variables.len());
+    for (variable, gradient) in variables.iter().zip(gradients) {
+        let gradient = gradient.ok_or_else(|| {
+            invalid_arg!("The loss has no gradient with respect to {}", variable.name)
+        })?;
+        let value = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .build(&mut scope.with_op_name(&format!("{}_value", variable.name)))?;
+        let assign = ops::assign(&mut scope, variable.output.clone(), value.clone())?;
+
+        let mut args = SessionRunArgs::new();
+        let value_token = args.request_fetch(&variable.output.operation, variable.output.index);
+        let gradient_token = args.request_fetch(&gradient.operation, gradient.index);
+        session.run(&mut args)?;
+        let original: Tensor<f32> = args.fetch(value_token)?;
+        let analytic: Tensor<f32> = args.fetch(gradient_token)?;
+
+        let loss_at = |values: &Tensor<f32>| -> Result<f64> {
+            set_value(session, &value, &assign, values)?;
+            let mut args = SessionRunArgs::new();
+            let token = args.request_fetch(&loss.operation, loss.index);
+            session.run(&mut args)?;
+            Ok(f64::from(args.fetch::<f32>(token)?[0]))
+        };
+        let indices = sample_indices(original.len(), opts.max_elements);
+        let mut max_error = 0.0f32;
+        let mut perturbed = original.clone();
+        for &i in &indices {
+            perturbed[i] = original[i] + opts.delta;
+            let above = loss_at(&perturbed);
+            perturbed[i] = original[i] - opts.delta;
+            let below = loss_at(&perturbed);
+            perturbed[i] = original[i];
+            let (above, below) = match (above, below) {
+                (Ok(above), Ok(below)) => (above, below),
+                (Err(e), _) | (_, Err(e)) => {
+                    set_value(session, &value, &assign, &original)?;
+                    return Err(e);
+                }
+            };
+            let numeric = (above - below) / (2.0 * f64::from(opts.delta));
+            let error = (numeric - f64::from(analytic[i])).abs() as f32;
+            if error > max_error || error.is_nan() {
+                max_error = error;
+            }
+        }
+        set_value(session, &value, &assign, &original)?;
+        errors.push(GradientError {
+            variable: variable.name.clone(),
+            elements: indices.len(),
+            max_error,
+        });
+    }
+    Ok(errors)
+}
+
+fn set_value(
+    session: &Session,
+    placeholder: &Operation,
+    assign: &Operation,
+    values: &Tensor<f32>,
+) -> Result<()> {
+    let mut args = SessionRunArgs::new();
+    args.add_feed(placeholder, 0, values);
+    args.add_target(assign);
+    session.run(&mut args)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SessionOptions;
+
+    #[test]
+    fn cubes() {
+        let mut scope = Scope::new_root_scope();
+        let x = Variable::builder()
+            .const_initial_value(Tensor::new(&[3]).with_values(&[1.0f32, 2.0, -1.5]).unwrap())
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let y = Variable::builder()
+            .const_initial_value(0.5f32)
+            .build(&mut scope.with_op_name("y"))
+            .unwrap();
+        // loss = sum(x^3) * y
+        let three = ops::constant(&mut scope, 3.0f32).unwrap();
+        let cubes = ops::pow(&mut scope, x.output.clone(), three).unwrap();
+        let axis = ops::constant(&mut scope, 0i32).unwrap();
+        let sum = ops::sum(&mut scope, cubes, axis).unwrap();
+        let loss = ops::multiply(&mut scope, sum, y.output.clone()).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&x.initializer);
+        args.add_target(&y.initializer);
+        session.run(&mut args).unwrap();
+
+        let errors = check_gradients(
+            &mut scope,
+            &session,
+            loss.clone().into(),
+            &[x.clone(), y],
+            GradientCheckOptions::default().with_max_elements(2),
+        )
+        .unwrap();
+        assert_eq!(errors.len(), 2);
+        assert_eq!(errors[0].variable, "x");
+        assert_eq!(errors[0].elements, 2);
+        assert_eq!(errors[1].elements, 1);
+        for error in &errors {
+            assert!(error.max_error < 1e-2, "{:?}", error);
+        }
+
+        // The variables are restored.
+        let mut args = SessionRunArgs::new();
+        let token = args.request_fetch(&x.output.operation, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<f32>(token).unwrap()[..], &[1.0, 2.0, -1.5]);
+
+        let bad_delta = GradientCheckOptions::default().with_delta(0.0);
+        assert!(
+            check_gradients(&mut scope, &session, loss.into(), &[x.clone()], bad_delta).is_err()
+        );
+        assert!(check_gradients(
+            &mut scope,
+            &session,
+            x.output.clone(),
+            &[x],
+            Default::default()
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn sampling() {
+        assert_eq!(sample_indices(3, 5), vec![0, 1, 2]);
+        assert_eq!(sample_indices(10, 4), vec![0, 2, 5, 7]);
+    }
+}
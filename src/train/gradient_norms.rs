@@ -0,0 +1,189 @@
+use crate::io::SummaryWriter;
+use crate::ops;
+use crate::FetchToken;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::SessionRunArgs;
+use crate::Variable;
+
+/// Returns the sum of the squares of the elements of `tensor`.
+fn squared_norm(scope: &mut Scope, tensor: Output) -> Result<Output> {
+    let flat_shape = ops::constant(scope, &[-1i32][..])?;
+    let axis = ops::constant(scope, 0i32)?;
+    let flat = ops::reshape(scope, tensor, flat_shape)?;
+    let squared = ops::square(scope, flat)?;
+    Ok(ops::sum(scope, squared, axis)?.into())
+}
+
+/// Returns the global norm of `tensors`, the square root of the sum of the
+/// squares of all their elements, as if they were concatenated into one
+/// vector.  This is the norm `ClipByGlobalNorm` clips to.
+pub fn global_norm(scope: &mut Scope, tensors: &[Output]) -> Result<Output> {
+    if tensors.is_empty() {
+        return Err(invalid_arg!("Can't compute the global norm of no tensors"));
+    }
+    let mut scope = scope.new_sub_scope("global_norm");
+    let scope = &mut scope;
+    let mut total: Option<Output> = None;
+    for tensor in tensors {
+        let squared = squared_norm(scope, tensor.clone())?;
+        total = Some(match total {
+            Some(total) => ops::add(scope, total, squared)?.into(),
+            None => squared,
+        });
+    }
+    // tensors isn't empty, so total is set.
+    Ok(ops::sqrt(scope, total.unwrap())?.into())
+}
+
+/// The norm of each variable's gradient and their global norm, for tracking
+/// gradient magnitudes during training.
+///
+/// ```ignore
+/// let (grads_and_vars, _) = ...;
+/// let norms = GradientNorms::new(&mut scope, &grads_and_vars)?;
+/// let mut writer = SummaryWriter::new("/tmp/logs")?;
+/// // In the training loop:
+/// let mut args = SessionRunArgs::new();
+/// args.add_target(&train_op);
+/// let fetches = norms.request_fetches(&mut args);
+/// session.run(&mut args)?;
+/// fetches.write_summaries(&mut args, &mut writer, step)?;
+/// ```
+///
+/// The summaries are tagged `gradient_norm/<variable name>` and
+/// `gradient_norm/global`.  Variables without a gradient are skipped.
+#[derive(Debug, Clone)]
+pub struct GradientNorms {
+    norms: Vec<(String, Output)>,
+    global: Output,
+}
+
+impl GradientNorms {
+    /// Adds ops computing the norms of the gradients in `grads_and_vars`, as
+    /// returned by `Optimizer::compute_gradients`.
+    pub fn new(scope: &mut Scope, grads_and_vars: &[(Option<Output>, Variable)]) -> Result<Self> {
+        let mut scope = scope.new_sub_scope("gradient_norms");
+        let scope = &mut scope;
+        let mut norms = Vec::new();
+        for (grad, var) in grads_and_vars {
+            if let Some(grad) = grad {
+                let squared = squared_norm(scope, grad.clone())?;
+                norms.push((var.name.clone(), ops::sqrt(scope, squared)?.into()));
+            }
+        }
+        if norms.is_empty() {
+            return Err(invalid_arg!("There are no gradients to compute norms of"));
+        }
+        let global = global_norm(scope, &gradients(grads_and_vars))?;
+        Ok(Self { norms, global })
+    }
+
+    /// Returns the norm of each variable's gradient, with the variable's
+    /// name.
+    pub fn norms(&self) -> &[(String, Output)] {
+        &self.norms
+    }
+
+    /// Returns the global norm of all the gradients.
+    pub fn global_norm(&self) -> &Output {
+        &self.global
+    }
+
+    /// Requests fetches of all the norms.
+    pub fn request_fetches(&self, args: &mut SessionRunArgs<'_>) -> GradientNormFetches {
+        let mut tokens: Vec<_> = self
+            .norms
+            .iter()
+            .map(|(name, norm)| {
+                (
+                    format!("gradient_norm/{}", name),
+                    args.request_fetch(&norm.operation, norm.index),
+                )
+            })
+            .collect();
+        tokens.push((
+            "gradient_norm/global".to_string(),
+            args.request_fetch(&self.global.operation, self.global.index),
+        ));
+        GradientNormFetches { tokens }
+    }
+}
+
+/// The norms requested by `GradientNorms::request_fetches`.
+#[derive(Debug)]
+pub struct GradientNormFetches {
+    tokens: Vec<(String, FetchToken)>,
+}
+
+impl GradientNormFetches {
+    /// Returns the fetched norms with their summary tags, ending with the
+    /// global norm.  Must be called after the session has run `args`.
+    pub fn values(&self, args: &mut SessionRunArgs<'_>) -> Result<Vec<(String, f32)>> {
+        self.tokens
+            .iter()
+            .map(|(tag, token)| Ok((tag.clone(), args.fetch::<f32>(*token)?[0])))
+            .collect()
+    }
+
+    /// Writes the fetched norms to `writer` as scalar summaries for the
+    /// training step `step`.
+    pub fn write_summaries(
+        &self,
+        args: &mut SessionRunArgs<'_>,
+        writer: &mut SummaryWriter,
+        step: i64,
+    ) -> Result<()> {
+        for (tag, value) in self.values(args)? {
+            writer
+                .add_scalar(&tag, value, step)
+                .map_err(|e| invalid_arg!("Unable to write summary {}: {}", tag, e))?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the gradients in `grads_and_vars` which aren't `None`.
+pub(crate) fn gradients(grads_and_vars: &[(Option<Output>, Variable)]) -> Vec<Output> {
+    grads_and_vars
+        .iter()
+        .filter_map(|(grad, _)| grad.clone())
+        .collect()
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::constant_gradients;
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+
+    #[test]
+    fn norms() {
+        let mut scope = Scope::new_root_scope();
+        let grads_and_vars =
+            constant_gradients(&mut scope, &[("a", [3.0f32, 4.0]), ("b", [0.0, 12.0])]);
+        let grads = gradients(&grads_and_vars);
+        let global = global_norm(&mut scope, &grads).unwrap();
+        assert!(global_norm(&mut scope, &[]).is_err());
+        let norms = GradientNorms::new(&mut scope, &grads_and_vars).unwrap();
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let global_token = args.request_fetch(&global.operation, global.index);
+        let fetches = norms.request_fetches(&mut args);
+        session.run(&mut args).unwrap();
+        assert_eq!(args.fetch::<f32>(global_token).unwrap()[0], 13.0);
+        assert_eq!(
+            fetches.values(&mut args).unwrap(),
+            vec![
+                ("gradient_norm/a".to_string(), 5.0),
+                ("gradient_norm/b".to_string(), 12.0),
+                ("gradient_norm/global".to_string(), 13.0),
+            ]
+        );
+    }
+}
@@ -0,0 +1,756 @@
+use super::add_gradient_noise;
+use super::create_step_counter;
+use super::create_zeros_slot;
+use super::global_norm;
+use super::gradient_norms::gradients;
+use super::ApplyGradientsOptions;
+use super::GradientNoise;
+use super::Optimizer;
+use crate::ops;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Variable;
+
+/// Gradients paired with their variables, as returned by
+/// `Optimizer::compute_gradients`.
+pub type GradsAndVars = Vec<(Option<Output>, Variable)>;
+
+/// A transformation of gradients between computing and applying them, such
+/// as clipping or scaling.
+///
+/// Transforms can be chained with `then` and combined with any optimizer
+/// using `TransformedOptimizer`, so training recipes can be assembled from
+/// parts rather than written as new optimizers.  Gradients which are `None`
+/// are passed through unchanged.  The transforms in this module expect float
+/// gradients.
+pub trait GradientTransform {
+    /// Returns the transformed gradients, and any variables created to hold
+    /// the transform's state, which need to be initialized before training.
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)>;
+
+    /// Returns a transform which applies this transform and then `next`.
+    fn then<T: GradientTransform>(self, next: T) -> Chain<Self, T>
+    where
+        Self: Sized,
+    {
+        Chain {
+            first: self,
+            second: next,
+        }
+    }
+}
+
+/// Applies one transform and then another.  Returned by
+/// `GradientTransform::then`.
+#[derive(Debug, Clone)]
+pub struct Chain<A, B> {
+    first: A,
+    second: B,
+}
+
+impl<A: GradientTransform, B: GradientTransform> GradientTransform for Chain<A, B> {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        let (mut variables, grads_and_vars) = self.first.transform(scope, grads_and_vars)?;
+        let (more_variables, grads_and_vars) = self.second.transform(scope, &grads_and_vars)?;
+        variables.extend(more_variables);
+        Ok((variables, grads_and_vars))
+    }
+}
+
+/// Applies `f` to each gradient which isn't `None`.
+fn map_grads<F>(grads_and_vars: &[(Option<Output>, Variable)], mut f: F) -> Result<GradsAndVars>
+where
+    F: FnMut(Output, &Variable) -> Result<Output>,
+{
+    grads_and_vars
+        .iter()
+        .map(|(grad, var)| {
+            let grad = match grad {
+                Some(grad) => Some(f(grad.clone(), var)?),
+                None => None,
+            };
+            Ok((grad, var.clone()))
+        })
+        .collect()
+}
+
+fn check_clip_range(min: f32, max: f32) -> Result<()> {
+    if min.is_nan() || max.is_nan() || min > max {
+        return Err(invalid_arg!("Can't clip to [{}, {}]", min, max));
+    }
+    Ok(())
+}
+
+fn check_clip_norm(clip_norm: f32) -> Result<()> {
+    if clip_norm.is_nan() || clip_norm <= 0.0 {
+        return Err(invalid_arg!(
+            "The norm to clip to must be positive, but is {}",
+            clip_norm
+        ));
+    }
+    Ok(())
+}
+
+/// Returns `t` with each element clipped to the range `[min, max]`.
+pub fn clip_by_value(scope: &mut Scope, t: Output, min: f32, max: f32) -> Result<Output> {
+    check_clip_range(min, max)?;
+    let mut scope = scope.new_sub_scope("clip_by_value");
+    let min = ops::constant(&mut scope, min)?;
+    let max = ops::constant(&mut scope, max)?;
+    Ok(ops::clip_by_value(&mut scope, t, min, max)?.into())
+}
+
+/// Returns `t` scaled so that its L2 norm, the square root of the sum of its
+/// squared elements, is at most `clip_norm`.
+pub fn clip_by_norm(scope: &mut Scope, t: Output, clip_norm: f32) -> Result<Output> {
+    check_clip_norm(clip_norm)?;
+    let mut scope = scope.new_sub_scope("clip_by_norm");
+    let scope = &mut scope;
+    let norm = global_norm(scope, std::slice::from_ref(&t))?;
+    let scale = clip_scale(scope, norm, clip_norm)?;
+    Ok(ops::multiply(scope, t, scale)?.into())
+}
+
+/// Returns `tensors` all scaled by the same factor so that their global norm
+/// (see `global_norm`) is at most `clip_norm`, along with the global norm
+/// before clipping.
+pub fn clip_by_global_norm(
+    scope: &mut Scope,
+    tensors: &[Output],
+    clip_norm: f32,
+) -> Result<(Vec<Output>, Output)> {
+    check_clip_norm(clip_norm)?;
+    let mut scope = scope.new_sub_scope("clip_by_global_norm");
+    let scope = &mut scope;
+    let norm = global_norm(scope, tensors)?;
+    let scale = clip_scale(scope, norm.clone(), clip_norm)?;
+    let clipped = tensors
+        .iter()
+        .map(|t| Ok(ops::multiply(scope, t.clone(), scale.clone())?.into()))
+        .collect::<Result<_>>()?;
+    Ok((clipped, norm))
+}
+
+/// Returns the factor which scales a tensor with the given norm to at most
+/// `clip_norm`.
+fn clip_scale(scope: &mut Scope, norm: Output, clip_norm: f32) -> Result<Output> {
+    // scale = clip_norm / max(norm, clip_norm)
+    let clip_norm = ops::constant(scope, clip_norm)?;
+    let limit = ops::maximum(scope, norm, clip_norm.clone())?;
+    Ok(ops::divide(scope, clip_norm, limit)?.into())
+}
+
+/// Clips each element of the gradients to the range `[min, max]`.
+#[derive(Debug, Clone, Copy)]
+pub struct ClipByValue {
+    min: f32,
+    max: f32,
+}
+
+impl ClipByValue {
+    /// Creates a transform clipping to `[min, max]`.
+    pub fn new(min: f32, max: f32) -> Self {
+        Self { min, max }
+    }
+}
+
+impl GradientTransform for ClipByValue {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        check_clip_range(self.min, self.max)?;
+        let grads_and_vars = map_grads(grads_and_vars, |grad, _| {
+            clip_by_value(scope, grad, self.min, self.max)
+        })?;
+        Ok((Vec::new(), grads_and_vars))
+    }
+}
+
+/// Scales each gradient separately so that its norm is at most `clip_norm`.
+/// See `ClipByGlobalNorm` to scale all gradients together.
+#[derive(Debug, Clone, Copy)]
+pub struct ClipByNorm {
+    clip_norm: f32,
+}
+
+impl ClipByNorm {
+    /// Creates a transform clipping to the given norm.
+    pub fn new(clip_norm: f32) -> Self {
+        Self { clip_norm }
+    }
+}
+
+impl GradientTransform for ClipByNorm {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        check_clip_norm(self.clip_norm)?;
+        let grads_and_vars = map_grads(grads_and_vars, |grad, _| {
+            clip_by_norm(scope, grad, self.clip_norm)
+        })?;
+        Ok((Vec::new(), grads_and_vars))
+    }
+}
+
+/// Scales all gradients together so that their global norm, the square root
+/// of the sum of all their squared elements, is at most `clip_norm`.  Unlike
+/// clipping each gradient separately, this preserves the direction of the
+/// update.
+#[derive(Debug, Clone, Copy)]
+pub struct ClipByGlobalNorm {
+    clip_norm: f32,
+}
+
+impl ClipByGlobalNorm {
+    /// Creates a transform clipping to the given norm.
+    pub fn new(clip_norm: f32) -> Self {
+        Self { clip_norm }
+    }
+}
+
+impl GradientTransform for ClipByGlobalNorm {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        check_clip_norm(self.clip_norm)?;
+        let grads = gradients(grads_and_vars);
+        if grads.is_empty() {
+            return Ok((Vec::new(), grads_and_vars.to_vec()));
+        }
+        let (mut clipped, _) = clip_by_global_norm(scope, &grads, self.clip_norm)?;
+        // Put the clipped gradients back in place of the ones which aren't
+        // `None`.
+        clipped.reverse();
+        let grads_and_vars = map_grads(grads_and_vars, |_, _| {
+            Ok(clipped.pop().expect("one clipped tensor per gradient"))
+        })?;
+        Ok((Vec::new(), grads_and_vars))
+    }
+}
+
+/// How `Optimizer::minimize` clips gradients before applying them.  See
+/// `MinimizeOptions::with_gradient_clipping`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum GradientClipping {
+    /// Clips each element to `[min, max]`, like `ClipByValue`.
+    ClipByValue(f32, f32),
+    /// Clips the norm of each gradient separately, like `ClipByNorm`.
+    ClipByNorm(f32),
+    /// Clips the global norm of all gradients together, like
+    /// `ClipByGlobalNorm`.
+    ClipByGlobalNorm(f32),
+}
+
+impl GradientTransform for GradientClipping {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        match *self {
+            GradientClipping::ClipByValue(min, max) => {
+                ClipByValue::new(min, max).transform(scope, grads_and_vars)
+            }
+            GradientClipping::ClipByNorm(clip_norm) => {
+                ClipByNorm::new(clip_norm).transform(scope, grads_and_vars)
+            }
+            GradientClipping::ClipByGlobalNorm(clip_norm) => {
+                ClipByGlobalNorm::new(clip_norm).transform(scope, grads_and_vars)
+            }
+        }
+    }
+}
+
+/// Multiplies the gradients by a constant factor.
+#[derive(Debug, Clone, Copy)]
+pub struct Scale {
+    factor: f32,
+}
+
+impl Scale {
+    /// Creates a transform multiplying by `factor`.
+    pub fn new(factor: f32) -> Self {
+        Self { factor }
+    }
+}
+
+impl GradientTransform for Scale {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        let mut scope = scope.new_sub_scope("scale");
+        let factor: Output = ops::constant(&mut scope, self.factor)?.into();
+        let grads_and_vars = map_grads(grads_and_vars, |grad, _| {
+            Ok(ops::multiply(&mut scope, grad, factor.clone())?.into())
+        })?;
+        Ok((Vec::new(), grads_and_vars))
+    }
+}
+
+/// Adds `weight_decay` times each variable to its gradient, which is L2
+/// regularization with strength `weight_decay / 2` without changing the
+/// loss.
+#[derive(Debug, Clone, Copy)]
+pub struct AddDecayedWeights {
+    weight_decay: f32,
+}
+
+impl AddDecayedWeights {
+    /// Creates a transform with the given decay rate.
+    pub fn new(weight_decay: f32) -> Self {
+        Self { weight_decay }
+    }
+}
+
+impl GradientTransform for AddDecayedWeights {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        let mut scope = scope.new_sub_scope("add_decayed_weights");
+        let weight_decay: Output = ops::constant(&mut scope, self.weight_decay)?.into();
+        let grads_and_vars = map_grads(grads_and_vars, |grad, var| {
+            let decay = ops::multiply(&mut scope, weight_decay.clone(), var.output.clone())?;
+            Ok(ops::add(&mut scope, grad, decay)?.into())
+        })?;
+        Ok((Vec::new(), grads_and_vars))
+    }
+}
+
+/// Accumulates gradients over several steps, to simulate batches too large to
+/// fit in memory.
+///
+/// On every `steps`-th step, the gradients are the mean of those of the last
+/// `steps` steps.  On all other steps, they are zero.  Optimizers with state,
+/// such as momentum, still update it on those steps, so this is best combined
+/// with `GradientDescentOptimizer`.
+#[derive(Debug, Clone, Copy)]
+pub struct Accumulate {
+    steps: u32,
+}
+
+impl Accumulate {
+    /// Creates a transform accumulating over `steps` steps.
+    pub fn new(steps: u32) -> Self {
+        Self { steps }
+    }
+}
+
+impl GradientTransform for Accumulate {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        if self.steps == 0 {
+            return Err(invalid_arg!("Can't accumulate gradients over zero steps"));
+        }
+        let mut scope = scope.new_sub_scope("accumulate");
+        let (step, t) = create_step_counter(&mut scope)?;
+        let mut variables = vec![step];
+        let scope = &mut scope;
+        // The last step of each group is the one where t - steps * floor(t / steps) == 0.
+        let steps: Output = ops::constant(scope, self.steps as f32)?.into();
+        let groups = ops::divide(scope, t.clone(), steps.clone())?;
+        let groups = ops::floor(scope, groups)?;
+        let done = ops::multiply(scope, steps.clone(), groups)?;
+        let remainder = ops::subtract(scope, t, done)?;
+        let zero = ops::constant(scope, 0.0f32)?;
+        let is_last: Output = ops::equal(scope, remainder, zero)?.into();
+        let grads_and_vars = map_grads(grads_and_vars, |grad, var| {
+            let mut scope = scope.new_sub_scope(&var.name);
+            let accum = create_zeros_slot(&mut scope, var, None)?;
+            let sum: Output = ops::add(&mut scope, accum.output.clone(), grad)?.into();
+            let zeros = ops::zeros_like(&mut scope, sum.clone())?;
+            let mean = ops::divide(&mut scope, sum.clone(), steps.clone())?;
+            // Start over after the last step of each group.
+            let reset = ops::select(&mut scope, is_last.clone(), zeros.clone(), sum)?;
+            let reset = ops::assign(&mut scope, accum.output.clone(), reset)?;
+            let gated = ops::Select::new().add_control_input(reset).build(
+                &mut scope,
+                is_last.clone(),
+                mean,
+                zeros,
+            )?;
+            variables.push(accum);
+            Ok(gated.into())
+        })?;
+        Ok((variables, grads_and_vars))
+    }
+}
+
+/// What `SanitizeGradients` does on a step where a gradient has NaN or
+/// infinite elements.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NonFiniteGradients {
+    /// Skips the step: the gradients are only passed on when they are all
+    /// finite, so neither the variables nor the optimizer's state are
+    /// updated.  Ops using the gradients don't run on a skipped step, so
+    /// running the training op still succeeds, but fetching any output which
+    /// depends on the gradients fails.
+    Skip,
+    /// Zeroes all gradients for the step.  This doesn't skip the update: no
+    /// variable is moved by plain gradient descent, but optimizers with state
+    /// still update it, and those with momentum still move the variables.
+    ZeroAll,
+    /// Replaces only the non-finite elements of each gradient with zero.
+    ZeroNonFinite,
+    /// Fails the step with an error naming the variable whose gradient is
+    /// non-finite.
+    Error,
+}
+
+/// Checks the gradients for NaN and infinite values on every step, and
+/// handles them as given by a `NonFiniteGradients`.
+///
+/// `NonFiniteGradients::Error` is meant for debugging diverging runs: the
+/// error names the first variable found with a bad gradient, so it points at
+/// the part of the model which diverged.
+#[derive(Debug, Clone, Copy)]
+pub struct SanitizeGradients {
+    action: NonFiniteGradients,
+}
+
+impl SanitizeGradients {
+    /// Creates a transform handling non-finite gradients with `action`.
+    pub fn new(action: NonFiniteGradients) -> Self {
+        Self { action }
+    }
+}
+
+impl GradientTransform for SanitizeGradients {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        let mut scope = scope.new_sub_scope("sanitize_gradients");
+        let scope = &mut scope;
+        let grads_and_vars = match self.action {
+            NonFiniteGradients::Error => map_grads(grads_and_vars, |grad, var| {
+                Ok(ops::CheckNumerics::new()
+                    .message(&format!("Non-finite gradient for variable {}", var.name))
+                    .build(scope, grad)?
+                    .into())
+            })?,
+            NonFiniteGradients::ZeroNonFinite => map_grads(grads_and_vars, |grad, _| {
+                let finite = ops::is_finite(scope, grad.clone())?;
+                let zeros = ops::zeros_like(scope, grad.clone())?;
+                Ok(ops::select(scope, finite, grad, zeros)?.into())
+            })?,
+            NonFiniteGradients::ZeroAll | NonFiniteGradients::Skip => {
+                let flat_shape = ops::constant(scope, &[-1i32][..])?;
+                let axis = ops::constant(scope, 0i32)?;
+                let mut all_finite: Option<Output> = None;
+                for grad in grads_and_vars.iter().filter_map(|(grad, _)| grad.as_ref()) {
+                    let flat = ops::reshape(scope, grad.clone(), flat_shape.clone())?;
+                    let finite = ops::is_finite(scope, flat)?;
+                    let finite: Output = ops::all(scope, finite, axis.clone())?.into();
+                    all_finite = Some(match all_finite {
+                        Some(all) => ops::logical_and(scope, all, finite)?.into(),
+                        None => finite,
+                    });
+                }
+                let all_finite = match all_finite {
+                    Some(all_finite) => all_finite,
+                    None => return Ok((Vec::new(), grads_and_vars.to_vec())),
+                };
+                if self.action == NonFiniteGradients::Skip {
+                    // Only the true branch of each switch is passed on, so
+                    // the ops applying the gradients are dead, and don't
+                    // run, when a gradient isn't finite.
+                    map_grads(grads_and_vars, |grad, _| {
+                        Ok(ops::switch(scope, grad, all_finite.clone())?.1)
+                    })?
+                } else {
+                    map_grads(grads_and_vars, |grad, _| {
+                        let zeros = ops::zeros_like(scope, grad.clone())?;
+                        Ok(ops::select(scope, all_finite.clone(), grad, zeros)?.into())
+                    })?
+                }
+            }
+        };
+        Ok((Vec::new(), grads_and_vars))
+    }
+}
+
+impl GradientTransform for GradientNoise {
+    fn transform(
+        &self,
+        scope: &mut Scope,
+        grads_and_vars: &[(Option<Output>, Variable)],
+    ) -> Result<(Vec<Variable>, GradsAndVars)> {
+        Ok((Vec::new(), add_gradient_noise(scope, grads_and_vars, self)?))
+    }
+}
+
+/// An optimizer which transforms gradients before handing them to a base
+/// optimizer.
+///
+/// ```ignore
+/// let optimizer = TransformedOptimizer::new(
+///     AdadeltaOptimizer::new(),
+///     ClipByGlobalNorm::new(5.0).then(AddDecayedWeights::new(1e-4)),
+/// );
+/// let (variables, train_op) = optimizer.minimize(&mut scope, loss, opts)?;
+/// ```
+#[derive(Debug)]
+pub struct TransformedOptimizer<O, T> {
+    optimizer: O,
+    transform: T,
+}
+
+impl<O: Optimizer, T: GradientTransform> TransformedOptimizer<O, T> {
+    /// Creates an optimizer applying `transform` and then `optimizer`.
+    pub fn new(optimizer: O, transform: T) -> Self {
+        Self {
+            optimizer,
+            transform,
+        }
+    }
+}
+
+impl<O: Optimizer, T: GradientTransform> Optimizer for TransformedOptimizer<O, T> {
+    fn apply_gradients(
+        &self,
+        scope: &mut Scope,
+        opts: ApplyGradientsOptions,
+    ) -> Result<(Vec<Variable>, Operation)> {
+        let (mut variables, grads_and_vars) =
+            self.transform.transform(scope, opts.grads_and_vars)?;
+        let (more_variables, apply) = self.optimizer.apply_gradients(
+            scope,
+            ApplyGradientsOptions::default().with_grads_and_vars(&grads_and_vars),
+        )?;
+        variables.extend(more_variables);
+        Ok((variables, apply))
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::super::tests::constant_gradients;
+    use super::super::tests::minimize_x_squared;
+    use super::super::GradientDescentOptimizer;
+    use super::super::MinimizeOptions;
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    /// Minimizes x^2 from x = 3 with SGD at the given learning rate and
+    /// returns x after each step.
+    fn minimize<T: GradientTransform>(learning_rate: f32, transform: T, steps: usize) -> Vec<f32> {
+        let mut scope = Scope::new_root_scope();
+        let learning_rate = ops::constant(&mut scope, learning_rate).unwrap();
+        let optimizer = TransformedOptimizer::new(
+            GradientDescentOptimizer::new(learning_rate.into()),
+            transform,
+        );
+        minimize_x_squared(&mut scope, &optimizer, steps)
+    }
+
+    fn assert_close(actual: &[f32], expected: &[f32]) {
+        assert_eq!(actual.len(), expected.len());
+        for (a, e) in actual.iter().zip(expected) {
+            assert!((a - e).abs() < 1e-5, "{:?} != {:?}", actual, expected);
+        }
+    }
+
+    #[test]
+    fn chained_clip_and_scale() {
+        // The gradient 6 is clipped to 1 and halved.
+        let xs = minimize(1.0, ClipByValue::new(-1.0, 1.0).then(Scale::new(0.5)), 1);
+        assert_close(&xs, &[2.5]);
+    }
+
+    #[test]
+    fn clip_by_global_norm() {
+        assert_close(&minimize(0.1, ClipByGlobalNorm::new(2.0), 1), &[2.8]);
+        // Gradients within the norm are untouched.
+        assert_close(&minimize(0.1, ClipByGlobalNorm::new(100.0), 1), &[2.4]);
+    }
+
+    #[test]
+    fn clip_norms_separately() {
+        // The gradient 6 is clipped to 2.
+        assert_close(&minimize(0.1, ClipByNorm::new(2.0), 1), &[2.8]);
+        assert_close(
+            &minimize(0.1, GradientClipping::ClipByValue(-1.0, 1.0), 1),
+            &[2.9],
+        );
+    }
+
+    #[test]
+    fn clipping_functions() {
+        let mut scope = Scope::new_root_scope();
+        let a: Output = ops::constant(&mut scope, &[3.0f32, 4.0][..])
+            .unwrap()
+            .into();
+        let b: Output = ops::constant(&mut scope, &[0.0f32, 12.0][..])
+            .unwrap()
+            .into();
+        let by_value = clip_by_value(&mut scope, a.clone(), -1.0, 3.5).unwrap();
+        let by_norm = clip_by_norm(&mut scope, a.clone(), 1.0).unwrap();
+        // The test of the same name shadows clip_by_global_norm.
+        let (by_global_norm, norm) =
+            super::clip_by_global_norm(&mut scope, &[a.clone(), b], 6.5).unwrap();
+        assert!(clip_by_value(&mut scope, a.clone(), 1.0, -1.0).is_err());
+        assert!(clip_by_norm(&mut scope, a, -1.0).is_err());
+        assert!(super::clip_by_global_norm(&mut scope, &[], 1.0).is_err());
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let fetches: Vec<_> = [
+            &by_value,
+            &by_norm,
+            &by_global_norm[0],
+            &by_global_norm[1],
+            &norm,
+        ]
+        .iter()
+        .map(|output| args.request_fetch(&output.operation, output.index))
+        .collect();
+        session.run(&mut args).unwrap();
+        let values: Vec<Vec<f32>> = fetches
+            .into_iter()
+            .map(|token| args.fetch::<f32>(token).unwrap().to_vec())
+            .collect();
+        assert_close(&values[0], &[3.0, 3.5]);
+        assert_close(&values[1], &[0.6, 0.8]);
+        // The global norm is 13, so everything is halved.
+        assert_close(&values[2], &[1.5, 2.0]);
+        assert_close(&values[3], &[0.0, 6.0]);
+        assert_close(&values[4], &[13.0]);
+    }
+
+    #[test]
+    fn add_decayed_weights() {
+        // 6 + 0.1 * 3
+        assert_close(&minimize(0.1, AddDecayedWeights::new(0.1), 1), &[2.37]);
+    }
+
+    #[test]
+    fn accumulate() {
+        // x stays put until the second step applies the mean gradient.
+        assert_close(
+            &minimize(0.1, Accumulate::new(2), 4),
+            &[3.0, 2.4, 2.4, 1.92],
+        );
+    }
+
+    #[test]
+    fn sanitize_gradients() {
+        let run = |action: NonFiniteGradients| -> Result<Vec<Vec<f32>>> {
+            let mut scope = Scope::new_root_scope();
+            let grads_and_vars = constant_gradients(
+                &mut scope,
+                &[("x", [1.0f32, std::f32::NAN]), ("y", [2.0, 3.0])],
+            );
+            let (_, grads_and_vars) = SanitizeGradients::new(action)
+                .transform(&mut scope, &grads_and_vars)
+                .unwrap();
+            let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+            let mut args = SessionRunArgs::new();
+            let tokens: Vec<_> = grads_and_vars
+                .iter()
+                .map(|(grad, _)| {
+                    let grad = grad.as_ref().unwrap();
+                    args.request_fetch(&grad.operation, grad.index)
+                })
+                .collect();
+            session.run(&mut args)?;
+            Ok(tokens
+                .into_iter()
+                .map(|token| args.fetch::<f32>(token).unwrap().to_vec())
+                .collect())
+        };
+        assert_eq!(
+            run(NonFiniteGradients::ZeroNonFinite).unwrap(),
+            vec![vec![1.0, 0.0], vec![2.0, 3.0]]
+        );
+        assert_eq!(
+            run(NonFiniteGradients::ZeroAll).unwrap(),
+            vec![vec![0.0, 0.0], vec![0.0, 0.0]]
+        );
+        let err = run(NonFiniteGradients::Error).unwrap_err();
+        assert!(
+            err.message().contains("Non-finite gradient for variable x"),
+            "{}",
+            err
+        );
+    }
+
+    #[test]
+    fn skip_non_finite_gradients() {
+        let skip = SanitizeGradients::new(NonFiniteGradients::Skip);
+        // The gradient is finite, so the step is taken.
+        assert_close(&minimize(0.1, skip, 1), &[2.4]);
+
+        let mut scope = Scope::new_root_scope();
+        let x_var = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let infinity = ops::constant(&mut scope, std::f32::INFINITY).unwrap();
+        let loss = ops::multiply(&mut scope, x_var.output.clone(), infinity).unwrap();
+        let learning_rate = ops::constant(&mut scope, 0.1f32).unwrap();
+        let optimizer =
+            TransformedOptimizer::new(GradientDescentOptimizer::new(learning_rate.into()), skip);
+        let (_, minimize) = optimizer
+            .minimize(
+                &mut scope,
+                loss.into(),
+                MinimizeOptions::default().with_variables(&[x_var.clone()]),
+            )
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&x_var.initializer);
+        session.run(&mut run_args).unwrap();
+        // The gradient is infinite, so the step runs without moving x.
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&minimize);
+        session.run(&mut run_args).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let x_fetch = run_args.request_fetch(&x_var.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(run_args.fetch::<f32>(x_fetch).unwrap()[0], 3.0);
+    }
+
+    #[test]
+    fn invalid_transforms() {
+        let mut scope = Scope::new_root_scope();
+        assert!(Accumulate::new(0).transform(&mut scope, &[]).is_err());
+        assert!(ClipByGlobalNorm::new(0.0)
+            .transform(&mut scope, &[])
+            .is_err());
+        assert!(ClipByValue::new(1.0, -1.0)
+            .transform(&mut scope, &[])
+            .is_err());
+        assert!(ClipByNorm::new(std::f32::NAN)
+            .transform(&mut scope, &[])
+            .is_err());
+    }
+}
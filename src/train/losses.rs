@@ -0,0 +1,228 @@
+use crate::ops;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+
+/// Options for the cross-entropy loss functions.
+#[derive(Debug, Clone, Default)]
+pub struct LossOptions {
+    class_weights: Option<Output>,
+    sample_weights: Option<Output>,
+}
+
+impl LossOptions {
+    /// Weights each example's loss by the weight of its class.  The weights
+    /// must be a float vector with one value per class, e.g. from
+    /// `balanced_class_weights`.
+    pub fn with_class_weights(self, class_weights: Output) -> Self {
+        Self {
+            class_weights: Some(class_weights),
+            ..self
+        }
+    }
+
+    /// Weights each example's loss by the corresponding value of
+    /// `sample_weights`, which must be a float vector with one value per
+    /// example, usually a placeholder.  Class weights, if given, are applied
+    /// as well.
+    pub fn with_sample_weights(self, sample_weights: Output) -> Self {
+        Self {
+            sample_weights: Some(sample_weights),
+            ..self
+        }
+    }
+}
+
+/// Returns the softmax cross-entropy between `logits` and `labels`, each of
+/// shape `[batch_size, num_classes]`, where each row of `labels` is a
+/// probability distribution (e.g. one-hot).
+///
+/// The result is a scalar: the mean over the batch of the weighted loss of
+/// each example.
+pub fn softmax_cross_entropy(
+    scope: &mut Scope,
+    logits: Output,
+    labels: Output,
+    opts: LossOptions,
+) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("softmax_cross_entropy");
+    let losses = ops::softmax_cross_entropy_with_logits(&mut scope, logits, labels.clone())?;
+    let class_weights = match opts.class_weights {
+        Some(class_weights) => {
+            // The weight of each example is the weight of its classes,
+            // weighted by the label.
+            let weighted = ops::multiply(&mut scope, labels, class_weights)?;
+            let axis = ops::constant(&mut scope, -1i32)?;
+            Some(ops::sum(&mut scope, weighted, axis)?.into())
+        }
+        None => None,
+    };
+    weighted_mean(
+        &mut scope,
+        losses.into(),
+        class_weights,
+        opts.sample_weights,
+    )
+}
+
+/// Returns the softmax cross-entropy between `logits`, of shape
+/// `[batch_size, num_classes]`, and `labels`, an int32 or int64 vector of
+/// class indices.
+///
+/// The result is a scalar: the mean over the batch of the weighted loss of
+/// each example.
+pub fn sparse_softmax_cross_entropy(
+    scope: &mut Scope,
+    logits: Output,
+    labels: Output,
+    opts: LossOptions,
+) -> Result<Output> {
+    let mut scope = scope.new_sub_scope("sparse_softmax_cross_entropy");
+    let losses = ops::sparse_softmax_cross_entropy_with_logits(&mut scope, logits, labels.clone())?;
+    let class_weights = match opts.class_weights {
+        Some(class_weights) => {
+            let axis = ops::constant(&mut scope, 0i32)?;
+            Some(ops::gather(&mut scope, class_weights, labels, axis)?.into())
+        }
+        None => None,
+    };
+    weighted_mean(
+        &mut scope,
+        losses.into(),
+        class_weights,
+        opts.sample_weights,
+    )
+}
+
+/// Returns the mean of `losses` after multiplying by each set of weights.
+fn weighted_mean(
+    scope: &mut Scope,
+    losses: Output,
+    class_weights: Option<Output>,
+    sample_weights: Option<Output>,
+) -> Result<Output> {
+    let mut losses = losses;
+    for weights in class_weights.into_iter().chain(sample_weights) {
+        losses = ops::multiply(scope, losses, weights)?.into();
+    }
+    let axis = ops::constant(scope, 0i32)?;
+    Ok(ops::mean(scope, losses, axis)?.into())
+}
+
+/// Returns class weights inversely proportional to the frequency of each
+/// class in `labels`, so that each class contributes equally to the loss.
+/// The weight of class `c` is `labels.len() / (num_classes * count(c))`, and
+/// classes which don't occur get a weight of zero.
+pub fn balanced_class_weights<L: Copy + Into<i64>>(
+    labels: &[L],
+    num_classes: usize,
+) -> Result<Vec<f32>> {
+    let mut counts = vec![0usize; num_classes];
+    for &label in labels {
+        counts[class_index(label, num_classes)?] += 1;
+    }
+    Ok(counts
+        .iter()
+        .map(|&count| {
+            if count == 0 {
+                0.0
+            } else {
+                labels.len() as f32 / (num_classes * count) as f32
+            }
+        })
+        .collect())
+}
+
+/// Returns the weight of each example, i.e. the weight of its class.
+pub fn class_sample_weights<L: Copy + Into<i64>>(
+    labels: &[L],
+    class_weights: &[f32],
+) -> Result<Vec<f32>> {
+    labels
+        .iter()
+        .map(|&label| Ok(class_weights[class_index(label, class_weights.len())?]))
+        .collect()
+}
+
+fn class_index<L: Into<i64>>(label: L, num_classes: usize) -> Result<usize> {
+    let label = label.into();
+    if label < 0 || label as usize >= num_classes {
+        return Err(invalid_arg!(
+            "Label {} is out of range for {} classes",
+            label,
+            num_classes
+        ));
+    }
+    Ok(label as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::fetch;
+    use crate::Tensor;
+
+    #[test]
+    fn weighted_cross_entropy() {
+        let mut scope = Scope::new_root_scope();
+        // Uniform logits give a loss of ln(2) for each example.
+        let logits: Output = ops::constant(&mut scope, Tensor::<f32>::new(&[2, 2]))
+            .unwrap()
+            .into();
+        let labels: Output = ops::constant(&mut scope, &[0i32, 1][..]).unwrap().into();
+        let one_hot: Output = ops::constant(
+            &mut scope,
+            Tensor::new(&[2, 2])
+                .with_values(&[1.0f32, 0.0, 0.0, 1.0])
+                .unwrap(),
+        )
+        .unwrap()
+        .into();
+        let class_weights: Output = ops::constant(&mut scope, &[1.0f32, 3.0][..])
+            .unwrap()
+            .into();
+        let sample_weights: Output = ops::constant(&mut scope, &[2.0f32, 0.0][..])
+            .unwrap()
+            .into();
+        let unweighted = sparse_softmax_cross_entropy(
+            &mut scope,
+            logits.clone(),
+            labels.clone(),
+            LossOptions::default(),
+        )
+        .unwrap();
+        let sparse = sparse_softmax_cross_entropy(
+            &mut scope,
+            logits.clone(),
+            labels,
+            LossOptions::default().with_class_weights(class_weights.clone()),
+        )
+        .unwrap();
+        let dense = softmax_cross_entropy(
+            &mut scope,
+            logits,
+            one_hot,
+            LossOptions::default()
+                .with_class_weights(class_weights)
+                .with_sample_weights(sample_weights),
+        )
+        .unwrap();
+        let ln2 = 2.0f32.ln();
+        assert!((fetch::<f32>(&scope, &unweighted)[0] - ln2).abs() < 1e-5);
+        assert!((fetch::<f32>(&scope, &sparse)[0] - 2.0 * ln2).abs() < 1e-5);
+        assert!((fetch::<f32>(&scope, &dense)[0] - ln2).abs() < 1e-5);
+    }
+
+    #[test]
+    fn class_weights() {
+        let labels = [0i32, 0, 0, 1];
+        let weights = balanced_class_weights(&labels, 3).unwrap();
+        assert_eq!(weights, vec![4.0 / 9.0, 4.0 / 3.0, 0.0]);
+        assert_eq!(
+            class_sample_weights(&labels, &weights).unwrap(),
+            vec![4.0 / 9.0, 4.0 / 9.0, 4.0 / 9.0, 4.0 / 3.0]
+        );
+        assert!(balanced_class_weights(&[3i64], 3).is_err());
+        assert!(class_sample_weights(&[-1i64], &weights).is_err());
+    }
+}
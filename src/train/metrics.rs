@@ -0,0 +1,99 @@
+/// How the values of a metric are combined over the batches of an epoch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+pub enum Aggregation {
+    /// The mean over all examples, where each batch's values are first
+    /// averaged and then weighted by the number of examples in the batch.
+    #[default]
+    Mean,
+    /// The sum of all values, e.g. for counts of correct predictions.
+    Sum,
+    /// The smallest value.
+    Min,
+    /// The largest value.
+    Max,
+}
+
+/// Aggregates the values of a metric batch by batch, without keeping them.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct StreamingMetric {
+    aggregation: Aggregation,
+    total: f64,
+    examples: usize,
+    extreme: Option<f64>,
+}
+
+impl StreamingMetric {
+    pub(crate) fn new(aggregation: Aggregation) -> Self {
+        Self {
+            aggregation,
+            total: 0.0,
+            examples: 0,
+            extreme: None,
+        }
+    }
+
+    /// Adds the values computed for a batch of `batch_size` examples.
+    pub(crate) fn update(&mut self, values: &[f32], batch_size: usize) {
+        match self.aggregation {
+            Aggregation::Mean => {
+                if !values.is_empty() {
+                    let sum: f64 = values.iter().map(|&v| f64::from(v)).sum();
+                    self.total += batch_size as f64 * sum / values.len() as f64;
+                }
+                self.examples += batch_size;
+            }
+            Aggregation::Sum => {
+                self.total += values.iter().map(|&v| f64::from(v)).sum::<f64>();
+            }
+            Aggregation::Min | Aggregation::Max => {
+                let max = self.aggregation == Aggregation::Max;
+                for &value in values {
+                    let value = f64::from(value);
+                    self.extreme = Some(match self.extreme {
+                        Some(extreme) if max => extreme.max(value),
+                        Some(extreme) => extreme.min(value),
+                        None => value,
+                    });
+                }
+            }
+        }
+    }
+
+    /// Returns the aggregated value, which is zero if there were no values.
+    pub(crate) fn result(&self) -> f64 {
+        match self.aggregation {
+            Aggregation::Mean if self.examples == 0 => 0.0,
+            Aggregation::Mean => self.total / self.examples as f64,
+            Aggregation::Sum => self.total,
+            Aggregation::Min | Aggregation::Max => self.extreme.unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn aggregations() {
+        let batches: &[(&[f32], usize)] = &[(&[1.0, 3.0], 2), (&[5.0], 1)];
+        let results: Vec<f64> = [
+            Aggregation::Mean,
+            Aggregation::Sum,
+            Aggregation::Min,
+            Aggregation::Max,
+        ]
+        .iter()
+        .map(|&aggregation| {
+            let mut metric = StreamingMetric::new(aggregation);
+            for &(values, batch_size) in batches {
+                metric.update(values, batch_size);
+            }
+            metric.result()
+        })
+        .collect();
+        assert_eq!(results, vec![3.0, 9.0, 1.0, 5.0]);
+        assert_eq!(StreamingMetric::new(Aggregation::Mean).result(), 0.0);
+        assert_eq!(StreamingMetric::new(Aggregation::Max).result(), 0.0);
+    }
+}
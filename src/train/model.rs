@@ -0,0 +1,314 @@
+use super::SavedModelBuilder;
+use super::Signature;
+use super::TrainerOptions;
+use super::DEFAULT_SERVING_SIGNATURE_DEF_KEY;
+use crate::feed::run_by_name;
+use crate::ops;
+use crate::DataType;
+use crate::FeedValue;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Session;
+use crate::Shape;
+use crate::Tensor;
+use crate::TensorType;
+use crate::Variable;
+use std::path::Path;
+
+/// A model with named inputs and outputs, built by calling layers on the
+/// outputs of other layers, so it may have several branches.
+///
+/// ```ignore
+/// let mut model = Model::new();
+/// let x = model.add_input(&mut scope, "x", DataType::Float, Shape::from(None))?;
+/// let hidden = model.layer(&scope, "hidden", |scope, variables| dense(scope, variables, x))?;
+/// let class = model.layer(&scope, "class", |scope, variables| dense(scope, variables, hidden.clone()))?;
+/// let score = model.layer(&scope, "score", |scope, variables| dense(scope, variables, hidden))?;
+/// model.add_output("class", class)?;
+/// model.add_output("score", score)?;
+/// let mut trainer = Trainer::new(&mut scope, loss, &optimizer, model.trainer_options())?;
+/// // ...
+/// model.save(&mut scope, trainer.session(), "/tmp/export/1")?;
+/// ```
+///
+/// Layers are plain functions which add ops to the graph; the variables they
+/// push are the variables the model trains and exports.  Placeholders which
+/// are only needed for training, such as labels, need not be inputs of the
+/// model.
+#[derive(Debug, Clone, Default)]
+pub struct Model {
+    inputs: Vec<(String, Output)>,
+    outputs: Vec<(String, Output)>,
+    variables: Vec<Variable>,
+}
+
+impl Model {
+    /// Creates a model with no inputs, outputs or variables.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a placeholder named `name` as an input of the model, and returns
+    /// it.
+    pub fn add_input(
+        &mut self,
+        scope: &mut Scope,
+        name: &str,
+        data_type: DataType,
+        shape: Shape,
+    ) -> Result<Output> {
+        if self.inputs.iter().any(|(n, _)| n == name) {
+            return Err(invalid_arg!("The model already has an input {:?}", name));
+        }
+        let input: Output = ops::Placeholder::new()
+            .data_type(data_type)
+            .shape(shape)
+            .build(&mut scope.with_op_name(name))?
+            .into();
+        self.inputs.push((name.to_string(), input.clone()));
+        Ok(input)
+    }
+
+    /// Calls `layer` with a sub-scope named `name` and the model's variables,
+    /// to which the layer should add the variables it creates, and returns
+    /// the layer's result, e.g. its output or a tuple of outputs.
+    pub fn layer<F, O>(&mut self, scope: &Scope, name: &str, layer: F) -> Result<O>
+    where
+        F: FnOnce(&mut Scope, &mut Vec<Variable>) -> Result<O>,
+    {
+        layer(&mut scope.new_sub_scope(name), &mut self.variables)
+    }
+
+    /// Adds `output` as an output of the model under `name`.
+    pub fn add_output<T: Into<Output>>(&mut self, name: &str, output: T) -> Result<()> {
+        if self.outputs.iter().any(|(n, _)| n == name) {
+            return Err(invalid_arg!("The model already has an output {:?}", name));
+        }
+        self.outputs.push((name.to_string(), output.into()));
+        Ok(())
+    }
+
+    /// Returns the inputs with their names, in the order they were added.
+    pub fn inputs(&self) -> &[(String, Output)] {
+        &self.inputs
+    }
+
+    /// Returns the outputs with their names, in the order they were added.
+    pub fn outputs(&self) -> &[(String, Output)] {
+        &self.outputs
+    }
+
+    /// Returns the input named `name`, e.g. to feed it in `TrainingData`.
+    pub fn input(&self, name: &str) -> Result<Output> {
+        find(&self.inputs, name).ok_or_else(|| invalid_arg!("The model has no input {:?}", name))
+    }
+
+    /// Returns the output named `name`, e.g. to compute a loss from it.
+    pub fn output(&self, name: &str) -> Result<Output> {
+        find(&self.outputs, name).ok_or_else(|| invalid_arg!("The model has no output {:?}", name))
+    }
+
+    /// Returns the variables created by the layers.
+    pub fn variables(&self) -> &[Variable] {
+        &self.variables
+    }
+
+    /// Returns options for a `Trainer` which trains the model's variables.
+    pub fn trainer_options(&self) -> TrainerOptions<'_> {
+        TrainerOptions::default().with_variables(&self.variables)
+    }
+
+    /// Returns a prediction signature with the model's inputs and outputs
+    /// under their names.
+    pub fn signature(&self) -> Signature {
+        let signature = self
+            .inputs
+            .iter()
+            .fold(Signature::predict(), |s, (name, input)| {
+                s.with_input(name, input.clone())
+            });
+        self.outputs.iter().fold(signature, |s, (name, output)| {
+            s.with_output(name, output.clone())
+        })
+    }
+
+    /// Saves the graph of `scope` with the model's variables from `session`
+    /// as a SavedModel in `export_dir`, with the model's signature as the
+    /// default serving signature.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        scope: &mut Scope,
+        session: &Session,
+        export_dir: P,
+    ) -> Result<()> {
+        SavedModelBuilder::new()
+            .with_signature(DEFAULT_SERVING_SIGNATURE_DEF_KEY, self.signature())
+            .save(scope, session, &self.variables, export_dir)
+    }
+
+    /// Feeds each tensor in `inputs` to the model input with the paired name,
+    /// and returns the value of the output named `output`.
+    pub fn predict<U: TensorType>(
+        &self,
+        session: &Session,
+        inputs: &[(&str, &dyn FeedValue)],
+        output: &str,
+    ) -> Result<Tensor<U>> {
+        run_by_name(
+            session,
+            inputs,
+            output,
+            |name| self.input(name),
+            |name| self.output(name),
+        )
+    }
+}
+
+fn find(tensors: &[(String, Output)], name: &str) -> Option<Output> {
+    tensors
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, output)| output.clone())
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::train::GradientDescentOptimizer;
+    use crate::train::Trainer;
+    use crate::train::TrainingData;
+    use crate::SessionOptions;
+
+    #[test]
+    fn two_branches() {
+        let mut scope = Scope::new_root_scope();
+        let mut model = Model::new();
+        let a = model
+            .add_input(
+                &mut scope,
+                "a",
+                DataType::Float,
+                Shape::from(Some(vec![None])),
+            )
+            .unwrap();
+        let b = model
+            .add_input(
+                &mut scope,
+                "b",
+                DataType::Float,
+                Shape::from(Some(vec![None])),
+            )
+            .unwrap();
+        assert!(model
+            .add_input(&mut scope, "a", DataType::Float, Shape::from(None))
+            .is_err());
+        let scale = |scope: &mut Scope, variables: &mut Vec<Variable>, x: Output| {
+            let w = Variable::builder()
+                .const_initial_value(0.0f32)
+                .build(&mut scope.with_op_name("w"))?;
+            variables.push(w.clone());
+            Ok(ops::multiply(scope, w.output().clone(), x)?.into())
+        };
+        let a_out: Output = model
+            .layer(&scope, "scale_a", |scope, vars| scale(scope, vars, a))
+            .unwrap();
+        let b_out: Output = model
+            .layer(&scope, "scale_b", |scope, vars| scale(scope, vars, b))
+            .unwrap();
+        let sum = model
+            .layer(&scope, "sum", |scope, _| {
+                ops::add(scope, a_out.clone(), b_out.clone())
+            })
+            .unwrap();
+        model.add_output("a_out", a_out.clone()).unwrap();
+        model.add_output("sum", sum.clone()).unwrap();
+        assert!(model.add_output("sum", sum.clone()).is_err());
+        assert_eq!(model.variables().len(), 2);
+        assert_eq!(model.inputs()[1].0, "b");
+        assert_eq!(model.input("a").unwrap().operation.name().unwrap(), "a");
+        assert!(model.output("missing").is_err());
+        let signature = model.signature();
+        assert_eq!(signature.inputs().len(), 2);
+        assert_eq!(signature.outputs()[1].0, "sum");
+
+        // Train a_out = 2 * a and sum = 2 * a + 3 * b.
+        let a_target = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .build(&mut scope.with_op_name("a_target"))
+            .unwrap();
+        let sum_target = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .build(&mut scope.with_op_name("sum_target"))
+            .unwrap();
+        let a_error = ops::subtract(&mut scope, a_out, a_target.clone()).unwrap();
+        let sum_error = ops::subtract(&mut scope, sum, sum_target.clone()).unwrap();
+        let a_loss = ops::multiply(&mut scope, a_error.clone(), a_error).unwrap();
+        let sum_loss = ops::multiply(&mut scope, sum_error.clone(), sum_error).unwrap();
+        let loss = ops::add(&mut scope, a_loss, sum_loss).unwrap();
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.05f32).unwrap().into());
+        let mut trainer = Trainer::new(
+            &mut scope,
+            loss.into(),
+            &optimizer,
+            model.trainer_options().with_batch_size(4),
+        )
+        .unwrap();
+        let xs: Vec<f32> = (0..8).map(|i| i as f32 / 8.0).collect();
+        let ys: Vec<f32> = xs.iter().rev().cloned().collect();
+        let a_targets: Vec<f32> = xs.iter().map(|x| 2.0 * x).collect();
+        let sum_targets: Vec<f32> = xs.iter().zip(&ys).map(|(x, y)| 2.0 * x + 3.0 * y).collect();
+        let data = TrainingData::new()
+            .with_input(model.input("a").unwrap(), Tensor::from(&xs[..]))
+            .with_input(model.input("b").unwrap(), Tensor::from(&ys[..]))
+            .with_input(a_target.into(), Tensor::from(&a_targets[..]))
+            .with_input(sum_target.into(), Tensor::from(&sum_targets[..]));
+        trainer.fit(&data, 200).unwrap();
+
+        let a = Tensor::from(&[1.0f32][..]);
+        let b = Tensor::from(&[1.0f32][..]);
+        let sum: Tensor<f32> = model
+            .predict(trainer.session(), &[("a", &a), ("b", &b)], "sum")
+            .unwrap();
+        assert!((sum[0] - 5.0).abs() < 0.1, "sum = {}", sum[0]);
+        assert!(model
+            .predict::<f32>(trainer.session(), &[("c", &a)], "sum")
+            .is_err());
+
+        let export_dir = std::env::temp_dir().join("tensorflow_functional_model");
+        let _ = std::fs::remove_dir_all(&export_dir);
+        model
+            .save(&mut scope, trainer.session(), &export_dir)
+            .unwrap();
+        assert!(export_dir.join("saved_model.pb").exists());
+    }
+
+    #[test]
+    fn predict_mixed_types() {
+        let mut scope = Scope::new_root_scope();
+        let mut model = Model::new();
+        let x = model
+            .add_input(&mut scope, "x", DataType::Float, Shape::from(None))
+            .unwrap();
+        let n = model
+            .add_input(&mut scope, "n", DataType::Int32, Shape::from(None))
+            .unwrap();
+        let scaled = model
+            .layer(&scope, "scaled", |scope, _| {
+                let n = ops::Cast::new().dst_type(DataType::Float).build(scope, n)?;
+                ops::multiply(scope, x, n)
+            })
+            .unwrap();
+        model.add_output("scaled", scaled).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let x = Tensor::from(&[1.5f32, 2.0][..]);
+        let n = vec![2i32, 3];
+        let scaled: Tensor<f32> = model
+            .predict(&session, &[("x", &x), ("n", &n)], "scaled")
+            .unwrap();
+        assert_eq!(&scaled[..], &[3.0, 6.0]);
+    }
+}
@@ -0,0 +1,434 @@
+use super::Saver;
+use crate::FetchToken;
+use crate::Output;
+use crate::Result;
+use crate::Session;
+use crate::SessionRunArgs;
+
+/// Hooks which a `MonitoredSession` calls around every run, e.g. to stop
+/// training, save checkpoints or check the loss.
+///
+/// All methods do nothing by default.  An error returned by a hook is
+/// returned from `MonitoredSession::run`.
+pub trait SessionRunHook {
+    /// Called before each run.  The hook may request additional fetches and
+    /// targets from `args`, which are run along with the caller's and removed
+    /// again after `after_run`, so the caller can reuse `args`.
+    fn before_run(
+        &mut self,
+        _context: &mut HookContext<'_>,
+        _args: &mut SessionRunArgs<'_>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called after each successful run, with the fetches requested in
+    /// `before_run` available from `args`.
+    fn after_run(
+        &mut self,
+        _context: &mut HookContext<'_>,
+        _args: &mut SessionRunArgs<'_>,
+    ) -> Result<()> {
+        Ok(())
+    }
+
+    /// Called when the session is closed with `MonitoredSession::close`.
+    fn end(&mut self, _context: &mut HookContext<'_>) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// The state of a `MonitoredSession` which is visible to a `SessionRunHook`.
+#[derive(Debug)]
+pub struct HookContext<'a> {
+    session: &'a Session,
+    step: u64,
+    stop_requested: &'a mut bool,
+}
+
+impl<'a> HookContext<'a> {
+    /// Returns the session.
+    pub fn session(&self) -> &Session {
+        self.session
+    }
+
+    /// Returns the number of runs completed, including the current one in
+    /// `after_run`.
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// Asks the caller to stop running, as reported by
+    /// `MonitoredSession::should_stop`.
+    pub fn request_stop(&mut self) {
+        *self.stop_requested = true;
+    }
+}
+
+/// A session which calls `SessionRunHook`s around every run, like
+/// `tf.train.MonitoredSession` in Python, so that concerns such as
+/// checkpointing and stopping conditions can be added to a training loop
+/// without changing it.
+///
+/// ```ignore
+/// let mut session = MonitoredSession::new(session)
+///     .with_hook(StopAtStepHook::new(10000))
+///     .with_hook(CheckpointSaverHook::new(saver, "/tmp/model-{step}.ckpt", 1000))
+///     .with_hook(NanLossHook::new(loss));
+/// while !session.should_stop() {
+///     let mut args = SessionRunArgs::new();
+///     args.add_target(&train_op);
+///     session.run(&mut args)?;
+/// }
+/// session.close()?;
+/// ```
+pub struct MonitoredSession {
+    session: Session,
+    hooks: Vec<Box<dyn SessionRunHook>>,
+    step: u64,
+    stop_requested: bool,
+}
+
+impl std::fmt::Debug for MonitoredSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MonitoredSession")
+            .field("session", &self.session)
+            .field("hooks", &self.hooks.len())
+            .field("step", &self.step)
+            .field("stop_requested", &self.stop_requested)
+            .finish()
+    }
+}
+
+impl MonitoredSession {
+    /// Wraps `session`, with no hooks.
+    pub fn new(session: Session) -> Self {
+        Self {
+            session,
+            hooks: Vec::new(),
+            step: 0,
+            stop_requested: false,
+        }
+    }
+
+    /// Adds a hook, which is called after the hooks added before it.
+    pub fn with_hook<H: SessionRunHook + 'static>(mut self, hook: H) -> Self {
+        self.hooks.push(Box::new(hook));
+        self
+    }
+
+    /// Returns the wrapped session.  Runs through it bypass the hooks.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    /// Returns the number of runs completed.
+    pub fn step(&self) -> u64 {
+        self.step
+    }
+
+    /// Returns whether a hook has asked to stop running.
+    pub fn should_stop(&self) -> bool {
+        self.stop_requested
+    }
+
+    /// Runs the session with `args`, calling each hook's `before_run` before
+    /// and `after_run` after.
+    pub fn run(&mut self, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        let counts = args.request_counts();
+        let result = self.run_with_hooks(args);
+        args.truncate(counts);
+        result
+    }
+
+    fn run_with_hooks(&mut self, args: &mut SessionRunArgs<'_>) -> Result<()> {
+        let mut context = HookContext {
+            session: &self.session,
+            step: self.step,
+            stop_requested: &mut self.stop_requested,
+        };
+        for hook in &mut self.hooks {
+            hook.before_run(&mut context, args)?;
+        }
+        self.session.run(args)?;
+        self.step += 1;
+        context.step = self.step;
+        for hook in &mut self.hooks {
+            hook.after_run(&mut context, args)?;
+        }
+        Ok(())
+    }
+
+    /// Calls each hook's `end` and closes the session.
+    pub fn close(mut self) -> Result<()> {
+        let mut context = HookContext {
+            session: &self.session,
+            step: self.step,
+            stop_requested: &mut self.stop_requested,
+        };
+        for hook in &mut self.hooks {
+            hook.end(&mut context)?;
+        }
+        self.session.close()
+    }
+}
+
+/// Asks to stop once a number of runs have completed.
+#[derive(Debug, Clone, Copy)]
+pub struct StopAtStepHook {
+    last_step: u64,
+}
+
+impl StopAtStepHook {
+    /// Asks to stop after `last_step` runs.
+    pub fn new(last_step: u64) -> Self {
+        Self { last_step }
+    }
+}
+
+impl SessionRunHook for StopAtStepHook {
+    fn after_run(
+        &mut self,
+        context: &mut HookContext<'_>,
+        _args: &mut SessionRunArgs<'_>,
+    ) -> Result<()> {
+        if context.step() >= self.last_step {
+            context.request_stop();
+        }
+        Ok(())
+    }
+}
+
+/// Saves checkpoints periodically, and when the session is closed.
+#[derive(Debug, Clone)]
+pub struct CheckpointSaverHook {
+    saver: Saver,
+    path: String,
+    every_steps: u64,
+    last_saved_step: Option<u64>,
+}
+
+impl CheckpointSaverHook {
+    /// Saves a checkpoint with `saver` after every `every_steps` runs.
+    /// `path` is the path prefix of the checkpoint, where "{step}" is
+    /// replaced with the number of runs completed, e.g.
+    /// "/tmp/model-{step}.ckpt".
+    pub fn new(saver: Saver, path: &str, every_steps: u64) -> Self {
+        Self {
+            saver,
+            path: path.to_string(),
+            every_steps: every_steps.max(1),
+            last_saved_step: None,
+        }
+    }
+
+    fn save(&mut self, context: &HookContext<'_>) -> Result<()> {
+        let path = self.path.replace("{step}", &context.step().to_string());
+        self.saver.save(context.session(), &path)?;
+        self.last_saved_step = Some(context.step());
+        Ok(())
+    }
+}
+
+impl SessionRunHook for CheckpointSaverHook {
+    fn after_run(
+        &mut self,
+        context: &mut HookContext<'_>,
+        _args: &mut SessionRunArgs<'_>,
+    ) -> Result<()> {
+        if context.step().is_multiple_of(self.every_steps) {
+            self.save(context)?;
+        }
+        Ok(())
+    }
+
+    fn end(&mut self, context: &mut HookContext<'_>) -> Result<()> {
+        if context.step() > 0 && self.last_saved_step != Some(context.step()) {
+            self.save(context)?;
+        }
+        Ok(())
+    }
+}
+
+/// Fetches the loss with every run and fails the run if it is NaN.
+#[derive(Debug, Clone)]
+pub struct NanLossHook {
+    loss: Output,
+    stop_only: bool,
+    token: Option<FetchToken>,
+}
+
+impl NanLossHook {
+    /// Checks `loss`, which must be a float scalar.
+    pub fn new(loss: Output) -> Self {
+        Self {
+            loss,
+            stop_only: false,
+            token: None,
+        }
+    }
+
+    /// Asks to stop rather than returning an error when the loss is NaN.
+    pub fn stop_only(self) -> Self {
+        Self {
+            stop_only: true,
+            ..self
+        }
+    }
+}
+
+impl SessionRunHook for NanLossHook {
+    fn before_run(
+        &mut self,
+        _context: &mut HookContext<'_>,
+        args: &mut SessionRunArgs<'_>,
+    ) -> Result<()> {
+        self.token = Some(args.request_fetch(&self.loss.operation, self.loss.index));
+        Ok(())
+    }
+
+    fn after_run(
+        &mut self,
+        context: &mut HookContext<'_>,
+        args: &mut SessionRunArgs<'_>,
+    ) -> Result<()> {
+        let token = match self.token.take() {
+            Some(token) => token,
+            None => return Ok(()),
+        };
+        let loss = args.fetch::<f32>(token)?;
+        if loss.iter().any(|x| x.is_nan()) {
+            if self.stop_only {
+                context.request_stop();
+            } else {
+                return Err(invalid_arg!("Loss is NaN after step {}", context.step()));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Calls a function after every `n` runs, e.g. to log progress or flush
+/// summaries.
+pub struct EveryNStepsHook<F> {
+    n: u64,
+    f: F,
+}
+
+impl<F> std::fmt::Debug for EveryNStepsHook<F> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("EveryNStepsHook")
+            .field("n", &self.n)
+            .finish()
+    }
+}
+
+impl<F: FnMut(&mut HookContext<'_>) -> Result<()>> EveryNStepsHook<F> {
+    /// Calls `f` after every `n` runs.
+    pub fn new(n: u64, f: F) -> Self {
+        Self { n: n.max(1), f }
+    }
+}
+
+impl<F: FnMut(&mut HookContext<'_>) -> Result<()>> SessionRunHook for EveryNStepsHook<F> {
+    fn after_run(
+        &mut self,
+        context: &mut HookContext<'_>,
+        _args: &mut SessionRunArgs<'_>,
+    ) -> Result<()> {
+        if context.step().is_multiple_of(self.n) {
+            (self.f)(context)?;
+        }
+        Ok(())
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::Scope;
+    use crate::SessionOptions;
+    use crate::Variable;
+    use std::cell::Cell;
+    use std::env;
+    use std::path::Path;
+    use std::rc::Rc;
+
+    /// Builds a counter which each run of the returned op increments, and a
+    /// loss which is NaN on the second run.
+    fn build() -> (Scope, Variable, crate::Operation, Output) {
+        let mut scope = Scope::new_root_scope();
+        let counter = Variable::builder()
+            .const_initial_value(0.0f32)
+            .build(&mut scope.with_op_name("counter"))
+            .unwrap();
+        let one = ops::constant(&mut scope, 1.0f32).unwrap();
+        let next = ops::add(&mut scope, counter.output().clone(), one).unwrap();
+        let increment = ops::assign(&mut scope, counter.output().clone(), next.clone()).unwrap();
+        let two = ops::constant(&mut scope, 2.0f32).unwrap();
+        let zero = ops::constant(&mut scope, 0.0f32).unwrap();
+        // (next - 2) / 0 is NaN when next is 2.
+        let diff = ops::subtract(&mut scope, next, two).unwrap();
+        let loss = ops::divide(&mut scope, diff, zero).unwrap();
+        (scope, counter, increment, loss.into())
+    }
+
+    fn new_session(scope: &Scope, counter: &Variable) -> Session {
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(counter.initializer());
+        session.run(&mut args).unwrap();
+        session
+    }
+
+    #[test]
+    fn stop_at_step_and_checkpoint() {
+        let (mut scope, counter, increment, _) = build();
+        let saver = Saver::new(&mut scope, &[counter.clone()]).unwrap();
+        let session = new_session(&scope, &counter);
+        let path = env::temp_dir().join("tensorflow_monitored_session-{step}.ckpt");
+        let calls = Rc::new(Cell::new(0));
+        let hook_calls = calls.clone();
+        let mut session = MonitoredSession::new(session)
+            .with_hook(StopAtStepHook::new(5))
+            .with_hook(CheckpointSaverHook::new(saver, path.to_str().unwrap(), 2))
+            .with_hook(EveryNStepsHook::new(2, move |_| {
+                hook_calls.set(hook_calls.get() + 1);
+                Ok(())
+            }));
+        let mut args = SessionRunArgs::new();
+        args.add_target(&increment);
+        while !session.should_stop() {
+            session.run(&mut args).unwrap();
+        }
+        assert_eq!(session.step(), 5);
+        assert_eq!(calls.get(), 2);
+        session.close().unwrap();
+        for step in &[2, 4, 5] {
+            let index = format!("tensorflow_monitored_session-{}.ckpt.index", step);
+            assert!(Path::new(&env::temp_dir().join(index)).exists());
+        }
+    }
+
+    #[test]
+    fn nan_loss() {
+        let (scope, counter, increment, loss) = build();
+        let session = new_session(&scope, &counter);
+        let mut session = MonitoredSession::new(session).with_hook(NanLossHook::new(loss.clone()));
+        let mut args = SessionRunArgs::new();
+        args.add_target(&increment);
+        session.run(&mut args).unwrap();
+        assert!(session.run(&mut args).is_err());
+
+        let session = new_session(&scope, &counter);
+        let mut session =
+            MonitoredSession::new(session).with_hook(NanLossHook::new(loss).stop_only());
+        session.run(&mut args).unwrap();
+        assert!(!session.should_stop());
+        session.run(&mut args).unwrap();
+        assert!(session.should_stop());
+    }
+}
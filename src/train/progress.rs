@@ -0,0 +1,355 @@
+use super::Callback;
+use super::CallbackContext;
+use super::EpochMetrics;
+use crate::Result;
+use std::ops::Range;
+use std::time::Duration;
+use std::time::Instant;
+
+/// The progress of training after a step.
+#[derive(Debug, Clone, PartialEq)]
+pub struct StepProgress {
+    epoch: usize,
+    epochs: Range<usize>,
+    batch: usize,
+    batches_per_epoch: usize,
+    loss: f64,
+    steps_per_sec: f64,
+    eta: Option<Duration>,
+}
+
+impl StepProgress {
+    /// Returns the index of the current epoch.
+    pub fn epoch(&self) -> usize {
+        self.epoch
+    }
+
+    /// Returns the epochs being trained, unless training is stopped early.
+    pub fn epochs(&self) -> Range<usize> {
+        self.epochs.clone()
+    }
+
+    /// Returns the number of steps finished since training started.
+    pub fn steps(&self) -> usize {
+        (self.epoch - self.epochs.start) * self.batches_per_epoch + self.batch + 1
+    }
+
+    /// Returns the total number of steps, unless training is stopped early.
+    pub fn total_steps(&self) -> usize {
+        self.epochs.len() * self.batches_per_epoch
+    }
+
+    /// Returns the index of the batch within the epoch.
+    pub fn batch(&self) -> usize {
+        self.batch
+    }
+
+    /// Returns the number of batches in each epoch.
+    pub fn batches_per_epoch(&self) -> usize {
+        self.batches_per_epoch
+    }
+
+    /// Returns the average loss of the batch.
+    pub fn loss(&self) -> f64 {
+        self.loss
+    }
+
+    /// Returns the number of steps per second since training started.
+    pub fn steps_per_sec(&self) -> f64 {
+        self.steps_per_sec
+    }
+
+    /// Returns the estimated time until training ends, if any steps have
+    /// finished in measurable time.
+    pub fn eta(&self) -> Option<Duration> {
+        self.eta
+    }
+}
+
+/// Receives training progress from `Progress`.
+pub trait ProgressReporter {
+    /// Called after every `Progress::every_steps` steps.
+    fn report_step(&mut self, progress: &StepProgress) -> Result<()>;
+
+    /// Called after each epoch with its metrics.  Does nothing by default.
+    fn report_epoch(&mut self, _epoch: usize, _metrics: &EpochMetrics) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// A `Callback` which measures training speed and passes the progress of
+/// training to a `ProgressReporter`.
+///
+/// ```ignore
+/// let mut progress = Progress::new(LogReporter::new()).every_steps(100);
+/// trainer.fit_with_callbacks(&data, 10, &mut [&mut progress])?;
+/// ```
+#[derive(Debug)]
+pub struct Progress<R: ProgressReporter> {
+    reporter: R,
+    every_steps: usize,
+    start: Option<Instant>,
+    steps: usize,
+}
+
+impl<R: ProgressReporter> Progress<R> {
+    /// Reports the progress of every step to `reporter`.
+    pub fn new(reporter: R) -> Self {
+        Self {
+            reporter,
+            every_steps: 1,
+            start: None,
+            steps: 0,
+        }
+    }
+
+    /// Reports only every `n`th step of each epoch, and the last.
+    pub fn every_steps(self, n: usize) -> Self {
+        Self {
+            every_steps: n.max(1),
+            ..self
+        }
+    }
+
+    /// Returns the reporter.
+    pub fn reporter(&self) -> &R {
+        &self.reporter
+    }
+}
+
+impl<R: ProgressReporter> Callback for Progress<R> {
+    fn on_epoch_begin(&mut self, context: &mut CallbackContext<'_>) -> Result<()> {
+        if context.epoch() == context.epochs().start || self.start.is_none() {
+            self.start = Some(Instant::now());
+            self.steps = 0;
+        }
+        Ok(())
+    }
+
+    fn on_batch_end(
+        &mut self,
+        context: &mut CallbackContext<'_>,
+        batch: usize,
+        loss: f64,
+    ) -> Result<()> {
+        self.steps += 1;
+        let batches_per_epoch = context.batches_per_epoch();
+        let last = batch + 1 == batches_per_epoch;
+        if batch % self.every_steps != self.every_steps - 1 && !last {
+            return Ok(());
+        }
+        let elapsed = self
+            .start
+            .map(|start| start.elapsed().as_secs_f64())
+            .unwrap_or_default();
+        let steps_per_sec = if elapsed > 0.0 {
+            self.steps as f64 / elapsed
+        } else {
+            0.0
+        };
+        let mut progress = StepProgress {
+            epoch: context.epoch(),
+            epochs: context.epochs(),
+            batch,
+            batches_per_epoch,
+            loss,
+            steps_per_sec,
+            eta: None,
+        };
+        if steps_per_sec > 0.0 {
+            let remaining_steps = progress.total_steps().saturating_sub(progress.steps());
+            progress.eta = Some(Duration::from_secs_f64(
+                remaining_steps as f64 / steps_per_sec,
+            ));
+        }
+        self.reporter.report_step(&progress)
+    }
+
+    fn on_epoch_end(
+        &mut self,
+        context: &mut CallbackContext<'_>,
+        metrics: &EpochMetrics,
+    ) -> Result<()> {
+        self.reporter.report_epoch(context.epoch(), metrics)
+    }
+}
+
+/// Reports training progress through the `log` crate at info level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogReporter {}
+
+impl LogReporter {
+    /// Creates a reporter which logs every reported step and epoch.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+impl ProgressReporter for LogReporter {
+    fn report_step(&mut self, progress: &StepProgress) -> Result<()> {
+        log::info!(
+            "epoch {}/{}, batch {}/{}: loss {:.6}, {:.1} steps/s, ETA {}",
+            progress.epoch() + 1,
+            progress.epochs().end,
+            progress.batch() + 1,
+            progress.batches_per_epoch(),
+            progress.loss(),
+            progress.steps_per_sec(),
+            format_eta(progress.eta())
+        );
+        Ok(())
+    }
+
+    fn report_epoch(&mut self, epoch: usize, metrics: &EpochMetrics) -> Result<()> {
+        let mut message = format!("epoch {}: loss {:.6}", epoch + 1, metrics.loss());
+        for (name, value) in metrics.metrics() {
+            message.push_str(&format!(", {} {:.6}", name, value));
+        }
+        log::info!("{}", message);
+        Ok(())
+    }
+}
+
+fn format_eta(eta: Option<Duration>) -> String {
+    match eta {
+        Some(eta) => {
+            let secs = eta.as_secs();
+            format!("{}:{:02}:{:02}", secs / 3600, secs / 60 % 60, secs % 60)
+        }
+        None => "unknown".to_string(),
+    }
+}
+
+/// Shows training progress as a progress bar in the terminal, with one bar
+/// per call to `fit`.
+#[cfg(feature = "progress_bar")]
+#[derive(Debug, Default)]
+pub struct ProgressBarReporter {
+    bar: Option<indicatif::ProgressBar>,
+}
+
+#[cfg(feature = "progress_bar")]
+impl ProgressBarReporter {
+    /// Creates a reporter which draws to stderr.
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[cfg(feature = "progress_bar")]
+impl ProgressReporter for ProgressBarReporter {
+    fn report_step(&mut self, progress: &StepProgress) -> Result<()> {
+        let total_steps = progress.total_steps() as u64;
+        let bar = self.bar.get_or_insert_with(|| {
+            let bar = indicatif::ProgressBar::new(total_steps);
+            bar.set_style(
+                indicatif::ProgressStyle::default_bar()
+                    .template("{bar:40} {pos}/{len} [{elapsed_precise}<{eta_precise}] {msg}"),
+            );
+            bar
+        });
+        bar.set_length(total_steps);
+        bar.set_position(progress.steps() as u64);
+        bar.set_message(&format!(
+            "epoch {}/{}, loss {:.4}",
+            progress.epoch() + 1,
+            progress.epochs().end,
+            progress.loss()
+        ));
+        Ok(())
+    }
+
+    fn report_epoch(&mut self, epoch: usize, metrics: &EpochMetrics) -> Result<()> {
+        if let Some(bar) = &self.bar {
+            bar.println(format!("epoch {}: loss {:.6}", epoch + 1, metrics.loss()));
+            if bar.position() >= bar.length() {
+                bar.finish();
+                self.bar = None;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Graph;
+    use crate::Session;
+    use crate::SessionOptions;
+
+    #[derive(Default)]
+    struct Record {
+        steps: Vec<StepProgress>,
+        epochs: Vec<usize>,
+    }
+
+    impl ProgressReporter for Record {
+        fn report_step(&mut self, progress: &StepProgress) -> Result<()> {
+            self.steps.push(progress.clone());
+            Ok(())
+        }
+
+        fn report_epoch(&mut self, epoch: usize, _metrics: &EpochMetrics) -> Result<()> {
+            self.epochs.push(epoch);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn progress() {
+        let session = Session::new(&SessionOptions::new(), &Graph::new()).unwrap();
+        let mut hyperparameters = vec![];
+        let mut stop_training = false;
+        let mut progress = Progress::new(Record::default()).every_steps(2);
+        for epoch in 3..5 {
+            let mut context = CallbackContext {
+                session: &session,
+                epoch,
+                epochs: 3..5,
+                batches_per_epoch: 3,
+                hyperparameters: &mut hyperparameters,
+                stop_training: &mut stop_training,
+            };
+            progress.on_epoch_begin(&mut context).unwrap();
+            for batch in 0..3 {
+                progress
+                    .on_batch_end(&mut context, batch, batch as f64)
+                    .unwrap();
+            }
+            let metrics = EpochMetrics {
+                loss: 1.0,
+                metrics: vec![],
+            };
+            progress.on_epoch_end(&mut context, &metrics).unwrap();
+        }
+        let record = progress.reporter();
+        assert_eq!(record.epochs, vec![3, 4]);
+        let steps: Vec<_> = record
+            .steps
+            .iter()
+            .map(|s| (s.epoch(), s.batch(), s.steps(), s.loss()))
+            .collect();
+        assert_eq!(
+            steps,
+            vec![
+                (3, 1, 2, 1.0),
+                (3, 2, 3, 2.0),
+                (4, 1, 5, 1.0),
+                (4, 2, 6, 2.0)
+            ]
+        );
+        assert_eq!(record.steps[0].total_steps(), 6);
+        // Nothing remains after the last step.
+        assert_eq!(
+            record.steps[3].eta().unwrap_or_default(),
+            Duration::default()
+        );
+    }
+
+    #[test]
+    fn eta_format() {
+        assert_eq!(format_eta(Some(Duration::from_secs(3723))), "1:02:03");
+        assert_eq!(format_eta(None), "unknown");
+    }
+}
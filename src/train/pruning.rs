@@ -0,0 +1,358 @@
+use super::Callback;
+use super::CallbackContext;
+use crate::ops;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Tensor;
+use crate::Variable;
+
+/// How the target sparsity of pruned weights grows during training.
+///
+/// The sparsity rises from the initial to the final sparsity between
+/// `begin_step` and `end_step`, quickly at first and then more slowly
+/// (polynomial decay with power 3), so the model can recover from pruning
+/// before more weights are removed.  Masks are updated every `frequency`
+/// steps in that range.
+#[derive(Debug, Clone, Copy)]
+pub struct PruningSchedule {
+    initial_sparsity: f32,
+    final_sparsity: f32,
+    begin_step: u64,
+    end_step: u64,
+    frequency: u64,
+}
+
+impl PruningSchedule {
+    /// Creates a schedule which reaches `final_sparsity`, the fraction of
+    /// weights which are zero, at `end_step`.  The initial sparsity is 0 and
+    /// masks are updated every 100 steps.
+    pub fn new(final_sparsity: f32, begin_step: u64, end_step: u64) -> Result<Self> {
+        if !(0.0..1.0).contains(&final_sparsity) {
+            return Err(invalid_arg!(
+                "Sparsity must be in [0, 1), got {}",
+                final_sparsity
+            ));
+        }
+        if end_step < begin_step {
+            return Err(invalid_arg!(
+                "End step {} is before begin step {}",
+                end_step,
+                begin_step
+            ));
+        }
+        Ok(Self {
+            initial_sparsity: 0.0,
+            final_sparsity,
+            begin_step,
+            end_step,
+            frequency: 100,
+        })
+    }
+
+    /// Sets the sparsity at `begin_step`.
+    pub fn with_initial_sparsity(self, initial_sparsity: f32) -> Self {
+        Self {
+            initial_sparsity,
+            ..self
+        }
+    }
+
+    /// Sets the number of steps between mask updates.  Default is 100.
+    pub fn with_frequency(self, frequency: u64) -> Self {
+        Self {
+            frequency: frequency.max(1),
+            ..self
+        }
+    }
+
+    /// Returns the target sparsity at `step`.
+    pub fn sparsity(&self, step: u64) -> f32 {
+        if step <= self.begin_step {
+            return self.initial_sparsity;
+        }
+        if step >= self.end_step {
+            return self.final_sparsity;
+        }
+        let progress = (step - self.begin_step) as f32 / (self.end_step - self.begin_step) as f32;
+        self.final_sparsity
+            + (self.initial_sparsity - self.final_sparsity) * (1.0 - progress).powi(3)
+    }
+
+    /// Returns whether masks are updated at `step`.
+    pub fn should_prune(&self, step: u64) -> bool {
+        step >= self.begin_step
+            && step <= self.end_step
+            && ((step - self.begin_step).is_multiple_of(self.frequency) || step == self.end_step)
+    }
+}
+
+/// A weight with its mask and the ops which update them.
+#[derive(Debug, Clone)]
+struct PrunedWeight {
+    weight: Variable,
+    mask: Variable,
+    mask_value: Operation,
+    assign_mask: Operation,
+    apply_mask: Operation,
+}
+
+/// Prunes the smallest weights of a model during training, producing a
+/// sparse model which compresses well.
+///
+/// ```ignore
+/// let mut pruning = Pruning::new(PruningSchedule::new(0.8, 1000, 5000)?);
+/// let w = pruning.prune(&mut scope, &w)?;
+/// let y = ops::mat_mul(&mut scope, x, w)?;
+/// let mut trainer = Trainer::new(&mut scope, loss, &optimizer, options)?;
+/// // The masks aren't trained, so the trainer doesn't initialize them.
+/// reinitialize_variables(trainer.session(), &pruning.masks())?;
+/// trainer.fit_with_callbacks(&data, epochs, &mut [&mut pruning])?;
+/// ```
+///
+/// Each pruned weight gets a mask variable of the same shape, and the model
+/// uses the product of the weight and its mask, so pruned weights neither
+/// affect the outputs nor receive gradients.  When the masks are updated,
+/// the weights with the smallest magnitudes are masked until the scheduled
+/// sparsity is reached, and the weights are multiplied by their masks, so the
+/// saved weights are sparse too.  Only float weights can be pruned.
+#[derive(Debug, Clone)]
+pub struct Pruning {
+    schedule: PruningSchedule,
+    weights: Vec<PrunedWeight>,
+}
+
+impl Pruning {
+    /// Creates pruning which follows `schedule` and has no weights yet.
+    pub fn new(schedule: PruningSchedule) -> Self {
+        Self {
+            schedule,
+            weights: Vec::new(),
+        }
+    }
+
+    /// Returns the schedule.
+    pub fn schedule(&self) -> &PruningSchedule {
+        &self.schedule
+    }
+
+    /// Adds a mask for `weight`, which must have a fully defined shape, and
+    /// returns the masked weight, which the model should use instead of the
+    /// weight.
+    pub fn prune(&mut self, scope: &mut Scope, weight: &Variable) -> Result<Output> {
+        if weight.data_type() != DataType::Float {
+            return Err(invalid_arg!(
+                "Only float weights can be pruned, but {} is {}",
+                weight.name(),
+                weight.data_type()
+            ));
+        }
+        let dims: Vec<u64> = match weight.shape().0 {
+            Some(ref dims) => dims
+                .iter()
+                .map(|d| d.map(|d| d as u64))
+                .collect::<Option<_>>()
+                .ok_or_else(|| {
+                    invalid_arg!("The shape of {} is not fully defined", weight.name())
+                })?,
+            None => return Err(invalid_arg!("The shape of {} is unknown", weight.name())),
+        };
+        let mut scope = scope.new_sub_scope(&format!("{}_pruning", weight.name()));
+        let size = dims.iter().product::<u64>() as usize;
+        let ones = Tensor::new(&dims).with_values(&vec![1.0f32; size])?;
+        let mask = Variable::builder()
+            .const_initial_value(ones)
+            .build(&mut scope.with_op_name("mask"))?;
+        let masked = ops::multiply(&mut scope, weight.output().clone(), mask.output().clone())?;
+        let mask_value = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(weight.shape().clone())
+            .build(&mut scope.with_op_name("mask_value"))?;
+        let assign_mask = ops::assign(&mut scope, mask.output().clone(), mask_value.clone())?;
+        let apply_mask = ops::assign(&mut scope, weight.output().clone(), masked.clone())?;
+        self.weights.push(PrunedWeight {
+            weight: weight.clone(),
+            mask,
+            mask_value,
+            assign_mask,
+            apply_mask,
+        });
+        Ok(masked.into())
+    }
+
+    /// Returns the mask variables, which must be initialized before training
+    /// but not trained.
+    pub fn masks(&self) -> Vec<Variable> {
+        self.weights.iter().map(|w| w.mask.clone()).collect()
+    }
+
+    /// Updates the masks if the schedule prunes at `step`, returning whether
+    /// they were updated.  This is for custom training loops; as a
+    /// `Callback`, pruning updates the masks after each batch.
+    pub fn update(&self, session: &Session, step: u64) -> Result<bool> {
+        if !self.schedule.should_prune(step) {
+            return Ok(false);
+        }
+        self.prune_to(session, self.schedule.sparsity(step))?;
+        Ok(true)
+    }
+
+    /// Masks the smallest weights so that each pruned weight has the given
+    /// sparsity, regardless of the schedule.
+    pub fn prune_to(&self, session: &Session, sparsity: f32) -> Result<()> {
+        let mut args = SessionRunArgs::new();
+        let tokens: Vec<_> = self
+            .weights
+            .iter()
+            .map(|w| args.request_fetch(&w.weight.output().operation, w.weight.output().index))
+            .collect();
+        session.run(&mut args)?;
+        let mut masks = Vec::with_capacity(self.weights.len());
+        for token in tokens {
+            masks.push(magnitude_mask(&args.fetch::<f32>(token)?, sparsity)?);
+        }
+        let mut args = SessionRunArgs::new();
+        for (w, mask) in self.weights.iter().zip(&masks) {
+            args.add_feed(&w.mask_value, 0, mask);
+            args.add_target(&w.assign_mask);
+        }
+        session.run(&mut args)?;
+        let mut args = SessionRunArgs::new();
+        for w in &self.weights {
+            args.add_target(&w.apply_mask);
+        }
+        session.run(&mut args)
+    }
+
+    /// Returns the fraction of the pruned weights' elements which are masked.
+    pub fn sparsity(&self, session: &Session) -> Result<f32> {
+        let mut args = SessionRunArgs::new();
+        let tokens: Vec<_> = self
+            .weights
+            .iter()
+            .map(|w| args.request_fetch(&w.mask.output().operation, w.mask.output().index))
+            .collect();
+        session.run(&mut args)?;
+        let mut masked = 0;
+        let mut total = 0;
+        for token in tokens {
+            let mask = args.fetch::<f32>(token)?;
+            masked += mask.iter().filter(|&&m| m == 0.0).count();
+            total += mask.len();
+        }
+        Ok(masked as f32 / total.max(1) as f32)
+    }
+}
+
+impl Callback for Pruning {
+    fn on_batch_end(
+        &mut self,
+        context: &mut CallbackContext<'_>,
+        batch: usize,
+        _loss: f64,
+    ) -> Result<()> {
+        let step = context.epoch() * context.batches_per_epoch() + batch + 1;
+        self.update(context.session(), step as u64)?;
+        Ok(())
+    }
+}
+
+/// Returns a mask which is zero for the `sparsity` fraction of `weight`'s
+/// elements with the smallest magnitudes and one elsewhere.
+fn magnitude_mask(weight: &Tensor<f32>, sparsity: f32) -> Result<Tensor<f32>> {
+    let mut order: Vec<usize> = (0..weight.len()).collect();
+    order.sort_by(|&a, &b| weight[a].abs().total_cmp(&weight[b].abs()));
+    let pruned = (sparsity * weight.len() as f32) as usize;
+    let mut mask = vec![1.0f32; weight.len()];
+    for &i in &order[..pruned.min(weight.len())] {
+        mask[i] = 0.0;
+    }
+    Tensor::new(weight.dims()).with_values(&mask)
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SessionOptions;
+
+    #[test]
+    fn schedule() {
+        let schedule = PruningSchedule::new(0.8, 100, 200)
+            .unwrap()
+            .with_initial_sparsity(0.2)
+            .with_frequency(25);
+        assert_eq!(schedule.sparsity(0), 0.2);
+        assert_eq!(schedule.sparsity(200), 0.8);
+        assert_eq!(schedule.sparsity(1000), 0.8);
+        let middle = schedule.sparsity(150);
+        assert!((middle - 0.725).abs() < 1e-6, "sparsity = {}", middle);
+        assert!(!schedule.should_prune(99));
+        assert!(schedule.should_prune(100));
+        assert!(!schedule.should_prune(110));
+        assert!(schedule.should_prune(125));
+        assert!(!schedule.should_prune(225));
+        assert!(PruningSchedule::new(1.0, 0, 10).is_err());
+        assert!(PruningSchedule::new(0.5, 10, 0).is_err());
+    }
+
+    #[test]
+    fn mask_smallest_weights() {
+        let weight = Tensor::new(&[2, 2])
+            .with_values(&[0.5f32, -0.1, -2.0, 0.3])
+            .unwrap();
+        let mask = magnitude_mask(&weight, 0.5).unwrap();
+        assert_eq!(mask.dims(), &[2, 2]);
+        assert_eq!(&mask[..], &[1.0, 0.0, 1.0, 0.0]);
+        assert_eq!(&magnitude_mask(&weight, 0.0).unwrap()[..], &[1.0; 4]);
+    }
+
+    #[test]
+    fn prune_weights() {
+        let mut scope = Scope::new_root_scope();
+        let w = Variable::builder()
+            .const_initial_value(
+                Tensor::new(&[4])
+                    .with_values(&[4.0f32, -1.0, 3.0, 2.0])
+                    .unwrap(),
+            )
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        let unknown = Variable::builder()
+            .initial_value(ops::constant(&mut scope, 1.0f32).unwrap())
+            .data_type(DataType::Float)
+            .build(&mut scope.with_op_name("unknown"))
+            .unwrap();
+        let mut pruning = Pruning::new(PruningSchedule::new(0.5, 0, 10).unwrap());
+        let masked = pruning.prune(&mut scope, &w).unwrap();
+        assert!(pruning.prune(&mut scope, &unknown).is_err());
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        for var in pruning.masks().iter().chain(&[w.clone()]) {
+            init.add_target(var.initializer());
+        }
+        session.run(&mut init).unwrap();
+        assert_eq!(pruning.sparsity(&session).unwrap(), 0.0);
+
+        assert!(!pruning.update(&session, 5).unwrap());
+        assert!(pruning.update(&session, 10).unwrap());
+        assert_eq!(pruning.sparsity(&session).unwrap(), 0.5);
+        let mut args = SessionRunArgs::new();
+        let masked_token = args.request_fetch(&masked.operation, masked.index);
+        let w_token = args.request_fetch(&w.output().operation, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(
+            &args.fetch::<f32>(masked_token).unwrap()[..],
+            &[4.0, 0.0, 3.0, 0.0]
+        );
+        assert_eq!(
+            &args.fetch::<f32>(w_token).unwrap()[..],
+            &[4.0, 0.0, 3.0, 0.0]
+        );
+    }
+}
@@ -0,0 +1,521 @@
+use super::Saver;
+use crate::protos::ProtoWriter;
+use crate::DataType;
+use crate::Graph;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Session;
+use crate::SessionOptions;
+use crate::Variable;
+use std::fs;
+use std::path::Path;
+
+/// The method name of a signature which maps arbitrary inputs to outputs.
+pub const PREDICT_METHOD_NAME: &str = "tensorflow/serving/predict";
+
+/// The key of the signature servers use when none is requested.
+pub const DEFAULT_SERVING_SIGNATURE_DEF_KEY: &str = "serving_default";
+
+/// The tag of the meta graph servers load.
+pub const SERVE_TAG: &str = "serve";
+
+/// The inputs and outputs of a function exported in a SavedModel, i.e. a
+/// `SignatureDef`.
+#[derive(Debug, Clone)]
+pub struct Signature {
+    method_name: String,
+    inputs: Vec<(String, Output)>,
+    outputs: Vec<(String, Output)>,
+}
+
+impl Signature {
+    /// Creates a signature with no inputs or outputs and the given method
+    /// name, e.g. `PREDICT_METHOD_NAME`.
+    pub fn new(method_name: &str) -> Self {
+        Self {
+            method_name: method_name.to_string(),
+            inputs: Vec::new(),
+            outputs: Vec::new(),
+        }
+    }
+
+    /// Creates a prediction signature with no inputs or outputs.
+    pub fn predict() -> Self {
+        Self::new(PREDICT_METHOD_NAME)
+    }
+
+    /// Creates a prediction signature from the inputs and outputs registered
+    /// with `Scope::register_input` and `Scope::register_output`.
+    pub fn from_registrations(scope: &Scope) -> Self {
+        Self {
+            method_name: PREDICT_METHOD_NAME.to_string(),
+            inputs: scope.registered_inputs(),
+            outputs: scope.registered_outputs(),
+        }
+    }
+
+    /// Adds an input under `key`.
+    pub fn with_input(mut self, key: &str, input: Output) -> Self {
+        self.inputs.push((key.to_string(), input));
+        self
+    }
+
+    /// Adds an output under `key`.
+    pub fn with_output(mut self, key: &str, output: Output) -> Self {
+        self.outputs.push((key.to_string(), output));
+        self
+    }
+
+    /// Returns the method name.
+    pub fn method_name(&self) -> &str {
+        &self.method_name
+    }
+
+    /// Returns the inputs with their keys.
+    pub fn inputs(&self) -> &[(String, Output)] {
+        &self.inputs
+    }
+
+    /// Returns the outputs with their keys.
+    pub fn outputs(&self) -> &[(String, Output)] {
+        &self.outputs
+    }
+
+    /// Returns the serialized `SignatureDef` proto.
+    fn to_proto(&self, graph: &Graph) -> Result<Vec<u8>> {
+        let mut signature = ProtoWriter::new();
+        for (field, tensors) in &[(1, &self.inputs), (2, &self.outputs)] {
+            for (key, output) in tensors.iter() {
+                let mut entry = ProtoWriter::new();
+                entry
+                    .string_field(1, key)
+                    .bytes_field(2, &tensor_info(graph, output)?);
+                signature.message_field(*field, &entry);
+            }
+        }
+        signature.string_field(3, &self.method_name);
+        Ok(signature.into_bytes())
+    }
+}
+
+/// Returns the serialized `TensorInfo` proto describing `output`.
+fn tensor_info(graph: &Graph, output: &Output) -> Result<Vec<u8>> {
+    let name = format!("{}:{}", output.operation.name()?, output.index);
+    let dtype = output.operation.output_type(output.index as usize);
+    let mut shape = ProtoWriter::new();
+    match graph.tensor_shape(output.clone())?.0 {
+        Some(dims) => {
+            for dim in dims {
+                let mut d = ProtoWriter::new();
+                d.int_field(1, dim.unwrap_or(-1));
+                shape.message_field(2, &d);
+            }
+        }
+        None => {
+            shape.bool_field(3, true);
+        }
+    }
+    let mut info = ProtoWriter::new();
+    info.string_field(1, &name)
+        .int_field(2, i64::from(dtype.to_int()))
+        .message_field(3, &shape);
+    Ok(info.into_bytes())
+}
+
+/// Fails if `export_dir` already contains a SavedModel.
+fn check_export_dir(export_dir: &Path) -> Result<()> {
+    if export_dir.join("saved_model.pb").exists() {
+        return Err(invalid_arg!(
+            "{} already contains a SavedModel",
+            export_dir.display()
+        ));
+    }
+    Ok(())
+}
+
+/// Writes SavedModels, which TensorFlow Serving and `SavedModelBundle` can
+/// load, from a graph and its variables.
+///
+/// ```ignore
+/// let signature = Signature::predict()
+///     .with_input("x", x.into())
+///     .with_output("y", y.into());
+/// SavedModelBuilder::new()
+///     .with_signature(DEFAULT_SERVING_SIGNATURE_DEF_KEY, signature)
+///     .save_from_checkpoint(&mut scope, &[], "/tmp/train/model.ckpt-1000", "/tmp/export/1")?;
+/// ```
+///
+/// If no signature is added under `DEFAULT_SERVING_SIGNATURE_DEF_KEY` and
+/// inputs or outputs have been registered with `Scope::register_input` or
+/// `Scope::register_output`, they are exported under that key, so a model
+/// which registers its tensors while it is built needs no explicit signature:
+///
+/// ```ignore
+/// scope.register_input("x", x.clone())?;
+/// scope.register_output("y", y.clone())?;
+/// SavedModelBuilder::new().save(&mut scope, &session, &[], "/tmp/export/1")?;
+/// ```
+///
+/// The variables are saved with a `Saver`, so they are stored under their op
+/// names, like the checkpoints written by `Saver`.  Python can load the
+/// result with `tf.saved_model.load(export_dir, tags)`, which exposes each
+/// signature as a function in `signatures`.
+#[derive(Debug, Clone)]
+pub struct SavedModelBuilder {
+    tags: Vec<String>,
+    signatures: Vec<(String, Signature)>,
+}
+
+impl Default for SavedModelBuilder {
+    fn default() -> Self {
+        Self {
+            tags: vec![SERVE_TAG.to_string()],
+            signatures: Vec::new(),
+        }
+    }
+}
+
+impl SavedModelBuilder {
+    /// Creates a builder for a model tagged with `SERVE_TAG` and no
+    /// signatures.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the tags of the meta graph, which are used to select it when
+    /// loading.
+    pub fn with_tags(self, tags: &[&str]) -> Self {
+        Self {
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            ..self
+        }
+    }
+
+    /// Adds a signature under `key`, e.g.
+    /// `DEFAULT_SERVING_SIGNATURE_DEF_KEY`, replacing any signature already
+    /// added under the same key.
+    pub fn with_signature(mut self, key: &str, signature: Signature) -> Self {
+        self.signatures.retain(|(k, _)| k != key);
+        self.signatures.push((key.to_string(), signature));
+        self
+    }
+
+    /// Saves the graph of `scope` with the current values of `variables` in
+    /// `session` to `export_dir`, which must not already contain a model.
+    /// If `variables` is empty, all variables in the graph are saved.
+    ///
+    /// This adds ops to save and restore the variables to the graph.
+    pub fn save<P: AsRef<Path>>(
+        &self,
+        scope: &mut Scope,
+        session: &Session,
+        variables: &[Variable],
+        export_dir: P,
+    ) -> Result<()> {
+        check_export_dir(export_dir.as_ref())?;
+        let saver = self.saver(scope, variables)?;
+        self.write(scope, session, saver.as_ref(), export_dir.as_ref())
+    }
+
+    /// Restores `variables` from the checkpoint with path prefix `checkpoint`
+    /// and saves them with the graph of `scope` to `export_dir`, converting
+    /// the output of a training job which only wrote checkpoints to a
+    /// servable model.  If `variables` is empty, all variables in the graph
+    /// are restored and saved.
+    pub fn save_from_checkpoint<P: AsRef<Path>>(
+        &self,
+        scope: &mut Scope,
+        variables: &[Variable],
+        checkpoint: &str,
+        export_dir: P,
+    ) -> Result<()> {
+        check_export_dir(export_dir.as_ref())?;
+        let saver = self
+            .saver(scope, variables)?
+            .ok_or_else(|| invalid_arg!("The graph has no variables to restore"))?;
+        let session = Session::new(&SessionOptions::new(), &scope.graph())?;
+        saver.restore(&session, checkpoint)?;
+        self.write(scope, &session, Some(&saver), export_dir.as_ref())
+    }
+
+    /// Writes the variables with `saver` and the graph to `export_dir`.
+    fn write(
+        &self,
+        scope: &Scope,
+        session: &Session,
+        saver: Option<&Saver>,
+        export_dir: &Path,
+    ) -> Result<()> {
+        if let Some(saver) = saver {
+            let variables_dir = export_dir.join("variables");
+            fs::create_dir_all(&variables_dir)
+                .map_err(|e| invalid_arg!("Unable to create {}: {}", variables_dir.display(), e))?;
+            let prefix = variables_dir.join("variables");
+            let prefix = prefix
+                .to_str()
+                .ok_or_else(|| invalid_arg!("Invalid export directory path"))?;
+            saver.save(session, prefix)?;
+        } else {
+            fs::create_dir_all(export_dir)
+                .map_err(|e| invalid_arg!("Unable to create {}: {}", export_dir.display(), e))?;
+        }
+        let saved_model = self.saved_model(scope, saver)?;
+        let path = export_dir.join("saved_model.pb");
+        fs::write(&path, saved_model)
+            .map_err(|e| invalid_arg!("Unable to write {}: {}", path.display(), e))
+    }
+
+    /// Adds a saver for `variables`, or all variables in the graph if it's
+    /// empty, or returns `None` if there are none.
+    fn saver(&self, scope: &mut Scope, variables: &[Variable]) -> Result<Option<Saver>> {
+        let variables: Vec<(String, Output, DataType)> = if variables.is_empty() {
+            let graph = scope.graph();
+            let mut found = Vec::new();
+            for operation in graph.operation_iter() {
+                if operation.op_type()? == "VariableV2" {
+                    let dtype = operation.get_attr_type("dtype")?;
+                    found.push((operation.name()?, operation.into(), dtype));
+                }
+            }
+            found
+        } else {
+            variables
+                .iter()
+                .map(|v| (v.name.clone(), v.output.clone(), v.dtype))
+                .collect()
+        };
+        if variables.is_empty() {
+            return Ok(None);
+        }
+        Ok(Some(Saver::for_outputs(scope, &variables)?))
+    }
+
+    /// Returns the serialized `SavedModel` proto.
+    fn saved_model(&self, scope: &Scope, saver: Option<&Saver>) -> Result<Vec<u8>> {
+        let graph = scope.graph();
+        let mut meta_info = ProtoWriter::new();
+        for tag in &self.tags {
+            meta_info.string_field(4, tag);
+        }
+        if let Ok(version) = crate::version() {
+            meta_info.string_field(5, &version);
+        }
+        let mut meta_graph = ProtoWriter::new();
+        meta_graph
+            .message_field(1, &meta_info)
+            .bytes_field(2, &graph.graph_def()?);
+        if let Some(saver) = saver {
+            meta_graph.bytes_field(3, &saver.saver_def()?);
+        }
+        let mut signatures: Vec<(&str, Signature)> = self
+            .signatures
+            .iter()
+            .map(|(key, signature)| (key.as_str(), signature.clone()))
+            .collect();
+        let registered = Signature::from_registrations(scope);
+        let has_default = signatures
+            .iter()
+            .any(|(key, _)| *key == DEFAULT_SERVING_SIGNATURE_DEF_KEY);
+        if !has_default && (!registered.inputs.is_empty() || !registered.outputs.is_empty()) {
+            signatures.push((DEFAULT_SERVING_SIGNATURE_DEF_KEY, registered));
+        }
+        for (key, signature) in &signatures {
+            let mut entry = ProtoWriter::new();
+            entry
+                .string_field(1, key)
+                .bytes_field(2, &signature.to_proto(&graph)?);
+            meta_graph.message_field(5, &entry);
+        }
+        let mut saved_model = ProtoWriter::new();
+        saved_model.int_field(1, 1).message_field(2, &meta_graph);
+        Ok(saved_model.into_bytes())
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::protos::ProtoReader;
+    use crate::SavedModelBundle;
+    use crate::SessionRunArgs;
+    use crate::Shape;
+    use crate::Tensor;
+    use std::env;
+
+    /// Builds y = w * x with w initialized to 3.
+    fn build(scope: &mut Scope) -> (Output, Output, Variable) {
+        let x = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape(Some(vec![None])))
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let w = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        let y = ops::multiply(&mut scope.with_op_name("y"), x.clone(), w.output().clone()).unwrap();
+        (x.into(), y.into(), w)
+    }
+
+    fn export_dir(name: &str) -> std::path::PathBuf {
+        let dir = env::temp_dir().join(name);
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn check_export(export_dir: &Path) {
+        let mut graph = Graph::new();
+        let bundle =
+            SavedModelBundle::load(&SessionOptions::new(), &[SERVE_TAG], &mut graph, export_dir)
+                .unwrap();
+        let x = graph.operation_by_name_required("x").unwrap();
+        let y = graph.operation_by_name_required("y").unwrap();
+        let input = Tensor::new(&[2]).with_values(&[1.0f32, 2.0]).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&x, 0, &input);
+        let token = args.request_fetch(&y, 0);
+        bundle.session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<f32>(token).unwrap()[..], &[3.0, 6.0]);
+    }
+
+    /// Returns the keys of the signatures in a serialized `SavedModel`, each
+    /// with the keys of its outputs.
+    fn signature_outputs(saved_model: &[u8]) -> Vec<(String, Vec<String>)> {
+        let mut signatures = Vec::new();
+        for field in ProtoReader::new(saved_model) {
+            let meta_graph = match field.unwrap() {
+                (2, meta_graph) => meta_graph.as_bytes().unwrap(),
+                _ => continue,
+            };
+            for field in ProtoReader::new(meta_graph) {
+                let entry = match field.unwrap() {
+                    (5, entry) => entry.as_bytes().unwrap(),
+                    _ => continue,
+                };
+                let mut key = String::new();
+                let mut outputs = Vec::new();
+                for field in ProtoReader::new(entry) {
+                    match field.unwrap() {
+                        (1, value) => key = value.as_str().unwrap().to_string(),
+                        (2, def) => {
+                            for field in ProtoReader::new(def.as_bytes().unwrap()) {
+                                if let (2, output) = field.unwrap() {
+                                    let output = output.as_bytes().unwrap();
+                                    let (_, key) =
+                                        ProtoReader::new(output).next().unwrap().unwrap();
+                                    outputs.push(key.as_str().unwrap().to_string());
+                                }
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                signatures.push((key, outputs));
+            }
+        }
+        signatures
+    }
+
+    #[test]
+    fn replace_signature() {
+        let mut scope = Scope::new_root_scope();
+        let (x, y, _) = build(&mut scope);
+        let builder = SavedModelBuilder::new()
+            .with_signature(
+                DEFAULT_SERVING_SIGNATURE_DEF_KEY,
+                Signature::predict()
+                    .with_input("x", x.clone())
+                    .with_output("first", y.clone()),
+            )
+            .with_signature(
+                DEFAULT_SERVING_SIGNATURE_DEF_KEY,
+                Signature::predict()
+                    .with_input("x", x)
+                    .with_output("second", y),
+            );
+        let saved_model = builder.saved_model(&scope, None).unwrap();
+        assert_eq!(
+            signature_outputs(&saved_model),
+            vec![(
+                DEFAULT_SERVING_SIGNATURE_DEF_KEY.to_string(),
+                vec!["second".to_string()]
+            )]
+        );
+    }
+
+    #[test]
+    fn save_from_session() {
+        let mut scope = Scope::new_root_scope();
+        let (x, y, w) = build(&mut scope);
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(w.initializer());
+        session.run(&mut args).unwrap();
+
+        let dir = export_dir("tensorflow_saved_model_builder_session");
+        let builder = SavedModelBuilder::new().with_signature(
+            DEFAULT_SERVING_SIGNATURE_DEF_KEY,
+            Signature::predict().with_input("x", x).with_output("y", y),
+        );
+        builder.save(&mut scope, &session, &[w], &dir).unwrap();
+        check_export(&dir);
+        assert!(builder.save(&mut scope, &session, &[], &dir).is_err());
+    }
+
+    #[test]
+    fn save_from_checkpoint() {
+        let mut scope = Scope::new_root_scope();
+        let (_, _, w) = build(&mut scope);
+        let saver = Saver::new(&mut scope, &[w.clone()]).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(w.initializer());
+        session.run(&mut args).unwrap();
+        let checkpoint = env::temp_dir().join("tensorflow_saved_model_builder.ckpt");
+        let checkpoint = checkpoint.to_str().unwrap();
+        saver.save(&session, checkpoint).unwrap();
+
+        // A fresh graph, as in a separate export job.
+        let mut scope = Scope::new_root_scope();
+        build(&mut scope);
+        let dir = export_dir("tensorflow_saved_model_builder_checkpoint");
+        SavedModelBuilder::new()
+            .save_from_checkpoint(&mut scope, &[], checkpoint, &dir)
+            .unwrap();
+        check_export(&dir);
+    }
+
+    #[test]
+    fn save_registered_signature() {
+        let mut scope = Scope::new_root_scope();
+        let (x, y, w) = build(&mut scope);
+        scope.register_input("x", x).unwrap();
+        scope.register_output("y", y).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(w.initializer());
+        session.run(&mut args).unwrap();
+
+        let dir = export_dir("tensorflow_saved_model_builder_registered");
+        SavedModelBuilder::new()
+            .save(&mut scope, &session, &[], &dir)
+            .unwrap();
+        check_export(&dir);
+        let mut graph = Graph::new();
+        let bundle =
+            SavedModelBundle::load(&SessionOptions::new(), &[SERVE_TAG], &mut graph, &dir).unwrap();
+        let contains = |needle: &str| {
+            bundle
+                .meta_graph_def
+                .windows(needle.len())
+                .any(|w| w == needle.as_bytes())
+        };
+        assert!(contains(DEFAULT_SERVING_SIGNATURE_DEF_KEY));
+        assert!(contains(PREDICT_METHOD_NAME));
+        assert!(contains("y:0"));
+    }
+}
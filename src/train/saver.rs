@@ -0,0 +1,187 @@
+use crate::ops;
+use crate::protos::ProtoWriter;
+use crate::DataType;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Shape;
+use crate::Tensor;
+use crate::Variable;
+
+/// Saves variables to and restores them from checkpoint files.
+///
+/// Checkpoints use the same V2 format as TensorFlow's Python `Saver`, with
+/// each variable stored under its op name, so they can be shared with Python
+/// code which names its variables the same way.
+///
+/// ```ignore
+/// let saver = Saver::new(&mut scope, &variables)?;
+/// // ... create a session and train ...
+/// saver.save(&session, "/tmp/model.ckpt")?;
+/// // Later, instead of running the variables' initializers:
+/// saver.restore(&session, "/tmp/model.ckpt")?;
+/// ```
+///
+/// A checkpoint is stored as several files starting with the path prefix,
+/// e.g. `model.ckpt.index` and `model.ckpt.data-00000-of-00001`.
+#[derive(Debug, Clone)]
+pub struct Saver {
+    filename: Operation,
+    save_op: Operation,
+    restore_op: Operation,
+}
+
+impl Saver {
+    /// Adds ops to save and restore `variables` to the graph, under a "save"
+    /// sub-scope.
+    pub fn new(scope: &mut Scope, variables: &[Variable]) -> Result<Self> {
+        let variables: Vec<(String, Output, DataType)> = variables
+            .iter()
+            .map(|v| (v.name.clone(), v.output.clone(), v.dtype))
+            .collect();
+        Self::for_outputs(scope, &variables)
+    }
+
+    /// Like `new`, but for variables given as their names, outputs and data
+    /// types, e.g. those of a graph imported from a `GraphDef`.
+    pub(crate) fn for_outputs(
+        scope: &mut Scope,
+        variables: &[(String, Output, DataType)],
+    ) -> Result<Self> {
+        if variables.is_empty() {
+            return Err(invalid_arg!("A saver requires at least one variable"));
+        }
+        let mut scope = scope.new_sub_scope("save");
+        let filename = ops::Placeholder::new()
+            .data_type(DataType::String)
+            .shape(Shape::from(Some(vec![])))
+            .build(&mut scope.with_op_name("filename"))?;
+        let names: Vec<String> = variables.iter().map(|(name, _, _)| name.clone()).collect();
+        let tensor_names = ops::constant(
+            &mut scope.with_op_name("tensor_names"),
+            Tensor::new(&[names.len() as u64]).with_values(&names)?,
+        )?;
+        // Empty strings, meaning each variable is saved whole.
+        let shape_and_slices = ops::constant(
+            &mut scope.with_op_name("shape_and_slices"),
+            Tensor::<String>::new(&[names.len() as u64]),
+        )?;
+        let dtypes: Vec<DataType> = variables.iter().map(|(_, _, dtype)| *dtype).collect();
+        let values: Vec<Output> = variables.iter().map(|(_, v, _)| v.clone()).collect();
+        let save_op = scope.new_operation("SaveV2", |nd| {
+            nd.add_input(filename.clone());
+            nd.add_input(tensor_names.clone());
+            nd.add_input(shape_and_slices.clone());
+            nd.add_input_list(&values);
+            nd.set_attr_type_list("dtypes", &dtypes)?;
+            Ok(())
+        })?;
+        let restored = scope.new_operation("RestoreV2", |nd| {
+            nd.add_input(filename.clone());
+            nd.add_input(tensor_names.clone());
+            nd.add_input(shape_and_slices.clone());
+            nd.set_attr_type_list("dtypes", &dtypes)?;
+            Ok(())
+        })?;
+        let mut assigns = Vec::with_capacity(variables.len());
+        for (i, (_, variable, _)) in variables.iter().enumerate() {
+            let value = Output {
+                operation: restored.clone(),
+                index: i as i32,
+            };
+            assigns.push(ops::assign(&mut scope, variable.clone(), value)?);
+        }
+        let restore_op = scope
+            .with_op_name("restore_all")
+            .new_operation("NoOp", |nd| {
+                for assign in &assigns {
+                    nd.add_control_input(assign);
+                }
+                Ok(())
+            })?;
+        Ok(Self {
+            filename,
+            save_op,
+            restore_op,
+        })
+    }
+
+    /// Saves the current values of the variables to the checkpoint with the
+    /// given path prefix, e.g. "/tmp/model.ckpt".
+    pub fn save(&self, session: &Session, path: &str) -> Result<()> {
+        self.run(session, &self.save_op, path)
+    }
+
+    /// Restores the variables from the checkpoint with the given path prefix.
+    pub fn restore(&self, session: &Session, path: &str) -> Result<()> {
+        self.run(session, &self.restore_op, path)
+    }
+
+    /// Returns the serialized `SaverDef` proto describing this saver, as
+    /// stored in a `MetaGraphDef`.
+    pub(crate) fn saver_def(&self) -> Result<Vec<u8>> {
+        let filename = format!("{}:0", self.filename.name()?);
+        let mut saver_def = ProtoWriter::new();
+        saver_def
+            .string_field(1, &filename)
+            .string_field(2, &filename)
+            .string_field(3, &self.restore_op.name()?)
+            // CheckpointFormatVersion V2
+            .int_field(7, 2);
+        Ok(saver_def.into_bytes())
+    }
+
+    fn run(&self, session: &Session, target: &Operation, path: &str) -> Result<()> {
+        let path = Tensor::from(path.to_string());
+        let mut args = SessionRunArgs::new();
+        args.add_feed(&self.filename, 0, &path);
+        args.add_target(target);
+        session.run(&mut args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::SessionOptions;
+    use std::env;
+
+    #[test]
+    fn save_and_restore() {
+        let mut scope = Scope::new_root_scope();
+        let a = Variable::builder()
+            .const_initial_value(1.0f32)
+            .build(&mut scope.with_op_name("a"))
+            .unwrap();
+        let b = Variable::builder()
+            .const_initial_value(&[1i32, 2][..])
+            .build(&mut scope.with_op_name("b"))
+            .unwrap();
+        let saver = Saver::new(&mut scope, &[a.clone(), b.clone()]).unwrap();
+        let new_a = ops::constant(&mut scope, 5.0f32).unwrap();
+        let set_a = ops::assign(&mut scope, a.output().clone(), new_a).unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut init = SessionRunArgs::new();
+        init.add_target(a.initializer());
+        init.add_target(b.initializer());
+        session.run(&mut init).unwrap();
+
+        let path = env::temp_dir().join("tensorflow_saver_test.ckpt");
+        let path = path.to_str().unwrap();
+        saver.save(&session, path).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(&set_a);
+        session.run(&mut args).unwrap();
+        saver.restore(&session, path).unwrap();
+
+        let mut args = SessionRunArgs::new();
+        let a_token = args.request_fetch(&a.output().operation, 0);
+        let b_token = args.request_fetch(&b.output().operation, 0);
+        session.run(&mut args).unwrap();
+        assert_eq!(&args.fetch::<f32>(a_token).unwrap()[..], &[1.0]);
+        assert_eq!(&args.fetch::<i32>(b_token).unwrap()[..], &[1, 2]);
+    }
+}
@@ -0,0 +1,737 @@
+use super::Callback;
+use super::CallbackContext;
+use crate::ops;
+use crate::DataType;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Variable;
+use std::f32::consts::PI;
+
+/// A learning rate which changes with the number of training steps taken.
+///
+/// A schedule can be evaluated on the host and fed to a placeholder with
+/// `LearningRateScheduler`, or computed in the graph from a global step:
+///
+/// ```ignore
+/// let global_step = create_global_step(&mut scope)?;
+/// let schedule = ExponentialDecay::new(0.1, 1000, 0.96)?;
+/// let learning_rate = schedule.learning_rate_op(&mut scope, global_step.output().clone())?;
+/// let optimizer = GradientDescentOptimizer::new(learning_rate);
+/// let (variables, train_op) = optimizer.minimize(
+///     &mut scope,
+///     loss,
+///     MinimizeOptions::default()
+///         .with_variables(&model_variables)
+///         .with_global_step(&global_step),
+/// )?;
+/// ```
+///
+/// The learning rate op can be passed to any optimizer in place of a
+/// constant learning rate.
+pub trait LearningRateSchedule {
+    /// Returns the learning rate for the step after `step` steps have been
+    /// taken.
+    fn learning_rate(&self, step: u64) -> f32;
+
+    /// Adds ops computing the float learning rate for the step after `step`
+    /// steps have been taken, where `step` is a numeric scalar such as the
+    /// output of a global step variable.
+    fn learning_rate_op(&self, scope: &mut Scope, step: Output) -> Result<Output>;
+}
+
+/// Creates an int64 scalar variable named "global_step", starting from zero,
+/// to count training steps.  `MinimizeOptions::with_global_step` increments
+/// it with each step.
+pub fn create_global_step(scope: &mut Scope) -> Result<Variable> {
+    Variable::builder()
+        .const_initial_value(0i64)
+        .build(&mut scope.with_op_name("global_step"))
+}
+
+/// Returns `step` as a float.
+fn float_step(scope: &mut Scope, step: Output) -> Result<Output> {
+    if step.operation.output_type(step.index as usize) == DataType::Float {
+        return Ok(step);
+    }
+    Ok(ops::Cast::new()
+        .dst_type(DataType::Float)
+        .build(scope, step)?
+        .into())
+}
+
+/// Decays the learning rate from its initial value to an end value over a
+/// number of steps, following a polynomial.
+///
+/// The learning rate at step `t` is
+/// `(initial - end) * (1 - min(t, decay_steps) / decay_steps)^power + end`,
+/// so a power of 1 decays linearly.  With cycling, the decay restarts every
+/// time it completes, from a learning rate which decays each cycle.
+#[derive(Debug, Clone, Copy)]
+pub struct PolynomialDecay {
+    initial_learning_rate: f32,
+    decay_steps: u64,
+    end_learning_rate: f32,
+    power: f32,
+    cycle: bool,
+}
+
+impl PolynomialDecay {
+    /// Creates a schedule decaying linearly from `initial_learning_rate` to
+    /// 0.0001 over `decay_steps` steps.
+    pub fn new(initial_learning_rate: f32, decay_steps: u64) -> Result<Self> {
+        if decay_steps == 0 {
+            return Err(invalid_arg!("The number of decay steps must be positive"));
+        }
+        Ok(Self {
+            initial_learning_rate,
+            decay_steps,
+            end_learning_rate: 0.0001,
+            power: 1.0,
+            cycle: false,
+        })
+    }
+
+    /// Sets the learning rate at the end of the decay.  Default is 0.0001.
+    pub fn with_end_learning_rate(self, end_learning_rate: f32) -> Self {
+        Self {
+            end_learning_rate,
+            ..self
+        }
+    }
+
+    /// Sets the power of the polynomial.  Default is 1.
+    pub fn with_power(self, power: f32) -> Self {
+        Self { power, ..self }
+    }
+
+    /// Restarts the decay after it completes, instead of keeping the end
+    /// learning rate.  Each cycle is as long as the number of steps taken so
+    /// far rounded up to a multiple of the decay steps.  Default is false.
+    pub fn with_cycle(self, cycle: bool) -> Self {
+        Self { cycle, ..self }
+    }
+}
+
+impl LearningRateSchedule for PolynomialDecay {
+    fn learning_rate(&self, step: u64) -> f32 {
+        let (step, decay_steps) = if self.cycle {
+            let cycles = step.div_ceil(self.decay_steps).max(1);
+            (step, self.decay_steps * cycles)
+        } else {
+            (step.min(self.decay_steps), self.decay_steps)
+        };
+        let remaining = 1.0 - step as f32 / decay_steps as f32;
+        (self.initial_learning_rate - self.end_learning_rate) * remaining.powf(self.power)
+            + self.end_learning_rate
+    }
+
+    fn learning_rate_op(&self, scope: &mut Scope, step: Output) -> Result<Output> {
+        let mut scope = scope.new_sub_scope("polynomial_decay");
+        let scope = &mut scope;
+        let step = float_step(scope, step)?;
+        let decay_steps = ops::constant(scope, self.decay_steps as f32)?;
+        let (step, decay_steps): (Output, Output) = if self.cycle {
+            let cycles = ops::divide(scope, step.clone(), decay_steps.clone())?;
+            let cycles = ops::ceil(scope, cycles)?;
+            let one = ops::constant(scope, 1.0f32)?;
+            let cycles = ops::maximum(scope, cycles, one)?;
+            (step, ops::multiply(scope, decay_steps, cycles)?.into())
+        } else {
+            (
+                ops::minimum(scope, step, decay_steps.clone())?.into(),
+                decay_steps.into(),
+            )
+        };
+        let one = ops::constant(scope, 1.0f32)?;
+        let progress = ops::divide(scope, step, decay_steps)?;
+        let remaining = ops::subtract(scope, one, progress)?;
+        let power = ops::constant(scope, self.power)?;
+        let decayed = ops::pow(scope, remaining, power)?;
+        let range = ops::constant(scope, self.initial_learning_rate - self.end_learning_rate)?;
+        let decayed = ops::multiply(scope, range, decayed)?;
+        let end = ops::constant(scope, self.end_learning_rate)?;
+        Ok(ops::add(scope, decayed, end)?.into())
+    }
+}
+
+/// Decays the learning rate in proportion to the inverse of the number of
+/// steps taken.
+///
+/// The learning rate at step `t` is
+/// `initial / (1 + decay_rate * t / decay_steps)`, with `t / decay_steps`
+/// rounded down in staircase mode, so the learning rate only changes every
+/// `decay_steps` steps.
+#[derive(Debug, Clone, Copy)]
+pub struct InverseTimeDecay {
+    initial_learning_rate: f32,
+    decay_steps: u64,
+    decay_rate: f32,
+    staircase: bool,
+}
+
+impl InverseTimeDecay {
+    /// Creates a schedule starting from `initial_learning_rate` which is
+    /// divided by `1 + decay_rate` after `decay_steps` steps.
+    pub fn new(initial_learning_rate: f32, decay_steps: u64, decay_rate: f32) -> Result<Self> {
+        if decay_steps == 0 {
+            return Err(invalid_arg!("The number of decay steps must be positive"));
+        }
+        if decay_rate < 0.0 {
+            return Err(invalid_arg!(
+                "The decay rate must not be negative, got {}",
+                decay_rate
+            ));
+        }
+        Ok(Self {
+            initial_learning_rate,
+            decay_steps,
+            decay_rate,
+            staircase: false,
+        })
+    }
+
+    /// Decays the learning rate in discrete intervals of `decay_steps` steps.
+    /// Default is false.
+    pub fn with_staircase(self, staircase: bool) -> Self {
+        Self { staircase, ..self }
+    }
+}
+
+impl LearningRateSchedule for InverseTimeDecay {
+    fn learning_rate(&self, step: u64) -> f32 {
+        let progress = if self.staircase {
+            (step / self.decay_steps) as f32
+        } else {
+            step as f32 / self.decay_steps as f32
+        };
+        self.initial_learning_rate / (1.0 + self.decay_rate * progress)
+    }
+
+    fn learning_rate_op(&self, scope: &mut Scope, step: Output) -> Result<Output> {
+        let mut scope = scope.new_sub_scope("inverse_time_decay");
+        let scope = &mut scope;
+        let progress = decay_progress(scope, step, self.decay_steps, self.staircase)?;
+        let decay_rate = ops::constant(scope, self.decay_rate)?;
+        let decay = ops::multiply(scope, decay_rate, progress)?;
+        let one = ops::constant(scope, 1.0f32)?;
+        let decay = ops::add(scope, one, decay)?;
+        let initial = ops::constant(scope, self.initial_learning_rate)?;
+        Ok(ops::divide(scope, initial, decay)?.into())
+    }
+}
+
+/// Returns `step / decay_steps` as a float, rounded down with `staircase`.
+fn decay_progress(
+    scope: &mut Scope,
+    step: Output,
+    decay_steps: u64,
+    staircase: bool,
+) -> Result<Output> {
+    let step = float_step(scope, step)?;
+    let decay_steps = ops::constant(scope, decay_steps as f32)?;
+    let progress = ops::divide(scope, step, decay_steps)?;
+    if staircase {
+        Ok(ops::floor(scope, progress)?.into())
+    } else {
+        Ok(progress.into())
+    }
+}
+
+/// Decays the learning rate exponentially.
+///
+/// The learning rate at step `t` is
+/// `initial * decay_rate^(t / decay_steps)`, with `t / decay_steps` rounded
+/// down in staircase mode.
+#[derive(Debug, Clone, Copy)]
+pub struct ExponentialDecay {
+    initial_learning_rate: f32,
+    decay_steps: u64,
+    decay_rate: f32,
+    staircase: bool,
+}
+
+impl ExponentialDecay {
+    /// Creates a schedule starting from `initial_learning_rate` which is
+    /// multiplied by `decay_rate` every `decay_steps` steps.
+    pub fn new(initial_learning_rate: f32, decay_steps: u64, decay_rate: f32) -> Result<Self> {
+        if decay_steps == 0 {
+            return Err(invalid_arg!("The number of decay steps must be positive"));
+        }
+        if decay_rate.is_nan() || decay_rate <= 0.0 {
+            return Err(invalid_arg!(
+                "The decay rate must be positive, got {}",
+                decay_rate
+            ));
+        }
+        Ok(Self {
+            initial_learning_rate,
+            decay_steps,
+            decay_rate,
+            staircase: false,
+        })
+    }
+
+    /// Decays the learning rate in discrete intervals of `decay_steps` steps.
+    /// Default is false.
+    pub fn with_staircase(self, staircase: bool) -> Self {
+        Self { staircase, ..self }
+    }
+}
+
+impl LearningRateSchedule for ExponentialDecay {
+    fn learning_rate(&self, step: u64) -> f32 {
+        let progress = if self.staircase {
+            (step / self.decay_steps) as f32
+        } else {
+            step as f32 / self.decay_steps as f32
+        };
+        self.initial_learning_rate * self.decay_rate.powf(progress)
+    }
+
+    fn learning_rate_op(&self, scope: &mut Scope, step: Output) -> Result<Output> {
+        let mut scope = scope.new_sub_scope("exponential_decay");
+        let scope = &mut scope;
+        let progress = decay_progress(scope, step, self.decay_steps, self.staircase)?;
+        let decay_rate = ops::constant(scope, self.decay_rate)?;
+        let decay = ops::pow(scope, decay_rate, progress)?;
+        let initial = ops::constant(scope, self.initial_learning_rate)?;
+        Ok(ops::multiply(scope, initial, decay)?.into())
+    }
+}
+
+/// A learning rate which is constant between step boundaries, e.g. 0.1 for
+/// the first 1000 steps, then 0.01 until step 5000, then 0.001.
+///
+/// The learning rate is `values[0]` for steps up to and including
+/// `boundaries[0]`, `values[1]` for the steps after that up to and including
+/// `boundaries[1]`, and so on.
+#[derive(Debug, Clone)]
+pub struct PiecewiseConstantDecay {
+    boundaries: Vec<u64>,
+    values: Vec<f32>,
+}
+
+impl PiecewiseConstantDecay {
+    /// Creates a schedule from increasing step `boundaries` and the learning
+    /// rates before, between and after them, so there must be one more value
+    /// than boundaries.
+    pub fn new(boundaries: &[u64], values: &[f32]) -> Result<Self> {
+        if values.len() != boundaries.len() + 1 {
+            return Err(invalid_arg!(
+                "Expected {} learning rates for {} boundaries, got {}",
+                boundaries.len() + 1,
+                boundaries.len(),
+                values.len()
+            ));
+        }
+        if boundaries.windows(2).any(|w| w[0] >= w[1]) {
+            return Err(invalid_arg!(
+                "The boundaries must be increasing, got {:?}",
+                boundaries
+            ));
+        }
+        Ok(Self {
+            boundaries: boundaries.to_vec(),
+            values: values.to_vec(),
+        })
+    }
+}
+
+impl LearningRateSchedule for PiecewiseConstantDecay {
+    fn learning_rate(&self, step: u64) -> f32 {
+        let passed = self.boundaries.iter().filter(|&&b| step > b).count();
+        self.values[passed]
+    }
+
+    fn learning_rate_op(&self, scope: &mut Scope, step: Output) -> Result<Output> {
+        let mut scope = scope.new_sub_scope("piecewise_constant_decay");
+        let scope = &mut scope;
+        // Counts the boundaries which have been passed.  Comparing as floats
+        // is exact for any realistic number of steps.
+        let step = float_step(scope, step)?;
+        let boundaries: Vec<f32> = self.boundaries.iter().map(|&b| b as f32).collect();
+        let boundaries = ops::constant(scope, &boundaries[..])?;
+        let passed = ops::greater(scope, step, boundaries)?;
+        let passed = ops::Cast::new()
+            .dst_type(DataType::Int32)
+            .build(scope, passed)?;
+        let axis = ops::constant(scope, 0i32)?;
+        let passed = ops::sum(scope, passed, axis.clone())?;
+        let values = ops::constant(scope, &self.values[..])?;
+        Ok(ops::gather(scope, values, passed, axis)?.into())
+    }
+}
+
+/// Decays the learning rate from its initial value following half a cosine
+/// wave over a number of steps, after which it stays at `alpha` times the
+/// initial value.
+///
+/// See [Loshchilov & Hutter](https://arxiv.org/abs/1608.03983).
+#[derive(Debug, Clone, Copy)]
+pub struct CosineDecay {
+    initial_learning_rate: f32,
+    decay_steps: u64,
+    alpha: f32,
+}
+
+impl CosineDecay {
+    /// Creates a schedule decaying from `initial_learning_rate` to zero over
+    /// `decay_steps` steps.
+    pub fn new(initial_learning_rate: f32, decay_steps: u64) -> Result<Self> {
+        if decay_steps == 0 {
+            return Err(invalid_arg!("The number of decay steps must be positive"));
+        }
+        Ok(Self {
+            initial_learning_rate,
+            decay_steps,
+            alpha: 0.0,
+        })
+    }
+
+    /// Sets the fraction of the initial learning rate to decay to.  Default
+    /// is 0.
+    pub fn with_alpha(self, alpha: f32) -> Self {
+        Self { alpha, ..self }
+    }
+}
+
+impl LearningRateSchedule for CosineDecay {
+    fn learning_rate(&self, step: u64) -> f32 {
+        let progress = step.min(self.decay_steps) as f32 / self.decay_steps as f32;
+        let cosine = 0.5 * (1.0 + (PI * progress).cos());
+        self.initial_learning_rate * ((1.0 - self.alpha) * cosine + self.alpha)
+    }
+
+    fn learning_rate_op(&self, scope: &mut Scope, step: Output) -> Result<Output> {
+        let mut scope = scope.new_sub_scope("cosine_decay");
+        let scope = &mut scope;
+        let step = float_step(scope, step)?;
+        let decay_steps = ops::constant(scope, self.decay_steps as f32)?;
+        let step = ops::minimum(scope, step, decay_steps.clone())?;
+        let progress = ops::divide(scope, step, decay_steps)?;
+        // cosine = 0.5 * (1 + cos(pi * progress))
+        let pi = ops::constant(scope, PI)?;
+        let angle = ops::multiply(scope, pi, progress)?;
+        let cosine = ops::cos(scope, angle)?;
+        let one = ops::constant(scope, 1.0f32)?;
+        let cosine = ops::add(scope, one, cosine)?;
+        let half = ops::constant(scope, 0.5f32)?;
+        let cosine = ops::multiply(scope, half, cosine)?;
+        let scale = ops::constant(scope, 1.0 - self.alpha)?;
+        let decayed = ops::multiply(scope, scale, cosine)?;
+        let alpha = ops::constant(scope, self.alpha)?;
+        let decayed = ops::add(scope, decayed, alpha)?;
+        let initial = ops::constant(scope, self.initial_learning_rate)?;
+        Ok(ops::multiply(scope, initial, decayed)?.into())
+    }
+}
+
+/// Ramps the learning rate up linearly for a number of steps before
+/// following another schedule, which avoids large, destabilizing updates
+/// early in training.
+///
+/// The learning rate rises from the initial warmup rate at step 0 to the
+/// schedule's rate at step 0, which it reaches after `warmup_steps` steps.
+/// From then on, the schedule is followed as if it had started then.
+#[derive(Debug, Clone)]
+pub struct LinearWarmup<S> {
+    schedule: S,
+    warmup_steps: u64,
+    initial_learning_rate: f32,
+}
+
+impl<S: LearningRateSchedule> LinearWarmup<S> {
+    /// Creates a schedule warming up from zero to `schedule` over
+    /// `warmup_steps` steps.
+    pub fn new(schedule: S, warmup_steps: u64) -> Self {
+        Self {
+            schedule,
+            warmup_steps,
+            initial_learning_rate: 0.0,
+        }
+    }
+
+    /// Sets the learning rate at the beginning of the warmup.  Default is 0.
+    pub fn with_initial_learning_rate(self, initial_learning_rate: f32) -> Self {
+        Self {
+            initial_learning_rate,
+            ..self
+        }
+    }
+}
+
+impl<S: LearningRateSchedule> LearningRateSchedule for LinearWarmup<S> {
+    fn learning_rate(&self, step: u64) -> f32 {
+        if step >= self.warmup_steps {
+            return self.schedule.learning_rate(step - self.warmup_steps);
+        }
+        let target = self.schedule.learning_rate(0);
+        let progress = step as f32 / self.warmup_steps as f32;
+        self.initial_learning_rate + (target - self.initial_learning_rate) * progress
+    }
+
+    fn learning_rate_op(&self, scope: &mut Scope, step: Output) -> Result<Output> {
+        let mut scope = scope.new_sub_scope("linear_warmup");
+        let scope = &mut scope;
+        let step = float_step(scope, step)?;
+        let warmup_steps = ops::constant(scope, self.warmup_steps as f32)?;
+        let zero = ops::constant(scope, 0.0f32)?;
+        let after_warmup = ops::subtract(scope, step.clone(), warmup_steps.clone())?;
+        let after_warmup = ops::maximum(scope, after_warmup, zero)?;
+        let scheduled = self.schedule.learning_rate_op(scope, after_warmup.into())?;
+        // The warmup rate, with one step's worth of warmup if there are no
+        // warmup steps, so that it never divides by zero.
+        let one = ops::constant(scope, 1.0f32)?;
+        let progress = ops::maximum(scope, warmup_steps.clone(), one)?;
+        let progress = ops::divide(scope, step.clone(), progress)?;
+        let initial = ops::constant(scope, self.initial_learning_rate)?;
+        let target = ops::constant(scope, self.schedule.learning_rate(0))?;
+        let range = ops::subtract(scope, target, initial.clone())?;
+        let warmup = ops::multiply(scope, range, progress)?;
+        let warmup = ops::add(scope, initial, warmup)?;
+        let warming_up = ops::less(scope, step, warmup_steps)?;
+        Ok(ops::select(scope, warming_up, warmup, scheduled)?.into())
+    }
+}
+
+/// Feeds the learning rate given by a schedule to a placeholder before every
+/// training step.
+///
+/// ```ignore
+/// let learning_rate = ops::Placeholder::new()
+///     .data_type(DataType::Float)
+///     .build(&mut scope.with_op_name("learning_rate"))?;
+/// let optimizer = GradientDescentOptimizer::new(learning_rate.clone().into());
+/// // ...
+/// let mut scheduler = LearningRateScheduler::new(
+///     learning_rate.into(),
+///     PolynomialDecay::new(0.1, 10_000)?.with_power(2.0),
+/// );
+/// trainer.fit_with_callbacks(&data, epochs, &mut [&mut scheduler])?;
+/// ```
+///
+/// Steps are counted from the first epoch the trainer has trained, so
+/// resuming training with a later epoch continues the schedule.
+#[derive(Debug, Clone)]
+pub struct LearningRateScheduler<S> {
+    learning_rate: Output,
+    schedule: S,
+}
+
+impl<S: LearningRateSchedule> LearningRateScheduler<S> {
+    /// Creates a callback feeding `schedule`'s learning rate to the scalar
+    /// float placeholder `learning_rate`.
+    pub fn new(learning_rate: Output, schedule: S) -> Self {
+        Self {
+            learning_rate,
+            schedule,
+        }
+    }
+
+    /// Returns the schedule.
+    pub fn schedule(&self) -> &S {
+        &self.schedule
+    }
+}
+
+impl<S: LearningRateSchedule> Callback for LearningRateScheduler<S> {
+    fn on_epoch_begin(&mut self, context: &mut CallbackContext<'_>) -> Result<()> {
+        let step = context.epoch() * context.batches_per_epoch();
+        let learning_rate = self.schedule.learning_rate(step as u64);
+        context.set_hyperparameter(&self.learning_rate, learning_rate);
+        Ok(())
+    }
+
+    fn on_batch_end(
+        &mut self,
+        context: &mut CallbackContext<'_>,
+        batch: usize,
+        _loss: f64,
+    ) -> Result<()> {
+        let step = context.epoch() * context.batches_per_epoch() + batch + 1;
+        let learning_rate = self.schedule.learning_rate(step as u64);
+        context.set_hyperparameter(&self.learning_rate, learning_rate);
+        Ok(())
+    }
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::super::GradientDescentOptimizer;
+    use super::super::MinimizeOptions;
+    use super::super::Optimizer;
+    use super::*;
+    use crate::Session;
+    use crate::SessionOptions;
+    use crate::SessionRunArgs;
+
+    fn assert_close(schedule: &dyn LearningRateSchedule, steps: &[u64], expected: &[f32]) {
+        for (&step, &e) in steps.iter().zip(expected) {
+            let actual = schedule.learning_rate(step);
+            assert!(
+                (actual - e).abs() < 1e-6,
+                "step {}: {} != {}",
+                step,
+                actual,
+                e
+            );
+        }
+    }
+
+    #[test]
+    fn polynomial_decay() {
+        let linear = PolynomialDecay::new(1.0, 10)
+            .unwrap()
+            .with_end_learning_rate(0.0);
+        assert_close(&linear, &[0, 5, 10, 20], &[1.0, 0.5, 0.0, 0.0]);
+        let squared = PolynomialDecay::new(1.1, 10)
+            .unwrap()
+            .with_end_learning_rate(0.1)
+            .with_power(2.0);
+        assert_close(&squared, &[0, 5, 10], &[1.1, 0.35, 0.1]);
+        // The second cycle decays over 20 steps.
+        let cyclic = linear.with_cycle(true);
+        assert_close(&cyclic, &[0, 5, 10, 15, 20], &[1.0, 0.5, 0.0, 0.25, 0.0]);
+        assert!(PolynomialDecay::new(1.0, 0).is_err());
+    }
+
+    #[test]
+    fn inverse_time_decay() {
+        let smooth = InverseTimeDecay::new(1.0, 10, 0.5).unwrap();
+        assert_close(&smooth, &[0, 5, 10, 20], &[1.0, 0.8, 1.0 / 1.5, 0.5]);
+        let staircase = smooth.with_staircase(true);
+        assert_close(
+            &staircase,
+            &[0, 9, 10, 19],
+            &[1.0, 1.0, 1.0 / 1.5, 1.0 / 1.5],
+        );
+        assert!(InverseTimeDecay::new(1.0, 10, -1.0).is_err());
+    }
+
+    #[test]
+    fn exponential_decay() {
+        let smooth = ExponentialDecay::new(1.0, 10, 0.5).unwrap();
+        assert_close(&smooth, &[0, 5, 10, 20], &[1.0, 0.5f32.sqrt(), 0.5, 0.25]);
+        let staircase = smooth.with_staircase(true);
+        assert_close(&staircase, &[9, 10, 19], &[1.0, 0.5, 0.5]);
+        assert!(ExponentialDecay::new(1.0, 10, 0.0).is_err());
+    }
+
+    #[test]
+    fn piecewise_constant_decay() {
+        let schedule = PiecewiseConstantDecay::new(&[10, 20], &[1.0, 0.5, 0.1]).unwrap();
+        assert_close(&schedule, &[0, 10, 11, 20, 21], &[1.0, 1.0, 0.5, 0.5, 0.1]);
+        assert!(PiecewiseConstantDecay::new(&[10], &[1.0]).is_err());
+        assert!(PiecewiseConstantDecay::new(&[10, 10], &[1.0, 0.5, 0.1]).is_err());
+    }
+
+    #[test]
+    fn cosine_decay() {
+        let schedule = CosineDecay::new(1.0, 10).unwrap().with_alpha(0.1);
+        assert_close(&schedule, &[0, 5, 10, 20], &[1.0, 0.55, 0.1, 0.1]);
+        assert!(CosineDecay::new(1.0, 0).is_err());
+    }
+
+    #[test]
+    fn linear_warmup() {
+        let decay = PolynomialDecay::new(1.0, 10)
+            .unwrap()
+            .with_end_learning_rate(0.0);
+        let schedule = LinearWarmup::new(decay, 4).with_initial_learning_rate(0.2);
+        assert_close(&schedule, &[0, 2, 4, 9, 14], &[0.2, 0.6, 1.0, 0.5, 0.0]);
+    }
+
+    #[test]
+    fn learning_rate_ops() {
+        let schedules: Vec<Box<dyn LearningRateSchedule>> = vec![
+            Box::new(
+                PolynomialDecay::new(1.0, 10)
+                    .unwrap()
+                    .with_power(2.0)
+                    .with_cycle(true),
+            ),
+            Box::new(InverseTimeDecay::new(1.0, 10, 0.5).unwrap()),
+            Box::new(
+                ExponentialDecay::new(1.0, 10, 0.5)
+                    .unwrap()
+                    .with_staircase(true),
+            ),
+            Box::new(PiecewiseConstantDecay::new(&[10, 20], &[1.0, 0.5, 0.1]).unwrap()),
+            Box::new(CosineDecay::new(1.0, 10).unwrap()),
+            Box::new(LinearWarmup::new(CosineDecay::new(1.0, 10).unwrap(), 4)),
+        ];
+        let steps = [0u64, 3, 5, 10, 11, 25];
+        let mut scope = Scope::new_root_scope();
+        let mut ops_and_expected = Vec::new();
+        for schedule in &schedules {
+            for &step in &steps {
+                let step_op = ops::constant(&mut scope, step as i64).unwrap();
+                let op = schedule
+                    .learning_rate_op(&mut scope, step_op.into())
+                    .unwrap();
+                ops_and_expected.push((op, schedule.learning_rate(step)));
+            }
+        }
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        let tokens: Vec<_> = ops_and_expected
+            .iter()
+            .map(|(op, _)| args.request_fetch(&op.operation, op.index))
+            .collect();
+        session.run(&mut args).unwrap();
+        for (token, (_, expected)) in tokens.into_iter().zip(&ops_and_expected) {
+            let actual = args.fetch::<f32>(token).unwrap()[0];
+            assert!(
+                (actual - expected).abs() < 1e-5,
+                "{} != {}",
+                actual,
+                expected
+            );
+        }
+    }
+
+    #[test]
+    fn global_step() {
+        let mut scope = Scope::new_root_scope();
+        let global_step = create_global_step(&mut scope).unwrap();
+        let x = Variable::builder()
+            .const_initial_value(3.0f32)
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let loss = ops::multiply(&mut scope, x.output().clone(), x.output().clone()).unwrap();
+        let schedule = PiecewiseConstantDecay::new(&[0], &[0.1, 0.0]).unwrap();
+        let learning_rate = schedule
+            .learning_rate_op(&mut scope, global_step.output().clone())
+            .unwrap();
+        let optimizer = GradientDescentOptimizer::new(learning_rate);
+        let (_, minimize) = optimizer
+            .minimize(
+                &mut scope,
+                loss.into(),
+                MinimizeOptions::default()
+                    .with_variables(&[x.clone()])
+                    .with_global_step(&global_step),
+            )
+            .unwrap();
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut args = SessionRunArgs::new();
+        args.add_target(x.initializer());
+        args.add_target(global_step.initializer());
+        session.run(&mut args).unwrap();
+        for _ in 0..3 {
+            let mut args = SessionRunArgs::new();
+            args.add_target(&minimize);
+            session.run(&mut args).unwrap();
+        }
+        let mut args = SessionRunArgs::new();
+        let x_token = args.request_fetch(&x.output().operation, 0);
+        let step_token = args.request_fetch(&global_step.output().operation, 0);
+        session.run(&mut args).unwrap();
+        // Only the first step has a nonzero learning rate.
+        assert!((args.fetch::<f32>(x_token).unwrap()[0] - 2.4).abs() < 1e-5);
+        assert_eq!(args.fetch::<i64>(step_token).unwrap()[0], 3);
+    }
+}
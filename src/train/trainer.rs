@@ -0,0 +1,876 @@
+use super::callbacks;
+use super::dataset;
+use super::losses;
+use super::Aggregation;
+use super::Callback;
+use super::CallbackContext;
+use super::MinimizeOptions;
+use super::Optimizer;
+use super::Rng;
+use super::StreamingMetric;
+use crate::AnyTensor;
+use crate::Operation;
+use crate::Output;
+use crate::Result;
+use crate::Scope;
+use crate::Session;
+use crate::SessionOptions;
+use crate::SessionRunArgs;
+use crate::Tensor;
+use crate::TensorType;
+use crate::Variable;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use std::mem;
+
+/// Options for `Trainer::new`.
+#[derive(Debug, Clone)]
+pub struct TrainerOptions<'a> {
+    variables: &'a [Variable],
+    metrics: &'a [(&'a str, Output)],
+    aggregations: &'a [(&'a str, Aggregation)],
+    training_flag: Option<Output>,
+    batch_size: usize,
+    shuffle_seed: Option<u64>,
+    session_options: Option<&'a SessionOptions>,
+}
+
+impl<'a> Default for TrainerOptions<'a> {
+    fn default() -> Self {
+        Self {
+            variables: &[],
+            metrics: &[],
+            aggregations: &[],
+            training_flag: None,
+            batch_size: 32,
+            shuffle_seed: None,
+            session_options: None,
+        }
+    }
+}
+
+impl<'a> TrainerOptions<'a> {
+    /// Sets the variables which will be trained and initialized.
+    pub fn with_variables(self, variables: &'a [Variable]) -> Self {
+        Self { variables, ..self }
+    }
+
+    /// Sets named metrics which are averaged over each epoch along with the
+    /// loss, e.g. `&[("accuracy", accuracy)]`.  Like the loss, each metric
+    /// must be a float tensor, and is averaged over its elements for each
+    /// batch.
+    pub fn with_metrics(self, metrics: &'a [(&'a str, Output)]) -> Self {
+        Self { metrics, ..self }
+    }
+
+    /// Sets how the named metrics are aggregated over each epoch, e.g.
+    /// `&[("correct", Aggregation::Sum)]`.  Metrics which aren't listed are
+    /// averaged.
+    pub fn with_metric_aggregations(self, aggregations: &'a [(&'a str, Aggregation)]) -> Self {
+        Self {
+            aggregations,
+            ..self
+        }
+    }
+
+    /// Sets a scalar bool placeholder which is fed true while training and
+    /// false while evaluating, for models whose ops (such as dropout or batch
+    /// normalization) behave differently during inference.
+    pub fn with_training_flag(self, training_flag: Output) -> Self {
+        Self {
+            training_flag: Some(training_flag),
+            ..self
+        }
+    }
+
+    /// Sets the number of examples in each training step.  The last batch of
+    /// an epoch may be smaller.  Default is 32.
+    pub fn with_batch_size(self, batch_size: usize) -> Self {
+        Self { batch_size, ..self }
+    }
+
+    /// Shuffles the examples before each epoch, using `seed` to make the
+    /// order reproducible.  By default, examples are used in order.
+    pub fn with_shuffle_seed(self, seed: u64) -> Self {
+        Self {
+            shuffle_seed: Some(seed),
+            ..self
+        }
+    }
+
+    /// Sets the options used to create the session.
+    pub fn with_session_options(self, session_options: &'a SessionOptions) -> Self {
+        Self {
+            session_options: Some(session_options),
+            ..self
+        }
+    }
+}
+
+/// In-memory examples to train or evaluate on.
+///
+/// Each input is a tensor whose first dimension indexes the examples, fed to
+/// a placeholder of the model in batches.  All inputs must have the same
+/// number of examples.
+#[derive(Debug, Default)]
+pub struct TrainingData {
+    inputs: Vec<(Output, Box<dyn Column>)>,
+}
+
+impl TrainingData {
+    /// Creates empty training data.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the values for the placeholder `input`.
+    pub fn with_input<T: TensorType>(mut self, input: Output, values: Tensor<T>) -> Self {
+        self.inputs.push((input, Box::new(values)));
+        self
+    }
+
+    /// Feeds the weight of each example's class, according to `labels` (one
+    /// class index per example) and `class_weights`, to the placeholder
+    /// `sample_weights`.  This weights examples for losses built with
+    /// `LossOptions::with_sample_weights(sample_weights)`.
+    pub fn with_class_weights<L: TensorType + Copy + Into<i64>>(
+        self,
+        sample_weights: Output,
+        labels: &Tensor<L>,
+        class_weights: &[f32],
+    ) -> Result<Self> {
+        if labels.dims().len() != 1 {
+            return Err(invalid_arg!(
+                "Labels must be a vector of class indices, but have shape {:?}",
+                labels.dims()
+            ));
+        }
+        let weights = losses::class_sample_weights(labels, class_weights)?;
+        Ok(self.with_input(sample_weights, Tensor::from(&weights[..])))
+    }
+
+    /// Returns the number of examples, or an error if there are no inputs or
+    /// the inputs don't agree.
+    pub fn num_examples(&self) -> Result<usize> {
+        let mut num_examples = None;
+        for (input, values) in &self.inputs {
+            let n = values.num_examples().ok_or_else(|| {
+                invalid_arg!(
+                    "Values for input {:?} must have at least one dimension",
+                    input
+                )
+            })?;
+            match num_examples {
+                Some(m) if m != n => {
+                    return Err(invalid_arg!(
+                        "Input {:?} has {} examples, but previous inputs have {}",
+                        input,
+                        n,
+                        m
+                    ))
+                }
+                _ => num_examples = Some(n),
+            }
+        }
+        num_examples.ok_or_else(|| invalid_arg!("Training data has no inputs"))
+    }
+
+    /// Returns the examples at `indices`, in that order.
+    pub fn select(&self, indices: &[usize]) -> Result<Self> {
+        let inputs = self
+            .inputs
+            .iter()
+            .map(|(input, values)| Ok((input.clone(), values.select(indices)?)))
+            .collect::<Result<_>>()?;
+        Ok(Self { inputs })
+    }
+
+    /// Randomly splits the examples into training and validation data, with
+    /// `validation_fraction` of them used for validation.  See
+    /// `split_indices`.
+    pub fn split(&self, validation_fraction: f64, seed: u64) -> Result<(Self, Self)> {
+        let (train, validation) = dataset::split_indices(
+            self.num_examples()?,
+            validation_fraction,
+            &mut Rng::new(seed),
+        )?;
+        Ok((self.select(&train)?, self.select(&validation)?))
+    }
+}
+
+/// A tensor of examples, indexed by the first dimension.
+trait Column: Debug {
+    /// Returns None if the tensor is a scalar.
+    fn num_examples(&self) -> Option<usize>;
+
+    /// Returns a tensor of the examples at `indices`.
+    fn batch(&self, indices: &[usize]) -> Result<Box<dyn AnyTensor>>;
+
+    /// Like `batch`, but returns the examples as a column.
+    fn select(&self, indices: &[usize]) -> Result<Box<dyn Column>>;
+}
+
+impl<T: TensorType> Column for Tensor<T> {
+    fn num_examples(&self) -> Option<usize> {
+        self.dims().first().map(|&n| n as usize)
+    }
+
+    fn batch(&self, indices: &[usize]) -> Result<Box<dyn AnyTensor>> {
+        Ok(Box::new(dataset::select_rows(self, indices)?))
+    }
+
+    fn select(&self, indices: &[usize]) -> Result<Box<dyn Column>> {
+        Ok(Box::new(dataset::select_rows(self, indices)?))
+    }
+}
+
+/// The average loss and aggregated metrics over one pass through the data.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EpochMetrics {
+    pub(crate) loss: f64,
+    pub(crate) metrics: Vec<(String, f64)>,
+}
+
+impl EpochMetrics {
+    /// Returns the loss, averaged over all examples.
+    pub fn loss(&self) -> f64 {
+        self.loss
+    }
+
+    /// Returns the named metrics, aggregated over all examples, in the order
+    /// they were given.
+    pub fn metrics(&self) -> &[(String, f64)] {
+        &self.metrics
+    }
+
+    /// Returns the metric with the given name.
+    pub fn metric(&self, name: &str) -> Option<f64> {
+        self.metrics
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|&(_, value)| value)
+    }
+
+    /// Returns the loss if `name` is "loss", and otherwise the metric with
+    /// the given name.
+    pub fn get(&self, name: &str) -> Option<f64> {
+        if name == "loss" {
+            Some(self.loss)
+        } else {
+            self.metric(name)
+        }
+    }
+
+    /// Returns the loss (under "loss") and the metrics by name.
+    pub fn to_map(&self) -> HashMap<String, f64> {
+        let mut map: HashMap<String, f64> = self.metrics.iter().cloned().collect();
+        map.insert("loss".to_string(), self.loss);
+        map
+    }
+}
+
+/// Trains a model on in-memory data, taking care of variable initialization,
+/// batching, stepping and averaging metrics.
+///
+/// The model's loss should be a float tensor computed from placeholders,
+/// which are fed from `TrainingData`.  The loss may be a scalar or one value
+/// per example; either way it is averaged to a single value per batch.
+#[derive(Debug)]
+pub struct Trainer {
+    session: Session,
+    train_op: Operation,
+    loss: Output,
+    metrics: Vec<(String, Output, Aggregation)>,
+    training_flag: Option<Output>,
+    batch_size: usize,
+    shuffle_seed: Option<u64>,
+    epochs: u64,
+    hyperparameters: Vec<(Output, f32)>,
+}
+
+/// Called with the index and loss of each batch and the hyperparameters to
+/// feed to the next step.
+type BatchHook<'a> = dyn FnMut(usize, f64, &mut Vec<(Output, f32)>) -> Result<()> + 'a;
+
+impl Trainer {
+    /// Adds the ops which minimize `loss` with `optimizer` to the graph, then
+    /// creates a session for the graph and initializes the variables.
+    ///
+    /// All ops must be added to the graph before the trainer is created.
+    pub fn new<O: Optimizer>(
+        scope: &mut Scope,
+        loss: Output,
+        optimizer: &O,
+        opts: TrainerOptions<'_>,
+    ) -> Result<Self> {
+        if opts.batch_size == 0 {
+            return Err(invalid_arg!("Batch size must be positive"));
+        }
+        let mut metrics = Vec::with_capacity(opts.metrics.len());
+        for (name, output) in opts.metrics {
+            let aggregation = opts
+                .aggregations
+                .iter()
+                .find(|(n, _)| n == name)
+                .map(|&(_, aggregation)| aggregation)
+                .unwrap_or_default();
+            metrics.push((name.to_string(), output.clone(), aggregation));
+        }
+        if let Some((name, _)) = opts
+            .aggregations
+            .iter()
+            .find(|(name, _)| !opts.metrics.iter().any(|(n, _)| n == name))
+        {
+            return Err(invalid_arg!(
+                "Aggregation given for unknown metric {}",
+                name
+            ));
+        }
+        let (optimizer_vars, train_op) = optimizer.minimize(
+            scope,
+            loss.clone(),
+            MinimizeOptions::default().with_variables(opts.variables),
+        )?;
+        let session = match opts.session_options {
+            Some(options) => Session::new(options, &scope.graph())?,
+            None => Session::new(&SessionOptions::new(), &scope.graph())?,
+        };
+        let mut init = SessionRunArgs::new();
+        for var in opts.variables.iter().chain(&optimizer_vars) {
+            init.add_target(var.initializer());
+        }
+        session.run(&mut init)?;
+        Ok(Self {
+            session,
+            train_op,
+            loss,
+            metrics,
+            training_flag: opts.training_flag,
+            batch_size: opts.batch_size,
+            shuffle_seed: opts.shuffle_seed,
+            epochs: 0,
+            hyperparameters: Vec::new(),
+        })
+    }
+
+    /// Feeds `value` to the scalar float placeholder `placeholder` in every
+    /// step, e.g. to control the learning rate.  Callbacks such as
+    /// `ReduceLROnPlateau` may change the value during training.
+    pub fn set_hyperparameter(&mut self, placeholder: Output, value: f32) {
+        callbacks::set_hyperparameter(&mut self.hyperparameters, &placeholder, value);
+    }
+
+    /// Returns the value fed to `placeholder`, if it was set with
+    /// `set_hyperparameter`.
+    pub fn hyperparameter(&self, placeholder: &Output) -> Option<f32> {
+        callbacks::find_hyperparameter(&self.hyperparameters, placeholder)
+            .map(|i| self.hyperparameters[i].1)
+    }
+
+    /// Trains for `epochs` passes through `data`, returning the metrics of
+    /// each epoch.  The metrics of an epoch are collected while training, so
+    /// they lag slightly behind the model.
+    pub fn fit(&mut self, data: &TrainingData, epochs: usize) -> Result<Vec<EpochMetrics>> {
+        self.fit_with_callbacks(data, epochs, &mut [])
+    }
+
+    /// Like `fit`, but calls `callbacks` at epoch and batch boundaries, in
+    /// order.  Training ends early if a callback stops it, in which case
+    /// fewer than `epochs` metrics are returned.
+    pub fn fit_with_callbacks(
+        &mut self,
+        data: &TrainingData,
+        epochs: usize,
+        callbacks: &mut [&mut dyn Callback],
+    ) -> Result<Vec<EpochMetrics>> {
+        // Callbacks may change the hyperparameters while the session is
+        // borrowed, so they are moved out for the duration of training.
+        let mut hyperparameters = mem::take(&mut self.hyperparameters);
+        let result = self.fit_epochs(data, epochs, callbacks, &mut hyperparameters);
+        self.hyperparameters = hyperparameters;
+        result
+    }
+
+    fn fit_epochs(
+        &mut self,
+        data: &TrainingData,
+        epochs: usize,
+        callbacks: &mut [&mut dyn Callback],
+        hyperparameters: &mut Vec<(Output, f32)>,
+    ) -> Result<Vec<EpochMetrics>> {
+        let mut history = Vec::with_capacity(epochs);
+        let mut stop_training = false;
+        let first_epoch = self.epochs as usize;
+        let num_examples = data.num_examples()?;
+        let batches_per_epoch =
+            num_examples / self.batch_size + usize::from(num_examples % self.batch_size > 0);
+        for _ in 0..epochs {
+            let mut order: Vec<usize> = (0..data.num_examples()?).collect();
+            if let Some(seed) = self.shuffle_seed {
+                Rng::new(seed.wrapping_add(self.epochs)).shuffle(&mut order);
+            }
+            let epoch = self.epochs as usize;
+            let session = &self.session;
+            for callback in callbacks.iter_mut() {
+                callback.on_epoch_begin(&mut CallbackContext {
+                    session,
+                    epoch,
+                    epochs: first_epoch..first_epoch + epochs,
+                    batches_per_epoch,
+                    hyperparameters,
+                    stop_training: &mut stop_training,
+                })?;
+            }
+            let metrics = self.run_epoch(
+                data,
+                &order,
+                true,
+                hyperparameters,
+                &mut |batch, loss, hyperparameters| {
+                    for callback in callbacks.iter_mut() {
+                        callback.on_batch_end(
+                            &mut CallbackContext {
+                                session,
+                                epoch,
+                                epochs: first_epoch..first_epoch + epochs,
+                                batches_per_epoch,
+                                hyperparameters,
+                                stop_training: &mut stop_training,
+                            },
+                            batch,
+                            loss,
+                        )?;
+                    }
+                    Ok(())
+                },
+            )?;
+            for callback in callbacks.iter_mut() {
+                callback.on_epoch_end(
+                    &mut CallbackContext {
+                        session,
+                        epoch,
+                        epochs: first_epoch..first_epoch + epochs,
+                        batches_per_epoch,
+                        hyperparameters,
+                        stop_training: &mut stop_training,
+                    },
+                    &metrics,
+                )?;
+            }
+            history.push(metrics);
+            self.epochs += 1;
+            if stop_training {
+                break;
+            }
+        }
+        Ok(history)
+    }
+
+    /// Computes the loss and metrics over `data` in inference mode, i.e.
+    /// without training and with the training flag (if any) set to false.
+    /// The metrics are aggregated batch by batch, so the data may be larger
+    /// than fits in a single step.
+    pub fn evaluate(&self, data: &TrainingData) -> Result<EpochMetrics> {
+        let order: Vec<usize> = (0..data.num_examples()?).collect();
+        let mut hyperparameters = self.hyperparameters.clone();
+        self.run_epoch(data, &order, false, &mut hyperparameters, &mut |_, _, _| {
+            Ok(())
+        })
+    }
+
+    /// Returns the session, e.g. for running predictions with the trained
+    /// model.
+    pub fn session(&self) -> &Session {
+        &self.session
+    }
+
+    fn run_epoch(
+        &self,
+        data: &TrainingData,
+        order: &[usize],
+        train: bool,
+        hyperparameters: &mut Vec<(Output, f32)>,
+        on_batch: &mut BatchHook<'_>,
+    ) -> Result<EpochMetrics> {
+        let mut loss = 0.0;
+        let mut metrics: Vec<_> = self
+            .metrics
+            .iter()
+            .map(|&(_, _, aggregation)| StreamingMetric::new(aggregation))
+            .collect();
+        let training_flag = Tensor::from(train);
+        for (batch_index, indices) in order.chunks(self.batch_size).enumerate() {
+            let batches = data
+                .inputs
+                .iter()
+                .map(|(_, values)| values.batch(indices))
+                .collect::<Result<Vec<_>>>()?;
+            let values: Vec<Tensor<f32>> = hyperparameters
+                .iter()
+                .map(|&(_, value)| Tensor::from(value))
+                .collect();
+            let batch_loss = {
+                let mut args = SessionRunArgs::new();
+                for ((input, _), batch) in data.inputs.iter().zip(&batches) {
+                    args.add_any_feed(&input.operation, input.index, batch.as_ref());
+                }
+                for ((placeholder, _), value) in hyperparameters.iter().zip(&values) {
+                    args.add_feed(&placeholder.operation, placeholder.index, value);
+                }
+                if let Some(flag) = &self.training_flag {
+                    args.add_feed(&flag.operation, flag.index, &training_flag);
+                }
+                if train {
+                    args.add_target(&self.train_op);
+                }
+                let loss_token = args.request_fetch(&self.loss.operation, self.loss.index);
+                let metric_tokens: Vec<_> = self
+                    .metrics
+                    .iter()
+                    .map(|(_, output, _)| args.request_fetch(&output.operation, output.index))
+                    .collect();
+                self.session.run(&mut args)?;
+                for (metric, token) in metrics.iter_mut().zip(metric_tokens) {
+                    metric.update(&args.fetch::<f32>(token)?, indices.len());
+                }
+                mean(&args.fetch::<f32>(loss_token)?)
+            };
+            loss += indices.len() as f64 * batch_loss;
+            on_batch(batch_index, batch_loss, hyperparameters)?;
+        }
+        Ok(EpochMetrics {
+            loss: loss / order.len().max(1) as f64,
+            metrics: self
+                .metrics
+                .iter()
+                .zip(metrics)
+                .map(|((name, _, _), metric)| (name.clone(), metric.result()))
+                .collect(),
+        })
+    }
+}
+
+fn mean(values: &[f32]) -> f64 {
+    if values.is_empty() {
+        return 0.0;
+    }
+    values.iter().map(|&v| f64::from(v)).sum::<f64>() / values.len() as f64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::train::sparse_softmax_cross_entropy;
+    use crate::train::EarlyStopping;
+    use crate::train::GradientDescentOptimizer;
+    use crate::train::LossOptions;
+    use crate::DataType;
+    use crate::Shape;
+
+    #[test]
+    fn fit_linear_regression() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None])))
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let y = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None])))
+            .build(&mut scope.with_op_name("y"))
+            .unwrap();
+        let w = Variable::builder()
+            .const_initial_value(0.0f32)
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        let w_x = ops::multiply(&mut scope, w.output().clone(), x.clone()).unwrap();
+        let error = ops::subtract(&mut scope, w_x, y.clone()).unwrap();
+        let loss = ops::multiply(&mut scope, error.clone(), error.clone()).unwrap();
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        let variables = [w.clone()];
+        let metrics = [("error", error.into())];
+        let mut trainer = Trainer::new(
+            &mut scope,
+            loss.into(),
+            &optimizer,
+            TrainerOptions::default()
+                .with_variables(&variables)
+                .with_metrics(&metrics)
+                .with_batch_size(3)
+                .with_shuffle_seed(7),
+        )
+        .unwrap();
+
+        let xs: Vec<f32> = (0..10).map(|i| i as f32 / 10.0).collect();
+        let ys: Vec<f32> = xs.iter().map(|x| 2.0 * x).collect();
+        let data = TrainingData::new()
+            .with_input(x.into(), Tensor::from(&xs[..]))
+            .with_input(y.into(), Tensor::from(&ys[..]));
+        assert_eq!(data.num_examples().unwrap(), 10);
+        let history = trainer.fit(&data, 50).unwrap();
+        assert_eq!(history.len(), 50);
+        assert!(history[49].loss() < history[0].loss());
+        let evaluation = trainer.evaluate(&data).unwrap();
+        assert!(evaluation.loss() < 1e-3, "loss = {}", evaluation.loss());
+        assert!(evaluation.metric("error").unwrap().abs() < 1e-1);
+        assert!(evaluation.metric("missing").is_none());
+
+        let mut args = SessionRunArgs::new();
+        let w_token = args.request_fetch(&w.output().operation, 0);
+        trainer.session().run(&mut args).unwrap();
+        let w_value = args.fetch::<f32>(w_token).unwrap()[0];
+        assert!((w_value - 2.0).abs() < 0.05, "w = {}", w_value);
+    }
+
+    #[derive(Default)]
+    struct CountBatches {
+        batches: usize,
+        learning_rates: Vec<f32>,
+    }
+
+    impl Callback for CountBatches {
+        fn on_batch_end(
+            &mut self,
+            _context: &mut CallbackContext<'_>,
+            _batch: usize,
+            loss: f64,
+        ) -> Result<()> {
+            assert!(loss.is_finite());
+            self.batches += 1;
+            Ok(())
+        }
+
+        fn on_epoch_begin(&mut self, context: &mut CallbackContext<'_>) -> Result<()> {
+            let learning_rate = context.hyperparameters[0].clone();
+            self.learning_rates.push(learning_rate.1);
+            context.set_hyperparameter(&learning_rate.0, learning_rate.1 / 2.0);
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn fit_with_callbacks() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None])))
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let learning_rate: Output = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![])))
+            .build(&mut scope.with_op_name("learning_rate"))
+            .unwrap()
+            .into();
+        let w = Variable::builder()
+            .const_initial_value(1.0f32)
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        // The loss is constant, so training stops early.
+        let zero = ops::multiply(&mut scope, w.output().clone(), x.clone()).unwrap();
+        let loss = ops::subtract(&mut scope, zero.clone(), zero).unwrap();
+        let optimizer = GradientDescentOptimizer::new(learning_rate.clone());
+        let variables = [w];
+        let mut trainer = Trainer::new(
+            &mut scope,
+            loss.into(),
+            &optimizer,
+            TrainerOptions::default()
+                .with_variables(&variables)
+                .with_batch_size(2),
+        )
+        .unwrap();
+        trainer.set_hyperparameter(learning_rate.clone(), 0.4);
+        assert_eq!(trainer.hyperparameter(&learning_rate), Some(0.4));
+
+        let data = TrainingData::new().with_input(x.into(), Tensor::from(&[1.0f32, 2.0, 3.0][..]));
+        let mut count = CountBatches::default();
+        let mut early_stopping = EarlyStopping::new("loss", 2);
+        let history = trainer
+            .fit_with_callbacks(&data, 10, &mut [&mut count, &mut early_stopping])
+            .unwrap();
+        assert_eq!(history.len(), 3);
+        assert_eq!(early_stopping.stopped_epoch(), Some(2));
+        assert_eq!(count.batches, 6);
+        assert_eq!(count.learning_rates, vec![0.4, 0.2, 0.1]);
+        assert_eq!(trainer.hyperparameter(&learning_rate), Some(0.05));
+    }
+
+    #[test]
+    fn evaluate() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None])))
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let training = ops::Placeholder::new()
+            .data_type(DataType::Bool)
+            .shape(Shape::from(Some(vec![])))
+            .build(&mut scope.with_op_name("training"))
+            .unwrap();
+        let w = Variable::builder()
+            .const_initial_value(1.0f32)
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        let loss = ops::multiply(&mut scope, w.output().clone(), x.clone()).unwrap();
+        let training_float = ops::Cast::new()
+            .dst_type(DataType::Float)
+            .build(&mut scope, training.clone())
+            .unwrap();
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        let variables = [w];
+        let metrics = [
+            ("sum", x.clone().into()),
+            ("max", x.clone().into()),
+            ("training", training_float.into()),
+        ];
+        let aggregations = [("sum", Aggregation::Sum), ("max", Aggregation::Max)];
+        let mut trainer = Trainer::new(
+            &mut scope,
+            loss.into(),
+            &optimizer,
+            TrainerOptions::default()
+                .with_variables(&variables)
+                .with_metrics(&metrics)
+                .with_metric_aggregations(&aggregations)
+                .with_training_flag(training.into())
+                .with_batch_size(2),
+        )
+        .unwrap();
+
+        let data = TrainingData::new().with_input(x.into(), Tensor::from(&[1.0f32, 2.0, 6.0][..]));
+        let evaluation = trainer.evaluate(&data).unwrap();
+        assert_eq!(evaluation.loss(), 3.0);
+        assert_eq!(evaluation.get("loss"), Some(3.0));
+        assert_eq!(evaluation.get("sum"), Some(9.0));
+        assert_eq!(evaluation.get("max"), Some(6.0));
+        assert_eq!(evaluation.get("training"), Some(0.0));
+        let map = evaluation.to_map();
+        assert_eq!(map.len(), 4);
+        assert_eq!(map["sum"], 9.0);
+        let history = trainer.fit(&data, 1).unwrap();
+        assert_eq!(history[0].metric("training"), Some(1.0));
+    }
+
+    #[test]
+    fn unknown_aggregation() {
+        let mut scope = Scope::new_root_scope();
+        let w = Variable::builder()
+            .const_initial_value(1.0f32)
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.1f32).unwrap().into());
+        let variables = [w.clone()];
+        let aggregations = [("missing", Aggregation::Sum)];
+        assert!(Trainer::new(
+            &mut scope,
+            w.output().clone(),
+            &optimizer,
+            TrainerOptions::default()
+                .with_variables(&variables)
+                .with_metric_aggregations(&aggregations),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn mismatched_data() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::constant(&mut scope, 1.0f32).unwrap();
+        let y = ops::constant(&mut scope, 2.0f32).unwrap();
+        assert!(TrainingData::new().num_examples().is_err());
+        let data = TrainingData::new()
+            .with_input(x.clone().into(), Tensor::<f32>::new(&[3]))
+            .with_input(y.into(), Tensor::<f32>::new(&[4]));
+        assert!(data.num_examples().is_err());
+        let data = TrainingData::new().with_input(x.into(), Tensor::<f32>::new(&[]));
+        assert!(data.num_examples().is_err());
+    }
+
+    #[test]
+    fn class_weights() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None, Some(1)])))
+            .build(&mut scope.with_op_name("x"))
+            .unwrap();
+        let labels = ops::Placeholder::new()
+            .data_type(DataType::Int32)
+            .shape(Shape::from(Some(vec![None])))
+            .build(&mut scope.with_op_name("labels"))
+            .unwrap();
+        let sample_weights: Output = ops::Placeholder::new()
+            .data_type(DataType::Float)
+            .shape(Shape::from(Some(vec![None])))
+            .build(&mut scope.with_op_name("sample_weights"))
+            .unwrap()
+            .into();
+        let w = Variable::builder()
+            .const_initial_value(Tensor::<f32>::new(&[1, 2]))
+            .build(&mut scope.with_op_name("w"))
+            .unwrap();
+        let logits = ops::mat_mul(&mut scope, x.clone(), w.output().clone()).unwrap();
+        let loss = sparse_softmax_cross_entropy(
+            &mut scope,
+            logits.into(),
+            labels.clone().into(),
+            LossOptions::default().with_sample_weights(sample_weights.clone()),
+        )
+        .unwrap();
+        let optimizer =
+            GradientDescentOptimizer::new(ops::constant(&mut scope, 0.5f32).unwrap().into());
+        let variables = [w.clone()];
+        let mut trainer = Trainer::new(
+            &mut scope,
+            loss,
+            &optimizer,
+            TrainerOptions::default().with_variables(&variables),
+        )
+        .unwrap();
+
+        // Both classes are equally common, but only class 0 is weighted.
+        let label_values = Tensor::from(&[0i32, 1, 0, 1][..]);
+        assert!(TrainingData::new()
+            .with_class_weights(sample_weights.clone(), &label_values, &[1.0])
+            .is_err());
+        let data = TrainingData::new()
+            .with_input(
+                x.into(),
+                Tensor::new(&[4, 1]).with_values(&[1.0f32; 4]).unwrap(),
+            )
+            .with_input(labels.into(), label_values.clone())
+            .with_class_weights(sample_weights, &label_values, &[1.0, 0.0])
+            .unwrap();
+        trainer.fit(&data, 10).unwrap();
+        let mut args = SessionRunArgs::new();
+        let w_token = args.request_fetch(&w.output().operation, 0);
+        trainer.session().run(&mut args).unwrap();
+        let w_value = args.fetch::<f32>(w_token).unwrap();
+        assert!(w_value[0] > w_value[1], "w = {:?}", &w_value[..]);
+    }
+
+    #[test]
+    fn split_data() {
+        let mut scope = Scope::new_root_scope();
+        let x = ops::constant(&mut scope, 1.0f32).unwrap();
+        let data = TrainingData::new().with_input(
+            x.into(),
+            Tensor::new(&[5, 2])
+                .with_values(&[0.0f32, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0])
+                .unwrap(),
+        );
+        let (train, validation) = data.split(0.4, 1).unwrap();
+        assert_eq!(train.num_examples().unwrap(), 3);
+        assert_eq!(validation.num_examples().unwrap(), 2);
+        let selected = data.select(&[4, 4]).unwrap();
+        assert_eq!(selected.num_examples().unwrap(), 2);
+        assert!(data.select(&[5]).is_err());
+    }
+}
@@ -1,4 +1,5 @@
 use crate::ops;
+use crate::scope::is_floating;
 use crate::AnyTensor;
 use crate::DataType;
 use crate::Operation;
@@ -26,6 +27,11 @@ impl Variable {
         VariableBuilder::default()
     }
 
+    /// Returns the name of the variable's op.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
     /// Returns the output which evaluates to the value of the variable.
     pub fn output(&self) -> &Output {
         &self.output
@@ -45,6 +51,13 @@ impl Variable {
     pub fn shape(&self) -> &Shape {
         &self.shape
     }
+
+    /// Returns the value of the variable cast to the compute data type of
+    /// the scope's `DTypePolicy`, for use in computations.  Without a policy,
+    /// this is the same as `output`.
+    pub fn compute_output(&self, scope: &mut Scope) -> Result<Output> {
+        ops::cast_to_compute_dtype(scope, self.output.clone())
+    }
 }
 
 #[derive(Debug)]
@@ -122,19 +135,25 @@ impl<'a> VariableBuilder<'a> {
     }
 
     /// Builds the Variable.
+    ///
+    /// If the scope has a `DTypePolicy` and the data type is floating point,
+    /// the variable is created with the policy's variable data type instead,
+    /// and the initial value is cast to it.
     pub fn build(self, scope: &mut Scope) -> Result<Variable> {
-        let name = scope.get_unique_name_for_op("VariableV2");
-        let dtype = match self.dtype {
+        let requested_dtype = match self.dtype {
             Some(d) => d,
             None => return Err(invalid_arg!("data_type must be specified")),
         };
-        let variable_op = {
-            let mut graph = scope.graph_mut();
-            let mut nd = graph.new_operation("VariableV2", &name)?;
+        let dtype = match scope.dtype_policy() {
+            Some(policy) if is_floating(requested_dtype) => policy.variable_dtype(),
+            _ => requested_dtype,
+        };
+        let variable_op = scope.new_operation("VariableV2", |nd| {
             nd.set_attr_type("dtype", dtype)?;
             nd.set_attr_shape("shape", &self.shape)?;
-            nd.finish()?
-        };
+            Ok(())
+        })?;
+        let name = variable_op.name()?;
         let initial_value = match self.initial_value {
             VariableInitialValue::Unspecified => {
                 return Err(invalid_arg!("an initial value is required"))
@@ -143,6 +162,14 @@ impl<'a> VariableBuilder<'a> {
             VariableInitialValue::TensorRef(t) => ops::any_constant(scope, t)?.into(),
             VariableInitialValue::Output(o) => o,
         };
+        let initial_value = if dtype == requested_dtype {
+            initial_value
+        } else {
+            ops::Cast::new()
+                .dst_type(dtype)
+                .build(scope, initial_value)?
+                .into()
+        };
         let initializer = ops::assign(scope, variable_op.clone(), initial_value)?;
         Ok(Variable {
             name,
@@ -160,6 +187,7 @@ impl<'a> VariableBuilder<'a> {
 mod tests {
     use super::*;
     use crate::Code;
+    use crate::DTypePolicy;
     use crate::Session;
     use crate::SessionOptions;
     use crate::SessionRunArgs;
@@ -284,4 +312,30 @@ mod tests {
         let output = run_args.fetch::<f32>(fetch).unwrap();
         assert_eq!(&output[..], &[3.0f32]);
     }
+
+    #[test]
+    fn mixed_precision() {
+        let mut scope = Scope::new_root_scope().with_dtype_policy(DTypePolicy::mixed_float16());
+        let half = Variable::builder()
+            .const_initial_value(half::f16::from_f32(1.5))
+            .build(&mut scope.with_op_name("half"))
+            .unwrap();
+        assert_eq!(half.dtype, DataType::Float);
+        let count = Variable::builder()
+            .const_initial_value(3i32)
+            .build(&mut scope.with_op_name("count"))
+            .unwrap();
+        assert_eq!(count.dtype, DataType::Int32);
+        let compute = half.compute_output(&mut scope).unwrap();
+        assert_eq!(compute.operation.output_type(0), DataType::Half);
+
+        let session = Session::new(&SessionOptions::new(), &scope.graph()).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        run_args.add_target(&half.initializer);
+        session.run(&mut run_args).unwrap();
+        let mut run_args = SessionRunArgs::new();
+        let fetch = run_args.request_fetch(&half.output.operation, 0);
+        session.run(&mut run_args).unwrap();
+        assert_eq!(&run_args.fetch::<f32>(fetch).unwrap()[..], &[1.5f32]);
+    }
 }
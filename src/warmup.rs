@@ -0,0 +1,585 @@
+use crate::io::RecordReader;
+use crate::protos::u32_from_le_bytes;
+use crate::protos::u64_from_le_bytes;
+use crate::protos::ProtoReader;
+use crate::protos::TensorProtoFields;
+use crate::ByteString;
+use crate::Code;
+use crate::DataType;
+use crate::Graph;
+use crate::Output;
+use crate::Result;
+use crate::SavedModelBundle;
+use crate::Session;
+use crate::SessionRunArgs;
+use crate::Status;
+use crate::Tensor;
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+
+/// The path of the warmup file within a SavedModel directory.
+const WARMUP_FILE: &str = "assets.extra/tf_serving_warmup_requests";
+
+/// TensorFlow Serving refuses warmup files with more records than this.
+const MAX_RECORDS: usize = 1000;
+
+/// The signature used by requests which don't name one.
+const DEFAULT_SIGNATURE: &str = "serving_default";
+
+/// The field numbers of the messages inspected here.
+const PREDICTION_LOG_CLASSIFY_LOG: u32 = 2;
+const PREDICTION_LOG_REGRESS_LOG: u32 = 3;
+const PREDICTION_LOG_MULTI_INFERENCE_LOG: u32 = 5;
+const PREDICTION_LOG_PREDICT_LOG: u32 = 6;
+const PREDICTION_LOG_SESSION_RUN_LOG: u32 = 7;
+const PREDICT_LOG_REQUEST: u32 = 1;
+const PREDICT_REQUEST_MODEL_SPEC: u32 = 1;
+const PREDICT_REQUEST_INPUTS: u32 = 2;
+const PREDICT_REQUEST_OUTPUT_FILTER: u32 = 3;
+const MODEL_SPEC_SIGNATURE_NAME: u32 = 3;
+const MAP_ENTRY_KEY: u32 = 1;
+const MAP_ENTRY_VALUE: u32 = 2;
+const META_GRAPH_DEF_SIGNATURE_DEF: u32 = 5;
+const SIGNATURE_DEF_INPUTS: u32 = 1;
+const SIGNATURE_DEF_OUTPUTS: u32 = 2;
+const TENSOR_INFO_NAME: u32 = 1;
+
+/// A tensor decoded from a `TensorProto` in a warmup request.
+#[derive(Debug)]
+enum WarmupTensor {
+    Float(Tensor<f32>),
+    Double(Tensor<f64>),
+    Int32(Tensor<i32>),
+    Int64(Tensor<i64>),
+    Int16(Tensor<i16>),
+    Int8(Tensor<i8>),
+    UInt16(Tensor<u16>),
+    UInt8(Tensor<u8>),
+    Bool(Tensor<bool>),
+    String(Tensor<ByteString>),
+}
+
+impl WarmupTensor {
+    fn add_feed<'l>(&'l self, args: &mut SessionRunArgs<'l>, output: &Output) {
+        let (operation, index) = (&output.operation, output.index);
+        match self {
+            WarmupTensor::Float(t) => args.add_feed(operation, index, t),
+            WarmupTensor::Double(t) => args.add_feed(operation, index, t),
+            WarmupTensor::Int32(t) => args.add_feed(operation, index, t),
+            WarmupTensor::Int64(t) => args.add_feed(operation, index, t),
+            WarmupTensor::Int16(t) => args.add_feed(operation, index, t),
+            WarmupTensor::Int8(t) => args.add_feed(operation, index, t),
+            WarmupTensor::UInt16(t) => args.add_feed(operation, index, t),
+            WarmupTensor::UInt8(t) => args.add_feed(operation, index, t),
+            WarmupTensor::Bool(t) => args.add_feed(operation, index, t),
+            WarmupTensor::String(t) => args.add_feed(operation, index, t),
+        }
+    }
+}
+
+/// A `PredictRequest` read from a warmup record.
+#[derive(Debug)]
+struct WarmupRequest {
+    signature_name: String,
+    inputs: Vec<(String, WarmupTensor)>,
+    output_filter: Vec<String>,
+}
+
+/// Requests recorded in a SavedModel's
+/// `assets.extra/tf_serving_warmup_requests` file, which TensorFlow Serving
+/// replays when it loads the model.
+///
+/// The first runs of a model are much slower than later ones, since they
+/// optimize the graph, allocate memory and initialize kernels.  Replaying
+/// the warmup requests before serving moves that cost out of the serving
+/// path, so the first real requests don't see a latency spike.
+///
+/// ```no_run
+/// # use tensorflow::Graph;
+/// # use tensorflow::ModelWarmup;
+/// # use tensorflow::SavedModelBundle;
+/// # use tensorflow::SessionOptions;
+/// let export_dir = "path/to/saved_model";
+/// let mut graph = Graph::new();
+/// let bundle =
+///     SavedModelBundle::load(&SessionOptions::new(), &["serve"], &mut graph, export_dir)?;
+/// let runs = ModelWarmup::load(export_dir)?.run_bundle(&bundle, &graph)?;
+/// println!("Warmed up with {} runs", runs);
+/// # Ok::<(), tensorflow::Status>(())
+/// ```
+///
+/// The records are `PredictionLog` protos, as written by TensorFlow
+/// Serving's tools.  Only `PredictLog`s are supported; their inputs are fed
+/// to the tensors named by the request's signature, and the signature's
+/// outputs (or those in the request's output filter) are fetched.
+#[derive(Debug)]
+pub struct ModelWarmup {
+    requests: Vec<WarmupRequest>,
+    iterations: usize,
+}
+
+impl ModelWarmup {
+    /// Reads the warmup requests of the SavedModel in `export_dir`.  A model
+    /// without a warmup file has no requests.
+    pub fn load<P: AsRef<Path>>(export_dir: P) -> Result<Self> {
+        let path = export_dir.as_ref().join(WARMUP_FILE);
+        match File::open(&path) {
+            Ok(file) => Self::from_records(BufReader::new(file)),
+            Err(ref e) if e.kind() == io::ErrorKind::NotFound => Ok(Self {
+                requests: Vec::new(),
+                iterations: 1,
+            }),
+            Err(e) => Err(invalid_arg!("Unable to read {}: {}", path.display(), e)),
+        }
+    }
+
+    /// Reads warmup requests from TFRecords of `PredictionLog` protos.
+    pub fn from_records<R: Read>(reader: R) -> Result<Self> {
+        let mut reader = RecordReader::new(reader);
+        let mut requests = Vec::new();
+        while let Some(record) = reader
+            .read_record()
+            .map_err(|e| invalid_arg!("Unable to read warmup record: {}", e))?
+        {
+            if requests.len() == MAX_RECORDS {
+                return Err(invalid_arg!(
+                    "Warmup files may have at most {} records",
+                    MAX_RECORDS
+                ));
+            }
+            requests.push(parse_prediction_log(&record)?);
+        }
+        Ok(Self {
+            requests,
+            iterations: 1,
+        })
+    }
+
+    /// Sets the number of times each request is replayed, which is 1 by
+    /// default.
+    pub fn with_iterations(self, iterations: usize) -> Self {
+        Self { iterations, ..self }
+    }
+
+    /// Returns the number of warmup requests.
+    pub fn len(&self) -> usize {
+        self.requests.len()
+    }
+
+    /// Returns true if there are no warmup requests.
+    pub fn is_empty(&self) -> bool {
+        self.requests.is_empty()
+    }
+
+    /// Replays the requests against a loaded SavedModel, returning the
+    /// number of runs.
+    pub fn run_bundle(&self, bundle: &SavedModelBundle, graph: &Graph) -> Result<usize> {
+        self.run(&bundle.session, graph, &bundle.meta_graph_def)
+    }
+
+    /// Replays the requests against `session`, whose graph was loaded from
+    /// the serialized `MetaGraphDef` `meta_graph_def`, returning the number
+    /// of runs.  With a `SessionPool`, call this from `SessionPool::warm_up`.
+    pub fn run(&self, session: &Session, graph: &Graph, meta_graph_def: &[u8]) -> Result<usize> {
+        if self.requests.is_empty() {
+            return Ok(0);
+        }
+        let signatures = parse_signatures(meta_graph_def)?;
+        let mut runs = 0;
+        for request in &self.requests {
+            let signature = signatures.get(&request.signature_name).ok_or_else(|| {
+                invalid_arg!(
+                    "Warmup request uses signature {:?}, which the model doesn't have",
+                    request.signature_name
+                )
+            })?;
+            let mut feeds = Vec::with_capacity(request.inputs.len());
+            for (alias, tensor) in &request.inputs {
+                let name = signature.inputs.get(alias).ok_or_else(|| {
+                    invalid_arg!(
+                        "Signature {:?} has no input {:?}",
+                        request.signature_name,
+                        alias
+                    )
+                })?;
+                feeds.push((graph.output_by_name_required(name)?, tensor));
+            }
+            let mut fetches = Vec::new();
+            if request.output_filter.is_empty() {
+                for name in signature.outputs.values() {
+                    fetches.push(graph.output_by_name_required(name)?);
+                }
+            } else {
+                for alias in &request.output_filter {
+                    let name = signature.outputs.get(alias).ok_or_else(|| {
+                        invalid_arg!(
+                            "Signature {:?} has no output {:?}",
+                            request.signature_name,
+                            alias
+                        )
+                    })?;
+                    fetches.push(graph.output_by_name_required(name)?);
+                }
+            }
+            for _ in 0..self.iterations {
+                let mut args = SessionRunArgs::new();
+                for (output, tensor) in &feeds {
+                    tensor.add_feed(&mut args, output);
+                }
+                for output in &fetches {
+                    args.request_fetch(&output.operation, output.index);
+                }
+                session.run(&mut args)?;
+                runs += 1;
+            }
+        }
+        Ok(runs)
+    }
+}
+
+/// The inputs and outputs of a `SignatureDef`, mapping their aliases to
+/// tensor names.
+#[derive(Debug, Default)]
+struct Signature {
+    inputs: BTreeMap<String, String>,
+    outputs: BTreeMap<String, String>,
+}
+
+fn parse_signatures(meta_graph_def: &[u8]) -> Result<BTreeMap<String, Signature>> {
+    let mut signatures = BTreeMap::new();
+    for field in ProtoReader::new(meta_graph_def) {
+        if let (META_GRAPH_DEF_SIGNATURE_DEF, entry) = field? {
+            let (key, value) = parse_map_entry(entry.as_bytes()?)?;
+            let mut signature = Signature::default();
+            for field in ProtoReader::new(value) {
+                let (field, value) = field?;
+                let tensors = match field {
+                    SIGNATURE_DEF_INPUTS => &mut signature.inputs,
+                    SIGNATURE_DEF_OUTPUTS => &mut signature.outputs,
+                    _ => continue,
+                };
+                let (alias, tensor_info) = parse_map_entry(value.as_bytes()?)?;
+                for field in ProtoReader::new(tensor_info) {
+                    if let (TENSOR_INFO_NAME, name) = field? {
+                        tensors.insert(alias.clone(), name.as_str()?.to_string());
+                    }
+                }
+            }
+            signatures.insert(key, signature);
+        }
+    }
+    Ok(signatures)
+}
+
+/// Returns the string key and message value of a map entry.
+fn parse_map_entry(entry: &[u8]) -> Result<(String, &[u8])> {
+    let mut key = String::new();
+    let mut value: &[u8] = &[];
+    for field in ProtoReader::new(entry) {
+        match field? {
+            (MAP_ENTRY_KEY, v) => key = v.as_str()?.to_string(),
+            (MAP_ENTRY_VALUE, v) => value = v.as_bytes()?,
+            _ => {}
+        }
+    }
+    Ok((key, value))
+}
+
+fn parse_prediction_log(log: &[u8]) -> Result<WarmupRequest> {
+    for field in ProtoReader::new(log) {
+        match field? {
+            (PREDICTION_LOG_PREDICT_LOG, predict_log) => {
+                for field in ProtoReader::new(predict_log.as_bytes()?) {
+                    if let (PREDICT_LOG_REQUEST, request) = field? {
+                        return parse_predict_request(request.as_bytes()?);
+                    }
+                }
+                return Err(invalid_arg!("Warmup PredictLog has no request"));
+            }
+            (PREDICTION_LOG_CLASSIFY_LOG, _)
+            | (PREDICTION_LOG_REGRESS_LOG, _)
+            | (PREDICTION_LOG_MULTI_INFERENCE_LOG, _)
+            | (PREDICTION_LOG_SESSION_RUN_LOG, _) => {
+                return Err(Status::new_set(
+                    Code::Unimplemented,
+                    "Only PredictLog warmup records are supported",
+                )
+                .unwrap());
+            }
+            _ => {}
+        }
+    }
+    Err(invalid_arg!("Warmup record has no log"))
+}
+
+fn parse_predict_request(request: &[u8]) -> Result<WarmupRequest> {
+    let mut signature_name = String::new();
+    let mut inputs = Vec::new();
+    let mut output_filter = Vec::new();
+    for field in ProtoReader::new(request) {
+        match field? {
+            (PREDICT_REQUEST_MODEL_SPEC, model_spec) => {
+                for field in ProtoReader::new(model_spec.as_bytes()?) {
+                    if let (MODEL_SPEC_SIGNATURE_NAME, name) = field? {
+                        signature_name = name.as_str()?.to_string();
+                    }
+                }
+            }
+            (PREDICT_REQUEST_INPUTS, entry) => {
+                let (alias, tensor) = parse_map_entry(entry.as_bytes()?)?;
+                inputs.push((alias, decode_tensor(tensor)?));
+            }
+            (PREDICT_REQUEST_OUTPUT_FILTER, alias) => {
+                output_filter.push(alias.as_str()?.to_string())
+            }
+            _ => {}
+        }
+    }
+    if signature_name.is_empty() {
+        signature_name = DEFAULT_SIGNATURE.to_string();
+    }
+    Ok(WarmupRequest {
+        signature_name,
+        inputs,
+        output_filter,
+    })
+}
+
+/// Decodes a `TensorProto` into a tensor of the types which can be fed from
+/// Rust.
+fn decode_tensor(tensor: &[u8]) -> Result<WarmupTensor> {
+    let fields = TensorProtoFields::parse(tensor)?;
+    Ok(match fields.data_type() {
+        DataType::Float => WarmupTensor::Float(fields.decode_f32()?),
+        DataType::Double => WarmupTensor::Double(
+            fields.decode(
+                |b| f64::from_bits(u64_from_le_bytes(b)),
+                fields
+                    .double_val
+                    .iter()
+                    .cloned()
+                    .map(f64::from_bits)
+                    .collect(),
+            )?,
+        ),
+        DataType::Int32 => WarmupTensor::Int32(fields.decode(
+            |b| u32_from_le_bytes(b) as i32,
+            fields.int_val.iter().map(|&v| v as i32).collect(),
+        )?),
+        DataType::Int64 => WarmupTensor::Int64(fields.decode(
+            |b| u64_from_le_bytes(b) as i64,
+            fields.int64_val.iter().map(|&v| v as i64).collect(),
+        )?),
+        DataType::Int16 => WarmupTensor::Int16(fields.decode(
+            |b| i16::from_le_bytes([b[0], b[1]]),
+            fields.int_val.iter().map(|&v| v as i16).collect(),
+        )?),
+        DataType::Int8 => WarmupTensor::Int8(fields.decode(
+            |b| b[0] as i8,
+            fields.int_val.iter().map(|&v| v as i8).collect(),
+        )?),
+        DataType::UInt16 => WarmupTensor::UInt16(fields.decode(
+            |b| u16::from_le_bytes([b[0], b[1]]),
+            fields.int_val.iter().map(|&v| v as u16).collect(),
+        )?),
+        DataType::UInt8 => WarmupTensor::UInt8(
+            fields.decode(|b| b[0], fields.int_val.iter().map(|&v| v as u8).collect())?,
+        ),
+        DataType::Bool => WarmupTensor::Bool(fields.decode(
+            |b| b[0] != 0,
+            fields.bool_val.iter().map(|&v| v != 0).collect(),
+        )?),
+        DataType::String => {
+            let mut tensor = Tensor::new(&fields.dims);
+            if fields.string_val.len() != tensor.len() {
+                return Err(invalid_arg!(
+                    "Tensor has {} strings but shape {:?}",
+                    fields.string_val.len(),
+                    fields.dims
+                ));
+            }
+            for (x, &s) in tensor.iter_mut().zip(&fields.string_val) {
+                *x = ByteString::from(s);
+            }
+            WarmupTensor::String(tensor)
+        }
+        dtype => {
+            return Err(invalid_arg!(
+                "Warmup tensors of type {} aren't supported",
+                dtype
+            ))
+        }
+    })
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::io::RecordWriter;
+    use crate::protos::ProtoWriter;
+    use crate::protos::DIM_SIZE;
+    use crate::protos::SHAPE_DIM;
+    use crate::protos::TENSOR_CONTENT;
+    use crate::protos::TENSOR_DTYPE;
+    use crate::protos::TENSOR_FLOAT_VAL;
+    use crate::protos::TENSOR_SHAPE;
+    use crate::protos::TENSOR_STRING_VAL;
+    use crate::SessionOptions;
+
+    fn map_entry(key: &str, value: &ProtoWriter) -> ProtoWriter {
+        let mut entry = ProtoWriter::new();
+        entry
+            .string_field(MAP_ENTRY_KEY, key)
+            .message_field(MAP_ENTRY_VALUE, value);
+        entry
+    }
+
+    fn shape(dims: &[i64]) -> ProtoWriter {
+        let mut shape = ProtoWriter::new();
+        for &d in dims {
+            let mut dim = ProtoWriter::new();
+            dim.int_field(DIM_SIZE, d);
+            shape.message_field(SHAPE_DIM, &dim);
+        }
+        shape
+    }
+
+    fn float_tensor(dims: &[i64], values: &[f32]) -> ProtoWriter {
+        let mut tensor = ProtoWriter::new();
+        tensor
+            .int_field(TENSOR_DTYPE, i64::from(DataType::Float.to_int()))
+            .message_field(TENSOR_SHAPE, &shape(dims));
+        for &v in values {
+            tensor.float_field(TENSOR_FLOAT_VAL, v);
+        }
+        tensor
+    }
+
+    fn prediction_log(signature_name: &str, inputs: &[(&str, ProtoWriter)]) -> Vec<u8> {
+        let mut model_spec = ProtoWriter::new();
+        model_spec.string_field(MODEL_SPEC_SIGNATURE_NAME, signature_name);
+        let mut request = ProtoWriter::new();
+        request.message_field(PREDICT_REQUEST_MODEL_SPEC, &model_spec);
+        for (alias, tensor) in inputs {
+            request.message_field(PREDICT_REQUEST_INPUTS, &map_entry(alias, tensor));
+        }
+        let mut predict_log = ProtoWriter::new();
+        predict_log.message_field(PREDICT_LOG_REQUEST, &request);
+        let mut log = ProtoWriter::new();
+        log.message_field(PREDICTION_LOG_PREDICT_LOG, &predict_log);
+        log.into_bytes()
+    }
+
+    #[test]
+    fn decode() {
+        match decode_tensor(float_tensor(&[2, 2], &[1.0, 2.0]).as_bytes()).unwrap() {
+            WarmupTensor::Float(t) => {
+                assert_eq!(t.dims(), &[2, 2]);
+                assert_eq!(&t[..], &[1.0, 2.0, 2.0, 2.0]);
+            }
+            t => panic!("{:?}", t),
+        }
+
+        let mut tensor = ProtoWriter::new();
+        tensor
+            .int_field(TENSOR_DTYPE, i64::from(DataType::Int64.to_int()))
+            .message_field(TENSOR_SHAPE, &shape(&[2]))
+            .bytes_field(
+                TENSOR_CONTENT,
+                &[7i64.to_le_bytes(), (-3i64).to_le_bytes()].concat(),
+            );
+        match decode_tensor(tensor.as_bytes()).unwrap() {
+            WarmupTensor::Int64(t) => assert_eq!(&t[..], &[7, -3]),
+            t => panic!("{:?}", t),
+        }
+
+        let mut tensor = ProtoWriter::new();
+        tensor
+            .int_field(TENSOR_DTYPE, i64::from(DataType::String.to_int()))
+            .message_field(TENSOR_SHAPE, &shape(&[2]))
+            .string_field(TENSOR_STRING_VAL, "a")
+            .string_field(TENSOR_STRING_VAL, "bc");
+        match decode_tensor(tensor.as_bytes()).unwrap() {
+            WarmupTensor::String(t) => {
+                assert_eq!(&t[..], &[ByteString::from("a"), ByteString::from("bc")])
+            }
+            t => panic!("{:?}", t),
+        }
+
+        assert!(decode_tensor(float_tensor(&[1], &[1.0, 2.0]).as_bytes()).is_err());
+        assert!(decode_tensor(float_tensor(&[-1], &[]).as_bytes()).is_err());
+        // A malformed record is an error rather than a panic.
+        let mut tensor = float_tensor(&[1], &[]);
+        tensor.bytes_field(TENSOR_FLOAT_VAL, &[0, 0, 0x80]);
+        assert!(decode_tensor(tensor.as_bytes()).is_err());
+    }
+
+    #[test]
+    fn replay() {
+        let mut g = Graph::new();
+        let x = {
+            let mut nd = g.new_operation("Placeholder", "x").unwrap();
+            nd.set_attr_type("dtype", DataType::Float).unwrap();
+            nd.finish().unwrap()
+        };
+        {
+            let mut nd = g.new_operation("Square", "y").unwrap();
+            nd.add_input(x);
+            nd.finish().unwrap();
+        }
+        let session = Session::new(&SessionOptions::new(), &g).unwrap();
+
+        let mut signature = ProtoWriter::new();
+        for &(field, alias, name) in &[
+            (SIGNATURE_DEF_INPUTS, "input", "x:0"),
+            (SIGNATURE_DEF_OUTPUTS, "output", "y:0"),
+        ] {
+            let mut tensor_info = ProtoWriter::new();
+            tensor_info.string_field(TENSOR_INFO_NAME, name);
+            signature.message_field(field, &map_entry(alias, &tensor_info));
+        }
+        let mut meta_graph_def = ProtoWriter::new();
+        meta_graph_def.message_field(
+            META_GRAPH_DEF_SIGNATURE_DEF,
+            &map_entry(DEFAULT_SIGNATURE, &signature),
+        );
+        let meta_graph_def = meta_graph_def.into_bytes();
+
+        let mut records = Vec::new();
+        {
+            let mut writer = RecordWriter::new(&mut records);
+            for log in &[
+                prediction_log("", &[("input", float_tensor(&[3], &[1.0, 2.0, 3.0]))]),
+                prediction_log(DEFAULT_SIGNATURE, &[("input", float_tensor(&[], &[4.0]))]),
+            ] {
+                writer.write_record(log).unwrap();
+            }
+        }
+        let warmup = ModelWarmup::from_records(&records[..])
+            .unwrap()
+            .with_iterations(3);
+        assert_eq!(warmup.len(), 2);
+        assert_eq!(warmup.run(&session, &g, &meta_graph_def).unwrap(), 6);
+
+        let mut writer_buf = Vec::new();
+        RecordWriter::new(&mut writer_buf)
+            .write_record(&prediction_log(
+                "missing",
+                &[("input", float_tensor(&[], &[1.0]))],
+            ))
+            .unwrap();
+        let warmup = ModelWarmup::from_records(&writer_buf[..]).unwrap();
+        assert!(warmup.run(&session, &g, &meta_graph_def).is_err());
+
+        // The test model has no warmup file.
+        let warmup = ModelWarmup::load("test_resources/regression-model").unwrap();
+        assert!(warmup.is_empty());
+        assert_eq!(warmup.run(&session, &g, &meta_graph_def).unwrap(), 0);
+    }
+}
@@ -0,0 +1,181 @@
+//! Derives `Feed` and `Fetch` for structs of named tensors.
+
+use crate::tensor_name::split_tensor_name;
+use proc_macro2::Span;
+use proc_macro2::TokenStream;
+use quote::quote;
+use syn::Data;
+use syn::DeriveInput;
+use syn::Error;
+use syn::Fields;
+use syn::Ident;
+use syn::Lit;
+use syn::Meta;
+use syn::NestedMeta;
+use syn::Result;
+use syn::Type;
+
+/// A field and the tensor it's bound to.
+struct Field {
+    ident: Ident,
+    ty: Type,
+    op_name: String,
+    index: i32,
+}
+
+/// Returns the tensor name given by `#[tensorflow(name = "...")]`, if any.
+fn tensor_name(field: &syn::Field) -> Result<Option<String>> {
+    let mut name = None;
+    for attr in &field.attrs {
+        if !attr.path.is_ident("tensorflow") {
+            continue;
+        }
+        let list = match attr.parse_meta()? {
+            Meta::List(list) => list,
+            meta => {
+                return Err(Error::new_spanned(
+                    meta,
+                    "expected #[tensorflow(name = \"...\")]",
+                ))
+            }
+        };
+        for nested in list.nested {
+            match nested {
+                NestedMeta::Meta(Meta::NameValue(ref value)) if value.ident == "name" => {
+                    match &value.lit {
+                        Lit::Str(s) => name = Some(s.value()),
+                        lit => return Err(Error::new_spanned(lit, "expected a string")),
+                    }
+                }
+                nested => {
+                    return Err(Error::new_spanned(
+                        nested,
+                        "unknown attribute; expected `name`",
+                    ))
+                }
+            }
+        }
+    }
+    Ok(name)
+}
+
+fn fields(input: &DeriveInput, derive: &str) -> Result<Vec<Field>> {
+    let named = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(named) => &named.named,
+            _ => {
+                return Err(Error::new(
+                    Span::call_site(),
+                    format!(
+                        "{} can only be derived for structs with named fields",
+                        derive
+                    ),
+                ))
+            }
+        },
+        _ => {
+            return Err(Error::new(
+                Span::call_site(),
+                format!("{} can only be derived for structs", derive),
+            ))
+        }
+    };
+    let mut fields = Vec::new();
+    for field in named {
+        let ident = field.ident.clone().unwrap();
+        let name = tensor_name(field)?.unwrap_or_else(|| ident.to_string());
+        let (op_name, index) = split_tensor_name(&name)
+            .ok_or_else(|| Error::new_spanned(field, format!("invalid tensor name {:?}", name)))?;
+        fields.push(Field {
+            ident,
+            ty: field.ty.clone(),
+            op_name,
+            index,
+        });
+    }
+    Ok(fields)
+}
+
+pub fn derive_feed(input: DeriveInput) -> Result<TokenStream> {
+    let fields = fields(&input, "Feed")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let feeds: Vec<TokenStream> = fields
+        .iter()
+        .map(|field| {
+            let (ident, op_name, index) = (&field.ident, &field.op_name, field.index);
+            quote! {
+                ::tensorflow::FeedValue::feed_value(
+                    &self.#ident,
+                    args,
+                    &graph.operation_by_name_required(#op_name)?,
+                    #index,
+                );
+            }
+        })
+        .collect();
+    Ok(quote! {
+        impl #impl_generics ::tensorflow::Feed for #name #ty_generics #where_clause {
+            fn add_feeds<'l>(
+                &'l self,
+                graph: &::tensorflow::Graph,
+                args: &mut ::tensorflow::SessionRunArgs<'l>,
+            ) -> ::tensorflow::Result<()> {
+                #(#feeds)*
+                Ok(())
+            }
+        }
+    })
+}
+
+pub fn derive_fetch(input: DeriveInput) -> Result<TokenStream> {
+    let fields = fields(&input, "Fetch")?;
+    let name = &input.ident;
+    let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
+    let requests: Vec<TokenStream> = fields
+        .iter()
+        .map(|field| {
+            let (op_name, index) = (&field.op_name, field.index);
+            quote! {
+                args.request_fetch(&graph.operation_by_name_required(#op_name)?, #index)
+            }
+        })
+        .collect();
+    let fetches: Vec<TokenStream> = fields
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let (ident, ty) = (&field.ident, &field.ty);
+            quote! {
+                #ident: <#ty as ::tensorflow::FetchValue>::fetch_value(args, tokens[#i])?,
+            }
+        })
+        .collect();
+    let count = fields.len();
+    Ok(quote! {
+        impl #impl_generics ::tensorflow::Fetch for #name #ty_generics #where_clause {
+            fn request_fetches(
+                graph: &::tensorflow::Graph,
+                args: &mut ::tensorflow::SessionRunArgs<'_>,
+            ) -> ::tensorflow::Result<::std::vec::Vec<::tensorflow::FetchToken>> {
+                Ok(vec![#(#requests),*])
+            }
+
+            fn fetch(
+                args: &mut ::tensorflow::SessionRunArgs<'_>,
+                tokens: &[::tensorflow::FetchToken],
+            ) -> ::tensorflow::Result<Self> {
+                if tokens.len() != #count {
+                    return Err(::tensorflow::Status::new_set(
+                        ::tensorflow::Code::InvalidArgument,
+                        &format!("Expected {} fetch tokens, got {}", #count, tokens.len()),
+                    )
+                    .unwrap());
+                }
+                Ok(Self {
+                    #(#fetches)*
+                })
+            }
+        }
+    })
+}
@@ -12,6 +12,7 @@ use syn::parse::Parse;
 use syn::parse::ParseStream;
 use syn::parse_macro_input;
 use syn::punctuated::Punctuated;
+use syn::DeriveInput;
 use syn::Error;
 use syn::Ident;
 use syn::LitStr;
@@ -19,6 +20,10 @@ use syn::Result;
 use syn::Token;
 use syn::Type;
 
+mod feed_fetch;
+mod signature;
+mod tensor_name;
+
 #[derive(Clone)]
 struct Arg {
     name: Ident,
@@ -238,6 +243,7 @@ impl<'a> ToTokens for SetAttr<'a> {
             "DataType" => quote! { nd.set_attr_type(#c_name, #value)?; },
             "bool" => quote! { nd.set_attr_bool(#c_name, #value)?; },
             "i64" => quote! { nd.set_attr_int(#c_name, #value)?; },
+            "f32" => quote! { nd.set_attr_float(#c_name, #value)?; },
             "Shape" => quote! { nd.set_attr_shape(#c_name, &#value)?; },
             ty => panic!(
                 "Unrecognized attribute type for {}: {}",
@@ -277,17 +283,16 @@ impl<'a> ToTokens for BuildFn<'a> {
             #[doc = #op_name]
             #[doc = "` operation."]
             pub fn build#build_fn_generics(&self, scope: &mut crate::Scope #build_fn_args) -> crate::Result<crate::Operation> {
-                let name = scope.get_unique_name_for_op(#op_name);
-                let mut graph = scope.graph_mut();
-                let mut nd = graph.new_operation(#op_name, &name)?;
-                #(
-                    nd.add_input(#arg_names);
-                )*
-                for op in &self.control_inputs {
-                    nd.add_control_input(op);
-                }
-                #(#set_attrs)*
-                nd.finish()
+                scope.new_operation(#op_name, |nd| {
+                    #(
+                        nd.add_input(#arg_names);
+                    )*
+                    for op in &self.control_inputs {
+                        nd.add_control_input(op);
+                    }
+                    #(#set_attrs)*
+                    ::std::result::Result::Ok(())
+                })
             }
         });
     }
@@ -375,3 +380,59 @@ pub fn define_op(input: TokenStream) -> TokenStream {
     };
     stream.into()
 }
+
+/// Generates types for calling a signature of a SavedModel, checked against
+/// the model when the crate is compiled.
+///
+/// ```ignore
+/// saved_model_signature!(
+///     Regress,
+///     path = "test_resources/regression-model",
+///     tags = ["train", "serve"],
+///     signature = "tensorflow/serving/regress",
+/// );
+/// ```
+///
+/// generates `RegressInputs` and `RegressOutputs`, with a `Tensor` field of
+/// the right type for each input and output of the signature, and `Regress`,
+/// which loads the model with `Regress::load(&options, export_dir)` and runs
+/// the signature with `run(&inputs)`.
+///
+/// `path` is relative to the directory containing the crate's `Cargo.toml`.
+/// `tags` defaults to `["serve"]`, and `signature` to `"serving_default"`.
+/// Field names are the signature keys converted to snake case.  The crate is
+/// rebuilt whenever `saved_model.pb` changes, and incompatible changes to the
+/// signature are compile errors rather than runtime errors.
+#[proc_macro]
+pub fn saved_model_signature(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as signature::SignatureInput);
+    match signature::expand(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Implements `Feed` for a struct of `Tensor`s, feeding each field to the
+/// tensor named by `#[tensorflow(name = "op:index")]`, or to output 0 of the
+/// op with the same name as the field.
+#[proc_macro_derive(Feed, attributes(tensorflow))]
+pub fn derive_feed(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match feed_fetch::derive_feed(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
+
+/// Implements `Fetch` for a struct whose fields implement `FetchValue`,
+/// fetching each field from the tensor named by
+/// `#[tensorflow(name = "op:index")]`, or from output 0 of the op with the
+/// same name as the field.
+#[proc_macro_derive(Fetch, attributes(tensorflow))]
+pub fn derive_fetch(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    match feed_fetch::derive_fetch(input) {
+        Ok(tokens) => tokens.into(),
+        Err(e) => e.to_compile_error().into(),
+    }
+}
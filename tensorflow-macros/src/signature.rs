@@ -0,0 +1,665 @@
+//! Generates typed wrappers for SavedModel signatures.
+
+use crate::tensor_name::split_tensor_name;
+use proc_macro2::Span;
+use proc_macro2::TokenStream;
+use quote::quote;
+use std::path::PathBuf;
+use syn::bracketed;
+use syn::parse::Parse;
+use syn::parse::ParseStream;
+use syn::punctuated::Punctuated;
+use syn::Error;
+use syn::Ident;
+use syn::LitStr;
+use syn::Result;
+use syn::Token;
+
+pub struct SignatureInput {
+    name: Ident,
+    path: LitStr,
+    tags: Vec<LitStr>,
+    signature: LitStr,
+}
+
+impl Parse for SignatureInput {
+    fn parse(input: ParseStream) -> Result<Self> {
+        let name = input.parse()?;
+        let mut path = None;
+        let mut tags = None;
+        let mut signature = None;
+        while !input.is_empty() {
+            input.parse::<Token![,]>()?;
+            if input.is_empty() {
+                break;
+            }
+            let key: Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            match key.to_string().as_str() {
+                "path" => path = Some(input.parse()?),
+                "signature" => signature = Some(input.parse()?),
+                "tags" => {
+                    let list;
+                    bracketed!(list in input);
+                    let parsed: Punctuated<LitStr, Token![,]> =
+                        list.parse_terminated(<LitStr as Parse>::parse)?;
+                    tags = Some(parsed.into_iter().collect());
+                }
+                _ => {
+                    return Err(Error::new(
+                        key.span(),
+                        "expected `path`, `tags` or `signature`",
+                    ))
+                }
+            }
+        }
+        let missing = |what| Error::new(Span::call_site(), format!("missing `{}`", what));
+        Ok(SignatureInput {
+            name,
+            path: path.ok_or_else(|| missing("path"))?,
+            tags: tags.unwrap_or_else(|| vec![LitStr::new("serve", Span::call_site())]),
+            signature: signature
+                .unwrap_or_else(|| LitStr::new("serving_default", Span::call_site())),
+        })
+    }
+}
+
+////////////////////////
+
+/// A field of a protocol buffer message.
+enum Value<'a> {
+    Varint(u64),
+    Bytes(&'a [u8]),
+    Fixed,
+}
+
+/// Iterates over the fields of a serialized protocol buffer message.
+struct Fields<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Fields<'a> {
+    fn varint(&mut self) -> std::result::Result<u64, String> {
+        let mut value = 0u64;
+        for shift in (0..64).step_by(7) {
+            let (&byte, rest) = self.buf.split_first().ok_or("truncated varint")?;
+            self.buf = rest;
+            value |= u64::from(byte & 0x7f) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(value);
+            }
+        }
+        Err("varint too long".to_string())
+    }
+
+    fn take(&mut self, len: usize) -> std::result::Result<&'a [u8], String> {
+        if self.buf.len() < len {
+            return Err("truncated field".to_string());
+        }
+        let (bytes, rest) = self.buf.split_at(len);
+        self.buf = rest;
+        Ok(bytes)
+    }
+
+    fn field(&mut self) -> std::result::Result<(u32, Value<'a>), String> {
+        let key = self.varint()?;
+        let value = match key & 7 {
+            0 => Value::Varint(self.varint()?),
+            1 => {
+                self.take(8)?;
+                Value::Fixed
+            }
+            2 => {
+                let len = self.varint()? as usize;
+                Value::Bytes(self.take(len)?)
+            }
+            5 => {
+                self.take(4)?;
+                Value::Fixed
+            }
+            wire_type => return Err(format!("unsupported wire type {}", wire_type)),
+        };
+        Ok(((key >> 3) as u32, value))
+    }
+}
+
+impl<'a> Iterator for Fields<'a> {
+    type Item = std::result::Result<(u32, Value<'a>), String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.buf.is_empty() {
+            None
+        } else {
+            Some(self.field())
+        }
+    }
+}
+
+fn fields(buf: &[u8]) -> Fields<'_> {
+    Fields { buf }
+}
+
+fn string(bytes: &[u8]) -> std::result::Result<String, String> {
+    String::from_utf8(bytes.to_vec()).map_err(|e| e.to_string())
+}
+
+/// A tensor of a signature.
+struct TensorInfo {
+    key: String,
+    name: String,
+    dtype: u64,
+}
+
+/// Parses the entries of a `map<string, TensorInfo>`.
+fn tensor_infos(entries: &[&[u8]]) -> std::result::Result<Vec<TensorInfo>, String> {
+    let mut infos = Vec::new();
+    for entry in entries {
+        let mut key = String::new();
+        let mut name = None;
+        let mut dtype = 0;
+        for field in fields(entry) {
+            match field? {
+                (1, Value::Bytes(bytes)) => key = string(bytes)?,
+                (2, Value::Bytes(info)) => {
+                    for field in fields(info) {
+                        match field? {
+                            (1, Value::Bytes(bytes)) => name = Some(string(bytes)?),
+                            (2, Value::Varint(value)) => dtype = value,
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        let name = name.ok_or_else(|| format!("{} is not a dense tensor", key))?;
+        infos.push(TensorInfo { key, name, dtype });
+    }
+    infos.sort_by(|a, b| a.key.cmp(&b.key));
+    Ok(infos)
+}
+
+/// Returns the inputs and outputs of the signature named `signature` in the
+/// meta graph with exactly `tags` in the serialized SavedModel `saved_model`.
+fn find_signature(
+    saved_model: &[u8],
+    tags: &[String],
+    signature: &str,
+) -> std::result::Result<(Vec<TensorInfo>, Vec<TensorInfo>), String> {
+    let mut found_tags = Vec::new();
+    for field in fields(saved_model) {
+        let meta_graph = match field? {
+            (2, Value::Bytes(meta_graph)) => meta_graph,
+            _ => continue,
+        };
+        let mut graph_tags = Vec::new();
+        let mut signatures = Vec::new();
+        for field in fields(meta_graph) {
+            match field? {
+                (1, Value::Bytes(meta_info)) => {
+                    for field in fields(meta_info) {
+                        if let (4, Value::Bytes(tag)) = field? {
+                            graph_tags.push(string(tag)?);
+                        }
+                    }
+                }
+                (5, Value::Bytes(entry)) => signatures.push(entry),
+                _ => {}
+            }
+        }
+        let mut sorted_tags = graph_tags.clone();
+        sorted_tags.sort();
+        let mut wanted_tags = tags.to_vec();
+        wanted_tags.sort();
+        if sorted_tags != wanted_tags {
+            found_tags.push(graph_tags);
+            continue;
+        }
+        let mut names = Vec::new();
+        for entry in signatures {
+            let mut key = String::new();
+            let mut def = None;
+            for field in fields(entry) {
+                match field? {
+                    (1, Value::Bytes(bytes)) => key = string(bytes)?,
+                    (2, Value::Bytes(bytes)) => def = Some(bytes),
+                    _ => {}
+                }
+            }
+            if key != signature {
+                names.push(key);
+                continue;
+            }
+            let mut inputs = Vec::new();
+            let mut outputs = Vec::new();
+            for field in fields(def.unwrap_or(&[])) {
+                match field? {
+                    (1, Value::Bytes(entry)) => inputs.push(entry),
+                    (2, Value::Bytes(entry)) => outputs.push(entry),
+                    _ => {}
+                }
+            }
+            return Ok((tensor_infos(&inputs)?, tensor_infos(&outputs)?));
+        }
+        return Err(format!(
+            "no signature named {:?}; available signatures are {:?}",
+            signature, names
+        ));
+    }
+    Err(format!(
+        "no meta graph with tags {:?}; available tag sets are {:?}",
+        tags, found_tags
+    ))
+}
+
+////////////////////////
+
+/// Returns the Rust type for the TensorFlow `DataType` enum value `dtype`.
+fn rust_type(dtype: u64) -> Option<TokenStream> {
+    Some(match dtype {
+        1 => quote!(f32),
+        2 => quote!(f64),
+        3 => quote!(i32),
+        4 => quote!(u8),
+        5 => quote!(i16),
+        6 => quote!(i8),
+        7 => quote!(::std::string::String),
+        9 => quote!(i64),
+        10 => quote!(bool),
+        17 => quote!(u16),
+        22 => quote!(u32),
+        23 => quote!(u64),
+        _ => return None,
+    })
+}
+
+/// Turns a signature key into a field name.
+fn field_name(key: &str) -> Ident {
+    let mut name: String = key
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect::<String>()
+        .to_lowercase();
+    if name.is_empty() || name.starts_with(|c: char| c.is_ascii_digit()) {
+        name.insert(0, '_');
+    }
+    // `_` alone isn't a valid field name.
+    if name == "_" {
+        name.push('_');
+    }
+    const KEYWORDS: &[&str] = &[
+        "as", "async", "await", "break", "const", "continue", "crate", "dyn", "else", "enum",
+        "extern", "false", "fn", "for", "if", "impl", "in", "let", "loop", "match", "mod", "move",
+        "mut", "pub", "ref", "return", "self", "static", "struct", "super", "trait", "true",
+        "type", "unsafe", "use", "where", "while",
+    ];
+    if KEYWORDS.contains(&name.as_str()) {
+        name.push('_');
+    }
+    Ident::new(&name, Span::call_site())
+}
+
+struct Field {
+    ident: Ident,
+    ty: TokenStream,
+    op_name: String,
+    index: i32,
+    doc: String,
+}
+
+fn to_fields(infos: &[TensorInfo], span: Span) -> Result<Vec<Field>> {
+    let mut result: Vec<Field> = Vec::new();
+    for info in infos {
+        let ty = rust_type(info.dtype).ok_or_else(|| {
+            Error::new(
+                span,
+                format!(
+                    "tensor {:?} has unsupported data type {}",
+                    info.key, info.dtype
+                ),
+            )
+        })?;
+        let ident = field_name(&info.key);
+        if result.iter().any(|field| field.ident == ident) {
+            return Err(Error::new(
+                span,
+                format!("two tensors map to the field name `{}`", ident),
+            ));
+        }
+        let (op_name, index) = split_tensor_name(&info.name).ok_or_else(|| {
+            Error::new(
+                span,
+                format!("tensor {:?} has invalid name {:?}", info.key, info.name),
+            )
+        })?;
+        result.push(Field {
+            ident,
+            ty,
+            op_name,
+            index,
+            doc: format!("The tensor {:?} (`{}`).", info.key, info.name),
+        });
+    }
+    Ok(result)
+}
+
+pub fn expand(input: SignatureInput) -> Result<TokenStream> {
+    let span = input.path.span();
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let export_dir = PathBuf::from(manifest_dir).join(input.path.value());
+    let model_path = export_dir.join("saved_model.pb");
+    let saved_model = std::fs::read(&model_path)
+        .map_err(|e| Error::new(span, format!("can't read {}: {}", model_path.display(), e)))?;
+    let tags: Vec<String> = input.tags.iter().map(LitStr::value).collect();
+    let signature = input.signature.value();
+    let (inputs, outputs) = find_signature(&saved_model, &tags, &signature).map_err(|e| {
+        Error::new(
+            span,
+            format!("invalid SavedModel {}: {}", model_path.display(), e),
+        )
+    })?;
+    let inputs = to_fields(&inputs, span)?;
+    let outputs = to_fields(&outputs, span)?;
+
+    let name = &input.name;
+    let inputs_name = Ident::new(&format!("{}Inputs", name), name.span());
+    let outputs_name = Ident::new(&format!("{}Outputs", name), name.span());
+    let model_path = model_path.to_string_lossy().into_owned();
+    let struct_doc = format!(
+        "The {:?} signature of the SavedModel in {:?} with tags {:?}.",
+        signature,
+        input.path.value(),
+        tags
+    );
+    let inputs_doc = format!("The inputs of [`{}`].", name);
+    let outputs_doc = format!("The outputs of [`{}`].", name);
+
+    let struct_fields = |fields: &[Field]| -> Vec<TokenStream> {
+        fields
+            .iter()
+            .map(|field| {
+                let (doc, ident, ty) = (&field.doc, &field.ident, &field.ty);
+                quote! {
+                    #[doc = #doc]
+                    pub #ident: ::tensorflow::Tensor<#ty>,
+                }
+            })
+            .collect()
+    };
+    let lookups = |fields: &[Field]| -> Vec<TokenStream> {
+        fields
+            .iter()
+            .map(|field| {
+                let (op_name, index) = (&field.op_name, field.index);
+                quote!((graph.operation_by_name_required(#op_name)?, #index))
+            })
+            .collect()
+    };
+    let input_fields = struct_fields(&inputs);
+    let output_fields = struct_fields(&outputs);
+    let input_lookups = lookups(&inputs);
+    let output_lookups = lookups(&outputs);
+    let feeds: Vec<TokenStream> = inputs
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let ident = &field.ident;
+            quote! {
+                let (operation, index) = &self.inputs[#i];
+                args.add_feed(operation, *index, &inputs.#ident);
+            }
+        })
+        .collect();
+    let fetches: Vec<TokenStream> = outputs
+        .iter()
+        .enumerate()
+        .map(|(i, field)| {
+            let ident = &field.ident;
+            quote! {
+                #ident: args.fetch(tokens[#i])?,
+            }
+        })
+        .collect();
+    let tags = &input.tags;
+
+    let types = quote! {
+        #[doc = #inputs_doc]
+        #[derive(Debug)]
+        pub struct #inputs_name {
+            #(#input_fields)*
+        }
+
+        #[doc = #outputs_doc]
+        #[derive(Debug)]
+        pub struct #outputs_name {
+            #(#output_fields)*
+        }
+
+        #[doc = #struct_doc]
+        #[derive(Debug)]
+        pub struct #name {
+            graph: ::tensorflow::Graph,
+            bundle: ::tensorflow::SavedModelBundle,
+            inputs: ::std::vec::Vec<(::tensorflow::Operation, i32)>,
+            outputs: ::std::vec::Vec<(::tensorflow::Operation, i32)>,
+        }
+    };
+    let load = quote! {
+        /// Loads the model from `export_dir`, which must contain a
+        /// SavedModel with the same signature as the one this type was
+        /// generated from.
+        pub fn load<P: ::std::convert::AsRef<::std::path::Path>>(
+            options: &::tensorflow::SessionOptions,
+            export_dir: P,
+        ) -> ::tensorflow::Result<Self> {
+            // Rebuild when the model changes.
+            const _: &[u8] = include_bytes!(#model_path);
+            let mut graph = ::tensorflow::Graph::new();
+            let bundle = ::tensorflow::SavedModelBundle::load(
+                options,
+                &[#(#tags),*],
+                &mut graph,
+                export_dir,
+            )?;
+            let inputs = vec![#(#input_lookups),*];
+            let outputs = vec![#(#output_lookups),*];
+            Ok(Self {
+                graph,
+                bundle,
+                inputs,
+                outputs,
+            })
+        }
+    };
+    let run = quote! {
+        /// Runs the signature on `inputs`.
+        pub fn run(&self, inputs: &#inputs_name) -> ::tensorflow::Result<#outputs_name> {
+            let mut args = ::tensorflow::SessionRunArgs::new();
+            #(#feeds)*
+            let tokens: ::std::vec::Vec<_> = self
+                .outputs
+                .iter()
+                .map(|(operation, index)| args.request_fetch(operation, *index))
+                .collect();
+            self.bundle.session.run(&mut args)?;
+            Ok(#outputs_name {
+                #(#fetches)*
+            })
+        }
+    };
+    Ok(quote! {
+        #types
+
+        impl #name {
+            #load
+
+            /// Returns the graph of the model.
+            pub fn graph(&self) -> &::tensorflow::Graph {
+                &self.graph
+            }
+
+            /// Returns the session of the model.
+            pub fn session(&self) -> &::tensorflow::Session {
+                &self.bundle.session
+            }
+
+            #run
+        }
+    })
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn varint(mut value: u64, out: &mut Vec<u8>) {
+        while value >= 0x80 {
+            out.push(value as u8 | 0x80);
+            value >>= 7;
+        }
+        out.push(value as u8);
+    }
+
+    fn bytes_field(number: u32, bytes: &[u8]) -> Vec<u8> {
+        let mut out = Vec::new();
+        varint(u64::from(number) << 3 | 2, &mut out);
+        varint(bytes.len() as u64, &mut out);
+        out.extend_from_slice(bytes);
+        out
+    }
+
+    fn varint_field(number: u32, value: u64) -> Vec<u8> {
+        let mut out = Vec::new();
+        varint(u64::from(number) << 3, &mut out);
+        varint(value, &mut out);
+        out
+    }
+
+    /// Returns an entry of a `map<string, TensorInfo>`.
+    fn tensor_entry(key: &str, name: &str, dtype: u64) -> Vec<u8> {
+        let mut info = bytes_field(1, name.as_bytes());
+        info.extend(varint_field(2, dtype));
+        let mut entry = bytes_field(1, key.as_bytes());
+        entry.extend(bytes_field(2, &info));
+        entry
+    }
+
+    /// Returns a SavedModel with one meta graph with `tags` and one
+    /// signature.
+    fn saved_model(tags: &[&str], key: &str, inputs: &[Vec<u8>], outputs: &[Vec<u8>]) -> Vec<u8> {
+        let mut meta_info = Vec::new();
+        for tag in tags {
+            meta_info.extend(bytes_field(4, tag.as_bytes()));
+        }
+        let mut def = Vec::new();
+        for input in inputs {
+            def.extend(bytes_field(1, input));
+        }
+        for output in outputs {
+            def.extend(bytes_field(2, output));
+        }
+        let mut entry = bytes_field(1, key.as_bytes());
+        entry.extend(bytes_field(2, &def));
+        let mut meta_graph = bytes_field(1, &meta_info);
+        meta_graph.extend(bytes_field(5, &entry));
+        bytes_field(2, &meta_graph)
+    }
+
+    fn tags(tags: &[&str]) -> Vec<String> {
+        tags.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn find_signature_parses_tensor_infos() {
+        let model = saved_model(
+            &["serve", "gpu"],
+            "serving_default",
+            &[tensor_entry("x", "x:0", 1), tensor_entry("ids", "ids", 9)],
+            &[tensor_entry("y", "dense/BiasAdd:1", 1)],
+        );
+        let (inputs, outputs) =
+            find_signature(&model, &tags(&["gpu", "serve"]), "serving_default").unwrap();
+        let inputs: Vec<_> = inputs
+            .iter()
+            .map(|info| (info.key.as_str(), info.name.as_str(), info.dtype))
+            .collect();
+        assert_eq!(inputs, vec![("ids", "ids", 9), ("x", "x:0", 1)]);
+        assert_eq!(outputs.len(), 1);
+        assert_eq!(outputs[0].name, "dense/BiasAdd:1");
+
+        let err = find_signature(&model, &tags(&["serve"]), "serving_default")
+            .err()
+            .unwrap();
+        assert!(err.starts_with("no meta graph with tags"), "{}", err);
+        let err = find_signature(&model, &tags(&["serve", "gpu"]), "predict")
+            .err()
+            .unwrap();
+        assert!(err.starts_with("no signature named"), "{}", err);
+    }
+
+    #[test]
+    fn find_signature_rejects_malformed_input() {
+        let serve = tags(&["serve"]);
+        let parse = |model: &[u8]| find_signature(model, &serve, "serving_default").err();
+
+        // A key which ends mid-varint.
+        assert_eq!(parse(&[0x80]).unwrap(), "truncated varint");
+        // A length-delimited field longer than the message.
+        assert_eq!(parse(&[0x12, 0x05, 0x00]).unwrap(), "truncated field");
+        // A group, which isn't supported.
+        assert_eq!(parse(&[0x13]).unwrap(), "unsupported wire type 3");
+        assert_eq!(parse(&[0xff; 11]).unwrap(), "varint too long");
+
+        // A sparse tensor, which has no name.
+        let mut sparse = bytes_field(1, b"x");
+        sparse.extend(bytes_field(2, &varint_field(2, 1)));
+        let model = saved_model(&["serve"], "serving_default", &[sparse], &[]);
+        assert_eq!(parse(&model).unwrap(), "x is not a dense tensor");
+
+        // A key which isn't UTF-8.
+        let model = saved_model(
+            &["serve"],
+            "serving_default",
+            &[bytes_field(1, &[0xff])],
+            &[],
+        );
+        assert!(parse(&model).is_some());
+    }
+
+    #[test]
+    fn field_names() {
+        let name = |key: &str| field_name(key).to_string();
+        assert_eq!(name("x"), "x");
+        assert_eq!(name("Input-Image"), "input_image");
+        assert_eq!(name("scores:0"), "scores_0");
+        assert_eq!(name("0_logits"), "_0_logits");
+        assert_eq!(name("type"), "type_");
+        assert_eq!(name("self"), "self_");
+        assert_eq!(name(""), "__");
+        assert_eq!(name("-"), "__");
+        assert_eq!(name("é"), "__");
+    }
+
+    #[test]
+    fn to_fields_splits_and_checks_names() {
+        let info = |key: &str, name: &str, dtype| TensorInfo {
+            key: key.to_string(),
+            name: name.to_string(),
+            dtype,
+        };
+        let span = Span::call_site();
+        let fields = to_fields(&[info("x", "x", 1), info("y", "op:1", 3)], span).unwrap();
+        assert_eq!(fields[0].op_name, "x");
+        assert_eq!(fields[0].index, 0);
+        assert_eq!(fields[1].op_name, "op");
+        assert_eq!(fields[1].index, 1);
+
+        assert!(to_fields(&[info("x", "op:one", 1)], span).is_err());
+        assert!(to_fields(&[info("x", "op:-1", 1)], span).is_err());
+        assert!(to_fields(&[info("x", ":0", 1)], span).is_err());
+        assert!(to_fields(&[info("x", "x", 0)], span).is_err());
+        assert!(to_fields(&[info("a-b", "a", 1), info("a_b", "b", 1)], span).is_err());
+    }
+}
@@ -0,0 +1,38 @@
+//! Parsing of tensor names like `"op:1"`.
+
+/// Splits a tensor name like `"op:1"` into the op name and output index.  A
+/// name without an index refers to output 0.  Returns `None` if the op name
+/// is empty or contains a colon, or the index isn't a non-negative integer.
+pub fn split_tensor_name(name: &str) -> Option<(String, i32)> {
+    let (op_name, index) = match name.rfind(':') {
+        Some(colon) => (&name[..colon], name[colon + 1..].parse().ok()?),
+        None => (name, 0),
+    };
+    if op_name.is_empty() || op_name.contains(':') || index < 0 {
+        return None;
+    }
+    Some((op_name.to_string(), index))
+}
+
+////////////////////////
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split() {
+        assert_eq!(split_tensor_name("x"), Some(("x".to_string(), 0)));
+        assert_eq!(split_tensor_name("x:0"), Some(("x".to_string(), 0)));
+        assert_eq!(
+            split_tensor_name("scope/op:12"),
+            Some(("scope/op".to_string(), 12))
+        );
+        assert_eq!(split_tensor_name(""), None);
+        assert_eq!(split_tensor_name(":1"), None);
+        assert_eq!(split_tensor_name("x:"), None);
+        assert_eq!(split_tensor_name("x:-1"), None);
+        assert_eq!(split_tensor_name("x:y"), None);
+        assert_eq!(split_tensor_name("x:1:2"), None);
+    }
+}
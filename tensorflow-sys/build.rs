@@ -38,6 +38,12 @@ macro_rules! log {
 macro_rules! log_var(($var:ident) => (log!(concat!(stringify!($var), " = {:?}"), $var)));
 
 fn main() {
+    if env::var_os("CARGO_FEATURE_RUNTIME_LINKING").is_some() {
+        generate_runtime_bindings();
+        log!("Returning early because {} is loaded at runtime", LIBRARY);
+        return;
+    }
+
     if check_windows_lib() {
         log!("Returning early because {} was already found", LIBRARY);
         return;
@@ -69,6 +75,20 @@ fn main() {
     }
 }
 
+// Writes bindgen.rs to OUT_DIR with the functions in each `extern "C"` block
+// declared by `link_functions!`, which looks them up in the library loaded
+// at runtime.
+fn generate_runtime_bindings() {
+    let manifest_dir = PathBuf::from(&get!("CARGO_MANIFEST_DIR"));
+    let bindings_path = manifest_dir.join("src").join("bindgen.rs");
+    println!("cargo:rerun-if-changed={}", bindings_path.display());
+    let bindings = ok!(fs::read_to_string(&bindings_path));
+    let bindings = bindings.replace("extern \"C\" {", "link_functions! {");
+    let out_path = PathBuf::from(&get!("OUT_DIR")).join("bindgen.rs");
+    log_var!(out_path);
+    ok!(fs::write(&out_path, bindings));
+}
+
 #[cfg(not(target_env = "msvc"))]
 fn check_windows_lib() -> bool {
     false
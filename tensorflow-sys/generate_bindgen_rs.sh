@@ -10,8 +10,19 @@ fi
 # See https://github.com/servo/rust-bindgen/issues/550 as to why
 # this is blacklisted.
 bindgen_options="--blacklist-type max_align_t"
-header="/usr/include/tensorflow/c_api.h"
+# The bindings cover the graph API, the eager API (the TFE_* functions) and
+# the experimental API, so the headers are included from a single wrapper.
+# The experimental API, e.g. TF_LoadPluggableDeviceLibrary, needs the
+# headers of TensorFlow 2.5 or later; the functions which only newer
+# releases have are used by the crate only behind cargo features.
+headers="/usr/include/tensorflow/c_api.h /usr/include/tensorflow/c_api_eager.h"
+headers="${headers} /usr/include/tensorflow/c_api_experimental.h"
+wrapper="$(mktemp -d)/tensorflow.h"
+for header in ${headers}; do
+    echo "#include \"${header}\"" >> "${wrapper}"
+done
 
-cmd="bindgen ${bindgen_options} ${header} --output src/bindgen.rs"
+cmd="bindgen ${bindgen_options} ${wrapper} --output src/bindgen.rs"
 echo ${cmd}
 ${cmd}
+rm -r "$(dirname "${wrapper}")"
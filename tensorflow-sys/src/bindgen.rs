@@ -1417,6 +1417,10 @@ extern "C" {
 extern "C" {
     pub fn TF_GetAllOpList() -> *mut TF_Buffer;
 }
+extern "C" {
+    pub fn TF_LoadPluggableDeviceLibrary(library_filename: *const ::std::os::raw::c_char,
+                                         status: *mut TF_Status) -> *mut TF_Library;
+}
 #[repr(C)]
 #[derive(Debug, Copy, Clone)]
 pub struct TF_ApiDefMap {
@@ -1475,3 +1479,166 @@ extern "C" {
 extern "C" {
     pub fn TF_DeleteServer(server: *mut TF_Server);
 }
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TFE_ContextOptions {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn TFE_NewContextOptions() -> *mut TFE_ContextOptions;
+}
+extern "C" {
+    pub fn TFE_ContextOptionsSetConfig(options: *mut TFE_ContextOptions,
+                                       proto: *const ::std::os::raw::c_void,
+                                       proto_len: usize,
+                                       status: *mut TF_Status);
+}
+extern "C" {
+    pub fn TFE_ContextOptionsSetAsync(arg1: *mut TFE_ContextOptions,
+                                      enable: ::std::os::raw::c_uchar);
+}
+extern "C" {
+    pub fn TFE_DeleteContextOptions(arg1: *mut TFE_ContextOptions);
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TFE_Context {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn TFE_NewContext(opts: *const TFE_ContextOptions,
+                          status: *mut TF_Status) -> *mut TFE_Context;
+}
+extern "C" {
+    pub fn TFE_DeleteContext(ctx: *mut TFE_Context);
+}
+extern "C" {
+    pub fn TFE_ContextListDevices(ctx: *mut TFE_Context,
+                                  status: *mut TF_Status)
+     -> *mut TF_DeviceList;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TFE_TensorHandle {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn TFE_NewTensorHandle(t: *const TF_Tensor, status: *mut TF_Status)
+     -> *mut TFE_TensorHandle;
+}
+extern "C" {
+    pub fn TFE_DeleteTensorHandle(h: *mut TFE_TensorHandle);
+}
+extern "C" {
+    pub fn TFE_TensorHandleDataType(h: *mut TFE_TensorHandle) -> TF_DataType;
+}
+extern "C" {
+    pub fn TFE_TensorHandleNumDims(h: *mut TFE_TensorHandle,
+                                   status: *mut TF_Status)
+     -> ::std::os::raw::c_int;
+}
+extern "C" {
+    pub fn TFE_TensorHandleDim(h: *mut TFE_TensorHandle,
+                               dim_index: ::std::os::raw::c_int,
+                               status: *mut TF_Status) -> i64;
+}
+extern "C" {
+    pub fn TFE_TensorHandleDeviceName(h: *mut TFE_TensorHandle,
+                                      status: *mut TF_Status)
+     -> *const ::std::os::raw::c_char;
+}
+extern "C" {
+    pub fn TFE_TensorHandleCopySharingTensor(h: *mut TFE_TensorHandle,
+                                             status: *mut TF_Status)
+     -> *mut TFE_TensorHandle;
+}
+extern "C" {
+    pub fn TFE_TensorHandleResolve(h: *mut TFE_TensorHandle,
+                                   status: *mut TF_Status) -> *mut TF_Tensor;
+}
+extern "C" {
+    pub fn TFE_TensorHandleCopyToDevice(h: *mut TFE_TensorHandle,
+                                        ctx: *mut TFE_Context,
+                                        device_name:
+                                            *const ::std::os::raw::c_char,
+                                        status: *mut TF_Status)
+     -> *mut TFE_TensorHandle;
+}
+#[repr(C)]
+#[derive(Debug, Copy, Clone)]
+pub struct TFE_Op {
+    _unused: [u8; 0],
+}
+extern "C" {
+    pub fn TFE_NewOp(ctx: *mut TFE_Context,
+                     op_or_function_name: *const ::std::os::raw::c_char,
+                     status: *mut TF_Status) -> *mut TFE_Op;
+}
+extern "C" {
+    pub fn TFE_DeleteOp(op: *mut TFE_Op);
+}
+extern "C" {
+    pub fn TFE_OpSetDevice(op: *mut TFE_Op,
+                           device_name: *const ::std::os::raw::c_char,
+                           status: *mut TF_Status);
+}
+extern "C" {
+    pub fn TFE_OpAddInput(op: *mut TFE_Op, input: *mut TFE_TensorHandle,
+                          status: *mut TF_Status);
+}
+extern "C" {
+    pub fn TFE_OpAddInputList(op: *mut TFE_Op,
+                              inputs: *mut *mut TFE_TensorHandle,
+                              num_inputs: ::std::os::raw::c_int,
+                              status: *mut TF_Status);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrString(op: *mut TFE_Op,
+                               attr_name: *const ::std::os::raw::c_char,
+                               value: *const ::std::os::raw::c_void,
+                               length: usize);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrInt(op: *mut TFE_Op,
+                            attr_name: *const ::std::os::raw::c_char,
+                            value: i64);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrFloat(op: *mut TFE_Op,
+                              attr_name: *const ::std::os::raw::c_char,
+                              value: f32);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrBool(op: *mut TFE_Op,
+                             attr_name: *const ::std::os::raw::c_char,
+                             value: ::std::os::raw::c_uchar);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrType(op: *mut TFE_Op,
+                             attr_name: *const ::std::os::raw::c_char,
+                             value: TF_DataType);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrShape(op: *mut TFE_Op,
+                              attr_name: *const ::std::os::raw::c_char,
+                              dims: *const i64,
+                              num_dims: ::std::os::raw::c_int,
+                              out_status: *mut TF_Status);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrIntList(op: *mut TFE_Op,
+                                attr_name: *const ::std::os::raw::c_char,
+                                values: *const i64,
+                                num_values: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn TFE_OpSetAttrTypeList(op: *mut TFE_Op,
+                                 attr_name: *const ::std::os::raw::c_char,
+                                 values: *const TF_DataType,
+                                 num_values: ::std::os::raw::c_int);
+}
+extern "C" {
+    pub fn TFE_Execute(op: *mut TFE_Op, retvals: *mut *mut TFE_TensorHandle,
+                       num_retvals: *mut ::std::os::raw::c_int,
+                       status: *mut TF_Status);
+}
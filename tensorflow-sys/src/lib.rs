@@ -2,7 +2,15 @@
 #![allow(non_snake_case)]
 #![allow(non_upper_case_globals)]
 
+#[cfg(feature = "runtime_linking")]
+#[macro_use]
+pub mod runtime_linking;
+
+#[cfg(not(feature = "runtime_linking"))]
 include!("bindgen.rs");
+// bindgen.rs with each `extern "C"` block replaced by `link_functions!`.
+#[cfg(feature = "runtime_linking")]
+include!(concat!(env!("OUT_DIR"), "/bindgen.rs"));
 
 pub use crate::TF_AttrType::*;
 pub use crate::TF_Code::*;
@@ -0,0 +1,251 @@
+//! Loading of the TensorFlow C library at runtime.
+//!
+//! With the `runtime_linking` feature, this crate does not link against
+//! libtensorflow at build time.  Instead, the library is opened with
+//! `dlopen` (or `LoadLibrary` on Windows) by `Loader::load`, which must be
+//! called before any other function in this crate.  This lets one binary run
+//! on machines with different TensorFlow installations, e.g. using a GPU
+//! build where one is installed and falling back to a CPU build elsewhere:
+//!
+//! ```ignore
+//! let path = Loader::new()
+//!     .with_path("/opt/tensorflow-gpu/lib")
+//!     .with_path("/opt/tensorflow-cpu/lib")
+//!     .with_version("2")
+//!     .load()?;
+//! ```
+//!
+//! Calling a function before the library is loaded panics, as does calling a
+//! function which the loaded library does not define, e.g. because it is an
+//! older version.
+
+use libloading::Library;
+use std::env;
+use std::env::consts::DLL_PREFIX;
+use std::env::consts::DLL_SUFFIX;
+use std::ffi::CStr;
+use std::os::raw::c_char;
+use std::os::raw::c_void;
+use std::path::Path;
+use std::path::PathBuf;
+use std::ptr;
+use std::sync::atomic::AtomicPtr;
+use std::sync::atomic::Ordering;
+
+/// Environment variable with paths which are tried before the ones given to
+/// `Loader::with_path`, separated like `PATH`.
+pub const PATH_VARIABLE: &str = "TENSORFLOW_RUNTIME_LIBRARY_PATH";
+
+struct Loaded {
+    library: Library,
+    path: PathBuf,
+}
+
+// Set once and never freed, since the addresses of loaded functions are
+// cached for the lifetime of the process.
+static LOADED: AtomicPtr<Loaded> = AtomicPtr::new(ptr::null_mut());
+
+fn loaded() -> Option<&'static Loaded> {
+    unsafe { LOADED.load(Ordering::Acquire).as_ref() }
+}
+
+/// Returns whether the TensorFlow library has been loaded.
+pub fn is_loaded() -> bool {
+    loaded().is_some()
+}
+
+/// Returns the path the TensorFlow library was loaded from, if it has been
+/// loaded.
+pub fn loaded_path() -> Option<&'static Path> {
+    loaded().map(|l| l.path.as_path())
+}
+
+/// Finds and loads the TensorFlow C library.
+///
+/// Candidates are tried in order: the entries of the
+/// `TENSORFLOW_RUNTIME_LIBRARY_PATH` environment variable, the paths given to
+/// `with_path`, and finally the library's file name alone, which the system's
+/// dynamic loader searches for in its usual locations (e.g.
+/// `LD_LIBRARY_PATH`).  A candidate may be a file or a directory containing
+/// the library.  The first candidate which loads, and which has the requested
+/// version if any, is used.
+#[derive(Debug, Clone, Default)]
+pub struct Loader {
+    paths: Vec<PathBuf>,
+    version: Option<String>,
+}
+
+impl Loader {
+    /// Creates a loader which only tries the environment variable and the
+    /// default search path.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file or directory to try after the ones already added.
+    pub fn with_path<P: AsRef<Path>>(mut self, path: P) -> Self {
+        self.paths.push(path.as_ref().to_path_buf());
+        self
+    }
+
+    /// Only accepts a library whose `TF_Version` is `version` or starts with
+    /// `version` followed by `.` or `-`, e.g. `"2"` accepts `"2.4.1"`.
+    pub fn with_version(self, version: &str) -> Self {
+        Self {
+            version: Some(version.to_string()),
+            ..self
+        }
+    }
+
+    fn file_names(&self) -> Vec<String> {
+        let file_name = format!("{}tensorflow{}", DLL_PREFIX, DLL_SUFFIX);
+        let mut names = Vec::new();
+        if cfg!(target_os = "linux") {
+            if let Some(ref version) = self.version {
+                names.push(format!("{}.{}", file_name, version));
+            }
+        }
+        names.push(file_name);
+        names
+    }
+
+    fn candidates(&self) -> Vec<PathBuf> {
+        let mut paths: Vec<PathBuf> = match env::var_os(PATH_VARIABLE) {
+            Some(value) => env::split_paths(&value).collect(),
+            None => Vec::new(),
+        };
+        paths.extend(self.paths.iter().cloned());
+        let names = self.file_names();
+        let mut candidates = Vec::new();
+        for path in paths {
+            if path.is_dir() {
+                candidates.extend(names.iter().map(|name| path.join(name)));
+            } else {
+                candidates.push(path);
+            }
+        }
+        candidates.extend(names.into_iter().map(PathBuf::from));
+        candidates
+    }
+
+    fn matches_version(&self, actual: &str) -> bool {
+        match self.version {
+            None => true,
+            Some(ref version) => {
+                actual == version
+                    || actual.starts_with(&format!("{}.", version))
+                    || actual.starts_with(&format!("{}-", version))
+            }
+        }
+    }
+
+    /// Loads the first candidate which succeeds, returning its path.  If the
+    /// library has already been loaded, returns the path it was loaded from
+    /// without loading another.  On failure, the error describes why each
+    /// candidate was rejected.
+    pub fn load(&self) -> Result<PathBuf, String> {
+        if let Some(loaded) = loaded() {
+            return Ok(loaded.path.clone());
+        }
+        let mut failures = Vec::new();
+        for path in self.candidates() {
+            let library = match unsafe { Library::new(&path) } {
+                Ok(library) => library,
+                Err(e) => {
+                    failures.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            let version = match unsafe {
+                library.get::<unsafe extern "C" fn() -> *const c_char>(b"TF_Version")
+            } {
+                Ok(f) => unsafe { CStr::from_ptr(f()) }
+                    .to_string_lossy()
+                    .into_owned(),
+                Err(e) => {
+                    failures.push(format!("{}: {}", path.display(), e));
+                    continue;
+                }
+            };
+            if !self.matches_version(&version) {
+                failures.push(format!(
+                    "{}: version {} does not match {}",
+                    path.display(),
+                    version,
+                    self.version.as_ref().unwrap()
+                ));
+                continue;
+            }
+            let new = Box::into_raw(Box::new(Loaded {
+                library,
+                path: path.clone(),
+            }));
+            return match LOADED.compare_exchange(
+                ptr::null_mut(),
+                new,
+                Ordering::AcqRel,
+                Ordering::Acquire,
+            ) {
+                Ok(_) => Ok(path),
+                Err(existing) => {
+                    // Another thread loaded the library first.
+                    drop(unsafe { Box::from_raw(new) });
+                    Ok(unsafe { &*existing }.path.clone())
+                }
+            };
+        }
+        Err(format!(
+            "Unable to load the TensorFlow library:\n  {}",
+            failures.join("\n  ")
+        ))
+    }
+}
+
+/// Loads the TensorFlow library from the default locations.  See `Loader`.
+pub fn load() -> Result<PathBuf, String> {
+    Loader::new().load()
+}
+
+#[doc(hidden)]
+pub fn symbol(name: &str) -> usize {
+    let loaded = loaded().unwrap_or_else(|| {
+        panic!(
+            "{} was called before the TensorFlow library was loaded with \
+             tensorflow_sys::runtime_linking::load",
+            name
+        )
+    });
+    match unsafe { loaded.library.get::<*const c_void>(name.as_bytes()) } {
+        Ok(symbol) => *symbol as usize,
+        Err(e) => panic!(
+            "The TensorFlow library at {} does not define {}: {}",
+            loaded.path.display(),
+            name,
+            e
+        ),
+    }
+}
+
+// Turns the declarations in an `extern "C"` block into functions which call
+// through the address of the symbol in the loaded library, looked up on the
+// first call.  The build script rewrites bindgen.rs to use this instead of
+// `extern "C"`.
+macro_rules! link_functions {
+    ($(pub fn $name:ident($($arg:ident: $ty:ty),* $(,)?) $(-> $ret:ty)?;)*) => {
+        $(
+            #[allow(clippy::missing_safety_doc, clippy::too_many_arguments)]
+            pub unsafe fn $name($($arg: $ty),*) $(-> $ret)? {
+                static ADDRESS: ::std::sync::atomic::AtomicUsize =
+                    ::std::sync::atomic::AtomicUsize::new(0);
+                let mut address = ADDRESS.load(::std::sync::atomic::Ordering::Relaxed);
+                if address == 0 {
+                    address = crate::runtime_linking::symbol(stringify!($name));
+                    ADDRESS.store(address, ::std::sync::atomic::Ordering::Relaxed);
+                }
+                let f: unsafe extern "C" fn($($ty),*) $(-> $ret)? =
+                    ::std::mem::transmute(address);
+                f($($arg),*)
+            }
+        )*
+    };
+}
@@ -0,0 +1,72 @@
+//! `#[derive(Feed, Fetch)]` expands to paths under `::tensorflow`, so it is
+//! tested from outside the crate.
+
+use tensorflow::DataType;
+use tensorflow::Feed;
+use tensorflow::Fetch;
+use tensorflow::Graph;
+use tensorflow::Operation;
+use tensorflow::Session;
+use tensorflow::SessionOptions;
+use tensorflow::Shape;
+use tensorflow::Tensor;
+
+#[derive(Feed)]
+struct Inputs {
+    #[tensorflow(name = "x:0")]
+    x: Tensor<f32>,
+    offsets: Vec<f32>,
+}
+
+#[derive(Fetch)]
+struct Outputs {
+    #[tensorflow(name = "sum")]
+    sum: Tensor<f32>,
+    #[tensorflow(name = "split:1")]
+    second: Vec<f32>,
+}
+
+fn placeholder(graph: &mut Graph, name: &str) -> Operation {
+    let mut nd = graph.new_operation("Placeholder", name).unwrap();
+    nd.set_attr_type("dtype", DataType::Float).unwrap();
+    nd.set_attr_shape("shape", &Shape::new(Some(vec![Some(4)])))
+        .unwrap();
+    nd.finish().unwrap()
+}
+
+#[test]
+fn derive_feed_fetch() {
+    let mut graph = Graph::new();
+    let x = placeholder(&mut graph, "x");
+    let offsets = placeholder(&mut graph, "offsets");
+    let sum = {
+        let mut nd = graph.new_operation("Add", "sum").unwrap();
+        nd.add_input(x);
+        nd.add_input(offsets);
+        nd.finish().unwrap()
+    };
+    let axis = {
+        let mut nd = graph.new_operation("Const", "axis").unwrap();
+        nd.set_attr_type("dtype", DataType::Int32).unwrap();
+        nd.set_attr_tensor("value", Tensor::from(0i32)).unwrap();
+        nd.finish().unwrap()
+    };
+    {
+        let mut nd = graph.new_operation("Split", "split").unwrap();
+        nd.add_input(axis);
+        nd.add_input(sum);
+        nd.set_attr_int("num_split", 2).unwrap();
+        nd.finish().unwrap();
+    }
+    let session = Session::new(&SessionOptions::new(), &graph).unwrap();
+    let inputs = Inputs {
+        x: Tensor::new(&[4])
+            .with_values(&[1.0f32, 2.0, 3.0, 4.0])
+            .unwrap(),
+        offsets: vec![10.0, 20.0, 30.0, 40.0],
+    };
+    let outputs: Outputs = session.run_typed(&graph, &inputs).unwrap();
+    assert_eq!(outputs.sum.dims(), &[4]);
+    assert_eq!(&outputs.sum[..], &[11.0, 22.0, 33.0, 44.0]);
+    assert_eq!(outputs.second, vec![33.0, 44.0]);
+}
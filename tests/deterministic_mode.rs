@@ -0,0 +1,18 @@
+//! Deterministic mode is process-wide, so it is tested in its own test
+//! binary, where changing it can't affect other tests.
+
+use tensorflow::is_deterministic;
+use tensorflow::set_deterministic;
+use tensorflow::SessionOptions;
+
+#[test]
+fn deterministic_mode() {
+    assert!(!is_deterministic());
+    // The test binary runs this test alone, before any TensorFlow call.
+    unsafe { set_deterministic(true) };
+    assert!(is_deterministic());
+    assert_eq!(std::env::var("TF_DETERMINISTIC_OPS").unwrap(), "1");
+    assert_eq!(std::env::var("TF_CUDNN_DETERMINISTIC").unwrap(), "1");
+    // Session options are still valid with the ops run one at a time.
+    SessionOptions::new();
+}